@@ -32,7 +32,7 @@ class StateManager private constructor(context: Context) {
                 -102 -> "Invalid state file format"
                 -103 -> "State file version mismatch"
                 -104 -> "State was saved with a different ROM"
-                -105 -> "State file is corrupted"
+                -105, -106 -> "State file is corrupted"
                 else -> "Unknown error ($code)"
             }
         }
@@ -18,10 +18,12 @@ use std::env;
 use std::fs::{self, File};
 use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::Path;
-use std::process::Command;
 use std::time::Instant;
 
 use emu_core::{Emu, StepInfo, IoTarget, IoOpType, disassemble};
+use emu_core::input_macro::InputMacro;
+use emu_core::rom_compare;
+use emu_core::watchpoint::WatchAccess;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -57,6 +59,10 @@ fn main() {
         "mathprint" => cmd_mathprint_trace(),
         "watchpoint" => cmd_watchpoint_mathprint(),
         "ports" => cmd_ports(),
+        "coverage" => {
+            let cycles = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(100_000_000u64);
+            cmd_coverage(cycles);
+        }
         "fulltrace" => {
             let steps = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(1000);
             cmd_fulltrace(steps);
@@ -82,6 +88,10 @@ fn main() {
             }
             cmd_bakerom(&args[2], &args[3..]);
         }
+        "testrom" => {
+            let output = args.get(2).map(|s| s.as_str()).unwrap_or("test.rom");
+            cmd_testrom(output);
+        }
         "rundoom" => {
             // Load baked ROM, boot, simulate pressing prgm → down → enter → enter
             cmd_rundoom();
@@ -150,6 +160,13 @@ fn main() {
             let count = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(40usize);
             cmd_disasm(addr, count);
         }
+        "abcompare" => {
+            if args.len() < 5 {
+                eprintln!("Usage: debug abcompare <a.rom> <b.rom> <input.macro>");
+                return;
+            }
+            cmd_abcompare(&args[2], &args[3], &args[4]);
+        }
         "help" | "--help" | "-h" => print_help(),
         _ => {
             eprintln!("Unknown command: {}", args[1]);
@@ -190,12 +207,16 @@ Commands:
   mathprint         Trace writes to MathPrint flag (0xD000C4) during boot
                     Investigates why emulator boots into Classic mode
 
-  watchpoint        Single-step boot and capture PC when 0xD000C4 is written
+  watchpoint        Watch boot for writes to 0xD000C4 and capture their PC
                     Provides exact code location making MathPrint decision
 
   ports             Dump control port values after boot
                     Useful for comparing with CEmu
 
+  coverage [cycles] Report which opcodes run during boot (and which never do)
+                    Default: 100000000 cycles
+                    Lists executed opcodes the core doesn't implement first
+
   fulltrace [steps] Generate comprehensive trace with I/O operations
                     Default: 1000 steps
                     Output: JSON with full instruction and I/O details
@@ -214,6 +235,10 @@ Commands:
                     the flash archive. The output ROM can be loaded directly
                     and programs will appear in TI-OS without needing sendfile.
 
+  testrom [output]  Write the built-in open test ROM to a file
+                    Default output: test.rom
+                    Used by the C FFI harness in tests/ffi
+
   run <file.8xp> [lib.8xv ...]
                     Run a program headless with debug output capture.
                     Boots TI-OS, injects files, launches via Asm(prgm<NAME>).
@@ -222,6 +247,11 @@ Commands:
                     Options: --timeout <secs> (default: 30)
                              --speed <N> (e.g. 1=real-time, default: unthrottled)
 
+  abcompare <a.rom> <b.rom> <input.macro>
+                    Play the same input macro on two ROMs (e.g. two OS
+                    versions) and report the first checkpoint where frame
+                    hashes and trace digests diverge
+
   help              Show this help message
 
 Environment Variables:
@@ -1276,25 +1306,12 @@ fn cmd_screen(output: &str) {
     // Render frame
     emu.render_frame();
 
-    // Save as PPM first
-    let ppm_path = output.replace(".png", ".ppm");
-    save_framebuffer_ppm(&emu, &ppm_path);
-
-    // Try to convert to PNG using sips (macOS)
     if output.ends_with(".png") {
-        let result = Command::new("sips")
-            .args(["-s", "format", "png", &ppm_path, "--out", output])
-            .output();
-
-        if result.is_ok() {
-            fs::remove_file(&ppm_path).ok();
-            println!("Saved: {}", output);
-        } else {
-            println!("Saved: {} (PNG conversion failed, keeping PPM)", ppm_path);
-        }
+        fs::write(output, emu.screenshot_png()).expect("Failed to write output file");
     } else {
-        println!("Saved: {}", ppm_path);
+        save_framebuffer_ppm(&emu, output);
     }
+    println!("Saved: {}", output);
 }
 
 fn save_framebuffer_ppm(emu: &Emu, path: &str) {
@@ -1319,6 +1336,27 @@ fn save_framebuffer_ppm(emu: &Emu, path: &str) {
     }
 }
 
+// === ROM A/B Comparison ===
+
+fn cmd_abcompare(rom_a: &str, rom_b: &str, macro_path: &str) {
+    let read = |path: &str| fs::read(path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+    let (rom_a, rom_b) = (read(rom_a), read(rom_b));
+    let text = fs::read_to_string(macro_path).unwrap_or_else(|e| panic!("{}: {}", macro_path, e));
+    let input = match InputMacro::parse(&text) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("{}: {}", macro_path, e);
+            return;
+        }
+    };
+
+    let dir = Path::new(macro_path).parent().unwrap_or(Path::new("."));
+    match rom_compare::compare(&rom_a, &rom_b, &input, &mut |file| fs::read(dir.join(file)).ok()) {
+        Ok(report) => print!("{}", report),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
 // === VRAM Analysis ===
 
 fn cmd_vram() {
@@ -1678,6 +1716,28 @@ fn cmd_mathprint_trace() {
 // === Control Port Dump ===
 
 /// Dump control port values after boot for comparison with CEmu
+fn cmd_coverage(cycles: u64) {
+    let mut emu = match create_emu() {
+        Some(e) => e,
+        None => return,
+    };
+
+    println!("\n=== Opcode Coverage ({} cycles) ===\n", cycles);
+    emu.set_opcode_coverage_enabled(true);
+    let mut total_cycles = 0u64;
+    while total_cycles < cycles {
+        let executed = emu.run_cycles(1_000_000);
+        if executed == 0 {
+            break;
+        }
+        total_cycles += executed as u64;
+    }
+
+    if let Some(coverage) = emu.opcode_coverage() {
+        print!("{}", coverage.report());
+    }
+}
+
 fn cmd_ports() {
     let mut emu = match create_emu() {
         Some(e) => e,
@@ -1859,22 +1919,10 @@ fn cmd_sendfile(files: &[String]) {
     println!("Control: 0x{:08X} (enabled={}, bpp_mode={})", lcd.control, (lcd.control & 1) != 0, bpp_mode);
     println!("VRAM base: 0x{:06X}", lcd.upbase);
 
-    // Save as PPM
-    let output = "sendfile_result.ppm";
-    save_framebuffer_ppm(&emu, output);
+    let output = "sendfile_result.png";
+    fs::write(output, emu.screenshot_png()).expect("Failed to write screenshot");
     println!("\nScreenshot saved to: {}", output);
 
-    // Try to convert to PNG
-    let png_output = "sendfile_result.png";
-    let result = Command::new("sips")
-        .args(["-s", "format", "png", output, "--out", png_output])
-        .output();
-
-    if result.is_ok() {
-        fs::remove_file(output).ok();
-        println!("Converted to: {}", png_output);
-    }
-
     // Analyze framebuffer
     println!("\n=== Framebuffer Analysis ===");
     analyze_framebuffer(&emu);
@@ -1977,6 +2025,14 @@ fn cmd_sendfile(files: &[String]) {
 // === Bake ROM Command ===
 
 /// Load ROM, inject .8xp/.8xv files into flash archive, and save modified ROM
+fn cmd_testrom(output_path: &str) {
+    let rom = emu_core::test_rom::build();
+    match fs::write(output_path, &rom) {
+        Ok(()) => println!("Wrote test ROM ({} bytes) to {}", rom.len(), output_path),
+        Err(e) => eprintln!("Failed to write {}: {}", output_path, e),
+    }
+}
+
 fn cmd_bakerom(output_path: &str, files: &[String]) {
     // Load ROM
     let rom_data = match load_rom() {
@@ -2033,7 +2089,7 @@ fn cmd_bakerom(output_path: &str, files: &[String]) {
 
 // === Watchpoint for MathPrint Investigation ===
 
-/// Single-step through boot and capture PC when 0xD000C4 is written
+/// Run boot with a write watchpoint and capture PC when 0xD000C4 is written
 /// This gives us the exact code location making the MathPrint/Classic decision
 fn cmd_watchpoint_mathprint() {
     let mut emu = match create_emu() {
@@ -2042,32 +2098,33 @@ fn cmd_watchpoint_mathprint() {
     };
 
     println!("\n=== MathPrint Watchpoint ===\n");
-    println!("Single-stepping through boot to find writes to 0xD000C4...");
+    println!("Running boot with a watchpoint on writes to 0xD000C4...");
     println!("This will capture the PC when the MathPrint flag is set.\n");
 
     const MATHPRINT_ADDR: u32 = 0xD000C4;
     const MAX_CYCLES: u64 = 70_000_000; // Stop after 70M cycles (full boot)
 
     let mut total_cycles = 0u64;
-    let mut step_count = 0u64;
+    let mut hit_count = 0u64;
     let mut prev_value = emu.peek_byte(MATHPRINT_ADDR);
     let mut writes_found: Vec<(u64, u32, u8, u8)> = Vec::new(); // (cycle, pc, old, new)
     let mut last_report = 0u64;
 
     println!("Initial value at 0xD000C4: 0x{:02X}", prev_value);
     println!();
+    emu.add_watchpoint(MATHPRINT_ADDR, MATHPRINT_ADDR, WatchAccess::Write);
 
     while total_cycles < MAX_CYCLES {
-        // Capture PC before stepping
-        let pc_before = emu.pc();
-
-        // Single step
-        let cycles = emu.run_cycles(1) as u64;
+        let cycles = emu.run_cycles(1_000_000) as u64;
         if cycles == 0 {
             break;
         }
         total_cycles += cycles;
-        step_count += 1;
+        let Some(hit) = emu.last_watchpoint() else {
+            continue;
+        };
+        hit_count += 1;
+        let pc_before = hit.pc;
 
         // Check if 0xD000C4 changed
         let new_value = emu.peek_byte(MATHPRINT_ADDR);
@@ -2077,7 +2134,7 @@ fn cmd_watchpoint_mathprint() {
             let mode = if new_value & 0x20 != 0 { "MathPrint" } else { "Classic" };
             println!("=== WRITE DETECTED ===");
             println!("  Cycle: {}", total_cycles);
-            println!("  Step: {}", step_count);
+            println!("  Store: {}", hit_count);
             println!("  PC: 0x{:06X}", pc_before);
             println!("  Old value: 0x{:02X}", prev_value);
             println!("  New value: 0x{:02X} ({})", new_value, mode);
@@ -2121,15 +2178,15 @@ fn cmd_watchpoint_mathprint() {
 
         // Progress report every 5M cycles
         if total_cycles - last_report >= 5_000_000 {
-            println!("  ... {} cycles ({} steps), {} writes found so far",
-                total_cycles, step_count, writes_found.len());
+            println!("  ... {} cycles ({} stores), {} writes found so far",
+                total_cycles, hit_count, writes_found.len());
             last_report = total_cycles;
         }
     }
 
     println!("\n=== Summary ===");
     println!("Total cycles: {}", total_cycles);
-    println!("Total steps: {}", step_count);
+    println!("Total stores: {}", hit_count);
     println!("Writes to 0xD000C4: {}", writes_found.len());
 
     if !writes_found.is_empty() {
@@ -2445,8 +2502,7 @@ fn cmd_runprog(files: &[&str], post_launch_cycles: u64) {
 
     // Screenshot before execution
     emu.render_frame();
-    save_framebuffer_ppm(&emu, "/tmp/runprog_before.ppm");
-    convert_ppm_to_png("/tmp/runprog_before.ppm", "/tmp/runprog_before.png");
+    fs::write("/tmp/runprog_before.png", emu.screenshot_png()).ok();
     println!("\n  Pre-exec screenshot: /tmp/runprog_before.png");
 
     // Phase 3: Execute!
@@ -2464,8 +2520,7 @@ fn cmd_runprog(files: &[&str], post_launch_cycles: u64) {
 
     // Final screenshot
     emu.render_frame();
-    save_framebuffer_ppm(&emu, "/tmp/runprog_after.ppm");
-    convert_ppm_to_png("/tmp/runprog_after.ppm", "/tmp/runprog_after.png");
+    fs::write("/tmp/runprog_after.png", emu.screenshot_png()).ok();
 
     println!("\nScreenshots:");
     println!("  Before: /tmp/runprog_before.png");
@@ -2537,8 +2592,7 @@ fn cmd_rundoom() {
     // Take screenshot showing Asm(prgmDOOM on homescreen
     // Note: CEmu doesn't send close paren - TI-OS handles it implicitly
     emu.render_frame();
-    save_framebuffer_ppm(&emu, "/tmp/doom_before_exec.ppm");
-    convert_ppm_to_png("/tmp/doom_before_exec.ppm", "/tmp/doom_before_exec.png");
+    fs::write("/tmp/doom_before_exec.png", emu.screenshot_png()).ok();
 
     // Dump homescreen state
     let cursor_row = emu.peek_byte(0xD00595);
@@ -2635,8 +2689,7 @@ fn cmd_rundoom() {
 
     // Take final screenshot
     emu.render_frame();
-    save_framebuffer_ppm(&emu, "/tmp/doom_after_exec.ppm");
-    convert_ppm_to_png("/tmp/doom_after_exec.ppm", "/tmp/doom_after_exec.png");
+    fs::write("/tmp/doom_after_exec.png", emu.screenshot_png()).ok();
 
     println!("\nScreenshots: /tmp/doom_after_exec.png");
 }
@@ -2706,13 +2759,6 @@ fn press_key_verbose(emu: &mut Emu, name: &str, row: usize, col: usize, hold_cyc
         released, release_cycles, emu.pc(), emu.is_halted(), emu.is_off());
 }
 
-fn convert_ppm_to_png(ppm_path: &str, png_path: &str) {
-    let _ = Command::new("sips")
-        .args(["-s", "format", "png", ppm_path, "--out", png_path])
-        .output();
-    let _ = fs::remove_file(ppm_path);
-}
-
 /// Dump TI-OS Variable Allocation Table to see registered programs
 fn dump_vat(emu: &mut Emu) {
     // Read key pointers from RAM
@@ -13,28 +13,369 @@ typedef void (*emu_log_cb_t)(const char* message);
 Emu* emu_create(void);
 void emu_destroy(Emu*);
 void emu_set_log_callback(emu_log_cb_t cb);
+// static description of any return code below ("ok" for >= 0); do not free
+const char* emu_error_string(int code);
+// core-owned strings: functions returning `char*` (rather than const
+// char*) hand over a NUL-terminated string the caller must release with
+// emu_string_free; they stay valid after emu_destroy. NULL, static
+// strings and double frees are ignored
+void emu_string_free(char* text);
 
 // ROM loading (bytes only)
 int  emu_load_rom(Emu*, const uint8_t* data, size_t len); // 0 ok, else error code
 
+// ROM-less demo: the open test ROM cycling test patterns, with a strip
+// along the bottom lighting one cell per held key (keypad groups 1-7, bit
+// 0 first). Loads in place of emu_load_rom; then emu_power_on as usual.
+// patterns 0 = all, seconds per pattern 0 = 3, at most 60
+enum {
+    EMU_DEMO_BARS = 1,    // vertical color bars
+    EMU_DEMO_GRAY = 2,    // horizontal gray bands
+    EMU_DEMO_CHECKER = 4, // black and white checkerboard
+};
+int  emu_load_demo(Emu*, uint32_t patterns, uint32_t seconds); // 0 ok, -1 null, -30 bad mask/seconds
+
 // Send .8xp/.8xv file (injects into flash archive before boot)
 // Must be called after load_rom() and before power_on().
-// Returns: entry count (>=0) or negative error code
+// Returns: entry count (>=0) or negative error code: -1 null/empty,
+// -10 no ROM, -11 not a TI variable file, -12 archive full, -13 already
+// booted, -14 bad checksum, -15 truncated entry, -16 variable too large
 int  emu_send_file(Emu*, const uint8_t* data, size_t len);
+// Same, while running: replaces same-named archived variables, then soft
+// resets so TI-OS finds them. Same error codes except -13.
+int  emu_send_file_live(Emu*, const uint8_t* data, size_t len);
+
+// Flash an OS upgrade (.8eu) from the host: erase + program the OS sectors,
+// verify, then reboot. Bypasses the boot code's receive mode and signature
+// check, so it is not an emulated "Transfer OS". Returns bytes programmed,
+// or -1 null, -80 no ROM, -81 not a flash file, -82 no CE OS, -83 too
+// large, -84 verify failed
+int  emu_flash_os(Emu*, const uint8_t* data, size_t len);
+
+// flash patch sets (IPS, or text lines "address: hex bytes"): applied now
+// and after every ROM load and reset; the ROM hash stays the unpatched
+// ROM's. Returns bytes patched, or -1 null, -110 malformed, -111 past flash
+int  emu_patch_add(Emu*, const uint8_t* data, size_t len);
+void emu_patch_clear(Emu*); // patched bytes stay until the next emu_load_rom
+
+// stable hash of the loaded ROM (boot code + OS, as loaded or installed);
+// key per-ROM settings on it. 0 ok, -1 null, -10 no ROM
+int  emu_rom_hash(Emu*, uint64_t* out);
+
+// model and versions from the loaded flash: the OS header (TI certificate
+// fields at 0x020000) and the first "a.b.c.dddd" string in the boot code.
+// Check after emu_load_rom and skip emu_power_on unless both EMU_ROM_BOOT_CODE
+// and EMU_ROM_CERT_VALID are set: such a dump won't boot
+enum {
+    EMU_MODEL_UNKNOWN = 0,
+    EMU_MODEL_TI84_PLUS_CE = 1,
+    EMU_MODEL_TI83_PREMIUM_CE = 2,
+    EMU_MODEL_TI82_AEP = 3, // TI-82 Advanced Edition Python
+};
+enum {
+    EMU_ROM_BOOT_CODE = 1,    // boot sectors not erased
+    EMU_ROM_BOOT_VERSION = 2, // boot_* valid
+    EMU_ROM_OS_VERSION = 4,   // os_* valid
+    EMU_ROM_CERT_VALID = 8,   // OS header parses and names a known product
+};
+typedef struct {
+    uint32_t model;       // EMU_MODEL_*
+    uint16_t boot_build;
+    uint8_t  boot_major, boot_minor, boot_revision;
+    uint8_t  os_major, os_minor, os_revision;
+    uint8_t  flags;       // EMU_ROM_*
+    uint8_t  reserved[3];
+} EmuRomInfo;
+int  emu_rom_info(const Emu*, EmuRomInfo* out); // 0 ok, -1 null, -10 no ROM
+
+void emu_reset(Emu*); // same as EMU_RESET_KIND_RAM_CLEAR
 
-void emu_reset(Emu*);
+// what a frontend reset clears; the calculator then waits for emu_power_on
+enum {
+    EMU_RESET_KIND_POWER_CYCLE = 0, // off and on: RAM stays powered, no "RAM Cleared"
+    EMU_RESET_KIND_RAM_CLEAR = 1,   // RAM cleared, archive and OS kept
+    EMU_RESET_KIND_FULL = 2,        // RAM and archive cleared, OS kept
+};
+int  emu_reset_kind(Emu*, int kind); // 0 ok, -1 null, -30 unknown kind
+
+// cause of the most recent reset: power-on = ROM load, frontend = emu_reset,
+// emu_reset_kind (other than a power cycle) or live file transfer, key
+// combo = reset keys held with ON. Watchdog, software and power-cycle
+// resets keep RAM; every other cause clears it
+enum {
+    EMU_RESET_POWER_ON = 0,
+    EMU_RESET_FRONTEND = 1,
+    EMU_RESET_WATCHDOG = 2,
+    EMU_RESET_KEY_COMBO = 3,
+    EMU_RESET_SOFTWARE = 4,
+    EMU_RESET_POWER_CYCLE = 5,
+};
+int  emu_get_reset_cause(const Emu*); // EMU_RESET_*, -1 null
+// report (reset cause, cycles, registers) saved when the last frame crashed;
+// free with emu_string_free; null if none or for a null pointer
+char* emu_crash_report(const Emu*);
+
+// reset button with keys held, read by the boot code; restarts without
+// emu_power_on and releases the keys once booting has seen them
+enum {
+    EMU_RESET_COMBO_BUTTON = 0,     // alone: RAM cleared
+    EMU_RESET_COMBO_REINSTALL = 1,  // [del]: wait for a new OS
+    EMU_RESET_COMBO_2ND_DEL = 2,    // [2nd]+[del]
+};
+int  emu_reset_combo(Emu*, int combo); // 0 ok, -1 null, -30 unknown combo
+
+// boot code sectors (0x000000-0x01FFFF) ignore CPU program/erase unless
+// protection is turned off; on by default, not part of save states
+int  emu_set_boot_protect(Emu*, int enabled); // 0 ok, -1 null
 
 // Power on (simulate ON key press+release to wake from reset)
 void emu_power_on(Emu*);
 
 // execution
 int  emu_run_cycles(Emu*, int cycles); // returns executed cycles
+// run to the LCD's next vertical sync, then render (LCD off: one 60 Hz
+// frame of cycles); returns executed cycles for pacing
+int  emu_run_frame(Emu*);
+// how often emu_run_cycles services peripherals; coarser is faster but
+// interrupts and timer/keypad reads can lag (not part of save states)
+enum {
+    EMU_GRANULARITY_INSTRUCTION = 0, // every instruction (exact)
+    EMU_GRANULARITY_CYCLES = 1,      // every `cycles` cycles
+    EMU_GRANULARITY_EVENTS = 2,      // when a scheduler event is due
+    EMU_GRANULARITY_LOOKAHEAD = 3,   // up to the next deadline (default, exact)
+};
+int  emu_set_run_granularity(Emu*, int mode, uint32_t cycles); // 0 ok, -1 null, -30 bad mode
+// make emu_run_cycles return at the next instruction boundary; callable
+// from any thread while it runs (lock-free); consumed by that call
+int  emu_request_stop(const Emu*); // 0 ok, -1 null
+// execute one instruction (a halted CPU runs to its wake-up); registers
+// are those before it ran; works while paused
+typedef struct {
+    uint64_t total_cycles; // cycles after the instruction
+    uint32_t pc;
+    uint32_t next_pc;      // where execution continues
+    uint32_t sp, bc, de, hl, ix, iy;
+    uint32_t cycles;       // taken by this instruction
+    uint8_t  opcode[4];    // prefix + opcode bytes (no operands), opcode_len valid
+    uint8_t  opcode_len;
+    uint8_t  a, f;
+    uint8_t  adl, iff1, iff2, im, halted;
+} EmuStepInfo;
+int  emu_step(Emu*, EmuStepInfo* out); // 0 ok, -1 null, -10 no ROM/not powered on
+// count instructions from addr in the current ADL/Z80 mode, one
+// "ADDRESS: BYTES  MNEMONIC" line each, read without side effects;
+// NULL if emu is null or count > 65536. Free with emu_string_free
+char* emu_disasm(const Emu*, uint32_t addr, uint32_t count);
+// run to cursor: run until PC reaches addr (from addr itself, until it comes
+// back) or max_cycles have run
+enum {
+    EMU_RUN_UNTIL_REACHED = 0,
+    EMU_RUN_UNTIL_BUDGET = 1,
+    EMU_RUN_UNTIL_STOPPED = 2, // breakpoint, watch, stop request, pause, off
+};
+int  emu_run_until_pc(Emu*, uint32_t addr, uint64_t max_cycles); // EMU_RUN_UNTIL_*, -1 null
+// PC breakpoints, any number: a run returns early before executing an enabled
+// one and queues EMU_EVENT_BREAKPOINT; the next run executes it and carries on
+int  emu_breakpoint_add(Emu*, uint32_t addr);                  // 0 ok, -1 null, -30 addr > 0xFFFFFF
+int  emu_breakpoint_remove(Emu*, uint32_t addr);               // 0 ok, -1 null, -30 none there
+int  emu_breakpoint_enable(Emu*, uint32_t addr, int enabled);  // 0 ok, -1 null, -30 none there
+int  emu_breakpoint_clear(Emu*);                               // 0 ok, -1 null
+int  emu_last_breakpoint(const Emu*, uint32_t* addr); // 1 last run stopped at one (addr set), 0 not, -1 null
+// main CPU registers for a debugger view; 24-bit values, sp is the stack
+// pointer for the current mode (SPL when adl, SPS otherwise); a changed pc
+// takes effect on the next instruction
+typedef struct {
+    uint32_t bc, de, hl, ix, iy;
+    uint32_t sp;
+    uint32_t pc;
+    uint16_t af;           // A << 8 | F
+    uint8_t  mbase;
+    uint8_t  adl;
+} EmuRegisters;
+int  emu_get_registers(const Emu*, EmuRegisters* out);  // 0 ok, -1 null
+int  emu_set_registers(Emu*, const EmuRegisters* regs); // 0 ok, -1 null
+
+// pause: emu_run_cycles does nothing and host time stops counting, so the
+// clock does not jump on resume; host time is any monotonic ms clock
+int  emu_set_paused(Emu*, int paused); // 0 ok, -1 null
+int  emu_is_paused(const Emu*);        // 1 paused, 0 running
+int  emu_sync_host_time(Emu*, uint64_t now_ms); // 0 ok, -1 null
+// RTC source: 0 = emulated cycles (turbo fast-forwards), 1 = host time
+int  emu_set_rtc_host_time(Emu*, int enable); // 0 ok, -1 null
+// RTC crystal error in ppm (+ fast, - slow), |ppm| <= 10000, kept across reset.
+int  emu_set_rtc_drift(Emu*, int ppm); // 0 ok, -1 null, -30 out of range
+// RTC trim: ticks skipped (+) or inserted (-) per 2^20, ~0.95 ppm each, to
+// model a calibrated crystal; kept across reset, not saved with state.
+int  emu_set_rtc_trim(Emu*, int trim); // 0 ok, -1 null, -30 outside int16
+
+// performance stats for the last completed one-second window
+typedef struct {
+    uint64_t cycles;        // emulated cycles executed
+    uint32_t frames;        // emu_run_cycles / emu_run_frame calls
+    uint64_t avg_frame_ns;  // average host time per call
+    uint64_t mutex_wait_ns; // total time those calls waited for the lock
+    uint64_t window_ns;     // actual window length
+} EmuPerfStats;
+int  emu_get_perf_stats(const Emu*, EmuPerfStats* out); // 0 ok, -1 null
 
-// framebuffer (owned by core), ARGB8888
+// bus access counters since the previous call or reset (cleared on read)
+typedef struct {
+    uint64_t fetches;     // instruction byte fetches
+    uint64_t reads;       // data byte reads (memory and MMIO)
+    uint64_t writes;      // data byte writes (memory and MMIO)
+    uint64_t port_reads;  // IN instructions
+    uint64_t port_writes; // OUT instructions
+    uint64_t wait_cycles; // memory/port wait states
+} EmuBusCounters;
+int  emu_take_bus_counters(Emu*, EmuBusCounters* out); // 0 ok, -1 null
+
+// rough supply current model, sampled after every run and step (estimates,
+// in uA); while off, the standby draw is counted over the host time
+// reported with emu_sync_host_time
+typedef struct {
+    uint32_t current_ua;   // total over the last run (standby while off)
+    uint32_t cpu_ua;       // by speed and share of cycles not halted
+    uint32_t lcd_ua;       // controller and panel while enabled
+    uint32_t backlight_ua; // linear in brightness
+    uint32_t flash_ua;     // by share of cycles accessing flash
+    uint32_t average_ua;   // since reset
+    uint64_t charge_uah;   // drawn since reset
+    uint64_t elapsed_ns;   // emulated plus off time since reset
+} EmuPowerStats;
+int  emu_get_power_stats(const Emu*, EmuPowerStats* out); // 0 ok, -1 null
+
+// watchdog pets (0xB9 written to the restart register) since reset or state
+// load. With a stall threshold set, EMU_EVENT_WATCHDOG_STALL is queued once
+// when the OS goes that many emulated ms without a pet (armed by the first
+// pet, re-armed by the next; time powered off doesn't count)
+typedef struct {
+    uint64_t pets;
+    uint64_t last_pet_ns;       // emulated time since reset, 0 before any pet
+    uint32_t mean_interval_us;  // between pets, 0 before the second
+    uint32_t max_interval_us;
+    uint32_t stalls;            // stall events queued
+    uint32_t stall_ms;          // threshold, 0 = off
+} EmuWatchdogStats;
+int  emu_get_watchdog_stats(const Emu*, EmuWatchdogStats* out); // 0 ok, -1 null
+int  emu_set_watchdog_stall(Emu*, uint32_t ms);                 // 0 = off; 0 ok, -1 null
+
+// LCD frames generated vs distinct frames fetched via emu_framebuffer*;
+// a rising dropped count means the frontend isn't keeping up
+typedef struct {
+    uint64_t generated; // LCD vertical syncs (none while the LCD is off)
+    uint64_t fetched;   // generated frames read at least once
+    uint64_t dropped;   // generated frames replaced before being read
+} EmuFrameCounters;
+int  emu_get_frame_counters(const Emu*, EmuFrameCounters* out); // 0 ok, -1 null
+
+// called at each LCD vertical sync with the frame number (as counted in
+// EmuFrameCounters.generated) and dirty = 1 if frame RAM at UPBASE was
+// written, or UPBASE/control/palette changed, since the previous frame
+// (0 means the picture is unchanged); runs with the emulator locked, so
+// don't call emu_* from it (fetch the framebuffer after emu_run_cycles
+// returns)
+typedef void (*emu_frame_cb_t)(void* user, uint64_t frame, uint32_t dirty);
+int  emu_set_frame_callback(Emu*, emu_frame_cb_t cb, void* user); // 0 ok, -1 null
+
+// framebuffer (owned by core), ARGB8888; counts as a fetch. The pixels
+// change under the pointer whenever the emulator runs: with a run loop on
+// another thread, use emu_copy_framebuffer instead
 const uint32_t* emu_framebuffer(const Emu*, int* w, int* h);
+// framebuffer copied into a caller-owned buffer under the emulator lock;
+// copies nothing if cap (pixels) is too small, so cap 0 queries; counts
+// as a fetch
+int emu_copy_framebuffer(const Emu*, uint32_t* dst, size_t cap, int* w, int* h); // pixel count, -1 null
+// framebuffer encoded as a PNG file (RGB, 320x240) into out; copies nothing
+// if cap (bytes) is too small, so cap 0 queries the size
+int emu_screenshot_png(const Emu*, uint8_t* out, size_t cap); // byte count, -1 null
+// framebuffer copied at integer scale 1-4, rotated clockwise 0/90/180/270
+// degrees; copies nothing if cap (pixels) is too small, so cap 0 queries
+int emu_framebuffer_scaled(const Emu*, int scale, int rotation, uint32_t* out, size_t cap,
+                           int* w, int* h); // pixel count, -1 null, -30 bad scale/rotation
+// framebuffer copy with a debug HUD (PC, frame count, watch values) drawn in
+// the top-left; needs cap >= 320*240 pixels and counts as a fetch
+enum {
+    EMU_HUD_PC = 1,
+    EMU_HUD_FRAMES = 2,
+    EMU_HUD_WATCHES = 4,
+};
+int emu_debug_overlay(const Emu*, uint32_t flags, uint32_t* out, size_t cap); // 0 ok, -1 null, -30 bad flags/cap
+
+// raw frame at UPBASE before color conversion: 320x240 at bpp 8 (palette
+// indices) or 16 (RGB565 LE). NULL if UPBASE isn't a full frame of RAM;
+// valid until the next call that runs or modifies the emulator
+const uint8_t* emu_vram_ptr(const Emu*, size_t* len, int* bpp);
+int emu_read_vram(const Emu*, uint8_t* out, size_t cap); // frame size, -1 null, -30 bad UPBASE
+
+// color profile applied to the framebuffer (not saved in state)
+enum {
+    EMU_COLOR_RAW = 0,   // CEmu colors
+    EMU_COLOR_PANEL = 1, // real LCD: lifted blacks, dim whites, less saturation
+    EMU_COLOR_VIVID = 2,
+};
+int emu_set_color_profile(Emu*, int profile); // 0 ok, -1 null, -30 unknown profile
+int emu_get_color_profile(const Emu*);        // EMU_COLOR_*, -1 null
+
+// format of emu_framebuffer_formatted, converted once per frame in core
+// (emu_framebuffer stays ARGB8888)
+enum {
+    EMU_FORMAT_ARGB8888 = 0,        // uint32_t 0xAARRGGBB, no conversion
+    EMU_FORMAT_RGB565 = 1,          // uint16_t, little-endian
+    EMU_FORMAT_RGBA8888_PREMUL = 2, // bytes R, G, B, A, premultiplied
+};
+int emu_set_framebuffer_format(Emu*, int format); // 0 ok, -1 null, -30 unknown format
+int emu_get_framebuffer_format(const Emu*);       // EMU_FORMAT_*, -1 null
+// framebuffer (owned by core) in the chosen format, stride in bytes;
+// counts as a fetch
+const void* emu_framebuffer_formatted(const Emu*, int* w, int* h, int* stride);
+
+// scanline rendering: rows are decoded as the LCD scans them out, so
+// mid-frame palette/VRAM changes (raster effects) show as on hardware; off
+// = one VRAM snapshot after each run. The framebuffer then holds the last
+// complete frame (not saved in state)
+int emu_set_scanline_render(Emu*, int enabled); // 0 ok, -1 null
+int emu_get_scanline_render(const Emu*);        // 1 on, 0 off, -1 null
 
 // input
 void emu_set_key(Emu*, int row, int col, int down);
+// ON key: drives the ON line, not the scanned matrix. Raises the ON
+// interrupt, wakes the CPU from HALT even with interrupts disabled, and
+// powers on or wakes a sleeping calculator (emu_set_key(2, 0) does the
+// same). 1 if the press powered on or woke the calculator, 0 otherwise,
+// -1 null
+int  emu_press_on_key(Emu*, int down);
+// keypad matrix, 8 bytes each (one per row as in emu_set_key, bit = col):
+// keys held now, and press edges no scan has picked up yet (a key tapped
+// between scans still reads as pressed once). The OS sees held | edges,
+// without ON (row 2 bit 0), which has its own line; no ghosting is
+// emulated. Either pointer may be NULL. 0 ok, -1 null
+int  emu_get_key_matrix(const Emu*, uint8_t* keys, uint8_t* edges);
+// keys by name instead of row/col, case-insensitive: GRAPH TRACE ZOOM WINDOW
+// YEQU 2ND MODE DEL ON STO LN LOG SQUARE RECIP MATH ALPHA 0-9 COMMA SIN APPS
+// XTTN DOT LPAREN COS PRGM STAT NEG RPAREN TAN VARS ENTER ADD SUB MUL DIV
+// POWER CLEAR DOWN LEFT RIGHT UP, or the key's symbol (Y= , . ( ) (-) + - * / ^)
+int emu_press_key_name(Emu*, const char* name);   // 0 ok, -1 null, -30 unknown name
+int emu_release_key_name(Emu*, const char* name); // 0 ok, -1 null, -30 unknown name
+// open an OS screen from anywhere ([2nd][mode] first), pressing the keys
+// for the OS version in flash; runs the emulator while keys are pressed
+enum {
+    EMU_SCREEN_HOME = 0,
+    EMU_SCREEN_MODE = 1,
+    EMU_SCREEN_MEMORY = 2,         // [2nd][+] MEM menu
+    EMU_SCREEN_MEM_ABOUT = 3,
+    EMU_SCREEN_MEM_MANAGEMENT = 4, // Mem Management/Delete...
+    EMU_SCREEN_MEM_RESET = 5,
+    EMU_SCREEN_CATALOG = 6,
+    EMU_SCREEN_PROGRAMS = 7,       // [prgm]
+    EMU_SCREEN_PYTHON_APP = 8,     // Python models, OS 5.6+
+};
+// keys pressed (0 if the OS lacks the screen), -1 null, -10 no ROM, -30 unknown screen
+int emu_nav_open(Emu*, int screen);
+// type expr on the homescreen (digits . + - * / ^ ( ), ~ for negation),
+// press ENTER and read back Ans; runs the emulator meanwhile.
+// 0 ok, -1 null, -10 no ROM, -30 untypeable char, -90 no Ans, -91 Ans not real,
+// -92 OS error (ERR:SYNTAX, ...), -93 no result in time
+int emu_eval(Emu*, const char* expr, double* out);
 
 // backlight
 uint8_t emu_get_backlight(const Emu*); // 0-255, 0 = off (screen black)
@@ -42,10 +383,300 @@ uint8_t emu_get_backlight(const Emu*); // 0-255, 0 = off (screen black)
 // LCD state - 1 if LCD is on (show content), 0 if LCD is off (show black)
 int emu_is_lcd_on(const Emu*);
 
+// homescreen text from the OS text shadow (for screen readers): UTF-8,
+// one line per row; cursor gets (row, col) if not NULL.
+// full text length or -1 null
+int emu_screen_text(const Emu*, char* out, size_t cap, uint8_t* cursor);
+
+// 1 if the last frame came from a valid UPBASE, 0 if a diagnostic pattern was drawn
+int emu_lcd_upbase_valid(const Emu*);
+
+// effective refresh rate from the LCD timing registers, in millihertz (0 = LCD off)
+uint32_t emu_lcd_refresh_millihz(const Emu*);
+
+// emulator events: delivered to the callback from inside emu_* calls with the
+// emulator locked (the callback must not call back into emu_*), and queued for
+// emu_poll_event (last 256 kept). value: brightness (backlight), 1 = valid
+// UPBASE (frame done, at each LCD vsync), PC (breakpoint, trap), variables
+// sent (transfer), registers changed (mmio diff), source bit |
+// EMU_IRQ_EDGE_* (interrupt watch)
+enum {
+    EMU_EVENT_LCD_ON = 1,
+    EMU_EVENT_LCD_OFF = 2,
+    EMU_EVENT_BACKLIGHT_ON = 3,
+    EMU_EVENT_BACKLIGHT_OFF = 4,
+    EMU_EVENT_FRAME_DONE = 5,
+    EMU_EVENT_BREAKPOINT = 6,
+    EMU_EVENT_TRANSFER_COMPLETE = 7,
+    EMU_EVENT_TRAP = 8,
+    EMU_EVENT_MMIO_DIFF = 9,
+    EMU_EVENT_INTERRUPT_WATCH = 10,
+    EMU_EVENT_BENCHMARK_DONE = 11,
+    EMU_EVENT_WATCHPOINT = 12, // value: accessed address
+    EMU_EVENT_PORT_WATCH = 13, // value: MMIO address or IN/OUT port
+    EMU_EVENT_WATCHDOG_STALL = 14, // value: emulated ms since the last pet
+};
+typedef struct {
+    uint32_t kind;
+    uint32_t value;
+    uint64_t cycles; // emulated cycle count when the event happened
+} EmuEvent;
+typedef void (*emu_event_cb_t)(void* user, const EmuEvent* event);
+void emu_set_event_callback(Emu*, emu_event_cb_t cb, void* user);
+int  emu_poll_event(Emu*, EmuEvent* out); // 1 event written, 0 empty, -1 null
+
+// bus snooping: every CPU data read/write in start..=end (inclusive, no
+// instruction fetches) with the PC that made it; the callback runs with the
+// emulator locked and must not call emu_*; NULL cb stops
+typedef struct {
+    uint64_t cycles;   // total cycles at the access
+    uint32_t addr;
+    uint32_t pc;       // instruction that made the access
+    uint8_t  value;    // byte read or written
+    uint8_t  write;    // 1 write, 0 read
+    uint8_t  reserved[2];
+} EmuSnoopAccess;
+typedef void (*emu_snoop_cb_t)(void* user, const EmuSnoopAccess* access);
+int  emu_snoop_range(Emu*, uint32_t start, uint32_t end,
+                     emu_snoop_cb_t cb, void* user); // 0 ok, -1 null, -30 bad range
+
+// data watchpoints: a run returns early after an instruction whose data
+// accesses touch start..=end (no instruction fetches or peeks/pokes) and
+// queues EMU_EVENT_WATCHPOINT
+enum {
+    EMU_WATCHPOINT_READ = 1,
+    EMU_WATCHPOINT_WRITE = 2,
+    EMU_WATCHPOINT_READ_WRITE = 3,
+};
+int  emu_watchpoint_add(Emu*, uint32_t start, uint32_t end, uint32_t access); // id > 0, -1 null, -30 bad range/access
+int  emu_watchpoint_remove(Emu*, int id); // 0 ok, -1 null, -30 unknown id
+int  emu_watchpoint_clear(Emu*);          // 0 ok, -1 null
+int  emu_last_watchpoint(const Emu*, EmuSnoopAccess* out); // 1 last run stopped at one (out set), 0 not, -1 null
+
+// port watches: CPU accesses to MMIO ports (0xE00000-0xFFFFFF) or IN/OUT
+// port numbers (0-0xFFFF) go to the callback, with old and new register
+// values; stop filters also end the run after the instruction and queue
+// EMU_EVENT_PORT_WATCH. The callback runs with the emulator locked and
+// must not call emu_*; NULL cb stops
+enum {
+    EMU_PORT_SPACE_MMIO = 0,
+    EMU_PORT_SPACE_IO = 1,
+};
+typedef struct {
+    uint64_t cycles;    // total cycles at the access
+    uint32_t addr;      // MMIO address, or port number when io
+    uint32_t pc;        // instruction that made the access
+    uint8_t  old_value; // register before (value read, for reads)
+    uint8_t  new_value; // value written (value read, for reads)
+    uint8_t  write;     // 1 write, 0 read
+    uint8_t  io;        // 1 IN/OUT port, 0 MMIO
+} EmuPortAccess;
+typedef void (*emu_port_watch_cb_t)(void* user, const EmuPortAccess* access);
+int  emu_port_watch_add(Emu*, int space, uint32_t start, uint32_t end, uint32_t access,
+                        int stop); // id > 0, -1 null, -30 bad space/range/access
+int  emu_port_watch_remove(Emu*, int id); // 0 ok, -1 null, -30 unknown id
+int  emu_port_watch_clear(Emu*);          // 0 ok, -1 null (callback kept)
+int  emu_port_watch_callback(Emu*, emu_port_watch_cb_t cb, void* user); // 0 ok, -1 null
+int  emu_last_port_watch(const Emu*, EmuPortAccess* out); // 1 last run stopped at one (out set), 0 not, -1 null
+
 // optional save state (buffer-based)
 size_t emu_save_state_size(const Emu*);
 int    emu_save_state(const Emu*, uint8_t* out, size_t cap); // bytes written or <0
+// 0 ok, -102 not a state, -103 unsupported version, -104 other ROM,
+// -105 truncated/corrupt, -106 checksum mismatch (nothing restored)
 int    emu_load_state(Emu*, const uint8_t* data, size_t len);
+// also loads states from another ROM/OS, keeping the current flash:
+// 0 exact, 1 flash kept (warning), <0 as emu_load_state
+int    emu_load_state_best_effort(Emu*, const uint8_t* data, size_t len);
+// state for bug reports without ROM contents: flash replaced by sector
+// hashes; copies nothing if cap (bytes) is too small, so cap 0 queries
+int    emu_save_redacted_state(const Emu*, uint8_t* out, size_t cap); // size, -1 null, -10 no ROM
+// loads one onto the loaded ROM's flash: flash sectors differing from the
+// reporter's (>= 0), -1 null, -10 no ROM, or an emu_load_state error
+int    emu_load_redacted_state(Emu*, const uint8_t* data, size_t len);
+
+// in-memory quick-save slots 0-9 (each holds a full save state, ~4.5MB)
+typedef struct {
+    uint32_t used;      // 1 if the slot holds a state
+    uint32_t size;      // state size in bytes
+    uint64_t cycles;    // emulated cycle count at save time
+    uint64_t sequence;  // increases with every save (newest slot is largest)
+} EmuSlotInfo;
+int  emu_slot_save(Emu*, int n);                      // bytes saved or <0 (-40 bad slot, -42 over limit)
+int  emu_slot_load(Emu*, int n);                      // 0 ok, -41 empty, or load_state error
+int  emu_slot_info(const Emu*, int n, EmuSlotInfo* out);
+int  emu_slot_clear(Emu*, int n);
+void emu_slot_set_limit(Emu*, size_t max_bytes);      // total across slots, 0 = unlimited
+
+// time-travel watch: which instruction last changed a byte. Replays from the
+// newest slot saved before now (then older ones) with a write watchpoint on
+// addr. Returns 1 if found, 0 if unchanged since the oldest slot, -43 if no
+// slot is older than now, -1 null, or an emu_load_state error; the current
+// state is restored. Breakpoints, watches, callbacks, heatmap, coverage and
+// macro recording see nothing of the replay.
+typedef struct {
+    uint64_t cycles;    // total cycles at the write
+    uint32_t addr;
+    uint32_t pc;        // instruction that wrote
+    uint32_t slot;      // slot the replay started from
+    uint8_t old_value;
+    uint8_t new_value;
+    uint8_t reserved[2];
+} EmuLastChange;
+int  emu_find_last_change(Emu*, uint32_t addr, EmuLastChange* out);
+// same, but on 1 leave the emulator stopped just after the writing
+// instruction; the current state is first saved to present_slot
+// (emu_slot_load returns to it). -40 bad slot.
+int  emu_rewind_to_last_change(Emu*, uint32_t addr, int present_slot, EmuLastChange* out);
+
+// autosave sink: receives a final save state from emu_destroy, when emulation
+// panics inside emu_run_cycles, or at a memory protection trap (EMU_EVENT_TRAP).
+// data is only valid during the call; the callback runs with the emulator
+// locked and must not call back into emu_*.
+enum { EMU_AUTOSAVE_DESTROY = 0, EMU_AUTOSAVE_CRASH = 1, EMU_AUTOSAVE_TRAP = 2 };
+typedef void (*emu_autosave_cb_t)(void* user, const uint8_t* data, size_t len, uint32_t reason);
+void emu_set_autosave_sink(Emu*, emu_autosave_cb_t cb, void* user);
+
+// extension devices: claim a range inside an unmapped MMIO window
+// (0xE40000-0xEFFFFF or 0xFB0000-0xFEFFFF); CPU loads/stores there go to the
+// callbacks with the offset from base. Callbacks run with the emulator locked.
+typedef uint8_t (*emu_ext_read_cb_t)(void* user, uint32_t offset);
+typedef void (*emu_ext_write_cb_t)(void* user, uint32_t offset, uint8_t value);
+int  emu_register_extension(Emu*, uint32_t base, uint32_t len,
+                            emu_ext_read_cb_t read, emu_ext_write_cb_t write, void* user);
+                                                      // id >= 0, or -50 bad range, -51 overlap, -52 full
+int  emu_unregister_extension(Emu*, uint32_t id);     // 0 ok, -53 unknown id
+
+// host bridge (off by default): registers at 0xFE0000 for test programs
+// +0 W output byte, +1 R next input byte, +2 R input pending, +3 R magic 0xB7,
+// +4 W test result (0 = pass, else fail code), +5 W 0x5A software reset
+// (RAM kept, EMU_RESET_SOFTWARE)
+int  emu_host_bridge_enable(Emu*, int enable);        // 0 ok, -51 range taken
+int  emu_host_bridge_input(Emu*, const uint8_t* data, size_t len);
+int  emu_host_bridge_output(Emu*, uint8_t* buf, size_t cap); // bytes copied
+// output is buffered up to 64 KiB; past that the oldest bytes are dropped
+int  emu_host_bridge_dropped(const Emu*, uint64_t* out); // 0 ok, -60 disabled
+int  emu_host_bridge_result(const Emu*);              // 0 pass, 1-255 fail, -61 none yet
+
+// bulk memory access for memory editors
+// flags bit 0: 1 = normal bus access (wait states, side effects), 0 = debug peek/poke
+// returns 0 ok, -30 if [addr, addr+len) leaves the 24-bit address space
+int  emu_read_block(const Emu*, uint32_t addr, uint8_t* out, size_t len, int flags);
+int  emu_write_block(Emu*, uint32_t addr, const uint8_t* data, size_t len, int flags);
+// debug peek/poke (flags 0 above): no cycles, no port side effects, flash
+// patched directly; 0 ok, -1 null, -30 out of range
+int  emu_read_memory(const Emu*, uint32_t addr, uint8_t* buf, size_t len);
+int  emu_write_memory(Emu*, uint32_t addr, const uint8_t* buf, size_t len);
+
+// masked pattern search/replace over flash and RAM ("3E ?? C9", ? = nibble wildcard)
+// search: writes up to cap addresses, returns count or <0
+// replace: flags bit 0 allows patching executing code (else -31); returns patched count or <0
+int  emu_search(const Emu*, const char* pattern, uint32_t start, uint32_t end, uint32_t* out, size_t cap);
+int  emu_replace(Emu*, const char* pattern, const char* replacement, uint32_t start, uint32_t end, int flags);
+
+// snippet test runner (no emulator instance needed): spec lines are
+// "pc", "adl", "max", "halt", "code/mem ADDR bytes", "reg/expect REG=val",
+// "expect_mem ADDR bytes" (hex); starts in ADL mode unless "adl 0";
+// report gets one line per mismatch
+int  emu_testkit_run(const char* spec, char* report, size_t cap); // 0 pass, 1 fail, -1 null, -70 parse error
+
+// memory map for disassembly/hex views, one region per line:
+// "START END kind name" (hex, END exclusive), kind is flash, ram, vram,
+// mmio, unmapped, debug or extension; covers 0x000000-0xFFFFFF in order
+int  emu_memory_map(const Emu*, char* out, size_t cap); // full text length or -1
+char* emu_memory_map_string(const Emu*);                 // same text, free with emu_string_free; NULL if null
+
+// execution heatmap: per-64-byte instruction counts (u32 LE, flash buckets then RAM)
+void   emu_heatmap_enable(Emu*, int enabled);
+void   emu_heatmap_clear(Emu*);
+size_t emu_heatmap_size(void);
+int    emu_heatmap_export(const Emu*, uint8_t* out, size_t cap); // bytes, -1 null, -33 cap, -34 disabled
+
+// opcode coverage: executed-instruction counts per opcode, 5 pages of 256
+// (main, CB, ED, DD/FD, DD/FD CB); the report lists per-page totals,
+// executed opcodes the core doesn't implement, and opcodes never executed
+void emu_opcode_coverage_enable(Emu*, int enabled);
+void emu_opcode_coverage_clear(Emu*);
+int  emu_opcode_coverage_export(const Emu*, uint32_t* out, size_t cap); // 1280, -1 null, -33 cap, -34 disabled
+int  emu_opcode_coverage_report(const Emu*, char* out, size_t cap);     // full text length, -1 null, -34 disabled
+
+// watch expressions, re-evaluated when a frame is rendered (at the end of
+// emu_run_cycles/emu_run_frame), not while instructions run
+// syntax: registers (a, hl, ix, sp, pc, ...), numbers (0x.., $.., ..h),
+// + - & | ^, and memory reads [addr] / w[addr] / l[addr] (8/16/24-bit);
+// brackets nest at most 32 deep
+int  emu_watch_add(Emu*, const char* expr);  // id (>=0) or <0 error
+int  emu_watch_remove(Emu*, int id);        // 0 ok, -22 unknown id
+void emu_watch_clear(Emu*);
+int  emu_watch_values(const Emu*, uint32_t* ids, uint32_t* values, size_t cap); // total count
+
+// input macro recording (see input_macro.rs): keys and power-on with their
+// cycle offsets plus frame hash checkpoints, replayed by the program corpus
+// test; start before emu_power_on
+int  emu_macro_record_start(Emu*);                  // 0 ok, -1 null
+int  emu_macro_checkpoint(Emu*, uint64_t* hash);    // 0 ok, -1 null, -95 not recording
+// text so far (NUL-terminated, truncated to cap); stop != 0 ends the recording
+int  emu_macro_text(Emu*, char* out, size_t cap, int stop); // full length, -1 null, -95 not recording
+
+// per-frame peripheral register diff: CPU writes (MMIO and OUT) whose net
+// effect over the frame changed the register, with the last writer's PC
+typedef struct {
+    uint32_t addr;     // 0xE00000+ address, or the OUT port number if port
+    uint32_t pc;       // last instruction that wrote it
+    uint32_t writes;   // writes this frame
+    uint8_t old_value; // at the start of the frame
+    uint8_t new_value;
+    uint8_t port;
+    uint8_t reserved;
+} EmuMmioChange;
+void emu_mmio_diff_enable(Emu*, int enabled);
+int  emu_mmio_diff(const Emu*, EmuMmioChange* out, size_t cap); // total count or -1
+
+// interrupt watchpoint: emu_run_cycles stops at the next instruction
+// boundary when a source in `raise` has its status raised or one in `ack`
+// is acknowledged (masks of 1 << EMU_IRQ_*, 0 for none)
+enum {
+    EMU_IRQ_ON_KEY = 0,
+    EMU_IRQ_TIMER1 = 1,
+    EMU_IRQ_TIMER2 = 2,
+    EMU_IRQ_TIMER3 = 3,
+    EMU_IRQ_OSTIMER = 4,
+    EMU_IRQ_KEYPAD = 10,
+    EMU_IRQ_LCD = 11,
+    EMU_IRQ_POWER = 15,
+    EMU_IRQ_WAKE = 19,
+};
+enum {
+    EMU_IRQ_EDGE_RAISED = 0x100,
+    EMU_IRQ_EDGE_ACKNOWLEDGED = 0x200,
+};
+int  emu_set_interrupt_watch(Emu*, uint32_t raise, uint32_t ack); // 0 ok, -1 null
+
+// per-source interrupt statistics since reset, indexed by source bit (times
+// in bus cycles, which rescale with the CPU speed)
+typedef struct {
+    uint64_t raises;               // status bit set (enabled or not)
+    uint64_t acks;                 // cleared through the acknowledge register
+    uint64_t service_cycles_total; // raise to acknowledge, summed over acks
+    uint64_t service_cycles_max;   // longest single raise to acknowledge
+    uint64_t last_raise_cycles;    // bus cycles at the last raise
+} EmuInterruptStats;
+int  emu_get_interrupt_stats(const Emu*, EmuInterruptStats* out, size_t cap); // 22, -1 null, -33 cap
+void emu_clear_interrupt_stats(Emu*);
+
+// emulated-time benchmark (see benchmark.rs): time from a start hook to an
+// end hook, deterministic for the same ROM, state and inputs. A write hook
+// fires on a CPU store to that port address, so a program can mark its own
+// sections (e.g. 0xFD0100); identical hooks alternate start/end. Each
+// completed measurement emits EMU_EVENT_BENCHMARK_DONE (value in us).
+enum {
+    EMU_BENCH_OFF = 0,
+    EMU_BENCH_PC = 1,
+    EMU_BENCH_WRITE = 2,
+};
+int  emu_benchmark_set(Emu*, int start_kind, uint32_t start_addr,
+                       int end_kind, uint32_t end_addr); // 0 ok, -1 null, -30 bad hook
+int  emu_benchmark_results(const Emu*, uint64_t* out_ns, size_t cap); // total count or -1
 
 #ifdef __cplusplus
 }
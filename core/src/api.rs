@@ -0,0 +1,245 @@
+//! Rust-first API
+//!
+//! `Emu` mirrors the C ABI: integer error codes, caller-sized buffers and
+//! raw framebuffer pointers. `Ti84ce` wraps it for Rust consumers (tests,
+//! tools, servers) with `Result`s, owned buffers and slices:
+//!
+//! ```
+//! use emu_core::{test_rom, Ti84ce};
+//!
+//! let mut calc = Ti84ce::new();
+//! let frame = calc.load_rom(&test_rom::build())?.run_frame();
+//! assert_eq!(frame.len(), Ti84ce::WIDTH * Ti84ce::HEIGHT);
+//! # Ok::<(), emu_core::Error>(())
+//! ```
+//!
+//! Anything not covered here is reachable through `emu()` / `emu_mut()`.
+
+use crate::emu::{Emu, StateLoad};
+use crate::error::{EmuError, LoadError};
+use crate::peripherals::{KEYPAD_COLS, KEYPAD_ROWS};
+
+/// Errors returned by `Ti84ce`. `code()` gives the C ABI code, for logging
+/// and for matching against emu.h.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// ROM or save state rejected, with the reason
+    Load(LoadError),
+    /// Variable file rejected by `send_file` (-10..-16)
+    File(EmuError),
+    /// OS upgrade rejected by `flash_os` (-80..-84)
+    Os(EmuError),
+    /// Key outside the 8x8 matrix
+    NoSuchKey { row: usize, col: usize },
+}
+
+impl Error {
+    /// C ABI error code (see emu.h)
+    pub fn code(self) -> i32 {
+        match self {
+            Error::Load(err) => err.code(),
+            Error::File(err) | Error::Os(err) => err.code(),
+            Error::NoSuchKey { .. } => -30,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Error::Load(err) => err.fmt(f),
+            Error::File(err) | Error::Os(err) => err.fmt(f),
+            Error::NoSuchKey { row, col } => write!(f, "no key at row {} col {}", row, col),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Load(err) => Some(err),
+            Error::File(err) | Error::Os(err) => Some(err),
+            Error::NoSuchKey { .. } => None,
+        }
+    }
+}
+
+impl From<LoadError> for Error {
+    fn from(err: LoadError) -> Self {
+        Error::Load(err)
+    }
+}
+
+/// A TI-84 Plus CE
+pub struct Ti84ce {
+    emu: Emu,
+}
+
+impl Default for Ti84ce {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ti84ce {
+    /// Screen width in pixels
+    pub const WIDTH: usize = 320;
+    /// Screen height in pixels
+    pub const HEIGHT: usize = 240;
+    /// CPU cycles per 60 Hz frame at 48 MHz (what the frontends run)
+    pub const FRAME_CYCLES: u32 = 800_000;
+
+    /// Calculator with no ROM
+    pub fn new() -> Self {
+        Self { emu: Emu::new() }
+    }
+
+    /// Load a ROM and power on
+    pub fn with_rom(rom: &[u8]) -> Result<Self, Error> {
+        let mut calc = Self::new();
+        calc.load_rom(rom)?;
+        Ok(calc)
+    }
+
+    /// Load a ROM and power on (press ON), ready to run
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<&mut Self, Error> {
+        self.emu.load_rom(rom)?;
+        self.emu.power_on();
+        Ok(self)
+    }
+
+    /// Run one frame's worth of cycles and return the rendered screen
+    /// (ARGB8888, `WIDTH` x `HEIGHT`)
+    pub fn run_frame(&mut self) -> &[u32] {
+        self.emu.run_cycles(Self::FRAME_CYCLES);
+        self.emu.render_frame();
+        self.emu.framebuffer_data()
+    }
+
+    /// Run up to `cycles` CPU cycles, returning how many ran
+    pub fn run_cycles(&mut self, cycles: u32) -> u32 {
+        self.emu.run_cycles(cycles)
+    }
+
+    /// Last rendered screen (ARGB8888, `WIDTH` x `HEIGHT`)
+    pub fn framebuffer(&self) -> &[u32] {
+        self.emu.framebuffer_data()
+    }
+
+    /// Text on the homescreen, from the OS text shadow
+    pub fn screen_text(&self) -> crate::screen_text::ScreenText {
+        crate::screen_text::extract(self.emu.ram_data())
+    }
+
+    /// Press or release the key at `row`, `col` of the keypad matrix
+    pub fn set_key(&mut self, row: usize, col: usize, down: bool) -> Result<(), Error> {
+        if row >= KEYPAD_ROWS || col >= KEYPAD_COLS {
+            return Err(Error::NoSuchKey { row, col });
+        }
+        self.emu.set_key(row, col, down);
+        Ok(())
+    }
+
+    /// Inject a .8xp/.8xv file into the archive (before the OS boots),
+    /// returning the number of variables written
+    pub fn send_file(&mut self, file: &[u8]) -> Result<usize, Error> {
+        self.emu.send_file(file).map_err(Error::File)
+    }
+
+    /// Program an OS upgrade (.8eu) into flash and reboot, returning the
+    /// OS size in bytes
+    pub fn flash_os(&mut self, file: &[u8]) -> Result<usize, Error> {
+        self.emu.flash_os(file).map_err(Error::Os)
+    }
+
+    /// Save the full machine state
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = vec![0; self.emu.save_state_size()];
+        let len = self
+            .emu
+            .save_state(&mut state)
+            .expect("buffer is save_state_size bytes");
+        state.truncate(len);
+        state
+    }
+
+    /// Restore a state from `save_state`
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), Error> {
+        Ok(self.emu.load_state(state)?)
+    }
+
+    /// Restore a state, keeping the current flash if it was saved with
+    /// another ROM (see `Emu::load_state_best_effort`)
+    pub fn load_state_best_effort(&mut self, state: &[u8]) -> Result<StateLoad, Error> {
+        Ok(self.emu.load_state_best_effort(state)?)
+    }
+
+    /// Stable hash of the loaded ROM (see `Emu::rom_hash`)
+    pub fn rom_hash(&self) -> Option<u64> {
+        self.emu.rom_hash()
+    }
+
+    /// Reset as from the frontend (RAM cleared, ROM kept)
+    pub fn reset(&mut self) {
+        self.emu.reset();
+    }
+
+    /// Underlying emulator, for everything the facade doesn't cover
+    pub fn emu(&self) -> &Emu {
+        &self.emu
+    }
+
+    pub fn emu_mut(&mut self) -> &mut Emu {
+        &mut self.emu
+    }
+
+    pub fn into_inner(self) -> Emu {
+        self.emu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rom;
+
+    #[test]
+    fn test_chained_boot_and_frame() {
+        let mut calc = Ti84ce::new();
+        let frame = calc.load_rom(&test_rom::build()).unwrap().run_frame();
+        assert_eq!(frame.len(), Ti84ce::WIDTH * Ti84ce::HEIGHT);
+
+        for _ in 0..5 {
+            calc.run_frame();
+        }
+        assert!(calc.emu().is_lcd_on());
+        let pixel = calc.framebuffer()[Ti84ce::WIDTH * Ti84ce::HEIGHT - 1];
+        assert_ne!(pixel, 0xFF000000, "test ROM fills the screen");
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut calc = Ti84ce::new();
+        assert_eq!(calc.load_rom(&[]).err(), Some(Error::Load(LoadError::EmptyRom)));
+        assert_eq!(calc.send_file(&[0; 8]).unwrap_err().code(), -10);
+        assert_eq!(calc.set_key(8, 0, true), Err(Error::NoSuchKey { row: 8, col: 0 }));
+        let not_ce = Error::Os(EmuError::Os(crate::os_update::OsUpdateError::NotCeOs));
+        assert_eq!((not_ce.code(), not_ce.to_string().as_str()), (-82, "no TI-84 Plus CE OS in file"));
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let mut calc = Ti84ce::with_rom(&test_rom::build()).unwrap();
+        calc.run_frame();
+        let state = calc.save_state();
+        let pc = calc.emu().pc();
+
+        calc.run_frame();
+        calc.load_state(&state).unwrap();
+        assert_eq!(calc.emu().pc(), pc);
+        assert_eq!(
+            calc.load_state(&state[..16]),
+            Err(Error::Load(LoadError::StateTooShort { len: 16, min: 20 }))
+        );
+    }
+}
@@ -0,0 +1,44 @@
+//! Final save state delivered to the frontend
+//!
+//! Mobile apps can be killed by the OS at any time. A registered sink
+//! receives one last `Emu::save_state` image when the emulator is destroyed
+//! or when emulation panics, so the session can be restored on next launch.
+//! A memory protection trap (the NMI the OS answers with a reset) also
+//! saves, so the state that crashed the calculator can be inspected.
+
+use std::os::raw::c_void;
+
+/// Why the autosave was produced (stable C ABI values, see emu.h)
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutosaveReason {
+    /// emu_destroy was called
+    Destroy = 0,
+    /// Emulation panicked; the state is whatever was reached before the fault
+    Crash = 1,
+    /// Memory protection violation raised an NMI (see `EventKind::Trap`);
+    /// the state is at the trap, before the NMI is taken
+    Trap = 2,
+}
+
+/// Sink callback: `data`/`len` are only valid for the duration of the call.
+/// Called with the emulator locked, so it must not call back into emu_*.
+pub type AutosaveCallback =
+    extern "C" fn(user: *mut c_void, data: *const u8, len: usize, reason: u32);
+
+/// Registered sink plus its user pointer (stored as usize to stay Send)
+#[derive(Clone, Copy)]
+pub(crate) struct AutosaveSink {
+    callback: AutosaveCallback,
+    user: usize,
+}
+
+impl AutosaveSink {
+    pub(crate) fn new(callback: AutosaveCallback, user: *mut c_void) -> Self {
+        Self { callback, user: user as usize }
+    }
+
+    pub(crate) fn deliver(&self, state: &[u8], reason: AutosaveReason) {
+        (self.callback)(self.user as *mut c_void, state.as_ptr(), state.len(), reason as u32);
+    }
+}
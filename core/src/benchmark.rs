@@ -0,0 +1,169 @@
+//! Emulated-time benchmarking between two hook points
+//!
+//! Measures how long a calculator program takes in emulated time, so a CE
+//! program can be benchmarked deterministically in CI: the same ROM, state
+//! and inputs give the same number on any host. Timing starts when the
+//! start hook fires and stops at the end hook, and each start/end pair adds
+//! one result. A hook is either an address the CPU is about to execute, or a
+//! store to a memory-mapped port address, which a program can do on purpose
+//! as a marker (for example `*(volatile uint8_t*)0xFD0100 = 0;`, inside the
+//! CE toolchain's debug port window but outside its registers).
+//!
+//! When both hooks are the same, hits alternate between start and end.
+//!
+//! Time is counted from the cycle count and CPU speed at each hook. The
+//! cycle counter is rescaled whenever the OS changes the CPU speed, so
+//! cycles / clock rate is the emulated time since reset at any speed, and
+//! speed changes inside the measured section are accounted for.
+
+/// Where a measurement starts or ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    /// The CPU is about to execute the instruction at this address
+    Pc(u32),
+    /// The CPU stored a byte to this port address (0xE00000+)
+    Write(u32),
+}
+
+impl Hook {
+    /// C ABI kind code (see emu.h) and address
+    pub fn from_raw(kind: i32, addr: u32) -> Option<Hook> {
+        match kind {
+            1 => Some(Hook::Pc(addr & 0xFFFFFF)),
+            2 if (0xE00000..=0xFFFFFF).contains(&addr) => Some(Hook::Write(addr)),
+            _ => None,
+        }
+    }
+}
+
+/// A port store seen by the bus while a write hook is armed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteMark {
+    pub addr: u32,
+    /// Total cycles at the store
+    pub cycles: u64,
+    /// CPU speed setting at the store (0-3)
+    pub cpu_speed: u8,
+}
+
+/// Emulated time since reset at `cycles` total cycles and speed setting
+/// `cpu_speed`, in nanoseconds
+pub fn emulated_nanos(cycles: u64, cpu_speed: u8) -> u64 {
+    let mhz: u64 = match cpu_speed & 3 {
+        0 => 6,
+        1 => 12,
+        2 => 24,
+        _ => 48,
+    };
+    (cycles as u128 * 1000 / mhz as u128) as u64
+}
+
+/// Start and end hooks plus the measurements taken so far
+#[derive(Debug, Clone)]
+pub struct Benchmark {
+    start: Hook,
+    end: Hook,
+    /// Emulated time at the start hook while a measurement is open
+    started_at: Option<u64>,
+    /// Last hook fed, so a PC hook seen twice before the instruction runs
+    /// (a run ending and resuming there) counts once
+    last_hit: Option<(Hook, u64)>,
+    results: Vec<u64>,
+}
+
+impl Benchmark {
+    pub fn new(start: Hook, end: Hook) -> Self {
+        Self { start, end, started_at: None, last_hit: None, results: Vec::new() }
+    }
+
+    pub fn hooks(&self) -> (Hook, Hook) {
+        (self.start, self.end)
+    }
+
+    /// Port addresses the bus should report stores to
+    pub fn write_hooks(&self) -> Option<[u32; 2]> {
+        match (self.start, self.end) {
+            (Hook::Write(start), Hook::Write(end)) => Some([start, end]),
+            (Hook::Write(addr), _) | (_, Hook::Write(addr)) => Some([addr, addr]),
+            _ => None,
+        }
+    }
+
+    /// Whether `pc` is a start or end PC hook
+    #[inline]
+    pub fn is_pc_hook(&self, pc: u32) -> bool {
+        self.start == Hook::Pc(pc) || self.end == Hook::Pc(pc)
+    }
+
+    /// Whether a measurement is open
+    pub fn running(&self) -> bool {
+        self.started_at.is_some()
+    }
+
+    /// Drop an open measurement (the cycle counter restarted)
+    pub fn abort(&mut self) {
+        self.started_at = None;
+        self.last_hit = None;
+    }
+
+    /// Feed a hook that fired at emulated time `nanos`. Returns the
+    /// duration when this closed a measurement.
+    pub fn hit(&mut self, hook: Hook, nanos: u64) -> Option<u64> {
+        if self.last_hit.replace((hook, nanos)) == Some((hook, nanos)) {
+            return None;
+        }
+        if self.started_at.is_some() && hook == self.end {
+            let elapsed = nanos.saturating_sub(self.started_at.take().unwrap_or(nanos));
+            self.results.push(elapsed);
+            Some(elapsed)
+        } else {
+            if hook == self.start {
+                self.started_at = Some(nanos);
+            }
+            None
+        }
+    }
+
+    /// Durations of the completed measurements in nanoseconds, oldest first
+    pub fn results(&self) -> &[u64] {
+        &self.results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emulated_nanos_survive_speed_changes() {
+        // 48M cycles at 48 MHz is one second; the counter is rescaled
+        // (x/4) when the CPU drops to 12 MHz, and the time stays the same
+        assert_eq!(emulated_nanos(48_000_000, 3), 1_000_000_000);
+        assert_eq!(emulated_nanos(12_000_000, 1), 1_000_000_000);
+        assert_eq!(emulated_nanos(1, 0), 166);
+    }
+
+    #[test]
+    fn test_pairs_and_shared_hook() {
+        let mut bench = Benchmark::new(Hook::Pc(0x100), Hook::Pc(0x200));
+        assert_eq!(bench.write_hooks(), None);
+        assert_eq!(bench.hit(Hook::Pc(0x200), 10), None);
+        assert_eq!(bench.hit(Hook::Pc(0x100), 50), None);
+        assert_eq!(bench.hit(Hook::Pc(0x100), 50), None);
+        assert!(bench.running());
+        // A second start restarts the measurement
+        assert_eq!(bench.hit(Hook::Pc(0x100), 70), None);
+        assert_eq!(bench.hit(Hook::Pc(0x200), 100), Some(30));
+        assert!(!bench.running());
+
+        let mut toggle = Benchmark::new(Hook::Write(0xFD0100), Hook::Write(0xFD0100));
+        assert_eq!(toggle.write_hooks(), Some([0xFD0100, 0xFD0100]));
+        for (i, nanos) in [0, 5, 10, 30].into_iter().enumerate() {
+            assert_eq!(toggle.hit(Hook::Write(0xFD0100), nanos).is_some(), i % 2 == 1);
+        }
+        assert_eq!(toggle.results(), &[5, 20]);
+
+        assert_eq!(Hook::from_raw(2, 0xD00000), None);
+        assert_eq!(Hook::from_raw(1, 0x1000123), Some(Hook::Pc(0x000123)));
+    }
+}
@@ -0,0 +1,186 @@
+//! Boot-to-homescreen regression test
+//!
+//! The `boot` command of the debug example as a gate: boot a real ROM
+//! (`TI-84 CE.rom`, see `test_rom::try_load_real_rom`) and require the OS
+//! to reach its homescreen idle loop with the LCD on within
+//! `BOOT_CYCLE_BUDGET` cycles. On the way the framebuffer is hashed every
+//! `CHECKPOINT_CYCLES` and compared with `tests/boot_frames/<rom hash>.txt`,
+//! so a change to boot timing or rendering shows up at the first frame
+//! that differs, not just as a failed boot.
+//!
+//! A debug boot of a real ROM takes minutes, so that test is ignored by
+//! default:
+//!
+//! ```text
+//! cargo test --release --lib boot_to_homescreen -- --ignored
+//! ```
+//!
+//! The built-in test ROM (`test_rom::build`) boots through the same checks
+//! in every test build, against its committed hash file.
+//!
+//! Record (or re-record, after an intended change) the frame hashes for the
+//! ROM at hand with `BOOT_FRAMES_UPDATE=1`. Without a hash file the budget
+//! is still enforced.
+
+#[cfg(test)]
+mod tests {
+    use crate::input_macro::frame_hash;
+    use crate::test_rom::{self, READY, READY_MAGIC};
+    use crate::Emu;
+    use std::path::{Path, PathBuf};
+
+    /// Boot must reach the homescreen within this many cycles
+    const BOOT_CYCLE_BUDGET: u64 = 150_000_000;
+
+    /// Cycles between framebuffer hashes
+    const CHECKPOINT_CYCLES: u32 = 10_000_000;
+
+    /// TI-OS idle loop (EI; NOP; HALT; PUSH HL), see cmd_boot
+    const IDLE_LOOP: std::ops::RangeInclusive<u32> = 0x085B7D..=0x085B80;
+
+    /// Consecutive checkpoints in the idle loop before boot counts as done
+    const IDLE_CHECKS: u32 = 3;
+
+    /// Checkpoints hashed while the built-in test ROM boots
+    const TEST_ROM_CHECKPOINTS: usize = 4;
+
+    /// Cycles between the built-in test ROM's checkpoints
+    const TEST_ROM_CHECKPOINT_CYCLES: u32 = 1_000_000;
+
+    fn frames_path(rom_hash: u64) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("tests/boot_frames/{:016x}.txt", rom_hash))
+    }
+
+    /// `cycles hash` per line, as written by `BOOT_FRAMES_UPDATE=1`
+    fn parse_frames(text: &str) -> Vec<(u64, u64)> {
+        text.lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (cycles, hash) = line.split_once(' ').expect("`cycles hash` lines");
+                (cycles.parse().unwrap(), u64::from_str_radix(hash.trim(), 16).unwrap())
+            })
+            .collect()
+    }
+
+    /// Checkpoint hashes seen during one boot, checked against the hash
+    /// file as they come in (or recorded with `BOOT_FRAMES_UPDATE=1`)
+    struct FrameLog {
+        rom_hash: u64,
+        update: bool,
+        expected: Option<Vec<(u64, u64)>>,
+        frames: Vec<(u64, u64)>,
+    }
+
+    impl FrameLog {
+        fn new(rom_hash: u64) -> Self {
+            let update = std::env::var("BOOT_FRAMES_UPDATE").is_ok_and(|v| v == "1");
+            let expected = if update {
+                None
+            } else {
+                std::fs::read_to_string(frames_path(rom_hash)).map(|text| parse_frames(&text)).ok()
+            };
+            Self { rom_hash, update, expected, frames: Vec::new() }
+        }
+
+        /// Hash the screen at `total` cycles and check it
+        fn checkpoint(&mut self, emu: &mut Emu, total: u64) {
+            let hash = frame_hash(emu);
+            if let Some((cycles, want)) = self.expected.as_ref().and_then(|e| e.get(self.frames.len()).copied()) {
+                assert_eq!(cycles, total, "checkpoint {} cycle count", self.frames.len());
+                assert_eq!(
+                    hash, want,
+                    "frame at {:.1}M cycles differs (PC={:06X}); re-record with BOOT_FRAMES_UPDATE=1 if intended",
+                    total as f64 / 1_000_000.0,
+                    emu.pc()
+                );
+            }
+            self.frames.push((total, hash));
+        }
+
+        /// Write the hash file when updating, else require every recorded
+        /// checkpoint to have been reached
+        fn finish(self) {
+            let path = frames_path(self.rom_hash);
+            if self.update {
+                let mut text = format!("# boot frame hashes for ROM {:016x}\n", self.rom_hash);
+                for (cycles, hash) in &self.frames {
+                    text.push_str(&format!("{} {:016x}\n", cycles, hash));
+                }
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(&path, text).unwrap();
+                println!("wrote {}", path.display());
+            } else if let Some(expected) = self.expected {
+                assert_eq!(self.frames.len(), expected.len(), "boot finished at a different checkpoint");
+            } else {
+                println!("no frame hashes for ROM {:016x}: record with BOOT_FRAMES_UPDATE=1", self.rom_hash);
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "requires ROM file"]
+    fn test_boot_to_homescreen() {
+        let rom = test_rom::try_load_real_rom().expect("ROM file not found");
+        let mut emu = Emu::new();
+        emu.load_rom(&rom).unwrap();
+        emu.press_on_key();
+        let mut log = FrameLog::new(emu.rom_hash().unwrap());
+        let mut idle = 0;
+        let mut total = 0u64;
+
+        while total < BOOT_CYCLE_BUDGET {
+            total += emu.run_cycles(CHECKPOINT_CYCLES) as u64;
+            if log.frames.is_empty() {
+                emu.release_on_key();
+            }
+            log.checkpoint(&mut emu, total);
+
+            if IDLE_LOOP.contains(&emu.pc()) && emu.is_lcd_on() {
+                idle += 1;
+                if idle >= IDLE_CHECKS {
+                    break;
+                }
+            } else {
+                idle = 0;
+            }
+        }
+
+        assert!(
+            idle >= IDLE_CHECKS,
+            "no homescreen within {}M cycles (PC={:06X}, lcd_on={})",
+            BOOT_CYCLE_BUDGET / 1_000_000,
+            emu.pc(),
+            emu.is_lcd_on()
+        );
+        println!("homescreen after {:.1}M cycles", total as f64 / 1_000_000.0);
+        log.finish();
+    }
+
+    #[test]
+    fn test_boot_test_rom_frames() {
+        let mut emu = Emu::new();
+        emu.load_rom(&test_rom::build()).unwrap();
+        emu.power_on();
+        let rom_hash = emu.rom_hash().unwrap();
+        assert!(
+            frames_path(rom_hash).exists() || std::env::var("BOOT_FRAMES_UPDATE").is_ok(),
+            "test ROM changed: record its frames with BOOT_FRAMES_UPDATE=1"
+        );
+        let mut log = FrameLog::new(rom_hash);
+        let mut total = 0u64;
+
+        for _ in 0..TEST_ROM_CHECKPOINTS {
+            total += emu.run_cycles(TEST_ROM_CHECKPOINT_CYCLES) as u64;
+            log.checkpoint(&mut emu, total);
+        }
+        assert_eq!(emu.peek_byte(READY), READY_MAGIC);
+        assert!(emu.is_lcd_on());
+        log.finish();
+    }
+
+    #[test]
+    fn test_parse_frames() {
+        let frames = parse_frames("# header\n10000000 00000000deadbeef\n\n20000000 ffffffffffffffff\n");
+        assert_eq!(frames, vec![(10_000_000, 0xdeadbeef), (20_000_000, u64::MAX)]);
+    }
+}
@@ -0,0 +1,128 @@
+//! Execution breakpoints
+//!
+//! Any number of PC breakpoints, each enabled or disabled without losing
+//! its place. `run_cycles` checks them before every instruction and stops
+//! with `StopReason::Breakpoint` (plus a `BreakpointHit` event) when the
+//! CPU is about to execute one. The next run resumes by executing that
+//! instruction rather than stopping on it again.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct Breakpoints {
+    /// Address -> enabled
+    entries: HashMap<u32, bool>,
+    /// Enabled entries, so the per-instruction check is one compare when
+    /// there are none
+    enabled: usize,
+    /// PC of the last hit, passed over once when execution resumes there
+    resume_at: Option<u32>,
+}
+
+impl Breakpoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an enabled breakpoint (re-enables an existing one)
+    pub fn add(&mut self, addr: u32) {
+        self.set_enabled_entry(addr & 0xFFFFFF, true);
+    }
+
+    /// Remove a breakpoint. Returns false if there was none at `addr`.
+    pub fn remove(&mut self, addr: u32) -> bool {
+        match self.entries.remove(&(addr & 0xFFFFFF)) {
+            Some(enabled) => {
+                self.enabled -= enabled as usize;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Enable or disable a breakpoint. Returns false if there was none at
+    /// `addr`.
+    pub fn set_enabled(&mut self, addr: u32, enabled: bool) -> bool {
+        let addr = addr & 0xFFFFFF;
+        if !self.entries.contains_key(&addr) {
+            return false;
+        }
+        self.set_enabled_entry(addr, enabled);
+        true
+    }
+
+    fn set_enabled_entry(&mut self, addr: u32, enabled: bool) {
+        if let Some(was) = self.entries.insert(addr, enabled) {
+            self.enabled -= was as usize;
+        }
+        self.enabled += enabled as usize;
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.enabled = 0;
+        self.resume_at = None;
+    }
+
+    /// Whether any breakpoint is enabled
+    #[inline]
+    pub fn armed(&self) -> bool {
+        self.enabled > 0
+    }
+
+    /// Whether execution should stop before the instruction at `pc`.
+    /// A hit is passed over once so the caller can resume from it.
+    pub fn check(&mut self, pc: u32) -> bool {
+        if self.resume_at.take() == Some(pc) {
+            return false;
+        }
+        if self.entries.get(&pc) == Some(&true) {
+            self.resume_at = Some(pc);
+            return true;
+        }
+        false
+    }
+
+    /// All breakpoints as (address, enabled), by address
+    pub fn list(&self) -> Vec<(u32, bool)> {
+        let mut list: Vec<_> = self.entries.iter().map(|(&addr, &enabled)| (addr, enabled)).collect();
+        list.sort_unstable();
+        list
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enable_remove_and_resume() {
+        let mut bps = Breakpoints::new();
+        assert!(!bps.armed());
+        bps.add(0x21A4C);
+        bps.add(0x1000010);
+        assert_eq!(bps.list(), [(0x000010, true), (0x021A4C, true)]);
+
+        assert!(bps.set_enabled(0x10, false));
+        assert!(!bps.check(0x10));
+        assert!(!bps.set_enabled(0x11, false));
+
+        // Stops once, then resumes past the same PC
+        assert!(bps.check(0x21A4C));
+        assert!(!bps.check(0x21A4C));
+        assert!(bps.check(0x21A4C));
+
+        assert!(bps.remove(0x21A4C));
+        assert!(!bps.remove(0x21A4C));
+        assert!(!bps.armed());
+        assert_eq!(bps.len(), 1);
+    }
+}
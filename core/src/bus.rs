@@ -15,7 +15,12 @@
 //! Reference: CEmu (https://github.com/CE-Programming/CEmu)
 
 use crate::memory::{addr, Flash, FlashError, Ports, Ram};
-use crate::peripherals::SpiController;
+use crate::mmio_diff::MmioDiff;
+use crate::benchmark::WriteMark;
+use crate::snoop::{EmuSnoopAccess, Snoop};
+use crate::port_watch::{EmuPortAccess, PortWatches};
+use crate::watchpoint::Watchpoints;
+use crate::peripherals::{Extensions, PortContext, SpiController, LCD_HEIGHT, LCD_WIDTH};
 use std::collections::BTreeMap;
 
 /// Bus access type for debugging/tracing
@@ -118,6 +123,7 @@ pub struct WriteRecord {
 
 /// I/O operation target type
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IoTarget {
     /// RAM write (0xD00000-0xD657FF)
     Ram,
@@ -131,6 +137,7 @@ pub enum IoTarget {
 
 /// I/O operation type
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IoOpType {
     Read,
     Write,
@@ -138,6 +145,7 @@ pub enum IoOpType {
 
 /// Comprehensive I/O operation record with instruction context
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IoRecord {
     /// Type of operation
     pub op_type: IoOpType,
@@ -159,6 +167,28 @@ pub struct IoRecord {
     pub opcode_len: u8,
 }
 
+/// Bus access counters (C layout, see emu.h)
+///
+/// Counted on every CPU-driven access; debugger peeks/pokes and LCD DMA are
+/// not. Wait cycles are the memory and port timing the accesses added on top
+/// of the CPU's internal cycles. Not part of save states.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BusCounters {
+    /// Instruction-stream byte fetches
+    pub fetches: u64,
+    /// Data byte reads (memory and MMIO)
+    pub reads: u64,
+    /// Data byte writes (memory and MMIO)
+    pub writes: u64,
+    /// IN instructions (port reads)
+    pub port_reads: u64,
+    /// OUT instructions (port writes)
+    pub port_writes: u64,
+    /// Cycles spent in wait states across all counted accesses
+    pub wait_cycles: u64,
+}
+
 /// Write tracer for debugging RAM writes during boot
 ///
 /// This is designed for investigating boot behavior to determine
@@ -455,6 +485,8 @@ pub struct Bus {
     pub ports: Ports,
     /// SPI controller (port range 0xD)
     spi: SpiController,
+    /// Frontend devices attached to unmapped MMIO ranges
+    extensions: Extensions,
     /// RNG for unmapped region reads
     rng: BusRng,
     /// Internal CPU cycle counter (matches CEmu's cpu.cycles)
@@ -467,11 +499,34 @@ pub struct Bus {
     fetch_buffer: [u8; FETCH_BUFFER_SIZE],
     /// Current index in fetch buffer (points to most recent byte + 1)
     fetch_index: usize,
+    /// Addresses of every instruction-stream fetch, when enabled (testing aid)
+    fetch_trace: Option<Vec<u32>>,
     /// Write tracer for debugging RAM writes
     pub write_tracer: WriteTracer,
+    /// Access counters since the last reset or take_counters
+    counters: BusCounters,
+    /// Running count of mapped flash reads, fetches and writes (power model)
+    flash_accesses: u64,
+    /// Peripheral register writes of the current frame, when enabled
+    mmio_diff: Option<Box<MmioDiff>>,
+    /// Port addresses whose stores are noted for benchmark hooks
+    write_hooks: Option<[u32; 2]>,
+    /// Stores to `write_hooks` not yet taken by the emulator
+    write_marks: Vec<WriteMark>,
+    /// Address range whose CPU data accesses go to a frontend callback
+    snoop: Option<Snoop>,
+    /// Data watchpoints, checked on the same accesses as the snoop
+    pub watchpoints: Watchpoints,
+    /// Port access filters, checked on every recorded MMIO and IN/OUT access
+    pub port_watches: PortWatches,
     /// Serial flash mode (newer TI-84 CE models)
     /// When true, uses flash cache timing; when false, uses parallel flash timing
     serial_flash: bool,
+    /// A port/MMIO access happened since take_ports_touched
+    ports_touched: bool,
+    /// RAM in the 16bpp frame at UPBASE was written since
+    /// take_lcd_frame_written (drives the frame callback's dirty flag)
+    lcd_frame_written: bool,
     /// Flash cache for serial flash timing simulation
     flash_cache: FlashCache,
 
@@ -554,12 +609,24 @@ impl Bus {
             ram: Ram::new(),
             ports: Ports::new(),
             spi: SpiController::new(),
+            extensions: Extensions::default(),
             rng: BusRng::new(),
             cycles: 0,
             mem_cycles: 0,
             fetch_buffer: [0; FETCH_BUFFER_SIZE],
             fetch_index: 0,
+            fetch_trace: None,
             write_tracer: WriteTracer::new(),
+            counters: BusCounters::default(),
+            flash_accesses: 0,
+            mmio_diff: None,
+            write_hooks: None,
+            write_marks: Vec::new(),
+            snoop: None,
+            watchpoints: Watchpoints::new(),
+            port_watches: PortWatches::new(),
+            ports_touched: false,
+            lcd_frame_written: true,
             serial_flash: false,  // Default to parallel flash (10 cycles, more compatible)
             flash_cache: FlashCache::new(),
             // I/O tracing fields
@@ -628,6 +695,15 @@ impl Bus {
         &mut self.spi
     }
 
+    /// Extension devices attached to unmapped MMIO ranges
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
     // === Debug port accessors ===
 
     /// Enable or disable debug port interception
@@ -659,6 +735,17 @@ impl Bus {
         self.debug_terminated = false;
     }
 
+    /// Whether a flash-window address is decoded by the flash chip.
+    ///
+    /// In parallel mode the flash controller only maps the first
+    /// `0x10000 << map_select` bytes (none at all if it is disabled), so
+    /// boot code that shrinks the map sees unmapped memory above it.
+    /// Serial flash is always fully mapped.
+    #[inline]
+    fn flash_mapped(&self, addr: u32) -> bool {
+        self.serial_flash || addr < self.ports.flash.cached_mapped_bytes()
+    }
+
     /// Determine which memory region an address maps to
     pub fn decode_address(addr: u32) -> MemoryRegion {
         let addr = addr & addr::ADDR_MASK;
@@ -694,9 +781,17 @@ impl Bus {
     /// The byte at the given address
     pub fn read_byte(&mut self, addr: u32) -> u8 {
         let addr = addr & addr::ADDR_MASK;
+        let wait_start = self.total_cycles();
+        self.counters.reads += 1;
 
         let (value, target) = match Self::decode_address(addr) {
+            MemoryRegion::Flash if !self.flash_mapped(addr) => {
+                // CEmu: parallel flash past flash.mappedBytes reads as unmapped
+                self.mem_cycles += Self::UNMAPPED_PARALLEL_CYCLES;
+                (self.rng.next(), None)
+            }
             MemoryRegion::Flash => {
+                self.flash_accesses += 1;
                 // Serial flash uses cache timing, parallel flash uses dynamic wait states
                 if self.serial_flash {
                     self.cycles += self.flash_cache.touch(addr);
@@ -723,6 +818,7 @@ impl Bus {
                 };
 
                 if is_mapped {
+                    self.touch_ports();
                     let port_offset = addr - addr::PORT_START;
                     let port_range = (port_offset >> 12) & 0xF;
                     self.mem_cycles += Self::PORT_READ_CYCLES[port_range as usize];
@@ -741,7 +837,16 @@ impl Bus {
                     } else {
                         self.mem_cycles += Self::UNMAPPED_MMIO_OTHER_CYCLES; // 2
                     }
-                    (self.rng.next(), None)
+                    let ctx = PortContext {
+                        key_state: self.ports.key_state(),
+                        cycles: self.cycles,
+                        cpu_speed: self.ports.control.cpu_speed(),
+                        delay_remaining: 0,
+                    };
+                    match self.extensions.read(addr, &ctx) {
+                        Some(value) => (value, Some(IoTarget::MmioPort)),
+                        None => (self.rng.next(), None),
+                    }
                 }
             }
             MemoryRegion::Unmapped => {
@@ -759,10 +864,131 @@ impl Bus {
         if let Some(target) = target {
             self.record_io_op(IoOpType::Read, target, addr, value, value);
         }
+        self.snoop_access(addr, value, false);
 
+        self.count_wait(wait_start);
         value
     }
 
+    /// Bring the peripherals up to date before a port/MMIO access and note
+    /// the access, so run_cycles services them right after the instruction
+    #[inline]
+    fn touch_ports(&mut self) {
+        self.ports.interrupt.set_clock(self.total_cycles());
+        self.ports.catch_up();
+        self.ports_touched = true;
+    }
+
+    /// Note a RAM write that lands in the frame the LCD scans out
+    #[inline]
+    fn note_frame_write(&mut self, addr: u32) {
+        const FRAME_BYTES: u32 = (LCD_WIDTH * LCD_HEIGHT * 2) as u32;
+        if addr.wrapping_sub(self.ports.lcd.upbase()) < FRAME_BYTES {
+            self.lcd_frame_written = true;
+        }
+    }
+
+    /// Whether RAM in the LCD's frame changed since the last call (also
+    /// true after a reset or state load)
+    #[inline]
+    pub fn take_lcd_frame_written(&mut self) -> bool {
+        std::mem::take(&mut self.lcd_frame_written)
+    }
+
+    /// Whether a port/MMIO access happened since the last call
+    #[inline]
+    pub fn take_ports_touched(&mut self) -> bool {
+        std::mem::take(&mut self.ports_touched)
+    }
+
+    /// Add the wait cycles of an access that started at `start` (total cycles)
+    #[inline]
+    fn count_wait(&mut self, start: u64) {
+        self.counters.wait_cycles += self.total_cycles().saturating_sub(start);
+    }
+
+    /// Mapped flash accesses since the bus was created (never reset)
+    pub fn flash_accesses(&self) -> u64 {
+        self.flash_accesses
+    }
+
+    /// Access counters accumulated since the last reset or take_counters
+    pub fn counters(&self) -> BusCounters {
+        self.counters
+    }
+
+    /// Return the access counters and start counting from zero
+    pub fn take_counters(&mut self) -> BusCounters {
+        std::mem::take(&mut self.counters)
+    }
+
+    /// Start (with an empty frame) or stop collecting peripheral register writes
+    pub fn set_mmio_diff_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            self.mmio_diff = None;
+        } else if self.mmio_diff.is_none() {
+            self.mmio_diff = Some(Box::new(MmioDiff::new()));
+        }
+    }
+
+    /// Note CPU stores to these port addresses (benchmark write hooks),
+    /// or stop with None. Drops marks not yet taken.
+    pub fn set_write_hooks(&mut self, hooks: Option<[u32; 2]>) {
+        self.write_hooks = hooks;
+        self.write_marks.clear();
+    }
+
+    /// Oldest store to a write hook address not yet taken
+    #[inline]
+    pub fn take_write_mark(&mut self) -> Option<WriteMark> {
+        if self.write_marks.is_empty() {
+            None
+        } else {
+            Some(self.write_marks.remove(0))
+        }
+    }
+
+    /// Send CPU data accesses in a range to a callback, or stop with None
+    pub(crate) fn set_snoop(&mut self, snoop: Option<Snoop>) {
+        self.snoop = snoop;
+    }
+
+    /// Remove the snoop, returning it for a later `set_snoop`
+    pub(crate) fn take_snoop(&mut self) -> Option<Snoop> {
+        self.snoop.take()
+    }
+
+    /// Hand a CPU data access to the snoop callback and the watchpoints
+    #[inline]
+    fn snoop_access(&mut self, addr: u32, value: u8, write: bool) {
+        if self.snoop.is_none() && self.watchpoints.is_empty() {
+            return;
+        }
+        let access = EmuSnoopAccess {
+            cycles: self.total_cycles(),
+            addr,
+            pc: self.cpu_pc,
+            value,
+            write: write as u8,
+            reserved: [0; 2],
+        };
+        if let Some(snoop) = self.snoop.as_ref().filter(|snoop| snoop.contains(addr)) {
+            snoop.deliver(&access);
+        }
+        if !self.watchpoints.is_empty() {
+            self.watchpoints.check(access);
+        }
+    }
+
+    /// Register write collector, when enabled
+    pub fn mmio_diff(&self) -> Option<&MmioDiff> {
+        self.mmio_diff.as_deref()
+    }
+
+    pub fn mmio_diff_mut(&mut self) -> Option<&mut MmioDiff> {
+        self.mmio_diff.as_deref_mut()
+    }
+
     /// Fetch a byte for instruction execution
     /// This records the byte in the fetch buffer for flash unlock sequence detection
     ///
@@ -774,10 +1000,17 @@ impl Bus {
     /// The byte at the given address
     pub fn fetch_byte(&mut self, addr: u32, pc: u32) -> u8 {
         let addr = addr & addr::ADDR_MASK;
+        let wait_start = self.total_cycles();
+        self.counters.fetches += 1;
         let is_flash = matches!(Self::decode_address(addr), MemoryRegion::Flash);
 
         let value = match Self::decode_address(addr) {
+            MemoryRegion::Flash if !self.flash_mapped(addr) => {
+                self.mem_cycles += Self::UNMAPPED_PARALLEL_CYCLES;
+                self.rng.next()
+            }
             MemoryRegion::Flash => {
+                self.flash_accesses += 1;
                 // Serial flash uses cache timing, parallel flash uses dynamic wait states
                 if self.serial_flash {
                     self.cycles += self.flash_cache.touch(addr);
@@ -786,7 +1019,7 @@ impl Bus {
                     // This is dynamically set by ROM via port 0xE10005 writes
                     self.mem_cycles += self.ports.flash.cached_total_wait_cycles() as u64;
                 }
-                self.flash.read(addr)
+                self.flash.fetch(addr)
             }
             MemoryRegion::Ram | MemoryRegion::Vram => {
                 self.mem_cycles += Self::RAM_READ_CYCLES;
@@ -797,6 +1030,7 @@ impl Bus {
                 let port_offset = addr - addr::PORT_START;
                 let port_range = (port_offset >> 12) & 0xF;
                 self.mem_cycles += Self::PORT_READ_CYCLES[port_range as usize];
+                self.touch_ports();
                 let keys = *self.ports.key_state();
                 self.ports.read(port_offset, &keys, self.cycles)
             }
@@ -810,6 +1044,7 @@ impl Bus {
                 self.rng.next()
             }
         };
+        self.count_wait(wait_start);
 
         // CEmu: When fetching from flash, check for unlock sequence BEFORE updating buffer
         // Only privileged code can trigger the unlock (is_unprivileged returns false)
@@ -819,6 +1054,10 @@ impl Bus {
             self.ports.control.set_flash_ready();
         }
 
+        if let Some(trace) = self.fetch_trace.as_mut() {
+            trace.push(addr);
+        }
+
         // Record in fetch buffer AFTER checking (like CEmu's check then mem.buffer[++mem.fetch] = value)
         self.fetch_buffer[self.fetch_index] = value;
         self.fetch_index = (self.fetch_index + 1) % FETCH_BUFFER_SIZE;
@@ -832,6 +1071,21 @@ impl Bus {
         value
     }
 
+    /// Start recording the address of every `fetch_byte` call.
+    /// Used to compare the prefetch stream against CEmu byte-for-byte.
+    pub fn enable_fetch_trace(&mut self) {
+        self.fetch_trace = Some(Vec::new());
+    }
+
+    /// Take the recorded fetch addresses, leaving an empty trace running.
+    /// Returns an empty Vec if tracing was never enabled.
+    pub fn take_fetch_trace(&mut self) -> Vec<u32> {
+        match self.fetch_trace.as_mut() {
+            Some(trace) => std::mem::take(trace),
+            None => Vec::new(),
+        }
+    }
+
     /// Check if the fetch buffer contains the flash unlock sequence
     /// CEmu: Only triggers for privileged code (unprivileged_code() returns false)
     fn detect_flash_unlock_sequence(&self, current: u8, pc: u32) -> bool {
@@ -873,6 +1127,8 @@ impl Bus {
     /// * `value` - Byte to write
     pub fn write_byte(&mut self, addr: u32, value: u8) {
         let addr = addr & addr::ADDR_MASK;
+        let wait_start = self.total_cycles();
+        self.counters.writes += 1;
 
         // CEmu memory protection: check stack limit (always, write still succeeds)
         let stack_limit = self.ports.control.stack_limit();
@@ -894,6 +1150,7 @@ impl Bus {
             self.nmi_violation_pc = raw_pc;
             return; // Block the write
         }
+        self.snoop_access(addr, value, true);
 
         match Self::decode_address(addr) {
             MemoryRegion::Flash => {
@@ -904,10 +1161,15 @@ impl Bus {
                     return; // Block the write
                 }
                 // CEmu mem_write_flash: serial uses cache touch, parallel uses waitStates
-                if self.serial_flash {
+                if !self.flash_mapped(addr) {
+                    // Past the mapped size nothing decodes the write
+                    self.mem_cycles += Self::UNMAPPED_PARALLEL_CYCLES;
+                } else if self.serial_flash {
+                    self.flash_accesses += 1;
                     self.cycles += self.flash_cache.touch(addr);
                     // Serial flash writes are ignored (no actual write occurs)
                 } else {
+                    self.flash_accesses += 1;
                     // CEmu: cpu.cycles += flash.waitStates for parallel flash writes
                     // Use flash controller's configured wait states
                     self.mem_cycles += self.ports.flash.cached_total_wait_cycles() as u64;
@@ -928,10 +1190,18 @@ impl Bus {
                     self.write_tracer.record(addr, value, self.cycles);
                 }
                 self.ram.write(addr - addr::RAM_START, value);
+                self.note_frame_write(addr);
                 // Record for comprehensive I/O tracing
                 self.record_io_op(IoOpType::Write, IoTarget::Ram, addr, old_value, value);
             }
             MemoryRegion::Ports => {
+                if self.write_hooks.is_some_and(|hooks| hooks.contains(&addr)) {
+                    self.write_marks.push(WriteMark {
+                        addr,
+                        cycles: self.total_cycles(),
+                        cpu_speed: self.ports.control.cpu_speed(),
+                    });
+                }
                 // CEmu mmio_mapped check for write path
                 let is_mapped = if addr < 0xF00000 {
                     addr < 0xE40000
@@ -940,11 +1210,22 @@ impl Bus {
                 };
 
                 if !is_mapped {
+                    // Extension devices take priority over the debug ports
+                    let ctx = PortContext {
+                        key_state: self.ports.key_state(),
+                        cycles: self.cycles,
+                        cpu_speed: self.ports.control.cpu_speed(),
+                        delay_remaining: 0,
+                    };
+                    let claimed = self.extensions.write(addr, value, &ctx);
+
                     // Debug port interception (CE toolchain conventions)
                     // dbg_printf uses sprintf(dbgout, ...) which writes to sequential
                     // addresses starting at 0xFB0000. Each byte write in the range is
                     // one output character. Null at 0xFB0000 exactly = termination sentinel.
-                    if self.debug_ports_enabled {
+                    if claimed {
+                        self.record_io_op(IoOpType::Write, IoTarget::MmioPort, addr, 0, value);
+                    } else if self.debug_ports_enabled {
                         if addr >= 0xFB0000 && addr < 0xFC0000 {
                             // stdout range
                             if value == 0x00 && addr == 0xFB0000 {
@@ -1012,6 +1293,7 @@ impl Bus {
 
                     // Add delay before port write (like CEmu)
                     self.mem_cycles += PORT_WRITE_DELAY;
+                    self.touch_ports();
 
                     // SPI lives on bus.spi (not bus.ports), intercept its MMIO range
                     let old_value;
@@ -1030,6 +1312,9 @@ impl Bus {
                     }
                     // Record for comprehensive I/O tracing
                     self.record_io_op(IoOpType::Write, IoTarget::MmioPort, addr, old_value, value);
+                    if let Some(diff) = self.mmio_diff.as_mut() {
+                        diff.record(addr, false, old_value, value, self.cpu_pc);
+                    }
 
                     // Speed conversion is now handled by run_cycles() after cpu.step()
                     // to prevent mid-instruction bus.cycles rescaling that breaks cycle_delta.
@@ -1051,6 +1336,8 @@ impl Bus {
                 }
             }
         }
+        // Blocked writes return earlier, before any wait states are added
+        self.count_wait(wait_start);
     }
 
     /// Read a 16-bit word (little-endian)
@@ -1062,11 +1349,14 @@ impl Bus {
 
     /// Write a 16-bit word (little-endian)
     pub fn write_word(&mut self, addr: u32, value: u16) {
+        self.ports.begin_wide_write();
         self.write_byte(addr, value as u8);
         self.write_byte(addr.wrapping_add(1), (value >> 8) as u8);
+        self.ports.end_wide_write();
     }
 
     /// Read a 24-bit address (little-endian, for eZ80 ADL mode)
+    /// Wraps at 16MB (0xFFFFFF + 1 -> 0x000000) via read_byte's address mask.
     pub fn read_addr24(&mut self, addr: u32) -> u32 {
         let b0 = self.read_byte(addr) as u32;
         let b1 = self.read_byte(addr.wrapping_add(1)) as u32;
@@ -1075,10 +1365,13 @@ impl Bus {
     }
 
     /// Write a 24-bit address (little-endian)
+    /// Wraps at 16MB (0xFFFFFF + 1 -> 0x000000) via write_byte's address mask.
     pub fn write_addr24(&mut self, addr: u32, value: u32) {
+        self.ports.begin_wide_write();
         self.write_byte(addr, value as u8);
         self.write_byte(addr.wrapping_add(1), (value >> 8) as u8);
         self.write_byte(addr.wrapping_add(2), (value >> 16) as u8);
+        self.ports.end_wide_write();
     }
 
     /// Read a 32-bit value (little-endian)
@@ -1092,14 +1385,49 @@ impl Bus {
 
     /// Write a 32-bit value (little-endian)
     pub fn write_dword(&mut self, addr: u32, value: u32) {
+        self.ports.begin_wide_write();
         self.write_byte(addr, value as u8);
         self.write_byte(addr.wrapping_add(1), (value >> 8) as u8);
         self.write_byte(addr.wrapping_add(2), (value >> 16) as u8);
         self.write_byte(addr.wrapping_add(3), (value >> 24) as u8);
+        self.ports.end_wide_write();
     }
 
-    /// Peek at a byte without affecting cycles (for debugging)
-    pub fn peek_byte(&mut self, addr: u32) -> u8 {
+    /// Address of the byte `offset` bytes past `addr` for a CPU multi-byte access.
+    ///
+    /// eZ80 operands wrap within the active address space (CEmu: cpu_mask_mode):
+    /// - ADL (`l == true`): the full 24-bit address advances, wrapping at 16MB.
+    /// - Z80 (`l == false`): only the low 16 bits advance, so the access stays in
+    ///   the 64KB page selected by MBASE (0xD0FFFF + 1 -> 0xD00000, not 0xD10000).
+    #[inline]
+    pub fn wrap_offset(addr: u32, offset: u32, l: bool) -> u32 {
+        if l {
+            addr.wrapping_add(offset) & addr::ADDR_MASK
+        } else {
+            (addr & 0xFF0000) | (addr.wrapping_add(offset) & 0xFFFF)
+        }
+    }
+
+    /// Read a 16-bit word for a Z80-mode data access (wraps within the 64KB page)
+    pub fn read_word_z80(&mut self, addr: u32) -> u16 {
+        let lo = self.read_byte(addr) as u16;
+        let hi = self.read_byte(Self::wrap_offset(addr, 1, false)) as u16;
+        lo | (hi << 8)
+    }
+
+    /// Write a 16-bit word for a Z80-mode data access (wraps within the 64KB page)
+    pub fn write_word_z80(&mut self, addr: u32, value: u16) {
+        self.ports.begin_wide_write();
+        self.write_byte(addr, value as u8);
+        self.write_byte(Self::wrap_offset(addr, 1, false), (value >> 8) as u8);
+        self.ports.end_wide_write();
+    }
+
+    /// Peek at a byte without affecting cycles or any other state (for
+    /// debuggers, disassembly and memory views). Peripheral registers read
+    /// through their side-effect-free paths: no keypad scans, SPI transfer
+    /// progress or FIFO drains, no I/O tracing or access counting.
+    pub fn peek_byte(&self, addr: u32) -> u8 {
         let addr = addr & addr::ADDR_MASK;
 
         match Self::decode_address(addr) {
@@ -1107,32 +1435,40 @@ impl Bus {
             MemoryRegion::Ram | MemoryRegion::Vram => {
                 self.ram.read(addr - addr::RAM_START)
             }
-            MemoryRegion::Ports => {
-                let keys = *self.ports.key_state();
-                // Use 0 for cycles in debug peek (no timing effects)
-                self.ports.read(addr - addr::PORT_START, &keys, 0)
-            }
+            MemoryRegion::Ports => self.peek_port(addr),
             MemoryRegion::Unmapped => 0x00,
         }
     }
 
     /// Peek a byte as it would be fetched by the CPU (includes flash command status)
-    pub fn peek_byte_fetch(&mut self, addr: u32) -> u8 {
+    pub fn peek_byte_fetch(&self, addr: u32) -> u8 {
         let addr = addr & addr::ADDR_MASK;
         match Self::decode_address(addr) {
             MemoryRegion::Flash => self.flash.peek_status(addr),
             MemoryRegion::Ram | MemoryRegion::Vram => {
                 self.ram.read(addr - addr::RAM_START)
             }
-            MemoryRegion::Ports => {
-                let keys = *self.ports.key_state();
-                // Use 0 for cycles in debug peek (no timing effects)
-                self.ports.read(addr - addr::PORT_START, &keys, 0)
-            }
+            MemoryRegion::Ports => self.peek_port(addr),
             MemoryRegion::Unmapped => 0x00,
         }
     }
 
+    /// Side-effect-free read of a memory-mapped port address
+    fn peek_port(&self, addr: u32) -> u8 {
+        let port_offset = addr - addr::PORT_START;
+        let is_mapped = if addr < 0xF00000 {
+            addr < 0xE40000
+        } else {
+            !(0xFB0000..0xFF0000).contains(&addr)
+        };
+        // SPI lives on bus.spi (not bus.ports), as in read_byte
+        if is_mapped && (port_offset >> 12) & 0xF == 0xD {
+            self.spi.peek(port_offset & 0x7F)
+        } else {
+            self.ports.peek(port_offset, self.ports.key_state())
+        }
+    }
+
     /// Poke a byte without affecting cycles (for debugging)
     pub fn poke_byte(&mut self, addr: u32, value: u8) {
         let addr = addr & addr::ADDR_MASK;
@@ -1143,6 +1479,7 @@ impl Bus {
             }
             MemoryRegion::Ram | MemoryRegion::Vram => {
                 self.ram.write(addr - addr::RAM_START, value);
+                self.note_frame_write(addr);
             }
             MemoryRegion::Ports => {
                 // Use 0 for cycles in debug poke (no timing effects)
@@ -1223,7 +1560,10 @@ impl Bus {
     /// Based on CEmu's port.c port_map array
     pub fn port_read(&mut self, port: u16) -> u8 {
         let range = (port >> 12) & 0xF;
+        self.counters.port_reads += 1;
+        self.counters.wait_cycles += Self::PORT_READ_CYCLES[range as usize];
         self.mem_cycles += Self::PORT_READ_CYCLES[range as usize];
+        self.touch_ports();
         let keys = *self.ports.key_state();
 
         let value = match range {
@@ -1303,9 +1643,12 @@ impl Bus {
     /// during the write, as the conversion happens with the +4 already added.
     pub fn port_write(&mut self, port: u16, value: u8) {
         let range = (port >> 12) & 0xF;
+        let wait_start = self.total_cycles();
+        self.counters.port_writes += 1;
 
         // CEmu: cpu.cycles += PORT_WRITE_DELAY (4) BEFORE the write
         self.mem_cycles += Self::PORT_WRITE_DELAY;
+        self.touch_ports();
 
         // Get old value for tracing (read before write)
         let old_value = self.port_read_for_trace(port);
@@ -1387,14 +1730,9 @@ impl Bus {
                     self.ports.keypad.needs_any_key_check = false;
 
                     let key_state = *self.ports.key_state();
-                    let should_interrupt = self.ports.keypad.any_key_check(&key_state);
-
-                    if should_interrupt {
-                        self.ports.interrupt.raise(crate::peripherals::interrupt::sources::KEYPAD);
-                    } else {
-                        self.ports.interrupt.clear_raw(crate::peripherals::interrupt::sources::KEYPAD);
-                    }
+                    self.ports.keypad.any_key_check(&key_state);
                 }
+                self.ports.sync_keypad_interrupt();
             }
             0xB => {
                 // Backlight - mask with 0xFF
@@ -1418,10 +1756,14 @@ impl Bus {
         // Rewind excess port write delay cycles
         let rewind = Self::PORT_WRITE_DELAY.saturating_sub(Self::PORT_WRITE_CYCLES[range as usize]);
         self.mem_cycles = self.mem_cycles.saturating_sub(rewind);
+        self.count_wait(wait_start);
 
         // Record for comprehensive I/O tracing (CPU port write)
         let addr = 0xFF0000 | (port as u32);
         self.record_io_op(IoOpType::Write, IoTarget::CpuPort, addr, old_value, value);
+        if let Some(diff) = self.mmio_diff.as_mut() {
+            diff.record(port as u32, true, old_value, value, self.cpu_pc);
+        }
     }
 
     /// Read a port value for tracing purposes (without affecting timing)
@@ -1482,19 +1824,37 @@ impl Bus {
     /// Reset bus and all memory to initial state
     pub fn reset(&mut self) {
         self.ram.reset();
+        self.lcd_frame_written = true;
+        self.reset_keep_ram();
+    }
+
+    /// Reset the bus and peripherals but keep RAM contents (resets that
+    /// don't remove power from the RAM, see `ResetCause::preserves_ram`)
+    pub fn reset_keep_ram(&mut self) {
         self.ports.reset();
+        self.ports_touched = false;
         self.spi.reset();
+        self.extensions.reset();
         self.cycles = 0;
         self.mem_cycles = 0;
         self.rng = BusRng::new();
         self.fetch_buffer = [0; FETCH_BUFFER_SIZE];
         self.fetch_index = 0;
         self.write_tracer.reset();
+        self.counters = BusCounters::default();
         // Reset I/O tracing state but preserve enabled flag
         self.current_pc = 0;
         self.current_opcode = [0; 4];
         self.current_opcode_len = 0;
         self.instruction_io_ops.clear();
+        // Register values no longer follow from the writes seen so far
+        if let Some(diff) = self.mmio_diff.as_mut() {
+            diff.discard_pending();
+        }
+        // Marks carry cycle counts from before the reset
+        self.write_marks.clear();
+        self.watchpoints.take_hit();
+        self.port_watches.take_hit();
         // Note: Flash is NOT reset - ROM data is preserved
         // Note: Write tracer enabled state is preserved across reset
         // Note: full_trace_enabled is preserved across reset
@@ -1569,6 +1929,19 @@ impl Bus {
 
     /// Record an I/O operation (internal helper)
     fn record_io_op(&mut self, op_type: IoOpType, target: IoTarget, addr: u32, old_value: u8, new_value: u8) {
+        if !self.port_watches.is_empty() && matches!(target, IoTarget::MmioPort | IoTarget::CpuPort) {
+            let io = target == IoTarget::CpuPort;
+            self.port_watches.check(EmuPortAccess {
+                cycles: self.total_cycles(),
+                // CPU ports are recorded as 0xFF0000 | port
+                addr: if io { addr & 0xFFFF } else { addr },
+                pc: self.cpu_pc,
+                old_value,
+                new_value,
+                write: (op_type == IoOpType::Write) as u8,
+                io: io as u8,
+            });
+        }
         if self.full_trace_enabled && self.instruction_io_ops.len() < Self::MAX_IO_OPS_PER_INSTRUCTION {
             self.instruction_io_ops.push(IoRecord {
                 op_type,
@@ -1781,6 +2154,32 @@ mod tests {
         assert_eq!(bus.mem_cycles(), Bus::UNMAPPED_PARALLEL_CYCLES);
     }
 
+    #[test]
+    fn test_access_counters() {
+        let mut bus = Bus::new();
+        bus.fetch_byte(0x000000, 0);
+        bus.read_byte(0xD00000);
+        bus.write_byte(0xD00000, 0x12);
+        bus.port_read(0x5000);
+        bus.port_write(0x5004, 0x00);
+        bus.peek_byte(0xD00000);
+        bus.poke_byte(0xD00000, 0x34);
+
+        let counters = bus.counters();
+        assert_eq!(counters.fetches, 1);
+        assert_eq!(counters.reads, 1);
+        assert_eq!(counters.writes, 1);
+        assert_eq!(counters.port_reads, 1);
+        assert_eq!(counters.port_writes, 1);
+        assert_eq!(counters.wait_cycles, bus.total_cycles());
+
+        assert_eq!(bus.take_counters(), counters);
+        assert_eq!(bus.counters(), BusCounters::default());
+        bus.read_byte(0x000000);
+        bus.reset();
+        assert_eq!(bus.counters(), BusCounters::default());
+    }
+
     #[test]
     fn test_peek_poke_no_cycles() {
         let mut bus = Bus::new();
@@ -1794,6 +2193,21 @@ mod tests {
         assert_eq!(bus.peek_byte(0xD00000), 0x42);
     }
 
+    #[test]
+    fn test_peek_spi_registers_without_side_effects() {
+        let mut bus = Bus::new();
+        // Queue a byte in the SPI TX FIFO
+        bus.write_byte(0xE0D018, 0x00);
+        let counters = bus.counters();
+
+        // STATUS decodes from the SPI controller, not fallback storage
+        assert_eq!(bus.peek_byte(0xE0D00D), 0x10);
+        assert_eq!(bus.peek_byte_fetch(0xE0D00D), 0x10);
+        assert_eq!(bus.peek_byte(0xE0D018), 0);
+        assert_eq!(bus.peek_byte(0xE0D00D), 0x10);
+        assert_eq!(bus.counters(), counters);
+    }
+
     #[test]
     fn test_reset() {
         let mut bus = Bus::new();
@@ -1911,6 +2325,36 @@ mod tests {
         assert_eq!(bus.peek_byte(0x000000), 0x34);
     }
 
+    #[test]
+    fn test_flash_map_select_limits_mapped_region() {
+        let mut bus = Bus::new();
+        bus.load_rom(&[0x12, 0x34]).unwrap();
+        bus.poke_byte(0x010000, 0x56);
+
+        // Default map (6) covers the whole 4MB chip
+        assert_eq!(bus.read_byte(0x010000), 0x56);
+
+        // Map 0 leaves only the first 64KB decoded
+        bus.write_byte(0xE10002, 0x00);
+        bus.reset_cycles();
+        assert_eq!(bus.read_byte(0x000001), 0x34);
+        assert_eq!(bus.mem_cycles(), bus.ports.flash.cached_total_wait_cycles() as u64);
+        bus.reset_cycles();
+        bus.read_byte(0x010000);
+        assert_eq!(bus.mem_cycles(), Bus::UNMAPPED_PARALLEL_CYCLES);
+        assert_eq!(bus.peek_byte(0x010000), 0x56, "contents untouched");
+
+        // Disabling the controller unmaps everything
+        bus.write_byte(0xE10000, 0x00);
+        bus.reset_cycles();
+        bus.fetch_byte(0x000000, 0);
+        assert_eq!(bus.mem_cycles(), Bus::UNMAPPED_PARALLEL_CYCLES);
+
+        // Serial flash ignores the map
+        bus.set_serial_flash(true);
+        assert_eq!(bus.read_byte(0x010000), 0x56);
+    }
+
     #[test]
     fn test_multi_byte_across_boundary() {
         let mut bus = Bus::new();
@@ -1926,4 +2370,68 @@ mod tests {
         // Read should work too
         assert_eq!(bus.read_word(0xD3FFFF), 0xABCD);
     }
+
+    #[test]
+    fn test_wide_store_to_interrupt_controller() {
+        let mut bus = Bus::new();
+        let value = (1 << 1) | (1 << 10) | (1 << 19);
+        bus.write_addr24(0xF00004, value);
+        assert_eq!(bus.ports.interrupt.enabled(), value);
+
+        bus.write_word(0xF00004, 0x0402);
+        assert_eq!(bus.ports.interrupt.enabled(), (1 << 19) | 0x0402);
+
+        bus.write_dword(0xF0000C, 0x0008_0001);
+        assert_eq!(bus.read_dword(0xF0000C), 0x0008_0001);
+    }
+
+    #[test]
+    fn test_wrap_offset_matrix() {
+        // (addr, offset, l, expected)
+        let cases = [
+            // Z80 mode: low 16 bits wrap, page (MBASE) preserved
+            (0xD0FFFF, 1, false, 0xD00000),
+            (0xD0FFFE, 2, false, 0xD00000),
+            (0xD0FFFF, 2, false, 0xD00001),
+            (0xD0FFFE, 1, false, 0xD0FFFF),
+            (0x00FFFF, 1, false, 0x000000),
+            (0xFFFFFF, 1, false, 0xFF0000),
+            // ADL mode: full 24-bit carry, wrap at 16MB
+            (0xD0FFFF, 1, true, 0xD10000),
+            (0xD3FFFF, 1, true, 0xD40000),
+            (0xFFFFFF, 1, true, 0x000000),
+            (0xFFFFFE, 2, true, 0x000000),
+            (0xFFFFFF, 2, true, 0x000001),
+        ];
+        for &(addr, offset, l, expected) in &cases {
+            assert_eq!(
+                Bus::wrap_offset(addr, offset, l), expected,
+                "wrap_offset(0x{:06X}, {}, l={})", addr, offset, l
+            );
+        }
+    }
+
+    #[test]
+    fn test_z80_word_wraps_within_page() {
+        let mut bus = Bus::new();
+        bus.write_word_z80(0xD0FFFF, 0xBEEF);
+        assert_eq!(bus.peek_byte(0xD0FFFF), 0xEF);
+        assert_eq!(bus.peek_byte(0xD00000), 0xBE, "high byte wraps to page start");
+        assert_eq!(bus.peek_byte(0xD10000), 0x00, "next page untouched");
+        assert_eq!(bus.read_word_z80(0xD0FFFF), 0xBEEF);
+
+        // No wrap inside the page: identical to the flat accessor
+        bus.write_word_z80(0xD01234, 0x5678);
+        assert_eq!(bus.read_word(0xD01234), 0x5678);
+    }
+
+    #[test]
+    fn test_addr24_carries_across_pages() {
+        let mut bus = Bus::new();
+        bus.write_addr24(0xD0FFFE, 0x123456);
+        assert_eq!(bus.peek_byte(0xD0FFFE), 0x56);
+        assert_eq!(bus.peek_byte(0xD0FFFF), 0x34);
+        assert_eq!(bus.peek_byte(0xD10000), 0x12);
+        assert_eq!(bus.read_addr24(0xD0FFFE), 0x123456);
+    }
 }
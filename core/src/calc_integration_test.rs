@@ -6,7 +6,7 @@
 #[cfg(test)]
 mod tests {
     use crate::Emu;
-    use std::path::Path;
+    use crate::test_rom;
 
     /// TI-84 CE expression result address
     /// Discovered via RAM search: expression results (like "6*7") go to 0xD00619
@@ -122,26 +122,9 @@ mod tests {
         read_result(emu).1
     }
 
-    /// Try to load ROM from common locations
-    fn try_load_rom() -> Option<Vec<u8>> {
-        let paths = [
-            "TI-84 CE.rom",
-            "../TI-84 CE.rom",
-            "../../TI-84 CE.rom",
-        ];
-        for path in paths {
-            if Path::new(path).exists() {
-                if let Ok(data) = std::fs::read(path) {
-                    return Some(data);
-                }
-            }
-        }
-        None
-    }
-
     /// Boot emulator and prepare for calculations
     fn boot_emulator() -> Option<Emu> {
-        let rom_data = try_load_rom()?;
+        let rom_data = test_rom::try_load_real_rom()?;
         let mut emu = Emu::new();
         emu.load_rom(&rom_data).ok()?;
         emu.press_on_key();
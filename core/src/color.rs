@@ -0,0 +1,171 @@
+//! Display color profiles
+//!
+//! `render_frame` expands VRAM to ARGB8888 exactly like CEmu, which is the
+//! `Raw` profile. The CE's TN panel doesn't show those values as-is: blacks
+//! are lifted, whites never reach full brightness, mid-tones come out
+//! lighter and the gamut is narrow. Raw screenshots therefore look
+//! oversaturated next to real hardware. `PanelAccurate` approximates the
+//! panel, and `Vivid` pushes the other way for small, dim phone screens.
+//!
+//! A profile is a saturation change around Rec. 601 luma, followed by a
+//! per-channel tone curve `black + (white - black) * x^gamma`. Both are
+//! precomputed once in `ColorTransform::new`, so applying one costs a few
+//! integer operations per pixel.
+
+/// Framebuffer color profile (stable C ABI values, see emu.h)
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorProfile {
+    /// Unmodified RGB565 expansion (CEmu output)
+    #[default]
+    Raw = 0,
+    /// Lifted blacks, dim whites, lighter mid-tones, reduced saturation
+    PanelAccurate = 1,
+    /// Deeper blacks, more contrast and saturation than raw
+    Vivid = 2,
+}
+
+impl ColorProfile {
+    pub fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(ColorProfile::Raw),
+            1 => Some(ColorProfile::PanelAccurate),
+            2 => Some(ColorProfile::Vivid),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorProfile::Raw => "raw",
+            ColorProfile::PanelAccurate => "panel",
+            ColorProfile::Vivid => "vivid",
+        }
+    }
+
+    /// (black level, white level, gamma, saturation in 1/256ths)
+    fn params(self) -> (f32, f32, f32, i32) {
+        match self {
+            ColorProfile::Raw => (0.0, 255.0, 1.0, 256),
+            ColorProfile::PanelAccurate => (22.0, 232.0, 0.8, 184),
+            ColorProfile::Vivid => (0.0, 255.0, 1.15, 320),
+        }
+    }
+}
+
+/// Precomputed transform for one profile
+#[derive(Debug, Clone)]
+pub struct ColorTransform {
+    profile: ColorProfile,
+    tone: [u8; 256],
+    saturation: i32,
+}
+
+impl Default for ColorTransform {
+    fn default() -> Self {
+        Self::new(ColorProfile::Raw)
+    }
+}
+
+impl ColorTransform {
+    pub fn new(profile: ColorProfile) -> Self {
+        let (black, white, gamma, saturation) = profile.params();
+        let mut tone = [0u8; 256];
+        for (i, level) in tone.iter_mut().enumerate() {
+            let x = i as f32 / 255.0;
+            *level = (black + (white - black) * x.powf(gamma)).round().clamp(0.0, 255.0) as u8;
+        }
+        Self { profile, tone, saturation }
+    }
+
+    pub fn profile(&self) -> ColorProfile {
+        self.profile
+    }
+
+    /// True for `Raw`, where `apply` returns its input
+    pub fn is_identity(&self) -> bool {
+        self.profile == ColorProfile::Raw
+    }
+
+    /// Transform one ARGB8888 pixel (alpha is passed through)
+    #[inline]
+    pub fn apply(&self, argb: u32) -> u32 {
+        let r = ((argb >> 16) & 0xFF) as i32;
+        let g = ((argb >> 8) & 0xFF) as i32;
+        let b = (argb & 0xFF) as i32;
+        let luma = (77 * r + 150 * g + 29 * b) >> 8;
+        let channel = |c: i32| {
+            let c = luma + (((c - luma) * self.saturation) >> 8);
+            self.tone[c.clamp(0, 255) as usize] as u32
+        };
+        (argb & 0xFF000000) | (channel(r) << 16) | (channel(g) << 8) | channel(b)
+    }
+
+    /// Transform every pixel of `pixels` in place
+    pub fn apply_all(&self, pixels: &mut [u32]) {
+        if self.is_identity() {
+            return;
+        }
+        for pixel in pixels {
+            *pixel = self.apply(*pixel);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channels(argb: u32) -> (i32, i32, i32) {
+        (((argb >> 16) & 0xFF) as i32, ((argb >> 8) & 0xFF) as i32, (argb & 0xFF) as i32)
+    }
+
+    #[test]
+    fn test_raw_is_identity() {
+        let raw = ColorTransform::new(ColorProfile::Raw);
+        for argb in [0xFF000000, 0xFFFFFFFF, 0xFFFF0000, 0xFF123456, 0x80ABCDEF] {
+            assert_eq!(raw.apply(argb), argb);
+        }
+        let mut pixels = [0xFF00FF00u32; 4];
+        raw.apply_all(&mut pixels);
+        assert_eq!(pixels, [0xFF00FF00; 4]);
+    }
+
+    #[test]
+    fn test_panel_lifts_blacks_and_desaturates() {
+        let panel = ColorTransform::new(ColorProfile::PanelAccurate);
+        let (r, g, b) = channels(panel.apply(0xFF000000));
+        assert!(r > 0 && r == g && g == b, "black is lifted evenly");
+        let (r, _, _) = channels(panel.apply(0xFFFFFFFF));
+        assert!(r < 255, "white is dimmed");
+
+        // Mid grey comes out lighter
+        let (grey, _, _) = channels(panel.apply(0xFF808080));
+        assert!(grey > 0x80);
+
+        // Pure red picks up green and blue
+        let (r, g, b) = channels(panel.apply(0xFFFF0000));
+        assert!(r > g && g > 0 && b > 0);
+        assert_eq!(panel.apply(0x00FF0000) >> 24, 0, "alpha preserved");
+    }
+
+    #[test]
+    fn test_vivid_increases_saturation() {
+        let vivid = ColorTransform::new(ColorProfile::Vivid);
+        assert_eq!(vivid.apply(0xFF000000), 0xFF000000);
+        assert_eq!(vivid.apply(0xFFFFFFFF), 0xFFFFFFFF);
+
+        // A muted red moves away from grey
+        let (r, g, b) = channels(vivid.apply(0xFFC06060));
+        assert!(r - g > 0x60 && g == b);
+    }
+
+    #[test]
+    fn test_profile_raw_values() {
+        for profile in [ColorProfile::Raw, ColorProfile::PanelAccurate, ColorProfile::Vivid] {
+            assert_eq!(ColorProfile::from_raw(profile as u32), Some(profile));
+        }
+        assert_eq!(ColorProfile::from_raw(3), None);
+        assert_eq!(ColorProfile::PanelAccurate.name(), "panel");
+    }
+}
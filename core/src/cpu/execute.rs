@@ -117,7 +117,7 @@ impl Cpu {
                             bus.write_addr24(nn, hl);
                             20
                         } else {
-                            bus.write_word(nn, hl as u16);
+                            bus.write_word_z80(nn, hl as u16);
                             16
                         }
                     }
@@ -147,7 +147,7 @@ impl Cpu {
                         let val = if self.l {
                             bus.read_addr24(nn)
                         } else {
-                            bus.read_word(nn) as u32
+                            bus.read_word_z80(nn) as u32
                         };
                         self.set_rp(2, val);
                         if self.l { 20 } else { 16 }
@@ -409,9 +409,7 @@ impl Cpu {
                 if self.check_cc(y) {
                     let nn = self.fetch_addr_no_prefetch(bus);
                     bus.add_cycles(1); // CEmu: cpu.cycles++ for jump taken
-                    self.prefetch(bus, nn); // Reload prefetch at target
-                    self.pc = nn;
-                    self.adl = self.il;
+                    self.jump(bus, nn, self.il); // Reload prefetch at target
                 } else {
                     // When not taken, use regular fetch which maintains prefetch correctly
                     let _ = self.fetch_addr(bus);
@@ -426,9 +424,7 @@ impl Cpu {
                         // Use fetch_addr_no_prefetch to match CEmu's cpu_fetch_word_no_prefetch
                         bus.add_cycles(1); // CEmu: cpu.cycles++ for JP nn
                         let target = self.fetch_addr_no_prefetch(bus);
-                        self.prefetch(bus, target); // Reload prefetch at target
-                        self.pc = target;
-                        self.adl = self.il;
+                        self.jump(bus, target, self.il); // Reload prefetch at target
                         10
                     }
                     1 => {
@@ -455,12 +451,12 @@ impl Cpu {
                         let sp_val = if self.l {
                             bus.read_addr24(sp_addr)
                         } else {
-                            bus.read_word(sp_addr) as u32
+                            bus.read_word_z80(sp_addr) as u32
                         };
                         if self.l {
                             bus.write_addr24(sp_addr, self.hl);
                         } else {
-                            bus.write_word(sp_addr, self.hl as u16);
+                            bus.write_word_z80(sp_addr, self.hl as u16);
                         }
                         self.hl = sp_val;
                         19
@@ -544,13 +540,8 @@ impl Cpu {
                         1 => {
                             // DD prefix (IX instructions)
                             // Execute the indexed instruction in the same step (not deferred)
-                            // If next byte is ED, CEmu ignores DD and executes ED in the same step
-                            let next = bus.peek_byte_fetch(self.mask_addr_instr(self.pc));
-                            if next == 0xED {
-                                self.execute_ed(bus)
-                            } else {
-                                self.execute_index(bus, true)
-                            }
+                            // DD ED: execute_index consumes the ED byte and ignores DD
+                            self.execute_index(bus, true)
                         }
                         2 => {
                             // ED prefix (extended instructions)
@@ -559,13 +550,8 @@ impl Cpu {
                         3 => {
                             // FD prefix (IY instructions)
                             // Execute the indexed instruction in the same step (not deferred)
-                            // If next byte is ED, CEmu ignores FD and executes ED in the same step
-                            let next = bus.peek_byte_fetch(self.mask_addr_instr(self.pc));
-                            if next == 0xED {
-                                self.execute_ed(bus)
-                            } else {
-                                self.execute_index(bus, false)
-                            }
+                            // FD ED: execute_index consumes the ED byte and ignores FD
+                            self.execute_index(bus, false)
                         }
                         _ => 4,
                     }
@@ -815,7 +801,7 @@ impl Cpu {
                     let val = if self.l {
                         bus.read_addr24(addr)
                     } else {
-                        bus.read_word(addr) as u32
+                        bus.read_word_z80(addr) as u32
                     };
                     self.iy = self.wrap_data(val);
                     8
@@ -872,7 +858,7 @@ impl Cpu {
                 if self.l {
                     bus.write_addr24(addr, self.iy);
                 } else {
-                    bus.write_word(addr, self.iy as u16);
+                    bus.write_word_z80(addr, self.iy as u16);
                 }
                 8
             }
@@ -888,7 +874,7 @@ impl Cpu {
                     let val = if self.l {
                         bus.read_addr24(addr)
                     } else {
-                        bus.read_word(addr) as u32
+                        bus.read_word_z80(addr) as u32
                     };
                     match p {
                         0 => self.bc = self.wrap_data(val),
@@ -909,7 +895,7 @@ impl Cpu {
                     if self.l {
                         bus.write_addr24(addr, val);
                     } else {
-                        bus.write_word(addr, val as u16);
+                        bus.write_word_z80(addr, val as u16);
                     }
                 }
                 8
@@ -1026,7 +1012,7 @@ impl Cpu {
                         bus.write_addr24(nn, rp);
                         23
                     } else {
-                        bus.write_word(nn, rp as u16);
+                        bus.write_word_z80(nn, rp as u16);
                         20
                     }
                 } else {
@@ -1034,7 +1020,7 @@ impl Cpu {
                     let val = if self.l {
                         bus.read_addr24(nn)
                     } else {
-                        bus.read_word(nn) as u32
+                        bus.read_word_z80(nn) as u32
                     };
                     self.set_rp(p, val);
                     if self.l {
@@ -1199,8 +1185,7 @@ impl Cpu {
                     }
                     6 => {
                         // SLP - sleep (same as HALT on TI-84 CE)
-                        bus.add_cycles(1); // CEmu: cpu.cycles++ for SLP
-                        self.halted = true;
+                        self.enter_halt(bus);
                         return 4;
                     }
                     7 => {
@@ -1758,8 +1743,7 @@ impl Cpu {
             1 => {
                 if y == 6 && z == 6 {
                     // HALT - not affected by prefix
-                    bus.add_cycles(1); // CEmu: cpu.cycles++ for HALT
-                    self.halted = true;
+                    self.enter_halt(bus);
                     4
                 } else if y == 6 {
                     // LD (IX+d), r - write register to indexed memory
@@ -1951,7 +1935,7 @@ impl Cpu {
                         let val = if self.l {
                             bus.read_addr24(addr)
                         } else {
-                            bus.read_word(addr) as u32
+                            bus.read_word_z80(addr) as u32
                         };
                         let masked = if self.l { val & 0xFFFFFF } else { val & 0xFFFF };
                         if use_ix {
@@ -2023,7 +2007,7 @@ impl Cpu {
                             bus.write_addr24(nn, index_reg);
                             20
                         } else {
-                            bus.write_word(nn, index_reg as u16);
+                            bus.write_word_z80(nn, index_reg as u16);
                             16
                         }
                     }
@@ -2034,7 +2018,7 @@ impl Cpu {
                         let val = if self.l {
                             bus.read_addr24(nn)
                         } else {
-                            bus.read_word(nn) as u32
+                            bus.read_word_z80(nn) as u32
                         };
                         if use_ix {
                             self.ix = val;
@@ -2140,7 +2124,7 @@ impl Cpu {
                     if self.l {
                         bus.write_addr24(addr, other_reg);
                     } else {
-                        bus.write_word(addr, other_reg as u16);
+                        bus.write_word_z80(addr, other_reg as u16);
                     }
                     19
                 } else if y == 6 {
@@ -2170,7 +2154,7 @@ impl Cpu {
                     let val = if self.l {
                         bus.read_addr24(addr)
                     } else {
-                        bus.read_word(addr) as u32
+                        bus.read_word_z80(addr) as u32
                     } & mask;
                     match p {
                         0 => self.bc = val,
@@ -2197,7 +2181,7 @@ impl Cpu {
                     if self.l {
                         bus.write_addr24(addr, rp3_val);
                     } else {
-                        bus.write_word(addr, rp3_val as u16);
+                        bus.write_word_z80(addr, rp3_val as u16);
                     }
                 }
                 19
@@ -2264,9 +2248,7 @@ impl Cpu {
                 // RET cc - not affected
                 if self.check_cc(y) {
                     let target = self.pop_addr(bus);
-                    self.prefetch(bus, target); // Reload prefetch at return address
-                    self.pc = target;
-                    self.adl = self.l;
+                    self.jump(bus, target, self.l); // Reload prefetch at return address
                     if self.adl {
                         12
                     } else {
@@ -2304,9 +2286,7 @@ impl Cpu {
                         0 => {
                             // RET
                             let target = self.pop_addr(bus);
-                            self.prefetch(bus, target); // Reload prefetch at return address
-                            self.pc = target;
-                            self.adl = self.l;
+                            self.jump(bus, target, self.l); // Reload prefetch at return address
                             10
                         }
                         1 => {
@@ -2340,9 +2320,7 @@ impl Cpu {
                 // CEmu: uses no_prefetch only when taken, regular fetch otherwise
                 if self.check_cc(y) {
                     let nn = self.fetch_addr_no_prefetch(bus);
-                    self.prefetch(bus, nn); // Reload prefetch at target
-                    self.pc = nn;
-                    self.adl = self.il;
+                    self.jump(bus, nn, self.il); // Reload prefetch at target
                 } else {
                     // When not taken, use regular fetch which maintains prefetch correctly
                     let _ = self.fetch_addr(bus);
@@ -2355,9 +2333,7 @@ impl Cpu {
                         // JP nn - not affected by prefix but needs prefetch
                         // Use fetch_addr_no_prefetch to match CEmu's cpu_fetch_word_no_prefetch
                         let target = self.fetch_addr_no_prefetch(bus);
-                        self.prefetch(bus, target); // Reload prefetch at target
-                        self.pc = target;
-                        self.adl = self.il;
+                        self.jump(bus, target, self.il); // Reload prefetch at target
                         10
                     }
                     1 => {
@@ -2370,13 +2346,13 @@ impl Cpu {
                         let sp_val = if self.l {
                             bus.read_addr24(sp_addr)
                         } else {
-                            bus.read_word(sp_addr) as u32
+                            bus.read_word_z80(sp_addr) as u32
                         };
                         let index_reg = if use_ix { self.ix } else { self.iy };
                         if self.l {
                             bus.write_addr24(sp_addr, index_reg);
                         } else {
-                            bus.write_word(sp_addr, index_reg as u16);
+                            bus.write_word_z80(sp_addr, index_reg as u16);
                         }
                         if use_ix {
                             self.ix = sp_val;
@@ -2397,9 +2373,7 @@ impl Cpu {
                 if self.check_cc(y) {
                     let nn = self.fetch_addr_no_prefetch(bus);
                     self.push_addr(bus, self.pc);
-                    self.prefetch(bus, nn); // Reload prefetch at target
-                    self.pc = nn;
-                    self.adl = self.il;
+                    self.jump(bus, nn, self.il); // Reload prefetch at target
                     if self.adl {
                         20
                     } else {
@@ -2440,9 +2414,7 @@ impl Cpu {
                             // Use fetch_addr_no_prefetch to match CEmu's cpu_fetch_word_no_prefetch
                             let nn = self.fetch_addr_no_prefetch(bus);
                             self.push_addr(bus, self.pc);
-                            self.prefetch(bus, nn); // Reload prefetch at target
-                            self.pc = nn;
-                            self.adl = self.il;
+                            self.jump(bus, nn, self.il); // Reload prefetch at target
                             if self.adl {
                                 20
                             } else {
@@ -2503,7 +2475,8 @@ impl Cpu {
                 bus.add_cycles(1);
                 let result = self.execute_rot(y, val);
                 bus.write_byte(addr, result);
-                // If z != 6, also copy to register (undocumented)
+                // If z != 6, also copy to register (undocumented). r[z] is the
+                // plain register here: H/L, never IXH/IXL, despite the prefix.
                 if z != 6 {
                     self.set_reg8(z, result, bus);
                 }
@@ -2581,9 +2554,7 @@ impl Cpu {
             // Normal CALL: push PC using L mode
             self.push_addr(bus, self.pc);
         }
-        self.adl = mode;
-        self.prefetch(bus, address);
-        self.pc = address;
+        self.jump(bus, address, mode);
     }
 
     /// RET implementation matching CEmu's cpu_return()
@@ -2608,15 +2579,11 @@ impl Cpu {
             } else {
                 pcl | (pch << 8)
             };
-            self.adl = mode;
-            self.prefetch(bus, address);
-            self.pc = address;
+            self.jump(bus, address, mode);
         } else {
             // Normal RET: pop address using L mode
             let target = self.pop_addr(bus);
-            self.adl = self.l;
-            self.prefetch(bus, target);
-            self.pc = target;
+            self.jump(bus, target, self.l);
         }
     }
 
@@ -2625,22 +2592,24 @@ impl Cpu {
         bus.add_cycles(1); // CEmu: cpu.cycles++ in cpu_rst()
         if mixed {
             // Mixed-mode RST: push flag byte for cross-mode transitions
+            // As in call_impl, the flag byte must end up on top of SPL so a
+            // mixed-mode RET pops it first
             let flag_byte = ((self.madl as u8) << 1) | (self.adl as u8);
             if self.adl {
                 self.push_byte_mode(bus, (self.pc >> 16) as u8, true); // PCU via SPL
-                self.push_byte_mode(bus, flag_byte, true); // flag byte via SPL
+                if !stack {
+                    self.push_byte_mode(bus, flag_byte, true); // flag byte via SPL
+                }
             }
             self.push_byte_mode(bus, (self.pc >> 8) as u8, stack); // PCH
             self.push_byte_mode(bus, self.pc as u8, stack); // PCL
-            if !self.adl {
+            if stack || !self.adl {
                 self.push_byte_mode(bus, flag_byte, true); // flag byte via SPL
             }
         } else {
             // Normal RST: push PC using L mode
             self.push_addr(bus, self.pc);
         }
-        self.adl = mode;
-        self.prefetch(bus, address);
-        self.pc = address;
+        self.jump(bus, address, mode);
     }
 }
@@ -22,3 +22,84 @@ pub const F5: u8 = 0b0010_0000;
 pub const Z: u8 = 0b0100_0000;
 /// Sign flag (bit 7)
 pub const S: u8 = 0b1000_0000;
+
+// ========== Flag Lookup Tables ==========
+// Indexed by the 8-bit result (or operand for INC/DEC). Entries never
+// contain F3/F5 or C: callers merge those, since CEmu keeps the previous
+// F3/F5 for most ALU operations.
+
+/// S and Z for each result byte
+pub const SZ: [u8; 256] = build_sz(false);
+
+/// S, Z and even parity (PV) for each result byte
+pub const SZP: [u8; 256] = build_sz(true);
+
+/// INC r flags by operand: S, Z, H, PV (N clear)
+pub const INC: [u8; 256] = build_inc_dec(false);
+
+/// DEC r flags by operand: S, Z, H, PV, N
+pub const DEC: [u8; 256] = build_inc_dec(true);
+
+const fn build_sz(with_parity: bool) -> [u8; 256] {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut f = (i as u8) & S;
+        if i == 0 {
+            f |= Z;
+        }
+        if with_parity && (i as u8).count_ones() & 1 == 0 {
+            f |= PV;
+        }
+        table[i] = f;
+        i += 1;
+    }
+    table
+}
+
+const fn build_inc_dec(dec: bool) -> [u8; 256] {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        let val = i as u8;
+        let (result, half, overflow) = if dec {
+            (val.wrapping_sub(1), val & 0x0F == 0x00, val == 0x80)
+        } else {
+            (val.wrapping_add(1), val & 0x0F == 0x0F, val == 0x7F)
+        };
+        let mut f = SZ[result as usize];
+        if half {
+            f |= H;
+        }
+        if overflow {
+            f |= PV;
+        }
+        if dec {
+            f |= N;
+        }
+        table[i] = f;
+        i += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tables_match_bitwise_definitions() {
+        for i in 0..=255u8 {
+            assert_eq!(SZ[i as usize] & Z != 0, i == 0);
+            assert_eq!(SZ[i as usize] & S != 0, i & 0x80 != 0);
+            assert_eq!(SZP[i as usize] & PV != 0, i.count_ones() % 2 == 0);
+            assert_eq!(SZP[i as usize] & !PV, SZ[i as usize]);
+            assert_eq!(INC[i as usize] & H != 0, i & 0x0F == 0x0F);
+            assert_eq!(DEC[i as usize] & Z != 0, i == 1);
+            assert_eq!(DEC[i as usize] & PV != 0, i == 0x80);
+            for table in [&SZ, &SZP, &INC, &DEC] {
+                assert_eq!(table[i as usize] & (F3 | F5 | C), 0);
+            }
+        }
+    }
+}
@@ -245,23 +245,16 @@ impl Cpu {
     /// Set flags based on 8-bit result (S, Z, F5, F3)
     #[inline]
     pub fn set_sz_flags(&mut self, result: u8) {
-        // Clear S, Z, F5, F3
-        self.f &= !(flags::S | flags::Z | flags::F5 | flags::F3);
-        // Set based on result
-        if result == 0 {
-            self.f |= flags::Z;
-        }
-        if result & 0x80 != 0 {
-            self.f |= flags::S;
-        }
-        // Undocumented: copy bits 5 and 3 of result
-        self.f |= result & (flags::F5 | flags::F3);
+        // Replace S, Z, F5, F3; undocumented F5/F3 are copies of result bits 5 and 3
+        self.f = (self.f & !(flags::S | flags::Z | flags::F5 | flags::F3))
+            | flags::SZ[result as usize]
+            | (result & (flags::F5 | flags::F3));
     }
 
     /// Calculate parity of a byte (true if even number of 1 bits)
     #[inline]
     pub fn parity(val: u8) -> bool {
-        val.count_ones() % 2 == 0
+        flags::SZP[val as usize] & flags::PV != 0
     }
 
     // ========== Register Pair Exchange ==========
@@ -473,6 +466,22 @@ impl Cpu {
         let effective_addr = self.mask_addr_instr(addr);
         // Read and store in prefetch buffer for the next fetch_byte() call
         self.prefetch = bus.fetch_byte(effective_addr, addr);
+        self.prefetch_valid = true;
+    }
+
+    /// Jump to `target`, switching to `mode` (true = ADL) first.
+    ///
+    /// CEmu: cpu_jump(address, mode) -> cpu_prefetch(address, mode). The new mode
+    /// must be in effect before the prefetch so the target is masked in the
+    /// destination's instruction space: a JP.LIL from Z80 code with MBASE=0xD0
+    /// prefetches 24-bit `target`, not MBASE:target, and a JP.SIS from ADL code
+    /// prefetches MBASE:target.
+    #[inline]
+    pub fn jump(&mut self, bus: &mut Bus, target: u32, mode: bool) {
+        self.adl = mode;
+        let target = self.wrap_pc(target);
+        self.prefetch(bus, target);
+        self.pc = target;
     }
 
     /// Discard prefetch by reading from PC+1 without storing
@@ -594,26 +603,18 @@ impl Cpu {
     pub(super) fn alu_add(&mut self, val: u8, carry: bool) -> u8 {
         let c = if carry && self.flag_c() { 1u16 } else { 0 };
         let result = self.a as u16 + val as u16 + c;
+        let r = result as u8;
 
-        // Half-carry: carry from bit 3 to bit 4
-        let half = ((self.a & 0x0F) + (val & 0x0F) + c as u8) > 0x0F;
-
-        // Overflow: sign of result differs from expected
-        let overflow = ((self.a ^ val) & 0x80 == 0) && ((self.a ^ result as u8) & 0x80 != 0);
+        // Half-carry: carry from bit 3 to bit 4 (bit 4 of a ^ val ^ result)
+        let half = (self.a ^ val ^ r) & flags::H;
+        // Overflow: operands share a sign that the result doesn't
+        let overflow = ((!(self.a ^ val) & (self.a ^ r)) >> 5) & flags::PV;
 
         // Preserve F3/F5 from previous F (CEmu: cpuflag_undef(r->F))
-        let old_f3f5 = self.f & (flags::F5 | flags::F3);
-        self.f = 0;
-        self.set_sz_flags(result as u8);
-        // Clear F3/F5 that set_sz_flags computed from result, then restore old values
-        self.f &= !(flags::F5 | flags::F3);
-        self.f |= old_f3f5; // Restore F3/F5
-        self.set_flag_c(result > 0xFF);
-        self.set_flag_h(half);
-        self.set_flag_pv(overflow);
-        self.set_flag_n(false);
+        self.f = flags::SZ[r as usize] | (self.f & (flags::F5 | flags::F3))
+            | half | overflow | (result >> 8) as u8;
 
-        result as u8
+        r
     }
 
     /// Subtract with flags (used by SUB, SBC, CP)
@@ -621,128 +622,53 @@ impl Cpu {
     pub(super) fn alu_sub(&mut self, val: u8, carry: bool, _store: bool) -> u8 {
         let c = if carry && self.flag_c() { 1u16 } else { 0 };
         let result = (self.a as u16).wrapping_sub(val as u16).wrapping_sub(c);
+        let r = result as u8;
 
         // Half-carry (borrow from bit 4)
-        let half = (self.a & 0x0F) < (val & 0x0F) + c as u8;
-
-        // Overflow
-        let overflow = ((self.a ^ val) & 0x80 != 0) && ((self.a ^ result as u8) & 0x80 != 0);
+        let half = (self.a ^ val ^ r) & flags::H;
+        // Overflow: operand signs differ and the result's sign differs from A
+        let overflow = (((self.a ^ val) & (self.a ^ r)) >> 5) & flags::PV;
 
         // Preserve F3/F5 from previous F (CEmu: cpuflag_undef(r->F))
-        let old_f3f5 = self.f & (flags::F5 | flags::F3);
-        self.f = 0;
-        self.set_sz_flags(result as u8);
-        // Clear F3/F5 that set_sz_flags computed from result, then restore old values
-        self.f &= !(flags::F5 | flags::F3);
-        self.f |= old_f3f5; // Restore F3/F5 for all sub operations
-        self.set_flag_c(result > 0xFF);
-        self.set_flag_h(half);
-        self.set_flag_pv(overflow);
-        self.set_flag_n(true);
+        self.f = flags::SZ[r as usize] | (self.f & (flags::F5 | flags::F3))
+            | half | overflow | flags::N | ((result >> 8) as u8 & flags::C);
 
-        result as u8
+        r
     }
 
     /// AND operation
     /// CEmu preserves F3/F5 from existing F register
     pub(super) fn alu_and(&mut self, val: u8) {
-        let old_f3f5 = self.f & (flags::F5 | flags::F3);
         self.a &= val;
-        self.f = 0;
-        if self.a == 0 {
-            self.f |= flags::Z;
-        }
-        if self.a & 0x80 != 0 {
-            self.f |= flags::S;
-        }
-        self.f |= old_f3f5; // Preserve F3/F5 from before
-        self.set_flag_h(true);
-        self.set_flag_pv(Self::parity(self.a));
+        self.f = flags::SZP[self.a as usize] | flags::H | (self.f & (flags::F5 | flags::F3));
     }
 
     /// OR operation
     /// CEmu preserves F3/F5 from existing F register
     pub(super) fn alu_or(&mut self, val: u8) {
-        let old_f3f5 = self.f & (flags::F5 | flags::F3);
         self.a |= val;
-        self.f = 0;
-        if self.a == 0 {
-            self.f |= flags::Z;
-        }
-        if self.a & 0x80 != 0 {
-            self.f |= flags::S;
-        }
-        self.f |= old_f3f5; // Preserve F3/F5 from before
-        self.set_flag_pv(Self::parity(self.a));
+        self.f = flags::SZP[self.a as usize] | (self.f & (flags::F5 | flags::F3));
     }
 
     /// XOR operation
     /// CEmu preserves F3/F5 from existing F register
     pub(super) fn alu_xor(&mut self, val: u8) {
-        let old_f3f5 = self.f & (flags::F5 | flags::F3);
         self.a ^= val;
-        self.f = 0;
-        if self.a == 0 {
-            self.f |= flags::Z;
-        }
-        if self.a & 0x80 != 0 {
-            self.f |= flags::S;
-        }
-        self.f |= old_f3f5; // Preserve F3/F5 from before
-        self.set_flag_pv(Self::parity(self.a));
+        self.f = flags::SZP[self.a as usize] | (self.f & (flags::F5 | flags::F3));
     }
 
     /// Increment 8-bit value with flags
-    /// CEmu preserves F3/F5 from existing F register
+    /// CEmu preserves carry and F3/F5 from existing F register
     pub(super) fn alu_inc(&mut self, val: u8) -> u8 {
-        let result = val.wrapping_add(1);
-        let half = (val & 0x0F) == 0x0F;
-        let overflow = val == 0x7F;
-
-        // Preserve carry and F3/F5, set other flags
-        let old_c = self.flag_c();
-        let old_f3f5 = self.f & (flags::F5 | flags::F3);
-        self.f = 0;
-        // Set S, Z but not F3/F5
-        if result == 0 {
-            self.f |= flags::Z;
-        }
-        if result & 0x80 != 0 {
-            self.f |= flags::S;
-        }
-        self.f |= old_f3f5; // Preserve F3/F5
-        self.set_flag_h(half);
-        self.set_flag_pv(overflow);
-        self.set_flag_c(old_c);
-
-        result
+        self.f = flags::INC[val as usize] | (self.f & (flags::F5 | flags::F3 | flags::C));
+        val.wrapping_add(1)
     }
 
     /// Decrement 8-bit value with flags
-    /// CEmu preserves F3/F5 from existing F register
+    /// CEmu preserves carry and F3/F5 from existing F register
     pub(super) fn alu_dec(&mut self, val: u8) -> u8 {
-        let result = val.wrapping_sub(1);
-        let half = (val & 0x0F) == 0x00;
-        let overflow = val == 0x80;
-
-        // Preserve carry and F3/F5, set other flags
-        let old_c = self.flag_c();
-        let old_f3f5 = self.f & (flags::F5 | flags::F3);
-        self.f = 0;
-        // Set S, Z but not F3/F5
-        if result == 0 {
-            self.f |= flags::Z;
-        }
-        if result & 0x80 != 0 {
-            self.f |= flags::S;
-        }
-        self.f |= old_f3f5; // Preserve F3/F5
-        self.set_flag_h(half);
-        self.set_flag_pv(overflow);
-        self.set_flag_n(true);
-        self.set_flag_c(old_c);
-
-        result
+        self.f = flags::DEC[val as usize] | (self.f & (flags::F5 | flags::F3 | flags::C));
+        val.wrapping_sub(1)
     }
 
     // ========== Register Access by Index ==========
@@ -8,6 +8,7 @@
 //! - `flags`: Flag bit constants for the F register
 //! - `helpers`: Helper functions (register access, fetch, push/pop, ALU, flags)
 //! - `execute`: Instruction execution functions (execute_x0, execute_cb, execute_ed, etc.)
+//! - `opcodes`: Opcode metadata (lengths, flags, suffixes) shared with the disassembler
 //!
 //! # Register Set
 //!
@@ -19,11 +20,13 @@
 //! - CEmu (https://github.com/CE-Programming/CEmu)
 
 use crate::bus::Bus;
+use crate::error::LoadError;
 
 // Module declarations
 mod execute;
 pub mod flags;
 mod helpers;
+pub mod opcodes;
 
 #[cfg(test)]
 mod tests;
@@ -33,6 +36,7 @@ pub use flags::*;
 
 /// Interrupt modes
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InterruptMode {
     /// Mode 0: Execute instruction on data bus
     #[default]
@@ -44,6 +48,7 @@ pub enum InterruptMode {
 }
 
 /// eZ80 CPU state
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cpu {
     // Main registers - stored as 32-bit for 24-bit values
     /// Accumulator (8-bit)
@@ -142,7 +147,22 @@ pub struct Cpu {
     /// CEmu prefetches the NEXT byte during each fetch, which charges cycles for
     /// the next instruction's first byte as part of the current instruction.
     /// This is essential for cycle parity with CEmu.
+    ///
+    /// Invariant (between instructions): `prefetch` is the byte at
+    /// `mask_addr_instr(pc)` and its bus cost has already been charged.
+    /// - `fetch_byte` returns `prefetch` and reads PC+1 into it.
+    /// - Branch targets (`fetch_addr_no_prefetch`) stop before the byte after
+    ///   the operand; the taken path then calls `jump`, which applies the new
+    ///   ADL mode and reads the target. Not-taken paths use `fetch_addr`.
+    /// - JP (HL)/(IX)/(IY) and IRQ/NMI entry first read PC+1 and discard it
+    ///   (`prefetch_discard`), then load the target. A halted CPU skips the
+    ///   discard and pays 1 wake cycle instead.
+    /// - HALT performs no further fetches until woken.
     pub prefetch: u8,
+    /// False until the buffer has been loaded for the current PC (after
+    /// `new`/`reset`, or `invalidate_prefetch` when PC is set externally).
+    /// `step` loads it on demand, matching an explicit `init_prefetch`.
+    prefetch_valid: bool,
 }
 
 impl Cpu {
@@ -197,6 +217,7 @@ impl Cpu {
             prefix: 0,
             // Prefetch starts at 0 - will be initialized by reset() with bus access
             prefetch: 0,
+            prefetch_valid: false,
         }
     }
 
@@ -268,6 +289,7 @@ impl Cpu {
         self.prefix = 0;
         // Prefetch will be initialized by init_prefetch() when bus is available
         self.prefetch = 0;
+        self.prefetch_valid = false;
     }
 
     /// Initialize the prefetch buffer after reset
@@ -279,6 +301,18 @@ impl Cpu {
         // Read the byte and store it in prefetch buffer
         // This charges cycles for the first instruction's first byte
         self.prefetch = bus.fetch_byte(effective_pc, self.pc);
+        self.prefetch_valid = true;
+    }
+
+    /// Mark the prefetch buffer stale after PC or mode was changed from outside
+    /// the CPU (debugger, tests). The next `step` reloads it at the new PC.
+    pub fn invalidate_prefetch(&mut self) {
+        self.prefetch_valid = false;
+    }
+
+    /// Whether the prefetch buffer currently holds the byte at PC
+    pub fn prefetch_valid(&self) -> bool {
+        self.prefetch_valid
     }
 
     // ========== Instruction Execution ==========
@@ -289,6 +323,12 @@ impl Cpu {
     /// both memory access cycles (flash/RAM/port reads/writes) and internal
     /// CPU processing cycles. This matches CEmu's cycle counting behavior.
     pub fn step(&mut self, bus: &mut Bus) -> u32 {
+        // A stale buffer is loaded before the cycle window opens: its cost
+        // belongs to whatever set PC (reset/debugger), as with init_prefetch.
+        if !self.prefetch_valid {
+            self.init_prefetch(bus);
+        }
+
         // Track cycles at start - we return the delta at the end
         // Use total_cycles() to include both CPU internal cycles and memory timing
         let start_cycles = bus.total_cycles();
@@ -405,9 +445,7 @@ impl Cpu {
             // If the next instruction is a DD/FD prefix (which sets self.prefix),
             // the suffix modes should NOT persist to the execute_index call in the
             // next step() - that would be incorrect behavior.
-            if x == 1 && y == z && z < 4 {
-                let s = (opcode & 0x01) != 0; // L mode (bit 0)
-                let r = (opcode & 0x02) != 0; // IL mode (bit 1)
+            if let Some((s, r)) = opcodes::suffix_modes(opcode) {
                 self.l = s;
                 self.il = r;
                 self.suffix = true; // Mark as suffixed for mixed-mode CALL/RET/RST
@@ -423,8 +461,7 @@ impl Cpu {
                 1 => {
                     if y == 6 && z == 6 {
                         // HALT
-                        bus.add_cycles(1); // CEmu: cpu.cycles++ before cpu_halt()
-                        self.halted = true;
+                        self.enter_halt(bus);
                     } else {
                         // LD r,r' - CEmu's cpu_read_write_reg does NOT add extra cycles
                         // Memory timing for (HL) operands comes from mem_read/mem_write
@@ -448,14 +485,41 @@ impl Cpu {
         cycle_delta(start_cycles, bus.total_cycles())
     }
 
-    /// Handle maskable interrupt (IRQ)
-    /// Matches CEmu's interrupt entry in cpu.c:943-969
-    /// CEmu calls cpu_interrupt(0x38) → cpu_rst(0x38, cpu.ADL, cpu.ADL|cpu.MADL, cpu.MADL)
-    fn handle_irq(&mut self, bus: &mut Bus) -> u32 {
-        let was_halted = self.halted;
+    /// Enter the halted state (HALT, and SLP which behaves the same here).
+    ///
+    /// EI;HALT is the OS idle loop idiom: HALT is the instruction that
+    /// completes EI's one-instruction delay, so the pending enable takes
+    /// effect as the CPU halts rather than at the next step. Otherwise the
+    /// emu loop's HALT fast-forward would see IFF1 clear and sleep through
+    /// an IRQ that arrived during the EI window.
+    fn enter_halt(&mut self, bus: &mut Bus) {
+        bus.add_cycles(1); // CEmu: cpu.cycles++ before cpu_halt()
+        self.halted = true;
+        if self.ei_delay > 0 {
+            self.ei_delay = 0;
+            self.iff1 = true;
+            self.iff2 = true;
+        }
+    }
 
+    /// Whether the next step would accept an interrupt (NMI, or IRQ with
+    /// IFF1 set). The emu HALT fast-forward stops as soon as this is true.
+    pub fn halt_wake_pending(&self) -> bool {
+        self.nmi_pending || (self.irq_pending && self.iff1)
+    }
+
+    /// Interrupt acknowledge prologue shared by IRQ and NMI entry.
+    ///
+    /// Bus cost of an acceptance, all charged through the bus counters:
+    /// - running: discarded fetch of PC+1 (memory wait states) + 1 cycle
+    /// - halted: 1 wake cycle + 1 cycle, no fetch
+    /// - then `rst_impl`: 1 cycle, the PC push (2-3 stack writes, or 3-4 with
+    ///   the MADL flag byte) and the vector fetch.
+    ///
+    /// Returns the L/IL mode used for the entry (ADL || MADL).
+    fn interrupt_ack(&mut self, bus: &mut Bus) -> bool {
         // CEmu: if halted, cpu.cycles++ (wake cost); else prefetch_discard()
-        if was_halted {
+        if self.halted {
             bus.add_cycles(1);
         } else {
             self.prefetch_discard(bus);
@@ -466,43 +530,31 @@ impl Cpu {
         let mode = self.adl || self.madl;
         self.l = mode;
         self.il = mode;
+        self.halted = false;
+        mode
+    }
 
-        // Disable interrupts and clear state
+    /// Handle maskable interrupt (IRQ)
+    /// Matches CEmu's interrupt entry in cpu.c:943-969
+    /// CEmu calls cpu_interrupt(0x38) → cpu_rst(0x38, cpu.ADL, cpu.ADL|cpu.MADL, cpu.MADL)
+    fn handle_irq(&mut self, bus: &mut Bus) {
+        let mode = self.interrupt_ack(bus);
         self.iff1 = false;
-        self.halted = false;
 
-        // CEmu: cpu_interrupt(0x38) → cpu_rst(0x38, cpu.ADL, cpu.ADL|cpu.MADL, cpu.MADL)
         // rst_impl handles both normal and mixed-mode (MADL) interrupt entry
         self.rst_impl(bus, 0x38, self.adl, mode, self.madl);
-        // Return 0 — cycles already tracked via bus
-        0
     }
 
     /// Handle non-maskable interrupt (NMI)
-    fn handle_nmi(&mut self, bus: &mut Bus) -> u32 {
-        let was_halted = self.halted;
+    /// Same entry sequence and cost as an IRQ, vectoring to 0x0066.
+    fn handle_nmi(&mut self, bus: &mut Bus) {
+        let mode = self.interrupt_ack(bus);
 
-        if was_halted {
-            bus.add_cycles(1);
-        } else {
-            self.prefetch_discard(bus);
-        }
-        bus.add_cycles(1);
-
-        let mode = self.adl || self.madl;
-        self.l = mode;
-        self.il = mode;
-
-        // Save IFF1 to IFF2, disable IFF1
+        // Save IFF1 to IFF2 so RETN can restore it, disable IFF1
         self.iff2 = self.iff1;
         self.iff1 = false;
-        self.halted = false;
 
-        // Jump to NMI handler at 0x0066
-        self.push_addr(bus, self.pc);
-        self.prefetch(bus, 0x66);
-        self.pc = 0x66;
-        0
+        self.rst_impl(bus, 0x66, self.adl, mode, self.madl);
     }
 }
 
@@ -580,9 +632,9 @@ impl Cpu {
     }
 
     /// Load CPU state from bytes
-    pub fn from_bytes(&mut self, buf: &[u8]) -> Result<(), i32> {
+    pub fn from_bytes(&mut self, buf: &[u8]) -> Result<(), LoadError> {
         if buf.len() < Self::SNAPSHOT_SIZE {
-            return Err(-105); // Buffer too small
+            return Err(LoadError::StateTruncated { expected: Self::SNAPSHOT_SIZE, found: buf.len() });
         }
 
         let mut pos = 0;
@@ -640,6 +692,8 @@ impl Cpu {
         self.madl = mode_flags & (1 << 3) != 0;
         self.prefix = buf[pos]; pos += 1;
         self.prefetch = buf[pos];
+        // Snapshots are taken between instructions, so the buffer is live
+        self.prefetch_valid = true;
 
         Ok(())
     }
@@ -0,0 +1,557 @@
+//! Opcode metadata shared by the CPU and the disassembler
+//!
+//! One entry per opcode per page: operation name, the operand bytes that
+//! follow the opcode, the flags it may change, and whether it can leave PC
+//! anywhere other than the next instruction. `decode` walks suffix and
+//! prefix bytes exactly as `Cpu::step` does, so the disassembler and the
+//! executor agree on instruction lengths (the tests in tests/opcodes.rs step
+//! every opcode to keep it that way).
+//!
+//! The executor's timing comes from bus accesses and the internal cycles it
+//! adds as it goes, so the base cycle counts here are a reference copy of
+//! what it charges in one fixed setting (ADL mode, code and data in RAM,
+//! the cheapest path through the instruction). The tests step every opcode
+//! to keep the two in line.
+//!
+//! # References
+//! - eZ80 CPU User Manual (Zilog UM0077)
+//! - CEmu (https://github.com/CE-Programming/CEmu)
+
+use super::flags::{C, F3, F5, H, N, PV, S, Z};
+
+/// Bytes that follow the opcode byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    None,
+    /// 8-bit immediate, port number or relative jump offset
+    Byte,
+    /// 16-bit or 24-bit immediate (width follows IL)
+    Word,
+    /// Index displacement: (IX+d)/(IY+d), LEA/PEA offsets
+    Disp,
+    /// Displacement then 8-bit immediate (LD (IX+d),n)
+    DispByte,
+}
+
+impl Operand {
+    /// Operand length in bytes for the given IL mode
+    pub const fn len(self, il: bool) -> usize {
+        match self {
+            Operand::None => 0,
+            Operand::Byte | Operand::Disp => 1,
+            Operand::Word => if il { 3 } else { 2 },
+            Operand::DispByte => 2,
+        }
+    }
+}
+
+/// Opcode page, selected by the prefix bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Page {
+    Main,
+    /// CB xx
+    Cb,
+    /// ED xx
+    Ed,
+    /// DD xx / FD xx
+    Index,
+    /// DD CB d xx / FD CB d xx (displacement precedes the opcode)
+    IndexCb,
+}
+
+/// Static description of one opcode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpInfo {
+    /// Operation name as the disassembler prints it ("LD", "JR", "NONI")
+    pub mnemonic: &'static str,
+    pub operand: Operand,
+    /// Flag bits the instruction may change
+    pub flags: u8,
+    /// May leave PC somewhere other than the next instruction
+    /// (jumps, calls, returns, RST)
+    pub branch: bool,
+    /// Base cycles in ADL mode running from RAM, prefixes included:
+    /// condition false, repeating block ops on their last iteration.
+    /// 0 for prefix and suffix bytes, which are counted on their page.
+    pub cycles: u8,
+}
+
+const fn op(mnemonic: &'static str, operand: Operand, flags: u8, branch: bool) -> OpInfo {
+    OpInfo { mnemonic, operand, flags, branch, cycles: 0 }
+}
+
+const fn plain(mnemonic: &'static str) -> OpInfo {
+    op(mnemonic, Operand::None, 0, false)
+}
+
+const fn with(mnemonic: &'static str, operand: Operand) -> OpInfo {
+    op(mnemonic, operand, 0, false)
+}
+
+const fn jump(mnemonic: &'static str, operand: Operand) -> OpInfo {
+    op(mnemonic, operand, 0, true)
+}
+
+/// Mnemonic of opcodes the executor ignores (CEmu NONI / OPCODETRAP)
+pub const NONI_MNEMONIC: &str = "NONI";
+
+const NONI: OpInfo = plain(NONI_MNEMONIC);
+
+/// S, Z, H, PV, N, C: the documented flags
+const ARITH: u8 = S | Z | H | PV | N | C;
+/// Everything but carry
+const SZHPN: u8 = S | Z | H | PV | N;
+/// Result-dependent ops that also copy result bits 5 and 3
+const UNDOC: u8 = F5 | F3;
+
+const ALU: [&str; 8] = ["ADD", "ADC", "SUB", "SBC", "AND", "XOR", "OR", "CP"];
+const ROT: [&str; 8] = ["RLC", "RRC", "RL", "RR", "SLA", "SRA", "SLL", "SRL"];
+
+/// eZ80 suffix opcodes: (L, IL) they select for the next instruction
+pub const fn suffix_modes(opcode: u8) -> Option<(bool, bool)> {
+    match opcode {
+        0x40 => Some((false, false)), // .SIS
+        0x49 => Some((true, false)),  // .LIS
+        0x52 => Some((false, true)),  // .SIL
+        0x5B => Some((true, true)),   // .LIL
+        _ => None,
+    }
+}
+
+/// Suffix name for a suffix opcode (".SIS", ...)
+pub const fn suffix_name(opcode: u8) -> Option<&'static str> {
+    match opcode {
+        0x40 => Some(".SIS"),
+        0x49 => Some(".LIS"),
+        0x52 => Some(".SIL"),
+        0x5B => Some(".LIL"),
+        _ => None,
+    }
+}
+
+const fn main_info(opcode: u8) -> OpInfo {
+    let x = opcode >> 6;
+    let y = (opcode >> 3) & 7;
+    let z = opcode & 7;
+    let p = y >> 1;
+    let q = y & 1;
+    match x {
+        0 => match z {
+            0 => match y {
+                0 => plain("NOP"),
+                1 => op("EX", Operand::None, 0xFF, false),
+                2 => jump("DJNZ", Operand::Byte),
+                _ => jump("JR", Operand::Byte),
+            },
+            1 => if q == 0 { with("LD", Operand::Word) } else { op("ADD", Operand::None, H | N | C, false) },
+            2 => if p >= 2 { with("LD", Operand::Word) } else { plain("LD") },
+            3 => if q == 0 { plain("INC") } else { plain("DEC") },
+            4 => op("INC", Operand::None, SZHPN, false),
+            5 => op("DEC", Operand::None, SZHPN, false),
+            6 => with("LD", Operand::Byte),
+            _ => match y {
+                0 => op("RLCA", Operand::None, H | N | C, false),
+                1 => op("RRCA", Operand::None, H | N | C, false),
+                2 => op("RLA", Operand::None, H | N | C, false),
+                3 => op("RRA", Operand::None, H | N | C, false),
+                4 => op("DAA", Operand::None, ARITH | UNDOC, false),
+                5 => op("CPL", Operand::None, H | N, false),
+                6 => op("SCF", Operand::None, H | N | C, false),
+                _ => op("CCF", Operand::None, H | N | C, false),
+            },
+        },
+        1 => if opcode == 0x76 { plain("HALT") } else { plain("LD") },
+        2 => op(ALU[y as usize], Operand::None, ARITH, false),
+        _ => match z {
+            0 => jump("RET", Operand::None),
+            1 => match (q, p) {
+                (0, 3) => op("POP", Operand::None, 0xFF, false),
+                (0, _) => plain("POP"),
+                (_, 0) => jump("RET", Operand::None),
+                (_, 1) => plain("EXX"),
+                (_, 2) => jump("JP", Operand::None),
+                _ => plain("LD"),
+            },
+            2 => jump("JP", Operand::Word),
+            3 => match y {
+                0 => jump("JP", Operand::Word),
+                1 => plain("CB"), // Prefix: decode() continues on the CB page
+                2 => with("OUT", Operand::Byte),
+                3 => with("IN", Operand::Byte),
+                4 | 5 => plain("EX"),
+                6 => plain("DI"),
+                _ => plain("EI"),
+            },
+            4 => jump("CALL", Operand::Word),
+            5 => match (q, p) {
+                (0, _) => plain("PUSH"),
+                (_, 0) => jump("CALL", Operand::Word),
+                (_, 1) => plain("DD"), // Prefixes
+                (_, 2) => plain("ED"),
+                _ => plain("FD"),
+            },
+            6 => op(ALU[y as usize], Operand::Byte, ARITH, false),
+            _ => jump("RST", Operand::None),
+        },
+    }
+}
+
+const fn cb_info(opcode: u8) -> OpInfo {
+    let y = (opcode >> 3) & 7;
+    match opcode >> 6 {
+        0 => op(ROT[y as usize], Operand::None, ARITH | UNDOC, false),
+        1 => op("BIT", Operand::None, SZHPN, false),
+        2 => plain("RES"),
+        _ => plain("SET"),
+    }
+}
+
+const fn ed_info(opcode: u8) -> OpInfo {
+    let x = opcode >> 6;
+    let y = (opcode >> 3) & 7;
+    let z = opcode & 7;
+    let p = y >> 1;
+    let q = y & 1;
+    match x {
+        0 => match z {
+            0 => op("IN0", Operand::Byte, SZHPN, false),
+            1 => if y == 6 { plain("LD") } else { with("OUT0", Operand::Byte) },
+            2 | 3 => if q == 0 { with("LEA", Operand::Disp) } else { NONI },
+            4 => op("TST", Operand::None, ARITH | UNDOC, false),
+            6 => if y == 7 { plain("LD") } else { NONI },
+            7 => plain("LD"),
+            _ => NONI,
+        },
+        1 => match z {
+            0 => op("IN", Operand::None, SZHPN, false),
+            1 => if y == 6 { NONI } else { plain("OUT") },
+            2 => op(if q == 0 { "SBC" } else { "ADC" }, Operand::None, ARITH, false),
+            3 => with("LD", Operand::Word),
+            4 => match (q, p) {
+                (0, 0) => op("NEG", Operand::None, ARITH, false),
+                (0, 1) => with("LEA", Operand::Disp),
+                (0, 2) => op("TST", Operand::Byte, ARITH | UNDOC, false),
+                (0, _) => op("TSTIO", Operand::Byte, ARITH | UNDOC, false),
+                _ => plain("MLT"),
+            },
+            5 => match y {
+                0 => jump("RETN", Operand::None),
+                1 => jump("RETI", Operand::None),
+                2 => with("LEA", Operand::Disp),
+                4 => with("PEA", Operand::Disp),
+                5 => plain("LD"),
+                7 => plain("STMIX"),
+                _ => NONI,
+            },
+            6 => match y {
+                0 | 2 | 3 => plain("IM"),
+                4 => with("PEA", Operand::Disp),
+                5 => plain("LD"),
+                6 => plain("SLP"),
+                7 => plain("RSMIX"),
+                _ => NONI,
+            },
+            _ => match y {
+                0 | 1 => plain("LD"),
+                2 | 3 => op("LD", Operand::None, SZHPN | UNDOC, false),
+                4 => op("RRD", Operand::None, SZHPN | UNDOC, false),
+                5 => op("RLD", Operand::None, SZHPN | UNDOC, false),
+                _ => NONI,
+            },
+        },
+        2 => match z {
+            0..=3 if y >= 4 => {
+                const NAMES: [[&str; 4]; 4] = [
+                    ["LDI", "CPI", "INI", "OUTI"],
+                    ["LDD", "CPD", "IND", "OUTD"],
+                    ["LDIR", "CPIR", "INIR", "OTIR"],
+                    ["LDDR", "CPDR", "INDR", "OTDR"],
+                ];
+                // Repeating forms run every iteration in one step, PC stays put.
+                // Compares also set bits 5 and 3 from A - (HL) - H.
+                let undoc = if z == 1 { UNDOC } else { 0 };
+                op(NAMES[y as usize - 4][z as usize], Operand::None, SZHPN | undoc, false)
+            }
+            2 | 3 => {
+                const NAMES: [[&str; 4]; 2] = [
+                    ["INIM", "INDM", "INIMR", "INDMR"],
+                    ["OTIM", "OTDM", "OTIMR", "OTDMR"],
+                ];
+                op(NAMES[z as usize - 2][y as usize], Operand::None, SZHPN | UNDOC, false)
+            }
+            4 => {
+                const NAMES: [&str; 8] = ["INI2", "IND2", "INI2R", "IND2R", "OUTI2", "OUTD2", "OTI2R", "OTD2R"];
+                op(NAMES[y as usize], Operand::None, Z | N, false)
+            }
+            _ => NONI,
+        },
+        _ => match opcode {
+            0xC7 => plain("LD"),
+            0xD7 => op("LD", Operand::None, ARITH, false),
+            0xC2 => op("INIRX", Operand::None, SZHPN, false),
+            0xCA => op("INDRX", Operand::None, SZHPN, false),
+            0xC3 => op("OTIRX", Operand::None, SZHPN, false),
+            0xCB => op("OTDRX", Operand::None, SZHPN, false),
+            _ => NONI,
+        },
+    }
+}
+
+/// DD/FD page: (HL) operands become (IX+d)/(IY+d), plus the eZ80 index
+/// loads. Opcodes the prefix doesn't affect keep their main-page entry.
+const fn index_info(opcode: u8) -> OpInfo {
+    let x = opcode >> 6;
+    let y = (opcode >> 3) & 7;
+    let z = opcode & 7;
+    let main = main_info(opcode);
+    match x {
+        0 => match z {
+            1 if opcode == 0x31 => with("LD", Operand::Disp), // LD IY/IX,(IX/IY+d)
+            4 | 5 if y == 6 => OpInfo { operand: Operand::Disp, ..main },
+            6 => match y {
+                6 => with("LD", Operand::DispByte),
+                7 => with("LD", Operand::Disp), // LD (IX/IY+d),IY/IX
+                _ => main,
+            },
+            7 => with("LD", Operand::Disp), // LD rp3,(IX+d) / LD (IX+d),rp3
+            _ => main,
+        },
+        1 if opcode != 0x76 && (y == 6 || z == 6) => with("LD", Operand::Disp),
+        2 if z == 6 => OpInfo { operand: Operand::Disp, ..main },
+        _ => main,
+    }
+}
+
+// Base cycle counts as `Cpu::step` charges them (see `OpInfo::cycles`)
+
+const MAIN_CYCLES: [u8; 256] = [
+     4, 16, 14,  4,  4,  4,  8,  4,  4,  4, 14,  4,  4,  4,  8,  4, // 0x
+     8, 16,  6,  4,  4,  4,  8,  4, 12,  4,  8,  4,  4,  4,  8,  4, // 1x
+     8, 16, 46,  4,  4,  4,  8,  4,  8,  4, 46,  4,  4,  4,  8,  4, // 2x
+     8, 16, 26,  4, 11, 11, 10,  4,  8,  4, 26,  4,  4,  4,  8,  4, // 3x
+     0,  4,  4,  4,  4,  4,  8,  4,  4,  0,  4,  4,  4,  4,  8,  4, // 4x
+     4,  4,  0,  4,  4,  4,  8,  4,  4,  4,  4,  0,  4,  4,  8,  4, // 5x
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 6x
+     6,  6,  6,  6,  6,  6,  5,  6,  4,  4,  4,  4,  4,  4,  8,  4, // 7x
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 8x
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 9x
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // Ax
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // Bx
+     5, 16, 16, 23, 16, 10,  8, 21,  5, 27, 16,  0, 16, 28,  8, 21, // Cx
+     5, 16, 16, 11, 16, 10,  8, 21,  5,  4, 16, 11, 16,  0,  8, 21, // Dx
+     5, 16, 16, 22, 16, 10,  8, 21,  5, 12, 16,  4, 16,  0,  8, 21, // Ex
+     5, 16, 16,  4, 16, 10,  8, 21,  5,  4, 16,  4, 16,  0,  8, 21, // Fx
+];
+
+const CB_CYCLES: [u8; 256] = [
+     8,  8,  8,  8,  8,  8, 15,  8,  8,  8,  8,  8,  8,  8, 15,  8, // 0x
+     8,  8,  8,  8,  8,  8, 15,  8,  8,  8,  8,  8,  8,  8, 15,  8, // 1x
+     8,  8,  8,  8,  8,  8, 15,  8,  8,  8,  8,  8,  8,  8, 15,  8, // 2x
+     8,  8,  8,  8,  8,  8, 15,  8,  8,  8,  8,  8,  8,  8, 15,  8, // 3x
+     8,  8,  8,  8,  8,  8, 12,  8,  8,  8,  8,  8,  8,  8, 12,  8, // 4x
+     8,  8,  8,  8,  8,  8, 12,  8,  8,  8,  8,  8,  8,  8, 12,  8, // 5x
+     8,  8,  8,  8,  8,  8, 12,  8,  8,  8,  8,  8,  8,  8, 12,  8, // 6x
+     8,  8,  8,  8,  8,  8, 12,  8,  8,  8,  8,  8,  8,  8, 12,  8, // 7x
+     8,  8,  8,  8,  8,  8, 15,  8,  8,  8,  8,  8,  8,  8, 15,  8, // 8x
+     8,  8,  8,  8,  8,  8, 15,  8,  8,  8,  8,  8,  8,  8, 15,  8, // 9x
+     8,  8,  8,  8,  8,  8, 15,  8,  8,  8,  8,  8,  8,  8, 15,  8, // Ax
+     8,  8,  8,  8,  8,  8, 15,  8,  8,  8,  8,  8,  8,  8, 15,  8, // Bx
+     8,  8,  8,  8,  8,  8, 15,  8,  8,  8,  8,  8,  8,  8, 15,  8, // Cx
+     8,  8,  8,  8,  8,  8, 15,  8,  8,  8,  8,  8,  8,  8, 15,  8, // Dx
+     8,  8,  8,  8,  8,  8, 15,  8,  8,  8,  8,  8,  8,  8, 15,  8, // Ex
+     8,  8,  8,  8,  8,  8, 15,  8,  8,  8,  8,  8,  8,  8, 15,  8, // Fx
+];
+
+const ED_CYCLES: [u8; 256] = [
+    14, 14, 12, 12,  8,  8,  8, 20, 14, 14,  8,  8,  8,  8,  8, 14, // 0x
+    14, 14, 12, 12,  8,  8,  8, 20, 14, 14,  8,  8,  8,  8,  8, 14, // 1x
+    14, 14, 12, 12,  8,  8,  8, 20, 14, 14,  8,  8,  8,  8,  8, 14, // 2x
+    14, 20, 12, 12, 12,  8,  8, 20, 14, 14,  8,  8,  8,  8, 14, 14, // 3x
+    10, 10,  8, 50,  8, 31,  8,  8, 10, 10,  8, 50,  8, 31,  8,  8, // 4x
+    10, 10,  8, 50, 12, 12,  8,  8, 10, 10,  8, 50,  8,  8,  8,  8, // 5x
+    10, 10,  8, 50, 12, 18, 18, 15, 10, 10,  8, 50,  8,  8,  8, 15, // 6x
+    10,  8,  8, 50, 14,  8,  9,  8, 10, 10,  8, 50,  8,  8,  8,  8, // 7x
+     8,  8, 13, 15, 13,  8,  8,  8,  8,  8, 13, 15, 13,  8,  8,  8, // 8x
+     8,  8, 13, 15, 13,  8,  8,  8,  8,  8, 13, 15, 13,  8,  8,  8, // 9x
+    15, 13, 13, 15, 15,  8,  8,  8, 15, 13, 13, 15, 15,  8,  8,  8, // Ax
+    15, 13, 13, 15, 15,  8,  8,  8, 15, 13, 13, 15, 15,  8,  8,  8, // Bx
+     8,  8, 13, 15,  8,  8,  8,  8,  8,  8, 13, 15,  8,  8,  8,  8, // Cx
+     8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8, // Dx
+     8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8, // Ex
+     8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8,  8, // Fx
+];
+
+const INDEX_CYCLES: [u8; 256] = [
+     8, 20, 18,  8,  8,  8, 12, 24,  8,  8, 18,  8,  8,  8, 12, 18, // 0x
+    12, 20, 10,  8,  8,  8, 12, 24, 16,  8, 12,  8,  8,  8, 12, 18, // 1x
+    12, 20, 50,  8,  8,  8, 12, 24, 12,  8, 50,  8,  8,  8, 12, 18, // 2x
+    12, 24, 30,  8, 19, 19, 18, 24, 12,  8, 30,  8,  8,  8, 18, 18, // 3x
+     8,  8,  8,  8,  8,  8, 16,  8,  8,  8,  8,  8,  8,  8, 16,  8, // 4x
+     8,  8,  8,  8,  8,  8, 16,  8,  8,  8,  8,  8,  8,  8, 16,  8, // 5x
+     8,  8,  8,  8,  8,  8, 16,  8,  8,  8,  8,  8,  8,  8, 16,  8, // 6x
+    14, 14, 14, 14, 14, 14,  9, 14,  8,  8,  8,  8,  8,  8, 16,  8, // 7x
+     8,  8,  8,  8,  8,  8, 16,  8,  8,  8,  8,  8,  8,  8, 16,  8, // 8x
+     8,  8,  8,  8,  8,  8, 16,  8,  8,  8,  8,  8,  8,  8, 16,  8, // 9x
+     8,  8,  8,  8,  8,  8, 16,  8,  8,  8,  8,  8,  8,  8, 16,  8, // Ax
+     8,  8,  8,  8,  8,  8, 16,  8,  8,  8,  8,  8,  8,  8, 16,  8, // Bx
+     8, 20, 20, 26, 20, 14, 12, 25,  8, 30, 20,  0, 20, 32, 12, 25, // Cx
+     8, 20, 20, 15, 20, 14, 12, 25,  8,  8, 20, 15, 20,  0, 12, 25, // Dx
+     8, 20, 20, 26, 20, 14, 12, 25,  8, 16, 20,  8, 20,  0, 12, 25, // Ex
+     8, 20, 20,  8, 20, 14, 12, 25,  8,  8, 20,  8, 20,  0, 12, 25, // Fx
+];
+
+const INDEX_CB_CYCLES: [u8; 256] = [
+    23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, // 0x
+    23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, // 1x
+    23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, // 2x
+    23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, // 3x
+    20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, // 4x
+    20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, // 5x
+    20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, // 6x
+    20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, // 7x
+    23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, // 8x
+    23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, // 9x
+    23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, // Ax
+    23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, // Bx
+    23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, // Cx
+    23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, // Dx
+    23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, // Ex
+    23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, // Fx
+];
+const fn build(page: Page) -> [OpInfo; 256] {
+    let mut table = [NONI; 256];
+    let mut i = 0;
+    while i < 256 {
+        let opcode = i as u8;
+        let (info, cycles) = match page {
+            Page::Main => (main_info(opcode), &MAIN_CYCLES),
+            Page::Cb => (cb_info(opcode), &CB_CYCLES),
+            Page::Ed => (ed_info(opcode), &ED_CYCLES),
+            Page::Index => (index_info(opcode), &INDEX_CYCLES),
+            Page::IndexCb => (OpInfo { operand: Operand::Disp, ..cb_info(opcode) }, &INDEX_CB_CYCLES),
+        };
+        table[i] = OpInfo { cycles: cycles[i], ..info };
+        i += 1;
+    }
+    table
+}
+
+static MAIN: [OpInfo; 256] = build(Page::Main);
+static CB: [OpInfo; 256] = build(Page::Cb);
+static ED: [OpInfo; 256] = build(Page::Ed);
+static INDEX: [OpInfo; 256] = build(Page::Index);
+static INDEX_CB: [OpInfo; 256] = build(Page::IndexCb);
+
+/// Metadata for `opcode` on `page`
+pub fn info(page: Page, opcode: u8) -> &'static OpInfo {
+    let table = match page {
+        Page::Main => &MAIN,
+        Page::Cb => &CB,
+        Page::Ed => &ED,
+        Page::Index => &INDEX,
+        Page::IndexCb => &INDEX_CB,
+    };
+    &table[opcode as usize]
+}
+
+/// A fully decoded instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decoded {
+    /// Total length, suffix and prefixes included
+    pub length: usize,
+    pub page: Page,
+    pub opcode: u8,
+    /// Suffix opcode byte, if any
+    pub suffix: Option<u8>,
+    /// IL in effect for the immediate (ADL unless a suffix overrides it)
+    pub il: bool,
+    pub info: &'static OpInfo,
+}
+
+/// Decode the instruction at the start of `bytes`. Returns None when
+/// `bytes` ends before the instruction does.
+pub fn decode(bytes: &[u8], adl: bool) -> Option<Decoded> {
+    let mut pos = 0;
+    let mut il = adl;
+    let mut suffix = None;
+    // Cpu::step applies suffixes in a loop; the last one wins
+    while let Some((_, suffix_il)) = suffix_modes(*bytes.get(pos)?) {
+        suffix = Some(bytes[pos]);
+        il = suffix_il;
+        pos += 1;
+    }
+
+    let mut page = Page::Main;
+    let mut opcode = bytes[pos];
+    pos += 1;
+    loop {
+        match (page, opcode) {
+            (Page::Main, 0xCB) => page = Page::Cb,
+            (Page::Main, 0xED) | (Page::Index, 0xED) => page = Page::Ed,
+            // Chained DD/FD: the last prefix selects the index register
+            (Page::Main, 0xDD | 0xFD) | (Page::Index, 0xDD | 0xFD) => page = Page::Index,
+            (Page::Index, 0xCB) => {
+                // DD CB d op: skip the displacement to reach the opcode
+                page = Page::IndexCb;
+                opcode = *bytes.get(pos + 1)?;
+                pos += 2;
+                break;
+            }
+            _ => break,
+        }
+        opcode = *bytes.get(pos)?;
+        pos += 1;
+    }
+
+    let info = info(page, opcode);
+    let operand = if page == Page::IndexCb { 0 } else { info.operand.len(il) };
+    let length = pos + operand;
+    if length > bytes.len() {
+        return None;
+    }
+    Some(Decoded { length, page, opcode, suffix, il, info })
+}
+
+/// Length of the instruction at the start of `bytes` (0 if truncated)
+pub fn instruction_length(bytes: &[u8], adl: bool) -> usize {
+    decode(bytes, adl).map_or(0, |d| d.length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_lengths() {
+        // LD HL,nn in ADL, Z80 and with suffixes that override IL
+        assert_eq!(instruction_length(&[0x21, 1, 2, 3], true), 4);
+        assert_eq!(instruction_length(&[0x21, 1, 2, 3], false), 3);
+        assert_eq!(instruction_length(&[0x40, 0x21, 1, 2, 3], true), 4);
+        assert_eq!(instruction_length(&[0x5B, 0x21, 1, 2, 3], false), 5);
+        assert_eq!(instruction_length(&[0x49, 0xC3, 1, 2, 3], true), 4);
+
+        // DD CB d op, DD prefix chains, prefix-insensitive opcodes
+        let d = decode(&[0xDD, 0xCB, 0x05, 0xC6], true).unwrap();
+        assert_eq!((d.length, d.page, d.opcode, d.info.mnemonic), (4, Page::IndexCb, 0xC6, "SET"));
+        assert_eq!(instruction_length(&[0xDD, 0xFD, 0x21, 1, 2, 3], true), 6);
+        assert_eq!(instruction_length(&[0xFD, 0xC3, 1, 2, 3], true), 5);
+        assert_eq!(instruction_length(&[0xDD, 0x36, 1, 2], false), 4);
+        assert_eq!(instruction_length(&[0x52, 0xED, 0x4B, 1, 2, 3], false), 6);
+
+        // Truncated input
+        assert_eq!(decode(&[0xC3, 1, 2], true), None);
+        assert_eq!(decode(&[0x5B], true), None);
+        assert_eq!(decode(&[0xDD, 0xCB, 0x05], true), None);
+    }
+
+    #[test]
+    fn test_suffix_modes_match_encoding() {
+        // Suffix opcodes are LD r,r with y == z < 4: bit 0 = L, bit 1 = IL
+        for opcode in 0..=255u8 {
+            let (y, z) = ((opcode >> 3) & 7, opcode & 7);
+            let expected = (opcode >> 6 == 1 && y == z && z < 4).then_some((z & 1 != 0, z & 2 != 0));
+            assert_eq!(suffix_modes(opcode), expected, "{:02X}", opcode);
+            assert_eq!(suffix_name(opcode).is_some(), expected.is_some());
+        }
+    }
+}
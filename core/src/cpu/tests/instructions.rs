@@ -215,6 +215,24 @@ fn test_mask_addr_data_z80() {
     assert_eq!(cpu.mask_addr(0xABCDEF), 0xD0CDEF); // Upper bits replaced with MBASE
 }
 
+#[test]
+fn test_mbase_translation_follows_mbase() {
+    let mut cpu = Cpu::new();
+    cpu.l = false;
+    cpu.adl = false;
+    cpu.mbase = 0xE0;
+    assert_eq!(cpu.mask_addr(0x1234), 0xE01234);
+    assert_eq!(cpu.mask_addr_instr(0x1234), 0xE01234);
+
+    // MBASE survives a snapshot round trip and reset clears it
+    let snapshot = cpu.to_bytes();
+    cpu.reset();
+    assert_eq!(cpu.mask_addr(0x1234), 0x001234);
+    cpu.from_bytes(&snapshot).unwrap();
+    assert_eq!(cpu.mbase, 0xE0);
+    assert_eq!(cpu.mask_addr(0x1234), 0xE01234);
+}
+
 #[test]
 fn test_index_registers() {
     let mut cpu = Cpu::new();
@@ -1535,6 +1553,53 @@ fn test_indexed_cb_res() {
     assert_eq!(bus.peek_byte(0xD00105), 0xFE);
 }
 
+/// Run one DD/FD CB d op with (index+5) = `val`, returning the CPU, the
+/// byte left in memory, and the cycles taken
+fn run_indexed_cb(prefix: u8, op: u8, val: u8) -> (Cpu, u8, u32) {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    cpu.adl = true;
+    cpu.f = flags::C;
+    cpu.bc = 0x111111;
+    cpu.de = 0x222222;
+    cpu.hl = 0x333333;
+    cpu.a = 0x44;
+    cpu.ix = 0xD00100;
+    cpu.iy = 0xD00100;
+    bus.poke_byte(0xD00105, val);
+    for (i, byte) in [prefix, 0xCB, 0x05, op].into_iter().enumerate() {
+        bus.poke_byte(i as u32, byte);
+    }
+    let cycles = step_full(&mut cpu, &mut bus);
+    (cpu, bus.peek_byte(0xD00105), cycles)
+}
+
+#[test]
+fn test_indexed_cb_undocumented_register_copy() {
+    // DD/FD CB d op with z != 6: the rotate/RES/SET result is written back to
+    // (IX+d)/(IY+d) and also copied to r[z] (H and L, not IXH/IXL)
+    for prefix in [0xDD, 0xFD] {
+        for op in (0u8..=0xFF).filter(|op| op >> 6 != 1 && op & 7 != 6) {
+            for val in [0x00, 0x01, 0x80, 0xA5] {
+                let (reference, expected, ref_cycles) = run_indexed_cb(prefix, (op & !7) | 6, val);
+                let (cpu, mem, cycles) = run_indexed_cb(prefix, op, val);
+                let context = format!("{:02X} CB 05 {:02X} with {:02X}", prefix, op, val);
+
+                assert_eq!(mem, expected, "{}: memory", context);
+                assert_eq!(cpu.f, reference.f, "{}: flags", context);
+                assert_eq!(cycles, ref_cycles, "{}: cycles", context);
+                let regs = [cpu.b(), cpu.c(), cpu.d(), cpu.e(), cpu.h(), cpu.l(), 0, cpu.a];
+                let before = [0x11, 0x11, 0x22, 0x22, 0x33, 0x33, 0, 0x44];
+                for (z, (&got, &old)) in regs.iter().zip(before.iter()).enumerate() {
+                    let want = if z as u8 == op & 7 { expected } else { old };
+                    assert_eq!(got, want, "{}: r[{}]", context, z);
+                }
+                assert_eq!((cpu.ix, cpu.iy), (0xD00100, 0xD00100), "{}: index registers", context);
+            }
+        }
+    }
+}
+
 #[test]
 fn test_inc_indexed_mem() {
     let mut cpu = Cpu::new();
@@ -3134,3 +3199,154 @@ fn test_ld_ixl_r_does_substitute() {
     assert_eq!(cpu.ixl(), 0x42, "LD IXL, B should write to IXL");
     assert_eq!(cpu.l(), 0x99, "L should be unchanged");
 }
+
+// ========== Interrupt Acceptance Tests ==========
+
+/// CPU running ADL code from RAM with the stack in RAM, buffer loaded
+fn setup_irq_entry(bus: &mut Bus) -> Cpu {
+    let mut cpu = Cpu::new();
+    cpu.adl = true;
+    cpu.pc = 0xD00000;
+    cpu.set_sp_both(0xD1A87E);
+    cpu.im = InterruptMode::Mode1;
+    cpu.iff1 = true;
+    cpu.iff2 = true;
+    bus.poke_byte(0xD00000, 0x00);
+    cpu.init_prefetch(bus);
+    cpu
+}
+
+#[test]
+fn test_irq_acceptance_cycles() {
+    let mut bus = Bus::new();
+    let mut cpu = setup_irq_entry(&mut bus);
+    cpu.irq_pending = true;
+
+    let cycles = cpu.step(&mut bus) as u64;
+    // Discarded RAM fetch + ack cycle + RST cycle + 3 stack writes + vector fetch
+    let expected = Bus::RAM_READ_CYCLES + 1 + 1 + 3 * Bus::RAM_WRITE_CYCLES + Bus::FLASH_READ_CYCLES;
+    assert_eq!(cycles, expected);
+    assert_eq!(cpu.pc, 0x38);
+    assert_eq!(cpu.sp(), 0xD1A87B);
+    assert!(!cpu.iff1);
+}
+
+#[test]
+fn test_irq_acceptance_cycles_from_halt() {
+    let mut bus = Bus::new();
+    let mut cpu = setup_irq_entry(&mut bus);
+    cpu.halted = true;
+    cpu.irq_pending = true;
+
+    let cycles = cpu.step(&mut bus) as u64;
+    // Wake cycle replaces the discarded fetch
+    let expected = 1 + 1 + 1 + 3 * Bus::RAM_WRITE_CYCLES + Bus::FLASH_READ_CYCLES;
+    assert_eq!(cycles, expected);
+    assert!(!cpu.halted);
+    assert_eq!(cpu.pc, 0x38);
+}
+
+#[test]
+fn test_nmi_acceptance_matches_irq_cost() {
+    let mut irq_bus = Bus::new();
+    let mut irq_cpu = setup_irq_entry(&mut irq_bus);
+    irq_cpu.irq_pending = true;
+    let irq_cycles = irq_cpu.step(&mut irq_bus);
+
+    let mut bus = Bus::new();
+    let mut cpu = setup_irq_entry(&mut bus);
+    cpu.nmi_pending = true;
+    let cycles = cpu.step(&mut bus);
+
+    assert_eq!(cycles, irq_cycles);
+    assert_eq!(cpu.pc, 0x66);
+    assert_eq!(cpu.sp(), 0xD1A87B);
+    assert_eq!(bus.peek_byte(0xD1A87B), 0x00);
+    assert_eq!(bus.peek_byte(0xD1A87D), 0xD0);
+    // IFF1 is saved in IFF2 for RETN
+    assert!(!cpu.iff1);
+    assert!(cpu.iff2);
+}
+
+#[test]
+fn test_nmi_from_z80_mode_with_madl_enters_adl() {
+    let mut bus = Bus::new();
+    let mut cpu = Cpu::new();
+    cpu.adl = false;
+    cpu.madl = true;
+    cpu.mbase = 0xD0;
+    cpu.pc = 0x0100;
+    cpu.set_sp_both(0xD1A87E);
+    bus.poke_byte(0xD00100, 0x00);
+    cpu.init_prefetch(&mut bus);
+    cpu.nmi_pending = true;
+
+    cpu.step(&mut bus);
+    assert!(cpu.adl);
+    assert_eq!(cpu.pc, 0x66);
+    // Mixed-mode entry: PCH, PCL on SPS, then the flag byte on SPL
+    assert_eq!(bus.peek_byte(0xD1A87D), 0b10, "flag byte records MADL=1, ADL=0");
+}
+
+#[test]
+fn test_irq_masked_for_instruction_after_ei() {
+    let mut bus = Bus::new();
+    let mut cpu = setup_irq_entry(&mut bus);
+    cpu.iff1 = false;
+    cpu.iff2 = false;
+    // EI ; NOP ; NOP
+    bus.poke_byte(0xD00000, 0xFB);
+    bus.poke_byte(0xD00001, 0x00);
+    bus.poke_byte(0xD00002, 0x00);
+    cpu.init_prefetch(&mut bus);
+
+    cpu.step(&mut bus);
+    cpu.irq_pending = true;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0xD00002, "instruction after EI runs with IRQs masked");
+    assert!(cpu.irq_pending);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x38);
+}
+
+#[test]
+fn test_ei_halt_enables_interrupts_as_cpu_halts() {
+    let mut bus = Bus::new();
+    let mut cpu = setup_irq_entry(&mut bus);
+    cpu.iff1 = false;
+    cpu.iff2 = false;
+    // EI ; HALT
+    bus.poke_byte(0xD00000, 0xFB);
+    bus.poke_byte(0xD00001, 0x76);
+    cpu.init_prefetch(&mut bus);
+
+    cpu.step(&mut bus);
+    // IRQ arrives inside the EI delay window
+    cpu.irq_pending = true;
+    cpu.step(&mut bus);
+    assert!(cpu.halted);
+    assert!(cpu.iff1, "HALT completes the EI delay so the halt is wakeable");
+
+    cpu.step(&mut bus);
+    assert!(!cpu.halted);
+    assert_eq!(cpu.pc, 0x38);
+    assert_eq!(bus.peek_byte(0xD1A87B), 0x02, "return address is after HALT");
+}
+
+#[test]
+fn test_di_halt_stays_masked() {
+    let mut bus = Bus::new();
+    let mut cpu = setup_irq_entry(&mut bus);
+    // DI ; HALT
+    bus.poke_byte(0xD00000, 0xF3);
+    bus.poke_byte(0xD00001, 0x76);
+    cpu.init_prefetch(&mut bus);
+
+    cpu.step(&mut bus);
+    cpu.step(&mut bus);
+    cpu.irq_pending = true;
+    cpu.step(&mut bus);
+    assert!(cpu.halted);
+    assert!(!cpu.iff1);
+}
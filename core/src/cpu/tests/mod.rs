@@ -2,8 +2,11 @@
 //!
 //! Test suite for the eZ80 CPU implementation, organized into:
 //! - instructions.rs: Tests for individual instructions and instruction families
+//! - opcodes.rs: Opcode metadata table checked against the executor
 //! - modes.rs: Tests for ADL mode and Z80 mode specific behavior
 //! - parity.rs: Comprehensive CEmu parity tests for flag and register behavior
+//! - prefetch.rs: Byte-level instruction fetch streams compared against CEmu
+//! - suffix.rs: Every suffix against each L/IL-dependent instruction class
 //!
 //! # References
 //! - eZ80 CPU User Manual (Zilog UM0077)
@@ -14,7 +17,10 @@ use crate::bus::Bus;
 
 mod instructions;
 mod modes;
+mod opcodes;
 mod parity;
+mod prefetch;
+mod suffix;
 
 // ========== Test Helpers ==========
 
@@ -993,3 +993,167 @@ fn test_z80_mode_ex_sp_ix_uses_mbase() {
     assert_eq!(bus.peek_byte(0xD04000), 0x34, "Stack low byte should be old IX low");
     assert_eq!(bus.peek_byte(0xD04001), 0x12, "Stack high byte should be old IX high");
 }
+
+#[test]
+fn test_z80_mode_ld_hl_nn_wraps_at_page_end() {
+    // LD HL,(0xFFFF) in Z80 mode reads the high byte from MBASE:0000, not the next page
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    bus.poke_byte(0xD0FFFF, 0x34);
+    bus.poke_byte(0xD00000, 0x12);
+    bus.poke_byte(0xD10000, 0xEE);
+
+    // 2A FF FF - LD HL,(nn)
+    bus.poke_byte(0xD00100, 0x2A);
+    bus.poke_byte(0xD00101, 0xFF);
+    bus.poke_byte(0xD00102, 0xFF);
+    setup_z80_mode_with_prefetch(&mut cpu, &mut bus);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.hl & 0xFFFF, 0x1234);
+}
+
+#[test]
+fn test_z80_mode_ld_nn_hl_wraps_at_page_end() {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+
+    // 22 FF FF - LD (nn),HL
+    bus.poke_byte(0xD00100, 0x22);
+    bus.poke_byte(0xD00101, 0xFF);
+    bus.poke_byte(0xD00102, 0xFF);
+    setup_z80_mode_with_prefetch(&mut cpu, &mut bus);
+    cpu.hl = 0xBEEF;
+    cpu.step(&mut bus);
+
+    assert_eq!(bus.peek_byte(0xD0FFFF), 0xEF);
+    assert_eq!(bus.peek_byte(0xD00000), 0xBE, "high byte wraps within the MBASE page");
+    assert_eq!(bus.peek_byte(0xD10000), 0x00);
+}
+
+#[test]
+fn test_z80_mode_ex_sp_hl_wraps_at_page_end() {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    bus.poke_byte(0xD0FFFF, 0x78);
+    bus.poke_byte(0xD00000, 0x56);
+
+    // E3 - EX (SP),HL
+    bus.poke_byte(0xD00100, 0xE3);
+    setup_z80_mode_with_prefetch(&mut cpu, &mut bus);
+    cpu.hl = 0x1234;
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.hl & 0xFFFF, 0x5678);
+    assert_eq!(bus.peek_byte(0xD0FFFF), 0x34);
+    assert_eq!(bus.peek_byte(0xD00000), 0x12);
+}
+
+#[test]
+fn test_sis_suffix_word_access_wraps_at_page_end() {
+    // .SIS in ADL mode forces a 16-bit data access, which must also wrap in the page
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    bus.poke_byte(0xD0FFFF, 0xCD);
+    bus.poke_byte(0xD00000, 0xAB);
+
+    // 40 2A FF FF - LD.SIS HL,(nn): 16-bit immediate, 16-bit data
+    bus.poke_byte(0xD00100, 0x40);
+    bus.poke_byte(0xD00101, 0x2A);
+    bus.poke_byte(0xD00102, 0xFF);
+    bus.poke_byte(0xD00103, 0xFF);
+    cpu.adl = true;
+    cpu.mbase = 0xD0;
+    cpu.pc = 0xD00100;
+    cpu.init_prefetch(&mut bus);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.hl & 0xFFFF, 0xABCD);
+}
+
+
+#[test]
+fn test_jp_lil_from_z80_prefetches_in_adl_space() {
+    // JP.LIL out of Z80 code must fetch the target as a 24-bit address,
+    // not as MBASE:target (the prefetch happens after the mode switch)
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    bus.poke_byte(0xD20000, 0x3C); // INC A at the real target
+    bus.poke_byte(0xD00000, 0x04); // INC B at MBASE:0000 (wrong target)
+
+    // 5B C3 00 00 D2 - JP.LIL 0xD20000
+    for (i, &b) in [0x5B, 0xC3, 0x00, 0x00, 0xD2].iter().enumerate() {
+        bus.poke_byte(0xD00100 + i as u32, b);
+    }
+    setup_z80_mode_with_prefetch(&mut cpu, &mut bus);
+    cpu.a = 0;
+    cpu.bc = 0;
+    cpu.step(&mut bus);
+    assert!(cpu.adl);
+    assert_eq!(cpu.pc, 0xD20000);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.a, 1, "INC A at 0xD20000 should execute");
+    assert_eq!(cpu.b(), 0);
+}
+
+#[test]
+fn test_jp_sis_from_adl_prefetches_in_mbase_page() {
+    // JP.SIS out of ADL code lands in Z80 mode, so the target is MBASE:nn
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    bus.poke_byte(0xD02000, 0x3C); // INC A
+
+    // 40 C3 00 20 - JP.SIS 0x2000
+    for (i, &b) in [0x40, 0xC3, 0x00, 0x20].iter().enumerate() {
+        bus.poke_byte(0xD00100 + i as u32, b);
+    }
+    cpu.adl = true;
+    cpu.mbase = 0xD0;
+    cpu.pc = 0xD00100;
+    cpu.init_prefetch(&mut bus);
+    cpu.a = 0;
+    cpu.step(&mut bus);
+    assert!(!cpu.adl);
+    assert_eq!(cpu.pc, 0x2000);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.a, 1, "INC A at MBASE:2000 should execute");
+    assert_eq!(cpu.pc, 0x2001);
+}
+
+#[test]
+fn test_z80_mode_index_displacement_wraps_in_data_page() {
+    // (IX+d) in Z80 mode: the 16-bit sum wraps, then MBASE is applied
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    bus.poke_byte(0xD00002, 0x5A);
+
+    // DD 7E 04 - LD A,(IX+4)
+    for (i, &b) in [0xDD, 0x7E, 0x04].iter().enumerate() {
+        bus.poke_byte(0xD00100 + i as u32, b);
+    }
+    setup_z80_mode_with_prefetch(&mut cpu, &mut bus);
+    cpu.ix = 0xFFFE;
+    step_full(&mut cpu, &mut bus);
+
+    assert_eq!(cpu.a, 0x5A);
+}
+
+#[test]
+fn test_z80_mode_push_wraps_sp_in_data_page() {
+    // PUSH with SPS=0x0001 in Z80 mode wraps to MBASE:FFFF, never leaving the page
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+
+    // C5 - PUSH BC
+    bus.poke_byte(0xD00100, 0xC5);
+    setup_z80_mode_with_prefetch(&mut cpu, &mut bus);
+    cpu.set_sp_both(0x0001);
+    cpu.bc = 0x1234;
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.sp(), 0xFFFF);
+    assert_eq!(bus.peek_byte(0xD00000), 0x12);
+    assert_eq!(bus.peek_byte(0xD0FFFF), 0x34);
+}
@@ -0,0 +1,184 @@
+//! Opcode metadata checked against the executor
+//!
+//! Every opcode on every page is stepped in ADL and Z80 mode, bare and under
+//! each suffix, and the PC advance and changed flags are compared against
+//! what `opcodes::decode` reports for the same bytes. Cycle counts are
+//! checked in ADL mode only, the setting `OpInfo::cycles` describes.
+
+use super::*;
+use crate::cpu::opcodes::{decode, info, suffix_modes, Page};
+
+const CODE: u32 = 0xD00100;
+
+/// Instruction bytes for `opcode` on `page`, operand bytes zeroed
+fn encode(page: Page, prefix: u8, opcode: u8) -> Vec<u8> {
+    let mut bytes = match page {
+        Page::Main => vec![opcode],
+        Page::Cb => vec![0xCB, opcode],
+        Page::Ed => vec![0xED, opcode],
+        Page::Index => vec![prefix, opcode],
+        Page::IndexCb => vec![prefix, 0xCB, 0x00, opcode],
+    };
+    bytes.extend_from_slice(&[0; 4]);
+    bytes
+}
+
+/// Step `code` once; returns (PC advance, F before, F after)
+fn run(code: &[u8], adl: bool, f: u8) -> (u32, u8, u8) {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    for (i, &b) in code.iter().enumerate() {
+        bus.poke_byte(CODE + i as u32, b);
+    }
+    cpu.adl = adl;
+    cpu.mbase = 0xD0;
+    cpu.pc = if adl { CODE } else { CODE & 0xFFFF };
+    cpu.set_sp_both(if adl { 0xD03000 } else { 0x3000 });
+    // Pointers into RAM, small counts so repeating block ops finish quickly
+    cpu.bc = 0x000203;
+    cpu.de = 0xD02100;
+    cpu.hl = 0xD02000;
+    cpu.ix = 0xD02200;
+    cpu.iy = 0xD02300;
+    if !adl {
+        cpu.de &= 0xFFFF;
+        cpu.hl &= 0xFFFF;
+        cpu.ix &= 0xFFFF;
+        cpu.iy &= 0xFFFF;
+    }
+    cpu.f = f;
+    let start = cpu.pc;
+    step_full(&mut cpu, &mut bus);
+    (cpu.pc.wrapping_sub(start) & 0xFFFF, f, cpu.f)
+}
+
+/// Step `opcode` in every mode, appending any disagreement to `errors`
+fn check(page: Page, prefix: u8, opcode: u8, errors: &mut Vec<String>) {
+    for adl in [true, false] {
+        for suffix in [None, Some(0x40), Some(0x49), Some(0x52), Some(0x5B)] {
+            let mut code: Vec<u8> = suffix.into_iter().collect();
+            code.extend(encode(page, prefix, opcode));
+            let decoded = decode(&code, adl).unwrap();
+            let context = format!("{:02X?} adl={}", &code[..decoded.length], adl);
+            if decoded.page != page {
+                errors.push(format!("{}: decoded as {:?}", context, decoded.page));
+            }
+
+            for f in [0x00, 0xFF] {
+                let (advance, before, after) = run(&code, adl, f);
+                if !decoded.info.branch && advance != decoded.length as u32 {
+                    errors.push(format!("{}: ran {} bytes, decoded {}", context, advance, decoded.length));
+                }
+                if (before ^ after) & !decoded.info.flags != 0 {
+                    errors.push(format!(
+                        "{}: F {:02X} -> {:02X} outside {:02X}", context, before, after, decoded.info.flags
+                    ));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_main_and_cb_pages_match_executor() {
+    let mut errors = Vec::new();
+    for opcode in 0..=0xFFu8 {
+        if !matches!(opcode, 0xCB | 0xDD | 0xED | 0xFD) && suffix_modes(opcode).is_none() {
+            check(Page::Main, 0, opcode, &mut errors);
+        }
+        check(Page::Cb, 0, opcode, &mut errors);
+    }
+    assert!(errors.is_empty(), "{}", errors.join("\n"));
+}
+
+#[test]
+fn test_ed_page_matches_executor() {
+    let mut errors = Vec::new();
+    for opcode in 0..=0xFFu8 {
+        check(Page::Ed, 0, opcode, &mut errors);
+    }
+    assert!(errors.is_empty(), "{}", errors.join("\n"));
+}
+
+#[test]
+fn test_index_pages_match_executor() {
+    let mut errors = Vec::new();
+    for prefix in [0xDD, 0xFD] {
+        for opcode in 0..=0xFFu8 {
+            if !matches!(opcode, 0xCB | 0xDD | 0xED | 0xFD) {
+                check(Page::Index, prefix, opcode, &mut errors);
+            }
+            check(Page::IndexCb, prefix, opcode, &mut errors);
+        }
+    }
+    assert!(errors.is_empty(), "{}", errors.join("\n"));
+}
+
+#[test]
+fn test_index_prefix_before_ed_runs_ed() {
+    // DD ED 44 is NEG: the ED byte is consumed, not executed as a second step
+    let (advance, _, _) = run(&[0xDD, 0xED, 0x44, 0x00], true, 0);
+    assert_eq!(advance, 3);
+    assert_eq!(decode(&[0xDD, 0xED, 0x44], true).unwrap().info.mnemonic, "NEG");
+}
+
+/// Cycles for one step of `code` in ADL mode, cheapest of both flag states
+/// and of a B or BC count of 1 (condition false, last block iteration)
+fn base_cycles(code: &[u8]) -> u32 {
+    let mut best = u32::MAX;
+    for f in [0x00, 0xFF] {
+        for bc in [0x000101, 0x000001] {
+            let mut cpu = Cpu::new();
+            let mut bus = Bus::new();
+            for (i, &b) in code.iter().enumerate() {
+                bus.poke_byte(CODE + i as u32, b);
+            }
+            cpu.adl = true;
+            cpu.mbase = 0xD0;
+            cpu.pc = CODE;
+            cpu.set_sp_both(0xD03000);
+            cpu.bc = bc;
+            cpu.de = 0xD02100;
+            cpu.hl = 0xD02000;
+            cpu.ix = 0xD02200;
+            cpu.iy = 0xD02300;
+            cpu.f = f;
+            best = best.min(step_full(&mut cpu, &mut bus));
+        }
+    }
+    best
+}
+
+#[test]
+fn test_base_cycles_match_executor() {
+    let mut errors = Vec::new();
+    for (page, prefix) in [(Page::Main, 0), (Page::Cb, 0), (Page::Ed, 0), (Page::Index, 0xDD),
+        (Page::Index, 0xFD), (Page::IndexCb, 0xDD), (Page::IndexCb, 0xFD)]
+    {
+        for opcode in 0..=0xFFu8 {
+            let prefix_byte = matches!(opcode, 0xCB | 0xDD | 0xED | 0xFD)
+                && matches!(page, Page::Main | Page::Index);
+            if prefix_byte || (page == Page::Main && suffix_modes(opcode).is_some()) {
+                continue;
+            }
+            let code = encode(page, prefix, opcode);
+            let want = info(page, opcode).cycles as u32;
+            let ran = base_cycles(&code);
+            if ran != want {
+                errors.push(format!("{:02X?}: ran {} cycles, table says {}", &code[..4], ran, want));
+            }
+        }
+    }
+    assert!(errors.is_empty(), "{}", errors.join("\n"));
+}
+
+#[test]
+fn test_base_cycles_known_opcodes() {
+    // NOP, LD A,n, JP nn, CALL nn, RET, PUSH BC: one RAM fetch is 4 cycles
+    for (opcode, cycles) in [(0x00, 4), (0x3E, 8), (0xC3, 23), (0xCD, 28), (0xC9, 27), (0xC5, 10)] {
+        assert_eq!(info(Page::Main, opcode).cycles, cycles, "opcode {:02X}", opcode);
+    }
+    assert_eq!(info(Page::Cb, 0x46).cycles, 12); // BIT 0,(HL)
+    assert_eq!(info(Page::Ed, 0x44).cycles, 8); // NEG
+    assert_eq!(info(Page::IndexCb, 0x06).cycles, 23); // RLC (IX+d)
+}
@@ -0,0 +1,146 @@
+//! Prefetch stream tests
+//!
+//! Each test records the address of every instruction-stream fetch and
+//! compares it against the sequence CEmu's cpu_fetch_byte/cpu_prefetch
+//! produce for the same code. The buffer always holds the byte at PC, so a
+//! taken branch reads the target exactly once and never the byte after the
+//! branch operand.
+//!
+//! # References
+//! - CEmu cpu.c: cpu_prefetch, cpu_fetch_byte, cpu_fetch_word_no_prefetch
+
+use super::*;
+
+const BASE: u32 = 0xD00000;
+
+/// ADL-mode CPU at BASE with `code` in RAM and fetch tracing enabled.
+/// The prefetch buffer is left stale so `step` loads it on demand.
+fn setup(code: &[u8]) -> (Cpu, Bus) {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    for (i, &b) in code.iter().enumerate() {
+        bus.poke_byte(BASE + i as u32, b);
+    }
+    cpu.adl = true;
+    cpu.pc = BASE;
+    cpu.set_sp_both(0xD1A87E);
+    bus.enable_fetch_trace();
+    (cpu, bus)
+}
+
+#[test]
+fn test_sequential_fetch_reads_each_byte_once() {
+    let (mut cpu, mut bus) = setup(&[0x00, 0x00, 0x00]);
+    for _ in 0..3 {
+        cpu.step(&mut bus);
+    }
+    assert_eq!(bus.take_fetch_trace(), vec![BASE, BASE + 1, BASE + 2, BASE + 3]);
+    assert_eq!(cpu.prefetch, bus.peek_byte(cpu.pc));
+}
+
+#[test]
+fn test_jp_nn_skips_byte_after_operand() {
+    // JP 0xD00100
+    let (mut cpu, mut bus) = setup(&[0xC3, 0x00, 0x01, 0xD0]);
+    bus.poke_byte(0xD00100, 0x3C); // INC A
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0xD00100);
+    assert_eq!(
+        bus.take_fetch_trace(),
+        vec![BASE, BASE + 1, BASE + 2, BASE + 3, 0xD00100]
+    );
+    assert_eq!(cpu.prefetch, 0x3C);
+}
+
+#[test]
+fn test_jp_nn_not_taken_continues_stream() {
+    // JP NZ with Z set falls through to the next instruction
+    let (mut cpu, mut bus) = setup(&[0xC2, 0x00, 0x01, 0xD0, 0x00]);
+    cpu.f = flags::Z;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, BASE + 4);
+    assert_eq!(
+        bus.take_fetch_trace(),
+        vec![BASE, BASE + 1, BASE + 2, BASE + 3, BASE + 4]
+    );
+}
+
+#[test]
+fn test_jr_taken_reads_target() {
+    // JR +0x10
+    let (mut cpu, mut bus) = setup(&[0x18, 0x10]);
+    cpu.step(&mut bus);
+    let target = BASE + 2 + 0x10;
+    assert_eq!(cpu.pc, target);
+    assert_eq!(bus.take_fetch_trace(), vec![BASE, BASE + 1, BASE + 2, target]);
+}
+
+#[test]
+fn test_jp_hl_discards_next_byte() {
+    // JP (HL)
+    let (mut cpu, mut bus) = setup(&[0xE9]);
+    cpu.hl = 0xD00200;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0xD00200);
+    assert_eq!(bus.take_fetch_trace(), vec![BASE, BASE + 1, BASE + 2, 0xD00200]);
+}
+
+#[test]
+fn test_irq_entry_discards_then_loads_vector() {
+    let (mut cpu, mut bus) = setup(&[0x00]);
+    cpu.iff1 = true;
+    cpu.im = InterruptMode::Mode1;
+    cpu.irq_pending = true;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x38);
+    assert_eq!(bus.take_fetch_trace(), vec![BASE, BASE + 1, 0x38]);
+}
+
+#[test]
+fn test_irq_from_halt_skips_discard() {
+    let (mut cpu, mut bus) = setup(&[0x76]); // HALT
+    cpu.im = InterruptMode::Mode1;
+    cpu.step(&mut bus);
+    assert!(cpu.halted);
+    // Halted steps perform no fetches
+    cpu.step(&mut bus);
+    cpu.iff1 = true;
+    cpu.irq_pending = true;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x38);
+    assert_eq!(bus.take_fetch_trace(), vec![BASE, BASE + 1, 0x38]);
+}
+
+#[test]
+fn test_lazy_prefetch_matches_explicit_init() {
+    let code = [0x3E, 0x42, 0xC3, 0x00, 0x01, 0xD0];
+
+    let (mut lazy, mut lazy_bus) = setup(&code);
+    let (mut eager, mut eager_bus) = setup(&code);
+    assert!(!lazy.prefetch_valid());
+    eager.init_prefetch(&mut eager_bus);
+
+    // The on-demand load is charged outside the first step, like init_prefetch
+    let before = lazy_bus.total_cycles();
+    let lazy_cycles = lazy.step(&mut lazy_bus) + lazy.step(&mut lazy_bus);
+    let eager_cycles = eager.step(&mut eager_bus) + eager.step(&mut eager_bus);
+    assert_eq!(lazy_cycles, eager_cycles);
+    assert_eq!(lazy_bus.total_cycles() - before, eager_bus.total_cycles());
+    assert_eq!(lazy.pc, eager.pc);
+    assert_eq!(lazy_bus.take_fetch_trace(), eager_bus.take_fetch_trace());
+}
+
+#[test]
+fn test_invalidate_prefetch_reloads_at_new_pc() {
+    let (mut cpu, mut bus) = setup(&[0x00]);
+    bus.poke_byte(0xD00300, 0x3C); // INC A
+    cpu.step(&mut bus);
+    bus.take_fetch_trace();
+
+    cpu.a = 0;
+    cpu.pc = 0xD00300;
+    cpu.invalidate_prefetch();
+    cpu.step(&mut bus);
+    assert_eq!(cpu.a, 1);
+    assert_eq!(bus.take_fetch_trace(), vec![0xD00300, 0xD00301]);
+}
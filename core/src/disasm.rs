@@ -1,7 +1,12 @@
 //! eZ80 Disassembler
 //!
 //! Provides instruction disassembly for trace comparison and debugging.
-//! Handles all eZ80 prefix combinations and addressing modes.
+//! Handles all eZ80 prefix combinations and addressing modes. Instruction
+//! lengths come from the opcode table the CPU is tested against
+//! (`cpu::opcodes`), so traces never split an instruction differently from
+//! the executor.
+
+use crate::cpu::opcodes::{self, suffix_modes, suffix_name};
 
 /// Result of disassembling an instruction
 #[derive(Debug, Clone)]
@@ -32,6 +37,8 @@ pub fn disassemble(opcode: &[u8], adl: bool) -> DisasmResult {
     }
 
     let (mnemonic, length) = disasm_main(opcode, adl);
+    // Truncated input keeps the partial length the formatter consumed
+    let length = opcodes::decode(opcode, adl).map_or(length, |d| d.length);
     let bytes = opcode[..length.min(opcode.len())]
         .iter()
         .map(|b| format!("{:02X}", b))
@@ -45,48 +52,30 @@ pub fn disassemble(opcode: &[u8], adl: bool) -> DisasmResult {
     }
 }
 
+/// Disassemble `count` instructions from `addr`, reading memory through
+/// `peek`, one line each: "ADDRESS: BYTES  MNEMONIC"
+pub fn listing(peek: impl Fn(u32) -> u8, addr: u32, count: usize, adl: bool) -> String {
+    use std::fmt::Write;
+
+    let mut text = String::new();
+    let mut pc = addr & 0xFFFFFF;
+    for _ in 0..count {
+        // Longest eZ80 instruction: suffix + prefix + opcode + 3 operand bytes
+        let bytes: Vec<u8> = (0..6).map(|i| peek((pc + i) & 0xFFFFFF)).collect();
+        let inst = disassemble(&bytes, adl);
+        let _ = writeln!(text, "{:06X}: {:<17} {}", pc, inst.bytes, inst.mnemonic);
+        pc = (pc + inst.length.max(1) as u32) & 0xFFFFFF;
+    }
+    text
+}
+
 /// Main disassembly dispatcher
 fn disasm_main(opcode: &[u8], adl: bool) -> (String, usize) {
     let op = opcode[0];
 
     match op {
         // Suffix opcodes (.SIS, .LIS, .SIL, .LIL)
-        0x40 => {
-            // .SIS suffix
-            if opcode.len() > 1 {
-                let (inner, inner_len) = disasm_with_suffix(opcode, 1, false, false);
-                (format!("{}.SIS", inner), 1 + inner_len)
-            } else {
-                ("NOP.SIS".to_string(), 1)
-            }
-        }
-        0x49 => {
-            // .LIS suffix
-            if opcode.len() > 1 {
-                let (inner, inner_len) = disasm_with_suffix(opcode, 1, true, false);
-                (format!("{}.LIS", inner), 1 + inner_len)
-            } else {
-                ("NOP.LIS".to_string(), 1)
-            }
-        }
-        0x52 => {
-            // .SIL suffix
-            if opcode.len() > 1 {
-                let (inner, inner_len) = disasm_with_suffix(opcode, 1, false, true);
-                (format!("{}.SIL", inner), 1 + inner_len)
-            } else {
-                ("NOP.SIL".to_string(), 1)
-            }
-        }
-        0x5B => {
-            // .LIL suffix
-            if opcode.len() > 1 {
-                let (inner, inner_len) = disasm_with_suffix(opcode, 1, true, true);
-                (format!("{}.LIL", inner), 1 + inner_len)
-            } else {
-                ("NOP.LIL".to_string(), 1)
-            }
-        }
+        op if suffix_modes(op).is_some() => disasm_suffix(opcode, op),
         // Index register prefixes
         0xDD => disasm_dd(opcode, adl),
         0xFD => disasm_fd(opcode, adl),
@@ -99,53 +88,31 @@ fn disasm_main(opcode: &[u8], adl: bool) -> (String, usize) {
     }
 }
 
-/// Disassemble with an explicit suffix (handles next byte after suffix)
-fn disasm_with_suffix(opcode: &[u8], offset: usize, il: bool, l: bool) -> (String, usize) {
-    if offset >= opcode.len() {
-        return ("???".to_string(), 0);
-    }
-
-    let op = opcode[offset];
-    match op {
-        0xDD => {
-            let (inner, len) = disasm_dd_inner(&opcode[offset..], il, l);
-            (inner, len)
-        }
-        0xFD => {
-            let (inner, len) = disasm_fd_inner(&opcode[offset..], il, l);
-            (inner, len)
-        }
-        0xED => {
-            let (inner, len) = disasm_ed_inner(&opcode[offset..], il, l);
-            (inner, len)
-        }
-        0xCB => {
-            let (inner, len) = disasm_cb(opcode, offset + 1);
-            (inner, len)
-        }
-        _ => {
-            // Use modified ADL for regular instructions
-            disasm_unprefixed(&opcode[offset..], l)
-        }
+/// Suffixed instruction: the suffix's IL sets the immediate width of the
+/// instruction that follows; a later suffix overrides an earlier one
+fn disasm_suffix(opcode: &[u8], op: u8) -> (String, usize) {
+    let (_, il) = suffix_modes(op).unwrap_or_default();
+    let name = suffix_name(op).unwrap_or_default();
+    if opcode.len() > 1 {
+        let (inner, inner_len) = disasm_main(&opcode[1..], il);
+        (format!("{}{}", inner, name), 1 + inner_len)
+    } else {
+        (format!("NOP{}", name), 1)
     }
 }
 
 /// DD prefix (IX instructions)
-fn disasm_dd(opcode: &[u8], adl: bool) -> (String, usize) {
-    disasm_dd_inner(opcode, adl, adl)
-}
-
-fn disasm_dd_inner(opcode: &[u8], il: bool, l: bool) -> (String, usize) {
+fn disasm_dd(opcode: &[u8], il: bool) -> (String, usize) {
     if opcode.len() < 2 {
         return ("DB DDh".to_string(), 1);
     }
 
     let op = opcode[1];
     match op {
-        0xCB => disasm_ddcb(opcode, il, l),
+        0xCB => disasm_ddcb(opcode),
         0x21 => {
             // LD IX,nn
-            let (val, size) = read_imm_word(&opcode[2..], l);
+            let (val, size) = read_imm_word(&opcode[2..], il);
             (format!("LD IX,{}", val), 2 + size)
         }
         0x22 => {
@@ -182,12 +149,12 @@ fn disasm_dd_inner(opcode: &[u8], il: bool, l: bool) -> (String, usize) {
             }
         }
         0x31 => {
-            // eZ80: LD IX,(IX+d)
+            // eZ80: LD IY,(IX+d)
             if opcode.len() >= 3 {
                 let d = opcode[2] as i8;
-                (format!("LD IX,(IX{:+})", d), 3)
+                (format!("LD IY,(IX{:+})", d), 3)
             } else {
-                ("LD IX,(IX+?)".to_string(), 2)
+                ("LD IY,(IX+?)".to_string(), 2)
             }
         }
         0x34 => {
@@ -217,29 +184,24 @@ fn disasm_dd_inner(opcode: &[u8], il: bool, l: bool) -> (String, usize) {
                 ("LD (IX+?),?".to_string(), 2)
             }
         }
-        0x37 => {
-            // eZ80: LD IX,(IX+d) long form
-            if opcode.len() >= 3 {
-                let d = opcode[2] as i8;
-                (format!("LD IX,(IX{:+})", d), 3)
-            } else {
-                ("LD IX,(IX+?)".to_string(), 2)
-            }
-        }
         0x3E => {
-            // eZ80: LD (IX+d),IX
+            // eZ80: LD (IX+d),IY
             if opcode.len() >= 3 {
                 let d = opcode[2] as i8;
-                (format!("LD (IX{:+}),IX", d), 3)
+                (format!("LD (IX{:+}),IY", d), 3)
             } else {
-                ("LD (IX+?),IX".to_string(), 2)
+                ("LD (IX+?),IY".to_string(), 2)
             }
         }
+        // eZ80: LD rp3,(IX+d) / LD (IX+d),rp3
+        op if op & 0xC7 == 0x07 => disasm_index_rp3(opcode, op, "IX"),
         0xE1 => ("POP IX".to_string(), 2),
         0xE3 => ("EX (SP),IX".to_string(), 2),
         0xE5 => ("PUSH IX".to_string(), 2),
         0xE9 => ("JP (IX)".to_string(), 2),
         0xF9 => ("LD SP,IX".to_string(), 2),
+        // HALT ignores the prefix
+        0x76 => ("HALT".to_string(), 2),
         // 8-bit loads with IX+d
         op if op & 0xC0 == 0x40 => {
             // LD r,(IX+d) or LD (IX+d),r or LD IXH/IXL,r
@@ -249,7 +211,11 @@ fn disasm_dd_inner(opcode: &[u8], il: bool, l: bool) -> (String, usize) {
         op if op >= 0x80 && op <= 0xBF => {
             disasm_dd_alu(opcode, op)
         }
-        _ => (format!("DB DDh,{:02X}h", op), 2),
+        // The prefix doesn't change this instruction (chained DD/FD and ED included)
+        _ => {
+            let (inner, inner_len) = disasm_main(&opcode[1..], il);
+            (inner, 1 + inner_len)
+        }
     }
 }
 
@@ -308,21 +274,37 @@ fn disasm_dd_alu(opcode: &[u8], op: u8) -> (String, usize) {
     }
 }
 
-/// FD prefix (IY instructions)
-fn disasm_fd(opcode: &[u8], adl: bool) -> (String, usize) {
-    disasm_fd_inner(opcode, adl, adl)
+/// DD/FD z=7: LD rp3,(IX+d) / LD (IX+d),rp3, where rp3 3 is the index
+/// register itself
+fn disasm_index_rp3(opcode: &[u8], op: u8, index: &str) -> (String, usize) {
+    let rp = match (op >> 4) & 3 {
+        0 => "BC",
+        1 => "DE",
+        2 => "HL",
+        _ => index,
+    };
+    if opcode.len() < 3 {
+        return (format!("LD {},({}+?)", rp, index), 2);
+    }
+    let mem = format!("({}{:+})", index, opcode[2] as i8);
+    if op & 0x08 == 0 {
+        (format!("LD {},{}", rp, mem), 3)
+    } else {
+        (format!("LD {},{}", mem, rp), 3)
+    }
 }
 
-fn disasm_fd_inner(opcode: &[u8], il: bool, l: bool) -> (String, usize) {
+/// FD prefix (IY instructions)
+fn disasm_fd(opcode: &[u8], il: bool) -> (String, usize) {
     if opcode.len() < 2 {
         return ("DB FDh".to_string(), 1);
     }
 
     let op = opcode[1];
     match op {
-        0xCB => disasm_fdcb(opcode, il, l),
+        0xCB => disasm_fdcb(opcode),
         0x21 => {
-            let (val, size) = read_imm_word(&opcode[2..], l);
+            let (val, size) = read_imm_word(&opcode[2..], il);
             (format!("LD IY,{}", val), 2 + size)
         }
         0x22 => {
@@ -355,11 +337,12 @@ fn disasm_fd_inner(opcode: &[u8], il: bool, l: bool) -> (String, usize) {
             }
         }
         0x31 => {
+            // eZ80: LD IX,(IY+d)
             if opcode.len() >= 3 {
                 let d = opcode[2] as i8;
-                (format!("LD IY,(IY{:+})", d), 3)
+                (format!("LD IX,(IY{:+})", d), 3)
             } else {
-                ("LD IY,(IY+?)".to_string(), 2)
+                ("LD IX,(IY+?)".to_string(), 2)
             }
         }
         0x34 => {
@@ -387,23 +370,31 @@ fn disasm_fd_inner(opcode: &[u8], il: bool, l: bool) -> (String, usize) {
             }
         }
         0x3E => {
+            // eZ80: LD (IY+d),IX
             if opcode.len() >= 3 {
                 let d = opcode[2] as i8;
-                (format!("LD (IY{:+}),IY", d), 3)
+                (format!("LD (IY{:+}),IX", d), 3)
             } else {
-                ("LD (IY+?),IY".to_string(), 2)
+                ("LD (IY+?),IX".to_string(), 2)
             }
         }
+        // eZ80: LD rp3,(IY+d) / LD (IY+d),rp3
+        op if op & 0xC7 == 0x07 => disasm_index_rp3(opcode, op, "IY"),
         0xE1 => ("POP IY".to_string(), 2),
         0xE3 => ("EX (SP),IY".to_string(), 2),
         0xE5 => ("PUSH IY".to_string(), 2),
         0xE9 => ("JP (IY)".to_string(), 2),
         0xF9 => ("LD SP,IY".to_string(), 2),
+        0x76 => ("HALT".to_string(), 2),
         // 8-bit loads with IY+d
         op if op & 0xC0 == 0x40 => disasm_fd_ld(opcode, op),
         // Arithmetic with IY+d
         op if op >= 0x80 && op <= 0xBF => disasm_fd_alu(opcode, op),
-        _ => (format!("DB FDh,{:02X}h", op), 2),
+        // The prefix doesn't change this instruction (chained DD/FD and ED included)
+        _ => {
+            let (inner, inner_len) = disasm_main(&opcode[1..], il);
+            (inner, 1 + inner_len)
+        }
     }
 }
 
@@ -458,11 +449,7 @@ fn disasm_fd_alu(opcode: &[u8], op: u8) -> (String, usize) {
 }
 
 /// ED prefix instructions
-fn disasm_ed(opcode: &[u8], adl: bool) -> (String, usize) {
-    disasm_ed_inner(opcode, adl, adl)
-}
-
-fn disasm_ed_inner(opcode: &[u8], il: bool, _l: bool) -> (String, usize) {
+fn disasm_ed(opcode: &[u8], il: bool) -> (String, usize) {
     if opcode.len() < 2 {
         return ("DB EDh".to_string(), 1);
     }
@@ -483,7 +470,6 @@ fn disasm_ed_inner(opcode: &[u8], il: bool, _l: bool) -> (String, usize) {
         0x68 => ("IN L,(C)".to_string(), 2),
         0x69 => ("OUT (C),L".to_string(), 2),
         0x70 => ("IN F,(C)".to_string(), 2),
-        0x71 => ("OUT (C),0".to_string(), 2),
         0x78 => ("IN A,(C)".to_string(), 2),
         0x79 => ("OUT (C),A".to_string(), 2),
 
@@ -531,10 +517,26 @@ fn disasm_ed_inner(opcode: &[u8], il: bool, _l: bool) -> (String, usize) {
             (format!("LD SP,({})", addr), 2 + size)
         }
 
-        // NEG - note: some opcodes have different meanings in eZ80
-        // 0x4C, 0x5C, 0x6C, 0x7C are MLT in eZ80
-        // 0x74 is TSTIO in eZ80
-        0x44 | 0x54 | 0x64 => ("NEG".to_string(), 2),
+        // NEG - 0x54/0x64/0x74 (NEG aliases on Z80) are LEA IX,IY+d, TST A,n
+        // and TSTIO n on the eZ80; 0x4C, 0x5C, 0x6C, 0x7C are MLT
+        0x44 => ("NEG".to_string(), 2),
+        0x54 => {
+            // LEA IX,IY+d
+            if opcode.len() >= 3 {
+                let d = opcode[2] as i8;
+                (format!("LEA IX,IY{:+}", d), 3)
+            } else {
+                ("LEA IX,IY+?".to_string(), 2)
+            }
+        }
+        0x64 => {
+            // TST A,n
+            if opcode.len() >= 3 {
+                (format!("TST A,0x{:02X}", opcode[2]), 3)
+            } else {
+                ("TST A,?".to_string(), 2)
+            }
+        }
 
         // MLT instructions (eZ80-specific, these are NEG aliases on Z80)
         0x4C => ("MLT BC".to_string(), 2),
@@ -542,16 +544,25 @@ fn disasm_ed_inner(opcode: &[u8], il: bool, _l: bool) -> (String, usize) {
         0x6C => ("MLT HL".to_string(), 2),
         0x7C => ("MLT SP".to_string(), 2),
 
-        // RETN/RETI - note: 0x6D is LD MB,A in eZ80, 0x7D is STMIX
+        // RETN/RETI - note: 0x6D is LD MB,A in eZ80, 0x7D is STMIX; the Z80
+        // RETN aliases 0x5D/0x75 trap
         0x45 => ("RETN".to_string(), 2),
         0x4D => ("RETI".to_string(), 2),
-        0x55 | 0x5D | 0x75 => ("RETN".to_string(), 2), // alternate RETN encodings
+        0x55 => {
+            // LEA IY,IX+d
+            if opcode.len() >= 3 {
+                let d = opcode[2] as i8;
+                (format!("LEA IY,IX{:+}", d), 3)
+            } else {
+                ("LEA IY,IX+?".to_string(), 2)
+            }
+        }
 
-        // IM modes - note: some alternate encodings have different meanings in eZ80
-        // 0x66 is PEA IY+d, 0x6E is LD A,MB, 0x76 is SLP
-        0x46 | 0x4E => ("IM 0".to_string(), 2),
+        // IM modes - note: the Z80 aliases have different meanings in eZ80
+        // 0x4E traps, 0x66 is PEA IY+d, 0x6E is LD A,MB, 0x76 is SLP, 0x7E is RSMIX
+        0x46 => ("IM 0".to_string(), 2),
         0x56 => ("IM 1".to_string(), 2),
-        0x5E | 0x7E => ("IM 2".to_string(), 2),
+        0x5E => ("IM 2".to_string(), 2),
 
         // Special registers
         0x47 => ("LD I,A".to_string(), 2),
@@ -585,6 +596,29 @@ fn disasm_ed_inner(opcode: &[u8], il: bool, _l: bool) -> (String, usize) {
         0xBA => ("INDR".to_string(), 2),
         0xBB => ("OTDR".to_string(), 2),
 
+        // eZ80: block I/O through port C (INIM/OTIM families) and BC/DE
+        // (INI2/OUTI2 families)
+        0x82 => ("INIM".to_string(), 2),
+        0x83 => ("OTIM".to_string(), 2),
+        0x8A => ("INDM".to_string(), 2),
+        0x8B => ("OTDM".to_string(), 2),
+        0x92 => ("INIMR".to_string(), 2),
+        0x93 => ("OTIMR".to_string(), 2),
+        0x9A => ("INDMR".to_string(), 2),
+        0x9B => ("OTDMR".to_string(), 2),
+        0x84 => ("INI2".to_string(), 2),
+        0x8C => ("IND2".to_string(), 2),
+        0x94 => ("INI2R".to_string(), 2),
+        0x9C => ("IND2R".to_string(), 2),
+        0xA4 => ("OUTI2".to_string(), 2),
+        0xAC => ("OUTD2".to_string(), 2),
+        0xB4 => ("OTI2R".to_string(), 2),
+        0xBC => ("OTD2R".to_string(), 2),
+
+        // eZ80: LD I,HL / LD HL,I
+        0xC7 => ("LD I,HL".to_string(), 2),
+        0xD7 => ("LD HL,I".to_string(), 2),
+
         // eZ80: INIRX/OTIRX/INDRX/OTDRX
         0xC2 => ("INIRX".to_string(), 2),
         0xC3 => ("OTIRX".to_string(), 2),
@@ -821,7 +855,22 @@ fn disasm_ed_inner(opcode: &[u8], il: bool, _l: bool) -> (String, usize) {
 
         // eZ80: STMIX/RSMIX
         0x7D => ("STMIX".to_string(), 2),
-        0x7F => ("RSMIX".to_string(), 2),
+        0x7E => ("RSMIX".to_string(), 2),
+
+        // eZ80: TST A,r
+        op if op & 0xC7 == 0x04 => (format!("TST A,{}", reg8_name((op >> 3) & 7)), 2),
+
+        // eZ80: LD rp3,(HL) / LD (HL),rp3, LD IY,(HL) / LD (HL),IY
+        op if op & 0xC7 == 0x07 => {
+            let rp = ["BC", "DE", "HL", "IX"][((op >> 4) & 3) as usize];
+            if op & 0x08 == 0 {
+                (format!("LD {},(HL)", rp), 2)
+            } else {
+                (format!("LD (HL),{}", rp), 2)
+            }
+        }
+        0x31 => ("LD IY,(HL)".to_string(), 2),
+        0x3E => ("LD (HL),IY".to_string(), 2),
 
         _ => (format!("DB EDh,{:02X}h", op), 2),
     }
@@ -865,7 +914,7 @@ fn disasm_cb(opcode: &[u8], offset: usize) -> (String, usize) {
 }
 
 /// DD CB prefix (IX+d bit operations)
-fn disasm_ddcb(opcode: &[u8], _il: bool, _l: bool) -> (String, usize) {
+fn disasm_ddcb(opcode: &[u8]) -> (String, usize) {
     if opcode.len() < 4 {
         return ("DB DDh,CBh".to_string(), 2);
     }
@@ -920,7 +969,7 @@ fn disasm_ddcb(opcode: &[u8], _il: bool, _l: bool) -> (String, usize) {
 }
 
 /// FD CB prefix (IY+d bit operations)
-fn disasm_fdcb(opcode: &[u8], _il: bool, _l: bool) -> (String, usize) {
+fn disasm_fdcb(opcode: &[u8]) -> (String, usize) {
     if opcode.len() < 4 {
         return ("DB FDh,CBh".to_string(), 2);
     }
@@ -1471,6 +1520,19 @@ mod tests {
         assert_eq!(disassemble(&[0xC9], false).mnemonic, "RET");
     }
 
+    #[test]
+    fn test_listing() {
+        let memory = [0x3E, 0x05, 0x01, 0x56, 0x34, 0x12, 0xC9];
+        let peek = |addr: u32| memory.get(addr as usize - 0xD00000).copied().unwrap_or(0);
+        assert_eq!(
+            listing(peek, 0xD00000, 3, true),
+            "D00000: 3E 05             LD A,0x05\n\
+             D00002: 01 56 34 12       LD BC,0x123456\n\
+             D00006: C9                RET\n"
+        );
+        assert_eq!(listing(peek, 0xD00000, 0, true), "");
+    }
+
     #[test]
     fn test_ld_instructions() {
         // LD B,0x42
@@ -1505,8 +1567,14 @@ mod tests {
         assert_eq!(result.mnemonic, "OUT0 (0x01),A");
 
         // IM 2
-        let result = disassemble(&[0xED, 0x7E], false);
+        let result = disassemble(&[0xED, 0x5E], false);
         assert_eq!(result.mnemonic, "IM 2");
+
+        // eZ80 reuses the Z80 NEG/RETN aliases for 3-byte instructions
+        let result = disassemble(&[0xED, 0x54, 0x05], true);
+        assert_eq!((result.mnemonic.as_str(), result.length), ("LEA IX,IY+5", 3));
+        let result = disassemble(&[0xED, 0x64, 0x0F], true);
+        assert_eq!((result.mnemonic.as_str(), result.length), ("TST A,0x0F", 3));
     }
 
     #[test]
@@ -1527,6 +1595,35 @@ mod tests {
         assert!(result.mnemonic.contains(".SIS"));
     }
 
+    #[test]
+    fn test_formatter_agrees_with_opcode_table() {
+        use crate::cpu::opcodes::{decode, NONI_MNEMONIC};
+
+        let prefixes: [&[u8]; 7] = [&[], &[0xCB], &[0xED], &[0xDD], &[0xFD], &[0xDD, 0xCB, 0x01], &[0xFD, 0xCB, 0x01]];
+        for adl in [true, false] {
+            for suffix in [None, Some(0x40), Some(0x49), Some(0x52), Some(0x5B)] {
+                for prefix in prefixes {
+                    for op in 0..=0xFFu8 {
+                        let mut code: Vec<u8> = suffix.into_iter().collect();
+                        code.extend_from_slice(prefix);
+                        code.extend_from_slice(&[op, 0x12, 0x34, 0x56]);
+                        let decoded = decode(&code, adl).unwrap();
+                        let (mnemonic, length) = disasm_main(&code, adl);
+                        let context = format!("{:02X?} adl={}: {}", &code[..decoded.length], adl, mnemonic);
+
+                        assert_eq!(length, decoded.length, "{}", context);
+                        let name = mnemonic.split([' ', '.']).next().unwrap();
+                        let expected = match decoded.info.mnemonic {
+                            NONI_MNEMONIC => "DB",
+                            other => other,
+                        };
+                        assert_eq!(name, expected, "{}", context);
+                    }
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_cb_prefix() {
         // RLC B
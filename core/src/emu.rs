@@ -2,13 +2,53 @@
 //!
 //! Coordinates the CPU, bus, and peripherals to run the TI-84 Plus CE.
 
+use crate::autosave::{AutosaveCallback, AutosaveReason, AutosaveSink};
+use crate::benchmark::{self, Benchmark, Hook};
+use crate::breakpoint::Breakpoints;
+use crate::color::{ColorProfile, ColorTransform};
+use crate::crc::crc32;
+use crate::pixel_format::{self, PixelFormat};
 use crate::bus::{Bus, IoRecord};
 use crate::cpu::{Cpu, InterruptMode};
-use crate::peripherals::rtc::LATCH_TICK_OFFSET;
-use crate::scheduler::{EventId, Scheduler};
-use std::os::raw::c_char;
+use crate::peripherals::interrupt::{EmuInterruptStats, InterruptWatchHit, SOURCE_COUNT};
+use crate::peripherals::rtc::{RtcClock, RtcMode, LATCH_TICK_OFFSET};
+use crate::peripherals::{Peripheral, Peripherals, KEYPAD_COLS, KEYPAD_ROWS};
+use crate::scheduler::{ClockId, EventId, Scheduler};
+use crate::error::{EmuError, LoadError};
+use crate::events::{
+    DisplayState, EmuEvent, EventCallback, EventKind, EventQueue, EventSink, FrameCallback, FrameSink,
+};
+use crate::hash;
+use crate::heatmap::ExecHeatmap;
+use crate::opcode_coverage::OpcodeCoverage;
+use crate::host_clock::HostClock;
+use crate::host_bridge::{BridgeState, HostBridge, BRIDGE_BASE, BRIDGE_LEN};
+use crate::memory_map::{self, Annotation, RegionKind};
+use crate::memory::addr::{ARCHIVE_END, ARCHIVE_START, SECTOR_SIZE};
+use crate::mmio_diff::MmioChange;
+use crate::overlay;
+use crate::png;
+use crate::patch::{PatchError, PatchSet};
+use crate::port_watch::{EmuPortAccess, PortSpace, PortWatch, PortWatchCallback};
+use crate::power::{EmuPowerStats, PowerModel, PowerState};
+use crate::redact;
+use crate::rom_info::RomInfo;
+use crate::scanline::ScanlineRenderer;
+use crate::watchdog_monitor::{EmuWatchdogStats, WatchdogMonitor};
+use crate::input_macro::{frame_hash, InputMacro, Recorder};
+use crate::key_name::KeyName;
+use crate::last_change::EmuLastChange;
+use crate::scale::{self, Rotation};
+use crate::search::BytePattern;
+use crate::slots::{EmuSlotInfo, SaveSlots, MAX_SLOTS};
+use crate::snoop::{EmuSnoopAccess, Snoop, SnoopCallback};
+use crate::watch::WatchList;
+use crate::watchpoint::{WatchAccess, Watchpoint};
+use std::os::raw::{c_char, c_void};
+use std::ops::Range;
 use std::ptr;
 use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
 
 /// Zero-cost logging macro — compiles to nothing in WASM builds.
 /// Use this instead of `log_event(&format!(...))` to avoid format string
@@ -87,6 +127,9 @@ fn check_armed_trace_on_wake(was_halted: bool, is_halted: bool) {
 pub const SCREEN_WIDTH: usize = 320;
 pub const SCREEN_HEIGHT: usize = 240;
 
+/// Diagnostic color drawn when UPBASE is outside RAM (ARGB8888 magenta)
+const INVALID_UPBASE_COLOR: u32 = 0xFFFF00FF;
+
 /// Convert RGB565 (R=15:11, G=10:5, B=4:0) to ARGB8888.
 /// Matches CEmu's lcd_rgb565out + lcd_argb8888out.
 #[inline]
@@ -236,8 +279,164 @@ pub fn log_event(_message: &str) {
     // Use log_event_fmt!() macro instead for zero-cost in WASM.
 }
 
+/// Why the last reset happened (stable C ABI values, see emu.h)
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResetCause {
+    /// ROM load / first boot
+    PowerOn = 0,
+    /// Requested by the frontend (emu_reset, live file transfer)
+    Frontend = 1,
+    /// Watchdog timer expired
+    Watchdog = 2,
+    /// Reset key combination held with ON
+    KeyCombo = 3,
+    /// Triggered by emulated software
+    Software = 4,
+    /// Frontend power cycle (`ResetKind::PowerCycle`)
+    PowerCycle = 5,
+}
+
+impl ResetCause {
+    /// Whether RAM (and VRAM) keeps its contents through this reset.
+    ///
+    /// Watchdog and software resets and power cycles only restart the
+    /// ASIC, so the RAM the OS validates on boot is still there and no
+    /// "RAM Cleared" is shown.
+    /// Power-on, the reset button and the reset key combinations start
+    /// from cleared RAM, which the OS reports as "RAM Cleared".
+    ///
+    /// The hardware keeps or loses RAM as a whole. Which areas survive
+    /// after that (user variables, OS state, the screen) is up to the boot
+    /// code and OS, which check and clear RAM themselves on the emulated
+    /// CPU, so nothing per-area is modelled here. This split has not been
+    /// checked against a capture from a real calculator.
+    pub fn preserves_ram(self) -> bool {
+        matches!(self, ResetCause::Watchdog | ResetCause::Software | ResetCause::PowerCycle)
+    }
+}
+
+/// Keys held while the reset button is pressed (stable C ABI values, see
+/// emu.h). The emulator only holds the keys through the reset; what they
+/// do is up to the boot code and OS, which read the keypad while booting,
+/// just like on hardware.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetCombo {
+    /// Reset button alone: RAM is lost and TI-OS boots to "RAM Cleared"
+    Button = 0,
+    /// [del] held: boot code skips the installed OS and waits for a new
+    /// one to be sent ("reinstall OS")
+    ReinstallOs = 1,
+    /// [2nd]+[del] held: the boot code's other recovery combination
+    SecondDel = 2,
+}
+
+impl ResetCombo {
+    pub fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(ResetCombo::Button),
+            1 => Some(ResetCombo::ReinstallOs),
+            2 => Some(ResetCombo::SecondDel),
+            _ => None,
+        }
+    }
+
+    /// Matrix positions (row, col) held through the reset
+    pub fn keys(self) -> &'static [(usize, usize)] {
+        match self {
+            ResetCombo::Button => &[],
+            ResetCombo::ReinstallOs => &[(1, 7)],
+            ResetCombo::SecondDel => &[(1, 5), (1, 7)],
+        }
+    }
+}
+
+/// What a frontend reset clears (stable C ABI values, see emu.h). Every
+/// kind leaves the calculator off until `power_on`, like `reset`.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetKind {
+    /// Switched off and on again: the battery keeps RAM powered, so the
+    /// ASIC restarts but RAM and flash keep their contents and TI-OS
+    /// resumes without "RAM Cleared"
+    PowerCycle = 0,
+    /// RAM cleared, archive and OS kept (`reset`): TI-OS boots to
+    /// "RAM Cleared", like Reset RAM on the calculator
+    RamClear = 1,
+    /// RAM and the flash archive cleared, boot code and OS kept: a fresh
+    /// calculator without reflashing the OS, like Reset All Memory
+    Full = 2,
+}
+
+impl ResetKind {
+    pub fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(ResetKind::PowerCycle),
+            1 => Some(ResetKind::RamClear),
+            2 => Some(ResetKind::Full),
+            _ => None,
+        }
+    }
+}
+
+/// Cycles a reset combination stays held after the reset: past the boot
+/// code's keypad check (~2 s at the 6 MHz reset speed)
+const RESET_COMBO_HOLD_CYCLES: u64 = 12_000_000;
+
+/// How often run_cycles services the peripherals (scheduler events, LCD
+/// DMA, timers, keypad, OS timer, interrupt lines) between instructions.
+/// Coarser levels skip that work on most instructions, trading accuracy
+/// for speed. Whatever the level, NMIs from protection violations are
+/// taken after the instruction that caused them, port and MMIO accesses
+/// see timers and keypad advanced to the start of the instruction, the
+/// peripherals are always brought up to date before the CPU halts, and
+/// halted time is fast-forwarded exactly as before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RunGranularity {
+    /// After every instruction. The reference behaviour, matching CEmu
+    /// cycle for cycle; `Lookahead` must give identical results.
+    Instruction,
+    /// Every N cycles or more often: interrupts and scheduler events can
+    /// be handled up to N cycles late
+    Cycles(u32),
+    /// When a scheduler event is due, or at least every
+    /// `EVENT_DRIVEN_MAX_CYCLES`: LCD, RTC, SPI and DMA events stay on
+    /// time, but timer, OS timer and keypad interrupts can be raised up to
+    /// that many cycles late
+    EventDriven,
+    /// Default. Exact like `Instruction`, but the CPU runs in bursts up to
+    /// the next deadline (the next scheduler event, or the next timer,
+    /// OS timer or keypad change from `Peripherals::cycles_until_tick_event`),
+    /// and after any instruction that touched a port, since only those can
+    /// change what servicing does
+    #[default]
+    Lookahead,
+}
+
+impl RunGranularity {
+    /// Longest stretch EventDriven runs without servicing the peripherals
+    pub const EVENT_DRIVEN_MAX_CYCLES: u32 = 10_000;
+
+    /// From the C ABI mode (0 instruction, 1 cycles, 2 event-driven,
+    /// 3 lookahead);
+    /// `cycles` is only used by mode 1 and must be non-zero
+    pub fn from_raw(mode: u32, cycles: u32) -> Option<Self> {
+        match mode {
+            0 => Some(RunGranularity::Instruction),
+            1 if cycles > 0 => Some(RunGranularity::Cycles(cycles)),
+            2 => Some(RunGranularity::EventDriven),
+            3 => Some(RunGranularity::Lookahead),
+            _ => None,
+        }
+    }
+}
+
 /// Reason for stopping execution
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StopReason {
     /// Completed requested cycles
     CyclesComplete,
@@ -249,11 +448,48 @@ pub enum StopReason {
     // TODO: Wire up BusFault when Bus reports invalid memory access (Milestone 5+)
     /// Bus fault (invalid memory access)
     BusFault(u32),
+    /// Another thread called request_stop
+    StopRequested,
+    /// A source watched with `set_interrupt_watch` was raised or acknowledged
+    InterruptWatch(InterruptWatchHit),
+    /// PC reached the `run_until_pc` target
+    TargetReached,
+    /// The LCD started a new frame during `run_frame`
+    VSync,
+    /// PC reached an enabled breakpoint (see `add_breakpoint`)
+    Breakpoint(u32),
+    /// The previous instruction made an access a watchpoint covers
+    Watchpoint(EmuSnoopAccess),
+    /// The previous instruction touched a port a stopping port watch covers
+    PortWatch(EmuPortAccess),
+}
+
+/// Why `run_until_pc` returned (stable C ABI values, see emu.h)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunUntil {
+    /// PC is at the target; the instruction there hasn't run yet
+    Reached = 0,
+    /// The cycle budget ran out first
+    Budget = 1,
+    /// Something else stopped execution first (breakpoint, interrupt
+    /// watch, stop request, pause, power off)
+    Stopped = 2,
+}
+
+/// What `load_state_best_effort` restored (stable C ABI values, see emu.h)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateLoad {
+    /// Same ROM: restored exactly, as `load_state`
+    Full = 0,
+    /// Saved with another ROM: RAM, registers and peripherals restored,
+    /// current flash kept
+    KeptFlash = 1,
 }
 
 /// Information about a single instruction step (for trace comparison)
 /// Captures state BEFORE execution to match CEmu's trace format
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StepInfo {
     /// PC before instruction execution
     pub pc: u32,
@@ -295,6 +531,88 @@ pub struct StepInfo {
     pub io_ops: Vec<IoRecord>,
 }
 
+/// `StepInfo` in C layout for `emu_step` (see emu.h). Registers are the
+/// values before the instruction; `next_pc` is where execution continues.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EmuStepInfo {
+    /// Total cycles after this instruction
+    pub total_cycles: u64,
+    pub pc: u32,
+    /// PC after the instruction (or interrupt dispatch)
+    pub next_pc: u32,
+    pub sp: u32,
+    pub bc: u32,
+    pub de: u32,
+    pub hl: u32,
+    pub ix: u32,
+    pub iy: u32,
+    /// Cycles used by this instruction
+    pub cycles: u32,
+    /// Prefix and opcode bytes at PC (no operands), `opcode_len` valid
+    pub opcode: [u8; 4],
+    pub opcode_len: u8,
+    pub a: u8,
+    pub f: u8,
+    pub adl: u8,
+    pub iff1: u8,
+    pub iff2: u8,
+    /// Interrupt mode 0-2
+    pub im: u8,
+    pub halted: u8,
+}
+
+impl From<&StepInfo> for EmuStepInfo {
+    fn from(info: &StepInfo) -> Self {
+        Self {
+            total_cycles: info.total_cycles,
+            pc: info.pc,
+            next_pc: info.pc,
+            sp: info.sp,
+            bc: info.bc,
+            de: info.de,
+            hl: info.hl,
+            ix: info.ix,
+            iy: info.iy,
+            cycles: info.cycles,
+            opcode: info.opcode,
+            opcode_len: info.opcode_len as u8,
+            a: info.a,
+            f: info.f,
+            adl: info.adl as u8,
+            iff1: info.iff1 as u8,
+            iff2: info.iff2 as u8,
+            im: match info.im {
+                InterruptMode::Mode0 => 0,
+                InterruptMode::Mode1 => 1,
+                InterruptMode::Mode2 => 2,
+            },
+            halted: info.halted as u8,
+        }
+    }
+}
+
+/// Main CPU registers as passed across the C ABI (`emu_get_registers` /
+/// `emu_set_registers`). 24-bit registers hold their full value whatever
+/// the mode; in Z80 mode only the low 16 bits are used, with MBASE as the
+/// high byte of memory addresses.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EmuRegisters {
+    pub bc: u32,
+    pub de: u32,
+    pub hl: u32,
+    pub ix: u32,
+    pub iy: u32,
+    /// Stack pointer for the current mode (SPL in ADL mode, SPS otherwise)
+    pub sp: u32,
+    pub pc: u32,
+    /// A in the high byte, F in the low byte
+    pub af: u16,
+    pub mbase: u8,
+    pub adl: u8,
+}
+
 /// Main emulator state
 pub struct Emu {
     /// eZ80 CPU
@@ -309,6 +627,8 @@ pub struct Emu {
 
     /// ROM loaded flag
     rom_loaded: bool,
+    /// Hash of the flash image as loaded (or as left by flash_os)
+    rom_hash: u64,
 
     /// Calculator is powered on (ON key was pressed)
     /// CPU won't execute until this is true
@@ -319,6 +639,8 @@ pub struct Emu {
 
     /// Last stop reason
     last_stop: StopReason,
+    /// Cause of the most recent reset
+    reset_cause: ResetCause,
 
     /// Total cycles executed
     total_cycles: u64,
@@ -331,18 +653,97 @@ pub struct Emu {
     #[cfg(not(target_arch = "wasm32"))]
     frame_count: u32,
 
-    /// Optional breakpoint PC - run_cycles will return early when PC hits this address
-    breakpoint_pc: Option<u32>,
+    /// PC breakpoints - run_cycles returns early when PC hits an enabled one
+    breakpoints: Breakpoints,
     /// Whether a breakpoint was hit during the last run_cycles call
     breakpoint_hit: bool,
+    /// `run_until_pc` target and the cycle count it was set at (the target
+    /// only counts once an instruction has run)
+    run_target: Option<(u32, u64)>,
+    /// Set while `run_frame` runs: whether the LCD's vertical sync was seen
+    vsync_stop: Option<bool>,
+    /// Set from any thread to end run_cycles at the next instruction boundary
+    stop_requested: Arc<AtomicBool>,
+    /// Set by the host bridge reset register; run_cycles performs the
+    /// software reset at the next instruction boundary
+    software_reset: Arc<AtomicBool>,
+    /// Host wall time reported by the frontend; also holds the pause state
+    host_clock: HostClock,
+    /// What advances the RTC (emulated cycles or host time)
+    rtc_clock: RtcClock,
+    /// Host seconds already added to the RTC (RtcClock::HostTime)
+    rtc_host_secs: u64,
 
     /// NMI debug logging (for WASM where log_evt is no-op)
     nmi_log_count: u32,
     nmi_log_pc: u32,
     nmi_log_sp: u32,
+
+    /// Watch expressions re-evaluated in render_frame
+    watches: WatchList,
+    /// Per-bucket execution counts (allocated only while enabled)
+    heatmap: Option<Box<ExecHeatmap>>,
+    /// Per-opcode execution counts (None = disabled)
+    opcode_coverage: Option<Box<OpcodeCoverage>>,
+    /// Input macro being recorded (see `crate::input_macro`)
+    macro_recorder: Option<Box<Recorder>>,
+    /// Flash patch sets, applied after ROM load and on every reset
+    patches: Vec<PatchSet>,
+    /// Emulated-time benchmark hooks and results (see `crate::benchmark`)
+    benchmark: Option<Box<Benchmark>>,
+    /// Supply current estimate, sampled after every run (see `crate::power`)
+    power: PowerModel,
+    /// Watchdog pet telemetry and stall detector (see `crate::watchdog_monitor`)
+    watchdog: WatchdogMonitor,
+    /// Running count of cycles skipped while halted, for the power model
+    halted_cycles: u64,
+    /// Whether the last rendered frame had UPBASE inside RAM
+    upbase_valid: bool,
+    /// Rows decoded as the LCD DMA reaches them (None = snapshot at
+    /// render time; frontend setting, see `crate::scanline`)
+    scanline: Option<Box<ScanlineRenderer>>,
+    /// Color profile applied to each rendered frame (frontend setting,
+    /// not saved in state)
+    color: ColorTransform,
+    /// Output format of `formatted_framebuffer` (frontend setting)
+    pixel_format: PixelFormat,
+    /// Each rendered frame converted to `pixel_format`; empty for ARGB8888
+    formatted_framebuffer: Vec<u8>,
+    /// Keys the frontend currently reports held (via set_key), kept across
+    /// state loads so the restored matrix can be reconciled with them
+    host_keys: [[bool; KEYPAD_COLS]; KEYPAD_ROWS],
+    /// Level of the ON line. ON is wired to its own interrupt and wake
+    /// logic, not the scanned matrix; key_matrix reports it at row 2 bit 0.
+    on_held: bool,
+    /// Reset combination keys to release once total_cycles reaches the
+    /// deadline (see reset_with_combo)
+    combo_release: Option<(u64, ResetCombo)>,
+    /// Peripheral service granularity (frontend setting, not saved)
+    granularity: RunGranularity,
+    /// With Lookahead, service once this many cycles are unticked
+    service_deadline: u32,
+    /// Frames generated/fetched/dropped
+    frame_counters: FrameCounters,
+    /// `frame_counters.generated` at the last fetch
+    last_fetched_frame: u64,
+    /// Frontend event callback
+    event_sink: Option<EventSink>,
+    /// Frontend callback at each LCD vertical sync
+    frame_sink: Option<FrameSink>,
+    /// Events waiting for emu_poll_event
+    event_queue: EventQueue,
+    /// Display state as of the last transition check
+    display_state: DisplayState,
+    /// Quick-save slots (not part of the saved state itself)
+    slots: SaveSlots,
+    /// Receives a final save state on destroy/crash
+    autosave_sink: Option<AutosaveSink>,
+    /// Host bridge extension id and shared state, while enabled
+    host_bridge: Option<(u32, Arc<Mutex<BridgeState>>)>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TimerSnapshot {
     pub counter: u32,
     pub reset_value: u32,
@@ -351,7 +752,25 @@ pub struct TimerSnapshot {
     pub control: u8,
 }
 
+/// LCD frames the emulated panel scanned out versus frames the frontend
+/// fetched (C layout, see emu.h). A frame counts as fetched the first time
+/// the framebuffer is read after it was generated; frames superseded before
+/// any read are dropped, so a rising `dropped` means the frontend isn't
+/// keeping up. Counted from emulator creation, not part of save states.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameCounters {
+    /// Vertical syncs of the LCD controller (none while it's disabled)
+    pub generated: u64,
+    /// Distinct frames read through the framebuffer API
+    pub fetched: u64,
+    /// Frames generated and replaced without being read
+    pub dropped: u64,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LcdSnapshot {
     pub timing: [u32; 4],
     pub control: u32,
@@ -365,61 +784,131 @@ pub struct LcdSnapshot {
 impl Emu {
     /// Create a new emulator instance
     pub fn new() -> Self {
-        Self {
+        let mut emu = Self {
             cpu: Cpu::new(),
             bus: Bus::new(),
             scheduler: Scheduler::new(),
             framebuffer: vec![0xFF000000; SCREEN_WIDTH * SCREEN_HEIGHT],
             rom_loaded: false,
+            rom_hash: 0,
             powered_on: false,
             history: ExecutionHistory::new(),
             last_stop: StopReason::CyclesComplete,
+            reset_cause: ResetCause::PowerOn,
             total_cycles: 0,
             halt_logged: false,
             boot_init_done: false,
             #[cfg(not(target_arch = "wasm32"))]
             frame_count: 0,
-            breakpoint_pc: None,
+            breakpoints: Breakpoints::new(),
             breakpoint_hit: false,
+            run_target: None,
+            vsync_stop: None,
+            stop_requested: Arc::new(AtomicBool::new(false)),
+            software_reset: Arc::new(AtomicBool::new(false)),
+            host_clock: HostClock::default(),
+            rtc_clock: RtcClock::Emulated,
+            rtc_host_secs: 0,
             nmi_log_count: 0,
             nmi_log_pc: 0,
             nmi_log_sp: 0,
-        }
+            watches: WatchList::new(),
+            heatmap: None,
+            opcode_coverage: None,
+            macro_recorder: None,
+            patches: Vec::new(),
+            benchmark: None,
+            power: PowerModel::new(),
+            watchdog: WatchdogMonitor::new(),
+            halted_cycles: 0,
+            upbase_valid: true,
+            scanline: None,
+            color: ColorTransform::default(),
+            pixel_format: PixelFormat::Argb8888,
+            formatted_framebuffer: Vec::new(),
+            host_keys: [[false; KEYPAD_COLS]; KEYPAD_ROWS],
+            on_held: false,
+            combo_release: None,
+            granularity: RunGranularity::Lookahead,
+            service_deadline: 0,
+            frame_counters: FrameCounters::default(),
+            last_fetched_frame: 0,
+            event_sink: None,
+            frame_sink: None,
+            event_queue: EventQueue::default(),
+            display_state: DisplayState::default(),
+            slots: SaveSlots::new(),
+            autosave_sink: None,
+            host_bridge: None,
+        };
+        // Power-on display state is the baseline for transition events
+        emu.display_state = emu.display_state();
+        emu
     }
 
     /// Load ROM data into flash
-    pub fn load_rom(&mut self, data: &[u8]) -> Result<(), i32> {
+    pub fn load_rom(&mut self, data: &[u8]) -> Result<(), LoadError> {
         if data.is_empty() {
-            return Err(-2); // Empty ROM
+            return Err(LoadError::EmptyRom);
         }
 
-        self.bus.load_rom(data).map_err(|_| -3)?; // -3 = ROM too large
+        self.bus.load_rom(data).map_err(|_| LoadError::RomTooLarge {
+            size: data.len(),
+            max: crate::memory::addr::FLASH_SIZE,
+        })?;
         self.rom_loaded = true;
-        log_evt!("ROM_LOADED bytes={}", data.len());
-        self.reset();
+        // Hash the ROM as dumped; patches are applied by the reset
+        self.rom_hash = self.hash_flash();
+        log_evt!("ROM_LOADED bytes={} hash={:016X}", data.len(), self.rom_hash);
+        self.reset_with_cause(ResetCause::PowerOn);
         Ok(())
     }
 
+    /// Load the ROM-less demo (`crate::test_rom::build_demo`) in place of
+    /// a TI ROM; power on as usual afterwards.
+    pub fn load_demo(&mut self, config: crate::test_rom::DemoConfig) -> Result<(), LoadError> {
+        self.load_rom(&crate::test_rom::build_demo(config))
+    }
+
+    /// Add a flash patch set (IPS or text, see `crate::patch`). It is
+    /// applied now if a ROM is loaded, and again after every ROM load and
+    /// reset. Returns the number of bytes it patches.
+    pub fn add_patch_set(&mut self, data: &[u8]) -> Result<usize, PatchError> {
+        let set = PatchSet::parse(data)?;
+        if self.rom_loaded {
+            set.apply(&mut self.bus.flash);
+        }
+        log_evt!("PATCH_SET: {} patches, {} bytes", set.patches.len(), set.len_bytes());
+        let bytes = set.len_bytes();
+        self.patches.push(set);
+        Ok(bytes)
+    }
+
+    /// Forget all patch sets. Bytes already patched stay in flash until the
+    /// ROM is loaded again.
+    pub fn clear_patches(&mut self) {
+        self.patches.clear();
+    }
+
     /// Send a .8xp/.8xv file to the emulator by injecting into flash archive.
     ///
     /// Must be called after `load_rom()` and before `power_on()`. The variable
     /// entries are written to the flash archive region (0x0C0000+) in the format
     /// the TI-OS expects. When the OS boots, it scans flash and discovers them.
     ///
-    /// Returns Ok(count) with the number of entries injected, or an error code.
-    pub fn send_file(&mut self, file_data: &[u8]) -> Result<usize, i32> {
+    /// Returns Ok(count) with the number of entries injected.
+    pub fn send_file(&mut self, file_data: &[u8]) -> Result<usize, EmuError> {
         use crate::ti_file::TiFile;
 
         if !self.rom_loaded {
-            return Err(-10); // ROM not loaded
+            return Err(EmuError::NoRom);
         }
         if self.powered_on {
-            return Err(-13); // Must inject before boot
+            return Err(EmuError::AlreadyBooted);
         }
 
-        let ti_file = TiFile::parse(file_data).map_err(|e| {
+        let ti_file = TiFile::parse(file_data).inspect_err(|e| {
             log_evt!("SEND_FILE_PARSE_ERROR: {}", e);
-            -11 // Parse error
         })?;
 
         let count = ti_file.entries.len();
@@ -427,6 +916,7 @@ impl Emu {
             self.inject_archive_entry(entry)?;
         }
 
+        self.emit_event(EventKind::TransferComplete, count as u32);
         Ok(count)
     }
 
@@ -439,10 +929,6 @@ impl Emu {
     /// Entries start at offset 1. Each entry: [flag] [size_lo] [size_hi] [payload...]
     /// TI-OS stops scanning when it hits 0xFF.
     fn find_archive_free_addr(&self) -> Option<u32> {
-        const ARCHIVE_START: u32 = 0x0C0000;
-        const ARCHIVE_END: u32 = 0x3B0000;
-        const SECTOR_SIZE: u32 = 0x10000; // 64KB
-
         let mut sector = ARCHIVE_START;
         while sector < ARCHIVE_END {
             let status = self.bus.flash.peek(sector);
@@ -491,18 +977,19 @@ impl Emu {
     /// The flag byte 0xFC marks a valid entry. The 2-byte size (LE) is the
     /// byte count of everything after the 3-byte header (flag+size).
     /// The 3-byte address field is self-referential: it points to the flag byte.
-    fn inject_archive_entry(&mut self, entry: &crate::ti_file::TiVarEntry) -> Result<(), i32> {
-        const ARCHIVE_END: u32 = 0x3B0000;
-        const SECTOR_SIZE: u32 = 0x10000; // 64KB
-
-        let free_addr = self.find_archive_free_addr().ok_or(-12)?; // -12 = no space
-
+    fn inject_archive_entry(&mut self, entry: &crate::ti_file::TiVarEntry) -> Result<(), EmuError> {
         let name_len = entry.name_len();
         // Payload after the 3-byte header (flag+size):
         //   type1(1) + type2(1) + version(1) + addr(3) + namelen(1) + name(N) + data
         let payload_len = 6 + 1 + name_len + entry.data.len();
         // Total entry: 3-byte header + payload
         let total_len = 3 + payload_len;
+        // Entries can't span sectors, so one this big never fits
+        if total_len as u32 > SECTOR_SIZE - 1 {
+            return Err(EmuError::VariableTooLarge);
+        }
+
+        let free_addr = self.find_archive_free_addr().ok_or(EmuError::ArchiveFull)?;
 
         let mut write_addr = free_addr;
 
@@ -525,7 +1012,7 @@ impl Emu {
             // Move to next sector and find free space within it
             let next_sector = sector_end;
             if next_sector + 1 + total_len as u32 > ARCHIVE_END {
-                return Err(-12); // No space
+                return Err(EmuError::ArchiveFull);
             }
             let status = self.bus.flash.peek(next_sector);
             if status == 0xFF {
@@ -625,10 +1112,6 @@ impl Emu {
         name_len: usize,
         var_type: u8,
     ) -> Option<u32> {
-        const ARCHIVE_START: u32 = 0x0C0000;
-        const ARCHIVE_END: u32 = 0x3B0000;
-        const SECTOR_SIZE: u32 = 0x10000; // 64KB
-
         let mut sector = ARCHIVE_START;
         while sector < ARCHIVE_END {
             let status = self.bus.flash.peek(sector);
@@ -700,17 +1183,16 @@ impl Emu {
     /// 3. Injects the new entry
     /// 4. Performs a soft reset (preserves flash) + power on
     ///
-    /// Returns Ok(count) with entries injected, or an error code.
-    pub fn send_file_live(&mut self, file_data: &[u8]) -> Result<usize, i32> {
+    /// Returns Ok(count) with entries injected.
+    pub fn send_file_live(&mut self, file_data: &[u8]) -> Result<usize, EmuError> {
         use crate::ti_file::TiFile;
 
         if !self.rom_loaded {
-            return Err(-10); // ROM not loaded
+            return Err(EmuError::NoRom);
         }
 
-        let ti_file = TiFile::parse(file_data).map_err(|e| {
+        let ti_file = TiFile::parse(file_data).inspect_err(|e| {
             log_evt!("SEND_FILE_LIVE_PARSE_ERROR: {}", e);
-            -11 // Parse error
         })?;
 
         let count = ti_file.entries.len();
@@ -738,9 +1220,50 @@ impl Emu {
         self.reset();
         self.power_on();
 
+        self.emit_event(EventKind::TransferComplete, count as u32);
         Ok(count)
     }
 
+    /// Flash an OS upgrade (.8eu) straight from the host. It:
+    /// 1. Parses and checks the file (CE OS records, fits in flash)
+    /// 2. Erases and programs the OS sectors through the flash commands
+    /// 3. Reads the image back
+    /// 4. Reboots: soft reset (preserves flash) + power on
+    ///
+    /// The boot code's receive mode is bypassed, so its signature check
+    /// never sees the OS (see `os_update`).
+    ///
+    /// Returns Ok(bytes programmed).
+    pub fn flash_os(&mut self, file_data: &[u8]) -> Result<usize, EmuError> {
+        use crate::os_update::{OsImage, OsUpdateError};
+
+        if !self.rom_loaded {
+            return Err(EmuError::OsNoRom);
+        }
+
+        let code = |e: OsUpdateError| {
+            log_evt!("FLASH_OS_ERROR: {}", e);
+            EmuError::Os(e)
+        };
+        let image = OsImage::parse(file_data).map_err(code)?;
+        log_evt!(
+            "FLASH_OS name={} version={}.{} size={}",
+            image.name,
+            image.version.0,
+            image.version.1,
+            image.data.len()
+        );
+        image.program(&mut self.bus.flash).map_err(code)?;
+        // The new OS is the baseline states and per-ROM settings key on
+        self.rom_hash = self.hash_flash();
+
+        self.reset();
+        self.power_on();
+
+        self.emit_event(EventKind::TransferComplete, 1);
+        Ok(image.data.len())
+    }
+
     /// Set serial flash mode
     /// - true: Serial flash (newer TI-84 CE models) - uses cache timing
     /// - false: Parallel flash (older models) - uses constant 10 cycle timing
@@ -753,11 +1276,40 @@ impl Emu {
         self.bus.is_serial_flash()
     }
 
-    /// Reset emulator to initial state
+    /// Reset emulator to initial state (a frontend-requested reset)
     pub fn reset(&mut self) {
-        log_evt!("RESET");
+        self.reset_with_cause(ResetCause::Frontend);
+    }
+
+    /// Reset emulator to initial state, recording why
+    pub fn reset_with_cause(&mut self, cause: ResetCause) {
+        self.restart(cause, cause.preserves_ram());
+    }
+
+    /// Frontend reset clearing what `kind` says (see `ResetKind`)
+    pub fn reset_kind(&mut self, kind: ResetKind) {
+        match kind {
+            ResetKind::PowerCycle => self.reset_with_cause(ResetCause::PowerCycle),
+            ResetKind::RamClear => self.reset(),
+            ResetKind::Full => {
+                self.bus.flash.erase_direct(ARCHIVE_START, ARCHIVE_END);
+                self.reset();
+            }
+        }
+    }
+
+    fn restart(&mut self, cause: ResetCause, keep_ram: bool) {
+        log_evt!("RESET cause={:?} keep_ram={}", cause, keep_ram);
+        self.reset_cause = cause;
+        self.combo_release = None;
+        self.service_deadline = 0;
+        self.on_held = false;
         self.cpu.reset();
-        self.bus.reset();
+        if keep_ram {
+            self.bus.reset_keep_ram();
+        } else {
+            self.bus.reset();
+        }
         self.scheduler.reset();
         self.history.clear();
         self.last_stop = StopReason::CyclesComplete;
@@ -765,6 +1317,19 @@ impl Emu {
         self.halt_logged = false;
         self.boot_init_done = false;
         self.powered_on = false; // Require ON key press to power on again
+        if self.rom_loaded {
+            for set in &self.patches {
+                set.apply(&mut self.bus.flash);
+            }
+        }
+        if let Some(bench) = self.benchmark.as_mut() {
+            bench.abort();
+        }
+        self.power.reset();
+        self.watchdog.clear();
+        if let Some(scanline) = self.scanline.as_mut() {
+            scanline.clear();
+        }
         // Initialize CPU prefetch buffer - charges cycles for first instruction's first byte
         // This matches CEmu's cpu_inst_start() call at the beginning of cpu_execute()
         self.cpu.init_prefetch(&mut self.bus);
@@ -772,6 +1337,7 @@ impl Emu {
         // Account for init_prefetch cycles in total_cycles for trace parity with CEmu
         // CEmu's cycle counter includes the prefetch cost before the first instruction
         self.total_cycles = self.bus.total_cycles();
+        self.sample_power();
 
         // Sync scheduler with initial cycles before scheduling RTC
         self.scheduler.advance(self.total_cycles);
@@ -786,6 +1352,56 @@ impl Emu {
         for pixel in &mut self.framebuffer {
             *pixel = 0xFF000000;
         }
+        self.update_formatted_framebuffer();
+    }
+
+    /// Reset requested by the emulated hardware, if any: an expired watchdog
+    /// with reset enabled, or a write to the host bridge reset register
+    fn take_reset_request(&mut self) -> Option<ResetCause> {
+        if self.bus.ports.watchdog.take_reset_request() {
+            Some(ResetCause::Watchdog)
+        } else if self.software_reset.swap(false, Ordering::Relaxed) {
+            Some(ResetCause::Software)
+        } else {
+            None
+        }
+    }
+
+    /// Restart the ASIC for a watchdog or software reset. Unlike the
+    /// frontend resets the calculator boots again at once, without waiting
+    /// for ON.
+    fn restart_asic(&mut self, cause: ResetCause) {
+        self.reset_with_cause(cause);
+        self.powered_on = true;
+    }
+
+    /// Press the reset button with `combo` held, like the hardware flows
+    /// for clearing RAM or reinstalling the OS. The calculator restarts
+    /// without waiting for ON, and the keys are released by run_cycles
+    /// once the boot code has had time to see them.
+    pub fn reset_with_combo(&mut self, combo: ResetCombo) {
+        let cause = if combo == ResetCombo::Button { ResetCause::Frontend } else { ResetCause::KeyCombo };
+        self.reset_with_cause(cause);
+        for &(row, col) in combo.keys() {
+            self.bus.set_key(row, col, true);
+        }
+        if !combo.keys().is_empty() {
+            self.combo_release = Some((self.total_cycles + RESET_COMBO_HOLD_CYCLES, combo));
+        }
+        self.power_on();
+        log_evt!("RESET_COMBO: {:?}", combo);
+    }
+
+    /// Release the keys held by a pending reset combination, except any the
+    /// frontend holds itself
+    fn release_combo_keys(&mut self) {
+        if let Some((_, combo)) = self.combo_release.take() {
+            for &(row, col) in combo.keys() {
+                if !self.host_keys[row][col] {
+                    self.bus.set_key(row, col, false);
+                }
+            }
+        }
     }
 
     /// Run for specified cycles, returns cycles actually executed
@@ -797,9 +1413,18 @@ impl Emu {
     /// screen ("TI-84 Plus CE", OS version, "RAM Cleared") to remain visible until the user
     /// presses their first key. See `set_key()` documentation for details.
     pub fn run_cycles(&mut self, cycles: u32) -> u32 {
-        if !self.rom_loaded || !self.powered_on || self.is_off() {
+        let executed = self.run_cycles_until_stop(cycles);
+        self.sample_power();
+        executed
+    }
+
+    fn run_cycles_until_stop(&mut self, cycles: u32) -> u32 {
+        if !self.rom_loaded || !self.powered_on || self.is_off() || self.is_paused() {
             return 0;
         }
+        if matches!(self.combo_release, Some((deadline, _)) if self.total_cycles >= deadline) {
+            self.release_combo_keys();
+        }
 
         // Sync check: bus.cycles should match total_cycles
         if self.total_cycles != self.bus.total_cycles() {
@@ -813,21 +1438,86 @@ impl Emu {
 
         let mut cycles_remaining = cycles as i32;
         let mut start_cycles = self.total_cycles;
+        // The host may have pressed keys or poked state since the last run
+        self.service_deadline = 0;
 
         while cycles_remaining > 0 {
             // Sync scheduler with CPU speed setting
             let cpu_speed = self.bus.ports.control.cpu_speed();
             self.scheduler.set_cpu_speed(cpu_speed);
 
-            // Check breakpoint BEFORE executing
-            if let Some(bp) = self.breakpoint_pc {
-                if self.cpu.pc == bp && !self.cpu.halted {
-                    self.breakpoint_hit = true;
+            // Stop request from another thread (consumed here)
+            if self.stop_requested.swap(false, Ordering::Relaxed) {
+                self.last_stop = StopReason::StopRequested;
+                self.flush_peripherals();
+                self.total_cycles = self.bus.total_cycles();
+                return (self.total_cycles - start_cycles) as u32;
+            }
+
+            // Watchdog expiry or software reset during the previous instruction
+            if let Some(cause) = self.take_reset_request() {
+                self.flush_peripherals();
+                let executed = (self.bus.total_cycles() - start_cycles) as u32;
+                self.restart_asic(cause);
+                return executed;
+            }
+
+            // Check breakpoints BEFORE executing
+            if self.breakpoints.armed() && !self.cpu.halted && self.breakpoints.check(self.cpu.pc) {
+                let bp = self.cpu.pc;
+                self.breakpoint_hit = true;
+                self.last_stop = StopReason::Breakpoint(bp);
+                self.flush_peripherals();
+                self.total_cycles = self.bus.total_cycles();
+                self.emit_event(EventKind::BreakpointHit, bp);
+                return (self.total_cycles - start_cycles) as u32;
+            }
+
+            // run_frame reached the next frame
+            if self.vsync_stop == Some(true) {
+                self.last_stop = StopReason::VSync;
+                self.flush_peripherals();
+                self.total_cycles = self.bus.total_cycles();
+                return (self.total_cycles - start_cycles) as u32;
+            }
+
+            // run_until_pc target
+            if let Some((target, set_at)) = self.run_target {
+                if self.cpu.pc == target && !self.cpu.halted && self.bus.total_cycles() > set_at {
+                    self.last_stop = StopReason::TargetReached;
+                    self.flush_peripherals();
                     self.total_cycles = self.bus.total_cycles();
                     return (self.total_cycles - start_cycles) as u32;
                 }
             }
 
+            // Watched interrupt source changed status (previous instruction
+            // or peripheral servicing)
+            if let Some(hit) = self.bus.ports.interrupt.take_watch_hit() {
+                self.flush_peripherals();
+                self.total_cycles = self.bus.total_cycles();
+                self.stop_on_interrupt_watch(hit);
+                return (self.total_cycles - start_cycles) as u32;
+            }
+
+            // Data watchpoint hit by the previous instruction
+            if let Some(hit) = self.bus.watchpoints.take_hit() {
+                self.flush_peripherals();
+                self.total_cycles = self.bus.total_cycles();
+                self.stop_on_watchpoint(hit);
+                return (self.total_cycles - start_cycles) as u32;
+            }
+            if let Some(hit) = self.bus.port_watches.take_hit() {
+                self.flush_peripherals();
+                self.total_cycles = self.bus.total_cycles();
+                self.stop_on_port_watch(hit);
+                return (self.total_cycles - start_cycles) as u32;
+            }
+
+            if self.benchmark.is_some() {
+                self.poll_benchmark();
+            }
+
             // Record PC and peek at opcode before execution
             let pc = self.cpu.pc;
             let (opcode, opcode_len) = self.peek_opcode(pc);
@@ -870,6 +1560,15 @@ impl Emu {
                 }
             }
 
+            if let Some(heatmap) = self.heatmap.as_mut() {
+                if !self.cpu.halted {
+                    heatmap.record(self.cpu.mask_addr_instr(pc));
+                }
+            }
+            if self.opcode_coverage.is_some() && !self.cpu.halted {
+                self.record_opcode(pc);
+            }
+
             // Execute one instruction
             let cycles_used = self.cpu.step(&mut self.bus);
 
@@ -905,31 +1604,31 @@ impl Emu {
                 start_cycles = start_cycles * new_mhz as u64 / old_mhz as u64;
             }
 
-            // Process pending scheduler events
-            self.process_scheduler_events();
-
-            // DMA cycle stealing: if LCD DMA consumed bus time, steal CPU cycles
-            let dma_stolen = self.process_dma_stealing();
-            if dma_stolen > 0 {
-                cycles_remaining -= dma_stolen as i32;
-            }
-
-            // Check if SPI needs initial scheduling (state changed via port write)
-            if self.bus.take_spi_schedule_flag() && !self.scheduler.is_active(EventId::Spi) {
-                if let Some(ticks) = self.bus.spi().try_start_transfer_for_scheduler() {
-                    self.scheduler.set(EventId::Spi, ticks);
+            // Service the peripherals as often as the granularity asks
+            let unticked = self.bus.ports.unticked_cycles.saturating_add(cycles_used);
+            self.bus.ports.unticked_cycles = unticked;
+            let touched = self.bus.take_ports_touched();
+            let service = match self.granularity {
+                RunGranularity::Instruction => true,
+                RunGranularity::Cycles(n) => unticked >= n,
+                RunGranularity::EventDriven => {
+                    unticked >= RunGranularity::EVENT_DRIVEN_MAX_CYCLES
+                        || self.scheduler.cycles_until_next_event() == 0
+                }
+                RunGranularity::Lookahead => touched || unticked >= self.service_deadline,
+            };
+            if service || self.cpu.halted || new_cpu_speed != cpu_speed || cycles_remaining <= 0 {
+                cycles_remaining -= self.service_peripherals() as i32;
+            } else {
+                if self.bus.take_nmi_flag() {
+                    self.raise_protection_nmi();
+                }
+                // The controller only changes on a service or port access, but
+                // re-assert its level after the CPU took the interrupt, as a
+                // tick would
+                if !self.cpu.irq_pending && self.bus.ports.irq_pending() {
+                    self.cpu.irq_pending = true;
                 }
-            }
-
-            // Check for NMI from memory protection violations
-            if self.bus.take_nmi_flag() {
-                self.cpu.nmi_pending = true;
-                self.log_nmi();
-            }
-
-            // Tick peripherals and check for interrupts
-            if self.tick_peripherals(cycles_used) {
-                self.cpu.irq_pending = true;
             }
 
             // Stop if device went off (OS wrote POWER bit 6 during this instruction)
@@ -954,6 +1653,10 @@ impl Emu {
                 loop {
                     // Stop if device went off during this frame (OS wrote POWER bit 6)
                     if self.is_off() { break; }
+                    // An IRQ already pending (e.g. raised during EI;HALT) wakes at once
+                    if self.cpu.halt_wake_pending() { break; }
+                    // run_frame stops at the top of the outer loop
+                    if self.vsync_stop == Some(true) { break; }
 
                     let skip = self.scheduler.cycles_until_next_event();
                     if skip == 0 {
@@ -995,6 +1698,7 @@ impl Emu {
                             .min(cycles_remaining.max(0) as u64);
                         if batch == 0 { break; }
                         self.bus.add_cycles(batch);
+                        self.halted_cycles += batch;
                         cycles_remaining -= batch as i32;
                         self.scheduler.advance(batch);
                         self.total_cycles = self.bus.total_cycles();
@@ -1010,6 +1714,7 @@ impl Emu {
                     if skip == 0 { break; }
 
                     self.bus.add_cycles(skip);
+                    self.halted_cycles += skip;
                     cycles_remaining -= skip as i32;
                     self.scheduler.advance(skip);
                     self.total_cycles = self.bus.total_cycles();
@@ -1034,9 +1739,10 @@ impl Emu {
                     }
 
                     // Check wake conditions
-                    if self.cpu.irq_pending && self.cpu.iff1 { break; }
-                    if self.cpu.nmi_pending { break; }
+                    if self.cpu.halt_wake_pending() { break; }
                     if cycles_remaining <= 0 { break; }
+                    // Leave the stop request for the outer loop to consume
+                    if self.stop_requested.load(Ordering::Relaxed) { break; }
                 }
 
                 // Flush any remaining peripheral debt
@@ -1048,6 +1754,7 @@ impl Emu {
             }
         }
 
+        self.flush_peripherals();
         self.last_stop = StopReason::CyclesComplete;
         let executed = (self.total_cycles - start_cycles) as u32;
 
@@ -1114,13 +1821,35 @@ impl Emu {
                 }
             }
 
-            // Check breakpoint BEFORE executing
-            if let Some(bp) = self.breakpoint_pc {
-                if self.cpu.pc == bp && !self.cpu.halted {
-                    self.breakpoint_hit = true;
-                    self.total_cycles = self.bus.total_cycles();
-                    return (self.total_cycles - start_cycles) as u32;
-                }
+            // Check breakpoints BEFORE executing
+            if self.breakpoints.armed() && !self.cpu.halted && self.breakpoints.check(self.cpu.pc) {
+                let bp = self.cpu.pc;
+                self.breakpoint_hit = true;
+                self.last_stop = StopReason::Breakpoint(bp);
+                self.total_cycles = self.bus.total_cycles();
+                self.emit_event(EventKind::BreakpointHit, bp);
+                return (self.total_cycles - start_cycles) as u32;
+            }
+
+            if let Some(hit) = self.bus.ports.interrupt.take_watch_hit() {
+                self.total_cycles = self.bus.total_cycles();
+                self.stop_on_interrupt_watch(hit);
+                return (self.total_cycles - start_cycles) as u32;
+            }
+
+            if let Some(hit) = self.bus.watchpoints.take_hit() {
+                self.total_cycles = self.bus.total_cycles();
+                self.stop_on_watchpoint(hit);
+                return (self.total_cycles - start_cycles) as u32;
+            }
+            if let Some(hit) = self.bus.port_watches.take_hit() {
+                self.total_cycles = self.bus.total_cycles();
+                self.stop_on_port_watch(hit);
+                return (self.total_cycles - start_cycles) as u32;
+            }
+
+            if self.benchmark.is_some() {
+                self.poll_benchmark();
             }
 
             let was_halted = self.cpu.halted;
@@ -1162,12 +1891,13 @@ impl Emu {
 
             // Check for NMI from memory protection violations
             if self.bus.take_nmi_flag() {
-                self.cpu.nmi_pending = true;
+                self.raise_protection_nmi();
             }
 
             if self.tick_peripherals(cycles_used) {
                 self.cpu.irq_pending = true;
             }
+            self.check_display_transitions();
 
             // Stop if device went off
             if self.is_off() {
@@ -1180,12 +1910,14 @@ impl Emu {
                 let mut peripheral_debt: u64 = 0;
 
                 loop {
+                    if self.cpu.halt_wake_pending() { break; }
                     let skip = self.scheduler.cycles_until_next_event();
                     if skip == 0 {
                         if !self.cpu.iff1 && !self.cpu.nmi_pending { break; }
                         let batch = HALT_TICK_BATCH.min(cycles_remaining.max(0) as u64);
                         if batch == 0 { break; }
                         self.bus.add_cycles(batch);
+                        self.halted_cycles += batch;
                         cycles_remaining -= batch as i32;
                         self.scheduler.advance(batch);
                         self.total_cycles = self.bus.total_cycles();
@@ -1200,6 +1932,7 @@ impl Emu {
                     if skip == 0 { break; }
 
                     self.bus.add_cycles(skip);
+                    self.halted_cycles += skip;
                     cycles_remaining -= skip as i32;
                     self.scheduler.advance(skip);
                     self.total_cycles = self.bus.total_cycles();
@@ -1219,8 +1952,7 @@ impl Emu {
                         peripheral_debt = 0;
                     }
 
-                    if self.cpu.irq_pending && self.cpu.iff1 { break; }
-                    if self.cpu.nmi_pending { break; }
+                    if self.cpu.halt_wake_pending() { break; }
                     if cycles_remaining <= 0 { break; }
                 }
 
@@ -1243,6 +1975,9 @@ impl Emu {
         if !self.rom_loaded || !self.powered_on {
             return None;
         }
+        if let Some(cause) = self.take_reset_request() {
+            self.restart_asic(cause);
+        }
 
         // Sync scheduler with CPU speed setting
         let cpu_speed = self.bus.ports.control.cpu_speed();
@@ -1284,6 +2019,15 @@ impl Emu {
             }
         }
 
+        if let Some(heatmap) = self.heatmap.as_mut() {
+            if !self.cpu.halted {
+                heatmap.record(self.cpu.mask_addr_instr(pc));
+            }
+        }
+        if self.opcode_coverage.is_some() && !self.cpu.halted {
+            self.record_opcode(pc);
+        }
+
         // Execute one instruction
         let cycles_used = self.cpu.step(&mut self.bus);
 
@@ -1321,13 +2065,14 @@ impl Emu {
 
         // Check for NMI from memory protection violations
         if self.bus.take_nmi_flag() {
-            self.cpu.nmi_pending = true;
+            self.raise_protection_nmi();
         }
 
         // Tick peripherals and check for interrupts
         if self.tick_peripherals(cycles_used) {
             self.cpu.irq_pending = true;
         }
+        self.check_display_transitions();
 
         // HALT fast-forward: advance to next scheduled event (batched for DMA efficiency)
         if self.cpu.halted {
@@ -1338,12 +2083,14 @@ impl Emu {
             let mut peripheral_debt: u64 = 0;
 
             loop {
+                if self.cpu.halt_wake_pending() { break; }
                 let skip = self.scheduler.cycles_until_next_event();
                 if skip == 0 {
                     if !self.cpu.iff1 && !self.cpu.nmi_pending { break; }
                     let batch = HALT_TICK_BATCH.min(STEP_HALT_CAP - total_advanced);
                     if batch == 0 { break; }
                     self.bus.add_cycles(batch);
+                    self.halted_cycles += batch;
                     self.scheduler.advance(batch);
                     self.total_cycles = self.bus.total_cycles();
                     total_advanced += batch;
@@ -1358,6 +2105,7 @@ impl Emu {
                 if skip == 0 { break; }
 
                 self.bus.add_cycles(skip);
+                self.halted_cycles += skip;
                 self.scheduler.advance(skip);
                 self.total_cycles = self.bus.total_cycles();
                 total_advanced += skip;
@@ -1372,8 +2120,7 @@ impl Emu {
                     peripheral_debt = 0;
                 }
 
-                if self.cpu.irq_pending && self.cpu.iff1 { break; }
-                if self.cpu.nmi_pending { break; }
+                if self.cpu.halt_wake_pending() { break; }
                 if total_advanced >= STEP_HALT_CAP { break; }
             }
 
@@ -1384,8 +2131,22 @@ impl Emu {
             }
         }
 
+        if let Some(hit) = self.bus.ports.interrupt.take_watch_hit() {
+            self.stop_on_interrupt_watch(hit);
+        }
+        if let Some(hit) = self.bus.watchpoints.take_hit() {
+            self.stop_on_watchpoint(hit);
+        }
+        if let Some(hit) = self.bus.port_watches.take_hit() {
+            self.stop_on_port_watch(hit);
+        }
+        if self.benchmark.is_some() {
+            self.poll_benchmark();
+        }
+
         // Collect I/O ops from this instruction
         let io_ops = self.bus.take_instruction_io_ops();
+        self.sample_power();
 
         Some(StepInfo {
             pc,
@@ -1415,8 +2176,18 @@ impl Emu {
     fn tick_peripherals(&mut self, cycles: u32) -> bool {
         // Get timer delay remaining for the delay pipeline packing
         let delay_remaining = self.scheduler.ticks_remaining(EventId::TimerDelay);
+        self.bus.ports.interrupt.set_clock(self.bus.total_cycles());
         let irq = self.bus.ports.tick(cycles, delay_remaining);
 
+        let restarts = self.bus.ports.watchdog.take_restarts();
+        if restarts > 0 || self.watchdog.armed() {
+            let now_ns = benchmark::emulated_nanos(self.bus.total_cycles(), self.bus.ports.control.cpu_speed());
+            self.watchdog.pet(now_ns, restarts);
+            if let Some(quiet_ms) = self.watchdog.check(now_ns, self.is_off()) {
+                self.emit_event(EventKind::WatchdogStall, quiet_ms);
+            }
+        }
+
         // If timer tick generated new delay pipeline data, schedule the TimerDelay event
         if self.bus.ports.timers.needs_delay_event {
             self.bus.ports.timers.needs_delay_event = false;
@@ -1441,16 +2212,94 @@ impl Emu {
         irq
     }
 
+    /// Bring the peripherals up to date with the cycles run since the last
+    /// call (`Peripherals::unticked_cycles`): scheduler events, DMA
+    /// stealing, SPI start, protection NMIs, peripheral ticks and display
+    /// transitions. Returns the cycles DMA stole from the CPU.
+    fn service_peripherals(&mut self) -> u64 {
+        let cycles = std::mem::take(&mut self.bus.ports.unticked_cycles);
+
+        // Process pending scheduler events
+        self.process_scheduler_events();
+
+        // DMA cycle stealing: if LCD DMA consumed bus time, steal CPU cycles
+        let dma_stolen = self.process_dma_stealing();
+
+        // Check if SPI needs initial scheduling (state changed via port write)
+        if self.bus.take_spi_schedule_flag() && !self.scheduler.is_active(EventId::Spi) {
+            if let Some(ticks) = self.bus.spi().try_start_transfer_for_scheduler() {
+                self.scheduler.set(EventId::Spi, ticks);
+            }
+        }
+
+        // Check for NMI from memory protection violations
+        if self.bus.take_nmi_flag() {
+            self.raise_protection_nmi();
+        }
+
+        // Tick peripherals and check for interrupts
+        if self.tick_peripherals(cycles) {
+            self.cpu.irq_pending = true;
+        }
+        self.check_display_transitions();
+
+        let next_event = self.scheduler.cycles_until_next_event().min(u32::MAX as u64) as u32;
+        self.service_deadline = next_event.min(self.bus.ports.cycles_until_tick_event());
+        dma_stolen
+    }
+
+    /// Service the peripherals if run_cycles left cycles unticked, e.g.
+    /// before returning early
+    fn flush_peripherals(&mut self) {
+        if self.bus.ports.unticked_cycles > 0 {
+            self.service_peripherals();
+            self.total_cycles = self.bus.total_cycles();
+        }
+    }
+
+    fn raise_protection_nmi(&mut self) {
+        self.cpu.nmi_pending = true;
+        self.log_nmi();
+        self.emit_event(EventKind::Trap, self.cpu.pc);
+        // The OS answers a violation by resetting: keep the state that led to it
+        self.autosave(AutosaveReason::Trap);
+    }
+
+    /// Choose how often run_cycles services the peripherals
+    pub fn set_run_granularity(&mut self, granularity: RunGranularity) {
+        self.granularity = granularity;
+    }
+
+    pub fn run_granularity(&self) -> RunGranularity {
+        self.granularity
+    }
+
+    /// Seconds the next RTC event adds if it is a TICK: always 1 on the
+    /// emulated clock, else the whole host seconds elapsed since the last one
+    fn rtc_tick_seconds(&mut self) -> u32 {
+        if self.rtc_clock == RtcClock::Emulated || self.bus.ports.rtc.mode() != RtcMode::Tick {
+            return 1;
+        }
+        // Bound the catch-up per tick; the rest is added on later ticks
+        const MAX_CATCH_UP: u64 = 24 * 60 * 60;
+        let host_secs = self.host_clock.running_ms() / 1000;
+        let seconds = host_secs.saturating_sub(self.rtc_host_secs).min(MAX_CATCH_UP);
+        self.rtc_host_secs += seconds;
+        seconds as u32
+    }
+
     /// Process any pending scheduler events
     fn process_scheduler_events(&mut self) {
         use crate::peripherals::interrupt::sources;
 
+        self.bus.ports.interrupt.set_clock(self.bus.total_cycles());
         // Process all pending events
         while let Some(event) = self.scheduler.next_pending_event() {
             match event {
                 EventId::Rtc => {
                     // Process RTC event using 3-state machine (TICK/LATCH/LOAD_LATCH)
-                    let (next_delay, raise_interrupt) = self.bus.ports.rtc.process_event();
+                    let seconds = self.rtc_tick_seconds();
+                    let (next_delay, raise_interrupt) = self.bus.ports.rtc.process_event_seconds(seconds);
                     if raise_interrupt {
                         // TODO: Wire RTC interrupt to interrupt controller
                         // CEmu: intrpt_set(INT_RTC, true) — INT_RTC is a dedicated line
@@ -1515,6 +2364,25 @@ impl Emu {
                 EventId::Lcd => {
                     // LCD event state machine — matches CEmu's lcd_event()
                     let result = self.bus.ports.lcd.process_event();
+                    if result.frame_start {
+                        self.frame_counters.generated += 1;
+                        if let Some(seen) = self.vsync_stop.as_mut() {
+                            *seen = true;
+                        }
+                        if let Some(scanline) = self.scanline.as_mut() {
+                            scanline.vsync(|rows, frame| Self::decode_rows(&self.bus, rows, frame));
+                        }
+                        let lcd = &self.bus.ports.lcd;
+                        let valid = Self::upbase_ram_offset(lcd.upbase(), Self::frame_bytes(lcd.bpp_mode())).is_some();
+                        self.emit_event_at(EventKind::FrameDone, valid as u32, self.bus.total_cycles());
+                        if self.frame_sink.is_some() {
+                            let hash = self.lcd_register_hash();
+                            let written = self.bus.take_lcd_frame_written();
+                            if let Some(sink) = self.frame_sink.as_mut() {
+                                sink.deliver(self.frame_counters.generated, hash, written);
+                            }
+                        }
+                    }
                     // Update interrupt controller based on lcd.ris & lcd.imsc
                     if result.interrupt_changed {
                         if self.bus.ports.lcd.check_interrupt() {
@@ -1560,6 +2428,10 @@ impl Emu {
                     } else {
                         self.scheduler.clear(EventId::LcdDma);
                     }
+                    if let Some(scanline) = self.scanline.as_mut() {
+                        let row = self.bus.ports.lcd.cur_row() as usize;
+                        scanline.catch_up(row, |rows, frame| Self::decode_rows(&self.bus, rows, frame));
+                    }
                 }
                 _ => {
                     // Unknown event - clear it
@@ -1608,7 +2480,7 @@ impl Emu {
 
     /// Peek at opcode bytes at address without affecting state
     /// Returns (bytes, length) to avoid heap allocation in hot loop
-    fn peek_opcode(&mut self, addr: u32) -> ([u8; 4], usize) {
+    fn peek_opcode(&self, addr: u32) -> ([u8; 4], usize) {
         let mut bytes = [0u8; 4];
         let first = self.bus.peek_byte(addr);
         bytes[0] = first;
@@ -1651,7 +2523,56 @@ impl Emu {
         &self.framebuffer
     }
 
-    /// Set key state
+    /// Record that the frontend read the framebuffer (see FrameCounters).
+    /// Repeated reads of the same frame count once.
+    pub fn note_frame_fetched(&mut self) {
+        let generated = self.frame_counters.generated;
+        if generated > self.last_fetched_frame {
+            self.frame_counters.fetched += 1;
+            self.frame_counters.dropped += generated - self.last_fetched_frame - 1;
+            self.last_fetched_frame = generated;
+        }
+    }
+
+    pub fn frame_counters(&self) -> FrameCounters {
+        self.frame_counters
+    }
+
+    /// Copy the framebuffer into `out` at an integer scale, rotated
+    /// clockwise (see scale::transform). Returns the output size, or None
+    /// for a bad scale or a too-small buffer.
+    pub fn framebuffer_scaled(&self, scale: usize, rotation: Rotation, out: &mut [u32]) -> Option<(usize, usize)> {
+        scale::transform(&self.framebuffer, SCREEN_WIDTH, SCREEN_HEIGHT, scale, rotation, out)
+    }
+
+    /// Copy the framebuffer (ARGB8888) into the start of `out`. Returns
+    /// false, copying nothing, if `out` is smaller than the screen.
+    pub fn copy_framebuffer(&self, out: &mut [u32]) -> bool {
+        let Some(out) = out.get_mut(..self.framebuffer.len()) else {
+            return false;
+        };
+        out.copy_from_slice(&self.framebuffer);
+        true
+    }
+
+    /// The framebuffer encoded as a PNG file
+    pub fn screenshot_png(&self) -> Vec<u8> {
+        png::encode(SCREEN_WIDTH, SCREEN_HEIGHT, &self.framebuffer)
+    }
+
+    /// Copy the framebuffer into `out` with the debug HUD selected by
+    /// `flags` (overlay::HUD_*) drawn on top. Returns false, copying
+    /// nothing, if `out` is smaller than the screen.
+    pub fn debug_overlay(&self, flags: u32, out: &mut [u32]) -> bool {
+        if !self.copy_framebuffer(out) {
+            return false;
+        }
+        let lines = overlay::hud_lines(flags, self.cpu.pc, self.frame_counters.generated, self.watches.values());
+        overlay::draw_lines(out, SCREEN_WIDTH, SCREEN_HEIGHT, &lines);
+        true
+    }
+
+    /// Set key state
     /// Special handling for ON key (row 2, col 0) which has dedicated interrupt
     /// Set key state in the keypad matrix.
     ///
@@ -1677,6 +2598,8 @@ impl Emu {
     ///
     /// See docs/findings.md "TI-OS Expression Parser Requires Initialization After Boot"
     pub fn set_key(&mut self, row: usize, col: usize, down: bool) {
+        self.note_host_key(row, col, down);
+
         // Auto-initialize TI-OS parser on first key press after boot
         // Skip ON key (row 2, col 0) - it's for power management, not normal input
         if down && !self.boot_init_done && self.total_cycles > BOOT_COMPLETE_CYCLES && !(row == 2 && col == 0) {
@@ -1703,6 +2626,43 @@ impl Emu {
             }
         }
 
+        self.apply_key(row, col, down);
+    }
+
+    /// `set_key` for a key by name (see `crate::key_name`)
+    pub fn set_named_key(&mut self, key: KeyName, down: bool) {
+        let (row, col) = key.position();
+        self.set_key(row, col, down);
+    }
+
+    /// Press or release ON, the key with its own interrupt and wake line
+    /// (`set_key(2, 0, down)` without the boot-screen handling). Returns true
+    /// if the press woke the calculator: powered it on or brought it out of
+    /// the OS's off state.
+    pub fn set_on_key(&mut self, down: bool) -> bool {
+        self.note_host_key(2, 0, down);
+        if down {
+            self.press_on_key()
+        } else {
+            self.release_on_key();
+            false
+        }
+    }
+
+    /// Frontend bookkeeping for a key press or release: the held set used
+    /// to reconcile state loads, and any macro being recorded
+    fn note_host_key(&mut self, row: usize, col: usize, down: bool) {
+        if row < KEYPAD_ROWS && col < KEYPAD_COLS {
+            self.host_keys[row][col] = down;
+        }
+        if let Some(recorder) = self.macro_recorder.as_mut() {
+            recorder.key(self.total_cycles, row, col, down);
+        }
+    }
+
+    /// Press or release a key in the emulated matrix (set_key without the
+    /// boot-screen handling or frontend bookkeeping)
+    fn apply_key(&mut self, row: usize, col: usize, down: bool) {
         // ON key (row 2, col 0) has special handling - it can wake from HALT
         // even with interrupts disabled and raises dedicated ON_KEY interrupt
         if row == 2 && col == 0 {
@@ -1747,6 +2707,84 @@ impl Emu {
         self.bus.ports.control.lcd_flag_enabled() && self.bus.ports.lcd.is_powered()
     }
 
+    /// Current LCD/backlight state as seen by transition events
+    fn display_state(&self) -> DisplayState {
+        DisplayState {
+            lcd_on: self.is_lcd_on(),
+            backlight_on: !self.bus.ports.backlight.is_off(),
+        }
+    }
+
+    /// Register (or clear with None) the frontend event callback.
+    pub fn set_event_callback(&mut self, callback: Option<EventCallback>, user: *mut c_void) {
+        self.event_sink = callback.map(|cb| EventSink::new(cb, user));
+    }
+
+    /// Register (or clear with None) the callback fired at each LCD
+    /// vertical sync (see `crate::events::FrameCallback`).
+    pub fn set_frame_callback(&mut self, callback: Option<FrameCallback>, user: *mut c_void) {
+        self.frame_sink = callback.map(|cb| FrameSink::new(cb, user));
+    }
+
+    /// Hash of the LCD registers that change the picture without touching
+    /// the frame's RAM: UPBASE, control and the palette
+    fn lcd_register_hash(&self) -> u64 {
+        let lcd = &self.bus.ports.lcd;
+        let registers = [lcd.upbase() as u64, lcd.control() as u64];
+        hash::fnv1a(registers.into_iter().chain(lcd.palette_for_mode().iter().map(|&color| color as u64)))
+    }
+
+    /// Send every CPU data read and write in `start..=end` to `callback`
+    /// (see `crate::snoop`), or stop snooping with None.
+    pub fn set_snoop(&mut self, start: u32, end: u32, callback: Option<SnoopCallback>, user: *mut c_void) {
+        self.bus.set_snoop(callback.map(|cb| Snoop::new(start, end, cb, user)));
+    }
+
+    /// Deliver an event stamped with the current cycle count to the callback
+    /// and the poll queue.
+    fn emit_event(&mut self, kind: EventKind, value: u32) {
+        self.emit_event_at(kind, value, self.total_cycles);
+    }
+
+    /// `emit_event` from inside the run loop, where `total_cycles` is only
+    /// brought up to date between instructions
+    fn emit_event_at(&mut self, kind: EventKind, value: u32, cycles: u64) {
+        let event = EmuEvent::new(kind, value, cycles);
+        if let Some(sink) = self.event_sink {
+            sink.deliver(&event);
+        }
+        self.event_queue.push(event);
+    }
+
+    /// Oldest queued event, if any
+    pub fn poll_event(&mut self) -> Option<EmuEvent> {
+        self.event_queue.pop()
+    }
+
+    /// Events dropped from the full poll queue since creation
+    pub fn events_dropped(&self) -> u64 {
+        self.event_queue.dropped()
+    }
+
+    /// Emit LCD/backlight on/off events for state changes since the last check.
+    /// Called after every instruction's port writes have been applied.
+    #[inline]
+    fn check_display_transitions(&mut self) {
+        let now = self.display_state();
+        if now == self.display_state {
+            return;
+        }
+        let prev = std::mem::replace(&mut self.display_state, now);
+        if now.lcd_on != prev.lcd_on {
+            let kind = if now.lcd_on { EventKind::LcdOn } else { EventKind::LcdOff };
+            self.emit_event(kind, 0);
+        }
+        if now.backlight_on != prev.backlight_on {
+            let kind = if now.backlight_on { EventKind::BacklightOn } else { EventKind::BacklightOff };
+            self.emit_event(kind, self.get_backlight() as u32);
+        }
+    }
+
     /// Check if the device is in the "off" (sleep) state.
     /// The OS writes bit 6 of the POWER register to enter sleep mode.
     /// The device stays off until an ON key press triggers a WAKE interrupt.
@@ -1761,17 +2799,22 @@ impl Emu {
     /// and clears control.off. The pulse (set then clear) handles the case where
     /// the OS has configured WAKE as inverted — the clear step sets the status bit.
     /// wake() also sets readBatteryStatus = 0xFE so the OS WAKE ISR sees a valid battery.
-    pub fn press_on_key(&mut self) {
+    ///
+    /// ON drives its own line, not the keypad matrix the OS scans. Returns
+    /// true if the press woke the calculator (powered it on, or woke it
+    /// from the off state).
+    pub fn press_on_key(&mut self) -> bool {
         use crate::peripherals::interrupt::sources;
 
         log_evt!("ON_KEY pressed");
         // Power on the calculator
+        let mut woke = !self.powered_on;
         self.powered_on = true;
         // Set the one-shot wake signal — consumed on first cpu.step() call.
         self.cpu.on_key_wake = true;
 
-        // Set ON key in keypad matrix (row 2, col 0)
-        self.bus.set_key(2, 0, true);
+        self.host_keys[2][0] = true;
+        self.on_held = true;
 
         // Raise INT_ON (matches CEmu: intrpt_set(INT_ON, onState) with onState truthy)
         self.bus.ports.interrupt.raise(sources::ON_KEY);
@@ -1781,6 +2824,7 @@ impl Emu {
         // wake() clears off and sets readBatteryStatus=0xFE so the OS ISR sees valid battery.
         if self.bus.ports.control.is_off() {
             log_evt!("WAKE: device off, clearing off + pulsing WAKE");
+            woke = true;
             self.bus.ports.control.wake();
             self.bus.ports.interrupt.pulse(sources::WAKE);
             // Disable APD on every wake — if the OS put the device to sleep via APD,
@@ -1793,6 +2837,7 @@ impl Emu {
         // Ensure CPU sees a pending interrupt even if interrupts are disabled.
         // ON key wake is special: ROM expects an interrupt path to run after wake.
         self.cpu.irq_pending = true;
+        woke
     }
 
     /// Release the ON key
@@ -1802,7 +2847,8 @@ impl Emu {
     pub fn release_on_key(&mut self) {
         use crate::peripherals::interrupt::sources;
         log_evt!("ON_KEY released");
-        self.bus.set_key(2, 0, false);
+        self.host_keys[2][0] = false;
+        self.on_held = false;
         self.bus.ports.interrupt.clear_raw(sources::ON_KEY);
     }
 
@@ -1810,11 +2856,34 @@ impl Emu {
     /// Call this after loading ROM but before run_cycles to simulate
     /// the calculator being turned on via the ON key
     pub fn power_on(&mut self) {
+        if let Some(recorder) = self.macro_recorder.as_mut() {
+            recorder.power_on(self.total_cycles);
+        }
         // Simulate the ON key being pressed and released.
         self.press_on_key();
         self.release_on_key();
     }
 
+    /// Keys held in the matrix, one byte per row (bit = column), after any
+    /// macro playback. The ON line shows at row 2 bit 0 although it isn't
+    /// part of the scanned matrix.
+    pub fn key_matrix(&self) -> [u8; KEYPAD_ROWS] {
+        let mut rows = [0u8; KEYPAD_ROWS];
+        for (bits, keys) in rows.iter_mut().zip(self.bus.key_state()) {
+            for (col, &down) in keys.iter().enumerate() {
+                *bits |= (down as u8) << col;
+            }
+        }
+        rows[2] |= self.on_held as u8;
+        rows
+    }
+
+    /// Press edges the keypad hasn't scanned yet: a key tapped and released
+    /// between scans still reads as pressed by the next scan of its row
+    pub fn key_edges(&self) -> [u8; KEYPAD_ROWS] {
+        self.bus.ports.keypad.edge_bits()
+    }
+
     /// Get current keypad mode (for debugging)
     pub fn keypad_mode(&self) -> u8 {
         self.bus.ports.keypad.mode()
@@ -1822,93 +2891,225 @@ impl Emu {
 
     /// Render the current VRAM contents to the framebuffer.
     /// Supports both 8bpp indexed color (used by graphx games) and 16bpp RGB565 (used by TI-OS).
+    /// With scanline rendering on, shows the last frame the LCD scanned out instead.
     pub fn render_frame(&mut self) {
-        let upbase = self.bus.ports.lcd.upbase();
-        let bpp_mode = self.bus.ports.lcd.bpp_mode();
+        let scanned = self.scanline.as_deref().filter(|_| self.bus.ports.lcd.is_enabled()).and_then(|s| s.frame());
+        self.upbase_valid = match scanned {
+            Some((frame, valid)) => {
+                self.framebuffer.copy_from_slice(frame);
+                valid
+            }
+            None => Self::decode_rows(&self.bus, 0..SCREEN_HEIGHT, &mut self.framebuffer),
+        };
+        // The diagnostic pattern stays exact
+        if self.upbase_valid {
+            self.color.apply_all(&mut self.framebuffer);
+        }
+        self.update_formatted_framebuffer();
 
-        match bpp_mode {
-            3 => self.render_frame_8bpp(upbase),
-            _ => self.render_frame_16bpp(upbase),
+        if !self.watches.is_empty() {
+            self.watches.evaluate(&self.cpu, &mut self.bus);
+        }
+        if let Some(changed) = self.bus.mmio_diff_mut().map(|diff| diff.finish_frame()) {
+            if changed > 0 {
+                self.emit_event(EventKind::MmioDiff, changed as u32);
+            }
         }
     }
 
-    /// Render 8bpp indexed color mode (BPP=3).
-    /// Each VRAM byte is a palette index. The palette at LCD 0xE30200 maps indices to colors.
-    /// This is what the graphx library and all CE games use.
-    fn render_frame_8bpp(&mut self, upbase: u32) {
-        let ram_offset = upbase.wrapping_sub(crate::memory::addr::RAM_START) as usize;
-        let needed = SCREEN_WIDTH * SCREEN_HEIGHT; // 1 byte per pixel
-        let ram_data = self.bus.ram.data();
-        // Copy palette to avoid borrow conflict in fallback path
-        let palette = *self.bus.ports.lcd.palette_for_mode();
+    /// Decode `rows` of the frame at UPBASE into `frame` (a full 320x240
+    /// ARGB8888 frame). 8bpp (BPP=3) is palette indices, as graphx and CE
+    /// games use, with the palette at LCD 0xE30200; every other mode is
+    /// read as 16bpp RGB565, as TI-OS uses. Returns false, drawing the
+    /// diagnostic pattern, when UPBASE does not point at a full frame of RAM.
+    fn decode_rows(bus: &Bus, rows: Range<usize>, frame: &mut [u32]) -> bool {
+        let lcd = &bus.ports.lcd;
+        let indexed = lcd.bpp_mode() == 3;
+        let bytes_per_pixel = if indexed { 1 } else { 2 };
+        let out = &mut frame[rows.start * SCREEN_WIDTH..rows.end * SCREEN_WIDTH];
+        let Some(ram_offset) = Self::upbase_ram_offset(lcd.upbase(), Self::frame_bytes(lcd.bpp_mode())) else {
+            Self::draw_invalid_upbase(rows, out);
+            return false;
+        };
+        let ram_data = bus.ram.data();
+        let palette = lcd.palette_for_mode();
 
-        if ram_offset < ram_data.len() && ram_offset + needed <= ram_data.len() {
-            let vram = &ram_data[ram_offset..ram_offset + needed];
-            for (i, &index) in vram.iter().enumerate() {
-                let bgr565 = palette[index as usize];
-                self.framebuffer[i] = bgr565_to_argb8888(bgr565);
+        if ram_data.is_empty() {
+            // RAM not allocated yet reads as zero
+            out.fill(if indexed { bgr565_to_argb8888(palette[0]) } else { rgb565_to_argb8888(0) });
+            return true;
+        }
+        let start = ram_offset + rows.start * SCREEN_WIDTH * bytes_per_pixel;
+        let vram = &ram_data[start..start + out.len() * bytes_per_pixel];
+        if indexed {
+            for (pixel, &index) in out.iter_mut().zip(vram) {
+                *pixel = bgr565_to_argb8888(palette[index as usize]);
             }
         } else {
-            // Fallback for out-of-range UPBASE
-            for i in 0..(SCREEN_WIDTH * SCREEN_HEIGHT) {
-                let vram_addr = upbase + i as u32;
-                let index = self.bus.peek_byte(vram_addr);
-                let bgr565 = palette[index as usize];
-                self.framebuffer[i] = bgr565_to_argb8888(bgr565);
+            for (pixel, chunk) in out.iter_mut().zip(vram.chunks_exact(2)) {
+                *pixel = rgb565_to_argb8888(u16::from_le_bytes([chunk[0], chunk[1]]));
             }
         }
+        true
     }
 
-    /// Render 16bpp direct color mode (BPP=4+).
-    /// Each pixel is 2 bytes of RGB565 in VRAM. Used by TI-OS boot screen.
-    fn render_frame_16bpp(&mut self, upbase: u32) {
-        let ram_offset = upbase.wrapping_sub(crate::memory::addr::RAM_START) as usize;
-        let needed = SCREEN_WIDTH * SCREEN_HEIGHT * 2;
-        let ram_data = self.bus.ram.data();
+    /// Bytes in one frame for an LCD BPP mode (see `decode_rows`)
+    fn frame_bytes(bpp_mode: u8) -> usize {
+        SCREEN_WIDTH * SCREEN_HEIGHT * if bpp_mode == 3 { 1 } else { 2 }
+    }
 
-        if ram_offset < ram_data.len() && ram_offset + needed <= ram_data.len() {
-            let vram = &ram_data[ram_offset..ram_offset + needed];
-            for (i, chunk) in vram.chunks_exact(2).enumerate() {
-                let rgb565 = u16::from_le_bytes([chunk[0], chunk[1]]);
-                self.framebuffer[i] = rgb565_to_argb8888(rgb565);
-            }
-        } else {
-            // Fallback for out-of-range UPBASE (e.g. before LCD is configured)
-            for y in 0..SCREEN_HEIGHT {
-                for x in 0..SCREEN_WIDTH {
-                    let pixel_offset = (y * SCREEN_WIDTH + x) * 2;
-                    let vram_addr = upbase + pixel_offset as u32;
-                    let lo = self.bus.peek_byte(vram_addr) as u16;
-                    let hi = self.bus.peek_byte(vram_addr + 1) as u16;
-                    let rgb565 = lo | (hi << 8);
-                    self.framebuffer[y * SCREEN_WIDTH + x] = rgb565_to_argb8888(rgb565);
-                }
+    /// RAM offset of a `needed`-byte frame at `upbase`, or None if any part
+    /// of it falls outside RAM.
+    fn upbase_ram_offset(upbase: u32, needed: usize) -> Option<usize> {
+        use crate::memory::addr::{RAM_END, RAM_START};
+        let end = upbase as u64 + needed as u64;
+        (upbase >= RAM_START && end <= RAM_END as u64).then(|| (upbase - RAM_START) as usize)
+    }
+
+    /// Diagnostic pattern for `rows` when UPBASE does not point at a full
+    /// frame of RAM (unconfigured LCD, OS bug or crashed program), instead
+    /// of showing whatever flash/MMIO happens to be there. Magenta/black
+    /// 8x8 checkerboard, so it cannot be mistaken for content.
+    fn draw_invalid_upbase(rows: Range<usize>, out: &mut [u32]) {
+        for (y, line) in rows.zip(out.chunks_exact_mut(SCREEN_WIDTH)) {
+            for (x, pixel) in line.iter_mut().enumerate() {
+                let on = ((x >> 3) ^ (y >> 3)) & 1 != 0;
+                *pixel = if on { INVALID_UPBASE_COLOR } else { 0xFF000000 };
             }
         }
     }
 
+    /// Whether the last rendered frame read from a valid UPBASE
+    /// (false means the diagnostic pattern was shown).
+    pub fn upbase_valid(&self) -> bool {
+        self.upbase_valid
+    }
+
+    /// Choose the color profile render_frame applies (takes effect on the
+    /// next frame).
+    pub fn set_color_profile(&mut self, profile: ColorProfile) {
+        if profile != self.color.profile() {
+            self.color = ColorTransform::new(profile);
+        }
+    }
+
+    pub fn color_profile(&self) -> ColorProfile {
+        self.color.profile()
+    }
+
+    /// Render each row as the LCD DMA fetches it instead of snapshotting
+    /// VRAM after the run, so mid-frame palette/VRAM changes show as on
+    /// hardware (see `crate::scanline`). Costs a second frame buffer.
+    pub fn set_scanline_render(&mut self, on: bool) {
+        if on != self.scanline.is_some() {
+            self.scanline = on.then(|| Box::new(ScanlineRenderer::new()));
+        }
+    }
+
+    pub fn scanline_render(&self) -> bool {
+        self.scanline.is_some()
+    }
+
+    /// Choose the format `formatted_framebuffer` is kept in. The current
+    /// frame is converted right away; ARGB8888 drops the second buffer.
+    pub fn set_pixel_format(&mut self, format: PixelFormat) {
+        self.pixel_format = format;
+        if format == PixelFormat::Argb8888 {
+            self.formatted_framebuffer = Vec::new();
+        }
+        self.update_formatted_framebuffer();
+    }
+
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.pixel_format
+    }
+
+    fn update_formatted_framebuffer(&mut self) {
+        if self.pixel_format != PixelFormat::Argb8888 {
+            pixel_format::convert(self.pixel_format, &self.framebuffer, &mut self.formatted_framebuffer);
+        }
+    }
+
+    /// Raw pointer to the last frame in `pixel_format` (the framebuffer
+    /// itself for ARGB8888)
+    pub fn formatted_framebuffer_ptr(&self) -> *const u8 {
+        match self.pixel_format {
+            PixelFormat::Argb8888 => self.framebuffer.as_ptr() as *const u8,
+            _ => self.formatted_framebuffer.as_ptr(),
+        }
+    }
+
+    /// The last frame in `pixel_format`, or None for ARGB8888 (use
+    /// `framebuffer_data`)
+    pub fn formatted_framebuffer(&self) -> Option<&[u8]> {
+        (self.pixel_format != PixelFormat::Argb8888).then_some(&self.formatted_framebuffer[..])
+    }
+
+    /// Bits per pixel of the raw frame at UPBASE: 8 (palette indices, BPP=3)
+    /// or 16 (RGB565), matching what render_frame decodes.
+    pub fn vram_bpp(&self) -> u32 {
+        if self.bus.ports.lcd.bpp_mode() == 3 { 8 } else { 16 }
+    }
+
+    /// Raw frame at the current UPBASE, before any color conversion:
+    /// 320x240 pixels at `vram_bpp`, little-endian for 16bpp. None when
+    /// UPBASE doesn't point at a full frame of RAM or RAM isn't allocated yet.
+    pub fn vram(&self) -> Option<&[u8]> {
+        let needed = SCREEN_WIDTH * SCREEN_HEIGHT * self.vram_bpp() as usize / 8;
+        let offset = Self::upbase_ram_offset(self.bus.ports.lcd.upbase(), needed)?;
+        self.bus.ram.data().get(offset..offset + needed)
+    }
+
+    /// Effective LCD refresh rate in millihertz derived from the timing
+    /// registers (0 when the LCD is disabled).
+    pub fn lcd_refresh_rate_millihz(&self) -> u32 {
+        self.bus.ports.lcd.refresh_rate_millihz()
+    }
+
     // ========== State Persistence ==========
 
-    /// State format version (v9: LCD palette + cursor state in peripheral snapshot)
-    const STATE_VERSION: u32 = 10;
+    /// State format version (v9: LCD palette + cursor state in peripheral
+    /// snapshot, v11: header holds `rom_hash` instead of a boot code hash,
+    /// v12: scheduler events saved as id/clock/deadline entries, v13:
+    /// header holds a CRC-32 of the payload)
+    pub(crate) const STATE_VERSION: u32 = 13;
     /// Magic bytes for state file identification
     const STATE_MAGIC: [u8; 4] = *b"CE84";
-    /// Header size: magic(4) + version(4) + rom_hash(8) + data_len(4) = 20
-    const STATE_HEADER_SIZE: usize = 20;
+    /// Header size: magic(4) + version(4) + rom_hash(8) + data_len(4) +
+    /// crc32(4) = 24
+    const STATE_HEADER_SIZE: usize = 24;
+    /// Header size before v13, without the CRC
+    const LEGACY_STATE_HEADER_SIZE: usize = 20;
     /// Metadata size: powered_on(1) + total_cycles(8) + boot_init_done(1) + padding(6) = 16
     const STATE_META_SIZE: usize = 16;
 
-    /// Compute a simple hash of the ROM for state validation
-    fn compute_rom_hash(&self) -> u64 {
+    /// Stable identifier of the loaded ROM: a hash of the whole flash
+    /// image, boot code and OS included, taken by `load_rom` (and again
+    /// after `flash_os`). Writes the OS makes to flash while running
+    /// don't change it, so frontends can key per-ROM settings, fast-boot
+    /// snapshots and flash deltas on it. Save states record it and refuse
+    /// to load under a different ROM. None until a ROM is loaded.
+    pub fn rom_hash(&self) -> Option<u64> {
+        self.rom_loaded.then_some(self.rom_hash)
+    }
+
+    /// Model and boot code/OS versions of the loaded ROM, read from the
+    /// flash as it is now (so an installed OS shows its own version).
+    /// None until a ROM is loaded.
+    pub fn rom_info(&self) -> Option<RomInfo> {
+        self.rom_loaded.then(|| RomInfo::parse(self.bus.flash.data()))
+    }
+
+    /// FNV-1a over the flash, 8 bytes at a time
+    fn hash_flash(&self) -> u64 {
+        hash::fnv1a(hash::words(self.bus.flash.data()))
+    }
+
+    /// Boot code hash the header held before v11
+    fn boot_code_hash(&self) -> u64 {
         // FNV-1a hash of first 64KB of ROM (fast, good distribution)
-        let mut hash: u64 = 0xcbf29ce484222325;
         let rom_data = self.bus.flash.data();
-        let len = rom_data.len().min(65536);
-        for &byte in &rom_data[..len] {
-            hash ^= byte as u64;
-            hash = hash.wrapping_mul(0x100000001b3);
-        }
-        hash
+        hash::fnv1a(hash::bytes(&rom_data[..rom_data.len().min(65536)]))
     }
 
     /// Log NMI trigger details
@@ -1957,7 +3158,7 @@ impl Emu {
 
     /// Save emulator state to buffer
     /// Returns number of bytes written on success
-    pub fn save_state(&self, buffer: &mut [u8]) -> Result<usize, i32> {
+    pub fn save_state(&self, buffer: &mut [u8]) -> Result<usize, EmuError> {
         use crate::cpu::Cpu;
         use crate::memory::addr::{FLASH_SIZE, RAM_SIZE};
         use crate::peripherals::Peripherals;
@@ -1965,7 +3166,7 @@ impl Emu {
 
         let required = self.save_state_size();
         if buffer.len() < required {
-            return Err(-101); // Buffer too small
+            return Err(EmuError::StateBufferTooSmall);
         }
 
         let mut pos = 0;
@@ -1975,11 +3176,13 @@ impl Emu {
         pos += 4;
         buffer[pos..pos+4].copy_from_slice(&Self::STATE_VERSION.to_le_bytes());
         pos += 4;
-        buffer[pos..pos+8].copy_from_slice(&self.compute_rom_hash().to_le_bytes());
+        buffer[pos..pos+8].copy_from_slice(&self.rom_hash.to_le_bytes());
         pos += 8;
         let data_len = (required - Self::STATE_HEADER_SIZE) as u32;
         buffer[pos..pos+4].copy_from_slice(&data_len.to_le_bytes());
         pos += 4;
+        let crc_pos = pos;
+        pos += 4; // CRC, filled in once the payload is written
 
         // Write CPU state
         let cpu_bytes = self.cpu.to_bytes();
@@ -2002,9 +3205,13 @@ impl Emu {
         buffer[pos] = if self.boot_init_done { 1 } else { 0 }; pos += 1;
         pos += 6; // Padding to 16 bytes
 
-        // Write RAM
+        // Write RAM (still unallocated if nothing was ever written)
         let ram_data = self.bus.ram.data();
-        buffer[pos..pos+RAM_SIZE].copy_from_slice(ram_data);
+        if ram_data.is_empty() {
+            buffer[pos..pos+RAM_SIZE].fill(0);
+        } else {
+            buffer[pos..pos+RAM_SIZE].copy_from_slice(ram_data);
+        }
         pos += RAM_SIZE;
 
         // Write Flash
@@ -2012,65 +3219,147 @@ impl Emu {
         buffer[pos..pos+FLASH_SIZE].copy_from_slice(flash_data);
         pos += FLASH_SIZE;
 
+        let crc = crc32(&buffer[Self::STATE_HEADER_SIZE..pos]);
+        buffer[crc_pos..crc_pos+4].copy_from_slice(&crc.to_le_bytes());
+
         log_evt!("STATE_SAVED: {} bytes", pos);
         Ok(pos)
     }
 
     /// Load emulator state from buffer
-    pub fn load_state(&mut self, buffer: &[u8]) -> Result<(), i32> {
+    pub fn load_state(&mut self, buffer: &[u8]) -> Result<(), LoadError> {
+        self.restore_state(buffer, false).map(|_| ())
+    }
+
+    /// Like `load_state`, but a state saved with a different ROM or OS
+    /// still loads: RAM, registers and peripherals are restored while the
+    /// current flash is kept. Code running from flash may then not match
+    /// what the state expects, so this is a last resort for recovering RAM
+    /// (programs, variables in RAM) rather than an exact restore.
+    pub fn load_state_best_effort(&mut self, buffer: &[u8]) -> Result<StateLoad, LoadError> {
+        self.restore_state(buffer, true)
+    }
+
+    /// Save state for bug reports with the flash replaced by sector hashes,
+    /// so no ROM contents are shared (see `crate::redact`)
+    pub fn save_redacted_state(&self) -> Result<Vec<u8>, EmuError> {
+        if !self.rom_loaded {
+            return Err(EmuError::NoRom);
+        }
+        let mut state = vec![0u8; self.save_state_size()];
+        let size = self.save_state(&mut state)?;
+        state.truncate(size);
+        Ok(redact::redact(&state, self.bus.flash.data()))
+    }
+
+    /// Load a redacted state, taking the flash from the loaded ROM (which
+    /// must be the one the state was saved with). Returns the flash
+    /// sectors that differ from the reporter's, e.g. archive variables
+    /// written during their session.
+    pub fn load_redacted_state(&mut self, bundle: &[u8]) -> Result<Vec<usize>, EmuError> {
+        if !self.rom_loaded {
+            return Err(EmuError::NoRom);
+        }
+        let (mut state, differing) = redact::restore(bundle, self.bus.flash.data())?;
+        // The state's own CRC covered the reporter's flash
+        if state.len() < Self::STATE_HEADER_SIZE {
+            return Err(LoadError::StateTooShort { len: state.len(), min: Self::STATE_HEADER_SIZE }.into());
+        }
+        let crc = crc32(&state[Self::STATE_HEADER_SIZE..]);
+        state[Self::STATE_HEADER_SIZE - 4..Self::STATE_HEADER_SIZE].copy_from_slice(&crc.to_le_bytes());
+        self.load_state(&state)?;
+        Ok(differing)
+    }
+
+    fn restore_state(&mut self, buffer: &[u8], allow_other_rom: bool) -> Result<StateLoad, LoadError> {
         use crate::cpu::Cpu;
         use crate::memory::addr::{FLASH_SIZE, RAM_SIZE};
         use crate::peripherals::Peripherals;
         use crate::scheduler::Scheduler;
 
-        // Check minimum size for header
-        if buffer.len() < Self::STATE_HEADER_SIZE {
-            return Err(-102); // Invalid magic / too small
+        // Check minimum size for header (the shorter pre-v13 one, until
+        // the version is known)
+        if buffer.len() < Self::LEGACY_STATE_HEADER_SIZE {
+            return Err(LoadError::StateTooShort { len: buffer.len(), min: Self::LEGACY_STATE_HEADER_SIZE });
         }
 
         let mut pos = 0;
 
         // Verify magic
-        if &buffer[pos..pos+4] != &Self::STATE_MAGIC {
-            return Err(-102); // Invalid magic
+        let magic: [u8; 4] = buffer[pos..pos+4].try_into().unwrap();
+        if magic != Self::STATE_MAGIC {
+            return Err(LoadError::BadMagic { expected: Self::STATE_MAGIC, found: magic });
         }
         pos += 4;
 
         // Check version
         let version = u32::from_le_bytes(buffer[pos..pos+4].try_into().unwrap());
-        if version != Self::STATE_VERSION {
-            return Err(-103); // Version mismatch
+        if !(10..=Self::STATE_VERSION).contains(&version) {
+            return Err(LoadError::VersionMismatch { expected: Self::STATE_VERSION, found: version });
         }
         pos += 4;
+        if version >= 13 && buffer.len() < Self::STATE_HEADER_SIZE {
+            return Err(LoadError::StateTooShort { len: buffer.len(), min: Self::STATE_HEADER_SIZE });
+        }
 
-        // Verify ROM hash
+        // Verify ROM hash (v10 only covered the boot code; the layout is
+        // otherwise the same)
         let saved_hash = u64::from_le_bytes(buffer[pos..pos+8].try_into().unwrap());
-        let current_hash = self.compute_rom_hash();
-        if saved_hash != current_hash {
-            return Err(-104); // ROM mismatch
-        }
+        let current_hash = if version == 10 { self.boot_code_hash() } else { self.rom_hash };
+        let loaded = if saved_hash == current_hash {
+            StateLoad::Full
+        } else if allow_other_rom && self.rom_loaded {
+            StateLoad::KeptFlash
+        } else {
+            return Err(LoadError::RomMismatch { expected: current_hash, found: saved_hash });
+        };
         pos += 8;
 
         // Check data length
         let data_len = u32::from_le_bytes(buffer[pos..pos+4].try_into().unwrap()) as usize;
         pos += 4;
+        let saved_crc = (version >= 13).then(|| u32::from_le_bytes(buffer[pos..pos+4].try_into().unwrap()));
+        if saved_crc.is_some() {
+            pos += 4;
+        }
 
-        let expected_data = Cpu::SNAPSHOT_SIZE + Scheduler::SNAPSHOT_SIZE
+        // Before v12 the scheduler section was positional and shorter
+        let sched_size = if version >= 12 { Scheduler::SNAPSHOT_SIZE } else { Scheduler::LEGACY_SNAPSHOT_SIZE };
+        let expected_data = Cpu::SNAPSHOT_SIZE + sched_size
             + Peripherals::SNAPSHOT_SIZE + Self::STATE_META_SIZE + RAM_SIZE + FLASH_SIZE;
         if data_len < expected_data || buffer.len() < pos + data_len {
-            return Err(-105); // Data corruption
+            return Err(LoadError::StateTruncated {
+                expected: expected_data,
+                found: data_len.min(buffer.len() - pos),
+            });
+        }
+        // Nothing is restored from a payload that fails its CRC
+        if let Some(saved_crc) = saved_crc {
+            let actual = crc32(&buffer[pos..pos + data_len]);
+            if actual != saved_crc {
+                return Err(LoadError::ChecksumMismatch { expected: saved_crc, found: actual });
+            }
         }
+        let bad_section = |section, offset| move |_| LoadError::BadSection { section, offset };
 
         // Load CPU state
-        self.cpu.from_bytes(&buffer[pos..pos+Cpu::SNAPSHOT_SIZE])?;
+        self.cpu.from_bytes(&buffer[pos..pos+Cpu::SNAPSHOT_SIZE]).map_err(bad_section("cpu", pos))?;
         pos += Cpu::SNAPSHOT_SIZE;
 
         // Load scheduler state
-        self.scheduler.from_bytes(&buffer[pos..pos+Scheduler::SNAPSHOT_SIZE])?;
-        pos += Scheduler::SNAPSHOT_SIZE;
+        let sched_bytes = &buffer[pos..pos+sched_size];
+        if version >= 12 {
+            self.scheduler.from_bytes(sched_bytes)
+        } else {
+            self.scheduler.from_legacy_bytes(sched_bytes)
+        }
+        .map_err(bad_section("scheduler", pos))?;
+        pos += sched_size;
 
         // Load peripheral state
-        self.bus.ports.from_bytes(&buffer[pos..pos+Peripherals::SNAPSHOT_SIZE])?;
+        self.bus.ports
+            .from_bytes(&buffer[pos..pos+Peripherals::SNAPSHOT_SIZE])
+            .map_err(bad_section("peripherals", pos))?;
         pos += Peripherals::SNAPSHOT_SIZE;
 
         // Load Emu metadata
@@ -2082,9 +3371,20 @@ impl Emu {
         // Load RAM
         self.bus.ram.load_data(&buffer[pos..pos+RAM_SIZE]);
         pos += RAM_SIZE;
+        if let Some(sink) = self.frame_sink.as_mut() {
+            sink.invalidate();
+        }
+        self.watchdog.clear();
+        if let Some(scanline) = self.scanline.as_mut() {
+            scanline.clear();
+        }
 
-        // Load Flash
-        self.bus.flash.load_data(&buffer[pos..pos+FLASH_SIZE]);
+        // Load Flash, unless the state belongs to another ROM
+        if loaded == StateLoad::Full {
+            self.bus.flash.load_data(&buffer[pos..pos+FLASH_SIZE]);
+        } else {
+            log_evt!("STATE_ROM_MISMATCH saved={:016X} current={:016X}: keeping flash", saved_hash, current_hash);
+        }
 
         // Sync bus cycle counter with restored total_cycles.
         // load_rom() → reset() zeroed bus.cycles, but total_cycles was restored
@@ -2104,6 +3404,10 @@ impl Emu {
         self.halt_logged = false;
         self.history.clear();
         self.last_stop = StopReason::CyclesComplete;
+        // The state has no pending combination; its keys are released below
+        // unless the frontend holds them
+        self.combo_release = None;
+        self.reconcile_keys();
 
         log_evt!(
             "STATE_LOADED total_cycles={} bus_cycles={} base_ticks={} dma_ts={} cpu_speed={} pc={:06X}",
@@ -2114,7 +3418,255 @@ impl Emu {
             self.scheduler.cpu_speed(),
             self.cpu.pc
         );
-        Ok(())
+        Ok(loaded)
+    }
+
+    /// Bring the key matrix restored from a state in line with the keys the
+    /// frontend holds right now. A key held when the state was saved but
+    /// up now is released (else it would stay stuck: the frontend never
+    /// sends a release for it), and a key held now but not in the state is
+    /// pressed again. Both go through the normal key path, so edge flags
+    /// and the keypad/ON interrupts follow as for a live press or release.
+    /// Keys that agree keep their restored edge flags and pending interrupts.
+    fn reconcile_keys(&mut self) {
+        for row in 0..KEYPAD_ROWS {
+            for col in 0..KEYPAD_COLS {
+                let held = self.host_keys[row][col];
+                let restored = if (row, col) == (2, 0) { self.on_held } else { self.bus.key_state()[row][col] };
+                if restored != held {
+                    log_evt!("KEY_RECONCILE: ({},{}) -> {}", row, col, held);
+                    self.apply_key(row, col, held);
+                }
+            }
+        }
+    }
+
+    // === Save slots ===
+
+    /// Save the current state into slot `n` (see `crate::slots`).
+    /// Returns the state size in bytes.
+    pub fn slot_save(&mut self, n: usize) -> Result<usize, EmuError> {
+        let mut data = vec![0u8; self.save_state_size()];
+        let size = self.save_state(&mut data)?;
+        data.truncate(size);
+        self.slots.store(n, data, self.total_cycles)?;
+        Ok(size)
+    }
+
+    /// Restore the state held in slot `n`.
+    pub fn slot_load(&mut self, n: usize) -> Result<(), EmuError> {
+        let data = self.slots.get(n)?.to_vec();
+        Ok(self.load_state(&data)?)
+    }
+
+    /// Describe slot `n` (used = 0 if empty).
+    pub fn slot_info(&self, n: usize) -> Result<EmuSlotInfo, EmuError> {
+        self.slots.info(n)
+    }
+
+    /// Free the memory held by slot `n`.
+    pub fn slot_clear(&mut self, n: usize) -> Result<(), EmuError> {
+        self.slots.clear(n)
+    }
+
+    /// Cap the total bytes all slots may hold (None = unlimited).
+    pub fn set_slot_memory_limit(&mut self, limit: Option<usize>) {
+        self.slots.set_limit(limit);
+    }
+
+    // === Time-travel watch ===
+
+    /// Find the last instruction that changed the byte at `addr` by
+    /// replaying from the save slots (see `crate::last_change`). Ok(None)
+    /// means the byte didn't change since the oldest usable slot. The
+    /// current state is restored either way.
+    pub fn find_last_change(&mut self, addr: u32) -> Result<Option<EmuLastChange>, EmuError> {
+        self.last_change(addr, false)
+    }
+
+    /// Like `find_last_change`, but when found leave the emulator stopped
+    /// just after the writing instruction with `StopReason::Watchpoint`.
+    /// The current state is saved to `present_slot` first (replacing what
+    /// it held), so `slot_load(present_slot)` returns to it; if nothing is
+    /// found the current state is restored.
+    pub fn rewind_to_last_change(
+        &mut self,
+        addr: u32,
+        present_slot: usize,
+    ) -> Result<Option<EmuLastChange>, EmuError> {
+        self.slot_save(present_slot)?;
+        self.last_change(addr, true)
+    }
+
+    fn last_change(&mut self, addr: u32, stop: bool) -> Result<Option<EmuLastChange>, EmuError> {
+        let addr = addr & 0xFFFFFF;
+        let now = self.total_cycles;
+        let mut snapshots: Vec<(usize, u64)> = (0..MAX_SLOTS)
+            .filter_map(|n| self.slots.info(n).ok().map(|info| (n, info)))
+            .filter(|(_, info)| info.used != 0 && info.cycles < now)
+            .map(|(n, info)| (n, info.cycles))
+            .collect();
+        if snapshots.is_empty() {
+            return Err(EmuError::NoSnapshot);
+        }
+        snapshots.sort_by_key(|&(_, cycles)| std::cmp::Reverse(cycles));
+
+        let mut present = vec![0u8; self.save_state_size()];
+        let size = self.save_state(&mut present)?;
+        present.truncate(size);
+
+        let breakpoints = std::mem::take(&mut self.breakpoints);
+        let watchpoints = std::mem::take(&mut self.bus.watchpoints);
+        let port_watches = std::mem::take(&mut self.bus.port_watches);
+        let snoop = self.bus.take_snoop();
+        let event_sink = self.event_sink.take();
+        let frame_sink = self.frame_sink.take();
+        let heatmap = self.heatmap.take();
+        let opcode_coverage = self.opcode_coverage.take();
+        let macro_recorder = self.macro_recorder.take();
+        let (watch_raise, watch_ack) = self.interrupt_watch();
+        self.set_interrupt_watch(0, 0);
+
+        let mut result = Ok(None);
+        let mut end = now;
+        for (slot, start) in snapshots {
+            match self.replay_writes(slot, addr, end, None) {
+                Ok(Some((change, writes))) => {
+                    result = if stop {
+                        // Replay again, stopping at the changing write
+                        self.replay_writes(slot, addr, end, Some(writes))
+                            .map(|found| found.map(|(change, _)| change))
+                    } else {
+                        Ok(Some(change))
+                    };
+                    break;
+                }
+                Ok(None) => end = start,
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+        if !(stop && matches!(result, Ok(Some(_)))) {
+            if let Err(err) = self.load_state(&present) {
+                result = Err(err.into());
+            }
+        }
+
+        self.breakpoints = breakpoints;
+        self.bus.watchpoints = watchpoints;
+        self.bus.port_watches = port_watches;
+        self.bus.set_snoop(snoop);
+        self.event_sink = event_sink;
+        self.frame_sink = frame_sink;
+        self.heatmap = heatmap;
+        self.opcode_coverage = opcode_coverage;
+        self.macro_recorder = macro_recorder;
+        self.set_interrupt_watch(watch_raise, watch_ack);
+        result
+    }
+
+    /// Load `slot` and run to `end` cycles watching writes to `addr`.
+    /// Returns the last write that changed the byte and its index among all
+    /// writes; with `stop_at`, stops after that many writes.
+    fn replay_writes(
+        &mut self,
+        slot: usize,
+        addr: u32,
+        end: u64,
+        stop_at: Option<u32>,
+    ) -> Result<Option<(EmuLastChange, u32)>, EmuError> {
+        self.slot_load(slot)?;
+        let watch = self.bus.watchpoints.add(addr, addr, WatchAccess::Write);
+        let mut value = self.peek_byte(addr);
+        let mut writes = 0;
+        let mut last = None;
+        while self.total_cycles < end {
+            self.last_stop = StopReason::CyclesComplete;
+            let executed = self.run_cycles((end - self.total_cycles).min(1 << 24) as u32);
+            match self.last_stop {
+                StopReason::Watchpoint(hit) => {
+                    writes += 1;
+                    if hit.value != value {
+                        let change = EmuLastChange {
+                            cycles: hit.cycles,
+                            addr,
+                            pc: hit.pc,
+                            slot: slot as u32,
+                            old_value: value,
+                            new_value: hit.value,
+                            reserved: [0; 2],
+                        };
+                        last = Some((change, writes));
+                        value = hit.value;
+                    }
+                    if stop_at == Some(writes) {
+                        break;
+                    }
+                }
+                // Powered off or paused: nothing more will run
+                StopReason::CyclesComplete if executed == 0 => break,
+                _ => {}
+            }
+        }
+        self.bus.watchpoints.remove(watch);
+        Ok(last)
+    }
+
+    /// Register (or clear with None) the autosave sink.
+    pub fn set_autosave_sink(&mut self, callback: Option<AutosaveCallback>, user: *mut c_void) {
+        self.autosave_sink = callback.map(|cb| AutosaveSink::new(cb, user));
+    }
+
+    /// Attach `device` to `[base, base + len)` inside an unmapped MMIO window.
+    /// Returns the extension id (see peripherals::extension for error codes).
+    pub fn register_extension(
+        &mut self,
+        base: u32,
+        len: u32,
+        device: Box<dyn Peripheral + Send>,
+    ) -> Result<u32, EmuError> {
+        let id = self.bus.extensions_mut().register(base, len, device)?;
+        log_evt!("EXTENSION: id {} at {:06X}-{:06X}", id, base, base + len - 1);
+        Ok(id)
+    }
+
+    /// Detach extension `id`.
+    pub fn unregister_extension(&mut self, id: u32) -> Result<(), EmuError> {
+        self.bus.extensions_mut().unregister(id)
+    }
+
+    /// Memory map annotations (see memory_map), including attached extensions
+    pub fn memory_map(&self) -> Vec<Annotation> {
+        let bridge = self.host_bridge.as_ref().map(|(id, _)| *id);
+        let mut map = memory_map::annotations();
+        for (id, base, end) in self.bus.extensions().ranges() {
+            let name = if Some(id) == bridge { "host bridge" } else { "extension" };
+            memory_map::overlay(&mut map, Annotation { start: base, end, kind: RegionKind::Extension, name });
+        }
+        map
+    }
+
+    /// Produce a save state and hand it to the autosave sink, if one is
+    /// registered and a ROM is loaded. Returns whether the sink was called.
+    pub fn autosave(&mut self, reason: AutosaveReason) -> bool {
+        let Some(sink) = self.autosave_sink else { return false };
+        if !self.rom_loaded {
+            return false;
+        }
+        let mut data = vec![0u8; self.save_state_size()];
+        match self.save_state(&mut data) {
+            Ok(size) => {
+                log_evt!("AUTOSAVE: {:?}, {} bytes", reason, size);
+                sink.deliver(&data[..size], reason);
+                true
+            }
+            Err(code) => {
+                log_evt!("AUTOSAVE_FAILED: {:?}, error {}", reason, code);
+                false
+            }
+        }
     }
 
     /// Get the last stop reason
@@ -2198,13 +3750,29 @@ impl Emu {
         self.bus.cycles()
     }
 
+    /// Enable or disable write protection of the boot code sectors
+    /// (0x000000-0x01FFFF). On by default like retail hardware; turn it off
+    /// to let custom boot code be flashed from the emulated CPU.
+    pub fn set_boot_protect(&mut self, enabled: bool) {
+        self.bus.flash.set_boot_protect(enabled);
+    }
+
+    pub fn boot_protect(&self) -> bool {
+        self.bus.flash.boot_protect()
+    }
+
     /// Get raw flash data (4MB) — useful for baking programs into a ROM file
     pub fn flash_data(&self) -> &[u8] {
         self.bus.flash.data()
     }
 
+    /// Raw RAM (from 0xD00000, including VRAM)
+    pub fn ram_data(&self) -> &[u8] {
+        self.bus.ram.data()
+    }
+
     /// Peek at a memory byte without affecting emulation state
-    pub fn peek_byte(&mut self, addr: u32) -> u8 {
+    pub fn peek_byte(&self, addr: u32) -> u8 {
         self.bus.peek_byte(addr)
     }
 
@@ -2213,59 +3781,749 @@ impl Emu {
         self.bus.write_byte(addr, value);
     }
 
-    // === Breakpoint API ===
+    // === Block memory access ===
 
-    /// Set a PC breakpoint. run_cycles will return early when PC hits this address.
-    pub fn set_breakpoint(&mut self, addr: u32) {
-        self.breakpoint_pc = Some(addr);
-        self.breakpoint_hit = false;
+    /// Check that `len` bytes starting at `addr` fit in the 24-bit address space.
+    fn check_block_range(addr: u32, len: usize) -> Result<(), EmuError> {
+        let end = addr as u64 + len as u64;
+        if addr > crate::memory::addr::ADDR_MASK || end > crate::memory::addr::PORT_END as u64 {
+            return Err(EmuError::OutOfRange);
+        }
+        Ok(())
     }
 
-    /// Clear the PC breakpoint.
-    pub fn clear_breakpoint(&mut self) {
-        self.breakpoint_pc = None;
-        self.breakpoint_hit = false;
+    /// Read `out.len()` bytes starting at `addr`.
+    /// With `bus_access` false this uses the debug peek path (no cycles, no
+    /// flash status); with it true each byte is a normal bus read, charging
+    /// wait states and triggering port read side effects.
+    pub fn read_block(&mut self, addr: u32, out: &mut [u8], bus_access: bool) -> Result<(), EmuError> {
+        Self::check_block_range(addr, out.len())?;
+        for (i, byte) in out.iter_mut().enumerate() {
+            let a = addr + i as u32;
+            *byte = if bus_access { self.bus.read_byte(a) } else { self.bus.peek_byte(a) };
+        }
+        Ok(())
     }
 
-    /// Check if a breakpoint was hit during the last run_cycles call.
-    pub fn breakpoint_was_hit(&self) -> bool {
-        self.breakpoint_hit
+    /// Write `data` starting at `addr`.
+    /// With `bus_access` false bytes are stored directly (flash included,
+    /// bypassing the command state machine); with it true each byte is a normal
+    /// bus write, subject to flash locking and memory protection.
+    pub fn write_block(&mut self, addr: u32, data: &[u8], bus_access: bool) -> Result<(), EmuError> {
+        Self::check_block_range(addr, data.len())?;
+        for (i, &byte) in data.iter().enumerate() {
+            let a = addr + i as u32;
+            if bus_access {
+                self.bus.write_byte(a, byte);
+            } else {
+                self.bus.poke_byte(a, byte);
+            }
+        }
+        Ok(())
     }
 
-    // === Debug port API ===
+    // === Execution heatmap ===
 
-    /// Enable debug port interception (CE toolchain: 0xFB0000=stdout, 0xFC0000=stderr)
-    pub fn enable_debug_ports(&mut self) {
-        self.bus.set_debug_ports(true);
+    /// Start or stop counting executed instructions per 64-byte bucket.
+    /// Disabling frees the counters; re-enabling starts from zero.
+    pub fn set_heatmap_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            self.heatmap = None;
+        } else if self.heatmap.is_none() {
+            self.heatmap = Some(Box::new(ExecHeatmap::new()));
+        }
     }
 
-    /// Disable debug port interception
-    pub fn disable_debug_ports(&mut self) {
-        self.bus.set_debug_ports(false);
+    /// Zero the heatmap counters without disabling collection.
+    pub fn clear_heatmap(&mut self) {
+        if let Some(heatmap) = self.heatmap.as_mut() {
+            heatmap.clear();
+        }
     }
 
-    /// Take all pending stdout debug output lines
-    pub fn take_debug_stdout(&mut self) -> Vec<String> {
-        self.bus.take_debug_stdout()
+    /// Export the heatmap (see `crate::heatmap` for the layout).
+    /// Returns bytes written; fails if collection is disabled or `out` is
+    /// smaller than `heatmap::EXPORT_SIZE`.
+    pub fn export_heatmap(&self, out: &mut [u8]) -> Result<usize, EmuError> {
+        let heatmap = self.heatmap.as_ref().ok_or(EmuError::HeatmapDisabled)?;
+        heatmap.export(out).ok_or(EmuError::BufferTooSmall)
     }
 
-    /// Take all pending stderr debug output lines
-    pub fn take_debug_stderr(&mut self) -> Vec<String> {
-        self.bus.take_debug_stderr()
+    // === Opcode coverage ===
+
+    /// Start or stop counting executed instructions per opcode (see
+    /// `crate::opcode_coverage`). Disabling frees the counters;
+    /// re-enabling starts from zero.
+    pub fn set_opcode_coverage_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            self.opcode_coverage = None;
+        } else if self.opcode_coverage.is_none() {
+            self.opcode_coverage = Some(Box::new(OpcodeCoverage::new()));
+        }
     }
 
-    /// Check if program signaled termination (null byte on debug stdout port)
-    pub fn debug_terminated(&self) -> bool {
-        self.bus.debug_terminated()
+    /// Zero the opcode counters without disabling collection.
+    pub fn clear_opcode_coverage(&mut self) {
+        if let Some(coverage) = self.opcode_coverage.as_mut() {
+            coverage.clear();
+        }
     }
 
-    /// Reset debug port state
-    pub fn reset_debug_state(&mut self) {
-        self.bus.reset_debug_state();
+    /// The opcode counters, or None while collection is disabled
+    pub fn opcode_coverage(&self) -> Option<&OpcodeCoverage> {
+        self.opcode_coverage.as_deref()
     }
 
-    /// High-level key injection (like CEmu's sendKey)
-    /// This writes directly to TI-OS memory locations, bypassing hardware keypad.
+    /// Count the instruction about to execute at `pc`
+    fn record_opcode(&mut self, pc: u32) {
+        // Longest form: suffix, DD/FD, CB, displacement, opcode
+        let mut bytes = [0u8; 5];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = self.bus.peek_byte_fetch(self.cpu.mask_addr_instr(pc.wrapping_add(i as u32)));
+        }
+        if let Some(coverage) = self.opcode_coverage.as_mut() {
+            coverage.record(&bytes, self.cpu.adl);
+        }
+    }
+
+    // === Pattern search/replace ===
+
+    /// Find every match of `pattern` in flash and RAM within [start, end),
+    /// up to `limit` results. Unmapped space and MMIO are skipped so the
+    /// search never triggers port side effects.
+    /// Fails with `OutOfRange` if the range leaves the 24-bit address space.
+    pub fn search_memory(
+        &self,
+        pattern: &BytePattern,
+        start: u32,
+        end: u32,
+        limit: usize,
+    ) -> Result<Vec<u32>, EmuError> {
+        use crate::memory::addr;
+
+        if start > end || end > addr::PORT_END {
+            return Err(EmuError::OutOfRange);
+        }
+        let regions: [(u32, &[u8]); 2] = [
+            (addr::FLASH_START, self.bus.flash.data()),
+            (addr::RAM_START, self.bus.ram.data()),
+        ];
+
+        let mut results = Vec::new();
+        for (base, data) in regions {
+            let lo = start.max(base);
+            let hi = end.min(base + data.len() as u32);
+            if lo >= hi || results.len() >= limit {
+                continue;
+            }
+            let window = &data[(lo - base) as usize..(hi - base) as usize];
+            results.extend(
+                pattern
+                    .find_all(window, limit - results.len())
+                    .into_iter()
+                    .map(|off| lo + off as u32),
+            );
+        }
+        Ok(results)
+    }
+
+    /// Whether [addr, addr+len) overlaps the instruction at PC or one of the
+    /// recently executed instructions in the history ring.
+    fn overlaps_live_code(&self, addr: u32, len: u32) -> bool {
+        let overlaps = |start: u32, size: u32| addr < start + size && start < addr + len;
+        // Longest eZ80 instruction with suffix and prefixes is 6 bytes
+        overlaps(self.cpu.mask_addr_instr(self.cpu.pc), 6)
+            || self
+                .history
+                .iter()
+                .any(|e| overlaps(e.pc & crate::memory::addr::ADDR_MASK, e.opcode_len.max(1) as u32))
+    }
+
+    /// Replace every match of `pattern` within [start, end) with
+    /// `replacement` (same length; its wildcard nibbles keep the original).
+    /// Bytes are stored directly, so flash is patched too.
+    ///
+    /// Refuses with `LiveCode`, writing nothing, when a match overlaps the
+    /// executing instruction or recent execution history unless `force` is
+    /// set. Returns the patched addresses, `OutOfRange` for a bad range or
+    /// `LengthMismatch` when the pattern and replacement lengths differ.
+    pub fn replace_memory(
+        &mut self,
+        pattern: &BytePattern,
+        replacement: &BytePattern,
+        start: u32,
+        end: u32,
+        force: bool,
+    ) -> Result<Vec<u32>, EmuError> {
+        if pattern.len() != replacement.len() {
+            return Err(EmuError::LengthMismatch);
+        }
+        let matches = self.search_memory(pattern, start, end, usize::MAX)?;
+        let len = pattern.len() as u32;
+        if !force && matches.iter().any(|&a| self.overlaps_live_code(a, len)) {
+            return Err(EmuError::LiveCode);
+        }
+
+        let mut original = vec![0u8; pattern.len()];
+        for &a in &matches {
+            for (i, byte) in original.iter_mut().enumerate() {
+                *byte = self.bus.peek_byte(a + i as u32);
+            }
+            for (i, byte) in replacement.apply(&original).into_iter().enumerate() {
+                self.bus.poke_byte(a + i as u32, byte);
+            }
+        }
+        Ok(matches)
+    }
+
+    // === Interrupt watchpoint ===
+
+    /// Stop execution when a source in `raise` has its status raised or one
+    /// in `ack` is acknowledged (masks of `interrupt::sources` bits, 0 for
+    /// none). run_cycles returns at the next instruction boundary with
+    /// `StopReason::InterruptWatch` and emits an `InterruptWatch` event.
+    pub fn set_interrupt_watch(&mut self, raise: u32, ack: u32) {
+        self.bus.ports.interrupt.set_watch(raise, ack);
+    }
+
+    /// Watched (raise, acknowledge) source masks
+    pub fn interrupt_watch(&self) -> (u32, u32) {
+        self.bus.ports.interrupt.watch()
+    }
+
+    /// Raises, acknowledges and raise-to-acknowledge times per interrupt
+    /// source bit since reset, for checking e.g. the OS timer rate
+    pub fn interrupt_stats(&self) -> &[EmuInterruptStats; SOURCE_COUNT] {
+        self.bus.ports.interrupt.stats()
+    }
+
+    pub fn clear_interrupt_stats(&mut self) {
+        self.bus.ports.interrupt.clear_stats();
+    }
+
+    fn stop_on_interrupt_watch(&mut self, hit: InterruptWatchHit) {
+        log_evt!(
+            "INTWATCH: {} {:?} (source {}) at PC={:06X} cycle={}",
+            hit.peripheral(), hit.edge, hit.source, self.cpu.pc, self.total_cycles
+        );
+        self.last_stop = StopReason::InterruptWatch(hit);
+        self.emit_event(EventKind::InterruptWatch, ((hit.edge as u32) << 8) | hit.source);
+    }
+
+    fn stop_on_watchpoint(&mut self, hit: EmuSnoopAccess) {
+        log_evt!(
+            "WATCHPOINT: {} {:06X}={:02X} by PC={:06X} cycle={}",
+            if hit.write != 0 { "write" } else { "read" }, hit.addr, hit.value, hit.pc, hit.cycles
+        );
+        self.last_stop = StopReason::Watchpoint(hit);
+        self.emit_event(EventKind::Watchpoint, hit.addr);
+    }
+
+    // === Watchpoint API ===
+
+    /// Stop after any instruction whose data accesses of kind `access`
+    /// touch `start..=end`. Returns an id for `remove_watchpoint`.
+    pub fn add_watchpoint(&mut self, start: u32, end: u32, access: WatchAccess) -> u32 {
+        self.bus.watchpoints.add(start & 0xFFFFFF, end & 0xFFFFFF, access)
+    }
+
+    /// Remove a watchpoint. Returns false for an unknown id.
+    pub fn remove_watchpoint(&mut self, id: u32) -> bool {
+        self.bus.watchpoints.remove(id)
+    }
+
+    /// Remove all watchpoints.
+    pub fn clear_watchpoints(&mut self) {
+        self.bus.watchpoints.clear();
+    }
+
+    /// Watchpoints in the order they were added
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        self.bus.watchpoints.list()
+    }
+
+    /// The access that stopped the last run, if a watchpoint stopped it
+    pub fn last_watchpoint(&self) -> Option<EmuSnoopAccess> {
+        match self.last_stop {
+            StopReason::Watchpoint(hit) => Some(hit),
+            _ => None,
+        }
+    }
+
+    fn stop_on_port_watch(&mut self, hit: EmuPortAccess) {
+        log_evt!(
+            "PORTWATCH: {} {}{:06X} {:02X}->{:02X} by PC={:06X} cycle={}",
+            if hit.write != 0 { "write" } else { "read" }, if hit.io != 0 { "io " } else { "" },
+            hit.addr, hit.old_value, hit.new_value, hit.pc, hit.cycles
+        );
+        self.last_stop = StopReason::PortWatch(hit);
+        self.emit_event(EventKind::PortWatch, hit.addr);
+    }
+
+    // === Port watch API ===
+
+    /// Watch accesses of kind `access` to ports `start..=end` in `space`
+    /// (see `crate::port_watch`). Matches go to the port watch callback;
+    /// with `stop` the run also ends after the instruction. Returns an id
+    /// for `remove_port_watch`, or None for a range outside the space.
+    pub fn add_port_watch(
+        &mut self,
+        space: PortSpace,
+        start: u32,
+        end: u32,
+        access: WatchAccess,
+        stop: bool,
+    ) -> Option<u32> {
+        if !space.valid_range(start, end) {
+            return None;
+        }
+        Some(self.bus.port_watches.add(space, start, end, access, stop))
+    }
+
+    /// Remove a port watch. Returns false for an unknown id.
+    pub fn remove_port_watch(&mut self, id: u32) -> bool {
+        self.bus.port_watches.remove(id)
+    }
+
+    /// Remove all port watches (the callback stays registered).
+    pub fn clear_port_watches(&mut self) {
+        self.bus.port_watches.clear();
+    }
+
+    /// Port watches in the order they were added
+    pub fn port_watches(&self) -> &[PortWatch] {
+        self.bus.port_watches.list()
+    }
+
+    /// Send accesses matching any port watch to `callback`, or stop with
+    /// None. The callback runs inside the bus access and must not call
+    /// back into the emulator.
+    pub fn set_port_watch_callback(&mut self, callback: Option<PortWatchCallback>, user: *mut c_void) {
+        self.bus.port_watches.set_callback(callback, user);
+    }
+
+    /// The access that stopped the last run, if a port watch stopped it
+    pub fn last_port_watch(&self) -> Option<EmuPortAccess> {
+        match self.last_stop {
+            StopReason::PortWatch(hit) => Some(hit),
+            _ => None,
+        }
+    }
+
+    // === Breakpoint API ===
+
+    /// Set a PC breakpoint and reset `breakpoint_was_hit`. run_cycles will
+    /// return early when PC hits this address. Other breakpoints are kept
+    /// (same as `add_breakpoint`).
+    ///
+    /// Unlike the single breakpoint this used to set, a run that starts on
+    /// the breakpoint that just stopped it executes that instruction rather
+    /// than returning 0 cycles; step off it first if you relied on that.
+    pub fn set_breakpoint(&mut self, addr: u32) {
+        self.breakpoints.add(addr);
+        self.breakpoint_hit = false;
+    }
+
+    /// Remove all breakpoints, including those from `add_breakpoint`.
+    pub fn clear_breakpoint(&mut self) {
+        self.clear_breakpoints();
+    }
+
+    /// Add an enabled breakpoint at `addr`, or re-enable the one there.
+    /// There is no limit on how many are set; after a stop, the next run
+    /// starts by executing the instruction at the breakpoint.
+    pub fn add_breakpoint(&mut self, addr: u32) {
+        self.breakpoints.add(addr);
+    }
+
+    /// Remove the breakpoint at `addr`. Returns false if there was none.
+    pub fn remove_breakpoint(&mut self, addr: u32) -> bool {
+        self.breakpoints.remove(addr)
+    }
+
+    /// Enable or disable the breakpoint at `addr` without removing it.
+    /// Returns false if there was none.
+    pub fn enable_breakpoint(&mut self, addr: u32, enabled: bool) -> bool {
+        self.breakpoints.set_enabled(addr, enabled)
+    }
+
+    /// Remove all breakpoints.
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+        self.breakpoint_hit = false;
+    }
+
+    /// All breakpoints as (address, enabled), by address
+    pub fn breakpoints(&self) -> Vec<(u32, bool)> {
+        self.breakpoints.list()
+    }
+
+    /// The breakpoint that stopped the last run, if that's why it stopped
+    pub fn last_breakpoint(&self) -> Option<u32> {
+        match self.last_stop {
+            StopReason::Breakpoint(addr) => Some(addr),
+            _ => None,
+        }
+    }
+
+    /// Check if a breakpoint was hit during the last run_cycles call.
+    pub fn breakpoint_was_hit(&self) -> bool {
+        self.breakpoint_hit
+    }
+
+    /// Run until the LCD's next vertical sync starts a frame, then render,
+    /// so frontends don't have to guess a cycle count per frame. With the
+    /// LCD off it runs one 60 Hz frame's worth of cycles instead; other
+    /// stops (breakpoints, watches) end it early. Returns the cycles
+    /// executed, for real-time pacing.
+    pub fn run_frame(&mut self) -> u32 {
+        let hz = ClockId::Cpu.rate(self.bus.ports.control.cpu_speed());
+        // Cap in case the LCD never gets to its sync
+        let budget = if self.scheduler.is_active(EventId::Lcd) { hz / 10 } else { hz / 60 };
+        self.vsync_stop = Some(false);
+        let executed = self.run_cycles(budget as u32);
+        self.vsync_stop = None;
+        self.render_frame();
+        executed
+    }
+
+    /// Run until PC reaches `addr` or `max_cycles` have run ("run to
+    /// cursor"). Starting at `addr` runs until execution comes back to it.
+    /// Breakpoints and other stops still end the run early.
+    pub fn run_until_pc(&mut self, addr: u32, max_cycles: u64) -> RunUntil {
+        let start = self.total_cycles;
+        self.run_target = Some((addr & 0xFFFFFF, start));
+        let result = loop {
+            let used = self.total_cycles.saturating_sub(start);
+            if used >= max_cycles {
+                break RunUntil::Budget;
+            }
+            self.breakpoint_hit = false;
+            let ran = self.run_cycles((max_cycles - used).min(i32::MAX as u64) as u32);
+            if self.last_stop == StopReason::TargetReached {
+                break RunUntil::Reached;
+            }
+            if ran == 0 || self.breakpoint_hit || self.last_stop != StopReason::CyclesComplete {
+                break RunUntil::Stopped;
+            }
+        };
+        self.run_target = None;
+        result
+    }
+
+    // === Pause API ===
+
+    /// Pause emulation: run_cycles does nothing and host time stops counting
+    /// until `resume`, so the emulated clock does not jump after a long pause
+    pub fn pause(&mut self) {
+        if !self.host_clock.is_paused() {
+            log_evt!("PAUSE: at {} cycles", self.total_cycles);
+        }
+        self.host_clock.pause();
+    }
+
+    /// Resume after `pause`; host time counts again from the next sync
+    pub fn resume(&mut self) {
+        self.host_clock.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.host_clock.is_paused()
+    }
+
+    /// Report the host wall time (any monotonic millisecond clock). Time
+    /// spent off counts toward the standby draw in `power_stats`.
+    pub fn sync_host_time(&mut self, now_ms: u64) {
+        let before = self.host_clock.running_ms();
+        self.host_clock.sample(now_ms);
+        if self.rom_loaded && (!self.powered_on || self.is_off()) {
+            let elapsed_ms = self.host_clock.running_ms() - before;
+            self.power.standby(elapsed_ms.saturating_mul(1_000_000));
+        }
+    }
+
+    /// Host milliseconds that passed while not paused
+    pub fn host_running_ms(&self) -> u64 {
+        self.host_clock.running_ms()
+    }
+
+    /// Choose what advances the RTC. HostTime needs the frontend to call
+    /// `sync_host_time` regularly; switching never makes the clock jump.
+    pub fn set_rtc_clock(&mut self, clock: RtcClock) {
+        self.rtc_clock = clock;
+        self.rtc_host_secs = self.host_clock.running_ms() / 1000;
+    }
+
+    pub fn rtc_clock(&self) -> RtcClock {
+        self.rtc_clock
+    }
+
+    /// Emulate a crystal `ppm` parts per million fast (negative: slow), so
+    /// long sessions drift like real hardware. Survives reset. Returns false
+    /// if beyond +-10000 ppm.
+    pub fn set_rtc_drift_ppm(&mut self, ppm: i32) -> bool {
+        self.bus.ports.rtc.set_drift_ppm(ppm)
+    }
+
+    pub fn rtc_drift_ppm(&self) -> i32 {
+        self.bus.ports.rtc.drift_ppm()
+    }
+
+    /// Trim the RTC by skipping `trim` 32 kHz ticks per 2^20 (about 0.95 ppm
+    /// each, negative: insert), as a calibrated crystal would. Applies from
+    /// the next second and survives reset; like the drift, it is a setting
+    /// rather than part of save states.
+    pub fn set_rtc_trim(&mut self, trim: i16) {
+        self.bus.ports.rtc.set_trim(trim);
+    }
+
+    pub fn rtc_trim(&self) -> i16 {
+        self.bus.ports.rtc.trim()
+    }
+
+    // === Stop request API ===
+
+    /// Make the current (or next) run_cycles call return at the next
+    /// instruction boundary. The request is consumed by that call.
+    pub fn request_stop(&self) {
+        self.stop_requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Handle for requesting a stop without holding the emulator, for use
+    /// from other threads while run_cycles has it locked
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop_requested.clone()
+    }
+
+    // === MMIO diff API ===
+
+    /// Start or stop collecting per-frame peripheral register diffs (see
+    /// `crate::mmio_diff`). Each frame with changes emits an `MmioDiff` event.
+    pub fn set_mmio_diff_enabled(&mut self, enabled: bool) {
+        self.bus.set_mmio_diff_enabled(enabled);
+    }
+
+    /// Registers changed during the last frame (empty when disabled)
+    pub fn mmio_diff(&self) -> &[MmioChange] {
+        self.bus.mmio_diff().map_or(&[], |diff| diff.report())
+    }
+
+    // === Input macro recording ===
+
+    /// Start recording keys and power-on as an input macro (see
+    /// `crate::input_macro`), discarding any recording in progress. Start
+    /// before powering on so the macro replays from a fresh boot.
+    pub fn start_macro_recording(&mut self) {
+        self.macro_recorder = Some(Box::new(Recorder::new(self.total_cycles)));
+    }
+
+    /// Add a `frame` step with the hash of the current screen. Returns the
+    /// hash, or None when not recording.
+    pub fn macro_checkpoint(&mut self) -> Option<u64> {
+        self.macro_recorder.as_ref()?;
+        let hash = frame_hash(self);
+        let cycles = self.total_cycles;
+        self.macro_recorder.as_mut()?.frame(cycles, hash);
+        Some(hash)
+    }
+
+    /// The macro recorded so far, None when not recording
+    pub fn recorded_macro(&self) -> Option<InputMacro> {
+        let recorder = self.macro_recorder.as_deref()?;
+        Some(recorder.clone().finish(self.rom_hash()))
+    }
+
+    /// Stop recording and return the macro
+    pub fn stop_macro_recording(&mut self) -> Option<InputMacro> {
+        let recorder = self.macro_recorder.take()?;
+        Some(recorder.finish(self.rom_hash()))
+    }
+
+    // === Power estimate ===
+
+    /// Feed the power model the device state at the end of a run
+    fn sample_power(&mut self) {
+        self.power.sample(PowerState {
+            cycles: self.bus.total_cycles(),
+            cpu_speed: self.bus.ports.control.cpu_speed(),
+            halted_cycles: self.halted_cycles,
+            flash_accesses: self.bus.flash_accesses(),
+            lcd_on: self.bus.ports.lcd.is_enabled(),
+            backlight: self.bus.ports.backlight.brightness(),
+        });
+    }
+
+    /// Estimated supply current and charge drawn since reset (see
+    /// `crate::power`)
+    pub fn power_stats(&self) -> EmuPowerStats {
+        self.power.stats()
+    }
+
+    /// Watchdog pet telemetry since reset or state load (see
+    /// `crate::watchdog_monitor`)
+    pub fn watchdog_stats(&self) -> EmuWatchdogStats {
+        self.watchdog.stats()
+    }
+
+    /// Queue `EventKind::WatchdogStall` after `ms` emulated ms without a
+    /// watchdog pet (0 turns detection off)
+    pub fn set_watchdog_stall_ms(&mut self, ms: u32) {
+        self.watchdog.set_stall_ms(ms);
+    }
+
+    // === Emulated-time benchmark ===
+
+    /// Measure emulated time between `start` and `end` hooks (see
+    /// `crate::benchmark`), replacing any previous benchmark and its
+    /// results; None stops benchmarking. Each completed measurement emits a
+    /// `BenchmarkDone` event.
+    pub fn set_benchmark(&mut self, hooks: Option<(Hook, Hook)>) {
+        self.benchmark = hooks.map(|(start, end)| Box::new(Benchmark::new(start, end)));
+        self.bus.set_write_hooks(self.benchmark.as_ref().and_then(|bench| bench.write_hooks()));
+    }
+
+    /// Completed measurements in nanoseconds of emulated time
+    pub fn benchmark_results(&self) -> &[u64] {
+        self.benchmark.as_ref().map_or(&[], |bench| bench.results())
+    }
+
+    /// Feed the benchmark the port stores of the last instruction and the
+    /// PC about to execute
+    fn poll_benchmark(&mut self) {
+        let Some(bench) = self.benchmark.as_mut() else { return };
+        let mut done = Vec::new();
+        while let Some(mark) = self.bus.take_write_mark() {
+            let nanos = benchmark::emulated_nanos(mark.cycles, mark.cpu_speed);
+            done.extend(bench.hit(Hook::Write(mark.addr), nanos));
+        }
+        let pc = self.cpu.pc;
+        if !self.cpu.halted && bench.is_pc_hook(pc) {
+            let nanos = benchmark::emulated_nanos(self.bus.total_cycles(), self.bus.ports.control.cpu_speed());
+            done.extend(bench.hit(Hook::Pc(pc), nanos));
+        }
+        for elapsed in done {
+            log_evt!("BENCHMARK: {} ns at PC={:06X}", elapsed, pc);
+            self.emit_event(EventKind::BenchmarkDone, (elapsed / 1000).min(u32::MAX as u64) as u32);
+        }
+    }
+
+    // === Watch expression API ===
+
+    /// Register a watch expression (see `crate::watch` for the syntax).
+    /// Watches are re-evaluated in `render_frame`, not by `run_cycles`.
+    /// Returns the watch id; fails on a syntax error or when full.
+    pub fn add_watch(&mut self, expr: &str) -> Result<u32, EmuError> {
+        let id = self.watches.add(expr)?;
+        // Give the new watch a value before the next render_frame
+        self.watches.evaluate(&self.cpu, &mut self.bus);
+        Ok(id)
+    }
+
+    /// Remove a watch by id. Returns false if the id is unknown.
+    pub fn remove_watch(&mut self, id: u32) -> bool {
+        self.watches.remove(id)
+    }
+
+    /// Remove all watches.
+    pub fn clear_watches(&mut self) {
+        self.watches.clear();
+    }
+
+    /// (id, value) pairs as of the last frame, in registration order.
+    pub fn watch_values(&self) -> Vec<(u32, u32)> {
+        self.watches.values().collect()
+    }
+
+    // === Debug port API ===
+
+    /// Enable debug port interception (CE toolchain: 0xFB0000=stdout, 0xFC0000=stderr)
+    pub fn enable_debug_ports(&mut self) {
+        self.bus.set_debug_ports(true);
+    }
+
+    /// Disable debug port interception
+    pub fn disable_debug_ports(&mut self) {
+        self.bus.set_debug_ports(false);
+    }
+
+    /// Attach the host bridge register block (see host_bridge). Fails with
+    /// `ExtensionOverlap` if an extension already occupies its range;
+    /// enabling twice is a no-op.
+    pub fn enable_host_bridge(&mut self) -> Result<(), EmuError> {
+        if self.host_bridge.is_some() {
+            return Ok(());
+        }
+        let state = Arc::new(Mutex::new(BridgeState::default()));
+        let device = Box::new(HostBridge(state.clone(), self.software_reset.clone()));
+        let id = self.register_extension(BRIDGE_BASE, BRIDGE_LEN, device)?;
+        self.host_bridge = Some((id, state));
+        Ok(())
+    }
+
+    /// Detach the host bridge, discarding its pending input and output
+    pub fn disable_host_bridge(&mut self) {
+        if let Some((id, _)) = self.host_bridge.take() {
+            let _ = self.unregister_extension(id);
+        }
+    }
+
+    fn host_bridge_state(&self) -> Result<std::sync::MutexGuard<'_, BridgeState>, EmuError> {
+        match &self.host_bridge {
+            Some((_, state)) => Ok(state.lock().unwrap()),
+            None => Err(EmuError::BridgeDisabled),
+        }
+    }
+
+    /// Queue bytes for the program to read from the bridge input register
+    pub fn host_bridge_push_input(&mut self, data: &[u8]) -> Result<(), EmuError> {
+        self.host_bridge_state()?.push_input(data);
+        Ok(())
+    }
+
+    /// Take up to `max` bytes the program wrote to the bridge output register
+    pub fn take_host_output(&mut self, max: usize) -> Result<Vec<u8>, EmuError> {
+        Ok(self.host_bridge_state()?.take_output(max))
+    }
+
+    /// Output bytes dropped because the buffer filled before the host took
+    /// them (see `host_bridge::MAX_OUTPUT_BYTES`)
+    pub fn host_output_dropped(&self) -> Result<u64, EmuError> {
+        Ok(self.host_bridge_state()?.output_dropped())
+    }
+
+    /// Test result the program reported (0 = pass), `NoTestResult` if none
+    /// yet
+    pub fn host_test_result(&self) -> Result<u8, EmuError> {
+        self.host_bridge_state()?.result().ok_or(EmuError::NoTestResult)
+    }
+
+    /// Bus access counters since the last take (or reset)
+    pub fn take_bus_counters(&mut self) -> crate::bus::BusCounters {
+        self.bus.take_counters()
+    }
+
+    /// Take all pending stdout debug output lines
+    pub fn take_debug_stdout(&mut self) -> Vec<String> {
+        self.bus.take_debug_stdout()
+    }
+
+    /// Take all pending stderr debug output lines
+    pub fn take_debug_stderr(&mut self) -> Vec<String> {
+        self.bus.take_debug_stderr()
+    }
+
+    /// Check if program signaled termination (null byte on debug stdout port)
+    pub fn debug_terminated(&self) -> bool {
+        self.bus.debug_terminated()
+    }
+
+    /// Reset debug port state
+    pub fn reset_debug_state(&mut self) {
+        self.bus.reset_debug_state();
+    }
+
+    /// High-level key injection (like CEmu's sendKey)
+    /// This writes directly to TI-OS memory locations, bypassing hardware keypad.
     /// Returns true if key was successfully injected, false if TI-OS wasn't ready.
     ///
     /// TI-OS key addresses:
@@ -2491,6 +4749,65 @@ impl Emu {
         self.cpu.mask_addr_instr(addr)
     }
 
+    /// Main registers for a debugger view
+    pub fn registers(&self) -> EmuRegisters {
+        let cpu = &self.cpu;
+        EmuRegisters {
+            bc: cpu.bc,
+            de: cpu.de,
+            hl: cpu.hl,
+            ix: cpu.ix,
+            iy: cpu.iy,
+            sp: if cpu.adl { cpu.spl } else { cpu.sps },
+            pc: cpu.pc,
+            af: ((cpu.a as u16) << 8) | cpu.f as u16,
+            mbase: cpu.mbase,
+            adl: cpu.adl as u8,
+        }
+    }
+
+    /// Overwrite the main registers (values masked to 24 bits). SP goes to
+    /// the stack pointer of the new mode, and execution continues at the new
+    /// PC on the next step.
+    pub fn set_registers(&mut self, regs: &EmuRegisters) {
+        let cpu = &mut self.cpu;
+        let pc = regs.pc & 0xFFFFFF;
+        // The prefetched byte was fetched at the old PC in the old mode
+        let refetch = cpu.pc != pc || cpu.adl != (regs.adl != 0) || cpu.mbase != regs.mbase;
+        cpu.bc = regs.bc & 0xFFFFFF;
+        cpu.de = regs.de & 0xFFFFFF;
+        cpu.hl = regs.hl & 0xFFFFFF;
+        cpu.ix = regs.ix & 0xFFFFFF;
+        cpu.iy = regs.iy & 0xFFFFFF;
+        cpu.a = (regs.af >> 8) as u8;
+        cpu.f = regs.af as u8;
+        cpu.mbase = regs.mbase;
+        cpu.adl = regs.adl != 0;
+        cpu.l = cpu.adl;
+        cpu.il = cpu.adl;
+        cpu.set_sp(regs.sp & 0xFFFFFF);
+        cpu.pc = pc;
+        if refetch {
+            cpu.invalidate_prefetch();
+        }
+    }
+
+    /// CPU state, e.g. for serializing with the `serde` feature
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    /// Peripheral controller state, e.g. for serializing with the `serde`
+    /// feature
+    pub fn peripherals(&self) -> &Peripherals {
+        &self.bus.ports
+    }
+
+    /// Pending scheduler events
+    pub fn scheduler(&self) -> &Scheduler {
+        &self.scheduler
+    }
+
     /// Snapshot a timer's internal state (1, 2, or 3)
     pub fn timer_snapshot(&self, which: usize) -> Option<TimerSnapshot> {
         let idx = match which {
@@ -2704,6 +5021,24 @@ impl Emu {
             .collect()
     }
 
+    /// Cause of the most recent reset
+    pub fn reset_cause(&self) -> ResetCause {
+        self.reset_cause
+    }
+
+    /// One-block summary for crash reports: reset cause, timing, registers
+    pub fn crash_report(&self) -> String {
+        format!(
+            "reset_cause={:?} powered_on={} off={} cycles={} last_stop={:?}\n{}",
+            self.reset_cause,
+            self.powered_on,
+            self.is_off(),
+            self.total_cycles,
+            self.last_stop,
+            self.dump_registers(),
+        )
+    }
+
     /// Get CPU register dump for debugging
     pub fn dump_registers(&self) -> String {
         format!(
@@ -2782,7 +5117,86 @@ mod tests {
     fn test_empty_rom_fails() {
         let mut emu = Emu::new();
         let rom: Vec<u8> = vec![];
-        assert!(emu.load_rom(&rom).is_err());
+        assert_eq!(emu.load_rom(&rom), Err(LoadError::EmptyRom));
+        let rom = vec![0; crate::memory::addr::FLASH_SIZE + 1];
+        assert_eq!(emu.load_rom(&rom).unwrap_err().code(), -3);
+    }
+
+    #[test]
+    fn test_load_state_errors_carry_context() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00, 0x00, 0x76]).unwrap();
+        let mut state = vec![0; emu.save_state_size()];
+        let len = emu.save_state(&mut state).unwrap();
+        state.truncate(len);
+
+        let mut bad = state.clone();
+        bad[..4].copy_from_slice(b"8XP!");
+        assert_eq!(
+            emu.load_state(&bad),
+            Err(LoadError::BadMagic { expected: *b"CE84", found: *b"8XP!" })
+        );
+
+        let mut bad = state.clone();
+        bad[4..8].copy_from_slice(&3u32.to_le_bytes());
+        assert_eq!(
+            emu.load_state(&bad),
+            Err(LoadError::VersionMismatch { expected: Emu::STATE_VERSION, found: 3 })
+        );
+
+        let err = emu.load_state(&state[..state.len() - 1]).unwrap_err();
+        assert!(matches!(err, LoadError::StateTruncated { .. }), "{:?}", err);
+        assert_eq!(err.code(), -105);
+
+        // A damaged payload is refused before anything is restored
+        let mut bad = state.clone();
+        let last = bad.len() - 1;
+        bad[last] ^= 0xFF;
+        emu.cpu.pc = 0x1234;
+        let err = emu.load_state(&bad).unwrap_err();
+        assert!(matches!(err, LoadError::ChecksumMismatch { .. }), "{:?}", err);
+        assert_eq!(err.code(), -106);
+        assert_eq!(emu.cpu.pc, 0x1234);
+        assert_eq!(
+            emu.load_state(&state[..22]),
+            Err(LoadError::StateTooShort { len: 22, min: 24 })
+        );
+
+        let mut other = Emu::new();
+        other.load_rom(&[0x00, 0x76]).unwrap();
+        let err = other.load_state(&state).unwrap_err();
+        assert_eq!(err.code(), -104);
+        assert!(err.to_string().contains("another ROM"), "{}", err);
+        assert_eq!(emu.load_state(&state), Ok(()));
+    }
+
+    #[test]
+    fn test_redacted_state_round_trip() {
+        let rom = crate::test_rom::build();
+        let mut emu = Emu::new();
+        assert_eq!(emu.save_redacted_state(), Err(EmuError::NoRom));
+        emu.load_rom(&rom).unwrap();
+        emu.power_on();
+        emu.run_cycles(1_000_000);
+        let bundle = emu.save_redacted_state().unwrap();
+        assert!(!bundle.windows(64).any(|w| w == &rom[0x40..0x80]), "no ROM bytes");
+        let pc = emu.cpu.pc;
+
+        let mut other = Emu::new();
+        other.load_rom(&rom).unwrap();
+        assert_eq!(other.load_redacted_state(&bundle), Ok(vec![]));
+        assert_eq!((other.cpu.pc, other.total_cycles), (pc, emu.total_cycles));
+        assert_eq!(other.ram_data(), emu.ram_data());
+
+        // An archive the reporter had but we don't
+        let file = make_test_8xp(0x05, b"TEST\0\0\0\0", 0, 0, &[0x02, 0x00, 0xEF, 0x7B]);
+        emu.send_file_live(&file).unwrap();
+        let bundle = emu.save_redacted_state().unwrap();
+        assert_eq!(other.load_redacted_state(&bundle), Ok(vec![0x0C]));
+
+        let mut different = Emu::new();
+        different.load_rom(&[0x00, 0x76]).unwrap();
+        assert_eq!(different.load_redacted_state(&bundle).map_err(|e| e.code()), Err(-104));
     }
 
     #[test]
@@ -2910,6 +5324,36 @@ mod tests {
         assert!(!emu.cpu.on_key_wake); // One-shot consumed
     }
 
+    #[test]
+    fn test_set_on_key_reports_wake() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0xF3, 0x76, 0x00, 0x00]).unwrap(); // DI, HALT
+        assert!(emu.set_on_key(true), "first press powers on");
+        assert!(!emu.set_on_key(false));
+        emu.run_cycles(100);
+        assert!(emu.cpu.halted);
+
+        // Awake already: the press only wakes the CPU from HALT
+        assert!(!emu.set_on_key(true));
+        emu.run_cycles(20);
+        assert!(!emu.cpu.halted);
+        emu.set_on_key(false);
+
+        emu.bus.ports.control.set_off(true); // OS powered down
+        assert!(emu.is_off());
+        assert!(emu.set_on_key(true));
+        assert!(!emu.is_off());
+
+        // The ON line is separate from the scanned matrix
+        use crate::peripherals::interrupt::sources;
+        assert!(!emu.bus.key_state()[2][0]);
+        assert_eq!(emu.key_matrix()[2], 1);
+        assert_ne!(emu.bus.ports.interrupt.raw() & sources::ON_KEY, 0);
+        emu.set_on_key(false);
+        assert_eq!(emu.key_matrix()[2], 0);
+        assert_eq!(emu.bus.ports.interrupt.raw() & sources::ON_KEY, 0);
+    }
+
     #[test]
     fn test_on_key_raises_interrupt() {
         use crate::peripherals::interrupt::sources;
@@ -3000,6 +5444,30 @@ mod tests {
         assert!(emu.cpu.halted);
     }
 
+    #[test]
+    fn test_ei_halt_wakes_for_irq_raised_in_ei_window() {
+        let mut emu = Emu::new();
+        // ROM: EI, HALT, then IM 1 handler at 0x38: LD A,0x42 ; HALT
+        let mut rom = vec![0x00; 0x3B];
+        rom[0] = 0xFB;
+        rom[1] = 0x76;
+        rom[0x38] = 0x3E;
+        rom[0x39] = 0x42;
+        rom[0x3A] = 0x76;
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+        emu.cpu.im = InterruptMode::Mode1;
+
+        // IRQ arrives while EI's enable is still pending (before HALT runs)
+        emu.cpu.irq_pending = true;
+        emu.run_cycles(100_000);
+
+        // The halt must wake within this run, not sit out the remaining cycles
+        assert_eq!(emu.cpu.a, 0x42);
+        assert_eq!(emu.cpu.pc, 0x3B);
+        assert!(emu.cpu.halted);
+    }
+
     /// Helper: create a minimal .8xp from components
     fn make_test_8xp(var_type: u8, name: &[u8; 8], version: u8, flag: u8, var_data: &[u8]) -> Vec<u8> {
         let mut file = Vec::new();
@@ -3025,7 +5493,7 @@ mod tests {
     fn test_send_file_requires_rom() {
         let mut emu = Emu::new();
         let file = make_test_8xp(0x05, b"TEST\0\0\0\0", 0, 0, &[0, 0, 0xEF, 0x7B]);
-        assert_eq!(emu.send_file(&file), Err(-10));
+        assert_eq!(emu.send_file(&file), Err(EmuError::NoRom));
     }
 
     #[test]
@@ -3096,7 +5564,28 @@ mod tests {
         emu.power_on();
 
         let file = make_test_8xp(0x05, b"TEST\0\0\0\0", 0, 0, &[0, 0]);
-        assert_eq!(emu.send_file(&file), Err(-13));
+        assert_eq!(emu.send_file(&file), Err(EmuError::AlreadyBooted));
+    }
+
+    #[test]
+    fn test_send_file_error_codes() {
+        let mut emu = Emu::new();
+        emu.load_rom(&vec![0xFF; 1024]).unwrap();
+
+        let mut file = make_test_8xp(0x05, b"TEST\0\0\0\0", 0, 0, &[0, 0]);
+        assert_eq!(emu.send_file(&file[..20]).unwrap_err().code(), -11);
+        let len = file.len();
+        file[len - 1] ^= 0xFF;
+        assert_eq!(emu.send_file(&file).unwrap_err().code(), -14);
+
+        // The largest file's entry, with an 8-char name, overflows a sector
+        let big = make_test_8xp(0x15, b"BIGVAR12", 0, 0x80, &vec![0; 0xFFEE]);
+        assert_eq!(emu.send_file(&big), Err(EmuError::VariableTooLarge));
+        assert_eq!(emu.bus.flash.peek(0x0C0000), 0xFF);
+
+        // Live transfer reports the same codes, without resetting
+        emu.power_on();
+        assert_eq!(emu.send_file_live(&file).unwrap_err().code(), -14);
     }
 
     #[test]
@@ -3121,4 +5610,887 @@ mod tests {
             assert_eq!(emu.bus.flash.peek(0x0C000A), b'M');
         }
     }
+
+    #[test]
+    fn test_search_memory_spans_flash_and_ram() {
+        let mut emu = Emu::new();
+        let mut rom = vec![0xFF; 1024];
+        rom[0x10..0x13].copy_from_slice(&[0x3E, 0x01, 0xC9]);
+        emu.load_rom(&rom).unwrap();
+        emu.bus.poke_byte(0xD00200, 0x3E);
+        emu.bus.poke_byte(0xD00201, 0x7F);
+        emu.bus.poke_byte(0xD00202, 0xC9);
+
+        let p = BytePattern::parse("3E ?? C9").unwrap();
+        assert_eq!(emu.search_memory(&p, 0, 0x1000000, usize::MAX), Ok(vec![0x10, 0xD00200]));
+        assert_eq!(emu.search_memory(&p, 0xD00000, 0xE00000, usize::MAX), Ok(vec![0xD00200]));
+        assert_eq!(emu.search_memory(&p, 0, 0x1000000, 1), Ok(vec![0x10]));
+        assert_eq!(emu.search_memory(&p, 0, 0x1000001, 1), Err(EmuError::OutOfRange));
+    }
+
+    #[test]
+    fn test_replace_memory_refuses_live_code() {
+        let mut emu = Emu::new();
+        let rom = vec![0x00; 1024];
+        emu.load_rom(&rom).unwrap();
+        emu.bus.poke_byte(0xD00100, 0x3E);
+        emu.bus.poke_byte(0xD00101, 0x05);
+
+        let find = BytePattern::parse("3E ??").unwrap();
+        let patch = BytePattern::parse("?? 09").unwrap();
+        assert_eq!(emu.replace_memory(&find, &patch, 0xD00000, 0xD00200, false), Ok(vec![0xD00100]));
+        assert_eq!(emu.bus.peek_byte(0xD00101), 0x09);
+
+        // PC sits on the match: refused unless forced
+        emu.cpu.adl = true;
+        emu.cpu.pc = 0xD00100;
+        assert_eq!(emu.replace_memory(&find, &patch, 0xD00000, 0xD00200, false), Err(EmuError::LiveCode));
+        let short = BytePattern::parse("00").unwrap();
+        assert_eq!(emu.replace_memory(&find, &short, 0xD00000, 0xD00200, true), Err(EmuError::LengthMismatch));
+        assert_eq!(emu.replace_memory(&find, &BytePattern::exact(&[0x3E, 0x01]), 0xD00000, 0xD00200, true), Ok(vec![0xD00100]));
+        assert_eq!(emu.bus.peek_byte(0xD00101), 0x01);
+    }
+
+    #[test]
+    fn test_opcode_coverage_counts_executed_opcodes() {
+        use crate::cpu::opcodes::Page;
+
+        let mut emu = Emu::new();
+        // NOP x 0x40 then JR -2
+        let mut rom = vec![0x00; 0x42];
+        rom[0x40] = 0x18;
+        rom[0x41] = 0xFE;
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+        assert!(emu.opcode_coverage().is_none());
+
+        emu.set_opcode_coverage_enabled(true);
+        emu.run_cycles(10_000);
+        let coverage = emu.opcode_coverage().unwrap();
+        assert_eq!(coverage.count(Page::Main, 0x00), 0x40);
+        assert!(coverage.count(Page::Main, 0x18) > 10);
+        assert!(coverage.report().starts_with("main        2/"));
+
+        emu.clear_opcode_coverage();
+        assert_eq!(emu.opcode_coverage().unwrap().count(Page::Main, 0x00), 0);
+        emu.set_opcode_coverage_enabled(false);
+        assert!(emu.opcode_coverage().is_none());
+    }
+
+    #[test]
+    fn test_heatmap_counts_executed_buckets() {
+        let mut emu = Emu::new();
+        // NOP x 0x40 then JR -2 (tight loop in the second bucket)
+        let mut rom = vec![0x00; 0x42];
+        rom[0x40] = 0x18;
+        rom[0x41] = 0xFE;
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+        assert_eq!(emu.export_heatmap(&mut []), Err(EmuError::HeatmapDisabled));
+
+        emu.set_heatmap_enabled(true);
+        emu.run_cycles(10_000);
+        let heatmap = emu.heatmap.as_ref().unwrap();
+        assert_eq!(heatmap.count(0x00), 0x40);
+        assert!(heatmap.count(0x40) > 10);
+
+        let mut out = vec![0u8; crate::heatmap::EXPORT_SIZE];
+        assert_eq!(emu.export_heatmap(&mut out), Ok(crate::heatmap::EXPORT_SIZE));
+        assert_eq!(emu.export_heatmap(&mut out[..4]), Err(EmuError::BufferTooSmall));
+        emu.set_heatmap_enabled(false);
+        assert_eq!(emu.export_heatmap(&mut out), Err(EmuError::HeatmapDisabled));
+    }
+
+    #[test]
+    fn test_mmio_diff_reported_at_frame_end() {
+        let mut emu = Emu::new();
+        // LD BC,0x5004 ; LD A,0x01 ; OUT (C),A ; JR -2
+        let rom = [0x01, 0x04, 0x50, 0x3E, 0x01, 0xED, 0x79, 0x18, 0xFE];
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+        emu.set_mmio_diff_enabled(true);
+        emu.run_cycles(1_000);
+        while emu.poll_event().is_some() {}
+
+        emu.render_frame();
+        let changes = emu.mmio_diff();
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].addr, changes[0].port, changes[0].pc), (0x5004, 1, 0x05));
+        assert_eq!(changes[0].new_value, 0x01);
+        assert_eq!(changes[0].peripheral(), "interrupt");
+        let event = emu.poll_event().unwrap();
+        assert_eq!((event.kind, event.value), (EventKind::MmioDiff as u32, 1));
+        assert!(emu.poll_event().is_none());
+
+        // Nothing changed since: no report, no diff event
+        emu.run_cycles(1_000);
+        emu.render_frame();
+        assert!(emu.mmio_diff().is_empty());
+        assert!(emu.poll_event().is_none());
+
+        emu.set_mmio_diff_enabled(false);
+        assert!(emu.mmio_diff().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_upbase_renders_diagnostic_pattern() {
+        let mut emu = Emu::new();
+        emu.render_frame();
+        assert!(emu.upbase_valid());
+
+        // Frame would run past the end of RAM
+        emu.bus.ports.lcd.set_upbase(0xD60000);
+        emu.render_frame();
+        assert!(!emu.upbase_valid());
+        assert_eq!(emu.framebuffer[0], 0xFF000000);
+        assert_eq!(emu.framebuffer[8], INVALID_UPBASE_COLOR);
+        assert_eq!(emu.framebuffer[8 * SCREEN_WIDTH], INVALID_UPBASE_COLOR);
+
+        emu.bus.ports.lcd.set_upbase(0x000000);
+        emu.render_frame();
+        assert!(!emu.upbase_valid());
+
+        emu.bus.ports.lcd.set_upbase(crate::memory::addr::VRAM_START);
+        emu.render_frame();
+        assert!(emu.upbase_valid());
+    }
+
+    #[test]
+    fn test_vram_follows_upbase_and_bpp() {
+        use crate::memory::addr::VRAM_START;
+
+        let mut emu = Emu::new();
+        // RAM is allocated on first write
+        emu.bus.poke_byte(VRAM_START, 0x34);
+        emu.bus.poke_byte(VRAM_START + 1, 0x12);
+        assert_eq!(emu.vram_bpp(), 16);
+        let vram = emu.vram().unwrap();
+        assert_eq!(vram.len(), SCREEN_WIDTH * SCREEN_HEIGHT * 2);
+        assert_eq!(&vram[..2], &[0x34, 0x12]);
+
+        // 8bpp frames are a byte per pixel
+        let control = emu.bus.ports.lcd.control();
+        emu.bus.ports.lcd.set_control((control & !0x0E) | (3 << 1));
+        assert_eq!(emu.vram_bpp(), 8);
+        assert_eq!(emu.vram().unwrap().len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+
+        emu.bus.ports.lcd.set_upbase(VRAM_START + 1);
+        assert_eq!(emu.vram().unwrap()[0], 0x12);
+        emu.bus.ports.lcd.set_upbase(0xD60000);
+        assert!(emu.vram().is_none());
+    }
+
+    #[test]
+    fn test_scanline_render_shows_mid_frame_changes() {
+        use crate::memory::addr::VRAM_START;
+        use crate::test_rom::DemoConfig;
+
+        const BLACK: u32 = 0xFF000000;
+        let mut emu = Emu::new();
+        emu.load_demo(DemoConfig::new(DemoConfig::BARS, 60).unwrap()).unwrap();
+        emu.power_on();
+        emu.set_scanline_render(true);
+        emu.run_cycles(2_000_000);
+
+        // Paint the screen black while the LCD is halfway down it
+        while !(100..140).contains(&emu.bus.ports.lcd.cur_row()) {
+            emu.run_cycles(500);
+        }
+        let row = emu.bus.ports.lcd.cur_row() as usize;
+        let black = vec![0x00; SCREEN_WIDTH * SCREEN_HEIGHT * 2];
+        emu.write_block(VRAM_START, &black, false).unwrap();
+        emu.run_frame();
+        assert!(emu.upbase_valid());
+        assert_ne!(emu.framebuffer[(row - 1) * SCREEN_WIDTH], BLACK, "row {} was already scanned", row - 1);
+        assert_eq!(emu.framebuffer[200 * SCREEN_WIDTH], BLACK);
+
+        // The next frame is all black; a snapshot always was
+        emu.run_frame();
+        assert_eq!(emu.framebuffer[0], BLACK);
+        emu.set_scanline_render(false);
+        assert!(!emu.scanline_render());
+        emu.render_frame();
+        assert_eq!(emu.framebuffer[0], BLACK);
+    }
+
+    #[test]
+    fn test_color_profile_applies_to_rendered_frames() {
+        use crate::memory::addr::VRAM_START;
+
+        let mut emu = Emu::new();
+        emu.bus.poke_byte(VRAM_START, 0x00); // black
+        emu.render_frame();
+        assert_eq!(emu.framebuffer[0], 0xFF000000);
+
+        emu.set_color_profile(ColorProfile::PanelAccurate);
+        assert_eq!(emu.color_profile(), ColorProfile::PanelAccurate);
+        emu.render_frame();
+        assert_ne!(emu.framebuffer[0], 0xFF000000, "panel blacks are lifted");
+        assert_eq!(emu.framebuffer[0], ColorTransform::new(ColorProfile::PanelAccurate).apply(0xFF000000));
+
+        // Not part of the emulated machine
+        emu.reset();
+        assert_eq!(emu.color_profile(), ColorProfile::PanelAccurate);
+
+        // The invalid-UPBASE pattern is never transformed
+        emu.bus.ports.lcd.set_upbase(0xD60000);
+        emu.render_frame();
+        assert_eq!(emu.framebuffer[0], 0xFF000000);
+        assert_eq!(emu.framebuffer[8], INVALID_UPBASE_COLOR);
+    }
+
+    #[test]
+    fn test_pixel_format_follows_rendered_frames() {
+        let mut emu = Emu::new();
+        assert_eq!(emu.formatted_framebuffer(), None);
+        emu.set_pixel_format(PixelFormat::Rgb565);
+        assert_eq!(emu.formatted_framebuffer().unwrap().len(), SCREEN_WIDTH * SCREEN_HEIGHT * 2);
+
+        // Converted after the color profile, like the ARGB8888 frame
+        emu.set_color_profile(ColorProfile::PanelAccurate);
+        emu.bus.ports.lcd.set_upbase(0xD60000);
+        emu.render_frame();
+        let mut expected = Vec::new();
+        pixel_format::convert(PixelFormat::Rgb565, &emu.framebuffer, &mut expected);
+        assert_eq!(emu.formatted_framebuffer(), Some(&expected[..]));
+        assert_eq!(&expected[16..18], &0xF81Fu16.to_le_bytes()); // magenta
+
+        emu.set_pixel_format(PixelFormat::Argb8888);
+        assert_eq!(emu.formatted_framebuffer_ptr(), emu.framebuffer_ptr() as *const u8);
+    }
+
+    extern "C" fn collect_event(user: *mut c_void, event: *const EmuEvent) {
+        let events = unsafe { &mut *(user as *mut Vec<EmuEvent>) };
+        events.push(unsafe { *event });
+    }
+
+    #[test]
+    fn test_backlight_transition_events() {
+        let mut emu = Emu::new();
+        // LD BC,0xB024 ; LD A,0 ; OUT (C),A ; LD A,0xFF ; OUT (C),A ; JR -2
+        let rom = [0x01, 0x24, 0xB0, 0x3E, 0x00, 0xED, 0x79, 0x3E, 0xFF, 0xED, 0x79, 0x18, 0xFE];
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+
+        let mut events: Vec<EmuEvent> = Vec::new();
+        emu.set_event_callback(Some(collect_event), &mut events as *mut _ as *mut c_void);
+        emu.run_cycles(1_000);
+        emu.set_event_callback(None, ptr::null_mut());
+
+        // The LCD is unpowered from reset, so only the backlight toggles
+        let kinds: Vec<u32> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, vec![EventKind::BacklightOff as u32, EventKind::BacklightOn as u32]);
+        assert_eq!(events[0].value, 0);
+        assert_eq!(events[1].value, 0xFF);
+        assert!(events[0].cycles > 0 && events[0].cycles < events[1].cycles);
+    }
+
+    /// Extension device that answers reads with 0x80 | offset and logs writes
+    struct LoggingDevice(std::sync::Arc<std::sync::Mutex<Vec<(u32, u8)>>>);
+
+    impl Peripheral for LoggingDevice {
+        fn read(&mut self, offset: u32, _ctx: &crate::peripherals::PortContext) -> u8 {
+            0x80 | offset as u8
+        }
+
+        fn write(&mut self, offset: u32, value: u8, _ctx: &crate::peripherals::PortContext) {
+            self.0.lock().unwrap().push((offset, value));
+        }
+
+        fn reset(&mut self) {}
+    }
+
+    #[test]
+    fn test_extension_device_sees_cpu_accesses() {
+        let mut emu = Emu::new();
+        // LD A,0x42 ; LD.LIL (0xE40010),A ; LD.LIL A,(0xE40003) ; LD.LIL (0xE40011),A ; HALT
+        let rom = [
+            0x3E, 0x42, 0x5B, 0x32, 0x10, 0x00, 0xE4, 0x5B, 0x3A, 0x03, 0x00, 0xE4,
+            0x5B, 0x32, 0x11, 0x00, 0xE4, 0x76,
+        ];
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+        let writes = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let id = emu.register_extension(0xE40000, 0x20, Box::new(LoggingDevice(writes.clone()))).unwrap();
+
+        emu.run_cycles(1_000);
+        assert_eq!(*writes.lock().unwrap(), vec![(0x10, 0x42), (0x11, 0x83)]);
+
+        emu.unregister_extension(id).unwrap();
+        emu.reset();
+        emu.powered_on = true;
+        emu.run_cycles(1_000);
+        assert_eq!(writes.lock().unwrap().len(), 2, "detached device sees nothing");
+    }
+
+    #[test]
+    fn test_host_bridge_echo_and_result() {
+        let mut emu = Emu::new();
+        // LD.LIL A,(0xFE0001) ; LD.LIL (0xFE0000),A ; LD A,0 ; LD.LIL (0xFE0004),A ; HALT
+        let rom = [
+            0x5B, 0x3A, 0x01, 0x00, 0xFE, 0x5B, 0x32, 0x00, 0x00, 0xFE, 0x3E, 0x00,
+            0x5B, 0x32, 0x04, 0x00, 0xFE, 0x76,
+        ];
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+        assert_eq!(emu.host_test_result(), Err(EmuError::BridgeDisabled));
+        emu.enable_host_bridge().unwrap();
+        emu.enable_host_bridge().unwrap();
+        emu.host_bridge_push_input(b"Z").unwrap();
+        assert_eq!(emu.host_test_result(), Err(EmuError::NoTestResult));
+
+        emu.run_cycles(1_000);
+        assert_eq!(emu.take_host_output(16), Ok(b"Z".to_vec()));
+        assert_eq!(emu.host_test_result(), Ok(0));
+        assert_eq!(emu.host_output_dropped(), Ok(0));
+
+        emu.disable_host_bridge();
+        assert_eq!(emu.take_host_output(16), Err(EmuError::BridgeDisabled));
+        assert_eq!(emu.host_output_dropped(), Err(EmuError::BridgeDisabled));
+        // The range is free for other extensions again
+        let writes = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        emu.register_extension(BRIDGE_BASE, BRIDGE_LEN, Box::new(LoggingDevice(writes))).unwrap();
+        assert_eq!(emu.enable_host_bridge(), Err(EmuError::ExtensionOverlap));
+    }
+
+    #[test]
+    fn test_memory_map_includes_extensions() {
+        let mut emu = Emu::new();
+        let writes = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        emu.register_extension(0xE40000, 0x20, Box::new(LoggingDevice(writes))).unwrap();
+        emu.enable_host_bridge().unwrap();
+
+        let map = emu.memory_map();
+        let find = |addr| *crate::memory_map::lookup(&map, addr).unwrap();
+        assert_eq!(find(0xE40010).name, "extension");
+        assert_eq!(find(0xE40020).kind, RegionKind::Unmapped);
+        assert_eq!(find(BRIDGE_BASE).name, "host bridge");
+        assert_eq!(find(BRIDGE_BASE + BRIDGE_LEN).kind, RegionKind::Unmapped);
+        assert_eq!(map.len(), memory_map::annotations().len() + 3);
+    }
+
+    #[test]
+    fn test_breakpoint_and_frame_events_are_queued() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00, 0x00, 0x00, 0x76]).unwrap();
+        emu.powered_on = true;
+        emu.set_breakpoint(0x000002);
+
+        emu.run_cycles(1_000);
+        let event = emu.poll_event().unwrap();
+        assert_eq!(event.kind, EventKind::BreakpointHit as u32);
+        assert_eq!(event.value, 2);
+        assert_eq!(event.cycles, emu.total_cycles);
+        assert!(emu.poll_event().is_none());
+
+        // Rendering on demand is not a frame
+        emu.render_frame();
+        assert!(emu.poll_event().is_none());
+    }
+
+    #[test]
+    fn test_frame_done_at_lcd_vsync() {
+        let mut emu = Emu::new();
+        emu.load_rom(&crate::test_rom::build()).unwrap();
+        emu.power_on();
+        emu.run_cycles(2_000_000);
+        assert!(emu.is_lcd_on());
+        while emu.poll_event().is_some() {}
+
+        // The test ROM doesn't program the LCD timing, so frames are only a
+        // few cycles long (several can end in one instruction): stay under
+        // the queue's cap
+        let generated = emu.frame_counters.generated;
+        emu.run_cycles(400);
+        let frames: Vec<EmuEvent> =
+            std::iter::from_fn(|| emu.poll_event()).filter(|e| e.kind == EventKind::FrameDone as u32).collect();
+        assert_eq!(frames.len() as u64, emu.frame_counters.generated - generated);
+        assert!(!frames.is_empty());
+        assert!(frames.iter().all(|e| e.value == 1));
+        assert!(frames.windows(2).all(|w| w[0].cycles <= w[1].cycles));
+    }
+
+    #[test]
+    fn test_run_until_pc_stops_at_breakpoint_first() {
+        // NOP ; NOP ; NOP ; HALT
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00, 0x00, 0x00, 0x76]).unwrap();
+        emu.powered_on = true;
+        emu.set_breakpoint(0x01);
+        assert_eq!(emu.run_until_pc(0x03, 1_000), RunUntil::Stopped);
+        assert_eq!(emu.pc(), 0x01);
+        emu.clear_breakpoint();
+        assert_eq!(emu.run_until_pc(0x03, 1_000), RunUntil::Reached);
+        assert_eq!(emu.last_stop_reason(), StopReason::TargetReached);
+        // A later plain run no longer stops there
+        emu.run_cycles(100);
+        assert_eq!(emu.last_stop_reason(), StopReason::CyclesComplete);
+    }
+
+    #[test]
+    fn test_set_breakpoint_keeps_others() {
+        // NOP ; NOP ; NOP ; HALT
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00, 0x00, 0x00, 0x76]).unwrap();
+        emu.powered_on = true;
+        emu.add_breakpoint(0x01);
+        emu.set_breakpoint(0x02);
+        assert_eq!(emu.breakpoints(), [(0x01, true), (0x02, true)]);
+
+        emu.run_cycles(1_000);
+        assert_eq!(emu.last_breakpoint(), Some(0x01));
+        assert!(emu.breakpoint_was_hit());
+        emu.set_breakpoint(0x02);
+        assert!(!emu.breakpoint_was_hit());
+
+        // Resumes past 0x01 and stops at the next one
+        emu.run_cycles(1_000);
+        assert_eq!(emu.last_breakpoint(), Some(0x02));
+        assert_eq!(emu.pc(), 0x02);
+    }
+
+    #[test]
+    fn test_benchmark_pc_hooks_independent_of_run_chunks() {
+        // NOP ; LD A,1 ; NOP ; HALT, timed from 0x01 to 0x04
+        let run = |chunk: u32| {
+            let mut emu = Emu::new();
+            emu.load_rom(&[0x00, 0x3E, 0x01, 0x00, 0x76]).unwrap();
+            emu.powered_on = true;
+            emu.set_benchmark(Some((Hook::Pc(0x01), Hook::Pc(0x04))));
+            for _ in 0..200 / chunk {
+                emu.run_cycles(chunk);
+            }
+            emu
+        };
+
+        let mut emu = run(200);
+        let results = emu.benchmark_results().to_vec();
+        assert_eq!(results.len(), 1);
+        assert!(results[0] > 0);
+        let event = emu.poll_event().unwrap();
+        assert_eq!((event.kind, event.value), (EventKind::BenchmarkDone as u32, (results[0] / 1000) as u32));
+        // Runs ending on a hook PC don't restart or double count
+        assert_eq!(run(1).benchmark_results(), &results[..]);
+
+        emu.reset();
+        emu.set_benchmark(None);
+        assert!(emu.benchmark_results().is_empty());
+    }
+
+    #[test]
+    fn test_interrupt_watch_stops_on_raise_and_acknowledge() {
+        use crate::peripherals::interrupt::{sources, InterruptEdge};
+
+        let mut emu = Emu::new();
+        // LD A,1 ; LD BC,0x500C ; OUT (C),A (latch ON) ;
+        // LD C,0x08 ; OUT (C),A (acknowledge ON) ; HALT
+        emu.load_rom(&[0x3E, 0x01, 0x01, 0x0C, 0x50, 0xED, 0x79, 0x0E, 0x08, 0xED, 0x79, 0x76])
+            .unwrap();
+        emu.powered_on = true;
+        emu.set_interrupt_watch(sources::ON_KEY, sources::ON_KEY);
+
+        // Unwatched sources don't stop execution
+        emu.bus.ports.interrupt.raise(sources::TIMER1);
+        emu.bus.ports.interrupt.raise(sources::ON_KEY);
+        assert_eq!(emu.run_cycles(1_000), 0);
+        let hit = InterruptWatchHit { source: 0, edge: InterruptEdge::Raised };
+        assert_eq!(emu.last_stop_reason(), StopReason::InterruptWatch(hit));
+        assert_eq!(hit.peripheral(), "on key");
+        let event = emu.poll_event().unwrap();
+        assert_eq!((event.kind, event.value), (EventKind::InterruptWatch as u32, 0x100));
+
+        // Stops right after the acknowledging OUT
+        emu.run_cycles(1_000);
+        assert_eq!(emu.pc(), 0x0B);
+        let event = emu.poll_event().unwrap();
+        assert_eq!((event.kind, event.value), (EventKind::InterruptWatch as u32, 0x200));
+
+        emu.run_cycles(1_000);
+        assert_eq!(emu.last_stop_reason(), StopReason::CyclesComplete);
+        assert!(emu.poll_event().is_none());
+    }
+
+    #[test]
+    fn test_request_stop_ends_run_early() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00, 0x00, 0x18, 0xFE]).unwrap();
+        emu.powered_on = true;
+
+        // A request made while idle is consumed by the next run
+        emu.request_stop();
+        assert_eq!(emu.run_cycles(1_000), 0);
+        assert_eq!(emu.last_stop_reason(), StopReason::StopRequested);
+        assert!(emu.run_cycles(1_000) >= 1_000);
+
+        // From another thread while run_cycles holds the emulator
+        let stop = emu.stop_handle();
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            stop.store(true, Ordering::Relaxed);
+        });
+        let executed = emu.run_cycles(i32::MAX as u32);
+        stopper.join().unwrap();
+        assert!(executed < i32::MAX as u32);
+        assert_eq!(emu.last_stop_reason(), StopReason::StopRequested);
+        assert_eq!(emu.total_cycles, emu.bus.total_cycles());
+    }
+
+    #[test]
+    fn test_pause_freezes_execution_and_host_time() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00, 0x00, 0x18, 0xFE]).unwrap();
+        emu.powered_on = true;
+        emu.sync_host_time(5_000);
+        emu.sync_host_time(5_016);
+
+        emu.pause();
+        let pc = emu.cpu.pc;
+        let cycles = emu.total_cycles;
+        assert_eq!(emu.run_cycles(1_000), 0);
+        emu.sync_host_time(900_000);
+        assert_eq!((emu.cpu.pc, emu.total_cycles), (pc, cycles));
+
+        emu.resume();
+        emu.sync_host_time(900_010);
+        emu.sync_host_time(900_026);
+        assert_eq!(emu.host_running_ms(), 32);
+        assert!(emu.run_cycles(1_000) >= 1_000);
+    }
+
+    #[test]
+    fn test_rtc_host_time_clock() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00, 0x00, 0x18, 0xFE]).unwrap();
+        emu.powered_on = true;
+        emu.bus.ports.rtc.write(0x20, 0x01, 0, 0); // Enable counting
+        emu.sync_host_time(0);
+        emu.set_rtc_clock(RtcClock::HostTime);
+
+        // Two emulated seconds with no host time: the clock holds
+        let second = 6_000_000; // Reset speed is 6 MHz
+        emu.run_cycles(second);
+        emu.run_cycles(second);
+        emu.bus.ports.rtc.write(0x20, 0x81, 0, 0); // Latch on the next event
+        assert_eq!(emu.bus.ports.rtc.read(0x00, 0, 0), 0);
+
+        // Five host seconds are caught up on the next tick
+        emu.sync_host_time(5_000);
+        emu.run_cycles(second);
+        emu.run_cycles(second);
+        assert_eq!(emu.bus.ports.rtc.read(0x00, 0, 0), 5);
+
+        // Back on the emulated clock it counts with the run again (the latch
+        // trails the tick, so the second tick may not be visible yet)
+        emu.set_rtc_clock(RtcClock::Emulated);
+        emu.run_cycles(second);
+        emu.run_cycles(second);
+        assert!((6..=7).contains(&emu.bus.ports.rtc.read(0x00, 0, 0)));
+    }
+
+    #[test]
+    fn test_reset_cause() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00, 0x18, 0xFE]).unwrap();
+        assert_eq!(emu.reset_cause(), ResetCause::PowerOn);
+        emu.reset();
+        assert_eq!(emu.reset_cause(), ResetCause::Frontend);
+        emu.reset_with_cause(ResetCause::Watchdog);
+        assert!(emu.crash_report().starts_with("reset_cause=Watchdog "));
+    }
+
+    #[test]
+    fn test_power_standby_follows_host_time() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0xF3, 0x18, 0xFE]).unwrap();
+        emu.power_on();
+        emu.step().unwrap();
+        assert!(emu.power_stats().elapsed_ns > 0, "steps are sampled");
+        assert!(emu.power_stats().cpu_ua > 0);
+
+        // Host time while on is covered by emulated cycles, not standby
+        emu.sync_host_time(1_000);
+        emu.sync_host_time(2_000);
+        assert_eq!(emu.power_stats().charge_uah, 0);
+
+        emu.bus.ports.control.set_off(true);
+        emu.sync_host_time(2_000 + 3_600_000);
+        let stats = emu.power_stats();
+        assert_eq!(stats.current_ua, 100);
+        assert_eq!(stats.cpu_ua, 0);
+        assert_eq!(stats.charge_uah, 100);
+    }
+
+    #[test]
+    fn test_watchdog_expiry_resets() {
+        let mut emu = Emu::new();
+        // Load 0x1000, restart, enable with reset, then count boots at
+        // 0xD00100 and spin without petting the watchdog
+        let rom = [
+            0xF3,
+            0x3E, 0x00, 0x5B, 0x32, 0x04, 0x00, 0xF6,
+            0x3E, 0x10, 0x5B, 0x32, 0x05, 0x00, 0xF6,
+            0x3E, 0x00, 0x5B, 0x32, 0x06, 0x00, 0xF6, 0x5B, 0x32, 0x07, 0x00, 0xF6,
+            0x3E, 0xB9, 0x5B, 0x32, 0x08, 0x00, 0xF6,
+            0x3E, 0x03, 0x5B, 0x32, 0x0C, 0x00, 0xF6,
+            0x5B, 0x3A, 0x00, 0x01, 0xD0, 0x3C, 0x5B, 0x32, 0x00, 0x01, 0xD0,
+            0x18, 0xFE,
+        ];
+        emu.load_rom(&rom).unwrap();
+        emu.power_on();
+        for _ in 0..4 {
+            emu.run_cycles(20_000);
+        }
+        assert_eq!(emu.reset_cause(), ResetCause::Watchdog);
+        assert!(emu.peek_byte(0xD00100) >= 2, "RAM kept across watchdog resets");
+        assert!(emu.powered_on);
+    }
+
+    #[test]
+    fn test_host_bridge_software_reset() {
+        let mut emu = Emu::new();
+        // Count boots at 0xD00100; the first boot requests a software reset
+        let rom = [
+            0xF3,
+            0x5B, 0x3A, 0x00, 0x01, 0xD0, 0x3C, 0x5B, 0x32, 0x00, 0x01, 0xD0,
+            0xFE, 0x01, 0x20, 0xFE,
+            0x3E, crate::host_bridge::RESET_KEY, 0x5B, 0x32, 0x05, 0x00, 0xFE,
+            0x18, 0xFE,
+        ];
+        emu.load_rom(&rom).unwrap();
+        emu.enable_host_bridge().unwrap();
+        emu.power_on();
+        emu.run_cycles(10_000);
+        emu.run_cycles(10_000);
+        assert_eq!(emu.reset_cause(), ResetCause::Software);
+        assert_eq!(emu.peek_byte(0xD00100), 2);
+    }
+
+    #[test]
+    fn test_ram_cleared_by_reset_cause() {
+        use crate::memory::addr::{RAM_END, RAM_START, VRAM_START};
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00, 0x18, 0xFE]).unwrap();
+
+        // RAM and VRAM are kept or cleared together, edge to edge
+        let probes = [RAM_START, 0xD00100, VRAM_START - 1, VRAM_START, RAM_END - 1];
+        for cause in [ResetCause::Watchdog, ResetCause::Software, ResetCause::PowerCycle] {
+            for &addr in &probes {
+                emu.poke_byte(addr, 0xA5);
+            }
+            emu.reset_with_cause(cause);
+            for &addr in &probes {
+                assert_eq!(emu.peek_byte(addr), 0xA5, "{:?} at {:06X}", cause, addr);
+            }
+        }
+        for cause in [ResetCause::Frontend, ResetCause::KeyCombo, ResetCause::PowerOn] {
+            for &addr in &probes {
+                emu.poke_byte(addr, 0xA5);
+            }
+            emu.reset_with_cause(cause);
+            for &addr in &probes {
+                assert_eq!(emu.peek_byte(addr), 0x00, "{:?} at {:06X}", cause, addr);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reset_kinds() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00, 0x18, 0xFE]).unwrap();
+        let file = make_test_8xp(0x05, b"TEST\0\0\0\0", 0, 0, &[0x02, 0x00, 0xEF, 0x7B]);
+        emu.send_file(&file).unwrap();
+        let archived = emu.bus.flash.peek(0x0C0000);
+        assert_ne!(archived, 0xFF);
+
+        emu.poke_byte(0xD00100, 0xA5);
+        emu.powered_on = true;
+        emu.reset_kind(ResetKind::PowerCycle);
+        assert_eq!(emu.peek_byte(0xD00100), 0xA5);
+        assert_eq!(emu.reset_cause(), ResetCause::PowerCycle);
+        assert!(!emu.powered_on, "waits for ON");
+
+        emu.reset_kind(ResetKind::RamClear);
+        assert_eq!(emu.peek_byte(0xD00100), 0x00);
+        assert_eq!(emu.bus.flash.peek(0x0C0000), archived, "archive kept");
+
+        emu.reset_kind(ResetKind::Full);
+        assert_eq!(emu.bus.flash.peek(0x0C0000), 0xFF, "archive erased");
+        assert_eq!(emu.bus.flash.peek(0x000001), 0x18, "boot code kept");
+        assert_eq!(ResetKind::from_raw(3), None);
+    }
+
+    #[test]
+    fn test_slot_save_and_load() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00, 0x00, 0x18, 0xFE]).unwrap();
+        emu.powered_on = true;
+        emu.run_cycles(1_000);
+        let pc = emu.cpu.pc;
+        let cycles = emu.total_cycles;
+
+        let size = emu.slot_save(2).unwrap();
+        assert_eq!(size, emu.save_state_size());
+        let info = emu.slot_info(2).unwrap();
+        assert_eq!((info.used, info.size as usize, info.cycles), (1, size, cycles));
+
+        emu.run_cycles(5_000);
+        emu.slot_load(2).unwrap();
+        assert_eq!(emu.cpu.pc, pc);
+        assert_eq!(emu.total_cycles, cycles);
+
+        assert_eq!(emu.slot_load(3), Err(EmuError::EmptySlot));
+        emu.set_slot_memory_limit(Some(size));
+        assert_eq!(emu.slot_save(3), Err(EmuError::SlotLimit));
+        assert_eq!(emu.slot_save(2), Ok(size), "overwriting stays within the limit");
+        emu.slot_clear(2).unwrap();
+        assert_eq!(emu.slot_info(2).unwrap().used, 0);
+    }
+
+    #[test]
+    fn test_reset_combo_holds_keys_through_boot() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00, 0x00, 0x18, 0xFE]).unwrap();
+        emu.reset_with_combo(ResetCombo::SecondDel);
+        assert_eq!(emu.reset_cause(), ResetCause::KeyCombo);
+        assert!(emu.powered_on, "reset button restarts without ON");
+        assert!(emu.bus.key_state()[1][5] && emu.bus.key_state()[1][7]);
+        assert!(!emu.bus.key_state()[2][0], "ON is released again");
+
+        // A key the frontend holds survives the release
+        emu.set_key(1, 7, true);
+        emu.run_cycles(RESET_COMBO_HOLD_CYCLES as u32);
+        assert!(emu.bus.key_state()[1][5], "held until the deadline");
+        emu.run_cycles(1_000);
+        assert!(!emu.bus.key_state()[1][5]);
+        assert!(emu.bus.key_state()[1][7]);
+
+        emu.reset_with_combo(ResetCombo::Button);
+        assert_eq!(emu.reset_cause(), ResetCause::Frontend);
+        assert!(emu.combo_release.is_none());
+        assert_eq!(ResetCombo::from_raw(3), None);
+    }
+
+    #[test]
+    fn test_best_effort_load_keeps_flash_for_other_rom() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00, 0x00, 0x18, 0xFE]).unwrap();
+        emu.powered_on = true;
+        emu.run_cycles(1_000);
+        emu.poke_byte(0xD01234, 0x5A);
+        let pc = emu.cpu.pc;
+        let mut state = vec![0; emu.save_state_size()];
+        let len = emu.save_state(&mut state).unwrap();
+        state.truncate(len);
+
+        assert_eq!(emu.load_state_best_effort(&state), Ok(StateLoad::Full));
+
+        let mut other = Emu::new();
+        other.load_rom(&[0x00, 0x18, 0xFE]).unwrap();
+        let hash = other.rom_hash();
+        assert_eq!(other.load_state(&state).unwrap_err().code(), -104);
+        assert_eq!(other.load_state_best_effort(&state), Ok(StateLoad::KeptFlash));
+        assert_eq!(other.peek_byte(0xD01234), 0x5A, "RAM restored");
+        assert_eq!(other.cpu.pc, pc, "registers restored");
+        assert_eq!(other.peek_byte(0x000001), 0x18, "own flash kept");
+        assert_eq!(other.rom_hash(), hash);
+
+        // Corrupt states are still refused
+        let mut bad = state.clone();
+        bad[4] = 99;
+        assert!(other.load_state_best_effort(&bad).is_err());
+        assert!(Emu::new().load_state_best_effort(&state).is_err(), "needs a ROM to keep");
+    }
+
+    #[test]
+    fn test_held_keys_reconciled_on_state_load() {
+        use crate::peripherals::interrupt::sources;
+
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00, 0x00, 0x18, 0xFE]).unwrap();
+        emu.powered_on = true;
+        emu.bus.ports.keypad.write(0x00, 1); // any-key mode
+        emu.bus.ports.keypad.write(0x0C, 0x04); // any-key interrupt
+        emu.set_key(1, 3, true);
+        emu.run_cycles(1_000);
+        let keypad_status = emu.bus.ports.keypad.status();
+        let irq_status = emu.bus.ports.interrupt.status();
+        assert_ne!(irq_status & sources::KEYPAD, 0);
+        emu.slot_save(0).unwrap();
+
+        // Still held: restored exactly, pending interrupt included
+        emu.run_cycles(1_000);
+        emu.slot_load(0).unwrap();
+        assert!(emu.bus.key_state()[1][3]);
+        assert_eq!(emu.bus.ports.keypad.status(), keypad_status);
+        assert_eq!(emu.bus.ports.interrupt.status(), irq_status);
+
+        // Released since the save: cleared instead of sticking
+        emu.set_key(1, 3, false);
+        emu.slot_load(0).unwrap();
+        assert!(!emu.bus.key_state()[1][3]);
+
+        // Held now but not in the state: pressed again
+        emu.set_key(5, 5, true);
+        emu.slot_load(0).unwrap();
+        assert!(emu.bus.key_state()[5][5]);
+        assert!(!emu.bus.key_state()[1][3]);
+        assert!(emu.cpu.any_key_wake);
+
+        // A fresh emulator holds nothing
+        let mut data = vec![0u8; emu.save_state_size()];
+        emu.set_key(2, 0, true); // ON
+        emu.save_state(&mut data).unwrap();
+        let mut other = Emu::new();
+        other.load_rom(&[0x00, 0x00, 0x18, 0xFE]).unwrap();
+        other.load_state(&data).unwrap();
+        assert!(!other.bus.key_state()[5][5]);
+        assert!(!other.bus.key_state()[2][0]);
+    }
+
+    extern "C" fn capture_autosave(user: *mut c_void, data: *const u8, len: usize, reason: u32) {
+        let out = unsafe { &mut *(user as *mut Vec<(u32, Vec<u8>)>) };
+        out.push((reason, unsafe { std::slice::from_raw_parts(data, len) }.to_vec()));
+    }
+
+    #[test]
+    fn test_autosave_roundtrip() {
+        let mut saves: Vec<(u32, Vec<u8>)> = Vec::new();
+        let mut emu = Emu::new();
+        emu.set_autosave_sink(Some(capture_autosave), &mut saves as *mut _ as *mut c_void);
+        assert!(!emu.autosave(AutosaveReason::Destroy), "nothing to save without a ROM");
+
+        let rom = [0x00, 0x00, 0x18, 0xFE];
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+        emu.run_cycles(1_000);
+        assert!(emu.autosave(AutosaveReason::Crash));
+        assert_eq!(saves.len(), 1);
+        assert_eq!(saves[0].0, AutosaveReason::Crash as u32);
+
+        let mut restored = Emu::new();
+        restored.load_rom(&rom).unwrap();
+        restored.load_state(&saves[0].1).unwrap();
+        assert_eq!(restored.cpu.pc, emu.cpu.pc);
+        assert_eq!(restored.total_cycles, emu.total_cycles);
+    }
+
+    #[test]
+    fn test_autosave_on_protection_trap() {
+        let mut saves: Vec<(u32, Vec<u8>)> = Vec::new();
+        let mut emu = Emu::new();
+        emu.set_autosave_sink(Some(capture_autosave), &mut saves as *mut _ as *mut c_void);
+        // LD HL,0x1000 ; LD (HL),A ; JR $
+        emu.load_rom(&[0x21, 0x00, 0x10, 0x77, 0x18, 0xFE]).unwrap();
+        emu.powered_on = true;
+        // Writing the stack limit address is a violation
+        for (i, byte) in [0x00, 0x10, 0x00].into_iter().enumerate() {
+            emu.bus.ports.control.write(0x3A + i as u32, byte);
+        }
+        emu.run_cycles(1_000);
+        assert!(!saves.is_empty());
+        assert_eq!(saves[0].0, AutosaveReason::Trap as u32);
+        let mut restored = Emu::new();
+        restored.load_rom(&[0x21, 0x00, 0x10, 0x77, 0x18, 0xFE]).unwrap();
+        assert!(restored.load_state(&saves[0].1).is_ok());
+    }
 }
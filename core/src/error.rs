@@ -0,0 +1,309 @@
+//! Typed errors
+//!
+//! `Emu::load_rom` and `Emu::load_state` report why the data was rejected,
+//! with enough context (offsets, expected and found values) to tell a
+//! truncated download from a state made with another ROM. The other
+//! fallible `Emu` operations (file transfer, OS install, save state
+//! buffers, slots, block memory access) return `EmuError`, which wraps
+//! the detailed file, OS and load errors. The C ABI and wasm bindings
+//! reduce them to the codes in emu.h with `code()`, and `error_string`
+//! describes any code in emu.h, for logging from frontends.
+
+use std::ffi::CStr;
+use std::fmt;
+
+use crate::os_update::OsUpdateError;
+use crate::ti_file::TiFileError;
+
+/// Why `load_rom` or `load_state` rejected its input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadError {
+    /// ROM image has no bytes (-2)
+    EmptyRom,
+    /// ROM image is larger than flash (-3)
+    RomTooLarge { size: usize, max: usize },
+    /// Buffer shorter than the state header (-102)
+    StateTooShort { len: usize, min: usize },
+    /// First four bytes are not the state magic (-102)
+    BadMagic { expected: [u8; 4], found: [u8; 4] },
+    /// State written by an incompatible version (-103)
+    VersionMismatch { expected: u32, found: u32 },
+    /// State saved with a different ROM (-104)
+    RomMismatch { expected: u64, found: u64 },
+    /// Declared or actual payload shorter than the state needs (-105)
+    StateTruncated { expected: usize, found: usize },
+    /// A section failed to decode at `offset` into the buffer (-105)
+    BadSection { section: &'static str, offset: usize },
+    /// Payload doesn't match the CRC-32 in the header (-106)
+    ChecksumMismatch { expected: u32, found: u32 },
+}
+
+impl LoadError {
+    /// C ABI error code (see emu.h)
+    pub fn code(&self) -> i32 {
+        match self {
+            LoadError::EmptyRom => -2,
+            LoadError::RomTooLarge { .. } => -3,
+            LoadError::StateTooShort { .. } | LoadError::BadMagic { .. } => -102,
+            LoadError::VersionMismatch { .. } => -103,
+            LoadError::RomMismatch { .. } => -104,
+            LoadError::StateTruncated { .. }
+            | LoadError::BadSection { .. } => -105,
+            LoadError::ChecksumMismatch { .. } => -106,
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::EmptyRom => write!(f, "ROM is empty"),
+            LoadError::RomTooLarge { size, max } => {
+                write!(f, "ROM is {} bytes, flash holds {}", size, max)
+            }
+            LoadError::StateTooShort { len, min } => {
+                write!(f, "state is {} bytes, the header alone is {}", len, min)
+            }
+            LoadError::BadMagic { expected, found } => write!(
+                f,
+                "not a save state: expected magic {:02X?}, found {:02X?}",
+                expected, found
+            ),
+            LoadError::VersionMismatch { expected, found } => {
+                write!(f, "state version {} is not supported (expected {})", found, expected)
+            }
+            LoadError::RomMismatch { expected, found } => write!(
+                f,
+                "state was saved with another ROM (hash {:016X}, loaded ROM is {:016X})",
+                found, expected
+            ),
+            LoadError::StateTruncated { expected, found } => {
+                write!(f, "state payload is {} bytes, expected at least {}", found, expected)
+            }
+            LoadError::BadSection { section, offset } => {
+                write!(f, "corrupt {} section at offset {}", section, offset)
+            }
+            LoadError::ChecksumMismatch { expected, found } => {
+                write!(f, "state checksum is {:08X}, header says {:08X}", found, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Why an `Emu` operation failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmuError {
+    /// ROM or save state rejected (-2, -3, -102..-106)
+    Load(LoadError),
+    /// No ROM loaded (-10)
+    NoRom,
+    /// Variable file did not parse (-11, -14, -15)
+    File(TiFileError),
+    /// No free space in the flash archive (-12)
+    ArchiveFull,
+    /// `send_file` called after the OS booted (-13)
+    AlreadyBooted,
+    /// Variable does not fit in one archive sector (-16)
+    VariableTooLarge,
+    /// Watch expression does not parse (-20)
+    WatchSyntax,
+    /// MAX_WATCHES already registered (-21)
+    TooManyWatches,
+    /// Address range or argument out of bounds (-30)
+    OutOfRange,
+    /// Replacement overlaps the executing instruction or recent history (-31)
+    LiveCode,
+    /// Pattern and replacement lengths differ (-32)
+    LengthMismatch,
+    /// Output buffer smaller than the data (-33)
+    BufferTooSmall,
+    /// Heatmap export with collection disabled (-34)
+    HeatmapDisabled,
+    /// Slot number out of range (-40)
+    BadSlot,
+    /// Slot holds no state (-41)
+    EmptySlot,
+    /// Slot memory limit would be exceeded (-42)
+    SlotLimit,
+    /// No save slot older than the current state to replay from (-43)
+    NoSnapshot,
+    /// Extension range empty or not inside one unmapped MMIO window (-50)
+    ExtensionRange,
+    /// Extension range overlaps another extension (-51)
+    ExtensionOverlap,
+    /// MAX_EXTENSIONS already registered (-52)
+    TooManyExtensions,
+    /// No extension with that id (-53)
+    UnknownExtension,
+    /// Host bridge not enabled (-60)
+    BridgeDisabled,
+    /// Program hasn't reported a test result through the bridge (-61)
+    NoTestResult,
+    /// `flash_os` with no ROM loaded (-80)
+    OsNoRom,
+    /// OS upgrade rejected (-81..-84)
+    Os(OsUpdateError),
+    /// Buffer smaller than `save_state_size` (-101)
+    StateBufferTooSmall,
+}
+
+impl EmuError {
+    /// C ABI error code (see emu.h)
+    pub fn code(&self) -> i32 {
+        match self {
+            EmuError::Load(err) => err.code(),
+            EmuError::NoRom => -10,
+            EmuError::File(err) => err.code(),
+            EmuError::ArchiveFull => -12,
+            EmuError::AlreadyBooted => -13,
+            EmuError::VariableTooLarge => -16,
+            EmuError::WatchSyntax => -20,
+            EmuError::TooManyWatches => -21,
+            EmuError::OutOfRange => -30,
+            EmuError::LiveCode => -31,
+            EmuError::LengthMismatch => -32,
+            EmuError::BufferTooSmall => -33,
+            EmuError::HeatmapDisabled => -34,
+            EmuError::BadSlot => -40,
+            EmuError::EmptySlot => -41,
+            EmuError::SlotLimit => -42,
+            EmuError::NoSnapshot => -43,
+            EmuError::ExtensionRange => -50,
+            EmuError::ExtensionOverlap => -51,
+            EmuError::TooManyExtensions => -52,
+            EmuError::UnknownExtension => -53,
+            EmuError::BridgeDisabled => -60,
+            EmuError::NoTestResult => -61,
+            EmuError::OsNoRom => -80,
+            EmuError::Os(OsUpdateError::BadFile) => -81,
+            EmuError::Os(OsUpdateError::NotCeOs) => -82,
+            EmuError::Os(OsUpdateError::TooLarge) => -83,
+            EmuError::Os(OsUpdateError::VerifyFailed { .. }) => -84,
+            EmuError::StateBufferTooSmall => -101,
+        }
+    }
+}
+
+impl fmt::Display for EmuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmuError::Load(err) => err.fmt(f),
+            EmuError::File(err) => err.fmt(f),
+            EmuError::Os(err) => err.fmt(f),
+            EmuError::HeatmapDisabled => f.write_str("heatmap collection is disabled"),
+            _ => f.write_str(error_string(self.code())),
+        }
+    }
+}
+
+impl std::error::Error for EmuError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EmuError::Load(err) => Some(err),
+            EmuError::File(err) => Some(err),
+            EmuError::Os(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<LoadError> for EmuError {
+    fn from(err: LoadError) -> Self {
+        EmuError::Load(err)
+    }
+}
+
+impl From<TiFileError> for EmuError {
+    fn from(err: TiFileError) -> Self {
+        EmuError::File(err)
+    }
+}
+
+/// Short description of a C ABI return code (NUL-terminated for the C ABI).
+/// Codes that mean different things to different calls get the most common
+/// meaning; see emu.h for each call's codes.
+pub fn error_cstr(code: i32) -> &'static CStr {
+    match code {
+        0.. => c"ok",
+        -1 => c"null pointer",
+        -2 => c"ROM is empty",
+        -3 => c"ROM larger than flash",
+        -10 => c"no ROM loaded",
+        -11 => c"not a valid TI variable file",
+        -12 => c"no free space in the flash archive",
+        -13 => c"files must be sent before power on",
+        -14 => c"variable file checksum mismatch",
+        -15 => c"variable file is truncated",
+        -16 => c"variable too large for an archive sector",
+        -20 => c"watch expression does not parse",
+        -21 => c"too many watch expressions",
+        -22 => c"unknown watch id",
+        -30 => c"argument out of range",
+        -31 => c"match overlaps executing code",
+        -32 => c"pattern and replacement lengths differ",
+        -33 => c"output buffer too small",
+        -34 => c"collection is disabled",
+        -40 => c"slot number out of range",
+        -41 => c"slot is empty",
+        -42 => c"slot memory limit exceeded",
+        -43 => c"no save slot older than the current state",
+        -50 => c"extension outside an unmapped MMIO window",
+        -51 => c"extension overlaps another",
+        -52 => c"too many extensions",
+        -53 => c"unknown extension id",
+        -60 => c"host bridge not enabled",
+        -61 => c"no host test result yet",
+        -70 => c"test spec does not parse",
+        -80 => c"no ROM loaded",
+        -81 => c"not a valid OS upgrade file",
+        -82 => c"no TI-84 Plus CE OS in file",
+        -83 => c"OS image larger than flash",
+        -84 => c"OS verify failed",
+        -90 => c"no Ans variable",
+        -91 => c"Ans is not a real number",
+        -95 => c"macro recording not active",
+        -101 => c"state buffer too small",
+        -102 => c"not a save state",
+        -103 => c"state version not supported",
+        -104 => c"state was saved with another ROM",
+        -105 => c"state is truncated or corrupt",
+        -106 => c"state checksum mismatch",
+        -110 => c"malformed patch",
+        -111 => c"patch reaches past the end of flash",
+        _ => c"unknown error",
+    }
+}
+
+/// `error_cstr` as a `&str`
+pub fn error_string(code: i32) -> &'static str {
+    error_cstr(code).to_str().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emu_error_codes_and_strings() {
+        let err = EmuError::from(TiFileError::BadChecksum { expected: 1, actual: 2 });
+        assert_eq!(err.code(), -14);
+        assert_eq!(err.to_string(), "bad checksum: expected 0x0001, got 0x0002");
+        assert_eq!(EmuError::Os(OsUpdateError::VerifyFailed { addr: 0 }).code(), -84);
+        assert_eq!(EmuError::from(LoadError::EmptyRom).code(), -2);
+
+        assert_eq!(EmuError::EmptySlot.to_string(), "slot is empty");
+        assert_eq!(EmuError::ExtensionOverlap.code(), -51);
+        assert_ne!(EmuError::HeatmapDisabled.code(), -1, "-1 is reserved for null pointers");
+
+        use std::error::Error;
+        assert!(err.source().unwrap().is::<TiFileError>());
+        let os = EmuError::Os(OsUpdateError::NotCeOs);
+        assert!(os.source().unwrap().is::<OsUpdateError>());
+        assert!(EmuError::NoRom.source().is_none());
+        assert_eq!(error_string(-105), "state is truncated or corrupt");
+        assert_eq!(error_string(3), "ok");
+        assert_eq!(error_string(-9999), "unknown error");
+    }
+}
@@ -0,0 +1,327 @@
+//! Homescreen expression evaluation
+//!
+//! `eval` turns the emulator into a math engine: it returns to the
+//! homescreen (QUIT, CLEAR), types the expression on the keypad, presses
+//! ENTER, and reads the result back from the `Ans` variable, found
+//! through the OS's variable allocation table (VAT) rather than a fixed
+//! RAM address.
+//!
+//! Before ENTER the value of an existing Ans is overwritten with a marker
+//! and `errNo` is cleared, so a result can't be mistaken for the previous
+//! one: the evaluation is done when the OS stores a new Ans or sets
+//! `errNo`. If neither happens within `EVAL_CYCLES` it timed out. On
+//! failure the previous Ans value is put back.
+//!
+//! Expressions use digits, `.`, `+ - * / ^`, parentheses and `~` for the
+//! (-) negation key. Whitespace is skipped.
+//!
+//! The VAT grows down from `SYM_TABLE`; the entries above `progPtr` are
+//! the variables with 3-byte token names (reals, lists, matrices, Ans).
+//! Each is 9 bytes, read downward from its first byte: type, type 2,
+//! version, data address (low, high, upper), then the name. TI reals are
+//! 9 bytes: sign/type, exponent biased by 0x80, 14 BCD mantissa digits.
+//!
+//! Errors (C API): -10 no ROM loaded, -30 character with no key, -90 no
+//! Ans variable, -91 Ans is not a real number, -92 the OS reported an
+//! error (ERR:SYNTAX, ...), -93 no result within the cycle budget.
+//!
+//! Addresses: ti84pceg.inc.
+
+use crate::key_name::KeyName;
+use crate::memory::addr;
+use crate::os_nav::{self, Key};
+use crate::Emu;
+
+/// symTable: top of the VAT
+pub const SYM_TABLE: u32 = 0xD3FFFF;
+/// progPtr: pointer to the first program entry, below the 3-byte names
+pub const PROG_PTR: u32 = 0xD0259D;
+
+/// Name of the Ans variable (tAns token)
+pub const ANS_NAME: [u8; 3] = [0x72, 0, 0];
+
+/// Object type of real numbers
+pub const REAL_OBJ: u8 = 0x00;
+
+/// errNo: error code of the last OS error, 0 if none
+pub const ERR_NO: u32 = 0xD008DF;
+
+/// Most cycles to wait after ENTER for the OS to evaluate and store Ans
+pub const EVAL_CYCLES: u32 = 20_000_000;
+/// Cycles between checks for a result
+const POLL_CYCLES: u32 = 500_000;
+
+/// Written over the old Ans value before ENTER. Not a valid real: its
+/// mantissa digits are not BCD.
+const STALE_MARKER: [u8; 9] = [0x00, 0x80, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+
+/// Why `eval` produced no number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvalError {
+    NoRom,
+    /// The expression has a character no key types
+    NoKey(char),
+    /// No Ans in the VAT (nothing evaluated, or the OS isn't running)
+    NoAns,
+    /// Ans holds something other than a real (complex, list, ...)
+    NotReal { obj_type: u8 },
+    /// The OS stopped with an error; `code` is its errNo (E_Syntax, ...)
+    Os { code: u8 },
+    /// No result or error within `EVAL_CYCLES`
+    Timeout,
+}
+
+impl EvalError {
+    /// C ABI error code (see emu.h)
+    pub fn code(&self) -> i32 {
+        match self {
+            EvalError::NoRom => -10,
+            EvalError::NoKey(_) => -30,
+            EvalError::NoAns => -90,
+            EvalError::NotReal { .. } => -91,
+            EvalError::Os { .. } => -92,
+            EvalError::Timeout => -93,
+        }
+    }
+}
+
+impl std::fmt::Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvalError::NoRom => write!(f, "no ROM loaded"),
+            EvalError::NoKey(c) => write!(f, "no key types {:?}", c),
+            EvalError::NoAns => write!(f, "no Ans variable"),
+            EvalError::NotReal { obj_type } => write!(f, "Ans is not a real number (type {:02X})", obj_type),
+            EvalError::Os { code } => write!(f, "OS error {:02X}", code),
+            EvalError::Timeout => write!(f, "no result within {} cycles", EVAL_CYCLES),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+/// Keypad position of an expression character
+fn key_for(c: char) -> Option<Key> {
+    use KeyName::*;
+    let key = match c {
+        '0' => Num0,
+        '1' => Num1,
+        '2' => Num2,
+        '3' => Num3,
+        '4' => Num4,
+        '5' => Num5,
+        '6' => Num6,
+        '7' => Num7,
+        '8' => Num8,
+        '9' => Num9,
+        '.' => Dot,
+        '~' => Neg,
+        '(' => LParen,
+        ')' => RParen,
+        '+' => Add,
+        '-' => Sub,
+        '*' => Mul,
+        '/' => Div,
+        '^' => Power,
+        _ => return None,
+    };
+    Some(key.position())
+}
+
+/// Keys that type `expr`
+pub fn keys_for(expr: &str) -> Result<Vec<Key>, EvalError> {
+    expr.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| key_for(c).ok_or(EvalError::NoKey(c)))
+        .collect()
+}
+
+/// Decode a 9-byte TI real
+pub fn decode_real(bytes: &[u8; 9]) -> f64 {
+    let mut mantissa = 0.0;
+    for &byte in &bytes[2..] {
+        mantissa = mantissa * 100.0 + ((byte >> 4) * 10 + (byte & 0x0F)) as f64;
+    }
+    // 14 digits, the first before the decimal point. Scale by an exact
+    // power of ten so integers and short decimals come out exact.
+    let shift = bytes[1] as i32 - 0x80 - 13;
+    let value = if shift >= 0 { mantissa * 10f64.powi(shift) } else { mantissa / 10f64.powi(-shift) };
+    if bytes[0] & 0x80 != 0 { -value } else { value }
+}
+
+/// VAT entry of a variable with a 3-byte name: (object type, data address)
+pub fn find_var(ram: &[u8], name: [u8; 3]) -> Option<(u8, u32)> {
+    let byte = |address: u32| ram.get(address.checked_sub(addr::RAM_START)? as usize).copied();
+    let word = |address: u32| Some(byte(address)? as u32 | (byte(address + 1)? as u32) << 8 | (byte(address + 2)? as u32) << 16);
+    let end = word(PROG_PTR)?;
+
+    let mut entry = SYM_TABLE;
+    while entry > end && entry >= addr::RAM_START + 9 {
+        let data = byte(entry - 3)? as u32 | (byte(entry - 4)? as u32) << 8 | (byte(entry - 5)? as u32) << 16;
+        if [byte(entry - 6)?, byte(entry - 7)?, byte(entry - 8)?] == name {
+            return Some((byte(entry)? & 0x1F, data));
+        }
+        entry -= 9;
+    }
+    None
+}
+
+/// Current value of Ans
+pub fn read_ans(ram: &[u8]) -> Result<f64, EvalError> {
+    let (obj_type, data) = find_var(ram, ANS_NAME).ok_or(EvalError::NoAns)?;
+    if obj_type != REAL_OBJ {
+        return Err(EvalError::NotReal { obj_type });
+    }
+    let start = data.checked_sub(addr::RAM_START).ok_or(EvalError::NoAns)? as usize;
+    let bytes = ram.get(start..start + 9).ok_or(EvalError::NoAns)?;
+    Ok(decode_real(bytes.try_into().unwrap()))
+}
+
+/// Type `expr` on the homescreen of a running OS, press ENTER and return
+/// the result
+pub fn eval(emu: &mut Emu, expr: &str) -> Result<f64, EvalError> {
+    if emu.rom_hash().is_none() {
+        return Err(EvalError::NoRom);
+    }
+    let mut keys = os_nav::QUIT.to_vec();
+    keys.push(os_nav::key::CLEAR);
+    keys.extend(keys_for(expr)?);
+    os_nav::press(emu, &keys);
+
+    // Mark the old value so it can't be read back as the result
+    let previous = find_var(emu.ram_data(), ANS_NAME).map(|(_, data)| {
+        let mut bytes = [0u8; 9];
+        let _ = emu.read_block(data, &mut bytes, false);
+        let _ = emu.write_block(data, &STALE_MARKER, false);
+        (data, bytes)
+    });
+    emu.poke_byte(ERR_NO, 0);
+
+    let (row, col) = os_nav::key::ENTER;
+    emu.set_key(row, col, true);
+    emu.run_cycles(os_nav::HOLD_CYCLES);
+    emu.set_key(row, col, false);
+
+    let mut waited = 0;
+    let result = loop {
+        if let Some(result) = outcome(emu.ram_data(), previous.map(|(data, _)| data)) {
+            break result;
+        }
+        if waited >= EVAL_CYCLES {
+            break Err(EvalError::Timeout);
+        }
+        emu.run_cycles(POLL_CYCLES);
+        waited += POLL_CYCLES;
+    };
+    if let (Err(_), Some((data, bytes))) = (result, previous) {
+        if find_var(emu.ram_data(), ANS_NAME).map(|(_, at)| at) == Some(data) {
+            let _ = emu.write_block(data, &bytes, false);
+        }
+    }
+    result
+}
+
+/// Result of an evaluation started with Ans's value at `marked` replaced
+/// by the marker, or None while the OS is still working
+fn outcome(ram: &[u8], marked: Option<u32>) -> Option<Result<f64, EvalError>> {
+    let byte = |address: u32| ram.get(address.checked_sub(addr::RAM_START)? as usize).copied();
+    match byte(ERR_NO) {
+        Some(0) | None => {}
+        Some(code) => return Some(Err(EvalError::Os { code })),
+    }
+    let (_, data) = find_var(ram, ANS_NAME)?;
+    let stale = marked == Some(data)
+        && (0..9).all(|i| byte(data + i as u32) == Some(STALE_MARKER[i]));
+    if stale {
+        None
+    } else {
+        Some(read_ans(ram))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ram_at(ram: &mut [u8], address: u32) -> &mut [u8] {
+        &mut ram[(address - addr::RAM_START) as usize..]
+    }
+
+    #[test]
+    fn test_decode_real() {
+        assert_eq!(decode_real(&[0x00, 0x81, 0x13, 0, 0, 0, 0, 0, 0]), 13.0);
+        assert_eq!(decode_real(&[0x80, 0x80, 0x25, 0, 0, 0, 0, 0, 0]), -2.5);
+        assert_eq!(decode_real(&[0x00, 0x7F, 0x50, 0, 0, 0, 0, 0, 0]), 0.5);
+        assert_eq!(decode_real(&[0x00, 0x83, 0x98, 0x01, 0, 0, 0, 0, 0]), 9801.0);
+        assert_eq!(decode_real(&[0x00, 0x80, 0, 0, 0, 0, 0, 0, 0]), 0.0);
+    }
+
+    #[test]
+    fn test_read_ans_from_vat() {
+        let mut ram = vec![0u8; addr::RAM_SIZE];
+        // Two entries: A then Ans; progPtr just below them
+        let ans_data = 0xD1A000u32;
+        ram_at(&mut ram, PROG_PTR)[..3].copy_from_slice(&(SYM_TABLE - 18).to_le_bytes()[..3]);
+        let top = (SYM_TABLE - 8 - addr::RAM_START) as usize;
+        ram[top..top + 9].copy_from_slice(&[0, 0, b'A', 0xD1, 0xB0, 0x00, 0, 0, REAL_OBJ]);
+        let ans = top - 9;
+        let data = ans_data.to_le_bytes();
+        ram[ans..ans + 9].copy_from_slice(&[0, 0, 0x72, data[2], data[1], data[0], 0, 0, REAL_OBJ]);
+        ram_at(&mut ram, ans_data)[..9].copy_from_slice(&[0x00, 0x81, 0x13, 0, 0, 0, 0, 0, 0]);
+
+        assert_eq!(find_var(&ram, [b'A', 0, 0]), Some((REAL_OBJ, 0xD1B000)));
+        assert_eq!(read_ans(&ram), Ok(13.0));
+
+        ram[ans + 8] = 0x0C;
+        assert_eq!(read_ans(&ram), Err(EvalError::NotReal { obj_type: 0x0C }));
+        // Entries below progPtr are programs, not searched
+        ram_at(&mut ram, PROG_PTR)[..3].copy_from_slice(&(SYM_TABLE - 9).to_le_bytes()[..3]);
+        assert_eq!(read_ans(&ram), Err(EvalError::NoAns));
+    }
+
+    #[test]
+    fn test_keys_for() {
+        assert_eq!(keys_for("6 + 7"), Ok(vec![(5, 2), (6, 1), (3, 3)]));
+        assert_eq!(keys_for("~2^(1.5)").unwrap().len(), 8);
+        assert_eq!(keys_for("sin(1)"), Err(EvalError::NoKey('s')));
+        // Each character types the key of the same name
+        for c in "0123456789.()+-*/^".chars() {
+            assert_eq!(key_for(c), KeyName::from_name(&c.to_string()).map(KeyName::position), "{c:?}");
+        }
+        assert_eq!(key_for('~'), Some(KeyName::Neg.position()));
+        assert_eq!(eval(&mut Emu::new(), "1"), Err(EvalError::NoRom));
+    }
+
+    /// Emulator running `code` in place of an OS, with Ans = 13 in the VAT
+    fn emu_with_ans(code: &[u8]) -> Emu {
+        let mut emu = Emu::new();
+        emu.load_rom(code).unwrap();
+        emu.power_on();
+        emu.write_block(PROG_PTR, &(SYM_TABLE - 9).to_le_bytes()[..3], false).unwrap();
+        emu.write_block(SYM_TABLE - 8, &[0, 0, 0x72, 0xD1, 0xA0, 0x00, 0, 0, REAL_OBJ], false).unwrap();
+        emu.write_block(0xD1A000, &[0x00, 0x81, 0x13, 0, 0, 0, 0, 0, 0], false).unwrap();
+        emu
+    }
+
+    #[test]
+    fn test_stale_ans_is_not_a_result() {
+        // DI ; JR $: keys go nowhere, Ans is never rewritten
+        let mut emu = emu_with_ans(&[0xF3, 0x18, 0xFE]);
+        assert_eq!(eval(&mut emu, "6*7"), Err(EvalError::Timeout));
+        assert_eq!(read_ans(emu.ram_data()), Ok(13.0), "previous Ans put back");
+    }
+
+    #[test]
+    fn test_os_error() {
+        // DI, then loop: LD A,88h ; LD.LIL (errNo),A ; JR loop, like ERR:SYNTAX
+        let mut emu = emu_with_ans(&[0xF3, 0x3E, 0x88, 0x5B, 0x32, 0xDF, 0x08, 0xD0, 0x18, 0xF7]);
+        assert_eq!(eval(&mut emu, "1+"), Err(EvalError::Os { code: 0x88 }));
+        assert_eq!(read_ans(emu.ram_data()), Ok(13.0));
+
+        let mut ram = emu.ram_data().to_vec();
+        ram[(ERR_NO - addr::RAM_START) as usize] = 0;
+        assert_eq!(outcome(&ram, Some(0xD1A000)), Some(Ok(13.0)), "Ans at the marked address, new value");
+        ram[0x1A000..0x1A009].copy_from_slice(&STALE_MARKER);
+        assert_eq!(outcome(&ram, Some(0xD1A000)), None, "still the marker");
+    }
+}
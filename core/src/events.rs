@@ -0,0 +1,208 @@
+//! Emulator events delivered to frontends
+//!
+//! Events carry the emulated cycle count at which they happened so a
+//! frontend can, for example, start a fade exactly when the OS turned the
+//! LCD off instead of polling `emu_is_lcd_on` every frame.
+//!
+//! Every event goes to the registered callback (if any) and into a bounded
+//! queue drained with `emu_poll_event`, for frontends that prefer polling
+//! over calling back across the FFI boundary.
+//!
+//! A separate frame callback fires at each LCD vertical sync, so a frontend
+//! can render when a frame is ready rather than on every host vsync. Its
+//! dirty flag is set when the CPU or debugger wrote RAM in the frame at
+//! UPBASE, or UPBASE, the LCD control register or the palette changed,
+//! since the previous frame.
+
+use std::collections::VecDeque;
+use std::os::raw::c_void;
+
+/// Events kept for polling; the oldest are dropped once full
+pub const MAX_QUEUED_EVENTS: usize = 256;
+
+/// Event type codes (stable C ABI values, see emu.h)
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// LCD started showing content (control port flag and LCD power both on)
+    LcdOn = 1,
+    /// LCD stopped showing content
+    LcdOff = 2,
+    /// Backlight rose above the off threshold; `value` is the brightness
+    BacklightOn = 3,
+    /// Backlight dropped below the off threshold; `value` is the brightness
+    BacklightOff = 4,
+    /// The LCD started a new frame (vertical sync); `value` is 1 if UPBASE
+    /// points at a full frame of RAM, 0 if rendering it would draw the
+    /// diagnostic pattern
+    FrameDone = 5,
+    /// Execution stopped at a breakpoint; `value` is the PC
+    BreakpointHit = 6,
+    /// A file transfer finished; `value` is the number of variables sent
+    TransferComplete = 7,
+    /// Memory protection violation raised an NMI; `value` is the PC
+    Trap = 8,
+    /// Peripheral registers changed during the frame that just ended;
+    /// `value` is how many (report read with `emu_mmio_diff`)
+    MmioDiff = 9,
+    /// A watched interrupt source changed status and execution stopped;
+    /// `value` is the source bit, plus 0x100 if it was raised or 0x200 if
+    /// it was acknowledged
+    InterruptWatch = 10,
+    /// A benchmark measurement completed; `value` is its emulated duration
+    /// in microseconds (saturating, exact results from
+    /// `emu_benchmark_results`)
+    BenchmarkDone = 11,
+    /// A data watchpoint stopped execution; `value` is the accessed
+    /// address (details from `emu_last_watchpoint`)
+    Watchpoint = 12,
+    /// A stopping port watch filter matched; `value` is the MMIO address
+    /// or IN/OUT port (details from `emu_last_port_watch`)
+    PortWatch = 13,
+    /// The OS went the stall threshold without restarting the watchdog
+    /// (see `crate::watchdog_monitor`); `value` is the emulated ms since
+    /// the last restart
+    WatchdogStall = 14,
+}
+
+/// One event as passed across the C ABI
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmuEvent {
+    /// `EventKind` code
+    pub kind: u32,
+    /// Kind-specific payload (0 when unused)
+    pub value: u32,
+    /// Emulated CPU cycle count when the event happened (total_cycles)
+    pub cycles: u64,
+}
+
+impl EmuEvent {
+    pub fn new(kind: EventKind, value: u32, cycles: u64) -> Self {
+        Self { kind: kind as u32, value, cycles }
+    }
+}
+
+/// Event callback: receives the user pointer given at registration.
+/// Called on the emulation thread with the emulator locked, so it must not
+/// call back into the emulator.
+pub type EventCallback = extern "C" fn(user: *mut c_void, event: *const EmuEvent);
+
+/// Registered callback plus its user pointer (stored as usize to stay Send)
+#[derive(Clone, Copy)]
+pub(crate) struct EventSink {
+    callback: EventCallback,
+    user: usize,
+}
+
+impl EventSink {
+    pub(crate) fn new(callback: EventCallback, user: *mut c_void) -> Self {
+        Self { callback, user: user as usize }
+    }
+
+    pub(crate) fn deliver(&self, event: &EmuEvent) {
+        (self.callback)(self.user as *mut c_void, event);
+    }
+}
+
+/// Frame callback: `frame` is the LCD frame number (`FrameCounters::generated`)
+/// and `dirty` is 1 if the frame can differ from the previous one. Called
+/// with the emulator locked, so it must not call back into the emulator;
+/// fetch the framebuffer after the run returns.
+pub type FrameCallback = extern "C" fn(user: *mut c_void, frame: u64, dirty: u32);
+
+/// Registered frame callback, its user pointer (stored as usize to stay
+/// Send) and the LCD register hash at the last frame
+#[derive(Clone, Copy)]
+pub(crate) struct FrameSink {
+    callback: FrameCallback,
+    user: usize,
+    last_hash: Option<u64>,
+}
+
+impl FrameSink {
+    pub(crate) fn new(callback: FrameCallback, user: *mut c_void) -> Self {
+        Self { callback, user: user as usize, last_hash: None }
+    }
+
+    /// Report the next frame as dirty (its RAM was replaced wholesale)
+    pub(crate) fn invalidate(&mut self) {
+        self.last_hash = None;
+    }
+
+    /// Report frame `frame`, given the LCD register hash and whether its
+    /// RAM was written since the previous frame
+    pub(crate) fn deliver(&mut self, frame: u64, register_hash: u64, ram_written: bool) {
+        let registers_changed = self.last_hash.replace(register_hash) != Some(register_hash);
+        let dirty = registers_changed || ram_written;
+        (self.callback)(self.user as *mut c_void, frame, dirty as u32);
+    }
+}
+
+/// Bounded FIFO of events waiting to be polled
+#[derive(Debug, Default)]
+pub(crate) struct EventQueue {
+    events: VecDeque<EmuEvent>,
+    dropped: u64,
+}
+
+impl EventQueue {
+    pub(crate) fn push(&mut self, event: EmuEvent) {
+        if self.events.len() == MAX_QUEUED_EVENTS {
+            self.events.pop_front();
+            self.dropped += 1;
+        }
+        self.events.push_back(event);
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<EmuEvent> {
+        self.events.pop_front()
+    }
+
+    /// Events discarded because nobody polled
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+/// Last observed display state, compared after each instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct DisplayState {
+    pub lcd_on: bool,
+    pub backlight_on: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn collect_frame(user: *mut c_void, frame: u64, dirty: u32) {
+        unsafe { &mut *(user as *mut Vec<(u64, u32)>) }.push((frame, dirty));
+    }
+
+    #[test]
+    fn test_frame_sink_dirty_flag() {
+        let mut frames: Vec<(u64, u32)> = Vec::new();
+        let mut sink = FrameSink::new(collect_frame, &mut frames as *mut _ as *mut c_void);
+        sink.deliver(1, 0xAA, false);
+        sink.deliver(2, 0xAA, false);
+        sink.deliver(3, 0xAA, true);
+        sink.deliver(4, 0xBB, false);
+        assert_eq!(frames, [(1, 1), (2, 0), (3, 1), (4, 1)]);
+    }
+
+    #[test]
+    fn test_queue_drops_oldest_when_full() {
+        let mut queue = EventQueue::default();
+        for i in 0..MAX_QUEUED_EVENTS as u64 + 2 {
+            queue.push(EmuEvent::new(EventKind::FrameDone, 1, i));
+        }
+        assert_eq!(queue.dropped(), 2);
+        assert_eq!(queue.pop().map(|e| e.cycles), Some(2));
+        let mut rest = 1;
+        while queue.pop().is_some() {
+            rest += 1;
+        }
+        assert_eq!(rest, MAX_QUEUED_EVENTS);
+    }
+}
@@ -0,0 +1,57 @@
+//! Core-owned strings returned over the C API
+//!
+//! Functions whose text has no useful size bound (disassembly listings,
+//! reports) return a NUL-terminated copy allocated here instead of asking
+//! the caller to guess a buffer size. The caller owns it until it hands it
+//! back with `emu_string_free`. The pool records every live string, so
+//! freeing NULL, a pointer it never handed out or one already freed is
+//! ignored instead of corrupting the heap. Strings are independent of any
+//! `Emu` and stay valid after `emu_destroy`.
+//!
+//! Static strings (`emu_error_string`, the backend names) are not from the
+//! pool and must not be freed.
+
+use std::collections::BTreeSet;
+use std::ffi::{c_char, CString};
+use std::sync::Mutex;
+
+/// Addresses of the strings handed out and not yet freed
+static POOL: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
+
+/// Hand `text` to C. NUL bytes inside it are dropped, as C would stop
+/// reading there.
+pub(crate) fn into_raw(text: impl Into<Vec<u8>>) -> *mut c_char {
+    let mut bytes = text.into();
+    bytes.retain(|&b| b != 0);
+    let raw = CString::new(bytes).expect("NUL bytes removed").into_raw();
+    POOL.lock().unwrap().insert(raw as usize);
+    raw
+}
+
+/// Take back a string from `into_raw`. Returns false, doing nothing, for
+/// NULL or a pointer that isn't a live pool string.
+pub(crate) fn free(ptr: *mut c_char) -> bool {
+    if ptr.is_null() || !POOL.lock().unwrap().remove(&(ptr as usize)) {
+        return false;
+    }
+    drop(unsafe { CString::from_raw(ptr) });
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn test_round_trip_and_bad_frees() {
+        let text = into_raw("LD A,5\0\nRET");
+        assert_eq!(unsafe { CStr::from_ptr(text) }.to_str(), Ok("LD A,5\nRET"));
+
+        let mut foreign = *b"not ours\0";
+        assert!(!free(foreign.as_mut_ptr() as *mut c_char));
+        assert!(!free(std::ptr::null_mut()));
+        assert!(free(text));
+        assert!(!free(text), "double free ignored");
+    }
+}
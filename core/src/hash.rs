@@ -0,0 +1,49 @@
+//! FNV-1a (64-bit), the hash behind ROM ids, frame hashes and state digests
+//!
+//! Input is a sequence of values, each XORed in whole before the multiply:
+//! bytes for small buffers, pixels or registers as they are, and large
+//! buffers as 8-byte words (`words`) so hashing 4MB of flash stays cheap.
+//! Hashes are stored in save states and test fixtures, so the word packing
+//! must not change.
+
+const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// FNV-1a of `values`
+pub fn fnv1a(values: impl IntoIterator<Item = u64>) -> u64 {
+    values.into_iter().fold(OFFSET, |hash, value| (hash ^ value).wrapping_mul(PRIME))
+}
+
+/// `bytes` as little-endian 8-byte words, the last one padded with 0xFF
+pub fn words(bytes: &[u8]) -> impl Iterator<Item = u64> + '_ {
+    bytes.chunks(8).map(|word| {
+        let mut padded = [0xFF; 8];
+        padded[..word.len()].copy_from_slice(word);
+        u64::from_le_bytes(padded)
+    })
+}
+
+/// `bytes` one value per byte
+pub fn bytes(bytes: &[u8]) -> impl Iterator<Item = u64> + '_ {
+    bytes.iter().map(|&byte| byte as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_values() {
+        // Reference FNV-1a 64 values
+        assert_eq!(fnv1a(bytes(b"")), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(bytes(b"a")), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(bytes(b"foobar")), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn test_words() {
+        let packed: Vec<u64> = words(&[1, 2, 3, 4, 5, 6, 7, 8, 9]).collect();
+        assert_eq!(packed, vec![0x0807_0605_0403_0201, 0xFFFF_FFFF_FFFF_FF09]);
+        assert_ne!(fnv1a(words(&[0; 16])), fnv1a(words(&[0; 8])));
+    }
+}
@@ -0,0 +1,117 @@
+//! Execution heatmap
+//!
+//! Counts executed instructions per 64-byte bucket of flash and RAM so a
+//! frontend can render where time goes during boot or inside a program.
+//!
+//! # Export format
+//!
+//! Little-endian u32 counts, flash buckets first then RAM buckets:
+//!
+//! | Offset (buckets)      | Region                         |
+//! |-----------------------|--------------------------------|
+//! | 0 .. 65536            | Flash 0x000000 - 0x3FFFFF      |
+//! | 65536 .. 65536 + 6496 | RAM   0xD00000 - 0xD657FF      |
+//!
+//! Bucket `n` of a region covers `region_start + n * 64`. Instructions
+//! executed elsewhere (unmapped/MMIO) are not counted. Counts saturate.
+
+use crate::memory::addr;
+
+/// Bytes of address space per bucket
+pub const BUCKET_SIZE: u32 = 64;
+/// Number of flash buckets at the start of the export
+pub const FLASH_BUCKETS: usize = addr::FLASH_SIZE / BUCKET_SIZE as usize;
+/// Number of RAM buckets following the flash buckets
+pub const RAM_BUCKETS: usize = addr::RAM_SIZE / BUCKET_SIZE as usize;
+/// Size of the exported buffer in bytes
+pub const EXPORT_SIZE: usize = (FLASH_BUCKETS + RAM_BUCKETS) * 4;
+
+/// Per-bucket instruction counters
+pub struct ExecHeatmap {
+    buckets: Vec<u32>,
+}
+
+impl ExecHeatmap {
+    pub fn new() -> Self {
+        Self {
+            buckets: vec![0; FLASH_BUCKETS + RAM_BUCKETS],
+        }
+    }
+
+    /// Map an address to its bucket index, if it is in flash or RAM
+    fn bucket(addr: u32) -> Option<usize> {
+        if addr < addr::FLASH_END {
+            Some((addr / BUCKET_SIZE) as usize)
+        } else if (addr::RAM_START..addr::RAM_END).contains(&addr) {
+            Some(FLASH_BUCKETS + ((addr - addr::RAM_START) / BUCKET_SIZE) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Count one instruction executed at `addr` (masked 24-bit address)
+    #[inline]
+    pub fn record(&mut self, addr: u32) {
+        if let Some(i) = Self::bucket(addr) {
+            self.buckets[i] = self.buckets[i].saturating_add(1);
+        }
+    }
+
+    /// Count for the bucket containing `addr` (0 outside flash/RAM)
+    pub fn count(&self, addr: u32) -> u32 {
+        Self::bucket(addr).map_or(0, |i| self.buckets[i])
+    }
+
+    /// Zero every bucket
+    pub fn clear(&mut self) {
+        self.buckets.fill(0);
+    }
+
+    /// Write the export format into `out`, which must hold EXPORT_SIZE bytes.
+    /// Returns the number of bytes written, or None if `out` is too small.
+    pub fn export(&self, out: &mut [u8]) -> Option<usize> {
+        if out.len() < EXPORT_SIZE {
+            return None;
+        }
+        for (chunk, count) in out.chunks_exact_mut(4).zip(&self.buckets) {
+            chunk.copy_from_slice(&count.to_le_bytes());
+        }
+        Some(EXPORT_SIZE)
+    }
+}
+
+impl Default for ExecHeatmap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_layout() {
+        let mut map = ExecHeatmap::new();
+        map.record(0x000000);
+        map.record(0x00003F);
+        map.record(0x000040);
+        map.record(0xD00041);
+        map.record(0xE30000); // MMIO is ignored
+        assert_eq!(map.count(0x000010), 2);
+        assert_eq!(map.count(0x000040), 1);
+        assert_eq!(map.count(0xD00040), 1);
+        assert_eq!(map.count(0xE30000), 0);
+
+        let mut out = vec![0u8; EXPORT_SIZE];
+        assert_eq!(map.export(&mut out), Some(EXPORT_SIZE));
+        assert_eq!(&out[0..4], &2u32.to_le_bytes());
+        assert_eq!(&out[4..8], &1u32.to_le_bytes());
+        let ram1 = (FLASH_BUCKETS + 1) * 4;
+        assert_eq!(&out[ram1..ram1 + 4], &1u32.to_le_bytes());
+        assert_eq!(map.export(&mut out[..8]), None);
+
+        map.clear();
+        assert_eq!(map.count(0), 0);
+    }
+}
@@ -0,0 +1,192 @@
+//! Host bridge "magic port" for self-reporting programs
+//!
+//! When enabled (it is off by default), a small register block at
+//! `BRIDGE_BASE` lets emulated programs talk to the host: print text, read
+//! input the host queued, and report a test result. It sits in the unmapped
+//! MMIO window, clear of the CE toolchain debug ports (0xFB0000-0xFD0000),
+//! and is attached as an extension device so real hardware behavior is
+//! untouched while it is disabled.
+//!
+//! | Offset | Access | Meaning                                          |
+//! |--------|--------|--------------------------------------------------|
+//! | 0x00   | W      | Output byte                                      |
+//! | 0x01   | R      | Next input byte (0 when none is pending)         |
+//! | 0x02   | R      | Input bytes pending (saturates at 255)           |
+//! | 0x03   | R      | `BRIDGE_MAGIC`, so programs can detect the bridge |
+//! | 0x04   | W      | Test result: 0 = pass, anything else = fail code |
+//! | 0x05   | W      | Write `RESET_KEY` to request a software reset     |
+//!
+//! Output is buffered up to `MAX_OUTPUT_BYTES`; past that the oldest bytes
+//! are dropped and counted (`output_dropped`).
+//!
+//! Errors: -60 bridge not enabled, -61 no result reported yet.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::peripherals::device::{Peripheral, PortContext};
+
+/// CPU address of the register block
+pub const BRIDGE_BASE: u32 = 0xFE0000;
+
+/// Size of the register block
+pub const BRIDGE_LEN: u32 = 0x10;
+
+/// Value read at offset 0x03 (unmapped MMIO otherwise reads as noise)
+pub const BRIDGE_MAGIC: u8 = 0xB7;
+
+/// Output bytes kept for the host; once full the oldest are dropped and
+/// counted, so a program printing with nobody reading can't grow memory
+pub const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// Value to write at offset 0x05 to restart the calculator keeping RAM
+/// (`ResetCause::Software`); other values are ignored
+pub const RESET_KEY: u8 = 0x5A;
+
+mod regs {
+    pub const OUTPUT: u32 = 0x00;
+    pub const INPUT: u32 = 0x01;
+    pub const INPUT_PENDING: u32 = 0x02;
+    pub const MAGIC: u32 = 0x03;
+    pub const RESULT: u32 = 0x04;
+    pub const RESET: u32 = 0x05;
+}
+
+/// State shared between the bus-side device and the emulator API
+#[derive(Debug, Default)]
+pub struct BridgeState {
+    /// Bytes written by the program, not yet taken by the host
+    output: VecDeque<u8>,
+    /// Output bytes discarded because the host didn't take them in time
+    output_dropped: u64,
+    /// Bytes queued by the host, not yet read by the program
+    input: VecDeque<u8>,
+    /// Last reported test result
+    result: Option<u8>,
+}
+
+impl BridgeState {
+    /// Queue bytes for the program to read
+    pub fn push_input(&mut self, data: &[u8]) {
+        self.input.extend(data);
+    }
+
+    /// Take up to `max` of the oldest bytes the program has written
+    pub fn take_output(&mut self, max: usize) -> Vec<u8> {
+        let n = max.min(self.output.len());
+        self.output.drain(..n).collect()
+    }
+
+    /// Output bytes discarded from the full buffer since the bridge was
+    /// enabled
+    pub fn output_dropped(&self) -> u64 {
+        self.output_dropped
+    }
+
+    fn push_output(&mut self, value: u8) {
+        if self.output.len() == MAX_OUTPUT_BYTES {
+            self.output.pop_front();
+            self.output_dropped += 1;
+        }
+        self.output.push_back(value);
+    }
+
+    /// Test result reported by the program (0 = pass)
+    pub fn result(&self) -> Option<u8> {
+        self.result
+    }
+}
+
+/// Bus-side device; the emulator keeps the other handles to the state and
+/// the reset request flag
+pub struct HostBridge(pub Arc<Mutex<BridgeState>>, pub Arc<AtomicBool>);
+
+impl Peripheral for HostBridge {
+    fn read(&mut self, offset: u32, _ctx: &PortContext) -> u8 {
+        let mut state = self.0.lock().unwrap();
+        match offset {
+            regs::INPUT => state.input.pop_front().unwrap_or(0),
+            regs::INPUT_PENDING => state.input.len().min(255) as u8,
+            regs::MAGIC => BRIDGE_MAGIC,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, offset: u32, value: u8, _ctx: &PortContext) {
+        let mut state = self.0.lock().unwrap();
+        match offset {
+            regs::OUTPUT => state.push_output(value),
+            regs::RESULT => {
+                crate::emu::log_evt!("HOST_BRIDGE: test result {}", value);
+                state.result = Some(value);
+            }
+            regs::RESET if value == RESET_KEY => {
+                crate::emu::log_evt!("HOST_BRIDGE: software reset");
+                self.1.store(true, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+
+    /// A reset starts a new run: the old result no longer applies, but host
+    /// data (unread output, queued input) is kept
+    fn reset(&mut self) {
+        self.0.lock().unwrap().result = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peripherals::{KEYPAD_COLS, KEYPAD_ROWS};
+
+    #[test]
+    fn test_registers() {
+        let keys = [[false; KEYPAD_COLS]; KEYPAD_ROWS];
+        let ctx = PortContext { key_state: &keys, cycles: 0, cpu_speed: 0, delay_remaining: 0 };
+        let state = Arc::new(Mutex::new(BridgeState::default()));
+        let reset = Arc::new(AtomicBool::new(false));
+        let mut bridge = HostBridge(state.clone(), reset.clone());
+
+        assert_eq!(bridge.read(regs::MAGIC, &ctx), BRIDGE_MAGIC);
+        state.lock().unwrap().push_input(b"ok");
+        assert_eq!(bridge.read(regs::INPUT_PENDING, &ctx), 2);
+        assert_eq!(bridge.read(regs::INPUT, &ctx), b'o');
+
+        for &byte in b"hi\n" {
+            bridge.write(regs::OUTPUT, byte, &ctx);
+        }
+        bridge.write(regs::RESULT, 3, &ctx);
+        assert_eq!(state.lock().unwrap().take_output(2), b"hi");
+        assert_eq!(state.lock().unwrap().take_output(usize::MAX), b"\n");
+        assert_eq!(state.lock().unwrap().result(), Some(3));
+
+        bridge.write(regs::RESET, 0x01, &ctx);
+        assert!(!reset.load(Ordering::Relaxed));
+        bridge.write(regs::RESET, RESET_KEY, &ctx);
+        assert!(reset.load(Ordering::Relaxed));
+
+        bridge.reset();
+        assert_eq!(state.lock().unwrap().result(), None);
+        assert_eq!(bridge.read(regs::INPUT, &ctx), b'k');
+        assert_eq!(bridge.read(regs::INPUT, &ctx), 0);
+    }
+
+    #[test]
+    fn test_output_keeps_newest_bytes() {
+        let keys = [[false; KEYPAD_COLS]; KEYPAD_ROWS];
+        let ctx = PortContext { key_state: &keys, cycles: 0, cpu_speed: 0, delay_remaining: 0 };
+        let state = Arc::new(Mutex::new(BridgeState::default()));
+        let mut bridge = HostBridge(state.clone(), Arc::new(AtomicBool::new(false)));
+
+        for i in 0..MAX_OUTPUT_BYTES + 3 {
+            bridge.write(regs::OUTPUT, i as u8, &ctx);
+        }
+        let mut state = state.lock().unwrap();
+        assert_eq!(state.output_dropped(), 3);
+        let output = state.take_output(usize::MAX);
+        assert_eq!(output.len(), MAX_OUTPUT_BYTES);
+        assert_eq!(output[0], 3);
+    }
+}
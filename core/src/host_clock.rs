@@ -0,0 +1,81 @@
+//! Pause-aware host wall clock
+//!
+//! The core never reads host time itself (WASM has no usable clock here), so
+//! frontends report it with `sample`. The clock accumulates only the time
+//! that passed while the emulator was running: samples taken while paused
+//! are ignored, and the first sample after a resume re-bases instead of
+//! counting the gap. Anything derived from host time reads `running_ms`, so
+//! backgrounding the app never makes emulated time leap forward.
+
+/// Host time seen while running, fed by frontend samples
+#[derive(Debug, Clone, Default)]
+pub struct HostClock {
+    /// Last host timestamp sampled while running (None = re-base next sample)
+    last_ms: Option<u64>,
+    /// Host milliseconds accumulated while running
+    running_ms: u64,
+    paused: bool,
+}
+
+impl HostClock {
+    /// Record the host time. Backward steps (clock changes) are ignored.
+    pub fn sample(&mut self, now_ms: u64) {
+        if self.paused {
+            return;
+        }
+        if let Some(last) = self.last_ms {
+            self.running_ms += now_ms.saturating_sub(last);
+        }
+        self.last_ms = Some(now_ms.max(self.last_ms.unwrap_or(0)));
+    }
+
+    /// Freeze: host time stops counting until `resume`
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.last_ms = None;
+    }
+
+    /// Unfreeze; the next sample becomes the new base
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.last_ms = None;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Host milliseconds that elapsed while running
+    pub fn running_ms(&self) -> u64 {
+        self.running_ms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paused_time_is_not_counted() {
+        let mut clock = HostClock::default();
+        clock.sample(1_000);
+        clock.sample(1_600);
+        assert_eq!(clock.running_ms(), 600);
+
+        // Backgrounded for an hour
+        clock.pause();
+        clock.sample(2_000);
+        clock.sample(3_601_600);
+        assert_eq!(clock.running_ms(), 600);
+
+        clock.resume();
+        clock.sample(3_602_000);
+        clock.sample(3_602_250);
+        assert_eq!(clock.running_ms(), 850);
+
+        // Host clock set backwards: no negative step, no jump when it recovers
+        clock.sample(10);
+        clock.sample(3_602_300);
+        assert_eq!(clock.running_ms(), 900);
+    }
+}
@@ -0,0 +1,344 @@
+//! Recorded input macros with expected frame hashes
+//!
+//! A macro replays a short session from power-on: key presses at fixed
+//! emulated cycle offsets, with the FNV-1a hash of the rendered frame
+//! checked at chosen points. Replaying it on a fresh boot of the same ROM
+//! must reproduce every frame, which makes macros for real programs a
+//! regression corpus (see program_corpus_test.rs).
+//!
+//! The text format has one step per line; `#` starts a comment:
+//!
+//! ```text
+//! rom 1a2b3c4d5e6f7a8b   # ROM the hashes were recorded with (optional)
+//! send DOOM.8xp          # inject a variable file, path relative to the macro
+//! power                  # Emu::power_on
+//! wait 48000000          # run this many cycles
+//! down 6 0               # Emu::set_key(row, col, true)
+//! up 6 0
+//! frame 9c4e0f1d2a3b4c5d # render, the frame must hash to this
+//! ```
+//!
+//! Macros are recorded from a live session with `Emu::start_macro_recording`:
+//! keys and power-on are noted with the cycle count they happened at, and
+//! `Emu::macro_checkpoint` adds a `frame` step for the current screen.
+//! `send` steps are added by hand (the emulator only sees the file bytes).
+
+use std::fmt;
+
+use crate::error::EmuError;
+use crate::hash::fnv1a;
+use crate::Emu;
+
+/// One macro step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// Inject a variable file (`send_file` before power-on, `send_file_live`
+    /// after); the name is resolved by the player's loader
+    Send(String),
+    /// Press and release ON (`Emu::power_on`)
+    PowerOn,
+    /// Run this many cycles
+    Wait(u64),
+    /// `Emu::set_key`
+    Key { row: usize, col: usize, down: bool },
+    /// Expected hash of the rendered frame (see `frame_hash`)
+    Frame(u64),
+}
+
+/// A parsed macro
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputMacro {
+    /// ROM the frame hashes belong to; players skip other ROMs
+    pub rom_hash: Option<u64>,
+    pub steps: Vec<Step>,
+}
+
+/// Why a macro could not be parsed or played
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MacroError {
+    /// Malformed line (1-based)
+    Parse { line: usize, message: String },
+    /// The loader had no data for a `send` step
+    MissingFile(String),
+    /// `send_file` rejected the file
+    Send { file: String, error: EmuError },
+}
+
+impl fmt::Display for MacroError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacroError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            MacroError::MissingFile(file) => write!(f, "{}: file not found", file),
+            MacroError::Send { file, error } => write!(f, "{}: send failed ({})", file, error),
+        }
+    }
+}
+
+impl std::error::Error for MacroError {}
+
+impl InputMacro {
+    pub fn parse(text: &str) -> Result<Self, MacroError> {
+        let mut parsed = InputMacro::default();
+        for (i, raw) in text.lines().enumerate() {
+            let line = raw.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: &str| MacroError::Parse { line: i + 1, message: message.to_string() };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let number = |index: usize| -> Result<u64, MacroError> {
+                words.get(index).and_then(|w| w.parse().ok()).ok_or_else(|| error("expected a number"))
+            };
+            let hash = || -> Result<u64, MacroError> {
+                words.get(1).and_then(|w| u64::from_str_radix(w, 16).ok()).ok_or_else(|| error("expected a hex hash"))
+            };
+
+            let step = match words[0] {
+                "rom" => {
+                    parsed.rom_hash = Some(hash()?);
+                    continue;
+                }
+                "send" => Step::Send(line["send".len()..].trim().to_string()),
+                "power" => Step::PowerOn,
+                "wait" => Step::Wait(number(1)?),
+                "down" | "up" => Step::Key {
+                    row: number(1)? as usize,
+                    col: number(2)? as usize,
+                    down: words[0] == "down",
+                },
+                "frame" => Step::Frame(hash()?),
+                other => return Err(error(&format!("unknown step `{}`", other))),
+            };
+            if step == Step::Send(String::new()) {
+                return Err(error("expected a file name"));
+            }
+            parsed.steps.push(step);
+        }
+        Ok(parsed)
+    }
+
+    /// Copy with the `frame` steps replaced by the hashes a playback saw
+    /// (re-recording after an intended change)
+    pub fn with_frames(&self, checks: &[FrameCheck]) -> Self {
+        let mut updated = self.clone();
+        for check in checks {
+            updated.steps[check.step] = Step::Frame(check.actual);
+        }
+        updated
+    }
+}
+
+impl fmt::Display for InputMacro {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(hash) = self.rom_hash {
+            writeln!(f, "rom {:016x}", hash)?;
+        }
+        for step in &self.steps {
+            match step {
+                Step::Send(file) => writeln!(f, "send {}", file)?,
+                Step::PowerOn => writeln!(f, "power")?,
+                Step::Wait(cycles) => writeln!(f, "wait {}", cycles)?,
+                Step::Key { row, col, down } => {
+                    writeln!(f, "{} {} {}", if *down { "down" } else { "up" }, row, col)?
+                }
+                Step::Frame(hash) => writeln!(f, "frame {:016x}", hash)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// FNV-1a of the screen, rendered from VRAM first
+pub fn frame_hash(emu: &mut Emu) -> u64 {
+    emu.render_frame();
+    fnv1a(emu.framebuffer_data().iter().map(|&pixel| pixel as u64))
+}
+
+/// Notes inputs with the cycle count they happened at
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    steps: Vec<Step>,
+    last_cycles: u64,
+}
+
+impl Recorder {
+    /// Start recording at `cycles` (Emu::total_cycles)
+    pub fn new(cycles: u64) -> Self {
+        Self { steps: Vec::new(), last_cycles: cycles }
+    }
+
+    fn advance(&mut self, cycles: u64) {
+        let elapsed = cycles.saturating_sub(self.last_cycles);
+        if elapsed > 0 {
+            self.steps.push(Step::Wait(elapsed));
+            self.last_cycles = cycles;
+        }
+    }
+
+    pub fn key(&mut self, cycles: u64, row: usize, col: usize, down: bool) {
+        self.advance(cycles);
+        self.steps.push(Step::Key { row, col, down });
+    }
+
+    pub fn power_on(&mut self, cycles: u64) {
+        self.advance(cycles);
+        self.steps.push(Step::PowerOn);
+    }
+
+    pub fn frame(&mut self, cycles: u64, hash: u64) {
+        self.advance(cycles);
+        self.steps.push(Step::Frame(hash));
+    }
+
+    pub fn finish(self, rom_hash: Option<u64>) -> InputMacro {
+        InputMacro { rom_hash, steps: self.steps }
+    }
+}
+
+/// Outcome of one `frame` step during playback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameCheck {
+    /// Index into `InputMacro::steps`
+    pub step: usize,
+    /// Emulated cycles since playback started
+    pub cycles: u64,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl FrameCheck {
+    pub fn matches(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+/// Play `input` on `emu` (ROM loaded, not yet powered on), fetching `send`
+/// files through `load`. Waits are cumulative, so a run that overshoots
+/// one wait by a few cycles is caught up by the next. Returns every frame
+/// check, matching or not.
+pub fn play(
+    emu: &mut Emu,
+    input: &InputMacro,
+    load: &mut dyn FnMut(&str) -> Option<Vec<u8>>,
+) -> Result<Vec<FrameCheck>, MacroError> {
+    play_with(emu, input, load, &mut |_, _| {})
+}
+
+/// `play`, calling `on_frame` at every `frame` step with the emulator
+/// stopped there (to capture more than the frame hash)
+pub fn play_with(
+    emu: &mut Emu,
+    input: &InputMacro,
+    load: &mut dyn FnMut(&str) -> Option<Vec<u8>>,
+    on_frame: &mut dyn FnMut(&mut Emu, &FrameCheck),
+) -> Result<Vec<FrameCheck>, MacroError> {
+    let start = emu.total_cycles();
+    let mut target = start;
+    let mut powered = false;
+    let mut checks = Vec::new();
+
+    for (index, step) in input.steps.iter().enumerate() {
+        match step {
+            Step::Send(file) => {
+                let data = load(file).ok_or_else(|| MacroError::MissingFile(file.clone()))?;
+                let sent = if powered { emu.send_file_live(&data) } else { emu.send_file(&data) };
+                sent.map_err(|error| MacroError::Send { file: file.clone(), error })?;
+            }
+            Step::PowerOn => {
+                emu.power_on();
+                powered = true;
+            }
+            Step::Wait(cycles) => {
+                target += cycles;
+                while emu.total_cycles() < target {
+                    let chunk = (target - emu.total_cycles()).min(u32::MAX as u64) as u32;
+                    if emu.run_cycles(chunk) == 0 {
+                        break; // Off, paused or stopped: nothing more will run
+                    }
+                }
+            }
+            Step::Key { row, col, down } => {
+                emu.set_key(*row, *col, *down);
+                powered |= *down && (*row, *col) == (2, 0);
+            }
+            Step::Frame(expected) => {
+                let check = FrameCheck {
+                    step: index,
+                    cycles: emu.total_cycles() - start,
+                    expected: *expected,
+                    actual: frame_hash(emu),
+                };
+                on_frame(emu, &check);
+                checks.push(check);
+            }
+        }
+    }
+    Ok(checks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rom;
+
+    #[test]
+    fn test_parse_and_format_round_trip() {
+        let text = "rom 00000000000000ff\n# boot\nsend GAME.8xp\npower\nwait 1000 # settle\ndown 6 0\nup 6 0\nframe 0123456789abcdef\n";
+        let parsed = InputMacro::parse(text).unwrap();
+        assert_eq!(parsed.rom_hash, Some(0xFF));
+        assert_eq!(parsed.steps.len(), 6);
+        assert_eq!(parsed.steps[0], Step::Send("GAME.8xp".into()));
+        assert_eq!(parsed.steps[3], Step::Key { row: 6, col: 0, down: true });
+        assert_eq!(InputMacro::parse(&parsed.to_string()).unwrap(), parsed);
+
+        let error = InputMacro::parse("power\nwait soon\n").unwrap_err();
+        assert_eq!(error, MacroError::Parse { line: 2, message: "expected a number".into() });
+        assert!(InputMacro::parse("jump 3\n").is_err());
+        assert!(InputMacro::parse("send\n").is_err());
+    }
+
+    #[test]
+    fn test_recorded_session_replays() {
+        let rom = test_rom::build();
+        let mut emu = Emu::new();
+        emu.load_rom(&rom).unwrap();
+        emu.start_macro_recording();
+        emu.power_on();
+        emu.run_cycles(300_000);
+        emu.set_key(3, 2, true);
+        emu.run_cycles(200_000);
+        emu.set_key(3, 2, false);
+        emu.run_cycles(100_000);
+        emu.macro_checkpoint().unwrap();
+        let recorded = emu.stop_macro_recording().unwrap();
+        assert_eq!(recorded.rom_hash, emu.rom_hash());
+        assert_eq!(recorded.steps[0], Step::PowerOn);
+
+        let mut replay = Emu::new();
+        replay.load_rom(&rom).unwrap();
+        let checks = play(&mut replay, &recorded, &mut |_| None).unwrap();
+        assert_eq!(checks.len(), 1);
+        assert!(checks[0].matches());
+        assert_eq!(replay.ram_data(), emu.ram_data());
+
+        // A changed expectation is reported, and re-recording takes the new hash
+        let mut stale = recorded.clone();
+        stale.steps[checks[0].step] = Step::Frame(0);
+        let mut replay = Emu::new();
+        replay.load_rom(&rom).unwrap();
+        let checks = play(&mut replay, &stale, &mut |_| None).unwrap();
+        assert!(!checks[0].matches());
+        assert_eq!(stale.with_frames(&checks), recorded);
+    }
+
+    #[test]
+    fn test_missing_file_stops_playback() {
+        let mut emu = Emu::new();
+        emu.load_rom(&test_rom::build()).unwrap();
+        let input = InputMacro::parse("send GONE.8xp\npower\n").unwrap();
+        assert_eq!(
+            play(&mut emu, &input, &mut |_| None),
+            Err(MacroError::MissingFile("GONE.8xp".into()))
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! Keys by name
+//!
+//! The TI-84 Plus CE keypad as an enum over the 8x8 matrix that
+//! `Emu::set_key` takes (row = keypad data group, col = bit), so frontends
+//! and scripts can press "ENTER" instead of carrying their own copy of the
+//! row/col table. Row 0 is unused; ON sits at (2, 0) and gets the wake
+//! handling in `Emu::set_key`.
+//!
+//! Names are matched case-insensitively. Each key has one canonical name
+//! (`KeyName::name`, the labels below) plus a few aliases for the symbol
+//! printed on the key:
+//!
+//! ```text
+//! row 1: GRAPH TRACE ZOOM WINDOW YEQU 2ND MODE DEL
+//! row 2: ON STO LN LOG SQUARE RECIP MATH ALPHA
+//! row 3: 0 1 4 7 COMMA SIN APPS XTTN
+//! row 4: DOT 2 5 8 LPAREN COS PRGM STAT
+//! row 5: NEG 3 6 9 RPAREN TAN VARS
+//! row 6: ENTER ADD SUB MUL DIV POWER CLEAR
+//! row 7: DOWN LEFT RIGHT UP
+//! ```
+
+/// A key on the TI-84 Plus CE keypad
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyName {
+    Graph, Trace, Zoom, Window, YEqu, Second, Mode, Del,
+    On, Sto, Ln, Log, Square, Recip, Math, Alpha,
+    Num0, Num1, Num4, Num7, Comma, Sin, Apps, XTThetaN,
+    Dot, Num2, Num5, Num8, LParen, Cos, Prgm, Stat,
+    Neg, Num3, Num6, Num9, RParen, Tan, Vars,
+    Enter, Add, Sub, Mul, Div, Power, Clear,
+    Down, Left, Right, Up,
+}
+
+impl KeyName {
+    /// Every key, in matrix order (row, then column)
+    pub const ALL: [KeyName; 50] = {
+        use KeyName::*;
+        [
+            Graph, Trace, Zoom, Window, YEqu, Second, Mode, Del,
+            On, Sto, Ln, Log, Square, Recip, Math, Alpha,
+            Num0, Num1, Num4, Num7, Comma, Sin, Apps, XTThetaN,
+            Dot, Num2, Num5, Num8, LParen, Cos, Prgm, Stat,
+            Neg, Num3, Num6, Num9, RParen, Tan, Vars,
+            Enter, Add, Sub, Mul, Div, Power, Clear,
+            Down, Left, Right, Up,
+        ]
+    };
+
+    /// Keypad matrix position (row, col), as for `Emu::set_key`
+    pub const fn position(self) -> (usize, usize) {
+        use KeyName::*;
+        match self {
+            Graph => (1, 0),
+            Trace => (1, 1),
+            Zoom => (1, 2),
+            Window => (1, 3),
+            YEqu => (1, 4),
+            Second => (1, 5),
+            Mode => (1, 6),
+            Del => (1, 7),
+            On => (2, 0),
+            Sto => (2, 1),
+            Ln => (2, 2),
+            Log => (2, 3),
+            Square => (2, 4),
+            Recip => (2, 5),
+            Math => (2, 6),
+            Alpha => (2, 7),
+            Num0 => (3, 0),
+            Num1 => (3, 1),
+            Num4 => (3, 2),
+            Num7 => (3, 3),
+            Comma => (3, 4),
+            Sin => (3, 5),
+            Apps => (3, 6),
+            XTThetaN => (3, 7),
+            Dot => (4, 0),
+            Num2 => (4, 1),
+            Num5 => (4, 2),
+            Num8 => (4, 3),
+            LParen => (4, 4),
+            Cos => (4, 5),
+            Prgm => (4, 6),
+            Stat => (4, 7),
+            Neg => (5, 0),
+            Num3 => (5, 1),
+            Num6 => (5, 2),
+            Num9 => (5, 3),
+            RParen => (5, 4),
+            Tan => (5, 5),
+            Vars => (5, 6),
+            Enter => (6, 0),
+            Add => (6, 1),
+            Sub => (6, 2),
+            Mul => (6, 3),
+            Div => (6, 4),
+            Power => (6, 5),
+            Clear => (6, 6),
+            Down => (7, 0),
+            Left => (7, 1),
+            Right => (7, 2),
+            Up => (7, 3),
+        }
+    }
+
+    /// Canonical name (see the module docs)
+    pub fn name(self) -> &'static str {
+        use KeyName::*;
+        match self {
+            Graph => "GRAPH",
+            Trace => "TRACE",
+            Zoom => "ZOOM",
+            Window => "WINDOW",
+            YEqu => "YEQU",
+            Second => "2ND",
+            Mode => "MODE",
+            Del => "DEL",
+            On => "ON",
+            Sto => "STO",
+            Ln => "LN",
+            Log => "LOG",
+            Square => "SQUARE",
+            Recip => "RECIP",
+            Math => "MATH",
+            Alpha => "ALPHA",
+            Num0 => "0",
+            Num1 => "1",
+            Num4 => "4",
+            Num7 => "7",
+            Comma => "COMMA",
+            Sin => "SIN",
+            Apps => "APPS",
+            XTThetaN => "XTTN",
+            Dot => "DOT",
+            Num2 => "2",
+            Num5 => "5",
+            Num8 => "8",
+            LParen => "LPAREN",
+            Cos => "COS",
+            Prgm => "PRGM",
+            Stat => "STAT",
+            Neg => "NEG",
+            Num3 => "3",
+            Num6 => "6",
+            Num9 => "9",
+            RParen => "RPAREN",
+            Tan => "TAN",
+            Vars => "VARS",
+            Enter => "ENTER",
+            Add => "ADD",
+            Sub => "SUB",
+            Mul => "MUL",
+            Div => "DIV",
+            Power => "POWER",
+            Clear => "CLEAR",
+            Down => "DOWN",
+            Left => "LEFT",
+            Right => "RIGHT",
+            Up => "UP",
+        }
+    }
+
+    /// Key for a canonical name or alias, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        use KeyName::*;
+        let name = name.trim().to_ascii_uppercase();
+        let alias = match name.as_str() {
+            "Y=" => Some(YEqu),
+            "SECOND" => Some(Second),
+            "STO>" | "STO->" => Some(Sto),
+            "X^2" | "X2" => Some(Square),
+            "X^-1" | "X-1" => Some(Recip),
+            "X,T,THETA,N" | "X,T,O,N" => Some(XTThetaN),
+            "," => Some(Comma),
+            "." => Some(Dot),
+            "(" => Some(LParen),
+            ")" => Some(RParen),
+            "(-)" => Some(Neg),
+            "+" => Some(Add),
+            "-" => Some(Sub),
+            "*" => Some(Mul),
+            "/" => Some(Div),
+            "^" => Some(Power),
+            _ => None,
+        };
+        alias.or_else(|| Self::ALL.into_iter().find(|key| key.name() == name))
+    }
+
+    /// Key at a matrix position, if there is one
+    pub fn from_position(row: usize, col: usize) -> Option<Self> {
+        Self::ALL.into_iter().find(|key| key.position() == (row, col))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_and_positions() {
+        for key in KeyName::ALL {
+            assert_eq!(KeyName::from_name(key.name()), Some(key));
+            let (row, col) = key.position();
+            assert_eq!(KeyName::from_position(row, col), Some(key), "{:?} shares a position", key);
+        }
+        assert_eq!(KeyName::from_name("enter"), Some(KeyName::Enter));
+        assert_eq!(KeyName::from_name(" Y= "), Some(KeyName::YEqu));
+        assert_eq!(KeyName::from_name("(-)"), Some(KeyName::Neg));
+        assert_eq!(KeyName::from_name("-"), Some(KeyName::Sub));
+        assert_eq!(KeyName::from_name("F1"), None);
+        assert_eq!(KeyName::from_position(0, 0), None);
+        assert_eq!(KeyName::from_position(5, 7), None);
+    }
+}
@@ -0,0 +1,155 @@
+//! Key rollover stress test
+//!
+//! Mashes random multi-key combinations at random cycle offsets and checks
+//! that the program keeps running and the keypad data registers agree with
+//! the held keys once input settles. Targets edge/level interrupt bugs that
+//! only show up with overlapping presses (stuck keypad IRQ, lost wake-ups).
+
+#[cfg(test)]
+mod tests {
+    use crate::peripherals::{KEYPAD_COLS, KEYPAD_ROWS};
+    use crate::test_rom::{self, read24, KEYS, LOOPS, READY, READY_MAGIC, TICKS};
+    use crate::Emu;
+
+    /// Keypad data registers (8 rows x 16 bits)
+    const KEYPAD_DATA: u32 = 0xF50010;
+
+    /// Cycles a combination is held before the registers must match it
+    const SETTLE_CYCLES: u32 = 500_000;
+
+    /// Small deterministic generator so failures reproduce from the seed
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+    }
+
+    /// Real key positions: rows 1-7, skipping ON (row 2, col 0) which has
+    /// its own wake/interrupt path and would power-cycle the OS
+    fn is_mashable(row: usize, col: usize) -> bool {
+        (1..KEYPAD_ROWS).contains(&row) && col < KEYPAD_COLS && (row, col) != (2, 0)
+    }
+
+    /// Press/release random keys one at a time at random cycle offsets
+    fn mash(emu: &mut Emu, rng: &mut XorShift, held: &mut [[bool; KEYPAD_COLS]; KEYPAD_ROWS]) {
+        let changes = 1 + rng.below(8);
+        for _ in 0..changes {
+            let (row, col) = loop {
+                let row = rng.below(KEYPAD_ROWS as u64) as usize;
+                let col = rng.below(KEYPAD_COLS as u64) as usize;
+                if is_mashable(row, col) {
+                    break (row, col);
+                }
+            };
+            held[row][col] = !held[row][col];
+            emu.set_key(row, col, held[row][col]);
+            emu.run_cycles(rng.below(20_000) as u32);
+        }
+    }
+
+    fn release_all(emu: &mut Emu, held: &mut [[bool; KEYPAD_COLS]; KEYPAD_ROWS]) {
+        for (row, cols) in held.iter_mut().enumerate() {
+            for (col, down) in cols.iter_mut().enumerate() {
+                if *down {
+                    *down = false;
+                    emu.set_key(row, col, false);
+                }
+            }
+        }
+    }
+
+    fn expected_row(held: &[[bool; KEYPAD_COLS]; KEYPAD_ROWS], row: usize) -> u16 {
+        held[row]
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (col, &down)| bits | ((down as u16) << col))
+    }
+
+    fn read16(emu: &mut Emu, addr: u32) -> u16 {
+        emu.peek_byte(addr) as u16 | (emu.peek_byte(addr + 1) as u16) << 8
+    }
+
+    #[test]
+    fn test_random_rollover_keeps_running() {
+        for seed in [0x84CE_0001u64, 0xDEAD_BEEF] {
+            let mut rng = XorShift(seed);
+            let mut emu = Emu::new();
+            emu.load_rom(&test_rom::build()).unwrap();
+            emu.power_on();
+            emu.run_cycles(5_000_000);
+            assert_eq!(emu.peek_byte(READY), READY_MAGIC);
+
+            let mut held = [[false; KEYPAD_COLS]; KEYPAD_ROWS];
+            for round in 0..30 {
+                let ticks = read24(&emu, TICKS);
+                let loops = read24(&emu, LOOPS);
+
+                mash(&mut emu, &mut rng, &mut held);
+                if rng.below(4) == 0 {
+                    release_all(&mut emu, &mut held);
+                }
+                emu.run_cycles(SETTLE_CYCLES);
+
+                let ctx = format!("seed {seed:#X} round {round}");
+                assert!(read24(&emu, TICKS) > ticks, "{ctx}: timer ISR stopped");
+                assert!(read24(&emu, LOOPS) > loops, "{ctx}: main loop deadlocked");
+
+                for row in 0..KEYPAD_ROWS {
+                    let addr = row as u32 * 2;
+                    let expected = expected_row(&held, row);
+                    assert_eq!(read16(&mut emu, KEYPAD_DATA + addr), expected, "{ctx}: data row {row}");
+                    assert_eq!(read16(&mut emu, KEYS + addr), expected, "{ctx}: mailbox row {row}");
+                }
+            }
+        }
+    }
+
+    /// Try to load ROM from common locations
+    #[test]
+    #[ignore = "requires ROM file"]
+    fn test_os_survives_key_mashing() {
+        let rom = test_rom::try_load_real_rom().expect("ROM file not found");
+        let mut emu = Emu::new();
+        emu.load_rom(&rom).unwrap();
+        emu.press_on_key();
+        emu.run_cycles(70_000_000);
+        emu.release_on_key();
+        emu.run_cycles(1_000_000);
+
+        let mut rng = XorShift(0x84CE);
+        let mut held = [[false; KEYPAD_COLS]; KEYPAD_ROWS];
+        for round in 0..2_000 {
+            mash(&mut emu, &mut rng, &mut held);
+            emu.run_cycles(rng.below(200_000) as u32);
+            if rng.below(3) == 0 {
+                release_all(&mut emu, &mut held);
+                emu.run_cycles(SETTLE_CYCLES);
+            }
+            assert!(!emu.is_off(), "round {round}: OS powered off");
+            assert!(
+                !emu.is_halted() || emu.iff1(),
+                "round {round}: halted with interrupts disabled at PC {:06X}",
+                emu.pc()
+            );
+        }
+
+        // With everything released the OS must still wake for input
+        release_all(&mut emu, &mut held);
+        emu.run_cycles(5_000_000);
+        emu.set_key(6, 0, true);
+        emu.run_cycles(1_000_000);
+        emu.set_key(6, 0, false);
+        emu.run_cycles(1_000_000);
+        assert!(!emu.is_off());
+        assert!(emu.iff1() || !emu.is_halted(), "OS stopped responding");
+    }
+}
@@ -0,0 +1,40 @@
+//! Time-travel watch: when did a byte last change
+//!
+//! Answers "which instruction last changed this byte" without a watchpoint
+//! having been set beforehand. The core keeps no rewind buffer, so the
+//! stored snapshots are the save slots (`crate::slots`): slots saved
+//! earlier than the current cycle count are scanned newest first, and each
+//! is replayed up to the next newer one (or the present) with a write
+//! watchpoint on the byte. The last write in a window that changed the
+//! value is the answer; if a window has none, the next older slot is tried.
+//! The present state is then restored. On request
+//! (`Emu::rewind_to_last_change`) a second replay of that window instead
+//! stops right after the writing instruction, so a debugger can inspect
+//! registers and memory there; the present is first saved to a slot so it
+//! can be got back.
+//!
+//! Replay relies on execution being deterministic from a loaded state, so
+//! the slots must lie on the current timeline (saved earlier in this same
+//! run, with no state loads or input since that the replay can't see).
+//! Breakpoints, watchpoints, port watches, the interrupt watch, the snoop,
+//! the event and frame callbacks, the heatmap, opcode coverage and macro
+//! recording are detached while replaying, so frontends see nothing of the
+//! replayed execution.
+
+/// The write found by `Emu::find_last_change` (C layout, see emu.h)
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EmuLastChange {
+    /// Total cycles when the write happened
+    pub cycles: u64,
+    /// 24-bit address of the byte
+    pub addr: u32,
+    /// Instruction that made the write
+    pub pc: u32,
+    /// Save slot the replay started from
+    pub slot: u32,
+    /// Byte before and after the write
+    pub old_value: u8,
+    pub new_value: u8,
+    pub reserved: [u8; 2],
+}
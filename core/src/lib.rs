@@ -21,13 +21,58 @@
 //! | 0xD65800 - 0xDFFFFF | Unmapped            |
 //! | 0xE00000 - 0xFFFFFF | Memory-mapped I/O   |
 
+pub mod api;
+pub mod autosave;
+pub mod benchmark;
+pub mod breakpoint;
 pub mod memory;
+pub mod memory_map;
 pub mod bus;
+pub mod color;
 pub mod cpu;
+pub mod crc;
 pub mod peripherals;
 pub mod scheduler;
+pub mod scale;
+pub mod scanline;
+pub mod screen_text;
+pub mod search;
+pub mod slots;
+pub mod snoop;
 pub mod disasm;
+pub mod error;
+pub mod eval;
+pub mod events;
+pub mod ffi_string;
+pub mod hash;
+pub mod heatmap;
+pub mod host_bridge;
+pub mod host_clock;
+pub mod input_macro;
+pub mod key_name;
+pub mod last_change;
+pub mod link_capture;
+pub mod lockstep;
+pub mod mmio_diff;
+pub mod os_nav;
+pub mod os_update;
+pub mod overlay;
+pub mod patch;
+pub mod opcode_coverage;
+pub mod perf;
+pub mod pixel_format;
+pub mod png;
+pub mod port_watch;
+pub mod power;
+pub mod redact;
+pub mod rom_compare;
+pub mod rom_info;
 pub mod ti_file;
+pub mod test_rom;
+pub mod testkit;
+pub mod watch;
+pub mod watchpoint;
+pub mod watchdog_monitor;
 mod emu;
 
 #[cfg(target_arch = "wasm32")]
@@ -42,12 +87,30 @@ mod keypad_integration_test;
 #[cfg(test)]
 mod calc_integration_test;
 
-use std::os::raw::c_char;
+#[cfg(test)]
+mod keypad_stress_test;
+
+#[cfg(test)]
+mod state_compat_test;
+
+#[cfg(test)]
+mod boot_integration_test;
+
+#[cfg(test)]
+mod program_corpus_test;
+
+use std::os::raw::{c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr;
 use std::slice;
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-pub use emu::{Emu, LcdSnapshot, TimerSnapshot, StepInfo, log_event, enable_inst_trace, disable_inst_trace, arm_inst_trace_on_wake};
+pub use api::{Error, Ti84ce};
+pub use error::{EmuError, LoadError};
+pub use key_name::KeyName;
+pub use emu::{Emu, FrameCounters, LcdSnapshot, ResetCause, ResetCombo, ResetKind, RunGranularity, RunUntil, StateLoad, TimerSnapshot, StepInfo, EmuStepInfo, EmuRegisters, log_event, enable_inst_trace, disable_inst_trace, arm_inst_trace_on_wake};
 pub use bus::{IoTarget, IoOpType, IoRecord};
 pub use disasm::{disassemble, DisasmResult};
 
@@ -57,12 +120,23 @@ pub use disasm::{disassemble, DisasmResult};
 /// This is an opaque type from C's perspective (used via void*).
 pub struct SyncEmu {
     inner: Mutex<Emu>,
+    /// Host timing stats, under a separate lock so polling never waits on emulation
+    perf: Mutex<perf::PerfTracker>,
+    /// Stop request flag shared with the emulator, set without taking the lock
+    stop: Arc<AtomicBool>,
+    /// Crash report from the last panic in a frame, kept outside the
+    /// emulator lock (which the panic poisons)
+    crash: Mutex<Option<String>>,
 }
 
 impl SyncEmu {
     fn new() -> Self {
+        let emu = Emu::new();
         Self {
-            inner: Mutex::new(Emu::new()),
+            stop: emu.stop_handle(),
+            inner: Mutex::new(emu),
+            perf: Mutex::new(perf::PerfTracker::new()),
+            crash: Mutex::new(None),
         }
     }
 }
@@ -78,14 +152,181 @@ pub extern "C" fn emu_create() -> *mut SyncEmu {
 }
 
 /// Destroy an emulator instance.
+/// If an autosave sink is registered it receives a final save state first.
 /// Safe to call with null pointer.
 #[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
 #[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_destroy")]
 pub extern "C" fn emu_destroy(emu: *mut SyncEmu) {
     if !emu.is_null() {
-        unsafe {
-            drop(Box::from_raw(emu));
+        let sync_emu = unsafe { Box::from_raw(emu) };
+        if let Ok(mut emu) = sync_emu.inner.lock() {
+            emu.autosave(autosave::AutosaveReason::Destroy);
+        }
+        drop(sync_emu);
+    }
+}
+
+/// Register a sink that receives a final save state when the emulator is
+/// destroyed, emulation panics inside emu_run_cycles, or a memory
+/// protection trap fires (NULL clears it).
+/// The state buffer is only valid during the callback, which runs with the
+/// emulator locked and must not call any emu_* function.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_autosave_sink")]
+pub extern "C" fn emu_set_autosave_sink(
+    emu: *mut SyncEmu,
+    cb: Option<autosave::AutosaveCallback>,
+    user: *mut c_void,
+) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_autosave_sink(cb, user);
+}
+
+/// Attach a callback-backed device to `[base, base + len)`, which must lie
+/// inside an unmapped MMIO window (0xE40000-0xEFFFFF or 0xFB0000-0xFEFFFF).
+/// Callbacks receive the offset from `base` and run with the emulator locked.
+/// Returns the extension id (>= 0), or -50 bad range, -51 overlap,
+/// -52 too many extensions, -1 on null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_register_extension")]
+pub extern "C" fn emu_register_extension(
+    emu: *mut SyncEmu,
+    base: u32,
+    len: u32,
+    read: peripherals::extension::ExtensionReadCallback,
+    write: peripherals::extension::ExtensionWriteCallback,
+    user: *mut c_void,
+) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let device = peripherals::CallbackDevice::new(read, write, user);
+    match emu.register_extension(base, len, Box::new(device)) {
+        Ok(id) => id as i32,
+        Err(err) => err.code(),
+    }
+}
+
+/// Detach extension `id`. Returns 0 on success, -53 unknown id.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_unregister_extension")]
+pub extern "C" fn emu_unregister_extension(emu: *mut SyncEmu, id: u32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.unregister_extension(id) {
+        Ok(()) => 0,
+        Err(err) => err.code(),
+    }
+}
+
+/// Enable (nonzero) or disable (0) the host bridge register block at
+/// 0xFE0000. Returns 0 on success, -51 if an extension occupies the range.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_host_bridge_enable")]
+pub extern "C" fn emu_host_bridge_enable(emu: *mut SyncEmu, enable: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if enable == 0 {
+        emu.disable_host_bridge();
+        return 0;
+    }
+    match emu.enable_host_bridge() {
+        Ok(()) => 0,
+        Err(err) => err.code(),
+    }
+}
+
+/// Queue `len` bytes for the program to read from the bridge.
+/// Returns 0 on success, -60 if the bridge is not enabled.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_host_bridge_input")]
+pub extern "C" fn emu_host_bridge_input(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || (data.is_null() && len > 0) {
+        return -1;
+    }
+
+    let data = if len == 0 { &[][..] } else { unsafe { slice::from_raw_parts(data, len) } };
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.host_bridge_push_input(data) {
+        Ok(()) => 0,
+        Err(err) => err.code(),
+    }
+}
+
+/// Move up to `cap` bytes of program output into `buf`, oldest first.
+/// Returns the number of bytes copied, -60 if the bridge is not enabled.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_host_bridge_output")]
+pub extern "C" fn emu_host_bridge_output(emu: *mut SyncEmu, buf: *mut u8, cap: usize) -> i32 {
+    if emu.is_null() || (buf.is_null() && cap > 0) {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.take_host_output(cap.min(i32::MAX as usize)) {
+        Ok(bytes) => {
+            if !bytes.is_empty() {
+                unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), buf, bytes.len()) };
+            }
+            bytes.len() as i32
+        }
+        Err(err) => err.code(),
+    }
+}
+
+/// Write the number of output bytes dropped because the bridge buffer
+/// (64 KiB) filled before they were taken. Returns 0 on success, -1 for
+/// null pointers, -60 if the bridge is not enabled.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_host_bridge_dropped")]
+pub extern "C" fn emu_host_bridge_dropped(emu: *const SyncEmu, out: *mut u64) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    match emu.host_output_dropped() {
+        Ok(dropped) => {
+            unsafe { *out = dropped };
+            0
         }
+        Err(err) => err.code(),
+    }
+}
+
+/// Test result reported by the program: 0 = pass, 1-255 = fail code,
+/// -61 if nothing was reported yet, -60 if the bridge is not enabled.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_host_bridge_result")]
+pub extern "C" fn emu_host_bridge_result(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    match emu.host_test_result() {
+        Ok(result) => result as i32,
+        Err(err) => err.code(),
     }
 }
 
@@ -97,6 +338,24 @@ pub extern "C" fn emu_set_log_callback(cb: Option<extern "C" fn(*const c_char)>)
     emu::set_log_callback(cb);
 }
 
+/// Short description of a return code from any emu_* function ("ok" for
+/// codes >= 0, "unknown error" for codes not in emu.h), as a static
+/// NUL-terminated string the caller must not free.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_error_string")]
+pub extern "C" fn emu_error_string(code: i32) -> *const c_char {
+    error::error_cstr(code).as_ptr()
+}
+
+/// Free a string returned by an emu_* function documented as returning a
+/// core-owned string (see ffi_string.rs). NULL, static strings and
+/// pointers already freed are ignored.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_string_free")]
+pub extern "C" fn emu_string_free(text: *mut c_char) {
+    ffi_string::free(text);
+}
+
 /// Load ROM data into the emulator.
 /// Returns 0 on success, negative error code on failure.
 #[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
@@ -112,7 +371,28 @@ pub extern "C" fn emu_load_rom(emu: *mut SyncEmu, data: *const u8, len: usize) -
     let mut emu = sync_emu.inner.lock().unwrap();
     match emu.load_rom(rom_data) {
         Ok(()) => 0,
-        Err(code) => code,
+        Err(err) => err.code(),
+    }
+}
+
+/// Load the ROM-less demo instead of a TI ROM (see `Emu::load_demo`).
+/// `patterns` is a mask of EMU_DEMO_* (0 = all), `seconds` per pattern
+/// 0 = default. Returns 0 on success, -1 null, -30 bad mask or seconds.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_load_demo")]
+pub extern "C" fn emu_load_demo(emu: *mut SyncEmu, patterns: u32, seconds: u32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let Some(config) = test_rom::DemoConfig::new(patterns, seconds) else {
+        return -30;
+    };
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.load_demo(config) {
+        Ok(()) => 0,
+        Err(err) => err.code(),
     }
 }
 
@@ -120,7 +400,9 @@ pub extern "C" fn emu_load_rom(emu: *mut SyncEmu, data: *const u8, len: usize) -
 /// Injects the file into the flash archive so TI-OS discovers it on boot.
 /// Must be called after load_rom() and before power_on().
 /// Returns: number of entries injected (>=0), or negative error code.
-/// Error codes: -10 = ROM not loaded, -11 = parse error, -12 = no flash space, -13 = already booted
+/// Error codes: -1 = null/empty, -10 = ROM not loaded, -11 = not a TI
+/// variable file, -12 = no flash space, -13 = already booted,
+/// -14 = bad checksum, -15 = truncated entry, -16 = variable too large
 #[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
 #[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_send_file")]
 pub extern "C" fn emu_send_file(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
@@ -133,299 +415,3876 @@ pub extern "C" fn emu_send_file(emu: *mut SyncEmu, data: *const u8, len: usize)
     let mut emu = sync_emu.inner.lock().unwrap();
     match emu.send_file(file_data) {
         Ok(count) => count as i32,
-        Err(code) => code,
+        Err(err) => err.code(),
     }
 }
 
-/// Reset the emulator to initial state.
+/// Send a .8xp/.8xv file to a running emulator (hot reload): replaces any
+/// archived variable with the same name and type, then soft resets so
+/// TI-OS finds it. RAM contents are lost, as with a reset.
+/// Returns: number of entries injected (>=0), or the emu_send_file error
+/// codes except -13.
 #[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
-#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_reset")]
-pub extern "C" fn emu_reset(emu: *mut SyncEmu) {
-    if emu.is_null() {
-        return;
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_send_file_live")]
+pub extern "C" fn emu_send_file_live(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() || len == 0 {
+        return -1;
     }
 
     let sync_emu = unsafe { &*emu };
+    let file_data = unsafe { slice::from_raw_parts(data, len) };
     let mut emu = sync_emu.inner.lock().unwrap();
-    emu.reset();
+    match emu.send_file_live(file_data) {
+        Ok(count) => count as i32,
+        Err(err) => err.code(),
+    }
 }
 
-/// Power on the emulator (simulate ON key press+release).
-/// Must be called after load_rom() to start execution.
-#[no_mangle]
-#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_power_on")]
-pub extern "C" fn emu_power_on(emu: *mut SyncEmu) {
-    if emu.is_null() {
-        return;
+/// Flash an OS upgrade (.8eu) from the host, bypassing the boot code's
+/// receive mode and its signature check, and reboot into it.
+/// Returns: bytes programmed (>0), or negative error code.
+/// Error codes: -1 = null/empty, -80 = ROM not loaded, -81 = not a flash file,
+/// -82 = no CE OS in file, -83 = too large, -84 = flash verify failed
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_flash_os")]
+pub extern "C" fn emu_flash_os(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() || len == 0 {
+        return -1;
     }
 
     let sync_emu = unsafe { &*emu };
+    let file_data = unsafe { slice::from_raw_parts(data, len) };
     let mut emu = sync_emu.inner.lock().unwrap();
-    emu.power_on();
+    match emu.flash_os(file_data) {
+        Ok(size) => size as i32,
+        Err(err) => err.code(),
+    }
 }
 
-/// Run the emulator for the specified number of cycles.
-/// Returns the number of cycles actually executed.
-/// Also updates the framebuffer with current VRAM contents.
+/// Add a flash patch set: IPS, or text lines of `address: hex bytes` (see
+/// patch.rs). Applied now if a ROM is loaded, and again after every ROM
+/// load and reset; the ROM hash stays that of the unpatched ROM.
+/// Returns the number of bytes patched (>=0), -1 for null pointer,
+/// -110 for a malformed patch, -111 if a patch reaches past flash.
 #[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
-#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_run_cycles")]
-pub extern "C" fn emu_run_cycles(emu: *mut SyncEmu, cycles: i32) -> i32 {
-    if emu.is_null() || cycles <= 0 {
-        return 0;
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_patch_add")]
+pub extern "C" fn emu_patch_add(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() {
+        return -1;
     }
 
     let sync_emu = unsafe { &*emu };
+    let patch_data = unsafe { slice::from_raw_parts(data, len) };
     let mut emu = sync_emu.inner.lock().unwrap();
-    let executed = emu.run_cycles(cycles as u32) as i32;
-    emu.render_frame();
-    executed
+    match emu.add_patch_set(patch_data) {
+        Ok(bytes) => bytes as i32,
+        Err(err) => err.code(),
+    }
 }
 
-/// Get a pointer to the framebuffer.
-/// The framebuffer is ARGB8888 format, owned by the emulator.
-/// Writes width and height to the provided pointers if non-null.
-/// Returns null if emulator pointer is null.
-///
-/// WARNING: The returned pointer is only valid while the mutex is held.
-/// The caller should copy the framebuffer data immediately.
+/// Forget all patch sets; patched bytes stay in flash until the next
+/// emu_load_rom.
 #[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
-#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_framebuffer")]
-pub extern "C" fn emu_framebuffer(emu: *const SyncEmu, w: *mut i32, h: *mut i32) -> *const u32 {
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_patch_clear")]
+pub extern "C" fn emu_patch_clear(emu: *mut SyncEmu) {
     if emu.is_null() {
-        return ptr::null();
+        return;
     }
 
     let sync_emu = unsafe { &*emu };
-    let emu = sync_emu.inner.lock().unwrap();
-    let (width, height) = emu.framebuffer_size();
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.clear_patches();
+}
 
-    if !w.is_null() {
-        unsafe { *w = width as i32 };
+/// Write the loaded ROM's hash (see Emu::rom_hash) to `out`, for keying
+/// per-ROM settings and snapshots.
+/// Returns 0 on success, -1 for null pointers, -10 if no ROM is loaded.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_rom_hash")]
+pub extern "C" fn emu_rom_hash(emu: *mut SyncEmu, out: *mut u64) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
     }
-    if !h.is_null() {
-        unsafe { *h = height as i32 };
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    match emu.rom_hash() {
+        Some(hash) => {
+            unsafe { *out = hash };
+            0
+        }
+        None => -10,
     }
+}
 
-    emu.framebuffer_ptr()
+/// Write the loaded ROM's model and versions (see Emu::rom_info) to `out`.
+/// Returns 0 on success, -1 for null pointers, -10 if no ROM is loaded.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_rom_info")]
+pub extern "C" fn emu_rom_info(emu: *const SyncEmu, out: *mut rom_info::EmuRomInfo) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    match emu.rom_info() {
+        Some(info) => {
+            unsafe { *out = info.into() };
+            0
+        }
+        None => -10,
+    }
 }
 
-/// Set key state.
-/// row: 0-7, col: 0-7
-/// down: non-zero for pressed, zero for released
+/// Reset the emulator to initial state.
 #[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
-#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_key")]
-pub extern "C" fn emu_set_key(emu: *mut SyncEmu, row: i32, col: i32, down: i32) {
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_reset")]
+pub extern "C" fn emu_reset(emu: *mut SyncEmu) {
     if emu.is_null() {
         return;
     }
 
     let sync_emu = unsafe { &*emu };
     let mut emu = sync_emu.inner.lock().unwrap();
-    emu.set_key(row as usize, col as usize, down != 0);
+    emu.reset();
 }
 
-/// Get the backlight brightness level (0-255).
-/// Returns 0 if emulator pointer is null.
+/// Reset clearing what `kind` says (ResetKind code: 0 = power cycle, RAM
+/// kept; 1 = RAM cleared, like emu_reset; 2 = RAM and archive cleared,
+/// OS kept). The calculator waits for emu_power_on afterwards.
+/// Returns 0 on success, -1 for null pointer, -30 for an unknown kind.
 #[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
-#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_get_backlight")]
-pub extern "C" fn emu_get_backlight(emu: *const SyncEmu) -> u8 {
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_reset_kind")]
+pub extern "C" fn emu_reset_kind(emu: *mut SyncEmu, kind: i32) -> i32 {
     if emu.is_null() {
-        return 0;
+        return -1;
     }
+    let Some(kind) = ResetKind::from_raw(kind as u32) else {
+        return -30;
+    };
 
     let sync_emu = unsafe { &*emu };
-    let emu = sync_emu.inner.lock().unwrap();
-    emu.get_backlight()
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.reset_kind(kind);
+    0
 }
 
-/// Check if LCD is on (should display content).
-/// Returns 1 if LCD is on, 0 if LCD is off.
-/// LCD is off when either control port 0x05 bit 4 is clear OR lcd.control bit 11 is clear.
+/// Choose how often emu_run_cycles services the peripherals:
+/// mode 0 = after every instruction (CEmu-exact), 1 = every `cycles`
+/// cycles, 2 = when a scheduler event is due, 3 = in bursts up to the next
+/// peripheral deadline (default, as exact as 0; see RunGranularity).
+/// Returns 0 on success, -1 for null pointer, -30 for an unknown mode or
+/// mode 1 with zero cycles.
 #[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
-#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_is_lcd_on")]
-pub extern "C" fn emu_is_lcd_on(emu: *const SyncEmu) -> i32 {
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_run_granularity")]
+pub extern "C" fn emu_set_run_granularity(emu: *mut SyncEmu, mode: i32, cycles: u32) -> i32 {
     if emu.is_null() {
-        return 0;
+        return -1;
     }
+    let Some(granularity) = RunGranularity::from_raw(mode as u32, cycles) else {
+        return -30;
+    };
 
     let sync_emu = unsafe { &*emu };
-    let emu = sync_emu.inner.lock().unwrap();
-    if emu.is_lcd_on() { 1 } else { 0 }
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_run_granularity(granularity);
+    0
 }
 
-/// Get the size needed for a save state buffer.
+/// Press the reset button with a key combination held (ResetCombo code:
+/// 0 = button alone, 1 = [del] to reinstall the OS, 2 = [2nd]+[del]).
+/// The calculator restarts without emu_power_on; the keys are released
+/// after the boot code has read them.
+/// Returns 0 on success, -1 for null pointer, -30 for an unknown combination.
 #[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
-#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_save_state_size")]
-pub extern "C" fn emu_save_state_size(emu: *const SyncEmu) -> usize {
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_reset_combo")]
+pub extern "C" fn emu_reset_combo(emu: *mut SyncEmu, combo: i32) -> i32 {
     if emu.is_null() {
-        return 0;
+        return -1;
     }
+    let Some(combo) = ResetCombo::from_raw(combo as u32) else {
+        return -30;
+    };
 
     let sync_emu = unsafe { &*emu };
-    let emu = sync_emu.inner.lock().unwrap();
-    emu.save_state_size()
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.reset_with_combo(combo);
+    0
 }
 
-/// Save emulator state to a buffer.
-/// Returns bytes written on success, negative error code on failure.
+/// Enable (non-zero) or disable (zero) boot sector write protection.
+/// Returns 0 on success, -1 for null pointer.
 #[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
-#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_save_state")]
-pub extern "C" fn emu_save_state(emu: *const SyncEmu, out: *mut u8, cap: usize) -> i32 {
-    if emu.is_null() || out.is_null() {
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_boot_protect")]
+pub extern "C" fn emu_set_boot_protect(emu: *mut SyncEmu, enabled: i32) -> i32 {
+    if emu.is_null() {
         return -1;
     }
 
     let sync_emu = unsafe { &*emu };
-    let emu = sync_emu.inner.lock().unwrap();
-    let buffer = unsafe { slice::from_raw_parts_mut(out, cap) };
-
-    match emu.save_state(buffer) {
-        Ok(size) => size as i32,
-        Err(code) => code,
-    }
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_boot_protect(enabled != 0);
+    0
 }
 
-/// Load emulator state from a buffer.
-/// Returns 0 on success, negative error code on failure.
+/// Get the cause of the most recent reset (ResetCause code, see emu.h).
+/// Returns -1 for null pointer.
 #[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
-#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_load_state")]
-pub extern "C" fn emu_load_state(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
-    if emu.is_null() || data.is_null() {
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_get_reset_cause")]
+pub extern "C" fn emu_get_reset_cause(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
         return -1;
     }
 
     let sync_emu = unsafe { &*emu };
-    let mut emu = sync_emu.inner.lock().unwrap();
-    let buffer = unsafe { slice::from_raw_parts(data, len) };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.reset_cause() as i32
+}
 
-    match emu.load_state(buffer) {
-        Ok(()) => 0,
-        Err(code) => code,
+/// Crash report (reset cause, timing, registers) saved when the last frame
+/// panicked, for attaching to a bug report. Readable after the crash even
+/// though the emulator itself is unusable.
+/// Returns a core-owned string to release with emu_string_free, or null
+/// for a null pointer or if no frame has crashed.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_crash_report")]
+pub extern "C" fn emu_crash_report(emu: *const SyncEmu) -> *mut c_char {
+    if emu.is_null() {
+        return ptr::null_mut();
     }
-}
 
-// ============================================================
-// Backend API (for single-backend builds without bridge)
-// ============================================================
+    let sync_emu = unsafe { &*emu };
+    match sync_emu.crash.lock().unwrap().clone() {
+        Some(report) => ffi_string::into_raw(report),
+        None => ptr::null_mut(),
+    }
+}
 
-/// Get available backends (comma-separated list).
-/// For Rust-only builds, returns "rust".
+/// Power on the emulator (simulate ON key press+release).
+/// Must be called after load_rom() to start execution.
 #[no_mangle]
-#[cfg(not(feature = "ios_prefixed"))]
-pub extern "C" fn emu_backend_get_available() -> *const c_char {
-    static BACKENDS: &[u8] = b"rust\0";
-    BACKENDS.as_ptr() as *const c_char
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_power_on")]
+pub extern "C" fn emu_power_on(emu: *mut SyncEmu) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.power_on();
+}
+
+/// Run the emulator for the specified number of cycles.
+/// Returns the number of cycles actually executed.
+/// Also updates the framebuffer with current VRAM contents.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_run_cycles")]
+pub extern "C" fn emu_run_cycles(emu: *mut SyncEmu, cycles: i32) -> i32 {
+    if emu.is_null() || cycles <= 0 {
+        return 0;
+    }
+
+    run_host_frame(unsafe { &*emu }, |emu| {
+        let executed = emu.run_cycles(cycles as u32) as i32;
+        emu.render_frame();
+        executed
+    })
+}
+
+/// Run until the LCD's next vertical sync (a new frame), then render, so
+/// the frontend needn't guess cycles per frame. With the LCD off, runs one
+/// 60 Hz frame's worth. Returns the cycles executed, for real-time pacing
+/// (0 if null, paused or off).
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_run_frame")]
+pub extern "C" fn emu_run_frame(emu: *mut SyncEmu) -> i32 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    run_host_frame(unsafe { &*emu }, |emu| emu.run_frame() as i32)
+}
+
+/// Run one frontend frame: times it for the perf stats and autosaves the
+/// session if the emulator panics.
+fn run_host_frame(sync_emu: &SyncEmu, run: impl FnOnce(&mut Emu) -> i32) -> i32 {
+    let wait_start = Instant::now();
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if emu.is_paused() {
+        return 0; // Paused time stays out of the perf windows
+    }
+    let frame_start = Instant::now();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| run(&mut emu)));
+    let executed = match result {
+        Ok(executed) => executed,
+        Err(payload) => {
+            // Hand the session to the frontend before the panic continues
+            let report = emu.crash_report();
+            emu::log_evt!("CRASH: {}", report);
+            *sync_emu.crash.lock().unwrap() = Some(report);
+            emu.autosave(autosave::AutosaveReason::Crash);
+            drop(emu);
+            panic::resume_unwind(payload);
+        }
+    };
+    drop(emu);
+
+    let now = Instant::now();
+    sync_emu.perf.lock().unwrap().record_frame(
+        executed.max(0) as u64,
+        now - frame_start,
+        frame_start - wait_start,
+        now,
+    );
+    executed
+}
+
+/// Execute exactly one instruction (a halted CPU fast-forwards to its
+/// wake-up) and describe it in `out`: PC, opcode bytes, registers and
+/// flags before it ran, cycles taken and the PC it continues at. Works
+/// while paused, for debugger single-stepping.
+/// Returns 0 on success, -1 for null pointers, -10 if no ROM is loaded or
+/// the calculator isn't powered on.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_step")]
+pub extern "C" fn emu_step(emu: *mut SyncEmu, out: *mut EmuStepInfo) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.step() {
+        Some(info) => {
+            let mut step = EmuStepInfo::from(&info);
+            step.next_pc = emu.pc();
+            unsafe { *out = step };
+            0
+        }
+        None => -10,
+    }
+}
+
+/// Most instructions one emu_disasm call lists
+const MAX_DISASM_COUNT: u32 = 65536;
+
+/// Disassemble `count` instructions from `addr` in the CPU's current mode
+/// (ADL or Z80), one "ADDRESS: BYTES  MNEMONIC" line each, reading memory
+/// without side effects.
+/// Returns a core-owned string to release with emu_string_free, or null
+/// for a null pointer or `count` over 65536.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_disasm")]
+pub extern "C" fn emu_disasm(emu: *const SyncEmu, addr: u32, count: u32) -> *mut c_char {
+    if emu.is_null() || count > MAX_DISASM_COUNT {
+        return ptr::null_mut();
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let text = disasm::listing(|a| emu.peek_byte(a), addr, count as usize, emu.adl());
+    ffi_string::into_raw(text)
+}
+
+/// Run until PC reaches `addr` or `max_cycles` have run ("run to cursor";
+/// starting at `addr` runs until execution comes back to it). Returns 0 if
+/// PC reached `addr`, 1 if the cycle budget ran out, 2 if something else
+/// stopped execution first (breakpoint, interrupt watch, emu_request_stop,
+/// pause, power off, no ROM), -1 for null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_run_until_pc")]
+pub extern "C" fn emu_run_until_pc(emu: *mut SyncEmu, addr: u32, max_cycles: u64) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.run_until_pc(addr, max_cycles) as i32
+}
+
+/// Add an enabled PC breakpoint at `addr` (or re-enable the one there).
+/// There is no limit on the count. When the CPU is about to execute one,
+/// the run returns early and EMU_EVENT_BREAKPOINT is queued; the next run
+/// resumes by executing that instruction.
+/// Returns 0 on success, -1 for null pointer, -30 for an address past 24 bits.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_breakpoint_add")]
+pub extern "C" fn emu_breakpoint_add(emu: *mut SyncEmu, addr: u32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    if addr > 0xFFFFFF {
+        return -30;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.add_breakpoint(addr);
+    0
+}
+
+/// Remove the breakpoint at `addr`.
+/// Returns 0 on success, -1 for null pointer, -30 if there is none there.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_breakpoint_remove")]
+pub extern "C" fn emu_breakpoint_remove(emu: *mut SyncEmu, addr: u32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if addr > 0xFFFFFF || !emu.remove_breakpoint(addr) {
+        return -30;
+    }
+    0
+}
+
+/// Enable (non-zero) or disable the breakpoint at `addr` without removing it.
+/// Returns 0 on success, -1 for null pointer, -30 if there is none there.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_breakpoint_enable")]
+pub extern "C" fn emu_breakpoint_enable(emu: *mut SyncEmu, addr: u32, enabled: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if addr > 0xFFFFFF || !emu.enable_breakpoint(addr, enabled != 0) {
+        return -30;
+    }
+    0
+}
+
+/// Remove all breakpoints. Returns 0 on success, -1 for null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_breakpoint_clear")]
+pub extern "C" fn emu_breakpoint_clear(emu: *mut SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.clear_breakpoints();
+    0
+}
+
+/// Check whether the last run stopped at a breakpoint, writing its address
+/// to `addr` if non-null. Returns 1 if it did, 0 if not, -1 for null emu.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_last_breakpoint")]
+pub extern "C" fn emu_last_breakpoint(emu: *const SyncEmu, addr: *mut u32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    match emu.last_breakpoint() {
+        Some(bp) => {
+            if !addr.is_null() {
+                unsafe { *addr = bp };
+            }
+            1
+        }
+        None => 0,
+    }
+}
+
+/// Read the main CPU registers into `out`.
+/// Returns 0 on success, -1 for null pointers.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_get_registers")]
+pub extern "C" fn emu_get_registers(emu: *const SyncEmu, out: *mut EmuRegisters) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    unsafe { *out = emu.registers() };
+    0
+}
+
+/// Overwrite the main CPU registers, e.g. from a debugger's register view
+/// (read them first and change the ones being edited). A new PC takes
+/// effect on the next instruction. Returns 0 on success, -1 for null
+/// pointers.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_registers")]
+pub extern "C" fn emu_set_registers(emu: *mut SyncEmu, regs: *const EmuRegisters) -> i32 {
+    if emu.is_null() || regs.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_registers(unsafe { &*regs });
+    0
+}
+
+/// Pause (1) or resume (0) emulation. While paused emu_run_cycles does
+/// nothing and host time reported via emu_sync_host_time is not counted,
+/// so resuming after the app was backgrounded causes no clock jump.
+/// Returns 0 on success, -1 for null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_paused")]
+pub extern "C" fn emu_set_paused(emu: *mut SyncEmu, paused: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if paused != 0 {
+        emu.pause();
+    } else {
+        emu.resume();
+    }
+    // Neither side of the pause should report a window spanning it
+    sync_emu.perf.lock().unwrap().restart_window();
+    0
+}
+
+/// Returns 1 if paused, 0 if running (or null pointer).
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_is_paused")]
+pub extern "C" fn emu_is_paused(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.is_paused() as i32
+}
+
+/// Report the host wall time in milliseconds (any monotonic clock).
+/// Only time that passes while not paused is counted.
+/// Returns 0 on success, -1 for null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_sync_host_time")]
+pub extern "C" fn emu_sync_host_time(emu: *mut SyncEmu, now_ms: u64) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.sync_host_time(now_ms);
+    0
+}
+
+/// Choose what advances the RTC: 0 = emulated cycles (turbo fast-forwards
+/// the clock, the default), 1 = host time reported via emu_sync_host_time
+/// (the clock stays real-time at any speed). Switching causes no jump.
+/// Returns 0 on success, -1 for null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_rtc_host_time")]
+pub extern "C" fn emu_set_rtc_host_time(emu: *mut SyncEmu, enable: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_rtc_clock(if enable != 0 {
+        peripherals::rtc::RtcClock::HostTime
+    } else {
+        peripherals::rtc::RtcClock::Emulated
+    });
+    0
+}
+
+/// Make the emulated RTC crystal run `ppm` parts per million fast
+/// (negative: slow), up to +-10000. Kept across reset; 0 is exact.
+/// Returns 0 on success, -1 for null pointer, -30 if out of range.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_rtc_drift")]
+pub extern "C" fn emu_set_rtc_drift(emu: *mut SyncEmu, ppm: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if emu.set_rtc_drift_ppm(ppm) {
+        0
+    } else {
+        -30
+    }
+}
+
+/// Skip `trim` RTC ticks per 2^20 (about 0.95 ppm each; negative inserts),
+/// from -32768 to 32767, to counter emu_set_rtc_drift the way a calibrated
+/// crystal would. Kept across reset, not saved with state.
+/// Returns 0 on success, -1 for null pointer, -30 if out of range.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_rtc_trim")]
+pub extern "C" fn emu_set_rtc_trim(emu: *mut SyncEmu, trim: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let Ok(trim) = i16::try_from(trim) else {
+        return -30;
+    };
+    let sync_emu = unsafe { &*emu };
+    sync_emu.inner.lock().unwrap().set_rtc_trim(trim);
+    0
+}
+
+/// Ask the running (or next) emu_run_cycles call to return at the next
+/// instruction boundary. Does not take the emulator lock, so it can be
+/// called from any thread while emu_run_cycles is executing.
+/// Returns 0 on success, -1 for null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_request_stop")]
+pub extern "C" fn emu_request_stop(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    sync_emu.stop.store(true, Ordering::Relaxed);
+    0
+}
+
+/// Get performance statistics for the last completed one-second window:
+/// emulated cycles, frames (emu_run_cycles calls), average host time per
+/// frame segment and time spent waiting for the emulator lock.
+/// All fields are zero until the first window completes.
+/// Returns 0 on success, -1 for null pointers.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_get_perf_stats")]
+pub extern "C" fn emu_get_perf_stats(emu: *const SyncEmu, out: *mut perf::EmuPerfStats) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let stats = sync_emu.perf.lock().unwrap().last_window();
+    unsafe { *out = stats };
+    0
+}
+
+/// Copy the bus access counters (fetches, reads, writes, port accesses,
+/// wait cycles) accumulated since the previous call or reset, then clear
+/// them, so each call covers one measured run.
+/// Returns 0 on success, -1 for null pointers.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_take_bus_counters")]
+pub extern "C" fn emu_take_bus_counters(emu: *mut SyncEmu, out: *mut bus::BusCounters) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let counters = emu.take_bus_counters();
+    unsafe { *out = counters };
+    0
+}
+
+/// Copy the power estimate: modelled supply current over the last run and
+/// by component (CPU, LCD, backlight, flash), the average since reset and
+/// the charge drawn. Rough figures for comparing programs and draining a
+/// virtual battery, not measurements.
+/// Returns 0 on success, -1 for null pointers.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_get_power_stats")]
+pub extern "C" fn emu_get_power_stats(emu: *const SyncEmu, out: *mut power::EmuPowerStats) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    unsafe { *out = emu.power_stats() };
+    0
+}
+
+/// Copy the watchdog pet telemetry (see `crate::watchdog_monitor`).
+/// Returns 0 on success, -1 for null pointers.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_get_watchdog_stats")]
+pub extern "C" fn emu_get_watchdog_stats(emu: *const SyncEmu, out: *mut watchdog_monitor::EmuWatchdogStats) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    unsafe { *out = emu.watchdog_stats() };
+    0
+}
+
+/// Queue EMU_EVENT_WATCHDOG_STALL once the OS goes `ms` emulated ms
+/// without restarting the watchdog; 0 turns detection off.
+/// Returns 0 on success, -1 for a null handle.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_watchdog_stall")]
+pub extern "C" fn emu_set_watchdog_stall(emu: *mut SyncEmu, ms: u32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_watchdog_stall_ms(ms);
+    0
+}
+
+/// Copy the frame counters: LCD frames generated, frames fetched through
+/// emu_framebuffer/emu_framebuffer_scaled, and frames dropped in between.
+/// Returns 0 on success, -1 for null pointers.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_get_frame_counters")]
+pub extern "C" fn emu_get_frame_counters(emu: *const SyncEmu, out: *mut FrameCounters) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    unsafe { *out = emu.frame_counters() };
+    0
 }
 
-/// Get current backend name.
-/// For Rust-only builds, returns "rust".
-#[no_mangle]
-#[cfg(not(feature = "ios_prefixed"))]
-pub extern "C" fn emu_backend_get_current() -> *const c_char {
-    static RUST: &[u8] = b"rust\0";
-    RUST.as_ptr() as *const c_char
-}
+/// Get a pointer to the framebuffer.
+/// The framebuffer is ARGB8888 format, owned by the emulator.
+/// Writes width and height to the provided pointers if non-null.
+/// Returns null if emulator pointer is null.
+///
+/// WARNING: The pixels behind the returned pointer are rewritten by any
+/// call that runs the emulator, with no lock held once this returns; use
+/// emu_copy_framebuffer when another thread may be running it.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_framebuffer")]
+pub extern "C" fn emu_framebuffer(emu: *const SyncEmu, w: *mut i32, h: *mut i32) -> *const u32 {
+    if emu.is_null() {
+        return ptr::null();
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.note_frame_fetched();
+    let (width, height) = emu.framebuffer_size();
+
+    if !w.is_null() {
+        unsafe { *w = width as i32 };
+    }
+    if !h.is_null() {
+        unsafe { *h = height as i32 };
+    }
+
+    emu.framebuffer_ptr()
+}
+
+/// Copy the framebuffer (ARGB8888) into the caller's `dst` of `cap`
+/// pixels, under the emulator lock. Writes the size to `w`/`h` if non-null.
+/// Returns the pixel count; nothing is copied if `cap` is smaller, so a
+/// call with cap 0 queries the size. -1 for null pointers.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_copy_framebuffer")]
+pub extern "C" fn emu_copy_framebuffer(
+    emu: *const SyncEmu,
+    dst: *mut u32,
+    cap: usize,
+    w: *mut i32,
+    h: *mut i32,
+) -> i32 {
+    if emu.is_null() || (dst.is_null() && cap > 0) {
+        return -1;
+    }
+    let (width, height) = (emu::SCREEN_WIDTH, emu::SCREEN_HEIGHT);
+    if !w.is_null() {
+        unsafe { *w = width as i32 };
+    }
+    if !h.is_null() {
+        unsafe { *h = height as i32 };
+    }
+
+    let needed = width * height;
+    if cap >= needed {
+        let sync_emu = unsafe { &*emu };
+        let mut emu = sync_emu.inner.lock().unwrap();
+        emu.note_frame_fetched();
+        let out = unsafe { slice::from_raw_parts_mut(dst, cap) };
+        emu.copy_framebuffer(out);
+    }
+    needed as i32
+}
+
+/// Encode the framebuffer as a PNG file into `out` (`cap` bytes).
+/// Returns the file size; nothing is copied if `cap` is smaller, so a call
+/// with cap 0 queries the size. -1 for null pointers.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_screenshot_png")]
+pub extern "C" fn emu_screenshot_png(emu: *const SyncEmu, out: *mut u8, cap: usize) -> i32 {
+    if emu.is_null() || (out.is_null() && cap > 0) {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let png = sync_emu.inner.lock().unwrap().screenshot_png();
+    if cap >= png.len() {
+        unsafe { ptr::copy_nonoverlapping(png.as_ptr(), out, png.len()) };
+    }
+    png.len() as i32
+}
+
+/// Copy the framebuffer into `out` (ARGB8888, `cap` pixels) scaled by an
+/// integer factor (1-4) and rotated clockwise by `rotation` degrees
+/// (0, 90, 180 or 270). Writes the output size to `w`/`h` if non-null.
+/// Returns the output pixel count; nothing is copied if `cap` is smaller,
+/// so a call with cap 0 queries the size. -1 for null pointers, -30 for a
+/// bad scale or rotation.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_framebuffer_scaled")]
+pub extern "C" fn emu_framebuffer_scaled(
+    emu: *const SyncEmu,
+    scale: i32,
+    rotation: i32,
+    out: *mut u32,
+    cap: usize,
+    w: *mut i32,
+    h: *mut i32,
+) -> i32 {
+    if emu.is_null() || (out.is_null() && cap > 0) {
+        return -1;
+    }
+    let Some(rotation) = u32::try_from(rotation).ok().and_then(scale::Rotation::from_degrees) else {
+        return -30;
+    };
+    let (width, height) = (emu::SCREEN_WIDTH, emu::SCREEN_HEIGHT);
+    let Some((out_w, out_h)) = usize::try_from(scale)
+        .ok()
+        .and_then(|scale| scale::output_size(width, height, scale, rotation))
+    else {
+        return -30;
+    };
+
+    if !w.is_null() {
+        unsafe { *w = out_w as i32 };
+    }
+    if !h.is_null() {
+        unsafe { *h = out_h as i32 };
+    }
+    let needed = out_w * out_h;
+    if cap >= needed {
+        let sync_emu = unsafe { &*emu };
+        let mut emu = sync_emu.inner.lock().unwrap();
+        emu.note_frame_fetched();
+        let out = unsafe { slice::from_raw_parts_mut(out, cap) };
+        emu.framebuffer_scaled(scale as usize, rotation, out);
+    }
+    needed as i32
+}
+
+/// Copy the framebuffer into `out` (ARGB8888, `cap` pixels) with a debug
+/// HUD drawn on top: the PC, LCD frame count and watch values selected by
+/// `flags` (EMU_HUD_* bits). The framebuffer itself is left alone.
+/// Returns 0, -1 for null pointers, -30 for unknown flags or a buffer
+/// smaller than the screen.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_debug_overlay")]
+pub extern "C" fn emu_debug_overlay(emu: *const SyncEmu, flags: u32, out: *mut u32, cap: usize) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+    if flags & !overlay::HUD_ALL != 0 {
+        return -30;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let out = unsafe { slice::from_raw_parts_mut(out, cap) };
+    if !emu.debug_overlay(flags, out) {
+        return -30;
+    }
+    emu.note_frame_fetched();
+    0
+}
+
+/// Get a pointer to the raw frame at the current UPBASE, before color
+/// conversion. `len` receives its size in bytes and `bpp` 8 (palette
+/// indices) or 16 (RGB565, little-endian); either may be null.
+/// Returns null if UPBASE doesn't point at a full frame of RAM.
+/// Valid until the next call that runs or modifies the emulator.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_vram_ptr")]
+pub extern "C" fn emu_vram_ptr(emu: *const SyncEmu, len: *mut usize, bpp: *mut i32) -> *const u8 {
+    if emu.is_null() {
+        return ptr::null();
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let Some(vram) = emu.vram() else {
+        return ptr::null();
+    };
+
+    if !len.is_null() {
+        unsafe { *len = vram.len() };
+    }
+    if !bpp.is_null() {
+        unsafe { *bpp = emu.vram_bpp() as i32 };
+    }
+    vram.as_ptr()
+}
+
+/// Copy the raw frame at the current UPBASE (see emu_vram_ptr) into `out`,
+/// truncated to `cap` bytes.
+/// Returns the full frame size in bytes, -1 for null pointers, or -30 if
+/// UPBASE doesn't point at a full frame of RAM.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_read_vram")]
+pub extern "C" fn emu_read_vram(emu: *const SyncEmu, out: *mut u8, cap: usize) -> i32 {
+    if emu.is_null() || (out.is_null() && cap > 0) {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+
+    let Some(vram) = emu.vram() else {
+        return -30;
+    };
+    let n = vram.len().min(cap);
+    unsafe { ptr::copy_nonoverlapping(vram.as_ptr(), out, n) };
+    vram.len() as i32
+}
+
+/// Choose the color profile applied to the framebuffer from the next
+/// rendered frame on: 0 = raw (CEmu colors, the default), 1 = panel
+/// accurate (the real LCD's washed-out gamut and gamma), 2 = vivid.
+/// Returns 0 on success, -1 for null pointer, -30 for an unknown profile.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_color_profile")]
+pub extern "C" fn emu_set_color_profile(emu: *mut SyncEmu, profile: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let Some(profile) = color::ColorProfile::from_raw(profile as u32) else {
+        return -30;
+    };
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_color_profile(profile);
+    0
+}
+
+/// Current color profile (EMU_COLOR_*), or -1 for null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_get_color_profile")]
+pub extern "C" fn emu_get_color_profile(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.color_profile() as i32
+}
+
+/// Choose the format emu_framebuffer_formatted returns: 0 = ARGB8888 (the
+/// default, no extra work), 1 = RGB565 little-endian, 2 = RGBA8888 bytes
+/// premultiplied. Other formats are converted once per rendered frame.
+/// Returns 0 on success, -1 for null pointer, -30 for an unknown format.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_framebuffer_format")]
+pub extern "C" fn emu_set_framebuffer_format(emu: *mut SyncEmu, format: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let Some(format) = pixel_format::PixelFormat::from_raw(format as u32) else {
+        return -30;
+    };
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_pixel_format(format);
+    0
+}
+
+/// Current framebuffer format (EMU_FORMAT_*), or -1 for null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_get_framebuffer_format")]
+pub extern "C" fn emu_get_framebuffer_format(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.pixel_format() as i32
+}
+
+/// Get a pointer to the framebuffer in the format chosen with
+/// emu_set_framebuffer_format, owned by the emulator. Writes the width,
+/// height and row stride in bytes to the provided pointers if non-null.
+/// Returns null if emulator pointer is null.
+///
+/// WARNING: like emu_framebuffer, only valid until the next call that runs
+/// or modifies the emulator; copy or upload it immediately.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_framebuffer_formatted")]
+pub extern "C" fn emu_framebuffer_formatted(
+    emu: *const SyncEmu,
+    w: *mut i32,
+    h: *mut i32,
+    stride: *mut i32,
+) -> *const c_void {
+    if emu.is_null() {
+        return ptr::null();
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.note_frame_fetched();
+    let (width, height) = emu.framebuffer_size();
+
+    if !w.is_null() {
+        unsafe { *w = width as i32 };
+    }
+    if !h.is_null() {
+        unsafe { *h = height as i32 };
+    }
+    if !stride.is_null() {
+        unsafe { *stride = (width * emu.pixel_format().bytes_per_pixel()) as i32 };
+    }
+
+    emu.formatted_framebuffer_ptr() as *const c_void
+}
+
+/// Render each row as the LCD scans it out (1) instead of snapshotting
+/// VRAM after each run (0, the default), so programs that change the
+/// palette or VRAM mid-frame display as on hardware. Not saved in state.
+/// Returns 0 on success, -1 for null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_scanline_render")]
+pub extern "C" fn emu_set_scanline_render(emu: *mut SyncEmu, enabled: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_scanline_render(enabled != 0);
+    0
+}
+
+/// Whether scanline rendering is on (1) or off (0), or -1 for null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_get_scanline_render")]
+pub extern "C" fn emu_get_scanline_render(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.scanline_render() as i32
+}
+
+/// Set key state.
+/// row: 0-7, col: 0-7
+/// down: non-zero for pressed, zero for released
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_key")]
+pub extern "C" fn emu_set_key(emu: *mut SyncEmu, row: i32, col: i32, down: i32) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_key(row as usize, col as usize, down != 0);
+}
+
+/// Press or release the ON key through its own interrupt and wake line
+/// (see `Emu::set_on_key`). Returns 1 if the press powered on or woke the
+/// calculator, 0 otherwise, -1 for a null handle.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_press_on_key")]
+pub extern "C" fn emu_press_on_key(emu: *mut SyncEmu, down: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_on_key(down != 0) as i32
+}
+
+/// Copy the keypad matrix, 8 bytes each (one per row, bit = column): the
+/// keys held into `keys` and the press edges no scan has seen yet into
+/// `edges`. Either may be null. Returns 0 on success, -1 for a null handle.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_get_key_matrix")]
+pub extern "C" fn emu_get_key_matrix(emu: *const SyncEmu, keys: *mut u8, edges: *mut u8) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    if !keys.is_null() {
+        let out = unsafe { slice::from_raw_parts_mut(keys, 8) };
+        out.copy_from_slice(&emu.key_matrix());
+    }
+    if !edges.is_null() {
+        let out = unsafe { slice::from_raw_parts_mut(edges, 8) };
+        out.copy_from_slice(&emu.key_edges());
+    }
+    0
+}
+
+/// Press the key named `name` (NUL-terminated, case-insensitive, e.g.
+/// "ENTER", "2ND", "Y=", "(-)"; see key_name.rs for the table). Returns 0 on
+/// success, -1 for null pointers, -30 for an unknown name.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_press_key_name")]
+pub extern "C" fn emu_press_key_name(emu: *mut SyncEmu, name: *const c_char) -> i32 {
+    set_key_by_name(emu, name, true)
+}
+
+/// Release the key named `name`. Same names and return codes as
+/// emu_press_key_name.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_release_key_name")]
+pub extern "C" fn emu_release_key_name(emu: *mut SyncEmu, name: *const c_char) -> i32 {
+    set_key_by_name(emu, name, false)
+}
+
+fn set_key_by_name(emu: *mut SyncEmu, name: *const c_char, down: bool) -> i32 {
+    if emu.is_null() || name.is_null() {
+        return -1;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(name) };
+    let Some(key) = name.to_str().ok().and_then(KeyName::from_name) else {
+        return -30;
+    };
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_named_key(key, down);
+    0
+}
+
+/// Evaluate `expr` (NUL-terminated; digits . + - * / ^ ( ) and ~ for
+/// negation) on the homescreen of the running OS and write Ans to `out`.
+/// Runs the emulator while the keys are typed and the OS evaluates.
+/// Returns 0 on success, -1 for null pointers, -10 if no ROM is loaded, -30
+/// for a character with no key, -90 if there is no Ans, -91 if Ans is not
+/// a real number, -92 if the OS reported an error, -93 if no result came
+/// within the cycle budget (see eval.rs).
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_eval")]
+pub extern "C" fn emu_eval(emu: *mut SyncEmu, expr: *const c_char, out: *mut f64) -> i32 {
+    if emu.is_null() || expr.is_null() || out.is_null() {
+        return -1;
+    }
+    let Ok(expr) = unsafe { std::ffi::CStr::from_ptr(expr) }.to_str() else {
+        return -30;
+    };
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match eval::eval(&mut emu, expr) {
+        Ok(value) => {
+            unsafe { *out = value };
+            0
+        }
+        Err(err) => err.code(),
+    }
+}
+
+/// Navigate the running OS to a screen (os_nav::Screen code: 0 home,
+/// 1 mode, 2 MEM menu, 3 about, 4 memory management, 5 reset menu,
+/// 6 catalog, 7 program menu, 8 Python App), pressing the keys for the OS
+/// version in flash. Runs the emulator while the keys are pressed.
+/// Returns the number of keys pressed (0 if the OS lacks the screen), -1
+/// for null pointer, -10 if no ROM is loaded, -30 for an unknown screen.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_nav_open")]
+pub extern "C" fn emu_nav_open(emu: *mut SyncEmu, screen: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let Some(screen) = os_nav::Screen::from_raw(screen as u32) else {
+        return -30;
+    };
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if emu.rom_hash().is_none() {
+        return -10;
+    }
+    os_nav::open(&mut emu, screen) as i32
+}
+
+/// Get the backlight brightness level (0-255).
+/// Returns 0 if emulator pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_get_backlight")]
+pub extern "C" fn emu_get_backlight(emu: *const SyncEmu) -> u8 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.get_backlight()
+}
+
+/// Check if LCD is on (should display content).
+/// Returns 1 if LCD is on, 0 if LCD is off.
+/// LCD is off when either control port 0x05 bit 4 is clear OR lcd.control bit 11 is clear.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_is_lcd_on")]
+pub extern "C" fn emu_is_lcd_on(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    if emu.is_lcd_on() { 1 } else { 0 }
+}
+
+/// Write the homescreen text (see screen_text.rs) as NUL-terminated UTF-8,
+/// one line per row, truncated to `cap` bytes; `out` may be null when cap
+/// is 0. The cursor (row, col) goes to `cursor` if it is not null.
+/// Returns the full text length in bytes (excluding the NUL), or -1 null
+/// pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_screen_text")]
+pub extern "C" fn emu_screen_text(emu: *const SyncEmu, out: *mut c_char, cap: usize, cursor: *mut u8) -> i32 {
+    if emu.is_null() || (out.is_null() && cap > 0) {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+
+    let screen = screen_text::extract(emu.ram_data());
+    if !cursor.is_null() {
+        unsafe {
+            *cursor = screen.cursor.0;
+            *cursor.add(1) = screen.cursor.1;
+        }
+    }
+    let text = screen.to_text();
+    if cap > 0 {
+        // Cut at a character boundary so the output stays valid UTF-8
+        let mut n = text.len().min(cap - 1);
+        while !text.is_char_boundary(n) {
+            n -= 1;
+        }
+        unsafe {
+            ptr::copy_nonoverlapping(text.as_ptr(), out as *mut u8, n);
+            *out.add(n) = 0;
+        }
+    }
+    text.len() as i32
+}
+
+/// Check whether the last frame was rendered from a valid UPBASE.
+/// Returns 1 if valid, 0 if UPBASE was outside RAM and the diagnostic
+/// pattern (magenta/black checkerboard) was drawn instead.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_lcd_upbase_valid")]
+pub extern "C" fn emu_lcd_upbase_valid(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    if emu.upbase_valid() { 1 } else { 0 }
+}
+
+/// Get the effective LCD refresh rate in millihertz, derived from the LCD
+/// timing registers. Returns 0 if the LCD is disabled or emu is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_lcd_refresh_millihz")]
+pub extern "C" fn emu_lcd_refresh_millihz(emu: *const SyncEmu) -> u32 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.lcd_refresh_rate_millihz()
+}
+
+/// Register a callback for emulator events (LCD/backlight on/off), or pass
+/// NULL to clear it. Events carry the emulated cycle count when they happened.
+/// The callback runs on the emulation thread while the emulator is locked,
+/// so it must not call any emu_* function.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_event_callback")]
+pub extern "C" fn emu_set_event_callback(
+    emu: *mut SyncEmu,
+    cb: Option<events::EventCallback>,
+    user: *mut c_void,
+) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_event_callback(cb, user);
+}
+
+/// Call `cb` at each LCD vertical sync with the frame number and 1 if the
+/// frame can differ from the previous one (0 for an identical picture), or
+/// pass NULL to stop. The callback runs inside emu_run_cycles with the
+/// emulator locked, so it must not call any emu_* function; fetch the
+/// framebuffer after the run returns. Returns 0 on success, -1 for null
+/// pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_frame_callback")]
+pub extern "C" fn emu_set_frame_callback(
+    emu: *mut SyncEmu,
+    cb: Option<events::FrameCallback>,
+    user: *mut c_void,
+) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_frame_callback(cb, user);
+    0
+}
+
+/// Report every CPU data read and write in `start..=end` (24-bit, inclusive)
+/// to `cb` with the PC of the instruction that made it, or pass NULL to
+/// stop. Replaces any previous range. The callback runs inside the bus
+/// access with the emulator locked, so it must not call any emu_*
+/// function. Returns 0 on success, -1 for null pointer, -30 if start > end.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_snoop_range")]
+pub extern "C" fn emu_snoop_range(
+    emu: *mut SyncEmu,
+    start: u32,
+    end: u32,
+    cb: Option<snoop::SnoopCallback>,
+    user: *mut c_void,
+) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    if cb.is_some() && (start > end || end > 0xFFFFFF) {
+        return -30;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_snoop(start, end, cb, user);
+    0
+}
+
+/// Stop after any instruction whose data accesses touch `start..=end`
+/// (24-bit, inclusive). `access` is EMU_WATCHPOINT_READ, _WRITE or both
+/// or'd together. The run returns early and EMU_EVENT_WATCHPOINT is queued;
+/// `emu_last_watchpoint` has the access. Instruction fetches and debugger
+/// peeks/pokes never trigger one. Returns the watchpoint id (> 0), -1 for
+/// null pointer, -30 for a bad range or access kind.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_watchpoint_add")]
+pub extern "C" fn emu_watchpoint_add(emu: *mut SyncEmu, start: u32, end: u32, access: u32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let Some(access) = watchpoint::WatchAccess::from_raw(access) else {
+        return -30;
+    };
+    if start > end || end > 0xFFFFFF {
+        return -30;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.add_watchpoint(start, end, access) as i32
+}
+
+/// Remove the watchpoint with this id.
+/// Returns 0 on success, -1 for null pointer, -30 for an unknown id.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_watchpoint_remove")]
+pub extern "C" fn emu_watchpoint_remove(emu: *mut SyncEmu, id: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if id <= 0 || !emu.remove_watchpoint(id as u32) {
+        return -30;
+    }
+    0
+}
+
+/// Remove all watchpoints. Returns 0 on success, -1 for null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_watchpoint_clear")]
+pub extern "C" fn emu_watchpoint_clear(emu: *mut SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.clear_watchpoints();
+    0
+}
+
+/// Check whether the last run stopped at a watchpoint, writing the access
+/// that triggered it to `out` if non-null. Returns 1 if it did, 0 if not,
+/// -1 for null emu.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_last_watchpoint")]
+pub extern "C" fn emu_last_watchpoint(emu: *const SyncEmu, out: *mut snoop::EmuSnoopAccess) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    match emu.last_watchpoint() {
+        Some(hit) => {
+            if !out.is_null() {
+                unsafe { *out = hit };
+            }
+            1
+        }
+        None => 0,
+    }
+}
+
+/// Watch CPU accesses to ports `start..=end`: memory-mapped addresses
+/// (0xE00000-0xFFFFFF) when `space` is EMU_PORT_SPACE_MMIO, IN/OUT port
+/// numbers (0-0xFFFF) when it is EMU_PORT_SPACE_IO. `access` is an
+/// EMU_WATCHPOINT_* kind. Matching accesses go to the callback set with
+/// `emu_port_watch_callback`; with `stop` non-zero the run also returns
+/// after the instruction and EMU_EVENT_PORT_WATCH is queued. Returns the
+/// filter id (> 0), -1 for null pointer, -30 for a bad space, range or kind.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_port_watch_add")]
+pub extern "C" fn emu_port_watch_add(
+    emu: *mut SyncEmu,
+    space: i32,
+    start: u32,
+    end: u32,
+    access: u32,
+    stop: i32,
+) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let (Some(space), Some(access)) = (port_watch::PortSpace::from_raw(space), watchpoint::WatchAccess::from_raw(access))
+    else {
+        return -30;
+    };
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.add_port_watch(space, start, end, access, stop != 0) {
+        Some(id) => id as i32,
+        None => -30,
+    }
+}
+
+/// Remove the port watch with this id.
+/// Returns 0 on success, -1 for null pointer, -30 for an unknown id.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_port_watch_remove")]
+pub extern "C" fn emu_port_watch_remove(emu: *mut SyncEmu, id: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if id <= 0 || !emu.remove_port_watch(id as u32) {
+        return -30;
+    }
+    0
+}
+
+/// Remove all port watches; the callback stays registered.
+/// Returns 0 on success, -1 for null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_port_watch_clear")]
+pub extern "C" fn emu_port_watch_clear(emu: *mut SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.clear_port_watches();
+    0
+}
+
+/// Send every access matching a port watch to `cb`, or pass NULL to stop.
+/// The callback runs inside the bus access with the emulator locked, so it
+/// must not call any emu_* function. Returns 0 on success, -1 for null
+/// pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_port_watch_callback")]
+pub extern "C" fn emu_port_watch_callback(
+    emu: *mut SyncEmu,
+    cb: Option<port_watch::PortWatchCallback>,
+    user: *mut c_void,
+) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_port_watch_callback(cb, user);
+    0
+}
+
+/// Check whether the last run stopped at a port watch, writing the access
+/// that triggered it to `out` if non-null. Returns 1 if it did, 0 if not,
+/// -1 for null emu.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_last_port_watch")]
+pub extern "C" fn emu_last_port_watch(emu: *const SyncEmu, out: *mut port_watch::EmuPortAccess) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    match emu.last_port_watch() {
+        Some(hit) => {
+            if !out.is_null() {
+                unsafe { *out = hit };
+            }
+            1
+        }
+        None => 0,
+    }
+}
+
+/// Pop the oldest queued event into `out`. Returns 1 if an event was
+/// written, 0 if the queue is empty, -1 on null pointer. The queue holds
+/// the most recent `MAX_QUEUED_EVENTS`; older unpolled events are dropped.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_poll_event")]
+pub extern "C" fn emu_poll_event(emu: *mut SyncEmu, out: *mut events::EmuEvent) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.poll_event() {
+        Some(event) => {
+            unsafe { *out = event };
+            1
+        }
+        None => 0,
+    }
+}
+
+/// Get the size needed for a save state buffer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_save_state_size")]
+pub extern "C" fn emu_save_state_size(emu: *const SyncEmu) -> usize {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.save_state_size()
+}
+
+/// Save emulator state to a buffer.
+/// Returns bytes written on success, negative error code on failure.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_save_state")]
+pub extern "C" fn emu_save_state(emu: *const SyncEmu, out: *mut u8, cap: usize) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let buffer = unsafe { slice::from_raw_parts_mut(out, cap) };
+
+    match emu.save_state(buffer) {
+        Ok(size) => size as i32,
+        Err(err) => err.code(),
+    }
+}
+
+/// Load emulator state from a buffer.
+/// Returns 0 on success, negative error code on failure.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_load_state")]
+pub extern "C" fn emu_load_state(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let buffer = unsafe { slice::from_raw_parts(data, len) };
+
+    match emu.load_state(buffer) {
+        Ok(()) => 0,
+        Err(err) => err.code(),
+    }
+}
+
+/// Load a state even if it was saved with a different ROM/OS: RAM,
+/// registers and peripherals are restored, the current flash is kept.
+/// Returns 0 for an exact restore, 1 (warning) when flash was kept, or the
+/// negative emu_load_state error codes other than -104.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_load_state_best_effort")]
+pub extern "C" fn emu_load_state_best_effort(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let buffer = unsafe { slice::from_raw_parts(data, len) };
+
+    match emu.load_state_best_effort(buffer) {
+        Ok(loaded) => loaded as i32,
+        Err(err) => err.code(),
+    }
+}
+
+/// Save a state for bug reports with the flash replaced by sector hashes
+/// (no ROM contents) into `out` (`cap` bytes). Returns the size; nothing
+/// is copied if `cap` is smaller, so a call with cap 0 queries the size.
+/// -1 for null pointers, -10 without a ROM.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_save_redacted_state")]
+pub extern "C" fn emu_save_redacted_state(emu: *const SyncEmu, out: *mut u8, cap: usize) -> i32 {
+    if emu.is_null() || (out.is_null() && cap > 0) {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let bundle = match sync_emu.inner.lock().unwrap().save_redacted_state() {
+        Ok(bundle) => bundle,
+        Err(err) => return err.code(),
+    };
+    if cap >= bundle.len() {
+        unsafe { ptr::copy_nonoverlapping(bundle.as_ptr(), out, bundle.len()) };
+    }
+    bundle.len() as i32
+}
+
+/// Load a redacted state onto the loaded ROM's flash. Returns how many
+/// flash sectors differ from the reporter's (0 for an exact reproduction),
+/// -1 for null pointers, -10 without a ROM, or an emu_load_state error.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_load_redacted_state")]
+pub extern "C" fn emu_load_redacted_state(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let bundle = unsafe { slice::from_raw_parts(data, len) };
+    match emu.load_redacted_state(bundle) {
+        Ok(differing) => differing.len() as i32,
+        Err(err) => err.code(),
+    }
+}
+
+/// Save the current state into in-memory slot `n` (0-9).
+/// Returns the state size in bytes, or -40 bad slot, -42 over the memory
+/// limit, -1 null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_slot_save")]
+pub extern "C" fn emu_slot_save(emu: *mut SyncEmu, n: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.slot_save(n as usize) {
+        Ok(size) => size as i32,
+        Err(err) => err.code(),
+    }
+}
+
+/// Restore the state held in slot `n`.
+/// Returns 0 on success, -41 if the slot is empty, or a load_state error.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_slot_load")]
+pub extern "C" fn emu_slot_load(emu: *mut SyncEmu, n: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.slot_load(n as usize) {
+        Ok(()) => 0,
+        Err(err) => err.code(),
+    }
+}
+
+/// Describe slot `n` into `out`. Returns 0 on success (empty slots report
+/// used = 0), -40 for a bad slot number, -1 on null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_slot_info")]
+pub extern "C" fn emu_slot_info(emu: *const SyncEmu, n: i32, out: *mut slots::EmuSlotInfo) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    match emu.slot_info(n as usize) {
+        Ok(info) => {
+            unsafe { *out = info };
+            0
+        }
+        Err(err) => err.code(),
+    }
+}
+
+/// Free the memory held by slot `n`. Returns 0 on success, -40 bad slot.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_slot_clear")]
+pub extern "C" fn emu_slot_clear(emu: *mut SyncEmu, n: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.slot_clear(n as usize) {
+        Ok(()) => 0,
+        Err(err) => err.code(),
+    }
+}
+
+/// Cap the total bytes all save slots may use (0 = unlimited).
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_slot_set_limit")]
+pub extern "C" fn emu_slot_set_limit(emu: *mut SyncEmu, max_bytes: usize) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_slot_memory_limit(if max_bytes == 0 { None } else { Some(max_bytes) });
+}
+
+/// Find the last instruction that changed the byte at `addr` by replaying
+/// from the save slots (see `last_change`). Returns 1 and fills `out` when
+/// found, 0 if the byte didn't change since the oldest slot; -43 if no slot
+/// is older than the current state, -1 on null pointer, or a load_state
+/// error. The current state is restored afterwards.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_find_last_change")]
+pub extern "C" fn emu_find_last_change(emu: *mut SyncEmu, addr: u32, out: *mut last_change::EmuLastChange) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.find_last_change(addr) {
+        Ok(Some(change)) => {
+            unsafe { *out = change };
+            1
+        }
+        Ok(None) => 0,
+        Err(err) => err.code(),
+    }
+}
+
+/// Like emu_find_last_change, but when found leave the emulator stopped
+/// just after the writing instruction. The current state is saved to slot
+/// `present_slot` first, so emu_slot_load gets back to it. Returns 1, 0
+/// (state restored), -1 on null pointer, -40 for a bad slot, -43 or a
+/// load_state error.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_rewind_to_last_change")]
+pub extern "C" fn emu_rewind_to_last_change(
+    emu: *mut SyncEmu,
+    addr: u32,
+    present_slot: i32,
+    out: *mut last_change::EmuLastChange,
+) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+    let Ok(present_slot) = usize::try_from(present_slot) else {
+        return -40;
+    };
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.rewind_to_last_change(addr, present_slot) {
+        Ok(Some(change)) => {
+            unsafe { *out = change };
+            1
+        }
+        Ok(None) => 0,
+        Err(err) => err.code(),
+    }
+}
+
+/// Read a block of memory in one call, for memory editors.
+/// `flags` bit 0 selects normal bus reads (wait states, port side effects);
+/// otherwise the side-effect-free peek path is used.
+/// Returns 0 on success, -1 for null pointers, -30 if the range leaves the
+/// 24-bit address space.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_read_block")]
+pub extern "C" fn emu_read_block(emu: *const SyncEmu, addr: u32, out: *mut u8, len: usize, flags: i32) -> i32 {
+    if emu.is_null() || (out.is_null() && len > 0) {
+        return -1;
+    }
+    if len == 0 {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let buffer = unsafe { slice::from_raw_parts_mut(out, len) };
+
+    match emu.read_block(addr, buffer, flags & 1 != 0) {
+        Ok(()) => 0,
+        Err(err) => err.code(),
+    }
+}
+
+/// Write a block of memory in one call, for memory editors.
+/// `flags` bit 0 selects normal bus writes (flash locking, memory protection);
+/// otherwise bytes are stored directly.
+/// Returns 0 on success, -1 for null pointers, -30 if the range leaves the
+/// 24-bit address space. Nothing is written when the range is rejected.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_write_block")]
+pub extern "C" fn emu_write_block(emu: *mut SyncEmu, addr: u32, data: *const u8, len: usize, flags: i32) -> i32 {
+    if emu.is_null() || (data.is_null() && len > 0) {
+        return -1;
+    }
+    if len == 0 {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let buffer = unsafe { slice::from_raw_parts(data, len) };
+
+    match emu.write_block(addr, buffer, flags & 1 != 0) {
+        Ok(()) => 0,
+        Err(err) => err.code(),
+    }
+}
+
+/// Copy `len` bytes starting at `addr` into `buf` through the debug peek
+/// path: no cycles charged, no port read side effects (flash, RAM and
+/// peripheral registers as a debugger should see them).
+/// Same as emu_read_block with flags 0; same return codes.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_read_memory")]
+pub extern "C" fn emu_read_memory(emu: *const SyncEmu, addr: u32, buf: *mut u8, len: usize) -> i32 {
+    emu_read_block(emu, addr, buf, len, 0)
+}
+
+/// Store `len` bytes from `buf` starting at `addr` through the debug poke
+/// path: no cycles charged, flash patched directly (no unlock needed).
+/// Same as emu_write_block with flags 0; same return codes.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_write_memory")]
+pub extern "C" fn emu_write_memory(emu: *mut SyncEmu, addr: u32, buf: *const u8, len: usize) -> i32 {
+    emu_write_block(emu, addr, buf, len, 0)
+}
+
+// ============================================================
+// Snippet test runner
+// ============================================================
+
+/// Run a testkit spec (text format, see testkit.rs) on a fresh CPU and bus.
+/// Does not touch any emulator instance. Writes a NUL-terminated report
+/// (one line per mismatch, or the parse error) to `report`, truncated to
+/// `cap` bytes; `report` may be null when cap is 0.
+/// Returns 0 pass, 1 fail, -1 null pointer, -70 spec parse error.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_testkit_run")]
+pub extern "C" fn emu_testkit_run(spec: *const c_char, report: *mut c_char, cap: usize) -> i32 {
+    if spec.is_null() || (report.is_null() && cap > 0) {
+        return -1;
+    }
+    let spec = unsafe { std::ffi::CStr::from_ptr(spec) };
+
+    let (code, text) = match testkit::AsmTest::parse(&spec.to_string_lossy()) {
+        Ok(test) => {
+            let outcome = test.run();
+            (if outcome.passed() { 0 } else { 1 }, outcome.mismatches.join("\n"))
+        }
+        Err(message) => (-70, message),
+    };
+    if cap > 0 {
+        let n = text.len().min(cap - 1);
+        unsafe {
+            ptr::copy_nonoverlapping(text.as_ptr(), report as *mut u8, n);
+            *report.add(n) = 0;
+        }
+    }
+    code
+}
+
+// ============================================================
+// Pattern search/replace
+// ============================================================
+
+/// Parse a C string pattern like "3E ?? C9". Returns None for null/invalid input.
+fn parse_pattern(src: *const c_char) -> Option<search::BytePattern> {
+    if src.is_null() {
+        return None;
+    }
+    let src = unsafe { std::ffi::CStr::from_ptr(src) };
+    search::BytePattern::parse(src.to_str().ok()?)
+}
+
+/// Search flash and RAM in [start, end) for a masked byte pattern
+/// ("3E ?? C9", `?` is a nibble wildcard).
+/// Writes up to `cap` match addresses to `out` (may be null when cap is 0).
+/// Returns the number of matches written, or -1 null pointer,
+/// -30 bad range, -32 invalid pattern.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_search")]
+pub extern "C" fn emu_search(
+    emu: *const SyncEmu,
+    pattern: *const c_char,
+    start: u32,
+    end: u32,
+    out: *mut u32,
+    cap: usize,
+) -> i32 {
+    if emu.is_null() || (out.is_null() && cap > 0) {
+        return -1;
+    }
+    let Some(pattern) = parse_pattern(pattern) else {
+        return -32;
+    };
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    match emu.search_memory(&pattern, start, end, cap) {
+        Ok(matches) => {
+            for (i, &addr) in matches.iter().enumerate() {
+                unsafe { *out.add(i) = addr };
+            }
+            matches.len() as i32
+        }
+        Err(err) => err.code(),
+    }
+}
+
+/// Replace every match of `pattern` in [start, end) with `replacement`
+/// (same length; wildcard nibbles in the replacement keep the original).
+/// `flags` bit 0 confirms patching code that is executing or was just
+/// executed; without it such a request is refused and nothing is written.
+/// Returns the number of matches patched, or -1 null pointer, -30 bad range,
+/// -31 match overlaps executing code, -32 invalid pattern/replacement.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_replace")]
+pub extern "C" fn emu_replace(
+    emu: *mut SyncEmu,
+    pattern: *const c_char,
+    replacement: *const c_char,
+    start: u32,
+    end: u32,
+    flags: i32,
+) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let (Some(pattern), Some(replacement)) = (parse_pattern(pattern), parse_pattern(replacement)) else {
+        return -32;
+    };
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.replace_memory(&pattern, &replacement, start, end, flags & 1 != 0) {
+        Ok(patched) => patched.len() as i32,
+        Err(err) => err.code(),
+    }
+}
+
+// ============================================================
+// Memory map annotations
+// ============================================================
+
+/// Write the memory map (see memory_map.rs for the line format), including
+/// attached extension devices, as NUL-terminated text truncated to `cap`
+/// bytes; `out` may be null when cap is 0.
+/// Returns the full text length (excluding the NUL), or -1 null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_memory_map")]
+pub extern "C" fn emu_memory_map(emu: *const SyncEmu, out: *mut c_char, cap: usize) -> i32 {
+    if emu.is_null() || (out.is_null() && cap > 0) {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+
+    let text = memory_map::to_text(&emu.memory_map());
+    if cap > 0 {
+        let n = text.len().min(cap - 1);
+        unsafe {
+            ptr::copy_nonoverlapping(text.as_ptr(), out as *mut u8, n);
+            *out.add(n) = 0;
+        }
+    }
+    text.len() as i32
+}
+
+/// The memory map as emu_memory_map writes it, as a core-owned string to
+/// release with emu_string_free (no size query needed). Null for a null
+/// pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_memory_map_string")]
+pub extern "C" fn emu_memory_map_string(emu: *const SyncEmu) -> *mut c_char {
+    if emu.is_null() {
+        return ptr::null_mut();
+    }
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    ffi_string::into_raw(memory_map::to_text(&emu.memory_map()))
+}
+
+// ============================================================
+// Execution heatmap
+// ============================================================
+
+/// Enable (non-zero) or disable (zero) the execution heatmap.
+/// Enabling allocates zeroed counters; disabling frees them.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_heatmap_enable")]
+pub extern "C" fn emu_heatmap_enable(emu: *mut SyncEmu, enabled: i32) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_heatmap_enabled(enabled != 0);
+}
+
+/// Zero the heatmap counters.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_heatmap_clear")]
+pub extern "C" fn emu_heatmap_clear(emu: *mut SyncEmu) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.clear_heatmap();
+}
+
+/// Size in bytes of the buffer emu_heatmap_export() fills.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_heatmap_size")]
+pub extern "C" fn emu_heatmap_size() -> usize {
+    heatmap::EXPORT_SIZE
+}
+
+/// Export per-64-byte execution counts: little-endian u32, 65536 flash
+/// buckets then 6496 RAM buckets.
+/// Returns bytes written, -1 for null pointers, -33 if `cap` is smaller
+/// than emu_heatmap_size(), -34 if the heatmap is disabled.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_heatmap_export")]
+pub extern "C" fn emu_heatmap_export(emu: *const SyncEmu, out: *mut u8, cap: usize) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let buffer = unsafe { slice::from_raw_parts_mut(out, cap) };
+
+    match emu.export_heatmap(buffer) {
+        Ok(size) => size as i32,
+        Err(err) => err.code(),
+    }
+}
+
+// ============================================================
+// Opcode coverage
+// ============================================================
+
+/// Enable (non-zero) or disable (zero) per-opcode execution counting.
+/// Enabling allocates zeroed counters; disabling frees them.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_opcode_coverage_enable")]
+pub extern "C" fn emu_opcode_coverage_enable(emu: *mut SyncEmu, enabled: i32) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_opcode_coverage_enabled(enabled != 0);
+}
+
+/// Zero the opcode counters.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_opcode_coverage_clear")]
+pub extern "C" fn emu_opcode_coverage_clear(emu: *mut SyncEmu) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.clear_opcode_coverage();
+}
+
+/// Copy the opcode counters: 5 pages (main, CB, ED, DD/FD, DD/FD CB) of
+/// 256 u32 counts, indexed by opcode.
+/// Returns the number of counts written (1280), -1 for null pointers, -33
+/// if `cap` is smaller than 1280, -34 if coverage is disabled.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_opcode_coverage_export")]
+pub extern "C" fn emu_opcode_coverage_export(emu: *const SyncEmu, out: *mut u32, cap: usize) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let Some(coverage) = emu.opcode_coverage() else {
+        return -34;
+    };
+    let buffer = unsafe { slice::from_raw_parts_mut(out, cap) };
+    match coverage.export(buffer) {
+        Some(count) => count as i32,
+        None => -33,
+    }
+}
+
+/// Write the coverage report (per-page totals, executed NONI opcodes,
+/// opcodes never executed) as NUL-terminated text truncated to `cap`
+/// bytes; `out` may be null when cap is 0.
+/// Returns the full text length (excluding the NUL), -1 for null pointers,
+/// -34 if coverage is disabled.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_opcode_coverage_report")]
+pub extern "C" fn emu_opcode_coverage_report(emu: *const SyncEmu, out: *mut c_char, cap: usize) -> i32 {
+    if emu.is_null() || (out.is_null() && cap > 0) {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let Some(coverage) = emu.opcode_coverage() else {
+        return -34;
+    };
+
+    let text = coverage.report();
+    if cap > 0 {
+        let n = text.len().min(cap - 1);
+        unsafe {
+            ptr::copy_nonoverlapping(text.as_ptr(), out as *mut u8, n);
+            *out.add(n) = 0;
+        }
+    }
+    text.len() as i32
+}
+
+// ============================================================
+// Watch expressions
+// ============================================================
+
+/// Register a watch expression, e.g. "hl", "w[ix+3]" or "l[0xD0257B]".
+/// Watches are re-evaluated when a frame is rendered, once at the end of
+/// each emu_run_cycles/emu_run_frame call, not while instructions run.
+/// Returns the watch id (>=0), or negative error code:
+/// -1 null pointer, -20 invalid expression, -21 too many watches.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_watch_add")]
+pub extern "C" fn emu_watch_add(emu: *mut SyncEmu, expr: *const c_char) -> i32 {
+    if emu.is_null() || expr.is_null() {
+        return -1;
+    }
+
+    let expr = unsafe { std::ffi::CStr::from_ptr(expr) };
+    let Ok(expr) = expr.to_str() else {
+        return -20;
+    };
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.add_watch(expr) {
+        Ok(id) => id as i32,
+        Err(err) => err.code(),
+    }
+}
+
+/// Remove a watch expression.
+/// Returns 0 on success, -1 for a null pointer, -22 for an unknown id.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_watch_remove")]
+pub extern "C" fn emu_watch_remove(emu: *mut SyncEmu, id: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if id >= 0 && emu.remove_watch(id as u32) { 0 } else { -22 }
+}
+
+/// Remove all watch expressions.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_watch_clear")]
+pub extern "C" fn emu_watch_clear(emu: *mut SyncEmu) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.clear_watches();
+}
+
+/// Copy the values from the last rendered frame in one call.
+/// Writes up to `cap` entries to `ids` and `values` (either may be null),
+/// in registration order. Returns the total number of registered watches,
+/// which may exceed `cap`, or -1 for a null emulator pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_watch_values")]
+pub extern "C" fn emu_watch_values(emu: *const SyncEmu, ids: *mut u32, values: *mut u32, cap: usize) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let watches = emu.watch_values();
+    for (i, &(id, value)) in watches.iter().take(cap).enumerate() {
+        unsafe {
+            if !ids.is_null() {
+                *ids.add(i) = id;
+            }
+            if !values.is_null() {
+                *values.add(i) = value;
+            }
+        }
+    }
+    watches.len() as i32
+}
+
+/// Start recording an input macro (keys and power-on with their cycle
+/// offsets, see input_macro.rs), discarding any recording in progress.
+/// Start before emu_power_on so the macro replays from a fresh boot.
+/// Returns 0 on success, -1 for null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_macro_record_start")]
+pub extern "C" fn emu_macro_record_start(emu: *mut SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.start_macro_recording();
+    0
+}
+
+/// Add the current screen's hash to the macro being recorded; playback
+/// must render the same frame at this point. The hash goes to `hash` if
+/// it is not null.
+/// Returns 0 on success, -1 for null pointer, -95 if not recording.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_macro_checkpoint")]
+pub extern "C" fn emu_macro_checkpoint(emu: *mut SyncEmu, hash: *mut u64) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.macro_checkpoint() {
+        Some(frame) => {
+            if !hash.is_null() {
+                unsafe { *hash = frame };
+            }
+            0
+        }
+        None => -95,
+    }
+}
+
+/// Write the macro recorded so far as NUL-terminated text, truncated to
+/// `cap` bytes (`out` may be null when cap is 0); `stop` != 0 also ends the
+/// recording. Returns the full text length in bytes (excluding the NUL),
+/// -1 for null pointers, -95 if not recording.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_macro_text")]
+pub extern "C" fn emu_macro_text(emu: *mut SyncEmu, out: *mut c_char, cap: usize, stop: i32) -> i32 {
+    if emu.is_null() || (out.is_null() && cap > 0) {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let recorded = if stop != 0 { emu.stop_macro_recording() } else { emu.recorded_macro() };
+    let Some(recorded) = recorded else {
+        return -95;
+    };
+    // Plain ASCII, so any cut is a character boundary
+    let text = recorded.to_string();
+    if cap > 0 {
+        let n = text.len().min(cap - 1);
+        unsafe {
+            ptr::copy_nonoverlapping(text.as_ptr(), out as *mut u8, n);
+            *out.add(n) = 0;
+        }
+    }
+    text.len() as i32
+}
+
+/// Start (enabled != 0) or stop collecting per-frame peripheral register
+/// diffs. Each frame that changed registers emits EMU_EVENT_MMIO_DIFF.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_mmio_diff_enable")]
+pub extern "C" fn emu_mmio_diff_enable(emu: *mut SyncEmu, enabled: i32) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_mmio_diff_enabled(enabled != 0);
+}
+
+/// Copy up to `cap` registers changed during the last frame to `out` (may
+/// be null to query the count). Returns the total number of changed
+/// registers, which may exceed `cap`, or -1 for a null emulator pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_mmio_diff")]
+pub extern "C" fn emu_mmio_diff(emu: *const SyncEmu, out: *mut mmio_diff::MmioChange, cap: usize) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let changes = emu.mmio_diff();
+    if !out.is_null() {
+        for (i, change) in changes.iter().take(cap).enumerate() {
+            unsafe { *out.add(i) = *change };
+        }
+    }
+    changes.len() as i32
+}
+
+/// Stop emu_run_cycles when an interrupt source in `raise` has its status
+/// raised or one in `ack` is acknowledged by the OS (masks of interrupt
+/// source bits, 0 for none). The stop emits EMU_EVENT_INTERRUPT_WATCH
+/// naming the source and whether it was raised or acknowledged.
+/// Returns 0 on success, -1 for null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_interrupt_watch")]
+pub extern "C" fn emu_set_interrupt_watch(emu: *mut SyncEmu, raise: u32, ack: u32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_interrupt_watch(raise, ack);
+    0
+}
+
+/// Copy per-source interrupt statistics (raises, acknowledges, total and
+/// longest raise-to-acknowledge cycles, cycle of the last raise) since
+/// reset or emu_clear_interrupt_stats into `out`, indexed by source bit.
+/// Returns the number of sources written (22), -1 for null pointer, -33 if
+/// `cap` is smaller than 22.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_get_interrupt_stats")]
+pub extern "C" fn emu_get_interrupt_stats(
+    emu: *const SyncEmu,
+    out: *mut peripherals::interrupt::EmuInterruptStats,
+    cap: usize,
+) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let stats = emu.interrupt_stats();
+    if cap < stats.len() {
+        return -33;
+    }
+    let buffer = unsafe { slice::from_raw_parts_mut(out, stats.len()) };
+    buffer.copy_from_slice(stats);
+    stats.len() as i32
+}
+
+/// Zero the interrupt statistics.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_clear_interrupt_stats")]
+pub extern "C" fn emu_clear_interrupt_stats(emu: *mut SyncEmu) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.clear_interrupt_stats();
+}
+
+/// Measure emulated time between a start and an end hook (see
+/// benchmark.rs). Each kind is 1 for a PC about to execute or 2 for a CPU
+/// store to a port address (0xE00000+); a start kind of 0 stops
+/// benchmarking. Replaces the previous hooks and discards their results.
+/// Returns 0 on success, -1 for null pointer, -30 for a bad kind/address.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_benchmark_set")]
+pub extern "C" fn emu_benchmark_set(
+    emu: *mut SyncEmu,
+    start_kind: i32,
+    start_addr: u32,
+    end_kind: i32,
+    end_addr: u32,
+) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let hooks = if start_kind == 0 {
+        None
+    } else {
+        match (benchmark::Hook::from_raw(start_kind, start_addr), benchmark::Hook::from_raw(end_kind, end_addr)) {
+            (Some(start), Some(end)) => Some((start, end)),
+            _ => return -30,
+        }
+    };
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_benchmark(hooks);
+    0
+}
+
+/// Copy up to `cap` completed benchmark durations (nanoseconds of emulated
+/// time, oldest first) to `out` (may be null to query the count). Returns
+/// the total number of results, or -1 for a null emulator pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_benchmark_results")]
+pub extern "C" fn emu_benchmark_results(emu: *const SyncEmu, out: *mut u64, cap: usize) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let results = emu.benchmark_results();
+    if !out.is_null() {
+        for (i, nanos) in results.iter().take(cap).enumerate() {
+            unsafe { *out.add(i) = *nanos };
+        }
+    }
+    results.len() as i32
+}
+
+// ============================================================
+// Backend API (for single-backend builds without bridge)
+// ============================================================
+
+/// Get available backends (comma-separated list).
+/// For Rust-only builds, returns "rust".
+#[no_mangle]
+#[cfg(not(feature = "ios_prefixed"))]
+pub extern "C" fn emu_backend_get_available() -> *const c_char {
+    static BACKENDS: &[u8] = b"rust\0";
+    BACKENDS.as_ptr() as *const c_char
+}
+
+/// Get current backend name.
+/// For Rust-only builds, returns "rust".
+#[no_mangle]
+#[cfg(not(feature = "ios_prefixed"))]
+pub extern "C" fn emu_backend_get_current() -> *const c_char {
+    static RUST: &[u8] = b"rust\0";
+    RUST.as_ptr() as *const c_char
+}
+
+/// Set backend by name.
+/// For Rust-only builds, only "rust" is valid.
+/// Returns 0 on success, -1 on failure.
+#[no_mangle]
+#[cfg(not(feature = "ios_prefixed"))]
+pub extern "C" fn emu_backend_set(name: *const c_char) -> i32 {
+    if name.is_null() {
+        return -1;
+    }
+    let name_str = unsafe { std::ffi::CStr::from_ptr(name) };
+    if name_str.to_bytes() == b"rust" {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Get number of available backends.
+/// For Rust-only builds, returns 1.
+#[no_mangle]
+#[cfg(not(feature = "ios_prefixed"))]
+pub extern "C" fn emu_backend_count() -> i32 {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_destroy() {
+        let emu = emu_create();
+        assert!(!emu.is_null());
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_framebuffer() {
+        let emu = emu_create();
+        let mut w: i32 = 0;
+        let mut h: i32 = 0;
+        let fb = emu_framebuffer(emu, &mut w, &mut h);
+
+        assert!(!fb.is_null());
+        assert_eq!(w, 320);
+        assert_eq!(h, 240);
+
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_run_cycles() {
+        let emu = emu_create();
+        // Without ROM, should return 0
+        let executed = emu_run_cycles(emu, 1000);
+        assert_eq!(executed, 0);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_key_input() {
+        let emu = emu_create();
+        emu_set_key(emu, 0, 0, 1);
+        emu_set_key(emu, 0, 0, 0);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_thread_safety() {
+        use std::thread;
+
+        let emu = emu_create();
+
+        // Load a minimal ROM so we can run cycles
+        let rom = vec![0x00, 0x00, 0x76]; // NOP, NOP, HALT
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+
+        // Wrap in Arc for sharing across threads
+        let emu_ptr = emu as usize; // Convert to usize for Send
+
+        // Spawn threads that access the emulator concurrently
+        let handles: Vec<_> = (0..4).map(|i| {
+            thread::spawn(move || {
+                let emu = emu_ptr as *mut SyncEmu;
+                for _ in 0..100 {
+                    if i % 2 == 0 {
+                        emu_set_key(emu, (i % 8) as i32, 0, 1);
+                        emu_set_key(emu, (i % 8) as i32, 0, 0);
+                    } else {
+                        emu_run_cycles(emu, 10);
+                    }
+                }
+            })
+        }).collect();
+
+        // Wait for all threads
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_block_transfer() {
+        let emu = emu_create();
+        let data = [0x11u8, 0x22, 0x33, 0x44];
+        assert_eq!(emu_write_block(emu, 0xD00100, data.as_ptr(), data.len(), 0), 0);
+
+        let mut out = [0u8; 4];
+        assert_eq!(emu_read_block(emu, 0xD00100, out.as_mut_ptr(), out.len(), 0), 0);
+        assert_eq!(out, data);
+        out = [0; 4];
+        assert_eq!(emu_read_block(emu, 0xD00100, out.as_mut_ptr(), out.len(), 1), 0);
+        assert_eq!(out, data);
+
+        // Ranges past the end of the address space are rejected whole
+        assert_eq!(emu_read_block(emu, 0xFFFFFE, out.as_mut_ptr(), out.len(), 0), -30);
+        assert_eq!(emu_write_block(emu, 0x1000000, data.as_ptr(), 1, 0), -30);
+        assert_eq!(emu_read_block(emu, 0xFFFFFC, out.as_mut_ptr(), out.len(), 0), 0);
+        assert_eq!(emu_read_block(emu, 0, std::ptr::null_mut(), 0, 0), 0);
+        assert_eq!(emu_read_block(emu, 0, std::ptr::null_mut(), 1, 0), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_memory_ffi_charges_no_cycles() {
+        let emu = emu_create();
+        let rom = [0x00u8, 0x00, 0x18, 0xFE];
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        let cycles = unsafe { &*emu }.inner.lock().unwrap().total_cycles();
+
+        // Flash is patched directly, RAM written as is
+        let patch = [0xC9u8, 0x3E];
+        assert_eq!(emu_write_memory(emu, 0x000010, patch.as_ptr(), patch.len()), 0);
+        assert_eq!(emu_write_memory(emu, 0xD00000, patch.as_ptr(), patch.len()), 0);
+        let mut out = [0u8; 2];
+        assert_eq!(emu_read_memory(emu, 0x000010, out.as_mut_ptr(), out.len()), 0);
+        assert_eq!(out, patch);
+        assert_eq!(emu_read_memory(emu, 0xD00000, out.as_mut_ptr(), out.len()), 0);
+        assert_eq!(out, patch);
+        assert_eq!(unsafe { &*emu }.inner.lock().unwrap().total_cycles(), cycles);
+
+        assert_eq!(emu_read_memory(emu, 0xFFFFFF, out.as_mut_ptr(), out.len()), -30);
+        assert_eq!(emu_write_memory(std::ptr::null_mut(), 0, patch.as_ptr(), 1), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_search_replace_ffi() {
+        let emu = emu_create();
+        let code = [0xCD, 0x34, 0x12, 0x00];
+        emu_write_block(emu, 0xD00400, code.as_ptr(), code.len(), 0);
+
+        let mut found = [0u32; 4];
+        let n = emu_search(emu, c"CD ?? 12".as_ptr(), 0xD00000, 0xD65800, found.as_mut_ptr(), found.len());
+        assert_eq!(n, 1);
+        assert_eq!(found[0], 0xD00400);
+        assert_eq!(emu_search(emu, c"CD ?".as_ptr(), 0, 0x1000000, found.as_mut_ptr(), 4), -32);
+
+        assert_eq!(emu_replace(emu, c"CD ?? 12".as_ptr(), c"00 00 00".as_ptr(), 0xD00000, 0xD65800, 0), 1);
+        let mut out = [0xFFu8; 3];
+        emu_read_block(emu, 0xD00400, out.as_mut_ptr(), 3, 0);
+        assert_eq!(out, [0, 0, 0]);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_testkit_run_ffi() {
+        let mut report = [0 as c_char; 64];
+        let pass = c"pc D00000\nadl 1\ncode D00000 3E 07 76\nexpect A=07";
+        assert_eq!(emu_testkit_run(pass.as_ptr(), report.as_mut_ptr(), report.len()), 0);
+        assert_eq!(report[0], 0);
+
+        let fail = c"pc D00000\nadl 1\ncode D00000 3E 07 76\nexpect A=08";
+        assert_eq!(emu_testkit_run(fail.as_ptr(), report.as_mut_ptr(), report.len()), 1);
+        let text = unsafe { std::ffi::CStr::from_ptr(report.as_ptr()) };
+        assert_eq!(text.to_str().unwrap(), "A: expected 8, got 7");
+
+        assert_eq!(emu_testkit_run(c"bogus".as_ptr(), report.as_mut_ptr(), 5), -70);
+        assert_eq!(unsafe { std::ffi::CStr::from_ptr(report.as_ptr()) }.to_bytes(), b"line");
+        assert_eq!(emu_testkit_run(std::ptr::null(), std::ptr::null_mut(), 0), -1);
+    }
+
+    #[test]
+    fn test_memory_map_ffi() {
+        let emu = emu_create();
+        let len = emu_memory_map(emu, std::ptr::null_mut(), 0);
+        assert!(len > 0);
+        let mut text = vec![0 as c_char; len as usize + 1];
+        assert_eq!(emu_memory_map(emu, text.as_mut_ptr(), text.len()), len);
+        let text = unsafe { std::ffi::CStr::from_ptr(text.as_ptr()) }.to_str().unwrap();
+        assert!(text.lines().any(|line| line == "D40000 D65800 vram vram"));
+        assert_eq!(emu_memory_map(std::ptr::null(), std::ptr::null_mut(), 0), -1);
+
+        let owned = emu_memory_map_string(emu);
+        assert_eq!(unsafe { std::ffi::CStr::from_ptr(owned) }.to_str(), Ok(text));
+        emu_string_free(owned);
+        assert!(emu_memory_map_string(std::ptr::null()).is_null());
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_disasm_ffi() {
+        let emu = emu_create();
+        assert!(emu_disasm(std::ptr::null(), 0, 1).is_null());
+        assert!(emu_disasm(emu, 0, 65537).is_null());
+
+        // Z80 mode out of reset
+        let rom = vec![0x3E, 0x05, 0x01, 0x34, 0x12, 0xC9];
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        let text = emu_disasm(emu, 0, 3);
+        assert_eq!(
+            unsafe { std::ffi::CStr::from_ptr(text) }.to_str(),
+            Ok("000000: 3E 05             LD A,0x05\n\
+                000002: 01 34 12          LD BC,0x1234\n\
+                000005: C9                RET\n")
+        );
+        emu_destroy(emu);
+        // Owned by the caller, not the emulator
+        assert_eq!(unsafe { *text }, b'0' as c_char);
+        emu_string_free(text);
+        emu_string_free(text);
+        emu_string_free(std::ptr::null_mut());
+    }
+
+    #[test]
+    fn test_vram_ffi() {
+        let emu = emu_create();
+        let pixel = [0x1F, 0xF8];
+        assert_eq!(emu_write_block(emu, 0xD40000, pixel.as_ptr(), 2, 0), 0);
+
+        let (mut len, mut bpp) = (0usize, 0i32);
+        let vram = emu_vram_ptr(emu, &mut len, &mut bpp);
+        assert!(!vram.is_null());
+        assert_eq!((len, bpp), (320 * 240 * 2, 16));
+        assert_eq!(unsafe { std::slice::from_raw_parts(vram, 2) }, &pixel);
+
+        let mut out = [0u8; 4];
+        assert_eq!(emu_read_vram(emu, out.as_mut_ptr(), out.len()), len as i32);
+        assert_eq!(out, [0x1F, 0xF8, 0, 0]);
+        assert_eq!(emu_read_vram(emu, std::ptr::null_mut(), 0), len as i32);
+
+        // UPBASE = 0xD60000: the frame would run past the end of RAM
+        assert_eq!(emu_write_block(emu, 0xE30012, [0xD6].as_ptr(), 1, 0), 0);
+        assert!(emu_vram_ptr(emu, std::ptr::null_mut(), std::ptr::null_mut()).is_null());
+        assert_eq!(emu_read_vram(emu, out.as_mut_ptr(), out.len()), -30);
+        assert_eq!(emu_read_vram(std::ptr::null(), out.as_mut_ptr(), out.len()), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_frame_counters_ffi() {
+        let emu = emu_create();
+        let mut counters = FrameCounters { generated: 9, ..Default::default() };
+        assert_eq!(emu_get_frame_counters(emu, &mut counters), 0);
+        assert_eq!(counters, FrameCounters::default(), "LCD off: no frames");
+        emu_framebuffer(emu, std::ptr::null_mut(), std::ptr::null_mut());
+        assert_eq!(emu_get_frame_counters(emu, &mut counters), 0);
+        assert_eq!(counters.fetched, 0, "nothing new to fetch");
+        assert_eq!(emu_get_frame_counters(emu, std::ptr::null_mut()), -1);
+        assert_eq!(emu_get_frame_counters(std::ptr::null(), &mut counters), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_key_name_ffi() {
+        let emu = emu_create();
+        let rom = test_rom::build();
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        emu_run_cycles(emu, 5_000_000);
+
+        // The test ROM copies the keypad data registers to KEYS (two bytes per row)
+        let enter_row = test_rom::KEYS + 2 * KeyName::Enter.position().0 as u32;
+        let mut data = 0u8;
+        assert_eq!(emu_press_key_name(emu, c"enter".as_ptr()), 0);
+        emu_run_cycles(emu, 1_000_000);
+        emu_read_memory(emu, enter_row, &mut data, 1);
+        assert_eq!(data & 1, 1, "ENTER is row 6, bit 0");
+
+        assert_eq!(emu_release_key_name(emu, c"ENTER".as_ptr()), 0);
+        emu_run_cycles(emu, 1_000_000);
+        emu_read_memory(emu, enter_row, &mut data, 1);
+        assert_eq!(data & 1, 0);
+
+        assert_eq!(emu_press_key_name(emu, c"F1".as_ptr()), -30);
+        assert_eq!(emu_press_key_name(emu, ptr::null()), -1);
+        assert_eq!(emu_release_key_name(ptr::null_mut(), c"UP".as_ptr()), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_load_demo_ffi() {
+        let emu = emu_create();
+        assert_eq!(emu_load_demo(emu, 8, 0), -30);
+        assert_eq!(emu_load_demo(emu, 0, 61), -30);
+        assert_eq!(emu_load_demo(ptr::null_mut(), 0, 0), -1);
+
+        assert_eq!(emu_load_demo(emu, 0, 0), 0);
+        emu_power_on(emu);
+        emu_run_cycles(emu, 3_000_000);
+        let mut ready = 0u8;
+        emu_read_memory(emu, test_rom::READY, &mut ready, 1);
+        assert_eq!(ready, test_rom::READY_MAGIC);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_find_last_change_ffi() {
+        let emu = emu_create();
+        let rom = test_rom::build();
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        let mut change = last_change::EmuLastChange::default();
+        assert_eq!(emu_find_last_change(emu, test_rom::TICKS, &mut change), -43);
+
+        assert!(emu_slot_save(emu, 1) > 0);
+        emu_run_cycles(emu, 5_000_000);
+        let cycles = || unsafe { &*emu }.inner.lock().unwrap().total_cycles();
+        let now = cycles();
+        assert_eq!(emu_find_last_change(emu, test_rom::READY, &mut change), 1);
+        assert_eq!((change.new_value, change.slot), (test_rom::READY_MAGIC, 1));
+        assert_eq!(cycles(), now, "present restored");
+        assert_eq!(emu_find_last_change(emu, test_rom::READY, ptr::null_mut()), -1);
+
+        assert_eq!(emu_rewind_to_last_change(emu, test_rom::READY, 2, &mut change), 1);
+        assert!(cycles() < now, "stopped at the write");
+        assert_eq!(emu_slot_load(emu, 2), 0);
+        assert_eq!(cycles(), now);
+        assert_eq!(emu_rewind_to_last_change(emu, test_rom::READY, -1, &mut change), -40);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_frame_callback_ffi() {
+        extern "C" fn count_frames(user: *mut c_void, _frame: u64, _dirty: u32) {
+            unsafe { *(user as *mut u32) += 1 };
+        }
+
+        let emu = emu_create();
+        let mut frames = 0u32;
+        assert_eq!(emu_set_frame_callback(emu, Some(count_frames), &mut frames as *mut _ as *mut c_void), 0);
+        let rom = test_rom::build();
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        emu_run_cycles(emu, 1_000_000);
+        assert!(frames > 0, "LCD should be scanning out");
+
+        assert_eq!(emu_set_frame_callback(emu, None, ptr::null_mut()), 0);
+        assert_eq!(emu_set_frame_callback(ptr::null_mut(), None, ptr::null_mut()), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_copy_framebuffer_ffi() {
+        let emu = emu_create();
+        let rom = test_rom::build();
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        emu_run_cycles(emu, 5_000_000);
+
+        let (mut w, mut h) = (0, 0);
+        assert_eq!(emu_copy_framebuffer(emu, ptr::null_mut(), 0, &mut w, &mut h), 320 * 240);
+        assert_eq!((w, h), (320, 240));
+        let mut out = vec![0u32; 320 * 240 + 1];
+        assert_eq!(emu_copy_framebuffer(emu, out.as_mut_ptr(), out.len(), ptr::null_mut(), ptr::null_mut()), 320 * 240);
+        let (mut fw, mut fh) = (0, 0);
+        let pixels = unsafe { slice::from_raw_parts(emu_framebuffer(emu, &mut fw, &mut fh), 320 * 240) };
+        assert_eq!(&out[..320 * 240], pixels);
+        assert_ne!(out[320 * 239], 0xFF000000, "test ROM fill copied");
+        assert_eq!(out[320 * 240], 0, "nothing past the frame");
+
+        let mut short = vec![1u32; 16];
+        assert_eq!(emu_copy_framebuffer(emu, short.as_mut_ptr(), short.len(), &mut w, &mut h), 320 * 240);
+        assert_eq!(short, vec![1; 16]);
+        assert_eq!(emu_copy_framebuffer(emu, ptr::null_mut(), 4, &mut w, &mut h), -1);
+        assert_eq!(emu_copy_framebuffer(ptr::null(), ptr::null_mut(), 0, &mut w, &mut h), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_screenshot_png_ffi() {
+        let emu = emu_create();
+        let rom = test_rom::build();
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        emu_run_cycles(emu, 5_000_000);
+
+        let len = emu_screenshot_png(emu, ptr::null_mut(), 0);
+        assert!(len > 0 && (len as usize) < 320 * 240 * 3, "compressed: {len}");
+        let mut short = vec![0u8; len as usize - 1];
+        assert_eq!(emu_screenshot_png(emu, short.as_mut_ptr(), short.len()), len);
+        assert!(short.iter().all(|&b| b == 0));
+
+        let mut file = vec![0u8; len as usize];
+        assert_eq!(emu_screenshot_png(emu, file.as_mut_ptr(), file.len()), len);
+        assert_eq!(&file[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&file[12..24], b"IHDR\0\0\x01\x40\0\0\0\xF0");
+        let sync_emu = unsafe { &*emu };
+        assert_eq!(file, sync_emu.inner.lock().unwrap().screenshot_png());
+
+        assert_eq!(emu_screenshot_png(emu, ptr::null_mut(), 4), -1);
+        assert_eq!(emu_screenshot_png(ptr::null(), ptr::null_mut(), 0), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_framebuffer_scaled_ffi() {
+        let emu = emu_create();
+        let (mut w, mut h) = (0, 0);
+        assert_eq!(emu_framebuffer_scaled(emu, 2, 90, std::ptr::null_mut(), 0, &mut w, &mut h), 640 * 480);
+        assert_eq!((w, h), (480, 640));
+
+        let mut out = vec![0u32; 640 * 480];
+        assert_eq!(emu_framebuffer_scaled(emu, 2, 90, out.as_mut_ptr(), out.len(), &mut w, &mut h), 640 * 480);
+        assert!(out.iter().all(|&p| p == 0xFF000000), "fresh framebuffer is black");
+
+        // Too small: size reported, nothing copied
+        let mut short = vec![1u32; 16];
+        assert_eq!(emu_framebuffer_scaled(emu, 1, 0, short.as_mut_ptr(), short.len(), &mut w, &mut h), 320 * 240);
+        assert_eq!(short, vec![1; 16]);
+
+        assert_eq!(emu_framebuffer_scaled(emu, 5, 0, std::ptr::null_mut(), 0, &mut w, &mut h), -30);
+        assert_eq!(emu_framebuffer_scaled(emu, 1, 45, std::ptr::null_mut(), 0, &mut w, &mut h), -30);
+        assert_eq!(emu_framebuffer_scaled(emu, 1, -90, std::ptr::null_mut(), 0, &mut w, &mut h), -30);
+        assert_eq!(emu_framebuffer_scaled(emu, 1, 0, std::ptr::null_mut(), 4, &mut w, &mut h), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_run_granularity_ffi() {
+        let emu = emu_create();
+        assert_eq!(emu_set_run_granularity(emu, 1, 500), 0);
+        assert_eq!(unsafe { &*emu }.inner.lock().unwrap().run_granularity(), RunGranularity::Cycles(500));
+        assert_eq!(emu_set_run_granularity(emu, 2, 0), 0);
+        assert_eq!(emu_set_run_granularity(emu, 1, 0), -30);
+        assert_eq!(emu_set_run_granularity(emu, 4, 0), -30);
+        assert_eq!(unsafe { &*emu }.inner.lock().unwrap().run_granularity(), RunGranularity::EventDriven);
+        assert_eq!(emu_set_run_granularity(std::ptr::null_mut(), 0, 0), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_scanline_render_ffi() {
+        let emu = emu_create();
+        assert_eq!(emu_get_scanline_render(emu), 0);
+        assert_eq!(emu_set_scanline_render(emu, 1), 0);
+        assert_eq!(emu_get_scanline_render(emu), 1);
+        emu_reset(emu);
+        assert_eq!(emu_get_scanline_render(emu), 1);
+        assert_eq!(emu_set_scanline_render(emu, 0), 0);
+        assert_eq!(emu_get_scanline_render(emu), 0);
+        assert_eq!(emu_set_scanline_render(std::ptr::null_mut(), 1), -1);
+        assert_eq!(emu_get_scanline_render(std::ptr::null()), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_color_profile_ffi() {
+        let emu = emu_create();
+        assert_eq!(emu_get_color_profile(emu), 0);
+        assert_eq!(emu_set_color_profile(emu, 1), 0);
+        assert_eq!(emu_get_color_profile(emu), 1);
+        assert_eq!(emu_set_color_profile(emu, 3), -30);
+        assert_eq!(emu_set_color_profile(emu, -1), -30);
+        assert_eq!(emu_get_color_profile(emu), 1);
+        assert_eq!(emu_set_color_profile(std::ptr::null_mut(), 0), -1);
+        assert_eq!(emu_get_color_profile(std::ptr::null()), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_framebuffer_format_ffi() {
+        let emu = emu_create();
+        let (mut w, mut h, mut stride) = (0, 0, 0);
+        let argb = emu_framebuffer_formatted(emu, &mut w, &mut h, &mut stride);
+        assert_eq!(argb as *const u32, emu_framebuffer(emu, std::ptr::null_mut(), std::ptr::null_mut()));
+        assert_eq!((w, h, stride), (320, 240, 1280));
+
+        assert_eq!(emu_set_framebuffer_format(emu, 1), 0);
+        assert_eq!(emu_get_framebuffer_format(emu), 1);
+        let rgb565 = emu_framebuffer_formatted(emu, &mut w, &mut h, &mut stride) as *const u16;
+        assert_eq!(stride, 640);
+        assert_eq!(unsafe { *rgb565.add(320 * 240 - 1) }, 0, "fresh framebuffer is black");
+
+        assert_eq!(emu_set_framebuffer_format(emu, 3), -30);
+        assert_eq!(emu_get_framebuffer_format(emu), 1);
+        assert_eq!(emu_set_framebuffer_format(std::ptr::null_mut(), 0), -1);
+        assert!(emu_framebuffer_formatted(std::ptr::null(), &mut w, &mut h, &mut stride).is_null());
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_perf_stats() {
+        let emu = emu_create();
+        let mut stats = perf::EmuPerfStats { frames: 99, ..Default::default() };
+        assert_eq!(emu_get_perf_stats(emu, &mut stats), 0);
+        assert_eq!(stats, perf::EmuPerfStats::default());
+        assert_eq!(emu_get_perf_stats(emu, std::ptr::null_mut()), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_reset_cause_ffi() {
+        let emu = emu_create();
+        assert_eq!(emu_get_reset_cause(emu), ResetCause::PowerOn as i32);
+        emu_reset(emu);
+        assert_eq!(emu_get_reset_cause(emu), ResetCause::Frontend as i32);
+        assert_eq!(emu_reset_combo(emu, ResetCombo::ReinstallOs as i32), 0);
+        assert_eq!(emu_get_reset_cause(emu), ResetCause::KeyCombo as i32);
+        assert_eq!(emu_reset_combo(emu, 3), -30);
+        assert_eq!(emu_reset_kind(emu, ResetKind::PowerCycle as i32), 0);
+        assert_eq!(emu_get_reset_cause(emu), ResetCause::PowerCycle as i32);
+        assert_eq!(emu_reset_kind(emu, 3), -30);
+        assert_eq!(emu_reset_kind(std::ptr::null_mut(), 0), -1);
+        assert_eq!(emu_reset_combo(std::ptr::null_mut(), 0), -1);
+        assert_eq!(emu_get_reset_cause(std::ptr::null()), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_crash_report_survives_panic() {
+        let emu = emu_create();
+        assert!(emu_crash_report(emu).is_null());
+        let sync_emu = unsafe { &*emu };
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            run_host_frame(sync_emu, |_| panic!("emulator bug"))
+        }));
+        assert!(result.is_err());
+
+        let report = emu_crash_report(emu);
+        assert!(!report.is_null());
+        let text = unsafe { std::ffi::CStr::from_ptr(report) }.to_str().unwrap().to_owned();
+        assert!(text.starts_with("reset_cause=PowerOn "), "{}", text);
+        emu_string_free(report);
+        assert!(emu_crash_report(std::ptr::null()).is_null());
+        // The emulator lock is poisoned; free the handle without taking it
+        drop(unsafe { Box::from_raw(emu) });
+    }
+
+    #[test]
+    fn test_pause_ffi() {
+        let emu = emu_create();
+        assert_eq!(emu_set_paused(emu, 1), 0);
+        assert_eq!(emu_is_paused(emu), 1);
+        assert_eq!(emu_run_cycles(emu, 1_000), 0);
+        assert_eq!(emu_sync_host_time(emu, 1_000), 0);
+        assert_eq!(emu_set_paused(emu, 0), 0);
+        assert_eq!(emu_is_paused(emu), 0);
+        assert_eq!(emu_set_paused(std::ptr::null_mut(), 1), -1);
+        assert_eq!(emu_sync_host_time(std::ptr::null_mut(), 0), -1);
+        assert_eq!(emu_set_rtc_host_time(emu, 1), 0);
+        assert_eq!(unsafe { &*emu }.inner.lock().unwrap().rtc_clock(), peripherals::rtc::RtcClock::HostTime);
+        assert_eq!(emu_set_rtc_host_time(std::ptr::null_mut(), 1), -1);
+        assert_eq!(emu_set_rtc_drift(emu, -50), 0);
+        assert_eq!(unsafe { &*emu }.inner.lock().unwrap().rtc_drift_ppm(), -50);
+        assert_eq!(emu_set_rtc_drift(emu, 20_000), -30);
+        assert_eq!(emu_set_rtc_drift(std::ptr::null_mut(), 0), -1);
+        assert_eq!(emu_set_rtc_trim(emu, -105), 0);
+        assert_eq!(unsafe { &*emu }.inner.lock().unwrap().rtc_trim(), -105);
+        assert_eq!(emu_set_rtc_trim(emu, 40_000), -30);
+        assert_eq!(emu_set_rtc_trim(std::ptr::null_mut(), 0), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_step_ffi() {
+        let emu = emu_create();
+        let mut step = EmuStepInfo::default();
+        assert_eq!(emu_step(emu, &mut step), -10);
+        assert_eq!(emu_step(emu, std::ptr::null_mut()), -1);
+
+        // NOPs up to the power-on interrupt vector, then LD A,0x42 ; HALT
+        let mut rom = vec![0x00; 0x38];
+        rom.extend([0x3E, 0x42, 0x76]);
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        while step.next_pc != 0x38 {
+            assert_eq!(emu_step(emu, &mut step), 0);
+        }
+        assert_eq!(emu_step(emu, &mut step), 0);
+        assert_eq!((step.pc, step.next_pc), (0x38, 0x3A));
+        assert_eq!((step.opcode[0], step.opcode_len), (0x3E, 1));
+        assert!(step.cycles > 0);
+
+        // Single-stepping works while paused
+        emu_set_paused(emu, 1);
+        assert_eq!(emu_step(emu, &mut step), 0);
+        assert_eq!((step.pc, step.a, step.opcode[0]), (0x3A, 0x42, 0x76));
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_run_frame_ffi() {
+        let emu = emu_create();
+        assert_eq!(emu_run_frame(std::ptr::null_mut()), 0);
+        assert_eq!(emu_run_frame(emu), 0);
+
+        // JR $ with the LCD never enabled: a 60 Hz frame at 6 MHz
+        let rom = vec![0x18, 0xFE];
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        let cycles = emu_run_frame(emu);
+        assert!((100_000..100_100).contains(&cycles), "{}", cycles);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_debug_overlay_ffi() {
+        let emu = emu_create();
+        let pixels = emu::SCREEN_WIDTH * emu::SCREEN_HEIGHT;
+        let mut out = vec![0u32; pixels];
+        assert_eq!(emu_debug_overlay(emu, overlay::HUD_PC, out.as_mut_ptr(), pixels - 1), -30);
+        assert_eq!(emu_debug_overlay(emu, 8, out.as_mut_ptr(), pixels), -30);
+        assert_eq!(emu_debug_overlay(emu, overlay::HUD_ALL, out.as_mut_ptr(), pixels), 0);
+
+        // "PC:..." starts with a lit top-left pixel in the copy only
+        let index = 2 * emu::SCREEN_WIDTH + 2;
+        let framebuffer = unsafe { &*emu }.inner.lock().unwrap().framebuffer_ptr();
+        assert_eq!(out[index], 0xFFFFFFFF);
+        assert_ne!(unsafe { *framebuffer.add(index) }, 0xFFFFFFFF);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_breakpoints_ffi() {
+        let emu = emu_create();
+        assert_eq!(emu_breakpoint_add(std::ptr::null_mut(), 0), -1);
+        assert_eq!(emu_breakpoint_add(emu, 0x1000000), -30);
+        assert_eq!(emu_breakpoint_remove(emu, 0x3A), -30);
+
+        // NOPs up to the power-on interrupt vector, then a loop:
+        // 0x38: LD B,3 ; 0x3A: NOP ; DJNZ 0x3A ; HALT
+        let mut rom = vec![0x00; 0x38];
+        rom.extend([0x06, 0x03, 0x00, 0x10, 0xFD, 0x76]);
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        assert_eq!(emu_breakpoint_add(emu, 0x3A), 0);
+        assert_eq!(emu_breakpoint_add(emu, 0x3D), 0);
+        assert_eq!(emu_breakpoint_enable(emu, 0x3D, 0), 0);
+
+        // Stops on each pass around the loop, resuming past the breakpoint
+        let mut addr = 0;
+        for _ in 0..3 {
+            emu_run_cycles(emu, 10_000);
+            assert_eq!(emu_last_breakpoint(emu, &mut addr), 1);
+            assert_eq!(addr, 0x3A);
+        }
+        // Then runs on to the HALT; the disabled breakpoint there never fires
+        emu_run_cycles(emu, 10_000);
+        assert_eq!(emu_last_breakpoint(emu, &mut addr), 0);
+
+        assert_eq!(emu_breakpoint_remove(emu, 0x3A), 0);
+        assert_eq!(emu_breakpoint_clear(emu), 0);
+        assert_eq!(emu_breakpoint_enable(emu, 0x3D, 1), -30);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_watchpoints_ffi() {
+        let emu = emu_create();
+        assert_eq!(emu_watchpoint_add(std::ptr::null_mut(), 0, 0, 1), -1);
+        assert_eq!(emu_watchpoint_add(emu, 0, 0, 4), -30);
+        assert_eq!(emu_watchpoint_add(emu, 2, 1, 1), -30);
+        assert_eq!(emu_watchpoint_remove(emu, 1), -30);
+
+        // 0x38: LD A,5 ; 0x3A: LD.LIL (0xD000C4),A ; 0x3F: LD.LIL A,(0xD000C4) ; HALT
+        let mut rom = vec![0x00; 0x38];
+        rom.extend([0x3E, 0x05, 0x5B, 0x32, 0xC4, 0x00, 0xD0, 0x5B, 0x3A, 0xC4, 0x00, 0xD0, 0x76]);
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        let writes = emu_watchpoint_add(emu, 0xD000C4, 0xD000C4, 2);
+        assert!(writes > 0);
+        assert!(emu_watchpoint_add(emu, 0xD00000, 0xD000FF, 1) > writes);
+
+        // Each run stops after the instruction that made the access
+        let mut hit = snoop::EmuSnoopAccess::default();
+        emu_run_cycles(emu, 10_000);
+        assert_eq!(emu_last_watchpoint(emu, &mut hit), 1);
+        assert_eq!((hit.addr, hit.pc, hit.value, hit.write), (0xD000C4, 0x3A, 5, 1));
+        assert_eq!(unsafe { &*emu }.inner.lock().unwrap().pc(), 0x3F);
+
+        emu_run_cycles(emu, 10_000);
+        assert_eq!(emu_last_watchpoint(emu, &mut hit), 1);
+        assert_eq!((hit.pc, hit.write), (0x3F, 0));
+
+        emu_run_cycles(emu, 10_000);
+        assert_eq!(emu_last_watchpoint(emu, &mut hit), 0);
+        assert_eq!(emu_watchpoint_remove(emu, writes), 0);
+        assert_eq!(emu_watchpoint_clear(emu), 0);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_power_stats_ffi() {
+        let emu = emu_create();
+        let mut stats = power::EmuPowerStats::default();
+        assert_eq!(emu_get_power_stats(std::ptr::null(), &mut stats), -1);
+
+        // Spinning in flash at 6 MHz with the LCD off and the backlight up
+        let rom = vec![0x18, 0xFE];
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        emu_run_cycles(emu, 600_000);
+        assert_eq!(emu_get_power_stats(emu, &mut stats), 0);
+        assert_eq!(stats.lcd_ua, 0);
+        assert_eq!(stats.backlight_ua, 30_000);
+        assert!(stats.cpu_ua > 3_000 && stats.flash_ua > 0, "{:?}", stats);
+        assert_eq!(stats.current_ua, stats.cpu_ua + stats.backlight_ua + stats.flash_ua);
+        assert!((99_000_000..101_000_000).contains(&stats.elapsed_ns), "{}", stats.elapsed_ns);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_watchdog_stats_ffi() {
+        let emu = emu_create();
+        let mut stats = watchdog_monitor::EmuWatchdogStats::default();
+        assert_eq!(emu_get_watchdog_stats(std::ptr::null(), &mut stats), -1);
+        assert_eq!(emu_set_watchdog_stall(std::ptr::null_mut(), 100), -1);
+        assert_eq!(emu_set_watchdog_stall(emu, 100), 0);
+
+        // At the power-on vector: LD BC,0x6008 ; LD A,0xB9 ; LD D,32 ;
+        // pet D times ; JR $
+        let mut rom = vec![0x00; 0x38];
+        rom.extend([0x01, 0x08, 0x60, 0x3E, 0xB9, 0x16, 0x20, 0xED, 0x79, 0x15, 0x20, 0xFB, 0x18, 0xFE]);
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        emu_run_cycles(emu, 1_200_000);
+        assert_eq!(emu_get_watchdog_stats(emu, &mut stats), 0);
+        assert_eq!((stats.pets, stats.stalls, stats.stall_ms), (32, 1, 100));
+
+        let mut event = events::EmuEvent::new(events::EventKind::Trap, 0, 0);
+        let mut stall = None;
+        while emu_poll_event(emu, &mut event) == 1 {
+            if event.kind == events::EventKind::WatchdogStall as u32 {
+                stall = Some(event.value);
+            }
+        }
+        assert!(matches!(stall, Some(ms) if ms >= 100), "{:?}", stall);
+        emu_destroy(emu);
+    }
 
-/// Set backend by name.
-/// For Rust-only builds, only "rust" is valid.
-/// Returns 0 on success, -1 on failure.
-#[no_mangle]
-#[cfg(not(feature = "ios_prefixed"))]
-pub extern "C" fn emu_backend_set(name: *const c_char) -> i32 {
-    if name.is_null() {
-        return -1;
+    #[test]
+    fn test_run_until_pc_ffi() {
+        let emu = emu_create();
+        assert_eq!(emu_run_until_pc(std::ptr::null_mut(), 0, 0), -1);
+        assert_eq!(emu_run_until_pc(emu, 0x40, 1000), RunUntil::Stopped as i32);
+
+        // NOPs up to the power-on interrupt vector, then a loop:
+        // 0x38: LD B,3 ; 0x3A: NOP ; DJNZ 0x3A ; HALT
+        let mut rom = vec![0x00; 0x38];
+        rom.extend([0x06, 0x03, 0x00, 0x10, 0xFD, 0x76]);
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        let sync_emu = unsafe { &*emu };
+        assert_eq!(emu_run_until_pc(emu, 0x3A, 10_000), RunUntil::Reached as i32);
+        assert_eq!(sync_emu.inner.lock().unwrap().pc(), 0x3A);
+
+        // From the target, runs around the loop back to it
+        assert_eq!(emu_run_until_pc(emu, 0x3A, 10_000), RunUntil::Reached as i32);
+        assert_eq!((sync_emu.inner.lock().unwrap().bc() >> 8) & 0xFF, 2);
+        assert_eq!(emu_run_until_pc(emu, 0x100, 10_000), RunUntil::Budget as i32);
+        emu_destroy(emu);
     }
-    let name_str = unsafe { std::ffi::CStr::from_ptr(name) };
-    if name_str.to_bytes() == b"rust" {
-        0
-    } else {
-        -1
+
+    #[test]
+    fn test_patch_ffi() {
+        let emu = emu_create();
+        let peek = |addr: u32| {
+            let mut byte = 0u8;
+            emu_read_memory(emu, addr, &mut byte, 1);
+            byte
+        };
+        let patch = b"000002: 76 # HALT\n";
+        assert_eq!(emu_patch_add(emu, patch.as_ptr(), patch.len()), 1);
+        assert_eq!(emu_patch_add(emu, b"2: 7".as_ptr(), 4), -110);
+        assert_eq!(emu_patch_add(std::ptr::null_mut(), patch.as_ptr(), patch.len()), -1);
+
+        // Applied on load, and the hash is the unpatched ROM's
+        let rom = vec![0x00; 8];
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        assert_eq!(peek(0x02), 0x76);
+        let mut plain = Emu::new();
+        plain.load_rom(&rom).unwrap();
+        let mut hash = 0u64;
+        emu_rom_hash(emu, &mut hash);
+        assert_eq!(Some(hash), plain.rom_hash());
+
+        // Re-applied on reset after flash changed
+        emu_write_memory(emu, 0x02, [0x00].as_ptr(), 1);
+        assert_eq!(peek(0x02), 0x00);
+        emu_reset(emu);
+        assert_eq!(peek(0x02), 0x76);
+
+        emu_patch_clear(emu);
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        assert_eq!(peek(0x02), 0x00);
+        emu_destroy(emu);
     }
-}
 
-/// Get number of available backends.
-/// For Rust-only builds, returns 1.
-#[no_mangle]
-#[cfg(not(feature = "ios_prefixed"))]
-pub extern "C" fn emu_backend_count() -> i32 {
-    1
-}
+    #[test]
+    fn test_registers_ffi() {
+        let emu = emu_create();
+        let mut regs = EmuRegisters::default();
+        assert_eq!(emu_get_registers(emu, std::ptr::null_mut()), -1);
+        assert_eq!(emu_set_registers(std::ptr::null_mut(), &regs), -1);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        // NOPs up to the power-on interrupt vector, then LD A,0x42 ; HALT
+        let mut rom = vec![0x00; 0x38];
+        rom.extend([0x3E, 0x42, 0x76]);
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        assert_eq!(emu_get_registers(emu, &mut regs), 0);
+        assert_eq!((regs.pc, regs.adl), (0, 0));
+
+        // Skip the LD: the next instruction is the HALT at the new PC
+        regs.pc = 0x3A;
+        regs.af = 0x1234;
+        regs.hl = 0x1D0ABC;
+        regs.sp = 0xD1A87E;
+        regs.adl = 1;
+        assert_eq!(emu_set_registers(emu, &regs), 0);
+        let mut step = EmuStepInfo::default();
+        assert_eq!(emu_step(emu, &mut step), 0);
+        assert_eq!((step.pc, step.opcode[0]), (0x3A, 0x76));
+        assert_eq!((step.a, step.f, step.hl, step.sp), (0x12, 0x34, 0x1D0ABC, 0xD1A87E));
+
+        let mut read = EmuRegisters::default();
+        assert_eq!(emu_get_registers(emu, &mut read), 0);
+        assert_eq!(read, EmuRegisters { pc: 0x3B, ..regs });
+        emu_destroy(emu);
+    }
+
+    extern "C" fn collect_access(user: *mut c_void, access: *const snoop::EmuSnoopAccess) {
+        let seen = unsafe { &mut *(user as *mut Vec<snoop::EmuSnoopAccess>) };
+        seen.push(unsafe { *access });
+    }
+
+    extern "C" fn collect_port_access(user: *mut c_void, access: *const port_watch::EmuPortAccess) {
+        let seen = unsafe { &mut *(user as *mut Vec<port_watch::EmuPortAccess>) };
+        seen.push(unsafe { *access });
+    }
 
     #[test]
-    fn test_create_destroy() {
+    fn test_port_watch_ffi() {
         let emu = emu_create();
-        assert!(!emu.is_null());
+        let mut seen: Vec<port_watch::EmuPortAccess> = Vec::new();
+        let user = &mut seen as *mut Vec<port_watch::EmuPortAccess> as *mut c_void;
+        assert_eq!(emu_port_watch_add(emu, 0, 0xD00000, 0xD00000, 1, 1), -30);
+        assert_eq!(emu_port_watch_add(emu, 2, 0, 0, 1, 1), -30);
+        assert_eq!(emu_port_watch_add(emu, 1, 0, 0x10000, 1, 1), -30);
+        assert_eq!(emu_port_watch_callback(emu, Some(collect_port_access), user), 0);
+
+        // 0x38: LD.LIL A,(0xF50008) ; 0x3D: IN0 A,(0x28) ; HALT
+        let mut rom = vec![0x00; 0x38];
+        rom.extend([0x5B, 0x3A, 0x08, 0x00, 0xF5, 0xED, 0x38, 0x28, 0x76]);
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        assert!(emu_port_watch_add(emu, 0, 0xF50000, 0xF5003F, 1, 1) > 0);
+        let io = emu_port_watch_add(emu, 1, 0x28, 0x28, 3, 0);
+        assert!(io > 0);
+
+        // The keypad read stops the run; the IN only calls back
+        let mut hit = port_watch::EmuPortAccess::default();
+        emu_run_cycles(emu, 10_000);
+        assert_eq!(emu_last_port_watch(emu, &mut hit), 1);
+        assert_eq!((hit.addr, hit.pc, hit.write, hit.io), (0xF50008, 0x38, 0, 0));
+        emu_run_cycles(emu, 10_000);
+        assert_eq!(emu_last_port_watch(emu, &mut hit), 0);
+        assert_eq!(seen.len(), 2);
+        assert_eq!((seen[1].addr, seen[1].pc, seen[1].io), (0x28, 0x3D, 1));
+
+        assert_eq!(emu_port_watch_remove(emu, io), 0);
+        assert_eq!(emu_port_watch_remove(emu, io), -30);
+        assert_eq!(emu_port_watch_clear(emu), 0);
         emu_destroy(emu);
     }
 
     #[test]
-    fn test_framebuffer() {
+    fn test_snoop_range_ffi() {
         let emu = emu_create();
-        let mut w: i32 = 0;
-        let mut h: i32 = 0;
-        let fb = emu_framebuffer(emu, &mut w, &mut h);
+        let mut seen: Vec<snoop::EmuSnoopAccess> = Vec::new();
+        let user = &mut seen as *mut Vec<snoop::EmuSnoopAccess> as *mut c_void;
+        assert_eq!(emu_snoop_range(emu, 0xD00010, 0xD0000F, Some(collect_access), user), -30);
+        assert_eq!(emu_snoop_range(std::ptr::null_mut(), 0, 0, None, std::ptr::null_mut()), -1);
 
-        assert!(!fb.is_null());
-        assert_eq!(w, 320);
-        assert_eq!(h, 240);
+        // After the power-on interrupt: LD A,7 / LD.LIL (0xD00010),A /
+        // LD.LIL A,(0xD00010) / LD.LIL (0xD00020),A / HALT
+        let mut rom = vec![0x00; 0x38];
+        rom.extend([0x3E, 0x07, 0x5B, 0x32, 0x10, 0x00, 0xD0, 0x5B, 0x3A, 0x10, 0x00, 0xD0]);
+        rom.extend([0x5B, 0x32, 0x20, 0x00, 0xD0, 0x76]);
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        assert_eq!(emu_snoop_range(emu, 0xD00000, 0xD0001F, Some(collect_access), user), 0);
+        emu_power_on(emu);
+        emu_run_cycles(emu, 1000);
 
+        // The store outside the range and the debugger poke aren't reported
+        let fields: Vec<_> = seen.iter().map(|a| (a.addr, a.pc, a.value, a.write)).collect();
+        assert_eq!(fields, [(0xD00010, 0x3A, 7, 1), (0xD00010, 0x3F, 7, 0)]);
+        assert!(seen[0].cycles < seen[1].cycles);
+        let poke = [1u8];
+        emu_write_memory(emu, 0xD00010, poke.as_ptr(), 1);
+        assert_eq!(seen.len(), 2);
+
+        assert_eq!(emu_snoop_range(emu, 0, 0, None, std::ptr::null_mut()), 0);
         emu_destroy(emu);
     }
 
     #[test]
-    fn test_run_cycles() {
+    fn test_request_stop_ffi() {
         let emu = emu_create();
-        // Without ROM, should return 0
-        let executed = emu_run_cycles(emu, 1000);
-        assert_eq!(executed, 0);
+        assert_eq!(emu_request_stop(emu), 0);
+        let sync_emu = unsafe { &*emu };
+        assert!(sync_emu.inner.lock().unwrap().stop_handle().load(Ordering::Relaxed));
+        assert_eq!(emu_request_stop(std::ptr::null()), -1);
         emu_destroy(emu);
     }
 
     #[test]
-    fn test_key_input() {
+    fn test_take_bus_counters_ffi() {
         let emu = emu_create();
-        emu_set_key(emu, 0, 0, 1);
-        emu_set_key(emu, 0, 0, 0);
+        let mut counters = bus::BusCounters { reads: 99, ..Default::default() };
+        assert_eq!(emu_take_bus_counters(emu, &mut counters), 0);
+        assert_eq!(counters, bus::BusCounters::default());
+        assert_eq!(emu_take_bus_counters(emu, std::ptr::null_mut()), -1);
         emu_destroy(emu);
     }
 
     #[test]
-    fn test_thread_safety() {
-        use std::thread;
+    fn test_watch_ffi() {
+        let emu = emu_create();
+        let a = emu_watch_add(emu, c"a".as_ptr());
+        let pc = emu_watch_add(emu, c"pc + 1".as_ptr());
+        assert_eq!((a, pc), (0, 1));
+        assert_eq!(emu_watch_add(emu, c"[hl".as_ptr()), -20);
+        assert_eq!(emu_watch_add(std::ptr::null_mut(), c"a".as_ptr()), -1);
+
+        let mut ids = [0u32; 1];
+        let mut values = [0u32; 1];
+        // Count reports every watch even when the buffers are short
+        assert_eq!(emu_watch_values(emu, ids.as_mut_ptr(), values.as_mut_ptr(), 1), 2);
+        assert_eq!(ids[0], 0);
 
+        assert_eq!(emu_watch_remove(emu, a), 0);
+        assert_eq!(emu_watch_remove(emu, a), -22);
+        assert_eq!(emu_watch_values(emu, ids.as_mut_ptr(), values.as_mut_ptr(), 1), 1);
+        assert_eq!((ids[0], values[0]), (1, 1));
+
+        emu_watch_clear(emu);
+        assert_eq!(emu_watch_values(emu, std::ptr::null_mut(), std::ptr::null_mut(), 0), 0);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_mmio_diff_ffi() {
         let emu = emu_create();
+        assert_eq!(emu_mmio_diff(std::ptr::null(), std::ptr::null_mut(), 0), -1);
+        // NOPs up to the power-on interrupt vector, then
+        // LD BC,0x5004 ; LD A,0x01 ; OUT (C),A ; HALT
+        let mut rom = vec![0x00; 0x38];
+        rom.extend([0x01, 0x04, 0x50, 0x3E, 0x01, 0xED, 0x79, 0x76]);
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_mmio_diff_enable(emu, 1);
+        emu_power_on(emu);
+        emu_run_cycles(emu, 1000);
 
-        // Load a minimal ROM so we can run cycles
-        let rom = vec![0x00, 0x00, 0x76]; // NOP, NOP, HALT
+        let mut change = mmio_diff::MmioChange { addr: 0, pc: 0, writes: 0, old_value: 0, new_value: 0, port: 0, _reserved: 0 };
+        assert_eq!(emu_mmio_diff(emu, &mut change, 1), 1);
+        assert_eq!((change.addr, change.port, change.new_value), (0x5004, 1, 0x01));
+        assert_eq!(emu_mmio_diff(emu, std::ptr::null_mut(), 0), 1);
+
+        emu_mmio_diff_enable(emu, 0);
+        assert_eq!(emu_mmio_diff(emu, std::ptr::null_mut(), 0), 0);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_interrupt_watch_ffi() {
+        let emu = emu_create();
+        assert_eq!(emu_set_interrupt_watch(emu, 1 << 11, 1 << 4), 0);
+        assert_eq!(unsafe { &*emu }.inner.lock().unwrap().interrupt_watch(), (1 << 11, 1 << 4));
+        assert_eq!(emu_set_interrupt_watch(std::ptr::null_mut(), 0, 0), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_error_string_ffi() {
+        let text = |code| unsafe { std::ffi::CStr::from_ptr(emu_error_string(code)) }.to_str().unwrap();
+        assert_eq!(text(-104), "state was saved with another ROM");
+        assert_eq!(text(-41), "slot is empty");
+        assert_eq!(text(0), "ok");
+
+        let emu = emu_create();
+        assert_eq!(text(emu_slot_load(emu, 0)), "slot is empty");
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_interrupt_stats_ffi() {
+        use peripherals::interrupt::EmuInterruptStats;
+
+        let emu = emu_create();
+        let mut stats = [EmuInterruptStats::default(); 22];
+        assert_eq!(emu_get_interrupt_stats(emu, stats.as_mut_ptr(), 21), -33);
+        assert_eq!(emu_get_interrupt_stats(std::ptr::null(), stats.as_mut_ptr(), 22), -1);
+
+        // Power-on raises PWR
+        assert_eq!(emu_get_interrupt_stats(emu, stats.as_mut_ptr(), stats.len()), 22);
+        assert_eq!(stats[15].raises, 1);
+
+        emu_clear_interrupt_stats(emu);
+        assert_eq!(emu_get_interrupt_stats(emu, stats.as_mut_ptr(), stats.len()), 22);
+        assert_eq!(stats[15], EmuInterruptStats::default());
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_benchmark_ffi() {
+        let emu = emu_create();
+        // Write hook outside the port range
+        assert_eq!(emu_benchmark_set(emu, 2, 0xD00000, 2, 0xD00000), -30);
+        assert_eq!(emu_benchmark_set(emu, 1, 0x000100, 3, 0), -30);
+        assert_eq!(emu_benchmark_set(std::ptr::null_mut(), 0, 0, 0, 0), -1);
+
+        // Power-on interrupt lands at 0x38: LD.LIL (0xFD0100),A / NOP x3 /
+        // LD.LIL (0xFD0100),A / HALT
+        let mut rom = vec![0x00; 0x38];
+        rom.extend([0x5B, 0x32, 0x00, 0x01, 0xFD, 0x00, 0x00, 0x00, 0x5B, 0x32, 0x00, 0x01, 0xFD, 0x76]);
         emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        assert_eq!(emu_benchmark_set(emu, 2, 0xFD0100, 2, 0xFD0100), 0);
+        emu_run_cycles(emu, 200);
 
-        // Wrap in Arc for sharing across threads
-        let emu_ptr = emu as usize; // Convert to usize for Send
+        let mut results = [0u64; 4];
+        assert_eq!(emu_benchmark_results(emu, results.as_mut_ptr(), results.len()), 1);
+        assert!(results[0] > 0);
+        assert_eq!(emu_benchmark_results(emu, std::ptr::null_mut(), 0), 1);
 
-        // Spawn threads that access the emulator concurrently
-        let handles: Vec<_> = (0..4).map(|i| {
-            thread::spawn(move || {
-                let emu = emu_ptr as *mut SyncEmu;
-                for _ in 0..100 {
-                    if i % 2 == 0 {
-                        emu_set_key(emu, (i % 8) as i32, 0, 1);
-                        emu_set_key(emu, (i % 8) as i32, 0, 0);
-                    } else {
-                        emu_run_cycles(emu, 10);
-                    }
-                }
-            })
-        }).collect();
+        assert_eq!(emu_benchmark_set(emu, 0, 0, 0, 0), 0);
+        assert_eq!(emu_benchmark_results(emu, std::ptr::null_mut(), 0), 0);
+        emu_destroy(emu);
+    }
 
-        // Wait for all threads
-        for h in handles {
-            h.join().unwrap();
+    #[test]
+    fn test_macro_recording_ffi() {
+        let emu = emu_create();
+        assert_eq!(emu_macro_checkpoint(emu, std::ptr::null_mut()), -95);
+        assert_eq!(emu_macro_text(emu, std::ptr::null_mut(), 0, 0), -95);
+
+        assert_eq!(emu_macro_record_start(emu), 0);
+        emu_set_key(emu, 6, 0, 1);
+        let mut hash = 0u64;
+        assert_eq!(emu_macro_checkpoint(emu, &mut hash), 0);
+        let len = emu_macro_text(emu, std::ptr::null_mut(), 0, 0);
+        assert!(len > 0);
+
+        let mut text = vec![0 as c_char; len as usize + 1];
+        assert_eq!(emu_macro_text(emu, text.as_mut_ptr(), text.len(), 1), len);
+        let text = unsafe { std::ffi::CStr::from_ptr(text.as_ptr()) }.to_str().unwrap();
+        assert_eq!(text, format!("down 6 0\nframe {:016x}\n", hash));
+        // Stopped
+        assert_eq!(emu_macro_text(emu, std::ptr::null_mut(), 0, 0), -95);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_poll_event_ffi() {
+        let emu = emu_create();
+        let mut event = events::EmuEvent::new(events::EventKind::Trap, 0, 0);
+        assert_eq!(emu_poll_event(emu, &mut event), 0);
+        assert_eq!(emu_poll_event(emu, std::ptr::null_mut()), -1);
+
+        let rom = test_rom::build();
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        emu_run_cycles(emu, 2_000_000);
+
+        let mut kinds = Vec::new();
+        while emu_poll_event(emu, &mut event) == 1 {
+            kinds.push(event.kind);
         }
+        assert_eq!(kinds.last(), Some(&(events::EventKind::FrameDone as u32)));
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_heatmap_ffi() {
+        let emu = emu_create();
+        let mut out = vec![0u8; emu_heatmap_size()];
+        assert_eq!(emu_heatmap_export(std::ptr::null(), out.as_mut_ptr(), out.len()), -1);
+        assert_eq!(emu_heatmap_export(emu, out.as_mut_ptr(), out.len()), -34);
+
+        emu_heatmap_enable(emu, 1);
+        assert_eq!(emu_heatmap_export(emu, out.as_mut_ptr(), out.len()), out.len() as i32);
+        assert_eq!(emu_heatmap_export(emu, out.as_mut_ptr(), 4), -33);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_opcode_coverage_ffi() {
+        let emu = emu_create();
+        let mut counts = vec![0u32; opcode_coverage::EXPORT_COUNTS];
+        assert_eq!(emu_opcode_coverage_export(emu, counts.as_mut_ptr(), counts.len()), -34);
+        assert_eq!(emu_opcode_coverage_report(emu, std::ptr::null_mut(), 0), -34);
+        assert_eq!(emu_opcode_coverage_report(std::ptr::null(), std::ptr::null_mut(), 0), -1);
+
+        emu_opcode_coverage_enable(emu, 1);
+        assert_eq!(emu_opcode_coverage_export(emu, counts.as_mut_ptr(), counts.len()), 1280);
+        assert_eq!(emu_opcode_coverage_export(emu, counts.as_mut_ptr(), 4), -33);
+        let len = emu_opcode_coverage_report(emu, std::ptr::null_mut(), 0);
+        let mut text = vec![0u8; 16];
+        assert_eq!(emu_opcode_coverage_report(emu, text.as_mut_ptr() as *mut c_char, text.len()), len);
+        assert!(text.starts_with(b"main        0/"));
+        assert_eq!(text[15], 0);
+        emu_opcode_coverage_clear(emu);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_send_file_ffi() {
+        let emu = emu_create();
+        let file = b"**TI83F*";
+        assert_eq!(emu_send_file(emu, file.as_ptr(), file.len()), -10);
+        assert_eq!(emu_send_file_live(emu, file.as_ptr(), file.len()), -10);
+
+        let rom = vec![0x00, 0x00, 0x76]; // NOP, NOP, HALT
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        assert_eq!(emu_send_file(emu, file.as_ptr(), file.len()), -11);
+        emu_power_on(emu);
+        assert_eq!(emu_send_file(emu, file.as_ptr(), file.len()), -13);
+        assert_eq!(emu_send_file_live(emu, file.as_ptr(), file.len()), -11);
+        assert_eq!(emu_send_file_live(emu, std::ptr::null(), 0), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_flash_os_ffi() {
+        let emu = emu_create();
+        let file = os_update::build_file("TESTOS", os_update::DEVICE_CE, os_update::DATA_TYPE_OS, &[0x00, 0x76]);
+        assert_eq!(emu_flash_os(emu, file.as_ptr(), file.len()), -80);
+
+        let rom = vec![0x00, 0x00, 0x76]; // NOP, NOP, HALT
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        assert_eq!(emu_flash_os(emu, rom.as_ptr(), rom.len()), -81);
+        let app = os_update::build_file("APP", os_update::DEVICE_CE, 0x24, &[0x00]);
+        assert_eq!(emu_flash_os(emu, app.as_ptr(), app.len()), -82);
+
+        assert_eq!(emu_flash_os(emu, file.as_ptr(), file.len()), 2);
+        let mut byte = 0u8;
+        emu_read_block(emu, os_update::OS_START + 1, &mut byte, 1, 0);
+        assert_eq!(byte, 0x76);
+        assert_eq!(emu_get_reset_cause(emu), ResetCause::Frontend as i32);
+        assert_eq!(emu_flash_os(emu, std::ptr::null(), 0), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_load_state_best_effort_ffi() {
+        let emu = emu_create();
+        let rom = vec![0x00, 0x00, 0x76];
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        let mut state = vec![0u8; emu_save_state_size(emu)];
+        let len = emu_save_state(emu, state.as_mut_ptr(), state.len());
+        assert!(len > 0);
+
+        assert_eq!(emu_load_state_best_effort(emu, state.as_ptr(), state.len()), 0);
+        let other = vec![0x00, 0x76];
+        emu_load_rom(emu, other.as_ptr(), other.len());
+        assert_eq!(emu_load_state(emu, state.as_ptr(), state.len()), -104);
+        assert_eq!(emu_load_state_best_effort(emu, state.as_ptr(), state.len()), 1);
+        assert_eq!(emu_load_state_best_effort(emu, state.as_ptr(), 8), -102);
+        assert_eq!(emu_load_state_best_effort(std::ptr::null_mut(), state.as_ptr(), 8), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_redacted_state_ffi() {
+        let emu = emu_create();
+        assert_eq!(emu_save_redacted_state(emu, std::ptr::null_mut(), 0), -10);
+        let rom = vec![0x00, 0x00, 0x76];
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        let len = emu_save_redacted_state(emu, std::ptr::null_mut(), 0);
+        assert!(len > 0 && (len as usize) < emu_save_state_size(emu));
+        let mut bundle = vec![0u8; len as usize];
+        assert_eq!(emu_save_redacted_state(emu, bundle.as_mut_ptr(), 16), len);
+        assert_eq!(&bundle[..4], &[0; 4], "nothing copied into a short buffer");
+        assert_eq!(emu_save_redacted_state(emu, bundle.as_mut_ptr(), bundle.len()), len);
+        assert_eq!(&bundle[..4], b"CE8R");
+
+        assert_eq!(emu_load_redacted_state(emu, bundle.as_ptr(), bundle.len()), 0);
+        emu_write_block(emu, 0x0C0000, [0u8].as_ptr(), 1, 0);
+        assert_eq!(emu_load_redacted_state(emu, bundle.as_ptr(), bundle.len()), 1, "one sector differs");
+        bundle[100] ^= 1;
+        assert_eq!(emu_load_redacted_state(emu, bundle.as_ptr(), bundle.len()), -106);
+        assert_eq!(emu_load_redacted_state(std::ptr::null_mut(), bundle.as_ptr(), 8), -1);
+        assert_eq!(emu_save_redacted_state(emu, std::ptr::null_mut(), 4), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_screen_text_ffi() {
+        let emu = emu_create();
+        let rom = vec![0x00, 0x76];
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        let mut shadow = [b' '; screen_text::ROWS * screen_text::COLS];
+        shadow[..3].copy_from_slice(&[b'1', 0x1C, b'A']);
+        emu_write_block(emu, screen_text::TEXT_SHADOW, shadow.as_ptr(), shadow.len(), 0);
+        emu_write_block(emu, screen_text::CUR_ROW, [1u8, 4].as_ptr(), 2, 0);
+
+        let mut out = [0 as c_char; 64];
+        let mut cursor = [0u8; 2];
+        assert_eq!(emu_screen_text(emu, out.as_mut_ptr(), out.len(), cursor.as_mut_ptr()), 5);
+        let text = unsafe { std::ffi::CStr::from_ptr(out.as_ptr()) };
+        assert_eq!(text.to_str().unwrap(), "1→A");
+        assert_eq!(cursor, [1, 4]);
+
+        // Truncation never splits the arrow
+        assert_eq!(emu_screen_text(emu, out.as_mut_ptr(), 3, ptr::null_mut()), 5);
+        assert_eq!(unsafe { std::ffi::CStr::from_ptr(out.as_ptr()) }.to_bytes(), b"1");
+        assert_eq!(emu_screen_text(emu, ptr::null_mut(), 0, ptr::null_mut()), 5);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_eval_ffi() {
+        let emu = emu_create();
+        let mut value = 0.0;
+        assert_eq!(emu_eval(emu, c"6+7".as_ptr(), &mut value), -10);
+        let rom = vec![0x00, 0x76];
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        assert_eq!(emu_eval(emu, c"sin(1)".as_ptr(), &mut value), -30);
+        assert_eq!(emu_eval(emu, ptr::null(), &mut value), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_nav_open_ffi() {
+        let emu = emu_create();
+        assert_eq!(emu_nav_open(emu, 4), -10);
+
+        let rom = test_rom::build();
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_power_on(emu);
+        assert_eq!(emu_nav_open(emu, 9), -30);
+        // The test ROM has no OS header: navigated as OS 5.0, which has no Python App
+        assert_eq!(emu_nav_open(emu, 8), 0);
+        let before = unsafe { &*emu }.inner.lock().unwrap().total_cycles();
+        assert_eq!(emu_nav_open(emu, 4), 5);
+        let ran = unsafe { &*emu }.inner.lock().unwrap().total_cycles() - before;
+        assert!(ran >= 5 * (os_nav::HOLD_CYCLES + os_nav::GAP_CYCLES) as u64);
+        assert_eq!(emu_nav_open(std::ptr::null_mut(), 0), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_rom_hash_ffi() {
+        let emu = emu_create();
+        let mut hash = 0u64;
+        assert_eq!(emu_rom_hash(emu, &mut hash), -10);
+
+        let rom = vec![0x00, 0x00, 0x76];
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        assert_eq!(emu_rom_hash(emu, &mut hash), 0);
+        let first = hash;
+
+        // Stable across reloads and flash writes by the running OS
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_write_block(emu, 0x0C0000, [0u8].as_ptr(), 1, 0);
+        assert_eq!(emu_rom_hash(emu, &mut hash), 0);
+        assert_eq!(hash, first);
+
+        let other = vec![0x00, 0x76];
+        emu_load_rom(emu, other.as_ptr(), other.len());
+        emu_rom_hash(emu, &mut hash);
+        assert_ne!(hash, first);
+
+        // An OS upgrade gives a new key
+        let os = os_update::build_file("TESTOS", os_update::DEVICE_CE, os_update::DATA_TYPE_OS, &[0x00, 0x76]);
+        let before = hash;
+        emu_flash_os(emu, os.as_ptr(), os.len());
+        emu_rom_hash(emu, &mut hash);
+        assert_ne!(hash, before);
+        assert_eq!(emu_rom_hash(emu, std::ptr::null_mut()), -1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_rom_info_ffi() {
+        let emu = emu_create();
+        let mut info = rom_info::EmuRomInfo::default();
+        assert_eq!(emu_rom_info(emu, &mut info), -10);
+        assert_eq!(emu_rom_info(std::ptr::null(), &mut info), -1);
+
+        // Boot code without an OS: not worth booting
+        let mut rom = vec![0xFF; memory::addr::BOOT_CODE_END as usize];
+        rom[..3].copy_from_slice(&[0x00, 0x00, 0x76]);
+        rom[0x80..0x8A].copy_from_slice(b"5.3.0.0037");
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        assert_eq!(emu_rom_info(emu, &mut info), 0);
+        assert_eq!(info.flags, rom_info::ROM_BOOT_CODE | rom_info::ROM_BOOT_VERSION);
+        assert_eq!((info.boot_major, info.boot_minor, info.boot_build), (5, 3, 37));
+
+        // OS 5.8.1 for the TI-83 Premium CE
+        rom.extend([0x80, 0x0D, 15, 0x80, 0x12, 0x13, 0x00, 0x80, 0x21, 5, 0x80, 0x32, 8, 1, 0x80, 0xC2, 0, 1]);
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        assert_eq!(emu_rom_info(emu, &mut info), 0);
+        assert_eq!(info.model, rom_info::Model::Ti83PremiumCe as u32);
+        assert_eq!((info.os_major, info.os_minor, info.os_revision), (5, 8, 1));
+        assert_ne!(info.flags & rom_info::ROM_CERT_VALID, 0);
+        emu_destroy(emu);
+    }
+
+    extern "C" fn record_autosave(user: *mut c_void, _data: *const u8, len: usize, reason: u32) {
+        let saves = unsafe { &mut *(user as *mut Vec<(u32, usize)>) };
+        saves.push((reason, len));
+    }
+
+    #[test]
+    fn test_destroy_autosaves() {
+        let mut saves: Vec<(u32, usize)> = Vec::new();
+        let emu = emu_create();
+        let rom = vec![0x00, 0x00, 0x76]; // NOP, NOP, HALT
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_set_autosave_sink(emu, Some(record_autosave), &mut saves as *mut _ as *mut c_void);
+        let size = emu_save_state_size(emu);
+        emu_destroy(emu);
+        assert_eq!(saves, vec![(autosave::AutosaveReason::Destroy as u32, size)]);
+    }
+
+    extern "C" fn ext_read(_user: *mut c_void, offset: u32) -> u8 {
+        offset as u8
+    }
+
+    extern "C" fn ext_write(_user: *mut c_void, _offset: u32, _value: u8) {}
+
+    #[test]
+    fn test_extension_registration_ffi() {
+        let emu = emu_create();
+        let user = std::ptr::null_mut();
+        assert_eq!(emu_register_extension(std::ptr::null_mut(), 0xE40000, 16, ext_read, ext_write, user), -1);
+        assert_eq!(emu_register_extension(emu, 0xE30000, 16, ext_read, ext_write, user), -50);
+        let id = emu_register_extension(emu, 0xE40000, 0x20, ext_read, ext_write, user);
+        assert!(id >= 0);
+        assert_eq!(emu_register_extension(emu, 0xE4001F, 1, ext_read, ext_write, user), -51);
 
+        assert_eq!(emu_unregister_extension(emu, id as u32), 0);
+        assert_eq!(emu_unregister_extension(emu, id as u32), -53);
         emu_destroy(emu);
     }
 }
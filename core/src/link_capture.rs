@@ -0,0 +1,428 @@
+//! Link capture importer and transfer comparison
+//!
+//! Decodes USB captures of calculator transfers (DUSB, the CE's Direct USB
+//! protocol) into the variables that were sent. `compare` diffs those
+//! variables against the ones the emulator's file transfer leaves in the
+//! flash archive (`archived_vars`).
+//!
+//! No hardware capture has been committed yet: the fixtures in
+//! tests/link_captures/ are reconstructed from libticalcs' send sequence, so
+//! they check the importer and the emulator against the documented protocol,
+//! not against recorded ground truth.
+//!
+//! Captures are plain text, one USB bulk transfer per line, as exported from
+//! a logic analyzer or usbmon after stripping the bus framing:
+//!
+//! ```text
+//! # comment
+//! [time_us] H|C hex bytes...
+//! ```
+//!
+//! `H` is host to calculator, `C` calculator to host. The optional timestamp
+//! is kept for reference only. Raw packets may be split across lines.
+//!
+//! Reference: CEmu core/usb/dusb.c, libticalcs dusb_vpkt.c
+
+use crate::memory::addr::{ARCHIVE_END, ARCHIVE_START, SECTOR_SIZE};
+use crate::Emu;
+
+/// Raw packet types
+mod raw {
+    pub const BUF_SIZE_REQ: u8 = 1;
+    pub const BUF_SIZE_ALLOC: u8 = 2;
+    pub const VIRT_DATA: u8 = 3;
+    pub const VIRT_DATA_LAST: u8 = 4;
+    pub const VIRT_DATA_ACK: u8 = 5;
+}
+
+/// Virtual packet types used by variable transfers
+pub mod vpkt {
+    pub const RTS: u16 = 0x000B;
+    pub const VAR_CNTS: u16 = 0x000D;
+    pub const DATA_ACK: u16 = 0xAA00;
+    pub const EOT: u16 = 0xDD00;
+}
+
+/// Variable attribute carrying the type (`F0 07 00 type`)
+const AID_VAR_TYPE: u16 = 0x0002;
+
+/// Transfer direction of a capture line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    HostToCalc,
+    CalcToHost,
+}
+
+/// One captured bulk transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureRecord {
+    pub time_us: Option<u64>,
+    pub direction: Direction,
+    pub data: Vec<u8>,
+}
+
+/// Reassembled DUSB virtual packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtualPacket {
+    pub direction: Direction,
+    pub vtype: u16,
+    pub data: Vec<u8>,
+}
+
+/// A variable as seen on the link or in the archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkVar {
+    pub var_type: u8,
+    pub name: Vec<u8>,
+    /// Variable data (starts with the 2-byte size word, as in .8x files)
+    pub data: Vec<u8>,
+}
+
+/// Parse the text capture format. Errors name the offending line.
+pub fn parse_capture(text: &str) -> Result<Vec<CaptureRecord>, String> {
+    let mut records = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let err = |msg: &str| format!("line {}: {}", number + 1, msg);
+        let mut fields = line.split_whitespace().peekable();
+
+        let time_us = match fields.peek() {
+            Some(f) if f.starts_with('[') => {
+                let f = fields.next().unwrap();
+                let t = f.trim_start_matches('[').trim_end_matches(']');
+                Some(t.parse().map_err(|_| err("bad timestamp"))?)
+            }
+            _ => None,
+        };
+        let direction = match fields.next() {
+            Some("H") => Direction::HostToCalc,
+            Some("C") => Direction::CalcToHost,
+            _ => return Err(err("expected direction H or C")),
+        };
+        let data = fields
+            .map(|b| u8::from_str_radix(b, 16).map_err(|_| err("bad hex byte")))
+            .collect::<Result<Vec<u8>, String>>()?;
+        records.push(CaptureRecord { time_us, direction, data });
+    }
+    Ok(records)
+}
+
+fn be32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Per-direction reassembly state
+#[derive(Default)]
+struct Stream {
+    bytes: Vec<u8>,
+    packet: Option<(u16, u32, Vec<u8>)>,
+}
+
+/// Reassemble virtual packets from captured raw traffic, in capture order
+pub fn virtual_packets(records: &[CaptureRecord]) -> Result<Vec<VirtualPacket>, String> {
+    let mut streams = [Stream::default(), Stream::default()];
+    let mut packets = Vec::new();
+
+    for record in records {
+        let stream = &mut streams[record.direction as usize];
+        stream.bytes.extend_from_slice(&record.data);
+
+        // Consume every complete raw packet: [size:u32 BE][type:u8][payload]
+        while stream.bytes.len() >= 5 {
+            let size = be32(&stream.bytes) as usize;
+            if stream.bytes.len() < 5 + size {
+                break;
+            }
+            let raw_type = stream.bytes[4];
+            let payload: Vec<u8> = stream.bytes.drain(..5 + size).skip(5).collect();
+
+            match raw_type {
+                raw::VIRT_DATA | raw::VIRT_DATA_LAST => {
+                    // Only the first fragment of a virtual packet carries its header
+                    let first = stream.packet.is_none();
+                    let (vtype, vsize, mut data) = match stream.packet.take() {
+                        Some(partial) => partial,
+                        None => {
+                            if payload.len() < 6 {
+                                return Err("virtual packet header truncated".into());
+                            }
+                            let vsize = be32(&payload);
+                            let vtype = u16::from_be_bytes([payload[4], payload[5]]);
+                            (vtype, vsize, Vec::new())
+                        }
+                    };
+                    let body = if first { &payload[6..] } else { &payload[..] };
+                    data.extend_from_slice(body);
+                    if raw_type == raw::VIRT_DATA_LAST {
+                        if data.len() != vsize as usize {
+                            return Err(format!(
+                                "virtual packet {:04X}: header says {} bytes, got {}",
+                                vtype,
+                                vsize,
+                                data.len()
+                            ));
+                        }
+                        packets.push(VirtualPacket { direction: record.direction, vtype, data });
+                    } else {
+                        stream.packet = Some((vtype, vsize, data));
+                    }
+                }
+                raw::BUF_SIZE_REQ | raw::BUF_SIZE_ALLOC | raw::VIRT_DATA_ACK => {}
+                other => return Err(format!("unknown raw packet type {other}")),
+            }
+        }
+    }
+    Ok(packets)
+}
+
+/// Parse an RTS payload: name_len:u16, name, 0, size:u32, mode:u8,
+/// attr_count:u16, then attrs as id:u16 len:u16 data (all big-endian)
+fn parse_rts(data: &[u8]) -> Result<(Vec<u8>, u8, u32), String> {
+    let short = || "RTS truncated".to_string();
+    let name_len = u16::from_be_bytes([*data.first().ok_or_else(short)?, *data.get(1).ok_or_else(short)?]) as usize;
+    let name = data.get(2..2 + name_len).ok_or_else(short)?.to_vec();
+    let mut pos = 2 + name_len + 1;
+    let size = be32(data.get(pos..pos + 4).ok_or_else(short)?);
+    pos += 5;
+    let count = u16::from_be_bytes(data.get(pos..pos + 2).ok_or_else(short)?.try_into().unwrap());
+    pos += 2;
+
+    let mut var_type = None;
+    for _ in 0..count {
+        let header = data.get(pos..pos + 4).ok_or_else(short)?;
+        let id = u16::from_be_bytes([header[0], header[1]]);
+        let len = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let value = data.get(pos + 4..pos + 4 + len).ok_or_else(short)?;
+        if id == AID_VAR_TYPE && len == 4 {
+            var_type = Some(value[3]);
+        }
+        pos += 4 + len;
+    }
+    Ok((name, var_type.ok_or("RTS has no type attribute")?, size))
+}
+
+/// Variables the host sent to the calculator (RTS followed by VAR_CNTS)
+pub fn sent_vars(packets: &[VirtualPacket]) -> Result<Vec<LinkVar>, String> {
+    let mut vars = Vec::new();
+    let mut pending = None;
+    for packet in packets.iter().filter(|p| p.direction == Direction::HostToCalc) {
+        match packet.vtype {
+            vpkt::RTS => pending = Some(parse_rts(&packet.data)?),
+            vpkt::VAR_CNTS => {
+                let (name, var_type, size) = pending.take().ok_or("VAR_CNTS without RTS")?;
+                if packet.data.len() != size as usize {
+                    return Err(format!("{}: RTS announced {} bytes, sent {}", String::from_utf8_lossy(&name), size, packet.data.len()));
+                }
+                vars.push(LinkVar { var_type, name, data: packet.data.clone() });
+            }
+            _ => {}
+        }
+    }
+    Ok(vars)
+}
+
+/// Import a text capture straight to the variables it transferred
+pub fn import(text: &str) -> Result<Vec<LinkVar>, String> {
+    sent_vars(&virtual_packets(&parse_capture(text)?)?)
+}
+
+/// Variables currently stored in the flash archive, in storage order.
+/// Entry layout: flag, size:u16, type, type2, version, addr:3, name_len,
+/// name, data.
+pub fn archived_vars(emu: &mut Emu) -> Vec<LinkVar> {
+
+    let mut vars = Vec::new();
+    let mut sector = ARCHIVE_START;
+    while sector < ARCHIVE_END {
+        if emu.peek_byte(sector) == 0xFF {
+            sector += SECTOR_SIZE;
+            continue;
+        }
+        let mut addr = sector + 1;
+        while addr + 3 < sector + SECTOR_SIZE {
+            let flag = emu.peek_byte(addr);
+            let size = emu.peek_byte(addr + 1) as u32 | (emu.peek_byte(addr + 2) as u32) << 8;
+            if flag == 0xFF || size == 0 || addr + 3 + size > sector + SECTOR_SIZE {
+                break;
+            }
+            // 0xFC = valid entry; 0xF0 = deleted
+            if flag == 0xFC && size >= 7 {
+                let entry: Vec<u8> = (addr + 3..addr + 3 + size).map(|a| emu.peek_byte(a)).collect();
+                let name_len = entry[6] as usize;
+                if 7 + name_len <= entry.len() {
+                    vars.push(LinkVar {
+                        var_type: entry[0],
+                        name: entry[7..7 + name_len].to_vec(),
+                        data: entry[7 + name_len..].to_vec(),
+                    });
+                }
+            }
+            addr += 3 + size;
+        }
+        sector += SECTOR_SIZE;
+    }
+    vars
+}
+
+/// Differences between captured and emulated variables (empty = match)
+pub fn compare(captured: &[LinkVar], emulated: &[LinkVar]) -> Vec<String> {
+    let mut diffs = Vec::new();
+    for var in captured {
+        let name = String::from_utf8_lossy(&var.name);
+        match emulated.iter().find(|e| e.name == var.name) {
+            None => diffs.push(format!("{name}: missing from the emulated archive")),
+            Some(e) => {
+                if e.var_type != var.var_type {
+                    diffs.push(format!("{name}: type {:02X} on the link, {:02X} emulated", var.var_type, e.var_type));
+                }
+                if e.data != var.data {
+                    let at = e.data.iter().zip(&var.data).position(|(a, b)| a != b).unwrap_or(e.data.len().min(var.data.len()));
+                    diffs.push(format!(
+                        "{name}: data differs at byte {at} ({} bytes on the link, {} emulated)",
+                        var.data.len(),
+                        e.data.len()
+                    ));
+                }
+            }
+        }
+    }
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rom;
+    use crate::ti_file::make_8xp;
+    use std::fmt::Write;
+    use std::path::Path;
+
+    /// Encode one virtual packet as capture lines, split into raw packets of
+    /// at most `chunk` payload bytes (mirrors what a calculator negotiates)
+    fn encode(out: &mut String, dir: char, vtype: u16, data: &[u8], chunk: usize) {
+        let mut body = (data.len() as u32).to_be_bytes().to_vec();
+        body.extend_from_slice(&vtype.to_be_bytes());
+        body.extend_from_slice(data);
+        let pieces: Vec<&[u8]> = body.chunks(chunk).collect();
+        for (i, piece) in pieces.iter().enumerate() {
+            let raw_type = if i + 1 == pieces.len() { raw::VIRT_DATA_LAST } else { raw::VIRT_DATA };
+            let mut packet = (piece.len() as u32).to_be_bytes().to_vec();
+            packet.push(raw_type);
+            packet.extend_from_slice(piece);
+            // Split the first packet across two lines to exercise reassembly
+            let split = if i == 0 { 3 } else { packet.len() };
+            for part in [&packet[..split], &packet[split..]] {
+                if !part.is_empty() {
+                    let hex: Vec<String> = part.iter().map(|b| format!("{b:02X}")).collect();
+                    writeln!(out, "[{}] {} {}", i * 100, dir, hex.join(" ")).unwrap();
+                }
+            }
+        }
+        // Acknowledgement from the other side
+        let ack_dir = if dir == 'H' { 'C' } else { 'H' };
+        writeln!(out, "{ack_dir} 00 00 00 02 05 E0 00").unwrap();
+    }
+
+    fn rts(name: &[u8], var_type: u8, size: usize) -> Vec<u8> {
+        let mut data = (name.len() as u16).to_be_bytes().to_vec();
+        data.extend_from_slice(name);
+        data.push(0);
+        data.extend_from_slice(&(size as u32).to_be_bytes());
+        data.push(1); // silent mode
+        data.extend_from_slice(&2u16.to_be_bytes());
+        data.extend_from_slice(&[0x00, 0x02, 0x00, 0x04, 0xF0, 0x07, 0x00, var_type]);
+        data.extend_from_slice(&[0x00, 0x03, 0x00, 0x01, 0x01]); // archived
+        data
+    }
+
+    fn capture_of(name: &[u8], var_type: u8, var_data: &[u8]) -> String {
+        let mut text = String::from("# synthesized from the DUSB packet layout\n");
+        writeln!(text, "H 00 00 00 04 01 00 00 04 00").unwrap();
+        writeln!(text, "C 00 00 00 04 02 00 00 04 00").unwrap();
+        encode(&mut text, 'H', vpkt::RTS, &rts(name, var_type, var_data.len()), 0x3A);
+        encode(&mut text, 'C', vpkt::DATA_ACK, &[0x00, 0x00], 0x3A);
+        encode(&mut text, 'H', vpkt::VAR_CNTS, var_data, 0x3A);
+        encode(&mut text, 'H', vpkt::EOT, &[], 0x3A);
+        text
+    }
+
+    fn emulated_transfer(file: &[u8]) -> Vec<LinkVar> {
+        let mut emu = Emu::new();
+        emu.load_rom(&test_rom::build()).unwrap();
+        emu.send_file(file).unwrap();
+        archived_vars(&mut emu)
+    }
+
+    #[test]
+    fn test_parse_capture_lines() {
+        let records = parse_capture("# header\n[12] H 00 01  # trailing\n\nC ff\n").unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].time_us, Some(12));
+        assert_eq!(records[0].data, vec![0x00, 0x01]);
+        assert_eq!(records[1].direction, Direction::CalcToHost);
+        assert_eq!(parse_capture("X 00").unwrap_err(), "line 1: expected direction H or C");
+        assert_eq!(parse_capture("H zz").unwrap_err(), "line 1: bad hex byte");
+    }
+
+    #[test]
+    fn test_import_reassembles_fragmented_transfer() {
+        // 2-byte size word plus a body longer than one raw packet
+        let mut var_data = vec![0x80, 0x00];
+        var_data.extend((0..0x80).map(|i| i as u8));
+        let vars = import(&capture_of(b"BIGPRGM", 0x06, &var_data)).unwrap();
+        assert_eq!(vars, vec![LinkVar { var_type: 0x06, name: b"BIGPRGM".to_vec(), data: var_data }]);
+    }
+
+    #[test]
+    fn test_header_only_first_fragment() {
+        // The first raw packet carries just the 6-byte virtual header; the
+        // body arrives whole in the next one
+        let capture = "H 00 00 00 06 03 00 00 00 08 AA 00\n\
+                       H 00 00 00 08 04 01 02 03 04 05 06 07 08\n";
+        let packets = virtual_packets(&parse_capture(capture).unwrap()).unwrap();
+        assert_eq!(
+            packets,
+            vec![VirtualPacket { direction: Direction::HostToCalc, vtype: vpkt::DATA_ACK, data: (1..=8).collect() }]
+        );
+    }
+
+    #[test]
+    fn test_capture_matches_emulated_transfer() {
+        let var_data = [0x04, 0x00, 0xEF, 0x7B, 0xC9, 0x00];
+        let captured = import(&capture_of(b"ASM", 0x06, &var_data)).unwrap();
+        let emulated = emulated_transfer(&make_8xp(0x06, b"ASM\0\0\0\0\0", 0, 0x80, &var_data));
+        assert_eq!(compare(&captured, &emulated), Vec::<String>::new());
+
+        // A wrong payload or type is reported rather than silently accepted
+        let emulated = emulated_transfer(&make_8xp(0x05, b"ASM\0\0\0\0\0", 0, 0x80, &[0x04, 0x00, 0xEF, 0x7B, 0xC9, 0x01]));
+        let diffs = compare(&captured, &emulated);
+        assert_eq!(diffs.len(), 2, "{diffs:?}");
+        assert!(diffs[1].contains("byte 5"));
+    }
+
+    /// Committed captures: each `name.txt` in tests/link_captures/ sits next
+    /// to the `name.8xp` that was sent, and the emulator must archive the
+    /// same variables from the file. Hardware captures go here too, once
+    /// someone records one.
+    #[test]
+    fn test_link_captures() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/link_captures");
+        let mut checked = 0;
+        for entry in std::fs::read_dir(&dir).expect("tests/link_captures not found").flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("txt") {
+                continue;
+            }
+            let captured = import(&std::fs::read_to_string(&path).unwrap())
+                .unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+            let file = std::fs::read(path.with_extension("8xp")).expect("matching .8xp");
+            let diffs = compare(&captured, &emulated_transfer(&file));
+            assert!(diffs.is_empty(), "{}: {diffs:?}", path.display());
+            checked += 1;
+        }
+        assert!(checked > 0, "no captures found");
+    }
+}
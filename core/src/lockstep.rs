@@ -0,0 +1,202 @@
+//! Lockstep comparison of two emulator instances
+//!
+//! Guards alternative execution paths (run granularities, caches, future
+//! CPU backends) against the reference: both instances get the same inputs
+//! and advance by the same stride, and their state digests must match after
+//! every stride. A mismatch names the first stride that diverged and which
+//! part of the state differs, with both instances left at that point for a
+//! closer look (`Emu::history`, registers, `save_state`).
+//!
+//! ```ignore
+//! let mut reference = Emu::new();
+//! reference.set_run_granularity(RunGranularity::Instruction);
+//! let mut lockstep = Lockstep::new(reference, Emu::new(), Stride::Cycles(10_000));
+//! lockstep.input(|emu| emu.load_rom(&rom).unwrap());
+//! lockstep.input(|emu| emu.power_on());
+//! lockstep.run(500).unwrap();
+//! ```
+//!
+//! Digests cover the CPU, scheduler and peripheral snapshots (the save state
+//! sections), RAM and the cycle count. Flash is left out: hashing 4MB per
+//! stride would dominate the run, and flash writes show up in the CPU and
+//! flash controller state that performs them.
+
+use std::fmt;
+
+use crate::hash::{fnv1a, words};
+use crate::Emu;
+
+/// How far both instances advance between digest comparisons
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stride {
+    /// This many `Emu::step` calls (single instructions, or a halt
+    /// fast-forward)
+    Instructions(u32),
+    /// One `Emu::run_cycles` call of this many cycles, exercising the
+    /// batched run loop
+    Cycles(u32),
+}
+
+/// FNV-1a hashes of the parts of the machine state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateDigest {
+    pub cpu: u64,
+    pub scheduler: u64,
+    pub peripherals: u64,
+    pub ram: u64,
+    pub total_cycles: u64,
+}
+
+impl StateDigest {
+    pub fn of(emu: &Emu) -> Self {
+        Self {
+            cpu: fnv1a(words(&emu.cpu().to_bytes())),
+            scheduler: fnv1a(words(&emu.scheduler().to_bytes())),
+            peripherals: fnv1a(words(&emu.peripherals().to_bytes())),
+            ram: fnv1a(words(emu.ram_data())),
+            total_cycles: emu.total_cycles(),
+        }
+    }
+
+    /// Name of the first part that differs from `other`
+    pub fn first_difference(&self, other: &Self) -> Option<&'static str> {
+        [
+            ("cpu", self.cpu == other.cpu),
+            ("scheduler", self.scheduler == other.scheduler),
+            ("peripherals", self.peripherals == other.peripherals),
+            ("ram", self.ram == other.ram),
+            ("cycles", self.total_cycles == other.total_cycles),
+        ]
+        .into_iter()
+        .find(|&(_, same)| !same)
+        .map(|(name, _)| name)
+    }
+}
+
+/// Where the two instances first disagreed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Strides completed when the mismatch was seen (0: before running)
+    pub stride: u64,
+    /// First differing part of the state (see `StateDigest::first_difference`)
+    pub section: &'static str,
+    /// PC of the reference and candidate
+    pub pc: (u32, u32),
+    pub reference: StateDigest,
+    pub candidate: StateDigest,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} diverged after stride {} (reference pc={:06X} cycles={}, candidate pc={:06X} cycles={})",
+            self.section, self.stride, self.pc.0, self.reference.total_cycles, self.pc.1, self.candidate.total_cycles
+        )
+    }
+}
+
+/// Reference and candidate instance advanced together
+pub struct Lockstep {
+    pub reference: Emu,
+    pub candidate: Emu,
+    stride: Stride,
+    strides: u64,
+}
+
+impl Lockstep {
+    pub fn new(reference: Emu, candidate: Emu, stride: Stride) -> Self {
+        Self { reference, candidate, stride, strides: 0 }
+    }
+
+    /// Apply the same input (ROM load, key press, poke, ...) to both
+    pub fn input(&mut self, mut apply: impl FnMut(&mut Emu)) {
+        apply(&mut self.reference);
+        apply(&mut self.candidate);
+    }
+
+    /// Strides run so far
+    pub fn strides(&self) -> u64 {
+        self.strides
+    }
+
+    /// Compare the digests of both instances now
+    pub fn check(&self) -> Result<(), Divergence> {
+        let (reference, candidate) = (StateDigest::of(&self.reference), StateDigest::of(&self.candidate));
+        match reference.first_difference(&candidate) {
+            None => Ok(()),
+            Some(section) => Err(Divergence {
+                stride: self.strides,
+                section,
+                pc: (self.reference.pc(), self.candidate.pc()),
+                reference,
+                candidate,
+            }),
+        }
+    }
+
+    /// Run `strides` strides, comparing after each one. Stops at the
+    /// first divergence.
+    pub fn run(&mut self, strides: u64) -> Result<(), Divergence> {
+        for _ in 0..strides {
+            let stride = self.stride;
+            self.input(|emu| advance(emu, stride));
+            self.strides += 1;
+            self.check()?;
+        }
+        Ok(())
+    }
+}
+
+fn advance(emu: &mut Emu, stride: Stride) {
+    match stride {
+        Stride::Instructions(count) => {
+            for _ in 0..count {
+                if emu.step().is_none() {
+                    break;
+                }
+            }
+        }
+        Stride::Cycles(cycles) => {
+            emu.run_cycles(cycles);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_rom, RunGranularity};
+
+    fn booted(reference: RunGranularity, stride: Stride) -> Lockstep {
+        let mut first = Emu::new();
+        first.set_run_granularity(reference);
+        let mut lockstep = Lockstep::new(first, Emu::new(), stride);
+        let rom = test_rom::build();
+        lockstep.input(|emu| emu.load_rom(&rom).unwrap());
+        lockstep.input(|emu| emu.power_on());
+        lockstep
+    }
+
+    #[test]
+    fn test_lookahead_in_lockstep_with_instruction_servicing() {
+        let mut lockstep = booted(RunGranularity::Instruction, Stride::Cycles(50_000));
+        lockstep.run(40).unwrap();
+        lockstep.input(|emu| emu.set_key(3, 2, true));
+        lockstep.run(20).unwrap();
+        lockstep.input(|emu| emu.set_key(3, 2, false));
+        lockstep.run(20).unwrap();
+        assert_eq!(lockstep.strides(), 80);
+    }
+
+    #[test]
+    fn test_step_stride_and_divergence_report() {
+        let mut lockstep = booted(RunGranularity::Lookahead, Stride::Instructions(20));
+        lockstep.run(5).unwrap();
+
+        lockstep.candidate.write_block(0xD10000, &[0x5A], false).unwrap();
+        let divergence = lockstep.run(1).unwrap_err();
+        assert_eq!((divergence.stride, divergence.section), (6, "ram"));
+        assert!(divergence.to_string().starts_with("ram diverged after stride 6"));
+    }
+}
@@ -48,6 +48,16 @@ pub mod addr {
 
     /// Maximum address in 24-bit space
     pub const ADDR_MASK: u32 = 0xFFFFFF;
+
+    /// End of the boot code sectors (exclusive), write-protected on hardware
+    pub const BOOT_CODE_END: u32 = 0x020000;
+
+    /// User archive start: the first flash sector after the OS
+    pub const ARCHIVE_START: u32 = 0x0C0000;
+    /// User archive end (exclusive)
+    pub const ARCHIVE_END: u32 = 0x3B0000;
+    /// Flash sector size above the boot code (64KB)
+    pub const SECTOR_SIZE: u32 = 0x10000;
 }
 
 /// Flash memory state
@@ -55,12 +65,14 @@ pub mod addr {
 /// The TI-84 Plus CE has 4MB of NOR flash for OS and user programs.
 /// Flash is read-only from user code; writes require special unlock sequences.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum FlashCommand {
     None,
     SectorErase { reads_left: u8 },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum FlashWriteState {
     Idle,
     SawAA1,
@@ -71,6 +83,29 @@ enum FlashWriteState {
     SawA0,
 }
 
+/// Bytes per fetch line (the same 32 bytes as a serial flash cache line)
+const FETCH_LINE_BYTES: usize = 32;
+
+/// Copy of the flash line instructions were last fetched from.
+///
+/// Only data is cached: the bus still charges wait states (or serial cache
+/// timing) for every fetch, so cycle counts are unchanged. A sequential
+/// fetch from the same line is a tag compare and a masked index instead of
+/// the offset masking and `Vec` bounds check of `peek`.
+#[derive(Debug, Clone, Copy)]
+struct FetchLine {
+    /// Flash offset >> 5, or u32::MAX when invalid
+    tag: u32,
+    bytes: [u8; FETCH_LINE_BYTES],
+}
+
+impl Default for FetchLine {
+    fn default() -> Self {
+        Self { tag: u32::MAX, bytes: [0xFF; FETCH_LINE_BYTES] }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Flash {
     /// Flash memory contents
     data: Vec<u8>,
@@ -80,6 +115,11 @@ pub struct Flash {
     command: FlashCommand,
     /// Write sequence state for flash command detection
     write_state: FlashWriteState,
+    /// Whether CPU program/erase commands skip the boot code sectors
+    boot_protect: bool,
+    /// Line served by `fetch`, invalidated whenever `data` changes
+    #[cfg_attr(feature = "serde", serde(skip))]
+    fetch_line: FetchLine,
 }
 
 impl Flash {
@@ -91,6 +131,8 @@ impl Flash {
             initialized: false,
             command: FlashCommand::None,
             write_state: FlashWriteState::Idle,
+            boot_protect: true,
+            fetch_line: FetchLine::default(),
         }
     }
 
@@ -112,6 +154,7 @@ impl Flash {
         // Extend with 0xFF to reach full flash size
         new_data.resize(addr::FLASH_SIZE, 0xFF);
         self.data = new_data;
+        self.fetch_line = FetchLine::default();
 
         self.initialized = true;
         self.command = FlashCommand::None;
@@ -138,6 +181,26 @@ impl Flash {
         value
     }
 
+    /// Read a byte for an instruction fetch: the same value and side
+    /// effects as `read`, served from the cached line when possible
+    #[inline]
+    pub fn fetch(&mut self, addr: u32) -> u8 {
+        if self.command != FlashCommand::None {
+            return self.read(addr);
+        }
+        let offset = addr & (addr::FLASH_SIZE as u32 - 1);
+        let tag = offset >> 5;
+        if self.fetch_line.tag != tag {
+            let start = (tag as usize) * FETCH_LINE_BYTES;
+            match self.data.get(start..start + FETCH_LINE_BYTES) {
+                Some(line) => self.fetch_line.bytes.copy_from_slice(line),
+                None => self.fetch_line.bytes = [0xFF; FETCH_LINE_BYTES],
+            }
+            self.fetch_line.tag = tag;
+        }
+        self.fetch_line.bytes[offset as usize & (FETCH_LINE_BYTES - 1)]
+    }
+
     /// Peek flash content ignoring command status (debug-style read)
     pub fn peek(&self, addr: u32) -> u8 {
         if self.data.is_empty() {
@@ -170,12 +233,27 @@ impl Flash {
         }
         let offset = (addr & (addr::FLASH_SIZE as u32 - 1)) as usize;
         self.data[offset] = value;
+        self.fetch_line = FetchLine::default();
+    }
+
+    /// Erase `start..end` (relative to flash start) to 0xFF directly,
+    /// bypassing the command sequence like `write_direct`
+    pub fn erase_direct(&mut self, start: u32, end: u32) {
+        if self.data.is_empty() {
+            return; // Still fully erased
+        }
+        let end = (end as usize).min(addr::FLASH_SIZE);
+        if let Some(range) = self.data.get_mut(start as usize..end) {
+            range.fill(0xFF);
+        }
+        self.fetch_line = FetchLine::default();
     }
 
     /// Handle a CPU write to flash (command detection + optional program/erase)
     pub fn write_cpu(&mut self, addr: u32, value: u8) {
-        // Reset command mode on 0xF0 (common flash reset command)
-        if value == 0xF0 {
+        // Reset command mode on 0xF0 (common flash reset command), except
+        // as the data byte of a program command
+        if value == 0xF0 && self.write_state != FlashWriteState::SawA0 {
             self.command = FlashCommand::None;
             self.write_state = FlashWriteState::Idle;
             return;
@@ -234,8 +312,24 @@ impl Flash {
         };
     }
 
+    /// Enable or disable boot sector protection (on by default, as on
+    /// retail hardware). Disabling it lets custom boot code be flashed.
+    pub fn set_boot_protect(&mut self, enabled: bool) {
+        self.boot_protect = enabled;
+    }
+
+    /// Whether boot sector protection is enabled
+    pub fn boot_protect(&self) -> bool {
+        self.boot_protect
+    }
+
+    /// True if a CPU program/erase at `addr` must be ignored
+    fn is_protected(&self, addr: u32) -> bool {
+        self.boot_protect && (addr & (addr::FLASH_SIZE as u32 - 1)) < addr::BOOT_CODE_END
+    }
+
     fn erase_sector(&mut self, addr: u32) {
-        if self.data.is_empty() {
+        if self.data.is_empty() || self.is_protected(addr) {
             return;
         }
         let (start, size) = if addr < 0x10000 {
@@ -249,14 +343,16 @@ impl Flash {
         for offset in start..end {
             self.data[offset as usize] = 0xFF;
         }
+        self.fetch_line = FetchLine::default();
     }
 
     fn program_byte(&mut self, addr: u32, value: u8) {
-        if self.data.is_empty() {
+        if self.data.is_empty() || self.is_protected(addr) {
             return;
         }
         let offset = (addr & (addr::FLASH_SIZE as u32 - 1)) as usize;
         self.data[offset] &= value;
+        self.fetch_line = FetchLine::default();
     }
 
     /// Check if flash is initialized
@@ -273,6 +369,7 @@ impl Flash {
     pub fn load_data(&mut self, data: &[u8]) {
         let len = data.len().min(addr::FLASH_SIZE);
         self.data[..len].copy_from_slice(&data[..len]);
+        self.fetch_line = FetchLine::default();
         self.initialized = true;
         self.command = FlashCommand::None;
         self.write_state = FlashWriteState::Idle;
@@ -283,6 +380,7 @@ impl Flash {
         if !self.data.is_empty() {
             self.data.fill(0xFF);
         }
+        self.fetch_line = FetchLine::default();
         self.initialized = false;
         self.command = FlashCommand::None;
         self.write_state = FlashWriteState::Idle;
@@ -308,6 +406,7 @@ pub enum FlashError {
 ///
 /// The TI-84 Plus CE has 256KB of user RAM plus ~150KB of VRAM,
 /// all in a single contiguous region starting at 0xD00000.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Ram {
     /// RAM contents
     data: Vec<u8>,
@@ -432,6 +531,31 @@ mod tests {
     mod flash_tests {
         use super::*;
 
+        #[test]
+        fn test_fetch_line_tracks_flash_changes() {
+            let mut flash = Flash::new();
+            assert_eq!(flash.fetch(0x10), 0xFF);
+            let rom: Vec<u8> = (0..64).collect();
+            flash.load_rom(&rom).unwrap();
+            assert_eq!(flash.fetch(0x10), 0x10);
+            assert_eq!(flash.fetch(0x3F), 0x3F);
+            assert_eq!(flash.fetch(0x400010), 0x10); // mirrored
+
+            flash.write_direct(0x11, 0xAA);
+            assert_eq!(flash.fetch(0x11), 0xAA);
+
+            // Erase status reads through, then the erased line is refetched
+            flash.set_boot_protect(false);
+            for (addr, value) in [(0xAAA, 0xAA), (0x555, 0x55), (0xAAA, 0x80), (0xAAA, 0xAA), (0x555, 0x55), (0, 0x30)] {
+                flash.write_cpu(addr, value);
+            }
+            assert_eq!(flash.fetch(0x11), 0x80);
+            while flash.command != FlashCommand::None {
+                flash.read(0);
+            }
+            assert_eq!(flash.fetch(0x11), 0xFF);
+        }
+
         #[test]
         fn test_new_flash_is_erased() {
             let mut flash = Flash::new();
@@ -480,6 +604,44 @@ mod tests {
             assert!(!flash.is_initialized());
             assert_eq!(flash.read(0), 0xFF);
         }
+
+        fn program(flash: &mut Flash, addr: u32, value: u8) {
+            for (a, v) in [(0xAAA, 0xAA), (0x555, 0x55), (0xAAA, 0xA0), (addr, value)] {
+                flash.write_cpu(a, v);
+            }
+        }
+
+        fn erase(flash: &mut Flash, addr: u32) {
+            for (a, v) in [(0xAAA, 0xAA), (0x555, 0x55), (0xAAA, 0x80), (0xAAA, 0xAA), (0x555, 0x55), (addr, 0x30)] {
+                flash.write_cpu(a, v);
+            }
+        }
+
+        #[test]
+        fn test_boot_sectors_protected() {
+            let mut flash = Flash::new();
+            flash.load_rom(&vec![0x5A; 0x30000]).unwrap();
+            assert!(flash.boot_protect());
+
+            program(&mut flash, 0x1000, 0x00);
+            erase(&mut flash, 0x1FFFF);
+            // Mirrored addresses hit the same sectors
+            program(&mut flash, 0x401000, 0x00);
+            assert_eq!(flash.peek(0x1000), 0x5A);
+            assert_eq!(flash.peek(0x1E000), 0x5A);
+
+            // Past the boot code, commands still work
+            program(&mut flash, 0x20000, 0x00);
+            assert_eq!(flash.peek(0x20000), 0x00);
+            erase(&mut flash, 0x20000);
+            assert_eq!(flash.peek(0x2FFFF), 0xFF);
+
+            flash.set_boot_protect(false);
+            program(&mut flash, 0x1000, 0x00);
+            assert_eq!(flash.peek(0x1000), 0x00);
+            flash.reset();
+            assert!(!flash.boot_protect(), "setting survives reset");
+        }
     }
 
     mod ram_tests {
@@ -0,0 +1,192 @@
+//! Address-space annotations for disassembler and hex-view frontends
+//!
+//! `annotations` describes the full 24-bit address space as sorted,
+//! non-overlapping `[start, end)` regions built from the same constants the
+//! bus decodes with, so frontends can color flash, RAM, VRAM and MMIO
+//! without keeping their own copy of the map. `Emu::memory_map` adds the
+//! extension devices attached at runtime.
+//!
+//! Text export, one region per line (hex addresses, `end` exclusive):
+//!
+//! ```text
+//! E00000 E00100 mmio control
+//! ```
+
+use crate::memory::addr;
+use crate::peripherals::mmio_regions;
+
+/// What the bus does with accesses to a region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum RegionKind {
+    Flash = 0,
+    Ram = 1,
+    Vram = 2,
+    /// Peripheral registers, or mapped MMIO no peripheral decodes
+    Mmio = 3,
+    /// Reads return noise, writes are dropped
+    Unmapped = 4,
+    /// CE toolchain debug console ports (write-only, when enabled)
+    Debug = 5,
+    /// Device attached through the extension API
+    Extension = 6,
+}
+
+impl RegionKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            RegionKind::Flash => "flash",
+            RegionKind::Ram => "ram",
+            RegionKind::Vram => "vram",
+            RegionKind::Mmio => "mmio",
+            RegionKind::Unmapped => "unmapped",
+            RegionKind::Debug => "debug",
+            RegionKind::Extension => "extension",
+        }
+    }
+}
+
+/// One `[start, end)` region of the CPU address space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Annotation {
+    pub start: u32,
+    pub end: u32,
+    pub kind: RegionKind,
+    pub name: &'static str,
+}
+
+impl Annotation {
+    const fn new(start: u32, end: u32, kind: RegionKind, name: &'static str) -> Self {
+        Self { start, end, kind, name }
+    }
+
+    pub fn contains(&self, addr: u32) -> bool {
+        addr >= self.start && addr < self.end
+    }
+}
+
+/// MMIO windows (see Bus::read_byte): mapped ones are filled in with
+/// peripheral regions, the rest describe themselves
+const MMIO_WINDOWS: [Annotation; 8] = [
+    Annotation::new(0xE00000, 0xE40000, RegionKind::Mmio, "mmio"),
+    Annotation::new(0xE40000, 0xF00000, RegionKind::Unmapped, "unmapped mmio"),
+    Annotation::new(0xF00000, 0xFB0000, RegionKind::Mmio, "mmio"),
+    Annotation::new(0xFB0000, 0xFC0000, RegionKind::Debug, "debug stdout"),
+    Annotation::new(0xFC0000, 0xFD0000, RegionKind::Debug, "debug stderr"),
+    Annotation::new(0xFD0000, 0xFD0001, RegionKind::Debug, "debug control"),
+    Annotation::new(0xFD0001, 0xFF0000, RegionKind::Unmapped, "unmapped mmio"),
+    Annotation::new(0xFF0000, 0x1000000, RegionKind::Mmio, "mmio"),
+];
+
+/// The static memory map, covering 0x000000-0xFFFFFF
+pub fn annotations() -> Vec<Annotation> {
+    let mut map = vec![
+        Annotation::new(addr::FLASH_START, addr::FLASH_END, RegionKind::Flash, "flash"),
+        // Bus::decode_address routes 0x400000-0xBFFFFF to flash as well
+        Annotation::new(addr::FLASH_END, 0xC00000, RegionKind::Flash, "flash mirror"),
+        Annotation::new(0xC00000, addr::RAM_START, RegionKind::Unmapped, "unmapped"),
+        Annotation::new(addr::RAM_START, addr::VRAM_START, RegionKind::Ram, "ram"),
+        Annotation::new(addr::VRAM_START, addr::RAM_END, RegionKind::Vram, "vram"),
+        Annotation::new(addr::RAM_END, addr::PORT_START, RegionKind::Unmapped, "unmapped"),
+    ];
+    for window in MMIO_WINDOWS {
+        if window.kind != RegionKind::Mmio {
+            map.push(window);
+            continue;
+        }
+        let mut cursor = window.start;
+        for (start, end, name) in mmio_regions().filter(|&(start, _, _)| window.contains(start)) {
+            if start > cursor {
+                map.push(Annotation::new(cursor, start, RegionKind::Mmio, "mmio"));
+            }
+            map.push(Annotation::new(start, end, RegionKind::Mmio, name));
+            cursor = end;
+        }
+        if cursor < window.end {
+            map.push(Annotation::new(cursor, window.end, RegionKind::Mmio, "mmio"));
+        }
+    }
+    map
+}
+
+/// Split the region containing `overlay.start` so `overlay` replaces that
+/// part of it. Extensions always sit inside a single unmapped window.
+pub(crate) fn overlay(map: &mut Vec<Annotation>, overlay: Annotation) {
+    let Some(index) = map.iter().position(|a| a.contains(overlay.start)) else {
+        return;
+    };
+    let outer = map[index];
+    let mut parts = Vec::with_capacity(3);
+    if outer.start < overlay.start {
+        parts.push(Annotation { end: overlay.start, ..outer });
+    }
+    parts.push(overlay);
+    if overlay.end < outer.end {
+        parts.push(Annotation { start: overlay.end, ..outer });
+    }
+    map.splice(index..=index, parts);
+}
+
+/// Region containing `addr` (24-bit)
+pub fn lookup(map: &[Annotation], addr: u32) -> Option<&Annotation> {
+    let addr = addr & addr::ADDR_MASK;
+    let index = map.partition_point(|a| a.end <= addr);
+    map.get(index).filter(|a| a.contains(addr))
+}
+
+/// Render `map` in the text export format
+pub fn to_text(map: &[Annotation]) -> String {
+    let mut text = String::new();
+    for a in map {
+        text.push_str(&format!("{:06X} {:06X} {} {}\n", a.start, a.end, a.kind.name(), a.name));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::{Bus, MemoryRegion};
+
+    #[test]
+    fn test_map_covers_address_space() {
+        let map = annotations();
+        assert_eq!(map.first().unwrap().start, 0);
+        assert_eq!(map.last().unwrap().end, 0x1000000);
+        for pair in map.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start, "gap or overlap at {:06X}", pair[0].end);
+        }
+
+        // Agrees with the bus decoder outside MMIO
+        for a in map.iter().filter(|a| a.start < addr::PORT_START) {
+            let expected = match Bus::decode_address(a.start) {
+                MemoryRegion::Flash => RegionKind::Flash,
+                MemoryRegion::Ram => RegionKind::Ram,
+                MemoryRegion::Vram => RegionKind::Vram,
+                _ => RegionKind::Unmapped,
+            };
+            assert_eq!(a.kind, expected, "{:06X}", a.start);
+        }
+    }
+
+    #[test]
+    fn test_lookup_and_overlay() {
+        let mut map = annotations();
+        assert_eq!(lookup(&map, 0xF50010).unwrap().name, "keypad");
+        assert_eq!(lookup(&map, 0xE30200).unwrap().name, "lcd");
+        assert_eq!(lookup(&map, 0xFF0005).unwrap().name, "control");
+        assert_eq!(lookup(&map, 0xE00100).unwrap().name, "mmio");
+        assert_eq!(lookup(&map, 0xD40000).unwrap().kind, RegionKind::Vram);
+
+        let len = map.len();
+        overlay(&mut map, Annotation::new(0xE40100, 0xE40200, RegionKind::Extension, "extension"));
+        assert_eq!(map.len(), len + 2);
+        assert_eq!(lookup(&map, 0xE400FF).unwrap().kind, RegionKind::Unmapped);
+        assert_eq!(lookup(&map, 0xE40100).unwrap().kind, RegionKind::Extension);
+        assert_eq!(lookup(&map, 0xE40200).unwrap().kind, RegionKind::Unmapped);
+
+        let text = to_text(&map);
+        assert!(text.contains("F50000 F51000 mmio keypad\n"));
+        assert!(text.starts_with("000000 400000 flash flash\n"));
+    }
+}
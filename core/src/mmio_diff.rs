@@ -0,0 +1,166 @@
+//! Per-frame diff of peripheral registers
+//!
+//! While enabled, every CPU write to a peripheral register (memory-mapped
+//! at 0xE00000+ or through OUT) is noted with the value it replaced and the
+//! PC of the writing instruction. At each frame boundary the registers whose
+//! value differs from the start of the frame become the frame's report and
+//! an `MmioDiff` event carries how many there were. Registers written back
+//! to their old value don't appear.
+//!
+//! Debugger peeks/pokes and peripheral-internal updates (timer counting,
+//! status bits set by hardware) are not writes and are not reported.
+
+use std::collections::HashMap;
+
+use crate::peripherals::mmio_regions;
+
+/// Registers tracked per frame; writes to further registers are dropped
+pub const MAX_TRACKED_REGISTERS: usize = 512;
+
+/// One changed register (C layout, see emu.h)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MmioChange {
+    /// CPU address (0xE00000+), or the port number for OUT writes
+    pub addr: u32,
+    /// PC of the last instruction that wrote the register this frame
+    pub pc: u32,
+    /// Writes to the register this frame
+    pub writes: u32,
+    /// Value at the first write of the frame
+    pub old_value: u8,
+    /// Value after the last write
+    pub new_value: u8,
+    /// 1 if `addr` is an OUT port number, 0 for a memory-mapped address
+    pub port: u8,
+    pub _reserved: u8,
+}
+
+impl MmioChange {
+    /// Peripheral the register belongs to
+    pub fn peripheral(&self) -> &'static str {
+        if self.port != 0 {
+            port_name(self.addr as u16)
+        } else {
+            mmio_regions()
+                .find(|&(start, end, _)| (start..end).contains(&self.addr))
+                .map_or("mmio", |(_, _, name)| name)
+        }
+    }
+}
+
+/// Peripheral selected by an OUT port number (bits 12-15)
+pub fn port_name(port: u16) -> &'static str {
+    match port >> 12 {
+        0x0 | 0xF => "control",
+        0x1 => "flash controller",
+        0x2 => "sha256",
+        0x3 => "usb",
+        0x4 => "lcd",
+        0x5 => "interrupt",
+        0x6 => "watchdog",
+        0x7 => "timers",
+        0x8 => "rtc",
+        0x9 => "protected",
+        0xA => "keypad",
+        0xB => "backlight",
+        0xD => "spi",
+        0xE => "uart",
+        _ => "port",
+    }
+}
+
+/// Writes collected during the current frame plus the last frame's report
+#[derive(Debug, Default)]
+pub struct MmioDiff {
+    pending: Vec<MmioChange>,
+    index: HashMap<(u8, u32), usize>,
+    report: Vec<MmioChange>,
+}
+
+impl MmioDiff {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note a write of `new_value` over `old_value` by the instruction at `pc`
+    #[inline]
+    pub fn record(&mut self, addr: u32, port: bool, old_value: u8, new_value: u8, pc: u32) {
+        let key = (port as u8, addr);
+        if let Some(&i) = self.index.get(&key) {
+            let change = &mut self.pending[i];
+            change.new_value = new_value;
+            change.pc = pc;
+            change.writes += 1;
+        } else if self.pending.len() < MAX_TRACKED_REGISTERS {
+            self.index.insert(key, self.pending.len());
+            self.pending.push(MmioChange {
+                addr,
+                pc,
+                writes: 1,
+                old_value,
+                new_value,
+                port: port as u8,
+                _reserved: 0,
+            });
+        }
+    }
+
+    /// Close the frame: registers that changed become the report (ports
+    /// after memory-mapped registers, each by address). Returns their count.
+    pub fn finish_frame(&mut self) -> usize {
+        self.index.clear();
+        self.report = std::mem::take(&mut self.pending);
+        self.report.retain(|c| c.old_value != c.new_value);
+        self.report.sort_by_key(|c| (c.port, c.addr));
+        self.report.len()
+    }
+
+    /// Registers changed during the last completed frame
+    pub fn report(&self) -> &[MmioChange] {
+        &self.report
+    }
+
+    /// Forget the current frame's writes (keeps the last report)
+    pub fn discard_pending(&mut self) {
+        self.pending.clear();
+        self.index.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_report_keeps_net_changes() {
+        let mut diff = MmioDiff::new();
+        diff.record(0xF00004, false, 0x00, 0x01, 0x1000);
+        diff.record(0xF00004, false, 0x01, 0x03, 0x1004);
+        diff.record(0xE30018, false, 0x2D, 0x2C, 0x2000);
+        diff.record(0xE30018, false, 0x2C, 0x2D, 0x2004);
+        diff.record(0x5004, true, 0x00, 0x10, 0x3000);
+
+        assert_eq!(diff.finish_frame(), 2);
+        let report = diff.report();
+        assert_eq!(report[0].addr, 0xF00004);
+        assert_eq!((report[0].old_value, report[0].new_value), (0x00, 0x03));
+        assert_eq!((report[0].writes, report[0].pc), (2, 0x1004));
+        assert_eq!(report[0].peripheral(), "interrupt");
+        assert_eq!((report[1].addr, report[1].port), (0x5004, 1));
+        assert_eq!(report[1].peripheral(), "interrupt");
+
+        // The next frame starts fresh
+        assert_eq!(diff.finish_frame(), 0);
+        assert!(diff.report().is_empty());
+    }
+
+    #[test]
+    fn test_tracked_registers_are_bounded() {
+        let mut diff = MmioDiff::new();
+        for i in 0..MAX_TRACKED_REGISTERS as u32 + 10 {
+            diff.record(0xE30000 + i, false, 0, 1, 0);
+        }
+        assert_eq!(diff.finish_frame(), MAX_TRACKED_REGISTERS);
+    }
+}
@@ -0,0 +1,186 @@
+//! Opcode coverage
+//!
+//! Counts executed instructions per opcode on each page (main, CB, ED,
+//! DD/FD, DD/FD CB) to show which parts of the instruction set a ROM or
+//! program actually uses. The report lists the opcodes that ran but the
+//! core treats as NONI (ignored/trapped, so most likely to be wrong), then
+//! the defined opcodes that never ran: the ones no real code has checked.
+//!
+//! Instructions are decoded from memory at PC with the same walk as the
+//! disassembler (`opcodes::decode`), at the points the heatmap counts.
+//! Prefix and suffix bytes count toward the instruction they modify.
+//!
+//! # Export format
+//!
+//! `PAGES.len() * 256` u32 counts, page by page in `PAGES`
+//! order, indexed by opcode. Counts saturate.
+
+use std::fmt::Write;
+
+use crate::cpu::opcodes::{self, Page, NONI_MNEMONIC};
+
+/// Pages in export order
+pub const PAGES: [Page; 5] = [Page::Main, Page::Cb, Page::Ed, Page::Index, Page::IndexCb];
+/// Number of counters in the export
+pub const EXPORT_COUNTS: usize = PAGES.len() * 256;
+
+/// Bytes shown before the opcode when listing a page
+fn page_prefix(page: Page) -> &'static str {
+    match page {
+        Page::Main => "",
+        Page::Cb => "CB ",
+        Page::Ed => "ED ",
+        Page::Index => "DD/FD ",
+        Page::IndexCb => "DD/FD CB d ",
+    }
+}
+
+fn page_name(page: Page) -> &'static str {
+    match page {
+        Page::Main => "main",
+        Page::Cb => "CB",
+        Page::Ed => "ED",
+        Page::Index => "DD/FD",
+        Page::IndexCb => "DD/FD CB",
+    }
+}
+
+/// Whether `opcode` can end a decoded instruction on `page` (prefix and
+/// suffix bytes never do)
+fn reachable(page: Page, opcode: u8) -> bool {
+    match page {
+        Page::Main => !matches!(opcode, 0xCB | 0xDD | 0xED | 0xFD) && opcodes::suffix_modes(opcode).is_none(),
+        Page::Index => !matches!(opcode, 0xCB | 0xDD | 0xED | 0xFD),
+        Page::Cb | Page::Ed | Page::IndexCb => true,
+    }
+}
+
+/// Whether the core implements `opcode` on `page` (anything but NONI)
+fn defined(page: Page, opcode: u8) -> bool {
+    reachable(page, opcode) && opcodes::info(page, opcode).mnemonic != NONI_MNEMONIC
+}
+
+/// Per-opcode execution counters
+pub struct OpcodeCoverage {
+    counts: Vec<u32>,
+}
+
+impl OpcodeCoverage {
+    pub fn new() -> Self {
+        Self { counts: vec![0; EXPORT_COUNTS] }
+    }
+
+    fn index(page: Page, opcode: u8) -> usize {
+        let page = PAGES.iter().position(|&p| p == page).unwrap_or(0);
+        page * 256 + opcode as usize
+    }
+
+    /// Count the instruction starting with `bytes` (decoded with `adl`).
+    /// Truncated byte sequences are ignored.
+    #[inline]
+    pub fn record(&mut self, bytes: &[u8], adl: bool) {
+        if let Some(decoded) = opcodes::decode(bytes, adl) {
+            let i = Self::index(decoded.page, decoded.opcode);
+            self.counts[i] = self.counts[i].saturating_add(1);
+        }
+    }
+
+    pub fn count(&self, page: Page, opcode: u8) -> u32 {
+        self.counts[Self::index(page, opcode)]
+    }
+
+    /// Zero every counter
+    pub fn clear(&mut self) {
+        self.counts.fill(0);
+    }
+
+    /// Write the export format into `out`, which must hold EXPORT_COUNTS
+    /// entries. Returns the number written, or None if `out` is too small.
+    pub fn export(&self, out: &mut [u32]) -> Option<usize> {
+        out.get_mut(..EXPORT_COUNTS)?.copy_from_slice(&self.counts);
+        Some(EXPORT_COUNTS)
+    }
+
+    /// Human-readable summary (see the module docs)
+    pub fn report(&self) -> String {
+        let mut text = String::new();
+        let mut unimplemented = Vec::new();
+        let mut missing = Vec::new();
+        let (mut total_run, mut total_defined) = (0, 0);
+
+        for page in PAGES {
+            let (mut run, mut count) = (0, 0);
+            let mut page_missing = Vec::new();
+            for opcode in 0..=255u8 {
+                let hits = self.count(page, opcode);
+                if !defined(page, opcode) {
+                    if hits > 0 {
+                        unimplemented.push((page, opcode, hits));
+                    }
+                    continue;
+                }
+                count += 1;
+                if hits > 0 {
+                    run += 1;
+                } else {
+                    page_missing.push(opcode);
+                }
+            }
+            let _ = writeln!(text, "{:<9} {:>3}/{:<3} executed", page_name(page), run, count);
+            total_run += run;
+            total_defined += count;
+            missing.push((page, page_missing));
+        }
+        let _ = writeln!(text, "{:<9} {:>3}/{:<3} executed", "total", total_run, total_defined);
+
+        if !unimplemented.is_empty() {
+            let _ = writeln!(text, "\nExecuted but unimplemented (NONI):");
+            for (page, opcode, hits) in unimplemented {
+                let _ = writeln!(text, "  {}{:02X}  x{}", page_prefix(page), opcode, hits);
+            }
+        }
+
+        let _ = writeln!(text, "\nNever executed:");
+        for (page, opcodes) in missing.iter().filter(|(_, opcodes)| !opcodes.is_empty()) {
+            for row in opcodes.chunks(16) {
+                let row: Vec<String> = row.iter().map(|op| format!("{:02X}", op)).collect();
+                let _ = writeln!(text, "  {:<11}{}", page_prefix(*page), row.join(" "));
+            }
+        }
+        text
+    }
+}
+
+impl Default for OpcodeCoverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages_and_report() {
+        let mut coverage = OpcodeCoverage::new();
+        coverage.record(&[0x00], true); // NOP
+        coverage.record(&[0x5B, 0xDD, 0x21, 0x00, 0x00, 0xD0], false); // .LIL LD IX,nn
+        coverage.record(&[0xFD, 0xCB, 0x05, 0x46], true); // BIT 0,(IY+5)
+        coverage.record(&[0xED, 0x4E], true); // trap
+        coverage.record(&[0xDD, 0x21, 0x00], true); // truncated
+        assert_eq!(coverage.count(Page::Main, 0x00), 1);
+        assert_eq!(coverage.count(Page::Index, 0x21), 1);
+        assert_eq!(coverage.count(Page::IndexCb, 0x46), 1);
+
+        let report = coverage.report();
+        assert!(report.contains("Executed but unimplemented (NONI):\n  ED 4E  x1\n"), "{}", report);
+        assert!(report.contains("\n  DD/FD CB d 00 01 02"), "{}", report);
+        assert!(report.starts_with("main        1/"), "{}", report);
+
+        let mut out = vec![0; EXPORT_COUNTS];
+        assert_eq!(coverage.export(&mut out), Some(EXPORT_COUNTS));
+        assert_eq!(out[3 * 256 + 0x21], 1);
+        assert_eq!(coverage.export(&mut out[..4]), None);
+    }
+}
@@ -0,0 +1,224 @@
+//! TI-OS menu navigation
+//!
+//! The OS screens tests and frontends most often need (mode, the MEM menu
+//! and its entries, the catalog) as a graph: each edge is the key sequence
+//! that moves from one screen to the next, tagged with the first OS
+//! version it applies to. `keys_to` finds the path from the homescreen,
+//! prefixed with [2nd][mode] (QUIT) so it works from wherever the OS is,
+//! and `open` presses it on a running emulator:
+//!
+//! ```text
+//! os_nav::open(&mut emu, Screen::MemManagement);
+//! ```
+//!
+//! The OS version is read from the OS header in flash (see `RomInfo`); an
+//! OS without a readable header is navigated as `DEFAULT_OS`.
+//! Screens added by a later OS are unreachable on older ones, and an edge
+//! for a later OS that reaches the same screen replaces the older one.
+//!
+//! Keys are (row, col) in the keypad matrix, as for `Emu::set_key`.
+//! Errors (C API): -10 no ROM loaded, -30 unknown screen.
+
+use std::collections::VecDeque;
+
+use crate::rom_info::{OsVersion, RomInfo};
+use crate::Emu;
+
+/// Keypad matrix position (row, col)
+pub type Key = (usize, usize);
+
+/// Keys the navigation table uses
+pub mod key {
+    use super::Key;
+    use crate::key_name::KeyName;
+
+    pub const SECOND: Key = KeyName::Second.position();
+    pub const MODE: Key = KeyName::Mode.position();
+    pub const ENTER: Key = KeyName::Enter.position();
+    pub const ADD: Key = KeyName::Add.position();
+    pub const CLEAR: Key = KeyName::Clear.position();
+    pub const NUM_0: Key = KeyName::Num0.position();
+    pub const NUM_1: Key = KeyName::Num1.position();
+    pub const NUM_2: Key = KeyName::Num2.position();
+    pub const NUM_7: Key = KeyName::Num7.position();
+    pub const PRGM: Key = KeyName::Prgm.position();
+    pub const LEFT: Key = KeyName::Left.position();
+}
+
+/// [2nd][mode]: back to the homescreen from any menu or app
+pub const QUIT: [Key; 2] = [key::SECOND, key::MODE];
+
+/// Cycles each key is held (~30 ms at 48 MHz)
+pub const HOLD_CYCLES: u32 = 1_500_000;
+
+/// Cycles between keys, long enough for the OS to draw the next screen
+/// and see a repeated key as a new press
+pub const GAP_CYCLES: u32 = 3_000_000;
+
+/// OS screen a navigation can target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Screen {
+    Home = 0,
+    /// MODE settings
+    Mode = 1,
+    /// [2nd][+] MEM menu
+    Memory = 2,
+    /// MEM 1:About
+    MemAbout = 3,
+    /// MEM 2:Mem Management/Delete...
+    MemManagement = 4,
+    /// MEM 7:Reset...
+    MemReset = 5,
+    /// [2nd][0] CATALOG
+    Catalog = 6,
+    /// [prgm] program menu (EXEC EDIT NEW)
+    Programs = 7,
+    /// PRGM NEW 2:Python App, on Python models from OS 5.6
+    PythonApp = 8,
+}
+
+impl Screen {
+    pub const ALL: [Screen; 9] = [
+        Screen::Home,
+        Screen::Mode,
+        Screen::Memory,
+        Screen::MemAbout,
+        Screen::MemManagement,
+        Screen::MemReset,
+        Screen::Catalog,
+        Screen::Programs,
+        Screen::PythonApp,
+    ];
+
+    /// Screen for a C API code
+    pub fn from_raw(raw: u32) -> Option<Screen> {
+        Screen::ALL.get(raw as usize).copied()
+    }
+}
+
+/// First CE OS
+pub const MIN_OS: OsVersion = OsVersion::new(5, 0, 0);
+
+/// Assumed when the OS header can't be read
+pub const DEFAULT_OS: OsVersion = MIN_OS;
+
+/// One step of the menu graph
+struct Edge {
+    from: Screen,
+    to: Screen,
+    keys: &'static [Key],
+    since: OsVersion,
+}
+
+const EDGES: &[Edge] = &[
+    Edge { from: Screen::Home, to: Screen::Mode, keys: &[key::MODE], since: MIN_OS },
+    Edge { from: Screen::Home, to: Screen::Memory, keys: &[key::SECOND, key::ADD], since: MIN_OS },
+    Edge { from: Screen::Home, to: Screen::Catalog, keys: &[key::SECOND, key::NUM_0], since: MIN_OS },
+    Edge { from: Screen::Memory, to: Screen::MemAbout, keys: &[key::NUM_1], since: MIN_OS },
+    Edge { from: Screen::Memory, to: Screen::MemManagement, keys: &[key::NUM_2], since: MIN_OS },
+    Edge { from: Screen::Memory, to: Screen::MemReset, keys: &[key::NUM_7], since: MIN_OS },
+    Edge { from: Screen::Home, to: Screen::Programs, keys: &[key::PRGM], since: MIN_OS },
+    // LEFT wraps from EXEC to the NEW tab
+    Edge { from: Screen::Programs, to: Screen::PythonApp, keys: &[key::LEFT, key::NUM_2], since: OsVersion::new(5, 6, 0) },
+];
+
+/// Edges out of `from` for `os`: the newest edge to each screen that the
+/// OS is recent enough for
+fn edges_from(from: Screen, os: OsVersion) -> impl Iterator<Item = &'static Edge> {
+    EDGES.iter().filter(move |edge| {
+        edge.from == from
+            && edge.since <= os
+            && !EDGES.iter().any(|other| {
+                other.from == from && other.to == edge.to && other.since <= os && other.since > edge.since
+            })
+    })
+}
+
+/// Key sequence from `from` to `to` on `os` (fewest screens on the way),
+/// or None if `to` can't be reached
+pub fn route(from: Screen, to: Screen, os: OsVersion) -> Option<Vec<Key>> {
+    let mut prev: Vec<Option<&Edge>> = vec![None; Screen::ALL.len()];
+    let mut seen = [false; Screen::ALL.len()];
+    let mut queue = VecDeque::from([from]);
+    seen[from as usize] = true;
+
+    while let Some(screen) = queue.pop_front() {
+        if screen == to {
+            let mut edges = Vec::new();
+            let mut at = to;
+            while let Some(edge) = prev[at as usize] {
+                edges.push(edge);
+                at = edge.from;
+            }
+            return Some(edges.iter().rev().flat_map(|edge| edge.keys.iter().copied()).collect());
+        }
+        for edge in edges_from(screen, os) {
+            if !seen[edge.to as usize] {
+                seen[edge.to as usize] = true;
+                prev[edge.to as usize] = Some(edge);
+                queue.push_back(edge.to);
+            }
+        }
+    }
+    None
+}
+
+/// Keys that open `to` from anywhere in the OS: QUIT, then the route from
+/// the homescreen
+pub fn keys_to(to: Screen, os: OsVersion) -> Option<Vec<Key>> {
+    let mut keys = QUIT.to_vec();
+    keys.extend(route(Screen::Home, to, os)?);
+    Some(keys)
+}
+
+/// Press and release each key in turn, running the emulator between them
+pub fn press(emu: &mut Emu, keys: &[Key]) {
+    for &(row, col) in keys {
+        emu.set_key(row, col, true);
+        emu.run_cycles(HOLD_CYCLES);
+        emu.set_key(row, col, false);
+        emu.run_cycles(GAP_CYCLES);
+    }
+}
+
+/// Navigate a running OS to `to`, for the OS version in flash.
+/// Returns the number of keys pressed (0 if that OS has no such screen).
+pub fn open(emu: &mut Emu, to: Screen) -> usize {
+    let os = RomInfo::parse(emu.flash_data()).os_version.unwrap_or(DEFAULT_OS);
+    let keys = keys_to(to, os).unwrap_or_default();
+    press(emu, &keys);
+    keys.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routes() {
+        let os = DEFAULT_OS;
+        assert_eq!(route(Screen::Home, Screen::Home, os), Some(vec![]));
+        assert_eq!(
+            keys_to(Screen::MemManagement, os),
+            Some(vec![key::SECOND, key::MODE, key::SECOND, key::ADD, key::NUM_2])
+        );
+        assert_eq!(keys_to(Screen::Catalog, os).unwrap()[2..], [key::SECOND, key::NUM_0]);
+        // No edges lead back out of a submenu; QUIT does that
+        assert_eq!(route(Screen::MemAbout, Screen::Mode, os), None);
+        for screen in Screen::ALL {
+            assert!(keys_to(screen, OsVersion::new(5, 8, 0)).is_some(), "{:?} unreachable", screen);
+            assert_eq!(Screen::from_raw(screen as u32), Some(screen));
+        }
+        assert_eq!(Screen::from_raw(9), None);
+    }
+
+    #[test]
+    fn test_version_specific_edges() {
+        // The Python App only exists from OS 5.6
+        assert_eq!(keys_to(Screen::PythonApp, OsVersion::new(5, 5, 0)), None);
+        assert_eq!(
+            route(Screen::Home, Screen::PythonApp, OsVersion::new(5, 6, 0)),
+            Some(vec![key::PRGM, key::LEFT, key::NUM_2])
+        );
+    }
+}
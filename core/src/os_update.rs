@@ -0,0 +1,222 @@
+//! OS update files (.8eu) and a host-side OS flasher
+//!
+//! An OS upgrade is a `**TIFL**` flash file: one or more 78-byte headers,
+//! each followed by its data. Records for the CE (device 0x73) with the OS
+//! data type (0x23) are concatenated into the image that the boot code
+//! programs at `OS_START`; other records (certificates, licenses) are
+//! skipped.
+//!
+//! `program` writes the image from the host: every sector the image
+//! touches is erased and each byte is programmed with the chip's AA/55
+//! command sequences, so the boot sector protection and NOR semantics in
+//! `memory::Flash` apply, and the result is read back. This is a shortcut
+//! past "Transfer OS", not an emulation of it: the boot code's receive mode
+//! (the OS streamed over the link, checked by the boot code's own signature
+//! validation) never runs, so an OS the boot code would reject is flashed
+//! all the same.
+//!
+//! Reference: libtifiles2 files8x.c (TIFL layout), WikiTI flash layout
+
+use crate::memory::{addr, Flash};
+
+/// Magic signature at the start of every flash file record
+const MAGIC: &[u8; 8] = b"**TIFL**";
+
+/// Header size; the data length is the final 4 bytes
+const HEADER_SIZE: usize = 78;
+
+/// Device type of the TI-84 Plus CE / TI-83 Premium CE
+pub const DEVICE_CE: u8 = 0x73;
+
+/// Data type of OS records
+pub const DATA_TYPE_OS: u8 = 0x23;
+
+/// Flash address the OS is programmed at (just past the boot code)
+pub const OS_START: u32 = addr::BOOT_CODE_END;
+
+/// Largest OS image that fits in flash
+pub const MAX_OS_SIZE: usize = addr::FLASH_SIZE - OS_START as usize;
+
+/// Errors that can occur while parsing or flashing an OS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OsUpdateError {
+    /// Not a flash file, or a record is truncated
+    BadFile,
+    /// No OS record for the CE in the file
+    NotCeOs,
+    /// The image does not fit between OS_START and the end of flash
+    TooLarge,
+    /// Flash readback differs from the image at this address
+    VerifyFailed { addr: u32 },
+}
+
+impl std::fmt::Display for OsUpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OsUpdateError::BadFile => write!(f, "not a valid flash file (expected **TIFL**)"),
+            OsUpdateError::NotCeOs => write!(f, "no TI-84 Plus CE OS in file"),
+            OsUpdateError::TooLarge => write!(f, "OS image larger than flash"),
+            OsUpdateError::VerifyFailed { addr } => write!(f, "verify failed at 0x{:06X}", addr),
+        }
+    }
+}
+
+impl std::error::Error for OsUpdateError {}
+
+/// Parsed OS upgrade
+#[derive(Debug, Clone)]
+pub struct OsImage {
+    /// Name from the first OS record header
+    pub name: String,
+    /// Version from the first OS record header (major, minor)
+    pub version: (u8, u8),
+    /// OS bytes, programmed at OS_START
+    pub data: Vec<u8>,
+}
+
+impl OsImage {
+    /// Parse a .8eu file
+    pub fn parse(file: &[u8]) -> Result<Self, OsUpdateError> {
+        let mut offset = 0;
+        let mut image: Option<OsImage> = None;
+
+        while offset < file.len() {
+            let header = file.get(offset..offset + HEADER_SIZE).ok_or(OsUpdateError::BadFile)?;
+            if &header[0..8] != MAGIC {
+                return Err(OsUpdateError::BadFile);
+            }
+            let len = u32::from_le_bytes([header[74], header[75], header[76], header[77]]) as usize;
+            let start = offset + HEADER_SIZE;
+            let data = file.get(start..start + len).ok_or(OsUpdateError::BadFile)?;
+            offset = start + len;
+
+            if header[48] != DEVICE_CE || header[49] != DATA_TYPE_OS {
+                continue;
+            }
+            let image = image.get_or_insert_with(|| {
+                let name_len = (header[16] as usize).min(8);
+                OsImage {
+                    name: String::from_utf8_lossy(&header[17..17 + name_len]).into_owned(),
+                    version: (header[8], header[9]),
+                    data: Vec::new(),
+                }
+            });
+            image.data.extend_from_slice(data);
+        }
+
+        let image = image.ok_or(OsUpdateError::NotCeOs)?;
+        if image.data.is_empty() {
+            return Err(OsUpdateError::NotCeOs);
+        }
+        if image.data.len() > MAX_OS_SIZE {
+            return Err(OsUpdateError::TooLarge);
+        }
+        Ok(image)
+    }
+
+    /// Erase the sectors the image covers, program it at OS_START and
+    /// verify the result
+    pub fn program(&self, flash: &mut Flash) -> Result<(), OsUpdateError> {
+        let end = OS_START + self.data.len() as u32;
+        // OS_START is past the 8KB sectors, so every sector here is 64KB
+        let mut sector = OS_START;
+        while sector < end {
+            command(flash, &[0x80, 0xAA, 0x55], sector, 0x30);
+            sector += 0x10000;
+        }
+        // Leave erase-status mode before programming
+        flash.write_cpu(OS_START, 0xF0);
+
+        for (i, &value) in self.data.iter().enumerate() {
+            if value != 0xFF {
+                command(flash, &[0xA0], OS_START + i as u32, value);
+            }
+        }
+
+        for (i, &value) in self.data.iter().enumerate() {
+            let addr = OS_START + i as u32;
+            if flash.peek(addr) != value {
+                return Err(OsUpdateError::VerifyFailed { addr });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Issue AA/55 unlock, the extra command bytes, then `value` at `addr`
+fn command(flash: &mut Flash, extra: &[u8], addr: u32, value: u8) {
+    flash.write_cpu(0xAAA, 0xAA);
+    flash.write_cpu(0x555, 0x55);
+    for (i, &byte) in extra.iter().enumerate() {
+        // Sector erase repeats the unlock after 0x80
+        let cmd_addr = if i == 2 { 0x555 } else { 0xAAA };
+        flash.write_cpu(cmd_addr, byte);
+    }
+    flash.write_cpu(addr, value);
+}
+
+/// Build a single-record .8eu file (tests and tools)
+pub fn build_file(name: &str, device: u8, data_type: u8, data: &[u8]) -> Vec<u8> {
+    let mut file = vec![0u8; HEADER_SIZE];
+    file[0..8].copy_from_slice(MAGIC);
+    let name = &name.as_bytes()[..name.len().min(8)];
+    file[16] = name.len() as u8;
+    file[17..17 + name.len()].copy_from_slice(name);
+    file[48] = device;
+    file[49] = data_type;
+    file[74..78].copy_from_slice(&(data.len() as u32).to_le_bytes());
+    file.extend_from_slice(data);
+    file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_concatenates_os_records() {
+        let mut file = build_file("OS1", DEVICE_CE, DATA_TYPE_OS, &[1, 2, 3]);
+        file.extend(build_file("CERT", DEVICE_CE, 0x25, &[9; 5]));
+        file.extend(build_file("OS2", DEVICE_CE, DATA_TYPE_OS, &[4, 5]));
+        let image = OsImage::parse(&file).unwrap();
+        assert_eq!(image.name, "OS1");
+        assert_eq!(image.data, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(OsImage::parse(b"**TI83F*").unwrap_err(), OsUpdateError::BadFile);
+        let mut file = build_file("OS", DEVICE_CE, DATA_TYPE_OS, &[1, 2, 3]);
+        file.pop();
+        assert_eq!(OsImage::parse(&file).unwrap_err(), OsUpdateError::BadFile);
+
+        // TI-84 Plus (0x73 is the CE) and app records are not a CE OS
+        let file = build_file("OS", 0x74, DATA_TYPE_OS, &[1]);
+        assert_eq!(OsImage::parse(&file).unwrap_err(), OsUpdateError::NotCeOs);
+        let file = build_file("APP", DEVICE_CE, 0x24, &[1]);
+        assert_eq!(OsImage::parse(&file).unwrap_err(), OsUpdateError::NotCeOs);
+
+        let file = build_file("OS", DEVICE_CE, DATA_TYPE_OS, &vec![0; MAX_OS_SIZE + 1]);
+        assert_eq!(OsImage::parse(&file).unwrap_err(), OsUpdateError::TooLarge);
+    }
+
+    #[test]
+    fn test_program_erases_and_writes_os_sectors() {
+        let mut flash = Flash::new();
+        // Old OS and boot code everywhere
+        flash.load_rom(&vec![0x00; 0x50000]).unwrap();
+
+        let data: Vec<u8> = (0..0x10010u32).map(|i| (i * 7) as u8).collect();
+        let image = OsImage { name: "OS".into(), version: (5, 8), data };
+        image.program(&mut flash).unwrap();
+
+        assert_eq!(flash.peek(OS_START), 0x00);
+        assert_eq!(flash.peek(OS_START + 1), 7);
+        // Rest of the second sector is erased, the sector after is untouched
+        assert_eq!(flash.peek(OS_START + 0x10010), 0xFF);
+        assert_eq!(flash.peek(OS_START + 0x1FFFF), 0xFF);
+        assert_eq!(flash.peek(OS_START + 0x20000), 0x00);
+        assert_eq!(flash.peek(OS_START - 1), 0x00, "boot code untouched");
+        assert_eq!(flash.read(OS_START + 1), 7, "not left in status mode");
+    }
+}
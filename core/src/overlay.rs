@@ -0,0 +1,128 @@
+//! Debug HUD drawn over a copy of the framebuffer
+//!
+//! Thin frontends (a wasm canvas, a headless recorder) get an on-screen
+//! readout of the PC, the LCD frame counter and the watch values without
+//! drawing text themselves. Lines are drawn in a built-in 3x5 font at 2x
+//! in the top-left corner, over a darkened box so they stay readable on
+//! any screen. Only the caller's copy is drawn on; the framebuffer and
+//! emulated VRAM are untouched.
+
+/// Show the PC (C ABI flag bits, see emu.h)
+pub const HUD_PC: u32 = 1;
+/// Show the count of LCD frames generated
+pub const HUD_FRAMES: u32 = 2;
+/// Show one line per watch with its last per-frame value
+pub const HUD_WATCHES: u32 = 4;
+/// All defined flag bits
+pub const HUD_ALL: u32 = HUD_PC | HUD_FRAMES | HUD_WATCHES;
+
+const SCALE: usize = 2;
+const MARGIN: usize = 2;
+const ADVANCE: usize = 4 * SCALE;
+const LINE_HEIGHT: usize = 6 * SCALE;
+const TEXT_COLOR: u32 = 0xFFFFFFFF;
+
+/// 3x5 glyph rows, bit 2 is the left column. Unknown characters are blank.
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b111, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b111, 0b100, 0b100],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        _ => [0; 5],
+    }
+}
+
+/// HUD text for `flags`, one string per line
+pub fn hud_lines(flags: u32, pc: u32, frames: u64, watches: impl Iterator<Item = (u32, u32)>) -> Vec<String> {
+    let mut lines = Vec::new();
+    if flags & HUD_PC != 0 {
+        lines.push(format!("PC:{:06X}", pc));
+    }
+    if flags & HUD_FRAMES != 0 {
+        lines.push(format!("FR:{}", frames));
+    }
+    if flags & HUD_WATCHES != 0 {
+        lines.extend(watches.map(|(id, value)| format!("W{}:{:X}", id, value)));
+    }
+    lines
+}
+
+/// Draw `lines` into the top-left of a `width` x `height` ARGB8888 image.
+/// Lines that don't fit are clipped.
+pub fn draw_lines(image: &mut [u32], width: usize, height: usize, lines: &[String]) {
+    let columns = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+    if columns == 0 {
+        return;
+    }
+    // Backdrop: halve every channel, keeping alpha
+    let box_w = (columns * ADVANCE + MARGIN).min(width);
+    let box_h = (lines.len() * LINE_HEIGHT + MARGIN).min(height);
+    for y in 0..box_h {
+        for pixel in &mut image[y * width..y * width + box_w] {
+            *pixel = (*pixel & 0xFF000000) | ((*pixel >> 1) & 0x007F7F7F);
+        }
+    }
+
+    for (row, line) in lines.iter().enumerate() {
+        let top = MARGIN + row * LINE_HEIGHT;
+        for (col, c) in line.chars().enumerate() {
+            let left = MARGIN + col * ADVANCE;
+            for (gy, bits) in glyph(c).iter().enumerate() {
+                for gx in 0..3 {
+                    if bits & (0b100 >> gx) == 0 {
+                        continue;
+                    }
+                    for dy in 0..SCALE {
+                        for dx in 0..SCALE {
+                            let (x, y) = (left + gx * SCALE + dx, top + gy * SCALE + dy);
+                            if x < width && y < height {
+                                image[y * width + x] = TEXT_COLOR;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hud_lines_and_drawing() {
+        let watches = [(1, 0x2A), (7, 0)];
+        assert_eq!(
+            hud_lines(HUD_ALL, 0x0021A4, 12, watches.into_iter()),
+            ["PC:0021A4", "FR:12", "W1:2A", "W7:0"]
+        );
+        assert_eq!(hud_lines(HUD_FRAMES, 0, 3, watches.into_iter()), ["FR:3"]);
+
+        let (width, height) = (16, 8);
+        let mut image = vec![0xFF8080FEu32; width * height];
+        draw_lines(&mut image, width, height, &["1".to_string()]);
+        // '1' top row is 010: the middle column is lit at 2x
+        assert_eq!(image[MARGIN * width + MARGIN + 2], TEXT_COLOR);
+        assert_eq!(image[MARGIN * width + MARGIN], 0xFF40407F);
+        // Outside the box the image is unchanged, and the glyph is clipped
+        assert_eq!(image[width - 1], 0xFF8080FE);
+    }
+}
@@ -0,0 +1,204 @@
+//! Binary patch sets applied to flash
+//!
+//! Community bugfixes and translations ship as patches against a ROM dump
+//! rather than as modified ROMs. A patch set is applied to flash right
+//! after the ROM is loaded and again on every reset, so it survives the OS
+//! rewriting the patched sector. Two formats are accepted:
+//!
+//! - IPS (starts with `PATCH`): records of a 24-bit big-endian offset, a
+//!   16-bit size and the bytes, or size 0 followed by a 16-bit run length
+//!   and one fill byte; ends with `EOF`.
+//! - Text: one patch per line, flash address then hex bytes; `#` starts a
+//!   comment.
+//!
+//! ```text
+//! 021A4C: C9          # return early
+//! 0x05F000: 48 45 4C 4C 4F
+//! ```
+//!
+//! Patches target one ROM and are not checked against it.
+
+use std::fmt;
+
+use crate::memory::{addr, Flash};
+
+/// Bytes to place at a flash address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    pub addr: u32,
+    pub bytes: Vec<u8>,
+}
+
+/// Why a patch set was rejected (C ABI codes in `code`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// IPS record cut short or missing `EOF` (-110)
+    Truncated,
+    /// Malformed text line, 1-based (-110)
+    Parse { line: usize, message: String },
+    /// A patch reaches past the end of flash (-111)
+    OutOfRange { addr: u32, len: usize },
+}
+
+impl PatchError {
+    /// C ABI error code (see emu.h)
+    pub fn code(&self) -> i32 {
+        match self {
+            PatchError::Truncated | PatchError::Parse { .. } => -110,
+            PatchError::OutOfRange { .. } => -111,
+        }
+    }
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::Truncated => write!(f, "IPS patch is truncated"),
+            PatchError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            PatchError::OutOfRange { addr, len } => {
+                write!(f, "{} bytes at {:06X} reach past the end of flash", len, addr)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+/// A parsed patch set, in file order (later patches win on overlap)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatchSet {
+    pub patches: Vec<Patch>,
+}
+
+impl PatchSet {
+    /// Parse IPS or text, detected from the `PATCH` header
+    pub fn parse(data: &[u8]) -> Result<Self, PatchError> {
+        let set = if data.starts_with(b"PATCH") {
+            Self::parse_ips(&data[5..])?
+        } else {
+            let text = std::str::from_utf8(data)
+                .map_err(|_| PatchError::Parse { line: 1, message: "not IPS or text".into() })?;
+            Self::parse_text(text)?
+        };
+        for patch in &set.patches {
+            if patch.addr as usize + patch.bytes.len() > addr::FLASH_SIZE {
+                return Err(PatchError::OutOfRange { addr: patch.addr, len: patch.bytes.len() });
+            }
+        }
+        Ok(set)
+    }
+
+    fn parse_ips(mut data: &[u8]) -> Result<Self, PatchError> {
+        let mut take = |n: usize| -> Result<&[u8], PatchError> {
+            if data.len() < n {
+                return Err(PatchError::Truncated);
+            }
+            let (head, rest) = data.split_at(n);
+            data = rest;
+            Ok(head)
+        };
+        let mut set = PatchSet::default();
+        loop {
+            let offset = take(3)?;
+            if offset == b"EOF" {
+                // An optional truncation size may follow; flash keeps its size
+                return Ok(set);
+            }
+            let addr = u32::from_be_bytes([0, offset[0], offset[1], offset[2]]);
+            let size = take(2)?;
+            let bytes = match u16::from_be_bytes([size[0], size[1]]) {
+                0 => {
+                    let run = take(3)?;
+                    vec![run[2]; u16::from_be_bytes([run[0], run[1]]) as usize]
+                }
+                size => take(size as usize)?.to_vec(),
+            };
+            set.patches.push(Patch { addr, bytes });
+        }
+    }
+
+    fn parse_text(text: &str) -> Result<Self, PatchError> {
+        let mut set = PatchSet::default();
+        for (i, raw) in text.lines().enumerate() {
+            let line = raw.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: &str| PatchError::Parse { line: i + 1, message: message.to_string() };
+            let (addr, hex) = line.split_once(':').ok_or_else(|| error("expected `address: bytes`"))?;
+            let addr = addr.trim();
+            let addr = addr.strip_prefix("0x").or_else(|| addr.strip_prefix("0X")).unwrap_or(addr);
+            let addr = u32::from_str_radix(addr, 16).map_err(|_| error("bad address"))?;
+
+            let digits: String = hex.split_whitespace().collect();
+            if digits.is_empty() || !digits.len().is_multiple_of(2) || !digits.is_ascii() {
+                return Err(error("expected hex byte pairs"));
+            }
+            let bytes = (0..digits.len())
+                .step_by(2)
+                .map(|at| u8::from_str_radix(&digits[at..at + 2], 16))
+                .collect::<Result<Vec<u8>, _>>()
+                .map_err(|_| error("bad hex byte"))?;
+            set.patches.push(Patch { addr, bytes });
+        }
+        Ok(set)
+    }
+
+    /// Total bytes written by `apply`
+    pub fn len_bytes(&self) -> usize {
+        self.patches.iter().map(|patch| patch.bytes.len()).sum()
+    }
+
+    /// Write every patch into flash
+    pub fn apply(&self, flash: &mut Flash) {
+        for patch in &self.patches {
+            for (i, &byte) in patch.bytes.iter().enumerate() {
+                flash.write_direct(patch.addr + i as u32, byte);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ips_with_rle() {
+        let mut ips = b"PATCH".to_vec();
+        ips.extend([0x02, 0x1A, 0x4C, 0x00, 0x02, 0xC9, 0x00]);
+        ips.extend([0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xFF]);
+        ips.extend(b"EOF");
+        let set = PatchSet::parse(&ips).unwrap();
+        assert_eq!(
+            set.patches,
+            [
+                Patch { addr: 0x021A4C, bytes: vec![0xC9, 0x00] },
+                Patch { addr: 0x100000, bytes: vec![0xFF; 3] },
+            ]
+        );
+        assert_eq!(set.len_bytes(), 5);
+
+        assert_eq!(PatchSet::parse(&ips[..ips.len() - 3]), Err(PatchError::Truncated));
+        let past_end = b"PATCH\x3F\xFF\xFF\x00\x02\x00\x00EOF";
+        assert_eq!(PatchSet::parse(past_end).unwrap_err().code(), -111);
+    }
+
+    #[test]
+    fn test_parse_text() {
+        let set = PatchSet::parse(b"# fix\n021A4C: C9  # ret\n0x05F000: 4845 4C\n").unwrap();
+        assert_eq!(
+            set.patches,
+            [
+                Patch { addr: 0x021A4C, bytes: vec![0xC9] },
+                Patch { addr: 0x05F000, bytes: vec![0x48, 0x45, 0x4C] },
+            ]
+        );
+        assert_eq!(
+            PatchSet::parse(b"021A4C C9\n"),
+            Err(PatchError::Parse { line: 1, message: "expected `address: bytes`".into() })
+        );
+        assert!(PatchSet::parse(b"1000: C\n").is_err());
+        assert!(PatchSet::parse(b"1000: ZZ\n").is_err());
+    }
+}
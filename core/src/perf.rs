@@ -0,0 +1,114 @@
+//! Host-side performance statistics for the C API
+//!
+//! Each `emu_run_cycles` call is one frame segment. The tracker accumulates
+//! segments into one-second windows and publishes the last completed window,
+//! so frontends and CI can poll a stable snapshot without resetting anything.
+//!
+//! Uses host time, so it lives on the FFI side only (WASM builds measure
+//! time in JavaScript instead).
+
+use std::time::{Duration, Instant};
+
+/// Length of one statistics window
+pub const WINDOW: Duration = Duration::from_secs(1);
+
+/// Statistics for the last completed window (C layout, see emu.h)
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EmuPerfStats {
+    /// Emulated cycles executed during the window
+    pub cycles: u64,
+    /// Frames rendered (emu_run_cycles calls) during the window
+    pub frames: u32,
+    /// Average host nanoseconds spent per frame segment
+    pub avg_frame_ns: u64,
+    /// Total host nanoseconds spent waiting for the emulator mutex
+    pub mutex_wait_ns: u64,
+    /// Actual window length in nanoseconds (>= 1s unless partial)
+    pub window_ns: u64,
+}
+
+/// Accumulates frame segments and rolls them into one-second windows
+pub struct PerfTracker {
+    window_start: Option<Instant>,
+    current: EmuPerfStats,
+    frame_ns_total: u64,
+    last: EmuPerfStats,
+}
+
+impl PerfTracker {
+    pub fn new() -> Self {
+        Self {
+            window_start: None,
+            current: EmuPerfStats::default(),
+            frame_ns_total: 0,
+            last: EmuPerfStats::default(),
+        }
+    }
+
+    /// Record one frame segment that finished at `now`
+    pub fn record_frame(&mut self, cycles: u64, frame: Duration, wait: Duration, now: Instant) {
+        let start = *self.window_start.get_or_insert(now);
+        self.current.cycles += cycles;
+        self.current.frames += 1;
+        self.frame_ns_total += frame.as_nanos() as u64;
+        self.current.mutex_wait_ns += wait.as_nanos() as u64;
+
+        let elapsed = now.duration_since(start);
+        if elapsed >= WINDOW {
+            self.current.window_ns = elapsed.as_nanos() as u64;
+            self.current.avg_frame_ns = self.frame_ns_total / self.current.frames as u64;
+            self.last = self.current;
+            self.current = EmuPerfStats::default();
+            self.frame_ns_total = 0;
+            self.window_start = Some(now);
+        }
+    }
+
+    /// Drop the window in progress (e.g. around a pause); the published
+    /// window is kept and the next frame starts a new one
+    pub fn restart_window(&mut self) {
+        self.window_start = None;
+        self.current = EmuPerfStats::default();
+        self.frame_ns_total = 0;
+    }
+
+    /// Statistics for the last completed window (all zero before the first)
+    pub fn last_window(&self) -> EmuPerfStats {
+        self.last
+    }
+}
+
+impl Default for PerfTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_rollover() {
+        let mut perf = PerfTracker::new();
+        let t0 = Instant::now();
+        let ms = Duration::from_millis;
+
+        perf.record_frame(100, ms(2), ms(1), t0);
+        perf.record_frame(100, ms(4), ms(0), t0 + ms(500));
+        assert_eq!(perf.last_window(), EmuPerfStats::default());
+
+        perf.record_frame(100, ms(6), ms(1), t0 + ms(1000));
+        let stats = perf.last_window();
+        assert_eq!(stats.cycles, 300);
+        assert_eq!(stats.frames, 3);
+        assert_eq!(stats.avg_frame_ns, ms(4).as_nanos() as u64);
+        assert_eq!(stats.mutex_wait_ns, ms(2).as_nanos() as u64);
+        assert_eq!(stats.window_ns, ms(1000).as_nanos() as u64);
+
+        // The next window starts empty; the published one is unchanged
+        perf.record_frame(50, ms(1), ms(0), t0 + ms(1200));
+        assert_eq!(perf.last_window(), stats);
+    }
+}
@@ -1,9 +1,12 @@
+use super::device::{Peripheral, PortContext};
+
 /// Backlight controller emulation for TI-84 Plus CE
 ///
 /// Controls LCD backlight brightness via PWM. When brightness is 0,
 /// the screen appears black even though LCD controller and VRAM remain powered.
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Backlight {
     /// Backlight brightness level (0x00 = off, 0xFF = full brightness)
     /// Register at offset 0x30
@@ -69,3 +72,17 @@ impl Backlight {
         self.brightness < 13 // < 5% brightness
     }
 }
+
+impl Peripheral for Backlight {
+    fn read(&mut self, offset: u32, _ctx: &PortContext) -> u8 {
+        Backlight::read(self, offset)
+    }
+
+    fn write(&mut self, offset: u32, value: u8, _ctx: &PortContext) {
+        Backlight::write(self, offset, value)
+    }
+
+    fn reset(&mut self) {
+        Backlight::reset(self)
+    }
+}
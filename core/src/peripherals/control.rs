@@ -5,6 +5,8 @@
 //! These ports control system-level functions like CPU speed, battery status,
 //! and memory protection.
 
+use super::device::{Peripheral, PortContext};
+
 /// Register offsets
 mod regs {
     /// Power control
@@ -65,6 +67,7 @@ mod battery {
 
 /// Control Port Controller
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ControlPorts {
     /// Power control register
     power: u8,
@@ -578,6 +581,20 @@ impl Default for ControlPorts {
     }
 }
 
+impl Peripheral for ControlPorts {
+    fn read(&mut self, offset: u32, _ctx: &PortContext) -> u8 {
+        ControlPorts::read(self, offset)
+    }
+
+    fn write(&mut self, offset: u32, value: u8, _ctx: &PortContext) {
+        ControlPorts::write(self, offset, value)
+    }
+
+    fn reset(&mut self) {
+        ControlPorts::reset(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
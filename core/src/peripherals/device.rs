@@ -0,0 +1,48 @@
+//! Uniform peripheral interface
+//!
+//! Every memory-mapped controller implements `Peripheral`, so code that treats
+//! devices generically (resetting, register viewers, custom builds that
+//! compile in extra hardware) does not need to know each controller's own
+//! method signatures. The bus keeps calling the inherent
+//! methods directly on the hot path.
+//!
+//! Saving and restoring is not part of the trait: save states go through
+//! `Peripherals::to_bytes`/`from_bytes` only, so there is one format.
+
+use super::{KEYPAD_COLS, KEYPAD_ROWS};
+
+/// Emulator state some controllers need to service an access
+#[derive(Debug, Clone, Copy)]
+pub struct PortContext<'a> {
+    /// Current keyboard matrix
+    pub key_state: &'a [[bool; KEYPAD_COLS]; KEYPAD_ROWS],
+    /// CPU cycle count at the access
+    pub cycles: u64,
+    /// CPU speed setting (0=6MHz, 1=12MHz, 2=24MHz, 3=48MHz)
+    pub cpu_speed: u8,
+    /// CPU cycles until the timer delay event fires (0 if not active)
+    pub delay_remaining: u64,
+}
+
+/// Common interface over all hardware controllers
+///
+/// `offset` is relative to the controller's register window. Side effects
+/// the owning `Peripherals` normally handles after an access (interrupt line
+/// updates, scheduler flags) are left to the caller, exactly as with the
+/// inherent methods.
+pub trait Peripheral {
+    /// Read a register byte
+    fn read(&mut self, offset: u32, ctx: &PortContext) -> u8;
+
+    /// Write a register byte
+    fn write(&mut self, offset: u32, value: u8, ctx: &PortContext);
+
+    /// Advance by `cycles` CPU cycles. Returns true if the controller wants
+    /// its interrupt raised. Scheduler-driven controllers keep the default.
+    fn tick(&mut self, _cycles: u32, _ctx: &PortContext) -> bool {
+        false
+    }
+
+    /// Return to power-on state
+    fn reset(&mut self);
+}
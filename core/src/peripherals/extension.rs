@@ -0,0 +1,211 @@
+//! Frontend-provided memory-mapped devices
+//!
+//! The unmapped MMIO windows (0xE40000-0xEFFFFF and 0xFB0000-0xFEFFFF) read
+//! as noise and ignore writes on real hardware. An extension claims a range
+//! inside one of them and receives every CPU load and store there, so a
+//! frontend can attach a debug console or host bridge for homebrew without
+//! touching the emulated controllers. Extensions take priority over the CE
+//! toolchain debug ports when their ranges overlap.
+//!
+//! Debugger peeks and pokes never reach an extension, and access timing is
+//! the same as for the bare unmapped window.
+//!
+//! Errors (`EmuError`): -50 range empty or not inside one unmapped window,
+//! -51 range overlaps another extension, -52 too many extensions, -53
+//! unknown id.
+
+use std::os::raw::c_void;
+
+use super::device::{Peripheral, PortContext};
+use crate::error::EmuError;
+
+/// Extensions that may be registered at once
+pub const MAX_EXTENSIONS: usize = 8;
+
+/// Unmapped MMIO windows extensions may claim, `[start, end)`
+const WINDOWS: [(u32, u32); 2] = [(0xE40000, 0xF00000), (0xFB0000, 0xFF0000)];
+
+/// Read callback: `offset` is relative to the extension base
+pub type ExtensionReadCallback = extern "C" fn(user: *mut c_void, offset: u32) -> u8;
+
+/// Write callback: `offset` is relative to the extension base
+pub type ExtensionWriteCallback = extern "C" fn(user: *mut c_void, offset: u32, value: u8);
+
+/// Device forwarding accesses to C callbacks (user pointer stored as usize
+/// to stay Send). Called with the emulator locked, so the callbacks must not
+/// call back into emu_*.
+pub struct CallbackDevice {
+    read: ExtensionReadCallback,
+    write: ExtensionWriteCallback,
+    user: usize,
+}
+
+impl CallbackDevice {
+    pub fn new(read: ExtensionReadCallback, write: ExtensionWriteCallback, user: *mut c_void) -> Self {
+        Self { read, write, user: user as usize }
+    }
+}
+
+impl Peripheral for CallbackDevice {
+    fn read(&mut self, offset: u32, _ctx: &PortContext) -> u8 {
+        (self.read)(self.user as *mut c_void, offset)
+    }
+
+    fn write(&mut self, offset: u32, value: u8, _ctx: &PortContext) {
+        (self.write)(self.user as *mut c_void, offset, value)
+    }
+
+    // State lives on the host side
+    fn reset(&mut self) {}
+}
+
+struct Extension {
+    id: u32,
+    base: u32,
+    end: u32,
+    device: Box<dyn Peripheral + Send>,
+}
+
+/// Registered extension devices
+#[derive(Default)]
+pub struct Extensions {
+    slots: Vec<Extension>,
+    next_id: u32,
+}
+
+impl Extensions {
+    /// Claim `[base, base + len)` (CPU addresses). Returns the extension id.
+    pub fn register(&mut self, base: u32, len: u32, device: Box<dyn Peripheral + Send>) -> Result<u32, EmuError> {
+        let end = base.checked_add(len).ok_or(EmuError::ExtensionRange)?;
+        if len == 0 || !WINDOWS.iter().any(|&(start, stop)| base >= start && end <= stop) {
+            return Err(EmuError::ExtensionRange);
+        }
+        if self.slots.iter().any(|e| base < e.end && e.base < end) {
+            return Err(EmuError::ExtensionOverlap);
+        }
+        if self.slots.len() == MAX_EXTENSIONS {
+            return Err(EmuError::TooManyExtensions);
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.slots.push(Extension { id, base, end, device });
+        Ok(id)
+    }
+
+    /// Remove extension `id`
+    pub fn unregister(&mut self, id: u32) -> Result<(), EmuError> {
+        let index = self.slots.iter().position(|e| e.id == id).ok_or(EmuError::UnknownExtension)?;
+        self.slots.remove(index);
+        Ok(())
+    }
+
+    /// Number of registered extensions
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Claimed `(id, base, end)` ranges, in registration order
+    pub fn ranges(&self) -> impl Iterator<Item = (u32, u32, u32)> + '_ {
+        self.slots.iter().map(|e| (e.id, e.base, e.end))
+    }
+
+    fn find(&mut self, addr: u32) -> Option<&mut Extension> {
+        self.slots.iter_mut().find(|e| addr >= e.base && addr < e.end)
+    }
+
+    /// Read `addr` if an extension claims it
+    #[inline]
+    pub fn read(&mut self, addr: u32, ctx: &PortContext) -> Option<u8> {
+        if self.slots.is_empty() {
+            return None;
+        }
+        let ext = self.find(addr)?;
+        Some(ext.device.read(addr - ext.base, ctx))
+    }
+
+    /// Write `addr` if an extension claims it; returns false otherwise
+    #[inline]
+    pub fn write(&mut self, addr: u32, value: u8, ctx: &PortContext) -> bool {
+        if self.slots.is_empty() {
+            return false;
+        }
+        match self.find(addr) {
+            Some(ext) => {
+                ext.device.write(addr - ext.base, value, ctx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reset every extension device (emulator reset)
+    pub fn reset(&mut self) {
+        for ext in &mut self.slots {
+            ext.device.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peripherals::{KEYPAD_COLS, KEYPAD_ROWS};
+
+    /// 16-byte scratch RAM device
+    #[derive(Default)]
+    struct Scratch([u8; 16]);
+
+    impl Peripheral for Scratch {
+        fn read(&mut self, offset: u32, _ctx: &PortContext) -> u8 {
+            self.0[offset as usize]
+        }
+
+        fn write(&mut self, offset: u32, value: u8, _ctx: &PortContext) {
+            self.0[offset as usize] = value;
+        }
+
+        fn reset(&mut self) {
+            self.0 = [0; 16];
+        }
+    }
+
+    #[test]
+    fn test_register_validates_range() {
+        let mut ext = Extensions::default();
+        assert_eq!(ext.register(0xE30000, 16, Box::new(Scratch::default())), Err(EmuError::ExtensionRange));
+        assert_eq!(ext.register(0xEFFFF8, 16, Box::new(Scratch::default())), Err(EmuError::ExtensionRange));
+        assert_eq!(ext.register(0xE40000, 0, Box::new(Scratch::default())), Err(EmuError::ExtensionRange));
+
+        let id = ext.register(0xE40000, 16, Box::new(Scratch::default())).unwrap();
+        assert_eq!(ext.register(0xE4000F, 4, Box::new(Scratch::default())), Err(EmuError::ExtensionOverlap));
+        ext.register(0xE40010, 4, Box::new(Scratch::default())).unwrap();
+        assert_eq!(ext.unregister(id), Ok(()));
+        assert_eq!(ext.unregister(id), Err(EmuError::UnknownExtension));
+        assert_eq!(ext.len(), 1);
+
+        for i in 1..MAX_EXTENSIONS as u32 {
+            ext.register(0xFE0000 + i * 16, 16, Box::new(Scratch::default())).unwrap();
+        }
+        assert_eq!(ext.register(0xE80000, 1, Box::new(Scratch::default())), Err(EmuError::TooManyExtensions));
+    }
+
+    #[test]
+    fn test_accesses_use_relative_offsets() {
+        let keys = [[false; KEYPAD_COLS]; KEYPAD_ROWS];
+        let ctx = PortContext { key_state: &keys, cycles: 0, cpu_speed: 0, delay_remaining: 0 };
+        let mut ext = Extensions::default();
+        ext.register(0xFB1000, 16, Box::new(Scratch::default())).unwrap();
+
+        assert!(ext.write(0xFB1003, 0x42, &ctx));
+        assert!(!ext.write(0xFB1010, 0x42, &ctx));
+        assert_eq!(ext.read(0xFB1003, &ctx), Some(0x42));
+        assert_eq!(ext.read(0xFB0FFF, &ctx), None);
+
+        ext.reset();
+        assert_eq!(ext.read(0xFB1003, &ctx), Some(0));
+    }
+}
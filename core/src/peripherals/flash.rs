@@ -7,6 +7,8 @@
 //!
 //! Reference: CEmu flash.c
 
+use super::device::{Peripheral, PortContext};
+
 /// Register offsets
 mod regs {
     /// Flash enable (bit 0 writable)
@@ -30,6 +32,7 @@ mod regs {
 /// - Memory mapping selection
 /// - Wait state configuration
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FlashController {
     /// Flash enable (bit 0)
     enable: u8,
@@ -216,6 +219,20 @@ impl Default for FlashController {
     }
 }
 
+impl Peripheral for FlashController {
+    fn read(&mut self, offset: u32, _ctx: &PortContext) -> u8 {
+        FlashController::read(self, offset)
+    }
+
+    fn write(&mut self, offset: u32, value: u8, _ctx: &PortContext) {
+        FlashController::write(self, offset, value)
+    }
+
+    fn reset(&mut self) {
+        FlashController::reset(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -13,6 +13,8 @@
 //! - Bit 15: Power
 //! - Bit 19: Wake (power-on wake signal)
 
+use super::device::{Peripheral, PortContext};
+
 /// Interrupt source bit masks
 pub mod sources {
     pub const ON_KEY: u32 = 1 << 0;
@@ -26,6 +28,109 @@ pub mod sources {
     pub const WAKE: u32 = 1 << 19;
 }
 
+/// Short name of the peripheral behind interrupt source `bit`
+pub fn source_name(bit: u32) -> &'static str {
+    match bit {
+        0 => "on key",
+        1 => "timer 1",
+        2 => "timer 2",
+        3 => "timer 3",
+        4 => "os timer",
+        10 => "keypad",
+        11 => "lcd",
+        15 => "power",
+        19 => "wake",
+        _ => "unknown",
+    }
+}
+
+/// Status change an interrupt watchpoint breaks on (stable C ABI values)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InterruptEdge {
+    /// Status bit set by the peripheral
+    Raised = 1,
+    /// Status bit cleared by a write to the acknowledge register
+    Acknowledged = 2,
+}
+
+/// A watched source changing status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InterruptWatchHit {
+    /// Source bit (see `sources`)
+    pub source: u32,
+    pub edge: InterruptEdge,
+}
+
+impl InterruptWatchHit {
+    /// Peripheral behind the source
+    pub fn peripheral(&self) -> &'static str {
+        source_name(self.source)
+    }
+}
+
+/// Number of interrupt sources (status bits 0-21)
+pub const SOURCE_COUNT: usize = 22;
+
+/// Counters for one interrupt source (C layout, see emu.h). Times are bus
+/// cycles, which rescale with the CPU speed like the rest of the counters.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EmuInterruptStats {
+    /// Status bit set by the peripheral (enabled or not)
+    pub raises: u64,
+    /// Status bit cleared through the acknowledge register
+    pub acks: u64,
+    /// Raise-to-acknowledge cycles, summed over `acks`
+    pub service_cycles_total: u64,
+    /// Longest single raise-to-acknowledge time
+    pub service_cycles_max: u64,
+    /// Clock at the last raise
+    pub last_raise_cycles: u64,
+}
+
+/// Per-source statistics since reset, for request bank 0 (the one the OS
+/// services; debugger, not saved)
+#[derive(Debug, Clone, Default)]
+struct InterruptStats {
+    sources: [EmuInterruptStats; SOURCE_COUNT],
+    /// Clock at which each source's status was last set, until acknowledged
+    raised_at: [Option<u64>; SOURCE_COUNT],
+    /// Bus cycle count, kept current by the bus and the scheduler
+    clock: u64,
+}
+
+impl InterruptStats {
+    fn bits(mask: u32) -> impl Iterator<Item = usize> {
+        (0..SOURCE_COUNT).filter(move |&bit| mask & (1 << bit) != 0)
+    }
+
+    fn note_raised(&mut self, mask: u32) {
+        for bit in Self::bits(mask) {
+            self.sources[bit].raises += 1;
+            self.sources[bit].last_raise_cycles = self.clock;
+            self.raised_at[bit] = Some(self.clock);
+        }
+    }
+
+    fn note_acknowledged(&mut self, mask: u32) {
+        for bit in Self::bits(mask) {
+            let source = &mut self.sources[bit];
+            source.acks += 1;
+            if let Some(raised) = self.raised_at[bit].take() {
+                let service = self.clock.saturating_sub(raised);
+                source.service_cycles_total += service;
+                source.service_cycles_max = source.service_cycles_max.max(service);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        *self = Self { clock: self.clock, ..Self::default() };
+    }
+}
+
 /// Register offsets within the interrupt controller (used in tests)
 #[cfg(test)]
 mod regs {
@@ -40,6 +145,7 @@ mod regs {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct InterruptBank {
     status: u32,
     enabled: u32,
@@ -49,9 +155,18 @@ struct InterruptBank {
 
 /// Interrupt controller for the TI-84 Plus CE
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InterruptController {
     banks: [InterruptBank; 2],
     raw: u32,
+    /// Sources whose status rising is watched (debugger, not saved)
+    watch_raise: u32,
+    /// Sources whose acknowledgement is watched (debugger, not saved)
+    watch_ack: u32,
+    /// First watched change since the last `take_watch_hit`
+    watch_hit: Option<InterruptWatchHit>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    stats: InterruptStats,
 }
 
 impl InterruptController {
@@ -64,6 +179,10 @@ impl InterruptController {
                 InterruptBank { status: 0, enabled: 0, latched: 0, inverted: 0 },
             ],
             raw: 0,
+            watch_raise: 0,
+            watch_ack: 0,
+            watch_hit: None,
+            stats: InterruptStats::default(),
         };
         controller.raise(sources::PWR);
         controller
@@ -77,7 +196,9 @@ impl InterruptController {
             InterruptBank { status: 0, enabled: 0, latched: 0, inverted: 0 },
         ];
         self.raw = 0;
+        self.stats.clear();
         self.raise(sources::PWR);
+        self.watch_hit = None;
     }
 
     /// Check if any enabled interrupt is pending
@@ -108,12 +229,72 @@ impl InterruptController {
 
     /// Acknowledge (clear) interrupt status bits
     pub fn acknowledge(&mut self, mask: u32) {
+        let before = self.statuses();
         for bank in &mut self.banks {
             bank.status &= !mask;
         }
+        self.stats.note_acknowledged(before[0] & !self.banks[0].status);
+        self.note_watch(self.cleared_since(before) & self.watch_ack, InterruptEdge::Acknowledged);
+    }
+
+    /// Watch sources (masks of `sources` bits) for their status being
+    /// raised or acknowledged; 0 masks turn the watchpoint off
+    pub fn set_watch(&mut self, raise: u32, ack: u32) {
+        self.watch_raise = raise;
+        self.watch_ack = ack;
+        self.watch_hit = None;
+    }
+
+    /// Watched (raise, acknowledge) masks
+    pub fn watch(&self) -> (u32, u32) {
+        (self.watch_raise, self.watch_ack)
+    }
+
+    /// First watched status change since the last call, clearing it
+    #[inline]
+    pub fn take_watch_hit(&mut self) -> Option<InterruptWatchHit> {
+        self.watch_hit.take()
+    }
+
+    /// Advance the clock that statistics are timed by (bus total cycles)
+    #[inline]
+    pub fn set_clock(&mut self, cycles: u64) {
+        self.stats.clock = cycles;
+    }
+
+    /// Raise/acknowledge counts and service times per source bit, since
+    /// reset or the last `clear_stats`
+    pub fn stats(&self) -> &[EmuInterruptStats; SOURCE_COUNT] {
+        &self.stats.sources
+    }
+
+    pub fn clear_stats(&mut self) {
+        self.stats.clear();
+    }
+
+    fn statuses(&self) -> [u32; 2] {
+        [self.banks[0].status, self.banks[1].status]
+    }
+
+    /// Status bits set in either bank since `before`
+    fn raised_since(&self, before: [u32; 2]) -> u32 {
+        (self.banks[0].status & !before[0]) | (self.banks[1].status & !before[1])
+    }
+
+    /// Status bits cleared in either bank since `before`
+    fn cleared_since(&self, before: [u32; 2]) -> u32 {
+        (before[0] & !self.banks[0].status) | (before[1] & !self.banks[1].status)
+    }
+
+    /// Keep the first watched change (lowest source bit of `changed`)
+    fn note_watch(&mut self, changed: u32, edge: InterruptEdge) {
+        if changed != 0 && self.watch_hit.is_none() {
+            self.watch_hit = Some(InterruptWatchHit { source: changed.trailing_zeros(), edge });
+        }
     }
 
     fn set_source(&mut self, mask: u32, set: bool) {
+        let before = self.statuses();
         if set {
             self.raw |= mask;
         } else {
@@ -128,6 +309,8 @@ impl InterruptController {
                 bank.status &= !mask | bank.latched;
             }
         }
+        self.stats.note_raised(self.banks[0].status & !before[0]);
+        self.note_watch(self.raised_since(before) & self.watch_raise, InterruptEdge::Raised);
     }
 
     /// Read a register byte
@@ -162,25 +345,34 @@ impl InterruptController {
     /// Write a register byte
     /// addr is offset from controller base (0-0x0F)
     pub fn write(&mut self, addr: u32, value: u8) {
+        let bit_offset = (addr & 0x03) * 8;
+        self.write_masked(addr & !3, (value as u32) << bit_offset, 0xFF_u32 << bit_offset);
+    }
+
+    /// Write the bytes of a 32-bit register selected by `mask` in one step.
+    /// Used for 16/24/32-bit CPU stores so the register never holds a
+    /// partially written value. addr is the register offset (low 2 bits ignored).
+    pub fn write_masked(&mut self, addr: u32, value: u32, mask: u32) {
         let index = (addr >> 2) & 0x3F;
         let request = ((addr >> 5) & 0x01) as usize;
-        let bit_offset = (addr & 0x03) * 8;
-        let mask = 0xFF_u32 << bit_offset;
-        let shifted_value = (value as u32) << bit_offset;
+        let value = value & mask;
+        let before = self.statuses();
 
         let bank = &mut self.banks[request];
         match index {
             1 | 9 => {
-                bank.enabled = (bank.enabled & !mask) | (shifted_value & mask);
+                bank.enabled = (bank.enabled & !mask) | value;
             }
             2 | 10 => {
-                bank.status &= !((shifted_value) & bank.latched);
+                bank.status &= !(value & bank.latched);
+                self.stats.note_acknowledged(before[0] & !self.banks[0].status);
+                self.note_watch(self.cleared_since(before) & self.watch_ack, InterruptEdge::Acknowledged);
             }
             3 | 11 => {
-                bank.latched = (bank.latched & !mask) | (shifted_value & mask);
+                bank.latched = (bank.latched & !mask) | value;
             }
             4 | 12 => {
-                bank.inverted = (bank.inverted & !mask) | (shifted_value & mask);
+                bank.inverted = (bank.inverted & !mask) | value;
             }
             _ => {}
         }
@@ -278,6 +470,20 @@ impl Default for InterruptController {
     }
 }
 
+impl Peripheral for InterruptController {
+    fn read(&mut self, offset: u32, _ctx: &PortContext) -> u8 {
+        InterruptController::read(self, offset)
+    }
+
+    fn write(&mut self, offset: u32, value: u8, _ctx: &PortContext) {
+        InterruptController::write(self, offset, value)
+    }
+
+    fn reset(&mut self) {
+        InterruptController::reset(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -334,6 +540,58 @@ mod tests {
         assert!(!ic.irq_pending());
     }
 
+    #[test]
+    fn test_watch_hits() {
+        let mut ic = InterruptController::new();
+        ic.set_watch(sources::LCD, sources::LCD);
+
+        ic.raise(sources::TIMER1);
+        assert_eq!(ic.take_watch_hit(), None);
+        ic.raise(sources::LCD);
+        let hit = ic.take_watch_hit().unwrap();
+        assert_eq!((hit.source, hit.edge, hit.peripheral()), (11, InterruptEdge::Raised, "lcd"));
+        // Raising a source whose status is still set is not an edge
+        ic.raise(sources::LCD);
+        assert_eq!(ic.take_watch_hit(), None);
+
+        // Only a write to the acknowledge register counts, not the source
+        // going inactive
+        ic.write(0x0D, (sources::LCD >> 8) as u8);
+        ic.clear_raw(sources::LCD);
+        assert_eq!(ic.take_watch_hit(), None);
+        ic.write(0x09, (sources::LCD >> 8) as u8);
+        let hit = ic.take_watch_hit().unwrap();
+        assert_eq!((hit.source, hit.edge), (11, InterruptEdge::Acknowledged));
+
+        ic.set_watch(0, 0);
+        ic.raise(sources::LCD);
+        assert_eq!(ic.take_watch_hit(), None);
+    }
+
+    #[test]
+    fn test_stats_raises_acks_and_service_time() {
+        let mut ic = InterruptController::new();
+        ic.write(0x0C, sources::OSTIMER as u8); // latched
+        for (raise_at, ack_at) in [(100, 130), (200, 280)] {
+            ic.set_clock(raise_at);
+            ic.raise(sources::OSTIMER);
+            ic.clear_raw(sources::OSTIMER);
+            // Already pending: not another raise
+            ic.raise(sources::OSTIMER);
+            ic.set_clock(ack_at);
+            ic.write(0x08, sources::OSTIMER as u8);
+        }
+        let os_timer = ic.stats()[4];
+        assert_eq!((os_timer.raises, os_timer.acks), (2, 2));
+        assert_eq!((os_timer.service_cycles_total, os_timer.service_cycles_max), (110, 80));
+        assert_eq!(os_timer.last_raise_cycles, 200);
+        // PWR raised by new(), never acknowledged
+        assert_eq!((ic.stats()[15].raises, ic.stats()[15].acks), (1, 0));
+
+        ic.clear_stats();
+        assert_eq!(ic.stats()[4], EmuInterruptStats::default());
+    }
+
     #[test]
     fn test_read_write_enabled() {
         let mut ic = InterruptController::new();
@@ -444,4 +702,30 @@ mod tests {
         ic.acknowledge(sources::TIMER2 | sources::TIMER3);
         assert!(!ic.irq_pending());
     }
+
+    #[test]
+    fn test_write_masked_matches_byte_writes() {
+        let mut wide = InterruptController::new();
+        let mut bytes = InterruptController::new();
+
+        let value = sources::TIMER1 | sources::KEYPAD | sources::WAKE;
+        wide.write_masked(regs::ENABLED, value, 0x00FF_FFFF);
+        for i in 0..3 {
+            bytes.write(regs::ENABLED + i, (value >> (i * 8)) as u8);
+        }
+        assert_eq!(wide.enabled(), value);
+        assert_eq!(wide.enabled(), bytes.enabled());
+    }
+
+    #[test]
+    fn test_write_masked_preserves_unselected_bytes() {
+        let mut ic = InterruptController::new();
+        ic.write_masked(regs::LATCHED, 0xAABBCCDD, 0xFFFF_FFFF);
+        // 16-bit write to the middle two bytes
+        ic.write_masked(regs::LATCHED, 0x00112200, 0x00FF_FF00);
+        assert_eq!(ic.read(regs::LATCHED), 0xDD);
+        assert_eq!(ic.read(regs::LATCHED + 1), 0x22);
+        assert_eq!(ic.read(regs::LATCHED + 2), 0x11);
+        assert_eq!(ic.read(regs::LATCHED + 3), 0xAA);
+    }
 }
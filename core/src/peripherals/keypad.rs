@@ -27,6 +27,16 @@
 //! - Bit 0 (0x01): Scan complete - set when a full scan finishes
 //! - Bit 1 (0x02): Data changed - set when key state differs from previous scan
 //! - Bit 2 (0x04): Any key pressed - set when any key is detected during scan
+//!
+//! ## Interrupt
+//!
+//! The interrupt line is level-triggered per bit, like CEmu's
+//! keypad_intrpt_check(): it is high while any status bit is set whose
+//! enable bit (index 0x03) is also set. Writing 1s to the status register
+//! acknowledges just those bits, and changing the enable mask re-evaluates
+//! the line immediately. See `irq_level`.
+
+use super::device::{Peripheral, PortContext};
 
 /// Number of physical keypad rows
 pub const KEYPAD_ROWS: usize = 8;
@@ -77,6 +87,7 @@ mod regs {
 
 /// Keypad Controller
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct KeypadController {
     /// Packed control register: mode[1:0] | rowWait[15:2] | scanWait[31:16]
     control: u32,
@@ -227,8 +238,12 @@ impl KeypadController {
                 // but TI-OS might read data registers in mode 0.
                 self.data[row] |= 1 << col;
 
-                // Set status flags
-                self.status |= status::DATA_CHANGED | status::ANY_KEY;
+                // In the scanning modes (2/3) the scan reports the key when
+                // it reaches its row; setting status here would signal
+                // data-changed/any-key before the hardware does
+                if self.mode() & 2 == 0 {
+                    self.status |= status::DATA_CHANGED | status::ANY_KEY;
+                }
             } else {
                 // CEmu clears the keyMap bit on release:
                 // keyMap[row] &= ~(1 << col)
@@ -239,6 +254,17 @@ impl KeypadController {
         }
     }
 
+    /// Press edges not yet picked up by a scan, one byte per row (bit = column)
+    pub fn edge_bits(&self) -> [u8; KEYPAD_ROWS] {
+        let mut rows = [0u8; KEYPAD_ROWS];
+        for (bits, edges) in rows.iter_mut().zip(&self.key_edge_flags) {
+            for (col, &edge) in edges.iter().enumerate() {
+                *bits |= (edge as u8) << col;
+            }
+        }
+        rows
+    }
+
     // ========== Scan logic ==========
 
     /// Start a new scan cycle
@@ -303,6 +329,15 @@ impl KeypadController {
         (1u16 << col_limit) - 1
     }
 
+    /// CPU cycles until `tick` scans the next row (u32::MAX when idle)
+    pub fn cycles_until_event(&self) -> u32 {
+        if self.scanning {
+            self.scan_cycles_remaining
+        } else {
+            u32::MAX
+        }
+    }
+
     /// Advance the keypad controller by the given number of CPU cycles.
     /// This handles scan timing and status bit updates.
     /// Returns true if an interrupt should be raised.
@@ -359,7 +394,7 @@ impl KeypadController {
                     // Scan complete
                     self.finish_scan();
                     // Check if we should raise an interrupt
-                    if (self.status & self.enable) != 0 {
+                    if self.irq_level() {
                         interrupt_pending = true;
                     }
                 }
@@ -400,8 +435,15 @@ impl KeypadController {
         result
     }
 
-    /// Check if keypad interrupt should fire based on mode and key state
-    /// Returns true in mode 1 (any-key mode) or mode 2 (continuous) when any key is pressed
+    /// Interrupt line level: some status bit is set whose enable bit is set
+    /// too (CEmu's keypad_intrpt_check: intrpt_set(INT_KEYPAD, status & enable))
+    pub fn irq_level(&self) -> bool {
+        self.status & self.enable != 0
+    }
+
+    /// Check if a held key keeps the any-key interrupt asserted
+    /// Returns true in mode 1 (any-key mode) or mode 2 (continuous) when any
+    /// key is pressed and the any-key interrupt is enabled
     /// Note: CPU wake is handled separately via the any_key_wake signal
     pub fn check_interrupt(&self, key_state: &[[bool; KEYPAD_COLS]; KEYPAD_ROWS]) -> bool {
         // CEmu's keypad_any_check() runs when mode == 1 (any-key detection mode)
@@ -411,6 +453,9 @@ impl KeypadController {
         if m != mode::SINGLE && m != mode::CONTINUOUS {
             return false;
         }
+        if self.enable & status::ANY_KEY == 0 {
+            return false;
+        }
 
         // Check if any key is pressed (excluding ON key which has its own handling)
         for (row_idx, row) in key_state.iter().enumerate() {
@@ -531,8 +576,8 @@ impl KeypadController {
                 if bit_offset == 0 {
                     self.enable = value & 0x07;
                 }
-                // CEmu calls keypad_intrpt_check() but not any_key_check
-                // We don't need the flag here since intrpt_check is handled by mod.rs
+                // CEmu calls keypad_intrpt_check() but not any_key_check;
+                // the caller re-evaluates irq_level after every write
             }
             // data registers are read-only (unless poke, which we don't support here)
             0x04..=0x0B => {}
@@ -613,7 +658,7 @@ impl KeypadController {
         }
 
         // Return true if interrupt should fire (status & enable)
-        (self.status & self.enable) != 0
+        self.irq_level()
     }
 }
 
@@ -623,6 +668,25 @@ impl Default for KeypadController {
     }
 }
 
+impl Peripheral for KeypadController {
+    fn read(&mut self, offset: u32, ctx: &PortContext) -> u8 {
+        KeypadController::read(self, offset, ctx.key_state)
+    }
+
+    fn write(&mut self, offset: u32, value: u8, _ctx: &PortContext) {
+        KeypadController::write(self, offset, value)
+    }
+
+    fn tick(&mut self, cycles: u32, ctx: &PortContext) -> bool {
+        let scan_irq = KeypadController::tick(self, cycles, ctx.key_state);
+        scan_irq || self.check_interrupt(ctx.key_state)
+    }
+
+    fn reset(&mut self) {
+        KeypadController::reset(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -759,6 +823,49 @@ mod tests {
         assert_eq!(kp.status, 0xFA);
     }
 
+    #[test]
+    fn test_irq_level_per_bit() {
+        let mut kp = KeypadController::new();
+        assert!(!kp.irq_level());
+
+        kp.status = status::SCAN_DONE | status::ANY_KEY;
+        assert!(!kp.irq_level(), "nothing enabled");
+        kp.write(regs::INT_ACK, status::DATA_CHANGED);
+        assert!(!kp.irq_level(), "enabled bit not set");
+        kp.write(regs::INT_ACK, status::ANY_KEY);
+        assert!(kp.irq_level());
+
+        // Acknowledging a different bit keeps the line high
+        kp.write(regs::INT_STATUS, status::SCAN_DONE);
+        assert!(kp.irq_level());
+        kp.write(regs::INT_STATUS, status::ANY_KEY);
+        assert!(!kp.irq_level());
+    }
+
+    #[test]
+    fn test_scanning_modes_leave_status_to_the_scan() {
+        let mut kp = KeypadController::new();
+        kp.write(regs::CONTROL, mode::MULTI_GROUP);
+        kp.set_key_edge(0, 1, true);
+        assert_eq!(kp.status, 0);
+
+        kp.write(regs::CONTROL, mode::SINGLE);
+        kp.set_key_edge(0, 1, true);
+        assert_eq!(kp.status, status::DATA_CHANGED | status::ANY_KEY);
+    }
+
+    #[test]
+    fn test_held_key_needs_any_key_enable() {
+        let mut kp = KeypadController::new();
+        let mut keys = empty_key_state();
+        keys[0][0] = true;
+        kp.write(regs::CONTROL, mode::SINGLE);
+        kp.enable = status::SCAN_DONE;
+        assert!(!kp.check_interrupt(&keys));
+        kp.enable = status::ANY_KEY;
+        assert!(kp.check_interrupt(&keys));
+    }
+
     #[test]
     fn test_interrupt_check() {
         let mut kp = KeypadController::new();
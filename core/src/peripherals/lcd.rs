@@ -23,6 +23,8 @@
 //!
 //! LCD event uses CLOCK_24M; LCD DMA uses CLOCK_48M.
 
+use super::device::{Peripheral, PortContext};
+
 /// Display dimensions
 pub const LCD_WIDTH: usize = 320;
 pub const LCD_HEIGHT: usize = 240;
@@ -65,6 +67,7 @@ const LCD_PERIPH_ID: [u8; 8] = [0x11, 0x11, 0x14, 0x00, 0x0D, 0xF0, 0x05, 0xB1];
 
 /// LCD DMA state machine compare states (matches CEmu lcd_comp enum)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum LcdCompare {
     FrontPorch = 0,
@@ -82,6 +85,8 @@ pub struct LcdEventResult {
     pub schedule_dma_offset: Option<u64>,
     /// Whether interrupt state changed (caller should update interrupt controller)
     pub interrupt_changed: bool,
+    /// This event was the vertical sync starting a new frame
+    pub frame_start: bool,
 }
 
 /// Result from process_dma: optional reschedule info
@@ -93,8 +98,12 @@ pub struct LcdDmaResult {
     pub schedule_relative: Option<u64>,
 }
 
+/// Rate of the LCD event clock (scheduler Clock24M, CEmu CLOCK_24M)
+pub const LCD_CLOCK_HZ: u64 = 24_000_000;
+
 /// LCD Controller
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LcdController {
     /// Timing registers
     timing: [u32; 4],
@@ -113,12 +122,15 @@ pub struct LcdController {
     /// Lower panel current address
     lpcurr: u32,
     /// 256-entry color palette (stored as raw bytes, 2 bytes per entry)
+    #[cfg_attr(feature = "serde", serde(with = "serde_big_array::BigArray"))]
     palette: [u8; 512],
     /// Pre-converted palette: BGR565 (from 1555 raw palette)
     /// Updated on every palette write, matching CEmu's lcd.palettes[0]
+    #[cfg_attr(feature = "serde", serde(with = "serde_big_array::BigArray"))]
     palette_bgr565: [u16; 256],
     /// Pre-converted palette: RGB565 (R/B swapped from BGR565)
     /// Updated on every palette write, matching CEmu's lcd.palettes[1]
+    #[cfg_attr(feature = "serde", serde(with = "serde_big_array::BigArray"))]
     palette_rgb565: [u16; 256],
 
     // === DMA state machine (CEmu parity) ===
@@ -174,6 +186,7 @@ pub struct LcdController {
 
     /// Cursor image RAM (offsets 0x800-0xBFF, 1024 bytes)
     /// Used by CE programs (e.g. LibLoad) as scratch storage
+    #[cfg_attr(feature = "serde", serde(with = "serde_big_array::BigArray"))]
     cursor_image: [u8; 1024],
 
     // === Cursor registers (0xC00-0xC2C) ===
@@ -373,6 +386,36 @@ impl LcdController {
         self.ppf = 1 << (8 + self.wtrmrk - self.bpp);
     }
 
+    /// Full frame length in ticks of the 24 MHz LCD event clock, computed
+    /// from the timing registers as they are now (the event state machine
+    /// latches them at the next SYNC). The five LCD states sum to
+    /// (VSW + VBP + LPP + VFP) lines of HSW + HBP + CPL + HFP panel clocks.
+    pub fn frame_ticks(&self) -> u64 {
+        let t = &self.timing;
+        let hsw = (t[0] >> 8 & 0xFF) + 1;
+        let hfp = (t[0] >> 16 & 0xFF) + 1;
+        let hbp = (t[0] >> 24 & 0xFF) + 1;
+        let lpp = (t[1] & 0x3FF) + 1;
+        let vsw = (t[1] >> 10 & 0x3F) + 1;
+        let vfp = t[1] >> 16 & 0xFF;
+        let vbp = t[1] >> 24 & 0xFF;
+        let cpl = (t[2] >> 16 & 0x3FF) + 1;
+        let pcd = if t[2] >> 26 & 1 != 0 {
+            1
+        } else {
+            ((t[2] & 0x1F) | ((t[2] >> 27 & 0x1F) << 5)) + 2
+        };
+        (vsw + vbp + lpp + vfp) as u64 * (hsw + hbp + cpl + hfp) as u64 * pcd as u64
+    }
+
+    /// Effective refresh rate in millihertz (0 when the controller is disabled)
+    pub fn refresh_rate_millihz(&self) -> u32 {
+        if !self.is_enabled() {
+            return 0;
+        }
+        (LCD_CLOCK_HZ * 1000 / self.frame_ticks()) as u32
+    }
+
     /// Horizontal line duration in PCD units (used repeatedly in timing calculations)
     fn hline(&self) -> u32 {
         self.hsw + self.hbp + self.cpl + self.hfp
@@ -432,6 +475,7 @@ impl LcdController {
             duration,
             schedule_dma_offset,
             interrupt_changed: true,
+            frame_start: false,
         }
     }
 
@@ -459,6 +503,7 @@ impl LcdController {
             duration,
             schedule_dma_offset,
             interrupt_changed: true,
+            frame_start: true,
         }
     }
 
@@ -482,6 +527,7 @@ impl LcdController {
             duration,
             schedule_dma_offset,
             interrupt_changed: true,
+            frame_start: false,
         }
     }
 
@@ -972,6 +1018,20 @@ impl Default for LcdController {
     }
 }
 
+impl Peripheral for LcdController {
+    fn read(&mut self, offset: u32, _ctx: &PortContext) -> u8 {
+        LcdController::read(self, offset)
+    }
+
+    fn write(&mut self, offset: u32, value: u8, _ctx: &PortContext) {
+        LcdController::write(self, offset, value)
+    }
+
+    fn reset(&mut self) {
+        LcdController::reset(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -984,6 +1044,22 @@ mod tests {
         assert_eq!(lcd.compare, LcdCompare::FrontPorch);
     }
 
+    #[test]
+    fn test_refresh_rate_from_timing() {
+        let mut lcd = LcdController::new();
+        // 240x320 panel timing as programmed by TI-OS
+        lcd.timing = [0x1F0A0338, 0x0402093F, 0x00EF7802, 0];
+        // (VSW 3 + VBP 4 + LPP 320 + VFP 2) lines * (4 + 32 + 240 + 11) * PCD 4
+        assert_eq!(lcd.frame_ticks(), 329 * 287 * 4);
+        assert_eq!(lcd.refresh_rate_millihz(), 0, "disabled controller reports 0");
+        lcd.control = ctrl::ENABLE;
+        assert_eq!(lcd.refresh_rate_millihz(), 63543);
+
+        // Bypassing the divisor runs the panel at the full 24 MHz clock
+        lcd.timing[2] |= 1 << 26;
+        assert_eq!(lcd.frame_ticks(), 329 * 287);
+    }
+
     #[test]
     fn test_reset() {
         let mut lcd = LcdController::new();
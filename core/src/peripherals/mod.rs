@@ -12,6 +12,8 @@
 
 pub mod backlight;
 pub mod control;
+pub mod device;
+pub mod extension;
 pub mod flash;
 pub mod interrupt;
 pub mod keypad;
@@ -25,6 +27,8 @@ pub mod watchdog;
 
 pub use backlight::Backlight;
 pub use control::ControlPorts;
+pub use device::{Peripheral, PortContext};
+pub use extension::{CallbackDevice, Extensions};
 pub use flash::FlashController;
 pub use interrupt::InterruptController;
 pub use keypad::{KeypadController, KEYPAD_COLS, KEYPAD_ROWS};
@@ -35,6 +39,7 @@ pub use spi::SpiController;
 pub use timer::GeneralTimers;
 pub use watchdog::WatchdogController;
 
+use crate::error::LoadError;
 use interrupt::sources;
 
 /// Port address regions (offsets from 0xE00000)
@@ -61,8 +66,104 @@ const RTC_END: u32 = 0x180100;
 const BACKLIGHT_BASE: u32 = 0x1B0000; // 0xFB0000
 const BACKLIGHT_END: u32 = 0x1B0100;
 
+/// Controller selected by a port address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Device {
+    Control,
+    Flash,
+    Sha256,
+    Lcd,
+    Interrupt,
+    Timers,
+    Keypad,
+    Watchdog,
+    Rtc,
+    Backlight,
+}
+
+impl Device {
+    /// Short name used in memory map annotations
+    const fn name(self) -> &'static str {
+        match self {
+            Device::Control => "control",
+            Device::Flash => "flash controller",
+            Device::Sha256 => "sha256",
+            Device::Lcd => "lcd",
+            Device::Interrupt => "interrupt",
+            Device::Timers => "timers",
+            Device::Keypad => "keypad",
+            Device::Watchdog => "watchdog",
+            Device::Rtc => "rtc",
+            Device::Backlight => "backlight",
+        }
+    }
+}
+
+/// One memory-mapped register window: `[base, end)` offsets from 0xE00000
+#[derive(Debug, Clone, Copy)]
+struct Region {
+    base: u32,
+    end: u32,
+    device: Device,
+}
+
+/// Declarative port map, sorted by base. Each region starts on a 64KB page
+/// boundary and fits inside that page, so `PAGE_MAP` can dispatch with a
+/// single index instead of a chain of range compares.
+const REGIONS: [Region; 11] = [
+    Region { base: CONTROL_BASE, end: CONTROL_END, device: Device::Control },
+    Region { base: FLASH_BASE, end: FLASH_END, device: Device::Flash },
+    Region { base: SHA256_BASE, end: SHA256_END, device: Device::Sha256 },
+    Region { base: LCD_BASE, end: LCD_END, device: Device::Lcd },
+    Region { base: INT_BASE, end: INT_END, device: Device::Interrupt },
+    Region { base: TIMER_BASE, end: TIMER_END, device: Device::Timers },
+    Region { base: KEYPAD_BASE, end: KEYPAD_END, device: Device::Keypad },
+    Region { base: WATCHDOG_BASE, end: WATCHDOG_END, device: Device::Watchdog },
+    Region { base: RTC_BASE, end: RTC_END, device: Device::Rtc },
+    Region { base: BACKLIGHT_BASE, end: BACKLIGHT_END, device: Device::Backlight },
+    Region { base: CONTROL_ALT_BASE, end: CONTROL_ALT_END, device: Device::Control },
+];
+
+/// Number of 64KB pages in the port space (0xE00000 - 0xFFFFFF)
+const PAGE_COUNT: usize = 0x20;
+
+/// Page number -> index into REGIONS (u8::MAX = unmapped)
+const PAGE_MAP: [u8; PAGE_COUNT] = build_page_map();
+
+const fn build_page_map() -> [u8; PAGE_COUNT] {
+    let mut map = [u8::MAX; PAGE_COUNT];
+    let mut i = 0;
+    while i < REGIONS.len() {
+        let page = (REGIONS[i].base >> 16) as usize;
+        assert!(REGIONS[i].base & 0xFFFF == 0, "region must start on a page boundary");
+        assert!(REGIONS[i].end - REGIONS[i].base <= 0x10000, "region must fit in one page");
+        assert!(map[page] == u8::MAX, "one region per page");
+        map[page] = i as u8;
+        i += 1;
+    }
+    map
+}
+
+/// Peripheral register windows as `(start, end, name)` CPU addresses, sorted
+pub fn mmio_regions() -> impl Iterator<Item = (u32, u32, &'static str)> {
+    REGIONS.iter().map(|r| (0xE00000 + r.base, 0xE00000 + r.end, r.device.name()))
+}
+
+/// Resolve a port offset to its controller and register offset
+#[inline]
+fn decode_port(addr: u32) -> Option<(Device, u32)> {
+    let index = *PAGE_MAP.get((addr >> 16) as usize)?;
+    let region = REGIONS.get(index as usize)?;
+    if addr < region.end {
+        Some((region.device, addr - region.base))
+    } else {
+        None
+    }
+}
+
 /// Peripheral subsystem containing all hardware controllers
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Peripherals {
     /// Control ports (0xE00000, 0xFF0000)
     pub control: ControlPorts,
@@ -92,6 +193,14 @@ pub struct Peripherals {
     os_timer_state: bool,
     /// OS Timer cycle accumulator
     os_timer_cycles: u64,
+    /// True while a multi-byte bus write (LD (nn),HL etc.) is in progress
+    wide_write: bool,
+    /// Interrupt controller byte writes buffered until the wide write completes
+    pending_int_writes: Vec<(u32, u8)>,
+    /// Timer interrupt resync deferred until the wide write completes
+    timer_sync_pending: bool,
+    /// CPU cycles run since the last tick, while run_cycles batches ticks
+    pub unticked_cycles: u32,
 }
 
 impl Peripherals {
@@ -122,6 +231,10 @@ impl Peripherals {
             key_state: [[false; KEYPAD_COLS]; KEYPAD_ROWS],
             os_timer_state: false,
             os_timer_cycles: 0,
+            wide_write: false,
+            pending_int_writes: Vec::with_capacity(4),
+            timer_sync_pending: false,
+            unticked_cycles: 0,
         }
     }
 
@@ -140,8 +253,11 @@ impl Peripherals {
             self.keypad.set_key_edge(row, col, pressed);
 
             // Raise keypad interrupt on key press so TI-OS will check the keypad
-            // This is critical for TI-OS to detect keys when the keypad is in mode 0
-            if pressed {
+            // This is critical for TI-OS to detect keys when the keypad is in mode 0.
+            // In the other modes the line follows the status bits the
+            // program enabled, so e.g. a mode 3 scan that only enables
+            // scan-done isn't woken by every press.
+            if pressed && (self.keypad.mode() == 0 || self.keypad.irq_level()) {
                 self.interrupt.raise(sources::KEYPAD);
             }
         }
@@ -152,6 +268,16 @@ impl Peripherals {
         &self.key_state
     }
 
+    /// Drive the keypad interrupt from status & enable after a register
+    /// write (CEmu's keypad_intrpt_check)
+    pub fn sync_keypad_interrupt(&mut self) {
+        if self.keypad.irq_level() {
+            self.interrupt.raise(sources::KEYPAD);
+        } else {
+            self.interrupt.clear_raw(sources::KEYPAD);
+        }
+    }
+
     /// Reset all peripherals
     pub fn reset(&mut self) {
         self.control.reset();
@@ -167,6 +293,63 @@ impl Peripherals {
         self.key_state = [[false; KEYPAD_COLS]; KEYPAD_ROWS];
         self.os_timer_state = false;
         self.os_timer_cycles = 0;
+        self.wide_write = false;
+        self.pending_int_writes.clear();
+        self.timer_sync_pending = false;
+        self.unticked_cycles = 0;
+    }
+
+    /// Begin a multi-byte write (16/24/32-bit CPU store).
+    ///
+    /// Writes to the interrupt controller are buffered and timer interrupt
+    /// resync is deferred until `end_wide_write`, so the register sees the
+    /// whole value at once instead of passing through transient states
+    /// (e.g. half-updated enable masks latching a spurious interrupt).
+    pub fn begin_wide_write(&mut self) {
+        self.wide_write = true;
+    }
+
+    /// Finish a multi-byte write, committing buffered register updates.
+    pub fn end_wide_write(&mut self) {
+        self.wide_write = false;
+
+        // Group buffered bytes by 32-bit register and commit each at once
+        let mut i = 0;
+        while i < self.pending_int_writes.len() {
+            let reg = self.pending_int_writes[i].0 & !3;
+            let mut value = 0u32;
+            let mut mask = 0u32;
+            for &(addr, byte) in &self.pending_int_writes[i..] {
+                if addr & !3 != reg {
+                    break;
+                }
+                let shift = (addr & 3) * 8;
+                value |= (byte as u32) << shift;
+                mask |= 0xFF << shift;
+                i += 1;
+            }
+            self.interrupt.write_masked(reg, value, mask);
+        }
+        self.pending_int_writes.clear();
+
+        if self.timer_sync_pending {
+            self.timer_sync_pending = false;
+            self.sync_timer_interrupts();
+        }
+    }
+
+    /// Recalculate timer interrupt lines from (status & mask).
+    /// Matches CEmu's intrpt_set(INT_TIMERn, ...) after timer register updates.
+    fn sync_timer_interrupts(&mut self) {
+        let int_state = self.timers.interrupt_state();
+        let timer_sources = [sources::TIMER1, sources::TIMER2, sources::TIMER3];
+        for (i, &src) in timer_sources.iter().enumerate() {
+            if int_state & (1 << i) != 0 {
+                self.interrupt.raise(src);
+            } else {
+                self.interrupt.clear_raw(src);
+            }
+        }
     }
 
     /// Read from a port address
@@ -181,50 +364,46 @@ impl Peripherals {
     ) -> u8 {
         // Get CPU speed for timing calculations
         let cpu_speed = self.control.cpu_speed();
-        match addr {
-            // Control Ports (0xE00000 - 0xE000FF)
-            a if a >= CONTROL_BASE && a < CONTROL_END => self.control.read(a - CONTROL_BASE),
-
-            // Flash Controller (0xE10000 - 0xE100FF)
-            a if a >= FLASH_BASE && a < FLASH_END => self.flash.read(a - FLASH_BASE),
-
-            // SHA256 Accelerator (0xE20000 - 0xE200FF)
-            a if a >= SHA256_BASE && a < SHA256_END => self.sha256.read(a - SHA256_BASE),
-
-            // Control Ports alternate (0xFF0000 - 0xFF00FF, via OUT0/IN0)
-            a if a >= CONTROL_ALT_BASE && a < CONTROL_ALT_END => {
-                self.control.read(a - CONTROL_ALT_BASE)
-            }
-
-            // LCD Controller (0xE30000 - 0xE300FF)
-            a if a >= LCD_BASE && a < LCD_END => self.lcd.read(a - LCD_BASE),
-
-            // Interrupt Controller (0xF00000 - 0xF0001F)
-            a if a >= INT_BASE && a < INT_END => self.interrupt.read(a - INT_BASE),
-
-            // Timers (0xF20000 - 0xF2003F)
-            a if a >= TIMER_BASE && a < TIMER_END => self.timers.read(a - TIMER_BASE),
-
-            // Keypad Controller (0xF50000 - 0xF5003F)
-            a if a >= KEYPAD_BASE && a < KEYPAD_END => self.keypad.read(a - KEYPAD_BASE, key_state),
-
-            // Watchdog Controller (0xF60000 - 0xF600FF)
-            a if a >= WATCHDOG_BASE && a < WATCHDOG_END => self.watchdog.read(a - WATCHDOG_BASE),
-
-            // RTC Controller (0xF80000 - 0xF800FF)
-            a if a >= RTC_BASE && a < RTC_END => self.rtc.read(a - RTC_BASE, current_cycles, cpu_speed),
-
-            // Backlight Controller (0xFB0000 - 0xFB00FF)
-            a if a >= BACKLIGHT_BASE && a < BACKLIGHT_END => self.backlight.read(a - BACKLIGHT_BASE),
+        match decode_port(addr) {
+            Some((Device::Control, offset)) => self.control.read(offset),
+            Some((Device::Flash, offset)) => self.flash.read(offset),
+            Some((Device::Sha256, offset)) => self.sha256.read(offset),
+            Some((Device::Lcd, offset)) => self.lcd.read(offset),
+            Some((Device::Interrupt, offset)) => self.interrupt.read(offset),
+            Some((Device::Timers, offset)) => self.timers.read(offset),
+            Some((Device::Keypad, offset)) => self.keypad.read(offset, key_state),
+            Some((Device::Watchdog, offset)) => self.watchdog.read(offset),
+            Some((Device::Rtc, offset)) => self.rtc.read(offset, current_cycles, cpu_speed),
+            Some((Device::Backlight, offset)) => self.backlight.read(offset),
 
             // Unmapped - return from fallback storage
-            _ => {
+            None => {
                 let offset = (addr as usize) % Self::FALLBACK_SIZE;
                 self.fallback[offset]
             }
         }
     }
 
+    /// Read a port address without side effects, for debugger views:
+    /// takes `&self`, so no peripheral state can change. Registers read
+    /// as the CPU would see them, minus read-triggered updates.
+    /// addr is offset from 0xE00000
+    pub fn peek(&self, addr: u32, key_state: &[[bool; KEYPAD_COLS]; KEYPAD_ROWS]) -> u8 {
+        match decode_port(addr) {
+            Some((Device::Control, offset)) => self.control.read(offset),
+            Some((Device::Flash, offset)) => self.flash.read(offset),
+            Some((Device::Sha256, offset)) => self.sha256.read(offset),
+            Some((Device::Lcd, offset)) => self.lcd.read(offset),
+            Some((Device::Interrupt, offset)) => self.interrupt.read(offset),
+            Some((Device::Timers, offset)) => self.timers.read(offset),
+            Some((Device::Keypad, offset)) => self.keypad.peek(offset, key_state),
+            Some((Device::Watchdog, offset)) => self.watchdog.read(offset),
+            Some((Device::Rtc, offset)) => self.rtc.peek(offset),
+            Some((Device::Backlight, offset)) => self.backlight.read(offset),
+            None => self.fallback[(addr as usize) % Self::FALLBACK_SIZE],
+        }
+    }
+
     /// Write to a port address
     /// addr is offset from 0xE00000
     /// current_cycles: CPU cycle count for timing-sensitive peripherals
@@ -232,48 +411,33 @@ impl Peripherals {
         // Get CPU speed for timing calculations
         let cpu_speed = self.control.cpu_speed();
 
-        match addr {
-            // Control Ports (0xE00000 - 0xE000FF)
-            a if a >= CONTROL_BASE && a < CONTROL_END => self.control.write(a - CONTROL_BASE, value),
-
-            // Flash Controller (0xE10000 - 0xE100FF)
-            a if a >= FLASH_BASE && a < FLASH_END => self.flash.write(a - FLASH_BASE, value),
-
-            // SHA256 Accelerator (0xE20000 - 0xE200FF)
-            a if a >= SHA256_BASE && a < SHA256_END => self.sha256.write(a - SHA256_BASE, value),
-
-            // Control Ports alternate (0xFF0000 - 0xFF00FF, via OUT0/IN0)
-            a if a >= CONTROL_ALT_BASE && a < CONTROL_ALT_END => {
-                self.control.write(a - CONTROL_ALT_BASE, value)
+        match decode_port(addr) {
+            Some((Device::Control, offset)) => self.control.write(offset, value),
+            Some((Device::Flash, offset)) => self.flash.write(offset, value),
+            Some((Device::Sha256, offset)) => self.sha256.write(offset, value),
+            Some((Device::Lcd, offset)) => self.lcd.write(offset, value),
+
+            Some((Device::Interrupt, offset)) => {
+                if self.wide_write {
+                    self.pending_int_writes.push((offset, value));
+                } else {
+                    self.interrupt.write(offset, value);
+                }
             }
 
-            // LCD Controller (0xE30000 - 0xE300FF)
-            a if a >= LCD_BASE && a < LCD_END => self.lcd.write(a - LCD_BASE, value),
-
-            // Interrupt Controller (0xF00000 - 0xF0001F)
-            a if a >= INT_BASE && a < INT_END => self.interrupt.write(a - INT_BASE, value),
-
-            // Timers (0xF20000 - 0xF2003F)
-            a if a >= TIMER_BASE && a < TIMER_END => {
-                self.timers.write(a - TIMER_BASE, value);
+            Some((Device::Timers, offset)) => {
+                self.timers.write(offset, value);
                 // CEmu: after any timer register write, recalculate interrupt state
                 // for all 3 timers based on (status & mask). This is critical for the
                 // ISR to clear timer interrupts by writing to the status register.
-                let int_state = self.timers.interrupt_state();
-                let timer_sources = [sources::TIMER1, sources::TIMER2, sources::TIMER3];
-                for (i, &src) in timer_sources.iter().enumerate() {
-                    if int_state & (1 << i) != 0 {
-                        self.interrupt.raise(src);
-                    } else {
-                        self.interrupt.clear_raw(src);
-                    }
+                if self.wide_write {
+                    self.timer_sync_pending = true;
+                } else {
+                    self.sync_timer_interrupts();
                 }
             }
 
-            // Keypad Controller (0xF50000 - 0xF5003F)
-            a if a >= KEYPAD_BASE && a < KEYPAD_END => {
-                let offset = a - KEYPAD_BASE;
-
+            Some((Device::Keypad, offset)) => {
                 let flag_before = self.keypad.needs_any_key_check;
                 self.keypad.write(offset, value);
                 let flag_after = self.keypad.needs_any_key_check;
@@ -287,32 +451,18 @@ impl Peripherals {
                 if self.keypad.needs_any_key_check {
                     self.keypad.needs_any_key_check = false;
 
-                    let should_interrupt = self.keypad.any_key_check(&self.key_state);
-
-                    // Update keypad interrupt state
-                    if should_interrupt {
-                        self.interrupt.raise(sources::KEYPAD);
-                    } else {
-                        self.interrupt.clear_raw(sources::KEYPAD);
-                    }
+                    self.keypad.any_key_check(&self.key_state);
                 }
+                // Status acknowledges and enable changes take effect at once
+                self.sync_keypad_interrupt();
             }
 
-            // Watchdog Controller (0xF60000 - 0xF600FF)
-            a if a >= WATCHDOG_BASE && a < WATCHDOG_END => self.watchdog.write(a - WATCHDOG_BASE, value),
-
-            // RTC Controller (0xF80000 - 0xF800FF)
-            a if a >= RTC_BASE && a < RTC_END => {
-                self.rtc.write(a - RTC_BASE, value, current_cycles, cpu_speed)
-            }
-
-            // Backlight Controller (0xFB0000 - 0xFB00FF)
-            a if a >= BACKLIGHT_BASE && a < BACKLIGHT_END => {
-                self.backlight.write(a - BACKLIGHT_BASE, value)
-            }
+            Some((Device::Watchdog, offset)) => self.watchdog.write(offset, value),
+            Some((Device::Rtc, offset)) => self.rtc.write(offset, value, current_cycles, cpu_speed),
+            Some((Device::Backlight, offset)) => self.backlight.write(offset, value),
 
             // Unmapped - store in fallback
-            _ => {
+            None => {
                 let offset = (addr as usize) % Self::FALLBACK_SIZE;
                 self.fallback[offset] = value;
             }
@@ -330,21 +480,16 @@ impl Peripherals {
         // and raise interrupts.
         let cpu_speed = self.control.cpu_speed();
         self.timers.tick(cycles, cpu_speed, delay_remaining);
+        // Expiry with reset enabled is picked up by the emulator
+        // (take_reset_request), which restarts the ASIC
+        self.watchdog.tick(cycles, cpu_speed);
 
         // Sync timer interrupt state after tick — ensures stale raw bits are cleared
         // when timers no longer have active status. Without this, timer raw bits
         // accumulate from scheduler events but are only cleared when the ISR writes
         // to the timer registers. If the interrupt wasn't enabled, the ISR never ran,
         // and raw stays permanently set.
-        let int_state = self.timers.interrupt_state();
-        let timer_sources = [sources::TIMER1, sources::TIMER2, sources::TIMER3];
-        for (i, &src) in timer_sources.iter().enumerate() {
-            if int_state & (1 << i) != 0 {
-                self.interrupt.raise(src);
-            } else {
-                self.interrupt.clear_raw(src);
-            }
-        }
+        self.sync_timer_interrupts();
 
         // LCD interrupts are now driven by scheduler events (EventId::Lcd / EventId::LcdDma)
         // in emu.rs, matching CEmu's lcd_event()/lcd_dma() architecture.
@@ -353,9 +498,9 @@ impl Peripherals {
 
         // Tick keypad scan timing and update interrupt state
         // CEmu calls intrpt_set(INT_KEYPAD, status & enable) which sets OR clears raw
-        let keypad_scan_irq = self.keypad.tick(cycles, &self.key_state);
+        self.keypad.tick(cycles, &self.key_state);
         let keypad_any_irq = self.keypad.check_interrupt(&self.key_state);
-        if keypad_scan_irq || keypad_any_irq {
+        if self.keypad.irq_level() || keypad_any_irq {
             self.interrupt.raise(sources::KEYPAD);
         } else {
             self.interrupt.clear_raw(sources::KEYPAD);
@@ -375,33 +520,11 @@ impl Peripherals {
     ///   2. sched_repeat(id, ...)                       — reschedule
     ///   3. gpt.osTimerState = !gpt.osTimerState        — toggle state
     fn tick_os_timer(&mut self, cycles: u32) {
-        // Get CPU speed from control port (bits 0-1)
-        let speed = (self.control.read(0x01) & 0x03) as usize;
-
-        // CPU clock rates: 6MHz, 12MHz, 24MHz, 48MHz
-        let cpu_clock: u64 = match speed {
-            0 => 6_000_000,
-            1 => 12_000_000,
-            2 => 24_000_000,
-            _ => 48_000_000,
-        };
-
-        // Cycles per 32KHz tick at current CPU speed
-        let cycles_per_32k_tick = cpu_clock / Self::CLOCK_32K as u64;
-
         self.os_timer_cycles += cycles as u64;
 
         // Check if enough cycles have passed to toggle state
         loop {
-            // OS Timer interval in 32K ticks depends on state:
-            // - When state is false: wait ost_ticks[speed] ticks
-            // - When state is true: wait 1 tick
-            let ticks_needed = if self.os_timer_state {
-                1u64
-            } else {
-                Self::OS_TIMER_TICKS[speed] as u64
-            };
-            let cycles_needed = ticks_needed * cycles_per_32k_tick;
+            let cycles_needed = self.os_timer_interval();
 
             if self.os_timer_cycles < cycles_needed {
                 break;
@@ -422,6 +545,74 @@ impl Peripherals {
         }
     }
 
+    /// CPU cycles the OS Timer stays in its current state at the current
+    /// CPU speed
+    fn os_timer_interval(&self) -> u64 {
+        let speed = self.control.cpu_speed() as usize;
+
+        // CPU clock rates: 6MHz, 12MHz, 24MHz, 48MHz
+        let cpu_clock: u64 = match speed {
+            0 => 6_000_000,
+            1 => 12_000_000,
+            2 => 24_000_000,
+            _ => 48_000_000,
+        };
+
+        // Cycles per 32KHz tick at current CPU speed
+        let cycles_per_32k_tick = cpu_clock / Self::CLOCK_32K as u64;
+
+        // OS Timer interval in 32K ticks depends on state:
+        // - When state is false: wait ost_ticks[speed] ticks
+        // - When state is true: wait 1 tick
+        let ticks_needed = if self.os_timer_state {
+            1u64
+        } else {
+            Self::OS_TIMER_TICKS[speed] as u64
+        };
+        ticks_needed * cycles_per_32k_tick
+    }
+
+    /// CPU cycles until `tick` would next change anything the CPU can see
+    /// without a port access: a timer match/overflow, an OS Timer edge or a
+    /// keypad row scan. Ticking fewer cycles than this, in one call or many,
+    /// leaves the peripherals in the same state.
+    pub fn cycles_until_tick_event(&self) -> u32 {
+        let os_timer = self.os_timer_interval().saturating_sub(self.os_timer_cycles);
+        self.timers
+            .cycles_until_event(self.control.cpu_speed())
+            .min(self.keypad.cycles_until_event())
+            .min(os_timer.min(u32::MAX as u64) as u32)
+    }
+
+    /// Tick the cycles run since the last tick (`unticked_cycles`) so a
+    /// port access sees up-to-date timers and keypad. Only valid while no
+    /// tick event is due (see `cycles_until_tick_event`), so the timer
+    /// delay pipeline is never involved.
+    #[inline]
+    pub fn catch_up(&mut self) {
+        if self.unticked_cycles > 0 {
+            let cycles = std::mem::take(&mut self.unticked_cycles);
+            self.tick(cycles, 0);
+        }
+    }
+
+    /// Every controller owned by the subsystem, by name, for code that
+    /// handles devices generically (SPI lives on the bus)
+    pub fn devices_mut(&mut self) -> [(&'static str, &mut dyn Peripheral); 10] {
+        [
+            ("control", &mut self.control),
+            ("flash", &mut self.flash),
+            ("interrupt", &mut self.interrupt),
+            ("timers", &mut self.timers),
+            ("lcd", &mut self.lcd),
+            ("keypad", &mut self.keypad),
+            ("watchdog", &mut self.watchdog),
+            ("rtc", &mut self.rtc),
+            ("sha256", &mut self.sha256),
+            ("backlight", &mut self.backlight),
+        ]
+    }
+
     /// Check if any interrupt is pending
     pub fn irq_pending(&self) -> bool {
         self.interrupt.irq_pending()
@@ -560,9 +751,9 @@ impl Peripherals {
     }
 
     /// Load peripheral state from bytes
-    pub fn from_bytes(&mut self, buf: &[u8]) -> Result<(), i32> {
+    pub fn from_bytes(&mut self, buf: &[u8]) -> Result<(), LoadError> {
         if buf.len() < Self::SNAPSHOT_SIZE {
-            return Err(-105);
+            return Err(LoadError::StateTruncated { expected: Self::SNAPSHOT_SIZE, found: buf.len() });
         }
 
         let mut pos = 0;
@@ -931,6 +1122,42 @@ mod tests {
         assert!(pending);
     }
 
+    #[test]
+    fn test_keypad_interrupt_follows_status_and_enable() {
+        let mut p = Peripherals::new();
+        let raised = |p: &Peripherals| p.interrupt.raw() & sources::KEYPAD != 0;
+
+        // Mode 3 with nothing enabled: presses and scans never raise the line
+        p.write_test(KEYPAD_BASE + 0x00, 0x03);
+        p.set_key(1, 1, true);
+        assert!(!raised(&p));
+        p.tick(1000, 0);
+        assert!(!raised(&p));
+        assert_ne!(p.keypad.status(), 0, "the scan still reports status");
+
+        // Enabling scan-done raises it at once since a scan already finished
+        p.write_test(KEYPAD_BASE + 0x0C, 0x01);
+        assert!(raised(&p));
+
+        // Acknowledging other bits leaves it high; scan-done drops it
+        p.write_test(KEYPAD_BASE + 0x08, 0x06);
+        assert!(raised(&p));
+        p.write_test(KEYPAD_BASE + 0x08, 0x01);
+        assert!(!raised(&p));
+
+        // The next scan raises it again and it stays high until acknowledged
+        p.tick(1000, 0);
+        assert!(raised(&p));
+        p.tick(1, 0);
+        assert!(raised(&p));
+
+        // Masking the bit lowers the line without touching status
+        let status = p.keypad.status();
+        p.write_test(KEYPAD_BASE + 0x0C, 0x00);
+        assert!(!raised(&p));
+        assert_eq!(p.keypad.status(), status);
+    }
+
     /// Helper to process all pending delay tiers and raise timer interrupts
     fn process_timer_delays(p: &mut Peripherals) {
         loop {
@@ -1125,4 +1352,82 @@ mod tests {
         assert_eq!(p.flash.wait_states(), 0x04); // CEmu default
         assert_eq!(p.flash.map_select(), 0x06); // CEmu default
     }
+
+    #[test]
+    fn test_wide_write_commits_interrupt_enable_at_end() {
+        let mut p = Peripherals::new();
+        p.interrupt.raise(sources::KEYPAD);
+
+        p.begin_wide_write();
+        p.write_test(INT_BASE + 0x04, 0x00);
+        p.write_test(INT_BASE + 0x05, (sources::KEYPAD >> 8) as u8);
+        p.write_test(INT_BASE + 0x06, 0x00);
+        // Nothing visible until the store completes
+        assert_eq!(p.interrupt.enabled(), 0);
+        assert!(!p.irq_pending());
+
+        p.end_wide_write();
+        assert_eq!(p.interrupt.enabled(), sources::KEYPAD);
+        assert!(p.irq_pending());
+    }
+
+    #[test]
+    fn test_wide_write_spanning_registers() {
+        let mut p = Peripherals::new();
+        // 16-bit store to 0x07-0x08: top byte of ENABLED + ack byte
+        p.begin_wide_write();
+        p.write_test(INT_BASE + 0x07, 0x12);
+        p.write_test(INT_BASE + 0x08, 0x00);
+        p.end_wide_write();
+        assert_eq!(p.interrupt.enabled(), 0x12000000);
+    }
+
+    #[test]
+    fn test_wide_write_defers_timer_interrupt_sync() {
+        let mut p = Peripherals::new();
+        // Timer 0 has a pending zero/overflow status bit
+        p.timers.set_status_word(0x004);
+
+        p.begin_wide_write();
+        p.write_test(TIMER_BASE + 0x38, 0x04);
+        p.write_test(TIMER_BASE + 0x39, 0x00);
+        p.write_test(TIMER_BASE + 0x3A, 0x00);
+        assert_eq!(p.interrupt.raw() & sources::TIMER1, 0);
+
+        p.end_wide_write();
+        assert_ne!(p.interrupt.raw() & sources::TIMER1, 0);
+    }
+
+    #[test]
+    fn test_page_map_matches_region_table() {
+        for addr in 0..(PAGE_COUNT as u32) << 16 {
+            let linear = REGIONS
+                .iter()
+                .find(|r| addr >= r.base && addr < r.end)
+                .map(|r| (r.device, addr - r.base));
+            assert_eq!(decode_port(addr), linear, "addr {:06X}", addr + 0xE00000);
+        }
+        assert!(REGIONS.windows(2).all(|w| w[0].base < w[1].base), "regions sorted");
+        assert_eq!(decode_port(CONTROL_ALT_BASE + 0x01), Some((Device::Control, 0x01)));
+        assert_eq!(decode_port(LCD_END), None);
+        assert_eq!(decode_port(0x200000), None);
+    }
+
+    #[test]
+    fn test_generic_access_matches_port_dispatch() {
+        let mut p = Peripherals::new();
+        let keys = empty_keys();
+        let ctx = PortContext { key_state: &keys, cycles: 0, cpu_speed: 0, delay_remaining: 0 };
+        for (_, device) in p.devices_mut() {
+            device.reset();
+        }
+
+        let [.., (_, backlight)] = p.devices_mut();
+        backlight.write(0x24, 0x80, &ctx);
+        assert_eq!(p.read(BACKLIGHT_BASE + 0x24, &keys, 0), 0x80);
+
+        p.write_test(TIMER_BASE + 0x04, 0x5A);
+        let (_, timers) = &mut p.devices_mut()[3];
+        assert_eq!(timers.read(0x04, &ctx), 0x5A);
+    }
 }
@@ -9,6 +9,7 @@
 //!
 //! Reference: CEmu panel.c / panel.h
 
+
 /// ST7789V commands used during initialization
 #[allow(dead_code)]
 mod cmd {
@@ -30,6 +31,7 @@ mod cmd {
 
 /// Panel stub state
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PanelStub {
     /// Current command being processed
     current_cmd: u8,
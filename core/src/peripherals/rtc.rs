@@ -11,6 +11,18 @@
 //! - LOAD_LATCH: Copies load registers to latched, fires load-latch interrupt
 //!
 //! The RTC uses a 32.768 kHz clock. One full second is TICKS_PER_SECOND (32768) ticks.
+//!
+//! Crystal drift and trim: a real crystal runs some ppm fast or slow, so long
+//! sessions drift from wall time. The emulator can set a drift in ppm and a
+//! trim, a signed count of 32 kHz ticks skipped (positive) or inserted
+//! (negative) per 2^20 ticks (32 s), about 0.95 ppm per step, to test
+//! programs against a clock that was calibrated. Both are frontend settings
+//! kept across resets; the RTC has no trim register, so the CPU can't see
+//! either. They shift when each second ends; the fractional remainder is
+//! carried, so the long-run rate is exact. Neither has an effect while the
+//! clock follows host time.
+
+use super::device::{Peripheral, PortContext};
 
 /// Number of bits for time fields (8 bits each for sec, min, hour)
 const RTC_TIME_BITS: u8 = 8 * 3; // 24 bits
@@ -35,9 +47,14 @@ pub const TICKS_PER_SECOND: u64 = 32768;
 pub const LATCH_TICK_OFFSET: u64 = 16429;
 /// Delay for load-latch event after latch
 const LOAD_LATCH_TICK_OFFSET: u64 = LATCH_TICK_OFFSET + 7;
+/// Largest drift `set_drift_ppm` accepts (1%)
+pub const MAX_DRIFT_PPM: i32 = 10_000;
+/// Units of the drift accumulator per 32 kHz tick (ppm x 32 s of ticks)
+const DRIFT_UNITS_PER_TICK: i64 = 32_000_000;
 
 /// RTC operating mode (matches CEmu's rtc_mode enum)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RtcMode {
     /// Processing time tick and load completion
     Tick,
@@ -47,10 +64,23 @@ pub enum RtcMode {
     LoadLatch,
 }
 
+/// What drives the clock forward (an emulator setting, not hardware state)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RtcClock {
+    /// One second per emulated second: turbo fast-forwards the clock
+    #[default]
+    Emulated,
+    /// Follow host wall time (as reported to the emulator, pauses excluded):
+    /// the clock stays real-time at any emulation speed
+    HostTime,
+}
+
 /// RTC datetime representation (matches CEmu's rtc_datetime_t bit layout)
 /// Stored as a packed u64: day[39:24] | hour[23:16] | min[15:8] | sec[7:0]
 /// CEmu uses bitfield: day:16, hour:8, min:8, sec:8, pad:24 (little-endian order)
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct RtcDatetime {
     sec: u8,
     min: u8,
@@ -81,6 +111,7 @@ impl RtcDatetime {
 
 /// RTC alarm (only time fields, no day)
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct RtcAlarm {
     sec: u8,
     min: u8,
@@ -96,6 +127,7 @@ impl RtcAlarm {
 
 /// RTC Controller
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RtcController {
     /// Control register (bit 0 = enable, bit 6 = load, bit 7 = latch enable)
     control: u8,
@@ -113,6 +145,13 @@ pub struct RtcController {
     load: RtcDatetime,
     /// Alarm time
     alarm: RtcAlarm,
+    /// Ticks skipped per 2^20, signed (emulator setting)
+    trim: i16,
+    /// Crystal drift in ppm, positive = fast (emulator setting)
+    drift_ppm: i32,
+    /// Fractional tick error carried between seconds, in
+    /// 1/DRIFT_UNITS_PER_TICK ticks
+    drift_error: i64,
 }
 
 impl RtcController {
@@ -131,12 +170,49 @@ impl RtcController {
             latched: RtcDatetime::default(),
             load: RtcDatetime::default(),
             alarm: RtcAlarm::default(),
+            trim: 0,
+            drift_ppm: 0,
+            drift_error: 0,
         }
     }
 
-    /// Reset the RTC controller
+    /// Reset the RTC controller (the drift and trim settings are kept)
     pub fn reset(&mut self) {
-        *self = Self::new();
+        *self = Self { drift_ppm: self.drift_ppm, trim: self.trim, ..Self::new() };
+    }
+
+    /// Crystal drift in ppm (positive runs fast), up to +-MAX_DRIFT_PPM.
+    /// Returns false, changing nothing, if out of range.
+    pub fn set_drift_ppm(&mut self, ppm: i32) -> bool {
+        if ppm.abs() > MAX_DRIFT_PPM {
+            return false;
+        }
+        self.drift_ppm = ppm;
+        true
+    }
+
+    pub fn drift_ppm(&self) -> i32 {
+        self.drift_ppm
+    }
+
+    /// Skip `trim` 32 kHz ticks per 2^20 (negative: insert), from the next
+    /// second on
+    pub fn set_trim(&mut self, trim: i16) {
+        self.trim = trim;
+    }
+
+    pub fn trim(&self) -> i16 {
+        self.trim
+    }
+
+    /// Length of the second ending now in true 32 kHz ticks: nominally
+    /// `ticks`, shortened by a fast crystal and lengthened by skipped ticks
+    fn drift_adjusted(&mut self, ticks: u64) -> u64 {
+        // Per second: 32768 * (trim / 2^20 - ppm / 10^6) ticks, times 32*10^6
+        self.drift_error += self.trim as i64 * 1_000_000 - self.drift_ppm as i64 * 1_048_576;
+        let whole = self.drift_error / DRIFT_UNITS_PER_TICK;
+        self.drift_error -= whole * DRIFT_UNITS_PER_TICK;
+        (ticks as i64 + whole).max(1) as u64
     }
 
     /// Process load ticks from startTick to endTick
@@ -167,6 +243,11 @@ impl RtcController {
     /// Read a register byte
     /// addr is offset from controller base (0-0xFF)
     pub fn read(&mut self, addr: u32, _current_cycles: u64, _cpu_speed: u8) -> u8 {
+        self.peek(addr)
+    }
+
+    /// Read a register byte without side effects (for debugging)
+    pub fn peek(&self, addr: u32) -> u8 {
         let index = addr & 0xFF;
         let bit_offset = ((index & 3) << 3) as u32;
 
@@ -294,6 +375,12 @@ impl RtcController {
     /// - LATCH: latch counter to registers, check for load, transition
     /// - LOAD_LATCH: latch load registers, fire interrupt, transition to TICK
     pub fn process_event(&mut self) -> (u64, bool) {
+        self.process_event_seconds(1)
+    }
+
+    /// `process_event` with the TICK state adding `seconds` seconds instead of
+    /// one (0 holds the time). Used when the clock follows host time.
+    pub fn process_event_seconds(&mut self, seconds: u32) -> (u64, bool) {
         let mut raise_interrupt = false;
 
         match self.mode {
@@ -308,37 +395,10 @@ impl RtcController {
                 let delay = LATCH_TICK_OFFSET;
 
                 // Increment time if enabled (bit 0)
-                if self.control & 1 != 0 {
-                    let mut interrupts: u8 = 1; // Second interrupt always
-
-                    self.counter.sec += 1;
-                    if self.counter.sec >= 60 {
-                        if self.counter.sec == 60 {
-                            interrupts |= 2; // Minute rollover
-                            self.counter.min += 1;
-                            if self.counter.min >= 60 {
-                                if self.counter.min == 60 {
-                                    interrupts |= 4; // Hour rollover
-                                    self.counter.hour += 1;
-                                    if self.counter.hour >= 24 {
-                                        if self.counter.hour == 24 {
-                                            interrupts |= 8; // Day rollover
-                                            self.counter.day = self.counter.day.wrapping_add(1);
-                                        }
-                                        self.counter.hour = 0;
-                                    }
-                                }
-                                self.counter.min = 0;
-                            }
-                        }
-                        self.counter.sec = 0;
-                    }
-
-                    // Check alarm match
-                    // CEmu: counter.value >> (RTC_DATETIME_BITS - RTC_TIME_BITS) == alarm.value
-                    let counter_time = (self.counter.to_value() >> (RTC_DATETIME_BITS - RTC_TIME_BITS)) as u32;
-                    if counter_time == self.alarm.to_value() {
-                        interrupts |= 16;
+                if self.control & 1 != 0 && seconds > 0 {
+                    let mut interrupts: u8 = 0;
+                    for _ in 0..seconds {
+                        interrupts |= self.advance_second();
                     }
 
                     // Apply interrupt mask (control bits [5:1])
@@ -371,7 +431,7 @@ impl RtcController {
                 } else {
                     // No load — next event is tick
                     self.mode = RtcMode::Tick;
-                    (TICKS_PER_SECOND - LATCH_TICK_OFFSET, false)
+                    (self.drift_adjusted(TICKS_PER_SECOND - LATCH_TICK_OFFSET), false)
                 }
             }
 
@@ -383,9 +443,46 @@ impl RtcController {
                 raise_interrupt = true;
                 // Next event is tick
                 self.mode = RtcMode::Tick;
-                (TICKS_PER_SECOND - LOAD_LATCH_TICK_OFFSET, raise_interrupt)
+                (self.drift_adjusted(TICKS_PER_SECOND - LOAD_LATCH_TICK_OFFSET), raise_interrupt)
+            }
+        }
+    }
+
+    /// Add one second to the counter; returns the interrupt bits it raises
+    /// (before the control mask): 1 second, 2 minute, 4 hour, 8 day, 16 alarm
+    fn advance_second(&mut self) -> u8 {
+        let mut interrupts: u8 = 1; // Second interrupt always
+
+        self.counter.sec += 1;
+        if self.counter.sec >= 60 {
+            if self.counter.sec == 60 {
+                interrupts |= 2; // Minute rollover
+                self.counter.min += 1;
+                if self.counter.min >= 60 {
+                    if self.counter.min == 60 {
+                        interrupts |= 4; // Hour rollover
+                        self.counter.hour += 1;
+                        if self.counter.hour >= 24 {
+                            if self.counter.hour == 24 {
+                                interrupts |= 8; // Day rollover
+                                self.counter.day = self.counter.day.wrapping_add(1);
+                            }
+                            self.counter.hour = 0;
+                        }
+                    }
+                    self.counter.min = 0;
+                }
             }
+            self.counter.sec = 0;
+        }
+
+        // Check alarm match
+        // CEmu: counter.value >> (RTC_DATETIME_BITS - RTC_TIME_BITS) == alarm.value
+        let counter_time = (self.counter.to_value() >> (RTC_DATETIME_BITS - RTC_TIME_BITS)) as u32;
+        if counter_time == self.alarm.to_value() {
+            interrupts |= 16;
         }
+        interrupts
     }
 
     /// Legacy method for compatibility - advance the load operation by one 32kHz tick
@@ -422,6 +519,24 @@ impl Default for RtcController {
     }
 }
 
+impl Peripheral for RtcController {
+    fn read(&mut self, offset: u32, ctx: &PortContext) -> u8 {
+        RtcController::read(self, offset, ctx.cycles, ctx.cpu_speed)
+    }
+
+    fn write(&mut self, offset: u32, value: u8, ctx: &PortContext) {
+        RtcController::write(self, offset, value, ctx.cycles, ctx.cpu_speed)
+    }
+
+    fn tick(&mut self, cycles: u32, _ctx: &PortContext) -> bool {
+        RtcController::tick(self, cycles)
+    }
+
+    fn reset(&mut self) {
+        RtcController::reset(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -656,6 +771,56 @@ mod tests {
         assert_eq!(rtc.interrupt & 1, 1); // Second interrupt
     }
 
+    #[test]
+    fn test_tick_seconds() {
+        let mut rtc = RtcController::new();
+        rtc.write(0x20, 0x87, 0, CPU_SPEED_48MHZ); // enable, sec + min interrupts
+        rtc.counter.sec = 58;
+
+        // Zero seconds holds the time and raises nothing
+        rtc.mode = RtcMode::Tick;
+        assert_eq!(rtc.process_event_seconds(0), (LATCH_TICK_OFFSET, false));
+        assert_eq!(rtc.counter.sec, 58);
+        assert_eq!(rtc.mode, RtcMode::Latch);
+
+        rtc.mode = RtcMode::Tick;
+        let (_, raise) = rtc.process_event_seconds(3);
+        assert!(raise);
+        assert_eq!((rtc.counter.min, rtc.counter.sec), (1, 1));
+        assert_eq!(rtc.interrupt, 0x03); // Second + minute rollover
+    }
+
+    #[test]
+    fn test_drift_and_trim() {
+        // Ticks of true time per RTC second over `seconds` seconds
+        let run = |rtc: &mut RtcController, seconds: u64| -> u64 {
+            (0..seconds)
+                .map(|_| {
+                    rtc.mode = RtcMode::Latch;
+                    rtc.process_event().0 + LATCH_TICK_OFFSET
+                })
+                .sum()
+        };
+        let mut rtc = RtcController::new();
+        assert_eq!(run(&mut rtc, 10), 10 * TICKS_PER_SECOND);
+
+        // 100 ppm fast: 32768 * 100e-6 = 3.2768 ticks short per second
+        assert!(rtc.set_drift_ppm(100));
+        assert!(!rtc.set_drift_ppm(MAX_DRIFT_PPM + 1));
+        assert_eq!(run(&mut rtc, 1000), 1000 * TICKS_PER_SECOND - 3276);
+
+        // Trimming 105 ticks per 2^20 (~100.1 ppm) nearly cancels it
+        rtc.set_trim(105);
+        let ticks = run(&mut rtc, 1000) as i64;
+        assert!((ticks - 1000 * TICKS_PER_SECOND as i64).abs() <= 4, "{}", ticks);
+
+        // Not visible to the CPU
+        rtc.write(0x38, 0, 0, CPU_SPEED_48MHZ);
+        assert_eq!(rtc.read(0x38, 0, CPU_SPEED_48MHZ), 0);
+        rtc.reset();
+        assert_eq!((rtc.trim(), rtc.drift_ppm()), (105, 100));
+    }
+
     #[test]
     fn test_combined_latched_value() {
         let mut rtc = RtcController::new();
@@ -8,6 +8,8 @@
 //! - 0x10-0x4F: block[0-15] - 64 bytes of input data (16 x 32-bit words)
 //! - 0x60-0x7F: state[0-7] - 32 bytes of hash output (8 x 32-bit words)
 
+use super::device::{Peripheral, PortContext};
+
 /// SHA-256 round constants
 const K: [u32; 64] = [
     0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
@@ -22,6 +24,7 @@ const K: [u32; 64] = [
 
 /// SHA256 accelerator controller
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sha256Controller {
     /// Input block (64 bytes / 16 words)
     block: [u32; 16],
@@ -186,6 +189,20 @@ impl Default for Sha256Controller {
     }
 }
 
+impl Peripheral for Sha256Controller {
+    fn read(&mut self, offset: u32, _ctx: &PortContext) -> u8 {
+        Sha256Controller::read(self, offset)
+    }
+
+    fn write(&mut self, offset: u32, value: u8, _ctx: &PortContext) {
+        Sha256Controller::write(self, offset, value)
+    }
+
+    fn reset(&mut self) {
+        Sha256Controller::reset(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
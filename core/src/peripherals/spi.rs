@@ -8,6 +8,7 @@
 //! The SPI bus connects to the ST7789V LCD panel via 9-bit frames.
 //! When a transfer completes, TX data is forwarded to the panel stub.
 
+use super::device::{Peripheral, PortContext};
 use super::panel::PanelStub;
 
 /// SPI FIFO depth (matches CEmu)
@@ -20,6 +21,7 @@ const SPI_WIDTH: u8 = 32;
 
 /// SPI Controller
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SpiController {
     /// Control register 0 (CR0)
     cr0: u32,
@@ -263,7 +265,38 @@ impl SpiController {
         let shift = (addr & 3) << 3;
         let reg_idx = addr >> 2;
 
-        let value: u32 = match reg_idx {
+        if reg_idx == 3 && Self::trace_enabled() {
+            eprintln!(
+                "[spi] status cycle={} speed={} tfve={} rfve={} active={} next={:?} cr0=0x{:04X} cr1=0x{:06X} cr2=0x{:03X}",
+                current_cycles,
+                cpu_speed & 0x03,
+                self.tfve,
+                self.rfve,
+                (self.transfer_bits != 0) as u8,
+                self.next_event_cycle,
+                self.cr0,
+                self.cr1,
+                self.cr2
+            );
+        }
+        // DATA (0x18-0x1B) - reading drains RX FIFO
+        if reg_idx == 6 && shift == 0 && self.rfve > 0 {
+            self.rfve = self.rfve.saturating_sub(1);
+            self.rfvi = self.rfvi.wrapping_add(1);
+        }
+
+        (self.register(reg_idx) >> shift) as u8
+    }
+
+    /// Read a register byte as the CPU last could have seen it, without
+    /// advancing transfers or draining the RX FIFO (debugger views)
+    pub fn peek(&self, addr: u32) -> u8 {
+        (self.register(addr >> 2) >> ((addr & 3) << 3)) as u8
+    }
+
+    /// Current value of a 32-bit register (index = offset / 4)
+    fn register(&self, reg_idx: u32) -> u32 {
+        match reg_idx {
             // CR0 (0x00-0x03)
             0 => self.cr0,
             // CR1 (0x04-0x07)
@@ -281,39 +314,18 @@ impl SpiController {
                 let tx_not_full = if self.tfve < SPI_TXFIFO_DEPTH { 1 } else { 0 };
                 let rx_full = if self.rfve >= SPI_RXFIFO_DEPTH { 1 } else { 0 };
                 let transfer_active = if self.transfer_bits != 0 { 1 } else { 0 };
-                let status = ((self.tfve as u32) << 12)
+                ((self.tfve as u32) << 12)
                     | ((self.rfve as u32) << 4)
                     | (transfer_active << 2)
                     | (tx_not_full << 1)
-                    | rx_full;
-                if Self::trace_enabled() {
-                    eprintln!(
-                        "[spi] status cycle={} speed={} tfve={} rfve={} active={} next={:?} cr0=0x{:04X} cr1=0x{:06X} cr2=0x{:03X}",
-                        current_cycles,
-                        cpu_speed & 0x03,
-                        self.tfve,
-                        self.rfve,
-                        transfer_active,
-                        self.next_event_cycle,
-                        self.cr0,
-                        self.cr1,
-                        self.cr2
-                    );
-                }
-                status
+                    | rx_full
             }
             // INTCTRL (0x10-0x13)
             4 => self.int_ctrl,
             // INTSTATUS (0x14-0x17)
             5 => self.int_status,
-            // DATA (0x18-0x1B) - reading drains RX FIFO
-            6 => {
-                if shift == 0 && self.rfve > 0 {
-                    self.rfve = self.rfve.saturating_sub(1);
-                    self.rfvi = self.rfvi.wrapping_add(1);
-                }
-                0
-            }
+            // DATA (0x18-0x1B) reads as 0
+            6 => 0,
             // FEATURE (0x1C-0x1F)
             7 => {
                 // Features: TXFIFO_DEPTH-1, RXFIFO_DEPTH-1, WIDTH-1
@@ -332,9 +344,7 @@ impl SpiController {
                     | (SPI_WIDTH as u32 - 1)
             }
             _ => 0,
-        };
-
-        (value >> shift) as u8
+        }
     }
 
     /// Write to SPI port
@@ -560,6 +570,22 @@ impl Default for SpiController {
     }
 }
 
+impl Peripheral for SpiController {
+    fn read(&mut self, offset: u32, ctx: &PortContext) -> u8 {
+        SpiController::read(self, offset, ctx.cycles, ctx.cpu_speed)
+    }
+
+    fn write(&mut self, offset: u32, value: u8, ctx: &PortContext) {
+        // The "needs scheduling" hint is dropped; the pending transfer is
+        // still recorded in next_event_cycle for the caller to pick up
+        SpiController::write(self, offset, value, ctx.cycles, ctx.cpu_speed);
+    }
+
+    fn reset(&mut self) {
+        SpiController::reset(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -623,4 +649,20 @@ mod tests {
         let status0_done = spi.read(0x0C, 24, CPU_SPEED_24MHZ);
         assert_eq!(status0_done & 0x04, 0x00);
     }
+
+    #[test]
+    fn test_peek_has_no_side_effects() {
+        let mut spi = SpiController::new();
+        spi.write(0x18, 0x00, 0, CPU_SPEED_24MHZ);
+        spi.rfve = 2;
+
+        assert_eq!(spi.peek(0x0D), spi.read(0x0D, 0, CPU_SPEED_24MHZ));
+        assert_eq!(spi.peek(0x0C), 0x22);
+
+        // Peeking DATA leaves the RX FIFO alone; reading drains it
+        assert_eq!(spi.peek(0x18), 0);
+        assert_eq!(spi.rfve, 2);
+        spi.read(0x18, 0, CPU_SPEED_24MHZ);
+        assert_eq!(spi.rfve, 1);
+    }
 }
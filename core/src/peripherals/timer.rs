@@ -25,8 +25,11 @@
 //!   [3]: Timer1 match0, [4]: Timer1 match1, [5]: Timer1 overflow/zero
 //!   [6]: Timer2 match0, [7]: Timer2 match1, [8]: Timer2 overflow/zero
 
+use super::device::{Peripheral, PortContext};
+
 /// Per-timer data registers (16 bytes each)
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct TimerRegs {
     counter: u32,
     reset: u32,
@@ -51,6 +54,7 @@ impl TimerRegs {
 
 /// General Purpose Timer subsystem (all 3 timers)
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GeneralTimers {
     /// Per-timer registers
     timer: [TimerRegs; 3],
@@ -276,7 +280,11 @@ impl GeneralTimers {
 
                     // Check if overflow/reset enable is set (control bit i*3+2)
                     if self.control & (1 << (i * 3 + 2)) != 0 {
-                        self.timer[i].counter = self.timer[i].reset.wrapping_sub(remaining);
+                        // A batched tick (e.g. after a halt fast-forward) can
+                        // span several periods; land inside the last one
+                        let reset = self.timer[i].reset;
+                        let remaining = if reset != 0 { (remaining - 1) % reset + 1 } else { remaining };
+                        self.timer[i].counter = reset.wrapping_sub(remaining);
                     } else {
                         self.timer[i].counter = 0xFFFFFFFF_u32.wrapping_sub(remaining - 1);
                     }
@@ -312,6 +320,44 @@ impl GeneralTimers {
         0
     }
 
+    /// CPU cycles until `tick` would next see a match, overflow or
+    /// underflow (u32::MAX with no timer running). Ticking fewer cycles
+    /// than this only moves the counters.
+    pub fn cycles_until_event(&self, cpu_speed: u8) -> u32 {
+        let mut next = u64::from(u32::MAX);
+        for i in 0..3 {
+            if !self.is_enabled(i) {
+                continue;
+            }
+            let counter = self.timer[i].counter;
+            let mut ticks = if self.counts_up(i) {
+                (1u64 << 32) - counter as u64
+            } else {
+                counter as u64 + 1
+            };
+            for &match_val in &self.timer[i].match_val {
+                if self.counts_up(i) && match_val > counter {
+                    ticks = ticks.min((match_val - counter) as u64);
+                } else if !self.counts_up(i) && match_val < counter {
+                    ticks = ticks.min((counter - match_val) as u64);
+                }
+            }
+            let cycles_per_tick = if self.uses_32k_clock(i) {
+                let cpu_rate: u64 = match cpu_speed {
+                    0 => 6_000_000,
+                    1 => 12_000_000,
+                    2 => 24_000_000,
+                    _ => 48_000_000,
+                };
+                cpu_rate / 32_768
+            } else {
+                1
+            };
+            next = next.min((ticks * cycles_per_tick).saturating_sub(self.accum_cycles[i] as u64));
+        }
+        next as u32
+    }
+
     /// Check match conditions when counting up, returning status bits (not setting self.status)
     fn check_matches_up_bits(&self, i: usize, old: u32, new: u32, overflow: bool) -> u32 {
         let mut status = 0u32;
@@ -440,6 +486,24 @@ impl Default for GeneralTimers {
     }
 }
 
+impl Peripheral for GeneralTimers {
+    fn read(&mut self, offset: u32, _ctx: &PortContext) -> u8 {
+        GeneralTimers::read(self, offset)
+    }
+
+    fn write(&mut self, offset: u32, value: u8, _ctx: &PortContext) {
+        GeneralTimers::write(self, offset, value)
+    }
+
+    fn tick(&mut self, cycles: u32, ctx: &PortContext) -> bool {
+        GeneralTimers::tick(self, cycles, ctx.cpu_speed, ctx.delay_remaining) != 0
+    }
+
+    fn reset(&mut self) {
+        GeneralTimers::reset(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -545,6 +609,30 @@ mod tests {
         assert_eq!(gpt.timer[0].counter, 50);
     }
 
+    #[test]
+    fn test_cycles_until_event() {
+        let mut gpt = GeneralTimers::new();
+        assert_eq!(gpt.cycles_until_event(3), u32::MAX);
+
+        // Counting up towards a match at 100
+        gpt.control = 0x01 | (1 << 9);
+        gpt.timer[0].counter = 40;
+        gpt.timer[0].match_val = [100, 0];
+        let until = gpt.cycles_until_event(3);
+        assert_eq!(until, 60);
+        gpt.tick(until - 1, 3, 0);
+        assert!(!gpt.needs_delay_event, "no event before the deadline");
+        gpt.tick(1, 3, 0);
+        assert!(gpt.needs_delay_event, "match on the deadline");
+
+        // Counting down reaches zero and underflows after counter + 1 ticks
+        let mut gpt = GeneralTimers::new();
+        gpt.control = 0x01;
+        gpt.timer[0].counter = 10;
+        gpt.timer[0].match_val = [20, 20];
+        assert_eq!(gpt.cycles_until_event(3), 11);
+    }
+
     /// Helper: process all delay tiers, collecting combined status and intrpt
     fn drain_delay(gpt: &mut GeneralTimers) -> (u32, u8) {
         let mut combined_status = 0u32;
@@ -594,6 +682,25 @@ mod tests {
         assert_eq!(gpt.timer[0].counter, 995);
     }
 
+    #[test]
+    fn test_batched_underflow_reloads_into_last_period() {
+        // A halt fast-forward hands the timer one tick spanning several
+        // periods: it must land inside the last one, not wrap from reset
+        let mut gpt = GeneralTimers::new();
+        gpt.control = 0x01 | (1 << 2);
+        gpt.timer[0].counter = 5;
+        gpt.timer[0].reset = 1000;
+
+        // 5 to reach 0, two full periods, then 7 into the third
+        gpt.tick(5 + 2 * 1000 + 7, 3, 0);
+        assert_eq!(gpt.timer[0].counter, 993);
+
+        // Exactly to the end of a period reloads to 0
+        gpt.timer[0].counter = 5;
+        gpt.tick(5 + 1000, 3, 0);
+        assert_eq!(gpt.timer[0].counter, 0);
+    }
+
     #[test]
     fn test_disabled_timer_no_tick() {
         let mut gpt = GeneralTimers::new();
@@ -7,13 +7,17 @@
 //!   0x00-0x03: Current counter (32-bit, read-only)
 //!   0x04-0x07: Load value (32-bit, read/write)
 //!   0x08:      Restart (write 0xB9 to reload)
-//!   0x0C:      Control register
+//!   0x0C:      Control register (bit 0 enable, bit 1 reset on expiry,
+//!              bit 4 count the 32KHz crystal instead of the CPU clock)
 //!   0x10-0x13: Status (read, write-to-clear)
 //!   0x18:      Pulse load (8-bit)
 //!   0x1C-0x1F: Revision (0x00010602, read-only)
 
+use super::device::{Peripheral, PortContext};
+
 /// Watchdog Controller
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WatchdogController {
     /// Current countdown counter
     count: u32,
@@ -25,6 +29,13 @@ pub struct WatchdogController {
     status: u8,
     /// Pulse load value
     pulse_load: u8,
+    /// Restarts since the last `take_restarts` (telemetry, not state)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    restarts: u32,
+    /// CPU cycles not yet converted to 32KHz ticks
+    accum_cycles: u32,
+    /// Expired with reset enabled; taken by the emulator (`take_reset_request`)
+    reset_request: bool,
 }
 
 impl WatchdogController {
@@ -34,6 +45,10 @@ impl WatchdogController {
     /// Default load value on reset (from CEmu)
     const DEFAULT_LOAD: u32 = 0x03EF1480;
 
+    const CTRL_ENABLE: u8 = 1 << 0;
+    const CTRL_RESET: u8 = 1 << 1;
+    const CTRL_CLOCK_32K: u8 = 1 << 4;
+
     /// Create a new Watchdog controller
     pub fn new() -> Self {
         Self {
@@ -42,6 +57,9 @@ impl WatchdogController {
             control: 0x00,
             status: 0x00,
             pulse_load: 0xFF,
+            restarts: 0,
+            accum_cycles: 0,
+            reset_request: false,
         }
     }
 
@@ -52,6 +70,9 @@ impl WatchdogController {
         self.control = 0x00;
         self.status = 0x00;
         self.pulse_load = 0xFF;
+        self.restarts = 0;
+        self.accum_cycles = 0;
+        self.reset_request = false;
     }
 
     /// Read a register byte
@@ -103,6 +124,7 @@ impl WatchdogController {
             0x08 => {
                 if value == 0xB9 {
                     self.count = self.load;
+                    self.restarts = self.restarts.wrapping_add(1);
                 }
             }
 
@@ -125,10 +147,52 @@ impl WatchdogController {
         }
     }
 
-    /// Tick the watchdog (called periodically)
-    /// TODO: Implement proper countdown and state machine (Phase 4+)
-    pub fn tick(&mut self, _cycles: u32) -> bool {
-        false
+    /// Restarts since the last call (see `crate::watchdog_monitor`)
+    pub fn take_restarts(&mut self) -> u32 {
+        std::mem::take(&mut self.restarts)
+    }
+
+    /// Whether the watchdog expired with reset enabled since the last call
+    pub fn take_reset_request(&mut self) -> bool {
+        std::mem::take(&mut self.reset_request)
+    }
+
+    /// Count down by `cycles` CPU cycles, converted to 32KHz ticks when
+    /// control bit 4 selects the crystal (same conversion as the general
+    /// timers). On expiry the status bit is set, the counter reloads and, if
+    /// control bit 1 is set, a reset is requested.
+    ///
+    /// Returns true if the counter expired.
+    pub fn tick(&mut self, cycles: u32, cpu_speed: u8) -> bool {
+        if self.control & Self::CTRL_ENABLE == 0 {
+            return false;
+        }
+        let ticks = if self.control & Self::CTRL_CLOCK_32K != 0 {
+            let cpu_rate: u32 = match cpu_speed {
+                0 => 6_000_000,
+                1 => 12_000_000,
+                2 => 24_000_000,
+                _ => 48_000_000,
+            };
+            let cycles_per_tick = cpu_rate / 32_768;
+            self.accum_cycles += cycles;
+            let t = self.accum_cycles / cycles_per_tick;
+            self.accum_cycles %= cycles_per_tick;
+            t
+        } else {
+            cycles
+        };
+        if ticks < self.count {
+            self.count -= ticks;
+            return false;
+        }
+        self.count = self.load;
+        self.status = 1;
+        if self.control & Self::CTRL_RESET != 0 {
+            crate::emu::log_evt!("WATCHDOG: expired, requesting reset");
+            self.reset_request = true;
+        }
+        true
     }
 }
 
@@ -138,6 +202,24 @@ impl Default for WatchdogController {
     }
 }
 
+impl Peripheral for WatchdogController {
+    fn read(&mut self, offset: u32, _ctx: &PortContext) -> u8 {
+        WatchdogController::read(self, offset)
+    }
+
+    fn write(&mut self, offset: u32, value: u8, _ctx: &PortContext) {
+        WatchdogController::write(self, offset, value)
+    }
+
+    fn tick(&mut self, cycles: u32, ctx: &PortContext) -> bool {
+        WatchdogController::tick(self, cycles, ctx.cpu_speed)
+    }
+
+    fn reset(&mut self) {
+        WatchdogController::reset(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -204,6 +286,8 @@ mod tests {
         // Magic value 0xB9 should reload from load
         wdt.write(0x08, 0xB9);
         assert_eq!(wdt.count, 0x00001000);
+        assert_eq!(wdt.take_restarts(), 1);
+        assert_eq!(wdt.take_restarts(), 0);
     }
 
     #[test]
@@ -240,8 +324,46 @@ mod tests {
     }
 
     #[test]
-    fn test_tick_no_interrupt() {
+    fn test_tick_disabled() {
+        let mut wdt = WatchdogController::new();
+        assert!(!wdt.tick(1000, 3));
+        assert_eq!(wdt.count, WatchdogController::DEFAULT_LOAD);
+    }
+
+    #[test]
+    fn test_expiry_requests_reset() {
+        let mut wdt = WatchdogController::new();
+        wdt.write(0x04, 100);
+        wdt.write(0x05, 0);
+        wdt.write(0x06, 0);
+        wdt.write(0x07, 0);
+        wdt.write(0x08, 0xB9);
+
+        // Enabled without reset: status only
+        wdt.write(0x0C, 0x01);
+        assert!(!wdt.tick(99, 3));
+        assert_eq!(wdt.count, 1);
+        assert!(wdt.tick(1, 3));
+        assert_eq!(wdt.count, 100);
+        assert_eq!(wdt.read(0x10), 1);
+        assert!(!wdt.take_reset_request());
+
+        wdt.write(0x0C, 0x03);
+        assert!(wdt.tick(100, 3));
+        assert!(wdt.take_reset_request());
+        assert!(!wdt.take_reset_request());
+    }
+
+    #[test]
+    fn test_32k_clock() {
         let mut wdt = WatchdogController::new();
-        assert!(!wdt.tick(1000));
+        wdt.load = 2;
+        wdt.count = 2;
+        wdt.write(0x0C, 0x11);
+        // 6MHz: 183 CPU cycles per crystal tick
+        assert!(!wdt.tick(183, 0));
+        assert_eq!(wdt.count, 1);
+        assert!(!wdt.tick(182, 0));
+        assert!(wdt.tick(1, 0));
     }
 }
@@ -0,0 +1,85 @@
+//! Framebuffer output formats
+//!
+//! The framebuffer is ARGB8888, which is what the scaler, the HUD overlay
+//! and screenshots work on. Mobile GPUs and small LCDs want RGB565, and GL
+//! textures want RGBA bytes, so every frontend used to convert each frame
+//! itself. With another format selected, `render_frame` converts the
+//! finished frame (after the color profile) once into a second buffer that
+//! frontends can upload as-is.
+
+/// Pixel layout of the formatted framebuffer (stable C ABI values, see emu.h)
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelFormat {
+    /// 32-bit 0xAARRGGBB words (the framebuffer itself)
+    #[default]
+    Argb8888 = 0,
+    /// 16-bit 0bRRRRRGGGGGGBBBBB words, little-endian
+    Rgb565 = 1,
+    /// Bytes R, G, B, A with color premultiplied by alpha
+    Rgba8888Premultiplied = 2,
+}
+
+impl PixelFormat {
+    pub fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(PixelFormat::Argb8888),
+            1 => Some(PixelFormat::Rgb565),
+            2 => Some(PixelFormat::Rgba8888Premultiplied),
+            _ => None,
+        }
+    }
+
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgb565 => 2,
+            PixelFormat::Argb8888 | PixelFormat::Rgba8888Premultiplied => 4,
+        }
+    }
+}
+
+/// Convert ARGB8888 `pixels` into `out` (resized to fit) as `format`
+pub fn convert(format: PixelFormat, pixels: &[u32], out: &mut Vec<u8>) {
+    out.resize(pixels.len() * format.bytes_per_pixel(), 0);
+    match format {
+        PixelFormat::Argb8888 => {
+            for (bytes, &pixel) in out.chunks_exact_mut(4).zip(pixels) {
+                bytes.copy_from_slice(&pixel.to_ne_bytes());
+            }
+        }
+        PixelFormat::Rgb565 => {
+            for (bytes, &pixel) in out.chunks_exact_mut(2).zip(pixels) {
+                let rgb565 = ((pixel >> 8) & 0xF800) | ((pixel >> 5) & 0x07E0) | ((pixel >> 3) & 0x001F);
+                bytes.copy_from_slice(&(rgb565 as u16).to_le_bytes());
+            }
+        }
+        PixelFormat::Rgba8888Premultiplied => {
+            for (bytes, &pixel) in out.chunks_exact_mut(4).zip(pixels) {
+                let [a, r, g, b] = pixel.to_be_bytes();
+                let premultiply = |c: u8| ((c as u32 * a as u32 + 127) / 255) as u8;
+                bytes.copy_from_slice(&[premultiply(r), premultiply(g), premultiply(b), a]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_formats() {
+        let pixels = [0xFFFF8000, 0x80FFFFFF];
+        let mut out = Vec::new();
+
+        convert(PixelFormat::Rgb565, &pixels, &mut out);
+        assert_eq!(out, [0x00, 0xFC, 0xFF, 0xFF]);
+
+        convert(PixelFormat::Rgba8888Premultiplied, &pixels, &mut out);
+        assert_eq!(out, [0xFF, 0x80, 0x00, 0xFF, 0x80, 0x80, 0x80, 0x80]);
+
+        convert(PixelFormat::Argb8888, &pixels[..1], &mut out);
+        assert_eq!(out, 0xFFFF8000u32.to_ne_bytes());
+        assert_eq!(PixelFormat::from_raw(3), None);
+    }
+}
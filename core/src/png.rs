@@ -0,0 +1,273 @@
+//! PNG encoding for screenshots
+//!
+//! A small self-contained encoder so frontends get a ready-to-save image
+//! without an image pipeline of their own. Output is 8-bit RGB, no
+//! interlace, no row filters; the image data is compressed as a single
+//! fixed-Huffman deflate block with greedy LZ77 matching, which handles
+//! the large flat areas of calculator screens well.
+
+use crate::crc::crc32;
+
+/// Longest and farthest back a deflate match may reach
+const MAX_MATCH: usize = 258;
+const MIN_MATCH: usize = 3;
+const WINDOW: usize = 32768;
+const HASH_BITS: u32 = 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+    131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Encode `pixels` (ARGB8888, row-major, alpha ignored) as a PNG file
+pub fn encode(width: usize, height: usize, pixels: &[u32]) -> Vec<u8> {
+    assert_eq!(pixels.len(), width * height, "pixel count doesn't match size");
+
+    // Each row is prefixed with its filter type (0, none)
+    let mut raw = Vec::with_capacity(height * (1 + width * 3));
+    for row in pixels.chunks(width.max(1)).take(height) {
+        raw.push(0);
+        for &pixel in row {
+            raw.extend_from_slice(&[(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]);
+        }
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8-bit depth, truecolor, deflate, adaptive filtering, no interlace
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    write_chunk(&mut png, b"IHDR", &ihdr);
+    write_chunk(&mut png, b"IDAT", &zlib(&raw));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+/// zlib stream: header, one deflate block, Adler-32 of the input
+fn zlib(data: &[u8]) -> Vec<u8> {
+    // 32K window, no dictionary, fastest level (header checksum multiple of 31)
+    let mut bits = BitWriter { out: vec![0x78, 0x01], acc: 0, count: 0 };
+    // Final block, fixed Huffman codes
+    bits.put(1, 1);
+    bits.put(1, 2);
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let hash = |at: usize| {
+        let key = (data[at] as u32) << 16 | (data[at + 1] as u32) << 8 | data[at + 2] as u32;
+        (key.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+    };
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let mut best = 0;
+        let mut dist = 0;
+        if pos + MIN_MATCH <= data.len() {
+            let slot = hash(pos);
+            let candidate = head[slot];
+            head[slot] = pos;
+            if candidate != usize::MAX && pos - candidate <= WINDOW {
+                let limit = (data.len() - pos).min(MAX_MATCH);
+                best = (0..limit).take_while(|&i| data[candidate + i] == data[pos + i]).count();
+                dist = pos - candidate;
+            }
+        }
+
+        if best >= MIN_MATCH {
+            bits.put_length(best);
+            bits.put_distance(dist);
+            // Index the matched bytes so later matches can refer into them
+            for at in pos + 1..(pos + best).min(data.len().saturating_sub(MIN_MATCH - 1)) {
+                head[hash(at)] = at;
+            }
+            pos += best;
+        } else {
+            bits.put_literal(data[pos] as u16);
+            pos += 1;
+        }
+    }
+    bits.put_literal(256);
+
+    let mut out = bits.finish();
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// Deflate bit stream: values go in LSB first, Huffman codes MSB first
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn put(&mut self, value: u32, bits: u32) {
+        self.acc |= (value as u64) << self.count;
+        self.count += bits;
+        while self.count >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn put_code(&mut self, code: u32, bits: u32) {
+        self.put(code.reverse_bits() >> (32 - bits), bits);
+    }
+
+    /// Literal/length symbol in the fixed code
+    fn put_literal(&mut self, symbol: u16) {
+        let symbol = symbol as u32;
+        match symbol {
+            0..=143 => self.put_code(0x30 + symbol, 8),
+            144..=255 => self.put_code(0x190 + symbol - 144, 9),
+            256..=279 => self.put_code(symbol - 256, 7),
+            _ => self.put_code(0xC0 + symbol - 280, 8),
+        }
+    }
+
+    fn put_length(&mut self, length: usize) {
+        let index = LENGTH_BASE.iter().rposition(|&base| base as usize <= length).unwrap();
+        self.put_literal(257 + index as u16);
+        self.put((length - LENGTH_BASE[index] as usize) as u32, LENGTH_EXTRA[index] as u32);
+    }
+
+    fn put_distance(&mut self, distance: usize) {
+        let index = DIST_BASE.iter().rposition(|&base| base as usize <= distance).unwrap();
+        self.put_code(index as u32, 5);
+        self.put((distance - DIST_BASE[index] as usize) as u32, DIST_EXTRA[index] as u32);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal inflate for the fixed-Huffman blocks `zlib` writes
+    fn inflate(stream: &[u8]) -> Vec<u8> {
+        let mut pos = 0usize;
+        let mut bit = |n: u32| {
+            let mut value = 0;
+            for i in 0..n {
+                value |= ((stream[pos / 8] >> (pos % 8)) as u32 & 1) << i;
+                pos += 1;
+            }
+            value
+        };
+        assert_eq!((bit(1), bit(2)), (1, 1), "single final fixed block");
+
+        let mut out: Vec<u8> = Vec::new();
+        loop {
+            let mut code = 0;
+            let mut len = 0;
+            let symbol = loop {
+                code = code << 1 | bit(1);
+                len += 1;
+                match (len, code) {
+                    (7, 0..=0x17) => break code + 256,
+                    (8, 0x30..=0xBF) => break code - 0x30,
+                    (8, 0xC0..=0xC7) => break code - 0xC0 + 280,
+                    (9, 0x190..=0x1FF) => break code - 0x190 + 144,
+                    (9, _) => panic!("bad code"),
+                    _ => {}
+                }
+            };
+            match symbol {
+                0..=255 => out.push(symbol as u8),
+                256 => return out,
+                _ => {
+                    let i = symbol as usize - 257;
+                    let length = LENGTH_BASE[i] as usize + bit(LENGTH_EXTRA[i] as u32) as usize;
+                    let d = (0..5).fold(0, |acc, _| acc << 1 | bit(1)) as usize;
+                    let distance = DIST_BASE[d] as usize + bit(DIST_EXTRA[d] as u32) as usize;
+                    for _ in 0..length {
+                        out.push(out[out.len() - distance]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_chunks_and_round_trip() {
+        let (width, height) = (320, 240);
+        let pixels: Vec<u32> = (0..width * height)
+            .map(|i| if (i / width) < 120 { 0xFFFFFFFF } else { 0xFF000000 | (i as u32).wrapping_mul(2654435761) >> 8 })
+            .collect();
+        let png = encode(width, height, &pixels);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+
+        let mut chunks = Vec::new();
+        let mut at = 8;
+        while at < png.len() {
+            let len = u32::from_be_bytes(png[at..at + 4].try_into().unwrap()) as usize;
+            let body = &png[at + 4..at + 8 + len];
+            let crc = u32::from_be_bytes(png[at + 8 + len..at + 12 + len].try_into().unwrap());
+            assert_eq!(crc32(body), crc);
+            chunks.push((body[..4].to_vec(), body[4..].to_vec()));
+            at += 12 + len;
+        }
+        let kinds: Vec<&[u8]> = chunks.iter().map(|(kind, _)| &kind[..]).collect();
+        assert_eq!(kinds, [&b"IHDR"[..], b"IDAT", b"IEND"]);
+        assert_eq!(chunks[0].1, [0, 0, 1, 64, 0, 0, 0, 240, 8, 2, 0, 0, 0]);
+
+        let idat = &chunks[1].1;
+        assert_eq!(u16::from_be_bytes([idat[0], idat[1]]) % 31, 0);
+        let raw = inflate(&idat[2..idat.len() - 4]);
+        assert_eq!(adler32(&raw).to_be_bytes(), idat[idat.len() - 4..]);
+        assert_eq!(raw.len(), height * (1 + width * 3));
+        let row = &raw[130 * (1 + width * 3)..];
+        let pixel = pixels[130 * width + 5];
+        assert_eq!(row[0], 0);
+        assert_eq!(row[1 + 15..1 + 18], [(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]);
+        // The flat half compresses to almost nothing
+        assert!(idat.len() < raw.len() * 3 / 4, "{} of {}", idat.len(), raw.len());
+    }
+
+    #[test]
+    fn test_crc_and_adler_vectors() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+}
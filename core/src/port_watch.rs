@@ -0,0 +1,194 @@
+//! Watch filters on peripheral port accesses
+//!
+//! For reverse-engineering how the OS drives a peripheral: a filter covers
+//! a range of memory-mapped port addresses (0xE00000+, e.g. the keypad
+//! data at 0xF50008) or of 16-bit IN/OUT port numbers, and is checked
+//! wherever the bus records an I/O operation (`IoRecord`), so it sees the
+//! old and new register values. Every matching access goes to the
+//! registered callback, and a filter can also stop execution after the
+//! instruction, like a data watchpoint.
+
+use std::os::raw::c_void;
+
+use crate::watchpoint::WatchAccess;
+
+/// Address space a filter covers (stable C ABI values, see emu.h)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortSpace {
+    /// Memory-mapped ports, 0xE00000-0xFFFFFF
+    Mmio = 0,
+    /// IN/OUT port numbers, 0x0000-0xFFFF
+    Io = 1,
+}
+
+impl PortSpace {
+    pub fn from_raw(raw: i32) -> Option<Self> {
+        match raw {
+            0 => Some(PortSpace::Mmio),
+            1 => Some(PortSpace::Io),
+            _ => None,
+        }
+    }
+
+    /// Whether `start..=end` is a valid range in this space
+    pub fn valid_range(self, start: u32, end: u32) -> bool {
+        let (low, high) = match self {
+            PortSpace::Mmio => (0xE00000, 0xFFFFFF),
+            PortSpace::Io => (0, 0xFFFF),
+        };
+        low <= start && start <= end && end <= high
+    }
+}
+
+/// One port access as passed across the C ABI
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EmuPortAccess {
+    /// Total cycles when the access happened
+    pub cycles: u64,
+    /// MMIO address, or IN/OUT port number when `io` is set
+    pub addr: u32,
+    /// Instruction that made the access
+    pub pc: u32,
+    /// Register value before the access (the value read, for reads)
+    pub old_value: u8,
+    /// Value written (the value read, for reads)
+    pub new_value: u8,
+    /// 1 for a write, 0 for a read
+    pub write: u8,
+    /// 1 for an IN/OUT port, 0 for MMIO
+    pub io: u8,
+}
+
+/// Port watch callback: receives the user pointer given at registration.
+/// Called from inside the bus access with the emulator locked, so it must
+/// not call back into the emulator.
+pub type PortWatchCallback = extern "C" fn(user: *mut c_void, access: *const EmuPortAccess);
+
+/// A port range filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortWatch {
+    pub id: u32,
+    pub space: PortSpace,
+    pub start: u32,
+    pub end: u32,
+    pub access: WatchAccess,
+    /// Stop execution after the instruction, not just call back
+    pub stop: bool,
+}
+
+impl PortWatch {
+    fn matches(&self, access: &EmuPortAccess) -> bool {
+        (self.space == PortSpace::Io) == (access.io != 0)
+            && (self.start..=self.end).contains(&access.addr)
+            && self.access.matches(access.write != 0)
+    }
+}
+
+/// Filters, the callback (user pointer stored as usize to stay Send) and
+/// the first stopping hit not yet taken by the emulator
+#[derive(Debug, Clone, Default)]
+pub struct PortWatches {
+    entries: Vec<PortWatch>,
+    next_id: u32,
+    callback: Option<(PortWatchCallback, usize)>,
+    hit: Option<EmuPortAccess>,
+}
+
+impl PortWatches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a filter; returns an id for `remove` (ids start at 1)
+    pub fn add(&mut self, space: PortSpace, start: u32, end: u32, access: WatchAccess, stop: bool) -> u32 {
+        self.next_id += 1;
+        self.entries.push(PortWatch { id: self.next_id, space, start, end, access, stop });
+        self.next_id
+    }
+
+    /// Remove a filter. Returns false for an unknown id.
+    pub fn remove(&mut self, id: u32) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|watch| watch.id != id);
+        self.entries.len() != before
+    }
+
+    /// Remove every filter and any pending hit (the callback stays)
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.hit = None;
+    }
+
+    /// Send matching accesses to `callback`, or stop with None
+    pub fn set_callback(&mut self, callback: Option<PortWatchCallback>, user: *mut c_void) {
+        self.callback = callback.map(|callback| (callback, user as usize));
+    }
+
+    pub fn list(&self) -> &[PortWatch] {
+        &self.entries
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Feed one recorded port access
+    pub fn check(&mut self, access: EmuPortAccess) {
+        let mut matched = false;
+        let mut stop = false;
+        for watch in self.entries.iter().filter(|watch| watch.matches(&access)) {
+            matched = true;
+            stop |= watch.stop;
+        }
+        if !matched {
+            return;
+        }
+        if let Some((callback, user)) = self.callback {
+            callback(user as *mut c_void, &access);
+        }
+        if stop && self.hit.is_none() {
+            self.hit = Some(access);
+        }
+    }
+
+    /// The pending stopping hit, if any
+    #[inline]
+    pub fn take_hit(&mut self) -> Option<EmuPortAccess> {
+        self.hit.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spaces_and_stop_filters() {
+        let mut watches = PortWatches::new();
+        assert!(PortSpace::Mmio.valid_range(0xF50000, 0xF5003F));
+        assert!(!PortSpace::Mmio.valid_range(0xD00000, 0xE00000));
+        assert!(!PortSpace::Io.valid_range(0, 0x10000));
+
+        watches.add(PortSpace::Mmio, 0xF50008, 0xF50008, WatchAccess::Read, true);
+        let io = watches.add(PortSpace::Io, 0xA008, 0xA008, WatchAccess::ReadWrite, false);
+        let keypad = |io: u8, write: u8| EmuPortAccess {
+            addr: if io != 0 { 0xA008 } else { 0xF50008 },
+            io,
+            write,
+            ..Default::default()
+        };
+
+        // Callback-only filters never stop, and writes don't match a read filter
+        watches.check(keypad(1, 0));
+        watches.check(keypad(0, 1));
+        assert_eq!(watches.take_hit(), None);
+        watches.check(keypad(0, 0));
+        assert_eq!(watches.take_hit(), Some(keypad(0, 0)));
+
+        assert!(watches.remove(io));
+        assert_eq!(watches.list().len(), 1);
+    }
+}
@@ -0,0 +1,181 @@
+//! Rough supply current estimate
+//!
+//! Lets developers see roughly what a program costs in battery and lets
+//! frontends drain a virtual battery at a plausible rate. The draw is
+//! modelled as a sum of components, sampled after every run or step:
+//!
+//! - CPU: a per-speed active current, scaled by the share of cycles not
+//!   spent halted, plus an idle floor
+//! - LCD controller and panel while enabled
+//! - Backlight, linear in the PWM brightness
+//! - Flash, by the share of cycles that accessed it
+//!
+//! The constants are estimates for a TI-84 Plus CE, not measurements, and
+//! the state (speed, LCD, backlight) is read at the end of each run, so
+//! the model is only as fine-grained as the host's run calls. While the
+//! calculator is off no cycles run; the standby current is counted over
+//! the host time the frontend reports instead (see `standby`).
+
+use crate::benchmark::emulated_nanos;
+
+/// Active CPU current by speed setting (6, 12, 24, 48 MHz), uA
+const CPU_ACTIVE_UA: [u32; 4] = [4_000, 6_000, 9_000, 15_000];
+/// CPU core and RAM while halted, uA
+const CPU_HALTED_UA: u32 = 1_500;
+/// LCD controller and panel while enabled, uA
+const LCD_UA: u32 = 3_000;
+/// Backlight at full brightness, uA
+const BACKLIGHT_FULL_UA: u32 = 30_000;
+/// Flash reading every cycle, uA
+const FLASH_FULL_UA: u32 = 4_000;
+/// Whole calculator while off, uA
+const STANDBY_UA: u32 = 100;
+
+/// Power estimate snapshot (C layout, see emu.h). Currents are in uA.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EmuPowerStats {
+    /// Total draw over the last sampled run
+    pub current_ua: u32,
+    /// Components of `current_ua` (zero while off)
+    pub cpu_ua: u32,
+    pub lcd_ua: u32,
+    pub backlight_ua: u32,
+    pub flash_ua: u32,
+    /// Average draw since reset
+    pub average_ua: u32,
+    /// Charge drawn since reset, uAh
+    pub charge_uah: u64,
+    /// Time covered since reset (emulated, plus host time spent off), ns
+    pub elapsed_ns: u64,
+}
+
+/// Device state at a sample point
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerState {
+    /// Total cycles (rescaled with the CPU speed, see `emulated_nanos`)
+    pub cycles: u64,
+    /// CPU speed setting (0-3)
+    pub cpu_speed: u8,
+    /// Running count of cycles spent halted
+    pub halted_cycles: u64,
+    /// Running count of flash accesses
+    pub flash_accesses: u64,
+    pub lcd_on: bool,
+    /// Backlight PWM level (0-255)
+    pub backlight: u8,
+}
+
+/// Integrates the modelled current over emulated time
+#[derive(Debug, Clone, Default)]
+pub struct PowerModel {
+    last: Option<PowerState>,
+    stats: EmuPowerStats,
+    /// Charge since reset in uA*ns
+    charge: u128,
+}
+
+impl PowerModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start over (the cycle counter restarted)
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Account for the emulated time since the previous sample at `state`
+    pub fn sample(&mut self, state: PowerState) {
+        let Some(last) = self.last.replace(state) else { return };
+        let elapsed = emulated_nanos(state.cycles, state.cpu_speed)
+            .saturating_sub(emulated_nanos(last.cycles, last.cpu_speed));
+        if elapsed == 0 {
+            return;
+        }
+
+        let stats = &mut self.stats;
+        let cycles = state.cycles.saturating_sub(last.cycles).max(1);
+        let share = |count: u64, full: u32| (count.min(cycles) * full as u64 / cycles) as u32;
+        let halted = state.halted_cycles.wrapping_sub(last.halted_cycles);
+        let active = CPU_ACTIVE_UA[state.cpu_speed as usize & 3];
+        stats.cpu_ua = CPU_HALTED_UA + share(cycles - halted.min(cycles), active - CPU_HALTED_UA);
+        stats.lcd_ua = if state.lcd_on { LCD_UA } else { 0 };
+        stats.backlight_ua = state.backlight as u32 * BACKLIGHT_FULL_UA / 255;
+        stats.flash_ua = share(state.flash_accesses.wrapping_sub(last.flash_accesses), FLASH_FULL_UA);
+        stats.current_ua = stats.cpu_ua + stats.lcd_ua + stats.backlight_ua + stats.flash_ua;
+        self.accrue(elapsed);
+    }
+
+    /// Account for `elapsed_ns` of host time spent off, drawing only the
+    /// standby current
+    pub fn standby(&mut self, elapsed_ns: u64) {
+        if elapsed_ns == 0 {
+            return;
+        }
+        let stats = &mut self.stats;
+        stats.cpu_ua = 0;
+        stats.lcd_ua = 0;
+        stats.backlight_ua = 0;
+        stats.flash_ua = 0;
+        stats.current_ua = STANDBY_UA;
+        self.accrue(elapsed_ns);
+    }
+
+    /// Integrate the current draw over `elapsed` ns
+    fn accrue(&mut self, elapsed: u64) {
+        let stats = &mut self.stats;
+        self.charge += stats.current_ua as u128 * elapsed as u128;
+        stats.elapsed_ns += elapsed;
+        stats.average_ua = (self.charge / stats.elapsed_ns as u128) as u32;
+        stats.charge_uah = (self.charge / 3_600_000_000_000) as u64;
+    }
+
+    pub fn stats(&self) -> EmuPowerStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(cycles: u64, halted_cycles: u64, flash_accesses: u64) -> PowerState {
+        PowerState {
+            cycles,
+            cpu_speed: 3,
+            halted_cycles,
+            flash_accesses,
+            lcd_on: true,
+            backlight: 255,
+        }
+    }
+
+    #[test]
+    fn test_components_and_integration() {
+        let mut model = PowerModel::new();
+        model.sample(state(0, 0, 0));
+        assert_eq!(model.stats(), EmuPowerStats::default());
+
+        // One second at 48 MHz, busy and reading flash every other cycle
+        model.sample(state(48_000_000, 0, 24_000_000));
+        let stats = model.stats();
+        assert_eq!(stats.cpu_ua, 15_000);
+        assert_eq!(stats.flash_ua, 2_000);
+        assert_eq!(stats.current_ua, 15_000 + 3_000 + 30_000 + 2_000);
+        assert_eq!(stats.elapsed_ns, 1_000_000_000);
+
+        // Then one second halted with the backlight off
+        model.sample(PowerState { backlight: 0, ..state(96_000_000, 48_000_000, 24_000_000) });
+        let stats = model.stats();
+        assert_eq!(stats.current_ua, CPU_HALTED_UA + LCD_UA);
+        assert_eq!(stats.average_ua, (50_000 + 4_500) / 2);
+
+        // An hour off adds the standby charge
+        model.standby(3_600_000_000_000);
+        assert_eq!(model.stats().current_ua, STANDBY_UA);
+        assert_eq!(model.stats().cpu_ua, 0);
+        assert_eq!(model.stats().charge_uah, 100 + (50_000 + 4_500) / 3600);
+        assert_eq!(model.stats().elapsed_ns, 3_602_000_000_000);
+    }
+}
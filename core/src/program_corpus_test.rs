@@ -0,0 +1,124 @@
+//! Macro-replay regression corpus for real programs
+//!
+//! Each `tests/program_corpus/<name>.macro` (see input_macro.rs) boots the
+//! ROM, sends a program with its `send` steps, plays recorded input and
+//! checks frame hashes along the way. Program files sit next to the macros;
+//! macros whose files are missing are skipped.
+//!
+//! A macro's `rom` line picks the firmware. Macros recorded on the built-in
+//! demo ROM (`Emu::load_demo` with the default config) run with every test
+//! build. The rest boot a real ROM (`TI-84 CE.rom`, see
+//! `test_rom::try_load_real_rom`) and are ignored by default, since a debug
+//! boot takes minutes:
+//!
+//! ```text
+//! cargo test --release --lib program_corpus -- --ignored
+//! ```
+//!
+//! Macros recorded with another ROM are skipped. After an intended change
+//! to timing or rendering, re-record the hashes with
+//! `PROGRAM_CORPUS_UPDATE=1`; new macros come from a frontend session via
+//! `emu_macro_record_start` / `emu_macro_checkpoint`.
+
+#[cfg(test)]
+mod tests {
+    use crate::input_macro::{self, InputMacro, MacroError};
+    use crate::test_rom::{self, DemoConfig};
+    use crate::Emu;
+    use std::path::{Path, PathBuf};
+
+    fn corpus_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/program_corpus")
+    }
+
+    fn macros() -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(corpus_dir())
+            .map(|dir| dir.filter_map(|entry| entry.ok().map(|e| e.path())).collect())
+            .unwrap_or_default();
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "macro"));
+        paths.sort();
+        paths
+    }
+
+    fn demo_rom() -> Vec<u8> {
+        test_rom::build_demo(DemoConfig::default())
+    }
+
+    fn rom_hash(rom: &[u8]) -> Option<u64> {
+        let mut emu = Emu::new();
+        emu.load_rom(rom).unwrap();
+        emu.rom_hash()
+    }
+
+    /// Play the macros `wanted` selects on `rom`; returns how many played.
+    /// With `PROGRAM_CORPUS_UPDATE=1` their hashes are re-recorded instead.
+    fn play_corpus(rom: &[u8], wanted: impl Fn(&InputMacro) -> bool) -> usize {
+        let update = std::env::var("PROGRAM_CORPUS_UPDATE").is_ok_and(|v| v == "1");
+        let mut failures = Vec::new();
+        let mut played = 0;
+
+        for path in macros() {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            let text = std::fs::read_to_string(&path).unwrap();
+            let input = InputMacro::parse(&text).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            if !wanted(&input) {
+                continue;
+            }
+
+            let mut emu = Emu::new();
+            emu.load_rom(rom).unwrap();
+            if input.rom_hash.is_some_and(|hash| Some(hash) != emu.rom_hash()) && !update {
+                println!("{}: recorded with another ROM, skipped", name);
+                continue;
+            }
+
+            let dir = path.parent().unwrap().to_path_buf();
+            let checks = match input_macro::play(&mut emu, &input, &mut |file| std::fs::read(dir.join(file)).ok()) {
+                Ok(checks) => checks,
+                Err(MacroError::MissingFile(file)) => {
+                    println!("{}: {} not in the corpus directory, skipped", name, file);
+                    continue;
+                }
+                Err(e) => panic!("{}: {}", name, e),
+            };
+            played += 1;
+
+            if update {
+                let mut updated = input.with_frames(&checks);
+                updated.rom_hash = emu.rom_hash();
+                std::fs::write(&path, updated.to_string()).unwrap();
+                println!("{}: wrote {} frame hashes", name, checks.len());
+            } else if let Some(check) = checks.iter().find(|check| !check.matches()) {
+                failures.push(format!(
+                    "{}: frame at step {} ({:.1}M cycles) differs",
+                    name,
+                    check.step + 1,
+                    check.cycles as f64 / 1_000_000.0
+                ));
+            }
+        }
+
+        println!("{} macros played from {}", played, corpus_dir().display());
+        assert!(
+            failures.is_empty(),
+            "re-record with PROGRAM_CORPUS_UPDATE=1 if intended:\n{}",
+            failures.join("\n")
+        );
+        played
+    }
+
+    #[test]
+    fn test_program_corpus_demo_rom() {
+        let demo = rom_hash(&demo_rom());
+        let played = play_corpus(&demo_rom(), |input| input.rom_hash == demo);
+        assert!(played > 0, "no demo ROM macros in the corpus");
+    }
+
+    #[test]
+    #[ignore = "requires ROM file"]
+    fn test_program_corpus() {
+        let rom = test_rom::try_load_real_rom().expect("ROM file not found");
+        let demo = rom_hash(&demo_rom());
+        play_corpus(&rom, |input| input.rom_hash != demo);
+    }
+}
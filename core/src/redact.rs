@@ -0,0 +1,123 @@
+//! Redacted save states for bug reports
+//!
+//! A save state carries the whole flash, boot code and OS included, which
+//! users can't share. A redacted bundle keeps everything else (registers,
+//! scheduler, peripherals, RAM) and replaces the flash with a hash of each
+//! 64KB sector. Whoever loads it supplies the flash from their own copy of
+//! the ROM; sectors whose hash doesn't match (archive variables written
+//! during the session, a different OS build) are reported so the
+//! reproduction can be judged.
+//!
+//! Layout, little-endian:
+//!
+//! ```text
+//! magic "CE8R" | version u32 | crc32 u32 | sector count u32
+//! sector hashes (FNV-1a, u64 each)
+//! save state without its trailing flash section
+//! ```
+//!
+//! The CRC covers everything after the 16-byte header.
+
+use crate::crc::crc32;
+use crate::hash::{self, fnv1a};
+use crate::error::LoadError;
+
+pub const MAGIC: [u8; 4] = *b"CE8R";
+pub const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 16;
+const SECTOR_SIZE: usize = 0x10000;
+
+/// FNV-1a of every 64KB sector of `flash`
+pub fn sector_hashes(flash: &[u8]) -> Vec<u64> {
+    flash.chunks(SECTOR_SIZE).map(|sector| fnv1a(hash::bytes(sector))).collect()
+}
+
+/// Bundle for `state`, a save state whose last section is `flash`
+pub fn redact(state: &[u8], flash: &[u8]) -> Vec<u8> {
+    let hashes = sector_hashes(flash);
+    let mut bundle = Vec::with_capacity(HEADER_SIZE + hashes.len() * 8 + state.len() - flash.len());
+    bundle.extend_from_slice(&MAGIC);
+    bundle.extend_from_slice(&VERSION.to_le_bytes());
+    bundle.extend_from_slice(&[0; 4]); // CRC, once the rest is in
+    bundle.extend_from_slice(&(hashes.len() as u32).to_le_bytes());
+    for hash in &hashes {
+        bundle.extend_from_slice(&hash.to_le_bytes());
+    }
+    bundle.extend_from_slice(&state[..state.len() - flash.len()]);
+    let crc = crc32(&bundle[HEADER_SIZE..]);
+    bundle[8..12].copy_from_slice(&crc.to_le_bytes());
+    bundle
+}
+
+/// Rebuild the save state from `bundle` with `flash` as its flash section.
+/// Returns it with the indices of the sectors that differ from the
+/// reporter's.
+pub fn restore(bundle: &[u8], flash: &[u8]) -> Result<(Vec<u8>, Vec<usize>), LoadError> {
+    if bundle.len() < HEADER_SIZE {
+        return Err(LoadError::StateTooShort { len: bundle.len(), min: HEADER_SIZE });
+    }
+    let word = |at: usize| u32::from_le_bytes(bundle[at..at + 4].try_into().unwrap());
+    let magic: [u8; 4] = bundle[..4].try_into().unwrap();
+    if magic != MAGIC {
+        return Err(LoadError::BadMagic { expected: MAGIC, found: magic });
+    }
+    if word(4) != VERSION {
+        return Err(LoadError::VersionMismatch { expected: VERSION, found: word(4) });
+    }
+    let actual = crc32(&bundle[HEADER_SIZE..]);
+    if actual != word(8) {
+        return Err(LoadError::ChecksumMismatch { expected: word(8), found: actual });
+    }
+
+    let count = word(12) as usize;
+    let hashes_end = HEADER_SIZE + count * 8;
+    if bundle.len() < hashes_end {
+        return Err(LoadError::StateTruncated { expected: hashes_end, found: bundle.len() });
+    }
+    let ours = sector_hashes(flash);
+    let differing = (0..count.max(ours.len()))
+        .filter(|&i| {
+            let theirs = bundle
+                .get(HEADER_SIZE + i * 8..HEADER_SIZE + i * 8 + 8)
+                .filter(|_| i < count)
+                .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+            theirs != ours.get(i).copied()
+        })
+        .collect();
+
+    let mut state = bundle[hashes_end..].to_vec();
+    state.extend_from_slice(flash);
+    Ok((state, differing))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_and_restore() {
+        let mut flash = vec![0xFF; 4 * SECTOR_SIZE];
+        flash[..4].copy_from_slice(b"BOOT");
+        let mut state = b"header+ram".to_vec();
+        state.extend_from_slice(&flash);
+
+        let bundle = redact(&state, &flash);
+        assert_eq!(bundle.len(), HEADER_SIZE + 4 * 8 + 10);
+        assert!(!bundle.windows(4).any(|w| w == b"BOOT"), "no flash bytes");
+        assert_eq!(restore(&bundle, &flash), Ok((state.clone(), vec![])));
+
+        // Another archive: loads, with the sector named
+        let mut other = flash.clone();
+        other[2 * SECTOR_SIZE + 5] = 0;
+        let (restored, differing) = restore(&bundle, &other).unwrap();
+        assert_eq!(differing, vec![2]);
+        assert_eq!(&restored[10..], &other[..]);
+        assert_eq!(restore(&bundle, &flash[..SECTOR_SIZE]).unwrap().1, vec![1, 2, 3]);
+
+        let mut bad = bundle.clone();
+        *bad.last_mut().unwrap() ^= 1;
+        assert!(matches!(restore(&bad, &flash), Err(LoadError::ChecksumMismatch { .. })));
+        assert!(matches!(restore(&state, &flash), Err(LoadError::BadMagic { .. })));
+        assert!(matches!(restore(&bundle[..8], &flash), Err(LoadError::StateTooShort { .. })));
+    }
+}
@@ -0,0 +1,195 @@
+//! A/B comparison of two ROMs under the same input
+//!
+//! Boots each ROM on a fresh instance, plays the same input macro on both
+//! and records the frame hash and a trace digest (`lockstep::StateDigest`)
+//! at every `frame` step. The report names the first checkpoint where the
+//! screens differ and the first where the digests do, which shows whether
+//! a core change behaves the same across OS versions: run the comparison
+//! before and after the change and the divergence points should not move.
+//!
+//! The `frame` hashes written in the macro are ignored (a macro records one
+//! ROM's screens), as is its `rom` line; only the checkpoint positions
+//! matter.
+//!
+//! ```ignore
+//! let input = InputMacro::parse(&std::fs::read_to_string("menu.macro")?)?;
+//! let report = rom_compare::compare(&rom_5_3, &rom_5_8, &input, &mut |_| None)?;
+//! println!("{}", report);
+//! ```
+
+use std::fmt;
+
+use crate::error::LoadError;
+use crate::input_macro::{self, InputMacro, MacroError};
+use crate::lockstep::StateDigest;
+use crate::Emu;
+
+/// State of one instance at a `frame` step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Index into `InputMacro::steps`
+    pub step: usize,
+    /// Emulated cycles since power-on playback started
+    pub cycles: u64,
+    pub frame: u64,
+    pub digest: StateDigest,
+}
+
+/// Checkpoints of one ROM's playback
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomRun {
+    pub rom_hash: u64,
+    pub checkpoints: Vec<Checkpoint>,
+}
+
+/// Which ROM of the pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    A,
+    B,
+}
+
+/// Why a comparison could not run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompareError {
+    Load(Side, LoadError),
+    Macro(Side, MacroError),
+}
+
+impl fmt::Display for CompareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompareError::Load(side, error) => write!(f, "ROM {:?}: {}", side, error),
+            CompareError::Macro(side, error) => write!(f, "ROM {:?}: {}", side, error),
+        }
+    }
+}
+
+impl std::error::Error for CompareError {}
+
+/// Both runs, checkpoint by checkpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub a: RomRun,
+    pub b: RomRun,
+}
+
+impl Report {
+    fn pairs(&self) -> impl Iterator<Item = (usize, &Checkpoint, &Checkpoint)> {
+        self.a.checkpoints.iter().zip(&self.b.checkpoints).enumerate().map(|(i, (a, b))| (i, a, b))
+    }
+
+    /// Index of the first checkpoint whose frames differ
+    pub fn first_frame_divergence(&self) -> Option<usize> {
+        self.pairs().find(|(_, a, b)| a.frame != b.frame).map(|(i, _, _)| i)
+    }
+
+    /// Index of the first checkpoint whose trace digests differ, with the
+    /// first differing part (see `StateDigest::first_difference`)
+    pub fn first_digest_divergence(&self) -> Option<(usize, &'static str)> {
+        self.pairs().find_map(|(i, a, b)| a.digest.first_difference(&b.digest).map(|section| (i, section)))
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "A {:016x} vs B {:016x}: {} checkpoints",
+            self.a.rom_hash,
+            self.b.rom_hash,
+            self.a.checkpoints.len()
+        )?;
+        let at = |i: usize| {
+            let (a, b) = (&self.a.checkpoints[i], &self.b.checkpoints[i]);
+            format!("checkpoint {} (step {}, cycle {} / {})", i, a.step, a.cycles, b.cycles)
+        };
+        match self.first_frame_divergence() {
+            Some(i) => writeln!(f, "frames diverge at {}", at(i))?,
+            None => writeln!(f, "frames match")?,
+        }
+        match self.first_digest_divergence() {
+            Some((i, section)) => writeln!(f, "trace digests diverge at {}: {}", at(i), section),
+            None => writeln!(f, "trace digests match"),
+        }
+    }
+}
+
+/// Boot `rom` on a fresh instance and play `input`, recording every
+/// checkpoint
+fn run(
+    side: Side,
+    rom: &[u8],
+    input: &InputMacro,
+    load: &mut dyn FnMut(&str) -> Option<Vec<u8>>,
+) -> Result<RomRun, CompareError> {
+    let mut emu = Emu::new();
+    emu.load_rom(rom).map_err(|error| CompareError::Load(side, error))?;
+    let mut checkpoints = Vec::new();
+    input_macro::play_with(&mut emu, input, load, &mut |emu, check| {
+        checkpoints.push(Checkpoint {
+            step: check.step,
+            cycles: check.cycles,
+            frame: check.actual,
+            digest: StateDigest::of(emu),
+        });
+    })
+    .map_err(|error| CompareError::Macro(side, error))?;
+    Ok(RomRun { rom_hash: emu.rom_hash().unwrap_or_default(), checkpoints })
+}
+
+/// Play `input` on `rom_a` and `rom_b` and compare the checkpoints
+pub fn compare(
+    rom_a: &[u8],
+    rom_b: &[u8],
+    input: &InputMacro,
+    load: &mut dyn FnMut(&str) -> Option<Vec<u8>>,
+) -> Result<Report, CompareError> {
+    let a = run(Side::A, rom_a, input, load)?;
+    let b = run(Side::B, rom_b, input, load)?;
+    Ok(Report { a, b })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rom::{self, DemoConfig};
+
+    fn script() -> InputMacro {
+        InputMacro::parse("power\nwait 2000000\nframe 0\ndown 3 2\nwait 500000\nframe 0\nup 3 2\nwait 500000\nframe 0\n")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_same_behavior_with_different_images() {
+        // Trailing bytes the program never reads: a different ROM that
+        // behaves identically
+        let rom_a = test_rom::build();
+        let mut rom_b = rom_a.clone();
+        rom_b.extend_from_slice(b"padding");
+
+        let report = compare(&rom_a, &rom_b, &script(), &mut |_| None).unwrap();
+        assert_ne!(report.a.rom_hash, report.b.rom_hash);
+        assert_eq!(report.a.checkpoints.len(), 3);
+        assert_eq!(report.a.checkpoints, report.b.checkpoints);
+        assert_eq!((report.first_frame_divergence(), report.first_digest_divergence()), (None, None));
+        assert!(report.to_string().ends_with("frames match\ntrace digests match\n"));
+    }
+
+    #[test]
+    fn test_reports_first_divergence() {
+        let rom_a = test_rom::build();
+        let rom_b = test_rom::build_demo(DemoConfig::new(DemoConfig::BARS, 3).unwrap());
+        let report = compare(&rom_a, &rom_b, &script(), &mut |_| None).unwrap();
+        assert_eq!(report.first_frame_divergence(), Some(0));
+        assert!(matches!(report.first_digest_divergence(), Some((0, _))));
+        assert!(report.to_string().contains("frames diverge at checkpoint 0 (step 2"));
+
+        assert_eq!(compare(&[], &rom_b, &script(), &mut |_| None), Err(CompareError::Load(Side::A, LoadError::EmptyRom)));
+        let sends = InputMacro::parse("send GONE.8xp\n").unwrap();
+        assert_eq!(
+            compare(&rom_a, &rom_b, &sends, &mut |_| None),
+            Err(CompareError::Macro(Side::A, MacroError::MissingFile("GONE.8xp".into())))
+        );
+    }
+}
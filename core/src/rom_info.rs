@@ -0,0 +1,317 @@
+//! ROM metadata: calculator model, boot code and OS versions
+//!
+//! The OS starts at `BOOT_CODE_END` with a header in TI's certificate
+//! field format: a big-endian 16-bit type whose low nibble is the data
+//! size (0-12 bytes inline, 13/14/15 = a 1/2/4-byte big-endian size
+//! follows). The outer 0x800x field spans the OS; the fields inside it
+//! give the product ID (0x801x), major version (0x802x), minor and
+//! revision (0x803x) and, in the 0x80Cx field, whether the OS is for the
+//! TI-84 Plus CE or the TI-83 Premium CE. The model decoding follows CEmu.
+//!
+//! The boot code has no header; its version is the first
+//! "major.minor.revision.build" string in the boot sectors.
+//!
+//! A dump with erased boot sectors or an OS header that doesn't parse
+//! won't boot, so frontends can refuse it after `emu_load_rom` instead of
+//! powering on.
+
+use crate::memory::addr;
+
+/// Product ID of the TI-84 Plus CE family (also the TI-83 Premium CE)
+const PRODUCT_CE: u8 = 0x13;
+/// Product ID of the TI-82 Advanced Edition Python
+const PRODUCT_82AEP: u8 = 0x15;
+
+/// Inner header fields scanned before giving up
+const MAX_HEADER_FIELDS: usize = 16;
+
+/// Calculator model the OS was built for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    Unknown = 0,
+    Ti84PlusCe = 1,
+    Ti83PremiumCe = 2,
+    Ti82AdvancedEditionPython = 3,
+}
+
+impl Model {
+    pub fn name(self) -> &'static str {
+        match self {
+            Model::Unknown => "unknown",
+            Model::Ti84PlusCe => "TI-84 Plus CE",
+            Model::Ti83PremiumCe => "TI-83 Premium CE",
+            Model::Ti82AdvancedEditionPython => "TI-82 Advanced Edition Python",
+        }
+    }
+}
+
+/// Boot code version, major.minor.revision.build
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BootVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub revision: u8,
+    pub build: u16,
+}
+
+impl std::fmt::Display for BootVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}.{:04}", self.major, self.minor, self.revision, self.build)
+    }
+}
+
+/// OS version, major.minor.revision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct OsVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub revision: u8,
+}
+
+impl OsVersion {
+    pub const fn new(major: u8, minor: u8, revision: u8) -> Self {
+        OsVersion { major, minor, revision }
+    }
+}
+
+impl std::fmt::Display for OsVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.revision)
+    }
+}
+
+/// What a flash image says about itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RomInfo {
+    pub model: Model,
+    /// The boot sectors aren't erased
+    pub has_boot_code: bool,
+    pub boot_version: Option<BootVersion>,
+    pub os_version: Option<OsVersion>,
+    /// The OS header parses and names a known product
+    pub cert_valid: bool,
+}
+
+impl RomInfo {
+    /// Parse a flash image (a ROM dump, or the emulator's flash)
+    pub fn parse(flash: &[u8]) -> Self {
+        let boot = &flash[..flash.len().min(addr::BOOT_CODE_END as usize)];
+        let mut info = RomInfo {
+            model: Model::Unknown,
+            has_boot_code: boot.iter().any(|&b| b != 0xFF),
+            boot_version: find_boot_version(boot),
+            os_version: None,
+            cert_valid: false,
+        };
+        if let Some(header) = os_header(flash) {
+            info.parse_os_header(header);
+        }
+        info
+    }
+
+    /// Boot code and a valid OS header are present, so a boot is worth
+    /// trying
+    pub fn bootable(&self) -> bool {
+        self.has_boot_code && self.cert_valid
+    }
+
+    fn parse_os_header(&mut self, mut header: &[u8]) {
+        let (mut product, mut device) = (None, None);
+        let (mut major, mut minor) = (None, None);
+        for _ in 0..MAX_HEADER_FIELDS {
+            let Some((kind, data, rest)) = cert_field(header) else { break };
+            if kind >> 8 != 0x80 {
+                break;
+            }
+            match kind & 0xFFF0 {
+                0x8010 => product = data.first().copied(),
+                0x8020 => major = data.first().copied(),
+                0x8030 => minor = Some((data.first().copied(), data.get(1).copied())),
+                0x80C0 => device = data.get(1).copied(),
+                _ => {}
+            }
+            header = rest;
+        }
+
+        if let (Some(major), Some((Some(minor), revision))) = (major, minor) {
+            self.os_version = Some(OsVersion { major, minor, revision: revision.unwrap_or(0) });
+        }
+        self.model = match (product, device) {
+            (Some(PRODUCT_CE), Some(0)) => Model::Ti84PlusCe,
+            (Some(PRODUCT_CE), Some(1)) => Model::Ti83PremiumCe,
+            (Some(PRODUCT_82AEP), Some(1)) => Model::Ti82AdvancedEditionPython,
+            _ => Model::Unknown,
+        };
+        self.cert_valid = matches!(product, Some(PRODUCT_CE | PRODUCT_82AEP)) && self.os_version.is_some();
+    }
+}
+
+/// C view of `RomInfo` (see emu.h)
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EmuRomInfo {
+    /// `Model` code
+    pub model: u32,
+    pub boot_build: u16,
+    pub boot_major: u8,
+    pub boot_minor: u8,
+    pub boot_revision: u8,
+    pub os_major: u8,
+    pub os_minor: u8,
+    pub os_revision: u8,
+    /// `ROM_*` bits
+    pub flags: u8,
+    pub reserved: [u8; 3],
+}
+
+/// `EmuRomInfo::flags`: boot sectors aren't erased
+pub const ROM_BOOT_CODE: u8 = 1;
+/// Boot code version found
+pub const ROM_BOOT_VERSION: u8 = 2;
+/// OS header version found
+pub const ROM_OS_VERSION: u8 = 4;
+/// OS header valid for a known product
+pub const ROM_CERT_VALID: u8 = 8;
+
+impl From<RomInfo> for EmuRomInfo {
+    fn from(info: RomInfo) -> Self {
+        let boot = info.boot_version.unwrap_or(BootVersion { major: 0, minor: 0, revision: 0, build: 0 });
+        let os = info.os_version.unwrap_or(OsVersion { major: 0, minor: 0, revision: 0 });
+        let flags = [
+            (info.has_boot_code, ROM_BOOT_CODE),
+            (info.boot_version.is_some(), ROM_BOOT_VERSION),
+            (info.os_version.is_some(), ROM_OS_VERSION),
+            (info.cert_valid, ROM_CERT_VALID),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |flags, (_, bit)| flags | bit);
+        EmuRomInfo {
+            model: info.model as u32,
+            boot_build: boot.build,
+            boot_major: boot.major,
+            boot_minor: boot.minor,
+            boot_revision: boot.revision,
+            os_major: os.major,
+            os_minor: os.minor,
+            os_revision: os.revision,
+            flags,
+            reserved: [0; 3],
+        }
+    }
+}
+
+/// Split one certificate field off `data`: (type, field data, rest)
+fn cert_field(data: &[u8]) -> Option<(u16, &[u8], &[u8])> {
+    let kind = u16::from_be_bytes([*data.first()?, *data.get(1)?]);
+    let data = &data[2..];
+    let (len, skip) = match kind & 0xF {
+        0xD => (*data.first()? as usize, 1),
+        0xE => (u16::from_be_bytes([*data.first()?, *data.get(1)?]) as usize, 2),
+        0xF => (u32::from_be_bytes(data.get(..4)?.try_into().ok()?) as usize, 4),
+        len => (len as usize, 0),
+    };
+    let data = &data[skip..];
+    if len > data.len() {
+        return None;
+    }
+    Some((kind, &data[..len], &data[len..]))
+}
+
+/// Data of the outer OS header field at the start of the OS
+fn os_header(flash: &[u8]) -> Option<&[u8]> {
+    let (kind, data, _) = cert_field(flash.get(addr::BOOT_CODE_END as usize..)?)?;
+    (kind & 0xFFF0 == 0x8000).then_some(data)
+}
+
+/// First "major.minor.revision.build" in the boot code
+fn find_boot_version(boot: &[u8]) -> Option<BootVersion> {
+    (0..boot.len()).find_map(|start| {
+        if start > 0 && (boot[start - 1].is_ascii_digit() || boot[start - 1] == b'.') {
+            return None;
+        }
+        let mut parts = [0u32; 4];
+        let mut at = start;
+        for (i, part) in parts.iter_mut().enumerate() {
+            let digits = boot[at..].iter().take(5).take_while(|b| b.is_ascii_digit()).count();
+            if digits == 0 {
+                return None;
+            }
+            *part = std::str::from_utf8(&boot[at..at + digits]).ok()?.parse().ok()?;
+            at += digits;
+            if i < 3 {
+                if boot.get(at) != Some(&b'.') {
+                    return None;
+                }
+                at += 1;
+            }
+        }
+        if boot.get(at).is_some_and(|b| b.is_ascii_digit() || *b == b'.') {
+            return None;
+        }
+        Some(BootVersion {
+            major: u8::try_from(parts[0]).ok()?,
+            minor: u8::try_from(parts[1]).ok()?,
+            revision: u8::try_from(parts[2]).ok()?,
+            build: u16::try_from(parts[3]).ok()?,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flash with boot code naming `boot` and an OS header made of `fields`
+    fn flash_with(boot: &str, fields: &[u8]) -> Vec<u8> {
+        let mut flash = vec![0xFF; addr::FLASH_SIZE];
+        flash[0] = 0xC3;
+        flash[0x100..0x100 + boot.len()].copy_from_slice(boot.as_bytes());
+        let os = addr::BOOT_CODE_END as usize;
+        flash[os..os + 2].copy_from_slice(&[0x80, 0x0E]);
+        flash[os + 2..os + 4].copy_from_slice(&(fields.len() as u16).to_be_bytes());
+        flash[os + 4..os + 4 + fields.len()].copy_from_slice(fields);
+        flash
+    }
+
+    #[test]
+    fn test_parse_header_and_boot_version() {
+        // Product 0x13, OS 5.8.1, 0x80A1, device 0 (TI-84 Plus CE)
+        let fields = [0x80, 0x12, 0x13, 0x00, 0x80, 0x21, 5, 0x80, 0x32, 8, 1, 0x80, 0xA1, 0x0B, 0x80, 0xC2, 0, 0];
+        let info = RomInfo::parse(&flash_with("v5.3.6.0017 ", &fields));
+        assert_eq!(info.model, Model::Ti84PlusCe);
+        assert_eq!(info.os_version.unwrap().to_string(), "5.8.1");
+        assert_eq!(info.boot_version.unwrap().to_string(), "5.3.6.0017");
+        assert!(info.cert_valid && info.bootable());
+
+        let c = EmuRomInfo::from(info);
+        assert_eq!((c.model, c.flags), (1, ROM_BOOT_CODE | ROM_BOOT_VERSION | ROM_OS_VERSION | ROM_CERT_VALID));
+        assert_eq!((c.os_major, c.os_minor, c.os_revision, c.boot_build), (5, 8, 1, 17));
+
+        let mut fields = fields;
+        fields[17] = 1;
+        assert_eq!(RomInfo::parse(&flash_with("", &fields)).model, Model::Ti83PremiumCe);
+        fields[2] = PRODUCT_82AEP;
+        assert_eq!(RomInfo::parse(&flash_with("", &fields)).model, Model::Ti82AdvancedEditionPython);
+    }
+
+    #[test]
+    fn test_corrupt_dumps() {
+        let blank = RomInfo::parse(&vec![0xFF; addr::FLASH_SIZE]);
+        assert!(!blank.has_boot_code && !blank.cert_valid && !blank.bootable());
+        assert_eq!(EmuRomInfo::from(blank), EmuRomInfo::default());
+
+        // Header length runs past the end of flash
+        let mut flash = flash_with("1.2.3.4", &[0x80, 0x12, 0x13, 0x00, 0x80, 0x21, 5]);
+        let os = addr::BOOT_CODE_END as usize;
+        flash[os + 2..os + 4].copy_from_slice(&[0xFF, 0xFF]);
+        flash.truncate(os + 0x100);
+        let info = RomInfo::parse(&flash);
+        assert!(info.has_boot_code && !info.cert_valid && info.os_version.is_none());
+
+        // Unknown product, no minor version field
+        let info = RomInfo::parse(&flash_with("1.2.3", &[0x80, 0x12, 0x42, 0x00, 0x80, 0x21, 5]));
+        assert_eq!((info.model, info.os_version, info.boot_version), (Model::Unknown, None, None));
+        assert!(!info.bootable());
+    }
+}
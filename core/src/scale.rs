@@ -0,0 +1,144 @@
+//! Integer scaling and rotation of the framebuffer
+//!
+//! For frontends without cheap GPU scaling (wasm canvases drawn with
+//! putImageData, small embedded displays), `transform` writes the ARGB8888
+//! framebuffer into a caller buffer at 1x-4x nearest-neighbour scale,
+//! rotated clockwise by a multiple of 90 degrees. The framebuffer itself is
+//! never modified.
+
+/// Largest supported integer scale
+pub const MAX_SCALE: usize = 4;
+
+/// Clockwise rotation (stable C ABI values are the degrees, see emu.h)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    pub fn from_degrees(degrees: u32) -> Option<Self> {
+        match degrees {
+            0 => Some(Rotation::None),
+            90 => Some(Rotation::Cw90),
+            180 => Some(Rotation::Cw180),
+            270 => Some(Rotation::Cw270),
+            _ => None,
+        }
+    }
+
+    pub fn degrees(self) -> u32 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Cw90 => 90,
+            Rotation::Cw180 => 180,
+            Rotation::Cw270 => 270,
+        }
+    }
+
+    fn swaps_axes(self) -> bool {
+        matches!(self, Rotation::Cw90 | Rotation::Cw270)
+    }
+}
+
+/// Output (width, height) for a `width` x `height` source, or None if
+/// `scale` is outside 1..=MAX_SCALE
+pub fn output_size(width: usize, height: usize, scale: usize, rotation: Rotation) -> Option<(usize, usize)> {
+    if !(1..=MAX_SCALE).contains(&scale) {
+        return None;
+    }
+    let (w, h) = if rotation.swaps_axes() { (height, width) } else { (width, height) };
+    Some((w * scale, h * scale))
+}
+
+/// Scale and rotate `src` (`width` x `height`, row-major) into `out`.
+/// Returns the output size, or None for a bad scale or when `out` is
+/// smaller than width * height of the output (nothing is written then).
+pub fn transform(
+    src: &[u32],
+    width: usize,
+    height: usize,
+    scale: usize,
+    rotation: Rotation,
+    out: &mut [u32],
+) -> Option<(usize, usize)> {
+    let (out_w, out_h) = output_size(width, height, scale, rotation)?;
+    if out.len() < out_w * out_h || src.len() < width * height {
+        return None;
+    }
+    let (rot_w, rot_h) = (out_w / scale, out_h / scale);
+
+    for ry in 0..rot_h {
+        let row_start = ry * scale * out_w;
+        for rx in 0..rot_w {
+            // Source pixel shown at rotated position (rx, ry)
+            let (sx, sy) = match rotation {
+                Rotation::None => (rx, ry),
+                Rotation::Cw90 => (ry, height - 1 - rx),
+                Rotation::Cw180 => (width - 1 - rx, height - 1 - ry),
+                Rotation::Cw270 => (width - 1 - ry, rx),
+            };
+            let pixel = src[sy * width + sx];
+            out[row_start + rx * scale..row_start + (rx + 1) * scale].fill(pixel);
+        }
+        // Repeat the finished row for the rest of the scaled block
+        for copy in 1..scale {
+            out.copy_within(row_start..row_start + out_w, row_start + copy * out_w);
+        }
+    }
+    Some((out_w, out_h))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 3x2 source with distinct pixels:
+    /// 0 1 2
+    /// 3 4 5
+    const SRC: [u32; 6] = [0, 1, 2, 3, 4, 5];
+
+    fn run(scale: usize, rotation: Rotation) -> (usize, usize, Vec<u32>) {
+        let (w, h) = output_size(3, 2, scale, rotation).unwrap();
+        let mut out = vec![u32::MAX; w * h];
+        assert_eq!(transform(&SRC, 3, 2, scale, rotation, &mut out), Some((w, h)));
+        (w, h, out)
+    }
+
+    #[test]
+    fn test_rotations() {
+        assert_eq!(run(1, Rotation::None), (3, 2, SRC.to_vec()));
+        assert_eq!(run(1, Rotation::Cw90), (2, 3, vec![3, 0, 4, 1, 5, 2]));
+        assert_eq!(run(1, Rotation::Cw180), (3, 2, vec![5, 4, 3, 2, 1, 0]));
+        assert_eq!(run(1, Rotation::Cw270), (2, 3, vec![2, 5, 1, 4, 0, 3]));
+    }
+
+    #[test]
+    fn test_scale_replicates_pixels() {
+        let (w, h, out) = run(2, Rotation::None);
+        assert_eq!((w, h), (6, 4));
+        assert_eq!(&out[..6], &[0, 0, 1, 1, 2, 2]);
+        assert_eq!(&out[6..12], &[0, 0, 1, 1, 2, 2]);
+        assert_eq!(&out[18..], &[3, 3, 4, 4, 5, 5]);
+
+        let (w, h, out) = run(3, Rotation::Cw90);
+        assert_eq!((w, h), (6, 9));
+        assert_eq!(&out[..6], &[3, 3, 3, 0, 0, 0]);
+        assert_eq!(&out[48..], &[5, 5, 5, 2, 2, 2]);
+    }
+
+    #[test]
+    fn test_rejects_bad_scale_and_short_buffer() {
+        assert_eq!(output_size(320, 240, 0, Rotation::None), None);
+        assert_eq!(output_size(320, 240, MAX_SCALE + 1, Rotation::None), None);
+        assert_eq!(output_size(320, 240, 2, Rotation::Cw270), Some((480, 640)));
+
+        let mut out = vec![7u32; 5];
+        assert_eq!(transform(&SRC, 3, 2, 1, Rotation::None, &mut out), None);
+        assert_eq!(out, vec![7; 5], "nothing written");
+        assert_eq!(Rotation::from_degrees(45), None);
+        assert_eq!(Rotation::from_degrees(270).unwrap().degrees(), 270);
+    }
+}
@@ -0,0 +1,137 @@
+//! Scanline rendering
+//!
+//! By default `render_frame` decodes the whole frame from VRAM once, after
+//! the run, so a program that races the beam (changes the palette, VRAM
+//! or UPBASE while the LCD is scanning out, for split screens or raster
+//! effects) shows only the end state. With scanline rendering on, each
+//! row is decoded as the LCD DMA fetches it, into a back buffer that
+//! becomes the displayed frame once all rows are in. Until the first
+//! frame completes (or while the LCD is off) the snapshot is used.
+//!
+//! Rows follow the DMA row counter, which fast-forwards in batches when
+//! the CPU halts; a row is never decoded before the DMA reaches it, but a
+//! batch of rows can be decoded together slightly late, and rows the
+//! counter hasn't reached by the next vertical sync are decoded then.
+
+use std::ops::Range;
+
+use crate::emu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Back and front frame buffers plus the row the beam has reached
+#[derive(Debug, Clone)]
+pub struct ScanlineRenderer {
+    back: Vec<u32>,
+    front: Vec<u32>,
+    /// Rows of `back` decoded so far this frame
+    rows_done: usize,
+    /// Every row of `back` so far had a valid UPBASE
+    back_valid: bool,
+    front_valid: bool,
+    /// `front` holds a complete frame
+    has_frame: bool,
+}
+
+impl Default for ScanlineRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScanlineRenderer {
+    pub fn new() -> Self {
+        ScanlineRenderer {
+            back: vec![0xFF000000; SCREEN_WIDTH * SCREEN_HEIGHT],
+            front: vec![0xFF000000; SCREEN_WIDTH * SCREEN_HEIGHT],
+            rows_done: 0,
+            back_valid: true,
+            front_valid: true,
+            has_frame: false,
+        }
+    }
+
+    /// Forget the frames (reset, state load)
+    pub fn clear(&mut self) {
+        self.rows_done = 0;
+        self.back_valid = true;
+        self.has_frame = false;
+    }
+
+    /// Vertical sync: complete the frame with the rows the DMA didn't
+    /// reach; the next DMA row is row 0 of a new frame
+    pub fn vsync(&mut self, decode: impl FnMut(Range<usize>, &mut [u32]) -> bool) {
+        self.catch_up(SCREEN_HEIGHT, decode);
+        self.rows_done = 0;
+        self.back_valid = true;
+    }
+
+    /// Decode the rows the DMA has fetched up to `row` (exclusive) with
+    /// `decode(rows, frame)`, which returns false for an invalid UPBASE.
+    /// Completes the frame once all rows are in.
+    pub fn catch_up(&mut self, row: usize, mut decode: impl FnMut(Range<usize>, &mut [u32]) -> bool) {
+        let row = row.min(SCREEN_HEIGHT);
+        if row <= self.rows_done {
+            return;
+        }
+        self.back_valid &= decode(self.rows_done..row, &mut self.back);
+        self.rows_done = row;
+        if row == SCREEN_HEIGHT {
+            std::mem::swap(&mut self.back, &mut self.front);
+            self.front_valid = self.back_valid;
+            self.has_frame = true;
+            // Stay at the end until the next vertical sync
+        }
+    }
+
+    /// The last complete frame and whether its UPBASE was valid throughout
+    pub fn frame(&self) -> Option<(&[u32], bool)> {
+        self.has_frame.then_some((&self.front[..], self.front_valid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_decoded_as_reached() {
+        let mut renderer = ScanlineRenderer::new();
+        let mut color = 1;
+        let mut decode = |rows: Range<usize>, frame: &mut [u32]| {
+            frame[rows.start * SCREEN_WIDTH..rows.end * SCREEN_WIDTH].fill(color);
+            true
+        };
+        renderer.catch_up(100, &mut decode);
+        assert_eq!(renderer.frame(), None);
+
+        // The picture changes mid-frame: only rows not yet reached see it
+        color = 2;
+        let mut decode = |rows: Range<usize>, frame: &mut [u32]| {
+            frame[rows.start * SCREEN_WIDTH..rows.end * SCREEN_WIDTH].fill(color);
+            rows.start != 200
+        };
+        renderer.catch_up(50, &mut decode);
+        renderer.catch_up(200, &mut decode);
+        renderer.catch_up(1000, &mut decode);
+        let (frame, valid) = renderer.frame().unwrap();
+        assert_eq!((frame[99 * SCREEN_WIDTH], frame[100 * SCREEN_WIDTH]), (1, 2));
+        assert!(!valid);
+
+        // Nothing more until the next sync
+        renderer.catch_up(SCREEN_HEIGHT, |_, _| panic!("frame already complete"));
+        renderer.vsync(|_, _| panic!("frame already complete"));
+        renderer.catch_up(10, |_, _| true);
+        assert!(!renderer.frame().unwrap().1, "still the previous frame");
+
+        // A sync before the DMA got to the bottom finishes the frame
+        let mut decoded = None;
+        renderer.vsync(|rows, _| {
+            decoded = Some(rows);
+            true
+        });
+        assert_eq!(decoded, Some(10..SCREEN_HEIGHT));
+        assert!(renderer.frame().unwrap().1);
+
+        renderer.clear();
+        assert_eq!(renderer.frame(), None);
+    }
+}
@@ -3,6 +3,8 @@
 //! Based on CEmu's schedule.c implementation.
 //! Uses a 7.68 GHz base clock rate as LCM of all hardware clocks.
 
+use crate::error::LoadError;
+
 /// Base clock rate: 7,680,000,000 Hz (7.68 GHz)
 /// This is the LCM of all hardware clocks, allowing integer division for conversions.
 pub const SCHED_BASE_CLOCK_RATE: u64 = 7_680_000_000;
@@ -12,6 +14,7 @@ pub const TICKS_PER_SECOND: u64 = 32_768;
 
 /// Clock identifiers for different hardware components
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum ClockId {
     /// CPU clock (variable: 6/12/24/48 MHz)
@@ -68,6 +71,7 @@ impl ClockId {
 
 /// Event identifiers for scheduled events
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum EventId {
     /// RTC load operation
@@ -97,6 +101,7 @@ const INACTIVE_FLAG: u64 = 1 << 63;
 
 /// A scheduled event item
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SchedItem {
     /// Timestamp in base ticks (bit 63 set = inactive)
     pub timestamp: u64,
@@ -129,6 +134,7 @@ impl SchedItem {
 
 /// The scheduler manages timed events
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Scheduler {
     /// All scheduled event items
     items: [SchedItem; EventId::Count as usize],
@@ -522,9 +528,20 @@ impl Scheduler {
 // ========== State Persistence ==========
 
 impl Scheduler {
-    /// Size of scheduler state snapshot in bytes
+    /// Size of scheduler state snapshot in bytes:
+    /// 8 (base_ticks) + 1 (cpu_speed) + 8 (dma_last_mem_timestamp) + 1 (event count)
+    /// + 9 * 10 (event entries) = 108, round to 112
+    ///
+    /// Each entry is event id (1) + clock id (1) + raw timestamp (8, bit 63 set
+    /// when inactive). Entries name their event and clock instead of relying
+    /// on the position of `items`, so a restored deadline can't silently land
+    /// on another event, and inactive events keep the timestamp `repeat`
+    /// continues from.
+    pub const SNAPSHOT_SIZE: usize = 112;
+
+    /// Size of the positional snapshot used by state versions before 12:
     /// 8 (base_ticks) + 1 (cpu_speed) + 9*8 (item timestamps) + 8 (dma_last_mem_timestamp) = 89, round to 96
-    pub const SNAPSHOT_SIZE: usize = 96;
+    pub const LEGACY_SNAPSHOT_SIZE: usize = 96;
 
     /// Save scheduler state to bytes
     pub fn to_bytes(&self) -> [u8; Self::SNAPSHOT_SIZE] {
@@ -534,23 +551,67 @@ impl Scheduler {
         // Base timing state
         buf[pos..pos+8].copy_from_slice(&self.base_ticks.to_le_bytes()); pos += 8;
         buf[pos] = self.cpu_speed; pos += 1;
+        buf[pos..pos+8].copy_from_slice(&self.dma_last_mem_timestamp.to_le_bytes()); pos += 8;
 
-        // Event timestamps (9 events × 8 bytes each)
+        // Event entries
+        buf[pos] = self.items.len() as u8; pos += 1;
         for item in &self.items {
-            buf[pos..pos+8].copy_from_slice(&item.timestamp.to_le_bytes());
-            pos += 8;
+            buf[pos] = item.event as u8;
+            buf[pos+1] = item.clock as u8;
+            buf[pos+2..pos+10].copy_from_slice(&item.timestamp.to_le_bytes());
+            pos += 10;
         }
 
-        // DMA state
-        buf[pos..pos+8].copy_from_slice(&self.dma_last_mem_timestamp.to_le_bytes());
-
         buf
     }
 
-    /// Load scheduler state from bytes
-    pub fn from_bytes(&mut self, buf: &[u8]) -> Result<(), i32> {
+    /// Load scheduler state from bytes. Fails without touching the
+    /// scheduler if an entry names an unknown event or a clock the event
+    /// doesn't run on.
+    pub fn from_bytes(&mut self, buf: &[u8]) -> Result<(), LoadError> {
         if buf.len() < Self::SNAPSHOT_SIZE {
-            return Err(-105);
+            return Err(LoadError::StateTruncated { expected: Self::SNAPSHOT_SIZE, found: buf.len() });
+        }
+
+        let mut pos = 0;
+        let base_ticks = u64::from_le_bytes(buf[pos..pos+8].try_into().unwrap()); pos += 8;
+        let cpu_speed = buf[pos]; pos += 1;
+        let dma_last_mem_timestamp = u64::from_le_bytes(buf[pos..pos+8].try_into().unwrap()); pos += 8;
+
+        let bad = |offset| LoadError::BadSection { section: "scheduler", offset };
+        let count = buf[pos] as usize;
+        if count > self.items.len() {
+            return Err(bad(pos));
+        }
+        pos += 1;
+        // Events without an entry are idle
+        let mut timestamps = [INACTIVE_FLAG; EventId::Count as usize];
+        for _ in 0..count {
+            let (event, clock) = (buf[pos] as usize, buf[pos+1]);
+            let item = self.items.get(event).ok_or(bad(pos))?;
+            if item.clock as u8 != clock {
+                return Err(bad(pos + 1));
+            }
+            timestamps[event] = u64::from_le_bytes(buf[pos+2..pos+10].try_into().unwrap());
+            pos += 10;
+        }
+
+        self.base_ticks = base_ticks;
+        self.cpu_speed = cpu_speed;
+        self.cached_cpu_base_ticks = ClockId::Cpu.base_ticks_per_tick(cpu_speed);
+        self.dma_last_mem_timestamp = dma_last_mem_timestamp;
+        for (item, timestamp) in self.items.iter_mut().zip(timestamps) {
+            item.timestamp = timestamp;
+        }
+
+        self.recalc_next_event();
+        Ok(())
+    }
+
+    /// Load the positional scheduler state of state versions before 12
+    pub fn from_legacy_bytes(&mut self, buf: &[u8]) -> Result<(), LoadError> {
+        if buf.len() < Self::LEGACY_SNAPSHOT_SIZE {
+            return Err(LoadError::StateTruncated { expected: Self::LEGACY_SNAPSHOT_SIZE, found: buf.len() });
         }
 
         let mut pos = 0;
@@ -698,4 +759,61 @@ mod tests {
         assert_eq!(pending.len(), 1);
         assert_eq!(pending[0], EventId::Rtc);
     }
+
+    #[test]
+    fn test_snapshot_restores_pending_events() {
+        let mut sched = Scheduler::new();
+        sched.set_cpu_speed(3);
+        sched.set(EventId::Lcd, 1000);
+        sched.set(EventId::Timer1, 50);
+        sched.advance(10);
+        sched.set(EventId::Rtc, 3);
+        sched.clear(EventId::Rtc);
+
+        let mut restored = Scheduler::new();
+        restored.from_bytes(&sched.to_bytes()).unwrap();
+        assert_eq!(restored.base_ticks, sched.base_ticks);
+        assert_eq!(restored.cpu_speed(), 3);
+        for (a, b) in restored.items().iter().zip(sched.items()) {
+            assert_eq!((a.event, a.timestamp), (b.event, b.timestamp));
+        }
+        assert_eq!(restored.ticks_remaining(EventId::Lcd), sched.ticks_remaining(EventId::Lcd));
+        assert_eq!(restored.cycles_until_next_event(), sched.cycles_until_next_event());
+
+        // Inactive events resume repeating from their old timestamp
+        restored.repeat(EventId::Rtc, 1);
+        sched.repeat(EventId::Rtc, 1);
+        assert_eq!(restored.items()[EventId::Rtc as usize].timestamp, sched.items()[EventId::Rtc as usize].timestamp);
+    }
+
+    #[test]
+    fn test_snapshot_rejects_mismatched_entries() {
+        let mut sched = Scheduler::new();
+        sched.set(EventId::Spi, 10);
+        let bytes = sched.to_bytes();
+        let entry = |event: EventId| 18 + event as usize * 10;
+
+        let mut restored = Scheduler::new();
+        restored.set(EventId::Timer0, 5);
+        let mut bad = bytes;
+        bad[entry(EventId::Spi) + 1] = ClockId::Cpu as u8;
+        let section = "scheduler";
+        assert_eq!(restored.from_bytes(&bad), Err(LoadError::BadSection { section, offset: entry(EventId::Spi) + 1 }));
+        bad = bytes;
+        bad[entry(EventId::Lcd)] = EventId::Count as u8;
+        assert_eq!(restored.from_bytes(&bad), Err(LoadError::BadSection { section, offset: entry(EventId::Lcd) }));
+        assert!(matches!(restored.from_bytes(&bad[..10]), Err(LoadError::StateTruncated { .. })));
+        // A failed load leaves the scheduler as it was
+        assert!(restored.is_active(EventId::Timer0));
+        assert!(!restored.is_active(EventId::Spi));
+
+        // Entries are found by event id, not position
+        bad = bytes;
+        bad.copy_within(entry(EventId::Spi)..entry(EventId::Spi) + 10, entry(EventId::Rtc));
+        bad[17] = 1;
+        restored.from_bytes(&bad).unwrap();
+        assert!(restored.is_active(EventId::Spi));
+        assert!(!restored.is_active(EventId::Timer0));
+        assert_eq!(restored.ticks_remaining(EventId::Spi), 10);
+    }
 }
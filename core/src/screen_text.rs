@@ -0,0 +1,100 @@
+//! Homescreen text for screen readers
+//!
+//! TI-OS keeps a copy of every character it draws on the homescreen in
+//! `textShadow`, 10 rows of 26 large-font character codes, and the cursor
+//! position in `curRow` / `curCol`. Reading those instead of the
+//! framebuffer gives the displayed text whatever the renderer, scaling or
+//! color profile, for frontends to hand to a screen reader.
+//!
+//! Character codes are TI's large font: printable ASCII maps to itself
+//! and the math symbols below to their Unicode equivalents; anything else
+//! (cursors, unused glyphs) becomes a space. Only the homescreen is
+//! mirrored: while a menu or graph is shown the shadow holds the
+//! homescreen text underneath it.
+//!
+//! Addresses: ti84pceg.inc.
+
+use crate::memory::addr;
+
+/// textShadow: the homescreen's characters, row by row
+pub const TEXT_SHADOW: u32 = 0xD006C0;
+/// curRow / curCol: homescreen cursor position
+pub const CUR_ROW: u32 = 0xD00595;
+pub const CUR_COL: u32 = 0xD00596;
+
+/// Homescreen size in large-font characters
+pub const ROWS: usize = 10;
+pub const COLS: usize = 26;
+
+/// Homescreen text and cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenText {
+    /// One string per row, trailing spaces removed
+    pub rows: Vec<String>,
+    /// Cursor (row, col)
+    pub cursor: (u8, u8),
+}
+
+impl ScreenText {
+    /// Rows joined with newlines, trailing blank rows removed
+    pub fn to_text(&self) -> String {
+        let last = self.rows.iter().rposition(|row| !row.is_empty()).map_or(0, |i| i + 1);
+        self.rows[..last].join("\n")
+    }
+}
+
+/// Unicode for a large-font character code
+pub fn char_for_code(code: u8) -> char {
+    match code {
+        0x10 => '√',
+        0x12 => '²',
+        0x14 => '°',
+        0x17 => '≤',
+        0x18 => '≠',
+        0x19 => '≥',
+        0x1A => '⁻',
+        0x1B => 'ᴇ',
+        0x1C => '→',
+        0x5B => 'θ',
+        0x20..=0x7E => code as char,
+        _ => ' ',
+    }
+}
+
+/// Read the homescreen text from RAM (`addr::RAM_START`-based, as
+/// `Emu::ram_data`)
+pub fn extract(ram: &[u8]) -> ScreenText {
+    let byte = |address: u32| ram.get((address - addr::RAM_START) as usize).copied().unwrap_or(0);
+    let rows = (0..ROWS)
+        .map(|row| {
+            let start = TEXT_SHADOW + (row * COLS) as u32;
+            let text: String = (0..COLS as u32).map(|col| char_for_code(byte(start + col))).collect();
+            text.trim_end().to_string()
+        })
+        .collect();
+    ScreenText { rows, cursor: (byte(CUR_ROW), byte(CUR_COL)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract() {
+        let mut ram = vec![0u8; addr::RAM_SIZE];
+        let shadow = (TEXT_SHADOW - addr::RAM_START) as usize;
+        ram[shadow..shadow + ROWS * COLS].fill(b' ');
+        ram[shadow..shadow + 4].copy_from_slice(b"6+7 ");
+        let row1 = shadow + COLS;
+        ram[row1 + COLS - 2..row1 + COLS].copy_from_slice(b"13");
+        ram[shadow + 2 * COLS..shadow + 2 * COLS + 3].copy_from_slice(&[0x1A, b'2', 0x12]);
+        ram[(CUR_ROW - addr::RAM_START) as usize] = 3;
+
+        let text = extract(&ram);
+        assert_eq!(text.rows[0], "6+7");
+        assert_eq!(text.rows[1], format!("{:>26}", "13"));
+        assert_eq!(text.rows[2], "⁻2²");
+        assert_eq!(text.cursor, (3, 0));
+        assert_eq!(text.to_text(), format!("6+7\n{:>26}\n⁻2²", "13"));
+    }
+}
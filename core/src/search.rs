@@ -0,0 +1,120 @@
+//! Masked byte patterns for memory search and replace
+//!
+//! Patterns are written as space-separated hex bytes where any nibble may be
+//! `?`, e.g. `"3E ?? CD ?? ?? 02"` or `"C?"`. Used by patchers to locate code
+//! in flash/RAM and rewrite only the nibbles that are not wildcards.
+
+/// A byte sequence with a per-nibble care mask
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BytePattern {
+    bytes: Vec<u8>,
+    mask: Vec<u8>,
+}
+
+impl BytePattern {
+    /// Exact pattern with every bit significant
+    pub fn exact(bytes: &[u8]) -> Self {
+        Self {
+            bytes: bytes.to_vec(),
+            mask: vec![0xFF; bytes.len()],
+        }
+    }
+
+    /// Parse a pattern string. Returns None for empty or malformed input.
+    pub fn parse(src: &str) -> Option<Self> {
+        let mut bytes = Vec::new();
+        let mut mask = Vec::new();
+        for token in src.split_whitespace() {
+            let chars: Vec<char> = token.chars().collect();
+            if chars.len() != 2 {
+                return None;
+            }
+            let (mut byte, mut care) = (0u8, 0u8);
+            for &c in &chars {
+                byte <<= 4;
+                care <<= 4;
+                if c != '?' {
+                    byte |= c.to_digit(16)? as u8;
+                    care |= 0xF;
+                }
+            }
+            bytes.push(byte);
+            mask.push(care);
+        }
+        if bytes.is_empty() {
+            return None;
+        }
+        Some(Self { bytes, mask })
+    }
+
+    /// Pattern length in bytes
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Whether `window` (at least `len()` bytes) matches the pattern
+    pub fn matches(&self, window: &[u8]) -> bool {
+        self.bytes
+            .iter()
+            .zip(&self.mask)
+            .zip(window)
+            .all(|((&b, &m), &w)| w & m == b & m)
+    }
+
+    /// Offsets of every match in `data`, up to `limit` results
+    pub fn find_all(&self, data: &[u8], limit: usize) -> Vec<usize> {
+        if self.len() > data.len() {
+            return Vec::new();
+        }
+        (0..=data.len() - self.len())
+            .filter(|&i| self.matches(&data[i..]))
+            .take(limit)
+            .collect()
+    }
+
+    /// Apply this pattern as a replacement: cared-for nibbles are written,
+    /// wildcard nibbles keep the original value.
+    pub fn apply(&self, original: &[u8]) -> Vec<u8> {
+        self.bytes
+            .iter()
+            .zip(&self.mask)
+            .zip(original)
+            .map(|((&b, &m), &o)| (o & !m) | (b & m))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let p = BytePattern::parse("3E ?? c9 4?").unwrap();
+        assert_eq!(p.len(), 4);
+        assert_eq!(p.bytes, vec![0x3E, 0x00, 0xC9, 0x40]);
+        assert_eq!(p.mask, vec![0xFF, 0x00, 0xFF, 0xF0]);
+        for bad in ["", "3", "3E0", "GG", "3E ?"] {
+            assert!(BytePattern::parse(bad).is_none(), "{bad:?} should not parse");
+        }
+    }
+
+    #[test]
+    fn test_find_all_masked() {
+        let data = [0x3E, 0x01, 0xC9, 0x3E, 0x02, 0xC9, 0x3E];
+        let p = BytePattern::parse("3E ?? C9").unwrap();
+        assert_eq!(p.find_all(&data, usize::MAX), vec![0, 3]);
+        assert_eq!(p.find_all(&data, 1), vec![0]);
+        assert!(p.find_all(&data[..2], usize::MAX).is_empty());
+    }
+
+    #[test]
+    fn test_apply_keeps_wildcard_nibbles() {
+        let p = BytePattern::parse("00 ?? ?F").unwrap();
+        assert_eq!(p.apply(&[0x3E, 0x12, 0xC9]), vec![0x00, 0x12, 0xCF]);
+    }
+}
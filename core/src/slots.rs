@@ -0,0 +1,146 @@
+//! Numbered in-memory save slots
+//!
+//! Quick-save/quick-load for frontends that do not want to manage state
+//! buffers themselves. Each slot holds one full `Emu::save_state` image
+//! (~4.5MB, dominated by flash), so an optional byte limit caps the total
+//! memory all slots may use.
+//!
+//! Errors: `BadSlot` (-40) slot number out of range, `EmptySlot` (-41) slot
+//! empty, `SlotLimit` (-42) memory limit would be exceeded.
+
+use crate::error::EmuError;
+
+/// Number of save slots (valid slot numbers are 0..MAX_SLOTS)
+pub const MAX_SLOTS: usize = 10;
+
+/// Slot description for frontends (C layout, see emu.h)
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EmuSlotInfo {
+    /// 1 if the slot holds a state, 0 if empty
+    pub used: u32,
+    /// State size in bytes
+    pub size: u32,
+    /// Emulated cycle count when the slot was saved
+    pub cycles: u64,
+    /// Save sequence number, increasing across all slots (newest is largest)
+    pub sequence: u64,
+}
+
+struct Slot {
+    data: Vec<u8>,
+    cycles: u64,
+    sequence: u64,
+}
+
+/// Fixed set of save slots with an optional total size limit
+pub struct SaveSlots {
+    slots: Vec<Option<Slot>>,
+    /// Maximum total bytes across all slots (None = unlimited)
+    limit: Option<usize>,
+    next_sequence: u64,
+}
+
+impl SaveSlots {
+    pub fn new() -> Self {
+        Self {
+            slots: (0..MAX_SLOTS).map(|_| None).collect(),
+            limit: None,
+            next_sequence: 1,
+        }
+    }
+
+    fn check(n: usize) -> Result<(), EmuError> {
+        if n < MAX_SLOTS { Ok(()) } else { Err(EmuError::BadSlot) }
+    }
+
+    /// Set the total size limit (None = unlimited). Existing slots are kept
+    /// even if they already exceed it; only new saves are refused.
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+    }
+
+    /// Bytes currently held by all slots
+    pub fn total_bytes(&self) -> usize {
+        self.slots.iter().flatten().map(|s| s.data.len()).sum()
+    }
+
+    /// Store `data` in slot `n`, replacing its previous contents
+    pub fn store(&mut self, n: usize, data: Vec<u8>, cycles: u64) -> Result<(), EmuError> {
+        Self::check(n)?;
+        if let Some(limit) = self.limit {
+            let replaced = self.slots[n].as_ref().map_or(0, |s| s.data.len());
+            if self.total_bytes() - replaced + data.len() > limit {
+                return Err(EmuError::SlotLimit);
+            }
+        }
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.slots[n] = Some(Slot { data, cycles, sequence });
+        Ok(())
+    }
+
+    /// State bytes held in slot `n`
+    pub fn get(&self, n: usize) -> Result<&[u8], EmuError> {
+        Self::check(n)?;
+        self.slots[n].as_ref().map(|s| s.data.as_slice()).ok_or(EmuError::EmptySlot)
+    }
+
+    /// Describe slot `n`
+    pub fn info(&self, n: usize) -> Result<EmuSlotInfo, EmuError> {
+        Self::check(n)?;
+        Ok(self.slots[n].as_ref().map_or(EmuSlotInfo::default(), |s| EmuSlotInfo {
+            used: 1,
+            size: s.data.len() as u32,
+            cycles: s.cycles,
+            sequence: s.sequence,
+        }))
+    }
+
+    /// Free slot `n`
+    pub fn clear(&mut self, n: usize) -> Result<(), EmuError> {
+        Self::check(n)?;
+        self.slots[n] = None;
+        Ok(())
+    }
+}
+
+impl Default for SaveSlots {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_and_info() {
+        let mut slots = SaveSlots::new();
+        assert_eq!(slots.info(0), Ok(EmuSlotInfo::default()));
+        assert_eq!(slots.get(0), Err(EmuError::EmptySlot));
+        assert_eq!(slots.store(MAX_SLOTS, vec![1], 0), Err(EmuError::BadSlot));
+
+        slots.store(3, vec![1, 2, 3], 500).unwrap();
+        slots.store(1, vec![4], 600).unwrap();
+        assert_eq!(slots.get(3), Ok(&[1u8, 2, 3][..]));
+        let info = slots.info(3).unwrap();
+        assert_eq!((info.used, info.size, info.cycles), (1, 3, 500));
+        assert!(slots.info(1).unwrap().sequence > info.sequence);
+
+        slots.clear(3).unwrap();
+        assert_eq!(slots.get(3), Err(EmuError::EmptySlot));
+    }
+
+    #[test]
+    fn test_limit_counts_replaced_slot() {
+        let mut slots = SaveSlots::new();
+        slots.set_limit(Some(10));
+        slots.store(0, vec![0; 6], 0).unwrap();
+        assert_eq!(slots.store(1, vec![0; 6], 0), Err(EmuError::SlotLimit));
+        // Overwriting slot 0 frees its old bytes first
+        slots.store(0, vec![0; 10], 0).unwrap();
+        assert_eq!(slots.total_bytes(), 10);
+    }
+}
@@ -0,0 +1,59 @@
+//! Bus snooping filtered by address range
+//!
+//! A lighter alternative to the full I/O trace for watching one piece of
+//! memory, such as the OS key buffer or a game's state struct: every CPU
+//! data read and write inside the range is handed to a callback with the
+//! PC of the instruction that made it. Instruction fetches are not
+//! reported, and debugger peeks/pokes never reach the bus paths that
+//! deliver accesses.
+
+use std::os::raw::c_void;
+
+/// One access as passed across the C ABI
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EmuSnoopAccess {
+    /// Total cycles when the access happened
+    pub cycles: u64,
+    /// 24-bit address
+    pub addr: u32,
+    /// Instruction that made the access
+    pub pc: u32,
+    /// Byte read, or written
+    pub value: u8,
+    /// 1 for a write, 0 for a read
+    pub write: u8,
+    pub reserved: [u8; 2],
+}
+
+/// Snoop callback: receives the user pointer given at registration.
+/// Called from inside the bus access with the emulator locked, so it must
+/// not call back into the emulator.
+pub type SnoopCallback = extern "C" fn(user: *mut c_void, access: *const EmuSnoopAccess);
+
+/// Watched range plus the callback and its user pointer (stored as usize to
+/// stay Send)
+#[derive(Clone, Copy)]
+pub(crate) struct Snoop {
+    start: u32,
+    end: u32,
+    callback: SnoopCallback,
+    user: usize,
+}
+
+impl Snoop {
+    /// Watch `start..=end`
+    pub(crate) fn new(start: u32, end: u32, callback: SnoopCallback, user: *mut c_void) -> Self {
+        Self { start, end, callback, user: user as usize }
+    }
+
+    #[inline]
+    pub(crate) fn contains(&self, addr: u32) -> bool {
+        (self.start..=self.end).contains(&addr)
+    }
+
+    pub(crate) fn deliver(&self, access: &EmuSnoopAccess) {
+        (self.callback)(self.user as *mut c_void, access);
+    }
+}
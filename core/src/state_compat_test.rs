@@ -0,0 +1,192 @@
+//! Save-state compatibility tests
+//!
+//! `tests/state_corpus/` holds one save state per shipped format version,
+//! captured from the built-in test ROM. Versions listed in `LOADABLE` must
+//! load and keep running; every other corpus file must be rejected with a
+//! version mismatch. The current `STATE_VERSION` must have a corpus file and
+//! be loadable. Bumping the format therefore means adding a new corpus file
+//! *and* deciding, in review, whether the old one keeps loading (migration
+//! code) or is knowingly rejected (taken out of `LOADABLE`).
+//!
+//! Regenerate the file for the current version with:
+//!
+//! ```text
+//! cargo test --lib regenerate_state_corpus -- --ignored
+//! ```
+//!
+//! Corpus files are stored with a small repeat encoding (see `decode`)
+//! because raw states are ~4.5MB and almost entirely erased flash.
+
+#[cfg(test)]
+mod tests {
+    use crate::memory::addr::FLASH_SIZE;
+    use crate::test_rom::{self, read24, READY, READY_MAGIC, TICKS};
+    use crate::error::LoadError;
+    use crate::Emu;
+
+    /// Corpus versions the current loader must still load
+    const LOADABLE: &[u32] = &[10, 11, 12, 13];
+
+    /// Checked-in corpus, one entry per format version
+    const CORPUS: &[(u32, &[u8])] = &[
+        (10, include_bytes!("../tests/state_corpus/v10.state")),
+        (11, include_bytes!("../tests/state_corpus/v11.state")),
+        (12, include_bytes!("../tests/state_corpus/v12.state")),
+        (13, include_bytes!("../tests/state_corpus/v13.state")),
+    ];
+
+    /// Cycles the test ROM runs before the corpus state is captured
+    const CAPTURE_CYCLES: u32 = 3_000_000;
+
+    /// Encoding: a sequence of tagged records
+    /// - `0, len:u32, bytes[len]`: literal bytes
+    /// - `1, period:u8, len:u32`: copy `len` bytes from `period` bytes back
+    fn decode(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut pos = 0;
+        let read_len = |pos: usize| u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        while pos < data.len() {
+            match data[pos] {
+                0 => {
+                    let len = read_len(pos + 1);
+                    out.extend_from_slice(&data[pos + 5..pos + 5 + len]);
+                    pos += 5 + len;
+                }
+                1 => {
+                    let period = data[pos + 1] as usize;
+                    let len = read_len(pos + 2);
+                    for _ in 0..len {
+                        out.push(out[out.len() - period]);
+                    }
+                    pos += 6;
+                }
+                tag => panic!("bad corpus record tag {tag} at {pos}"),
+            }
+        }
+        out
+    }
+
+    fn encode(data: &[u8]) -> Vec<u8> {
+        const MIN_REPEAT: usize = 16;
+        let mut out = Vec::new();
+        let mut literal_start = 0;
+        let mut i = 0;
+        let flush = |out: &mut Vec<u8>, bytes: &[u8]| {
+            if !bytes.is_empty() {
+                out.push(0);
+                out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+                out.extend_from_slice(bytes);
+            }
+        };
+        while i < data.len() {
+            let best = (1..=4usize)
+                .filter(|&p| i >= p)
+                .map(|p| (p, data[i..].iter().zip(&data[i - p..]).take_while(|(a, b)| a == b).count()))
+                .max_by_key(|&(_, len)| len);
+            match best {
+                Some((period, len)) if len >= MIN_REPEAT => {
+                    flush(&mut out, &data[literal_start..i]);
+                    out.push(1);
+                    out.push(period as u8);
+                    out.extend_from_slice(&(len as u32).to_le_bytes());
+                    i += len;
+                    literal_start = i;
+                }
+                _ => i += 1,
+            }
+        }
+        flush(&mut out, &data[literal_start..]);
+        out
+    }
+
+    /// Load the ROM image embedded in a state (flash is the final section
+    /// in every format so far), so the ROM hash matches even after the test
+    /// ROM itself changes.
+    fn emu_for_state(state: &[u8]) -> Emu {
+        let mut emu = Emu::new();
+        emu.load_rom(&state[state.len() - FLASH_SIZE..]).unwrap();
+        emu
+    }
+
+    fn capture_current_state() -> Vec<u8> {
+        let mut emu = Emu::new();
+        emu.load_rom(&test_rom::build()).unwrap();
+        emu.power_on();
+        emu.run_cycles(CAPTURE_CYCLES);
+        let mut state = vec![0u8; emu.save_state_size()];
+        let size = emu.save_state(&mut state).unwrap();
+        state.truncate(size);
+        state
+    }
+
+    #[test]
+    fn test_encoding_roundtrip() {
+        let mut data = vec![0xFF; 1000];
+        data.extend((0..300).map(|i| (i % 2) as u8 * 0x1F));
+        data.extend((0..50).map(|i| i as u8));
+        assert_eq!(decode(&encode(&data)), data);
+        assert!(encode(&data).len() < 100);
+    }
+
+    #[test]
+    fn test_corpus_covers_current_version() {
+        assert!(
+            CORPUS.iter().any(|&(v, _)| v == Emu::STATE_VERSION),
+            "no corpus state for STATE_VERSION {}: run regenerate_state_corpus and add it to CORPUS/LOADABLE",
+            Emu::STATE_VERSION
+        );
+    }
+
+    #[test]
+    fn test_compat_matrix() {
+        assert!(LOADABLE.contains(&Emu::STATE_VERSION), "current STATE_VERSION must be loadable");
+        for &version in LOADABLE {
+            assert!(CORPUS.iter().any(|&(v, _)| v == version), "LOADABLE lists v{version} without a corpus file");
+        }
+
+        for &(version, encoded) in CORPUS {
+            let state = decode(encoded);
+            assert_eq!(u32::from_le_bytes(state[4..8].try_into().unwrap()), version);
+
+            let mut emu = emu_for_state(&state);
+            if LOADABLE.contains(&version) {
+                assert_eq!(emu.load_state(&state), Ok(()), "v{version} must load");
+                assert_eq!(emu.peek_byte(READY), READY_MAGIC, "v{version}: RAM restored");
+                let ticks = read24(&emu, TICKS);
+                emu.run_cycles(2_000_000);
+                assert!(read24(&emu, TICKS) > ticks, "v{version}: timers resume after load");
+            } else {
+                assert_eq!(
+                    emu.load_state(&state),
+                    Err(LoadError::VersionMismatch { expected: Emu::STATE_VERSION, found: version }),
+                    "v{version} must be rejected"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_current_capture_matches_corpus_layout() {
+        // A format change without a version bump shows up as a size change
+        let state = capture_current_state();
+        let (_, encoded) = CORPUS.iter().find(|&&(v, _)| v == Emu::STATE_VERSION).unwrap();
+        assert_eq!(
+            state.len(),
+            decode(encoded).len(),
+            "state size changed without bumping STATE_VERSION"
+        );
+    }
+
+    #[test]
+    #[ignore = "writes tests/state_corpus"]
+    fn regenerate_state_corpus() {
+        let state = capture_current_state();
+        let path = format!(
+            "{}/tests/state_corpus/v{}.state",
+            env!("CARGO_MANIFEST_DIR"),
+            Emu::STATE_VERSION
+        );
+        std::fs::write(&path, encode(&state)).unwrap();
+        println!("wrote {path}");
+    }
+}
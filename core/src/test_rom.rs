@@ -0,0 +1,802 @@
+//! Built-in open test ROM
+//!
+//! A tiny eZ80 program assembled in Rust that stands in for TI firmware.
+//! The image is not generated at build time: `build` and `build_demo`
+//! assemble it when called (well under a millisecond), so there is no
+//! build script and no ROM file in the tree.
+//! It exercises the LCD controller, keypad scanner, general purpose timers
+//! and interrupt controller, so `load_rom`-dependent integration tests and
+//! the web demo can run without distributing a real ROM image.
+//!
+//! The program reports what it observes through a small mailbox in RAM:
+//!
+//! | Address    | Size | Contents                                      |
+//! |------------|------|-----------------------------------------------|
+//! | `READY`    | 1    | `READY_MAGIC` once hardware setup is finished |
+//! | `TICKS`    | 3    | Timer 1 interrupts serviced                   |
+//! | `LOOPS`    | 3    | Main loop iterations (one per wake from HALT) |
+//! | `KEYS`     | 16   | Copy of keypad data registers (8 rows x 2)    |
+//!
+//! Boot flow: DI, switch to ADL, set up SP/IM 1, point UPBASE at VRAM in
+//! 16bpp mode and fill the screen, arm timer 1 (32kHz, auto-reload) and a
+//! continuous keypad scan, then EI and loop on HALT.
+//!
+//! `build_demo` assembles a variant for ROM-less demo mode
+//! (`Emu::load_demo`): it cycles full-screen test patterns and lights a
+//! cell in a strip along the bottom for each key held, so a frontend can be
+//! developed and shown before the user supplies a TI ROM.
+
+use std::collections::HashMap;
+
+/// Mailbox byte set to `READY_MAGIC` once setup has completed
+pub const READY: u32 = 0xD00000;
+/// 24-bit count of timer interrupts serviced by the ISR
+pub const TICKS: u32 = 0xD00001;
+/// 24-bit count of main loop iterations
+pub const LOOPS: u32 = 0xD00004;
+/// 16-byte copy of the keypad data registers, refreshed every loop
+pub const KEYS: u32 = 0xD00007;
+/// Value written to `READY` after setup
+pub const READY_MAGIC: u8 = 0xA5;
+/// RGB565 color the screen is filled with during setup
+pub const FILL_COLOR: u16 = 0x001F;
+
+/// Base of the 16bpp framebuffer the ROM draws into
+const VRAM: u32 = 0xD40000;
+const VRAM_BYTES: u32 = 320 * 240 * 2;
+/// Initial stack pointer (same as TI-OS)
+const STACK_TOP: u32 = 0xD1A87E;
+
+const CONTROL_FLAGS: u32 = 0xE00005;
+const LCD_UPBASE: u32 = 0xE30010;
+const LCD_CONTROL: u32 = 0xE30018;
+/// LCD enable | 16bpp 5:6:5 | TFT | BGR | power (TI-OS value)
+const LCD_CONTROL_VALUE: u32 = 0x00092D;
+const INT_ENABLE: u32 = 0xF00004;
+const INT_ACK: u32 = 0xF00008;
+const TIMER1_COUNTER: u32 = 0xF20000;
+const TIMER1_RESET: u32 = 0xF20004;
+const TIMER_CONTROL: u32 = 0xF20030;
+const TIMER_STATUS: u32 = 0xF20034;
+const TIMER_MASK: u32 = 0xF20038;
+/// ~100Hz from the 32kHz clock
+const TIMER1_RELOAD: u32 = 327;
+const KEYPAD_CONTROL: u32 = 0xF50000;
+const KEYPAD_STATUS: u32 = 0xF50008;
+const KEYPAD_ENABLE: u32 = 0xF5000C;
+const KEYPAD_DATA: u32 = 0xF50010;
+/// Mode 3 (continuous scan) | rowWait 0x200 | scanWait 0x10
+const KEYPAD_CONTROL_VALUE: u32 = 0x100803;
+
+/// LCD timing registers 0-2 and the TI-OS values (~63.5 Hz at 6 MHz)
+const LCD_TIMING: u32 = 0xE30000;
+const LCD_TIMING_VALUES: [u32; 3] = [0x1F0A0338, 0x0402093F, 0x00EF7802];
+const ROW_BYTES: u32 = 320 * 2;
+/// Demo mailbox, after `KEYS`: pattern index and the tick to switch at
+const DEMO_PATTERN: u32 = 0xD00017;
+const DEMO_NEXT: u32 = 0xD00018;
+/// Timer 1 interrupts per second (32768 / TIMER1_RELOAD, rounded)
+const TICKS_PER_SECOND: u32 = 100;
+/// White, yellow, cyan, green, magenta, red, blue, black
+const BAR_COLORS: [u16; 8] = [0xFFFF, 0xFFE0, 0x07FF, 0x07E0, 0xF81F, 0xF800, 0x001F, 0x0000];
+/// Key strip: bottom 8 rows, 56 cells of 4+1 pixels from x = 20
+const KEY_STRIP_ROW: u32 = 232;
+const KEY_STRIP_ROWS: u32 = 8;
+const KEY_STRIP_X: u32 = 20;
+const KEY_UP_COLOR: u16 = 0x4208;
+const KEY_DOWN_COLOR: u16 = 0xFFE0;
+
+/// What the demo ROM shows (`build_demo`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DemoConfig {
+    /// Patterns to cycle through, a mask of `BARS`, `GRAY`, `CHECKER`
+    pub patterns: u32,
+    /// Seconds each pattern stays up, 1-60
+    pub seconds: u32,
+}
+
+impl DemoConfig {
+    /// Eight vertical color bars
+    pub const BARS: u32 = 1 << 0;
+    /// Eight horizontal gray bands, black to white
+    pub const GRAY: u32 = 1 << 1;
+    /// 40-pixel black and white checkerboard
+    pub const CHECKER: u32 = 1 << 2;
+    pub const ALL_PATTERNS: u32 = Self::BARS | Self::GRAY | Self::CHECKER;
+    pub const DEFAULT_SECONDS: u32 = 3;
+    pub const MAX_SECONDS: u32 = 60;
+
+    /// Config from C-style arguments: 0 picks the default for either field.
+    /// None if `patterns` has unknown bits or `seconds` is over the max.
+    pub fn new(patterns: u32, seconds: u32) -> Option<Self> {
+        if patterns & !Self::ALL_PATTERNS != 0 || seconds > Self::MAX_SECONDS {
+            return None;
+        }
+        Some(Self {
+            patterns: if patterns == 0 { Self::ALL_PATTERNS } else { patterns },
+            seconds: if seconds == 0 { Self::DEFAULT_SECONDS } else { seconds },
+        })
+    }
+}
+
+impl Default for DemoConfig {
+    fn default() -> Self {
+        Self { patterns: Self::ALL_PATTERNS, seconds: Self::DEFAULT_SECONDS }
+    }
+}
+
+/// Minimal eZ80 assembler: only the encodings the test ROM needs.
+/// All multi-byte operands are emitted as 24-bit ADL immediates.
+struct Asm {
+    code: Vec<u8>,
+    labels: HashMap<&'static str, u32>,
+    /// (offset, label, relative) patch sites resolved in `finish`
+    fixups: Vec<(usize, &'static str, bool)>,
+}
+
+impl Asm {
+    fn new() -> Self {
+        Self { code: Vec::new(), labels: HashMap::new(), fixups: Vec::new() }
+    }
+
+    fn here(&self) -> u32 {
+        self.code.len() as u32
+    }
+
+    fn org(&mut self, addr: u32) {
+        assert!(addr >= self.here(), "org 0x{:06X} overlaps existing code", addr);
+        self.code.resize(addr as usize, 0xFF);
+    }
+
+    fn label(&mut self, name: &'static str) {
+        let prev = self.labels.insert(name, self.here());
+        assert!(prev.is_none(), "duplicate label {}", name);
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+
+    fn imm24(&mut self, value: u32) {
+        self.bytes(&[value as u8, (value >> 8) as u8, (value >> 16) as u8]);
+    }
+
+    fn op_imm24(&mut self, opcode: &[u8], value: u32) {
+        self.bytes(opcode);
+        self.imm24(value);
+    }
+
+    fn op_label(&mut self, opcode: &[u8], label: &'static str) {
+        self.bytes(opcode);
+        self.fixups.push((self.code.len(), label, false));
+        self.imm24(0);
+    }
+
+    fn jr(&mut self, opcode: u8, label: &'static str) {
+        self.bytes(&[opcode]);
+        self.fixups.push((self.code.len(), label, true));
+        self.bytes(&[0]);
+    }
+
+    /// LD A,n ; LD (addr),A
+    fn store8(&mut self, addr: u32, value: u8) {
+        self.bytes(&[0x3E, value]);
+        self.op_imm24(&[0x32], addr);
+    }
+
+    /// LD HL,nn ; LD (addr),HL
+    fn store24(&mut self, addr: u32, value: u32) {
+        self.op_imm24(&[0x21], value);
+        self.op_imm24(&[0x22], addr);
+    }
+
+    /// LD HL,(addr) ; INC HL ; LD (addr),HL
+    fn inc24(&mut self, addr: u32) {
+        self.op_imm24(&[0x2A], addr);
+        self.bytes(&[0x23]);
+        self.op_imm24(&[0x22], addr);
+    }
+
+    /// LD HL,src ; LD DE,dst ; LD BC,len ; LDIR
+    fn ldir(&mut self, src: u32, dst: u32, len: u32) {
+        self.op_imm24(&[0x21], src);
+        self.op_imm24(&[0x11], dst);
+        self.op_imm24(&[0x01], len);
+        self.bytes(&[0xED, 0xB0]);
+    }
+
+    /// Seed one RGB565 pixel at `addr`, then smear it over `bytes` with LDIR
+    fn fill16(&mut self, addr: u32, color: u16, bytes: u32) {
+        self.op_imm24(&[0x21], addr); // LD HL,addr
+        self.bytes(&[0x36, color as u8, 0x23]); // LD (HL),lo ; INC HL
+        self.bytes(&[0x36, (color >> 8) as u8]); // LD (HL),hi
+        if bytes > 2 {
+            self.ldir(addr, addr + 2, bytes - 2);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        for (offset, label, relative) in std::mem::take(&mut self.fixups) {
+            let target = *self.labels.get(label)
+                .unwrap_or_else(|| panic!("undefined label {}", label));
+            if relative {
+                let disp = target as i64 - (offset as i64 + 1);
+                assert!((-128..=127).contains(&disp), "JR to {} out of range", label);
+                self.code[offset] = disp as i8 as u8;
+            } else {
+                self.code[offset] = target as u8;
+                self.code[offset + 1] = (target >> 8) as u8;
+                self.code[offset + 2] = (target >> 16) as u8;
+            }
+        }
+        self.code
+    }
+}
+
+/// Where tests that need real firmware look for the user's ROM: the core
+/// directory and the repository root
+#[cfg(test)]
+const REAL_ROM_PATHS: [&str; 3] = ["TI-84 CE.rom", "../TI-84 CE.rom", "../../TI-84 CE.rom"];
+
+/// The user's TI ROM for tests that need real firmware, if one is present
+/// (ROM images are not distributed with the emulator)
+#[cfg(test)]
+pub fn try_load_real_rom() -> Option<Vec<u8>> {
+    REAL_ROM_PATHS
+        .iter()
+        .find(|path| std::path::Path::new(path).exists())
+        .and_then(|path| std::fs::read(path).ok())
+}
+
+/// Read a 24-bit mailbox counter (`TICKS`, `LOOPS`) without bus side effects
+#[cfg(test)]
+pub fn read24(emu: &crate::Emu, addr: u32) -> u32 {
+    emu.peek_byte(addr) as u32
+        | (emu.peek_byte(addr + 1) as u32) << 8
+        | (emu.peek_byte(addr + 2) as u32) << 16
+}
+
+/// Assemble the test ROM image.
+///
+/// The result is small (well under 1KB); `Emu::load_rom` pads the rest of
+/// flash with 0xFF.
+pub fn build() -> Vec<u8> {
+    let mut a = Asm::new();
+    setup(&mut a, false);
+
+    a.label("main");
+    a.bytes(&[0x76]); // HALT
+    a.inc24(LOOPS);
+    a.ldir(KEYPAD_DATA, KEYS, 16);
+    // Show the tick count in the top-left pixel
+    a.op_imm24(&[0x3A], TICKS); // LD A,(TICKS)
+    a.op_imm24(&[0x32], VRAM); // LD (VRAM),A
+    a.jr(0x18, "main"); // JR main
+
+    isr(&mut a);
+    a.finish()
+}
+
+/// Assemble the demo ROM for `config` (see `DemoConfig`).
+///
+/// Same setup, ISR and mailbox as `build`, plus TI-OS panel timing so
+/// frames pace like real hardware. The main loop redraws the key strip on
+/// every wake and switches pattern every `config.seconds` of timer ticks.
+pub fn build_demo(config: DemoConfig) -> Vec<u8> {
+    let patterns: Vec<(u32, &'static str)> = [
+        (DemoConfig::BARS, "pattern_bars"),
+        (DemoConfig::GRAY, "pattern_gray"),
+        (DemoConfig::CHECKER, "pattern_checker"),
+    ]
+    .into_iter()
+    .filter(|&(bit, _)| config.patterns & bit != 0)
+    .collect();
+    assert!(!patterns.is_empty(), "demo needs at least one pattern");
+    let interval = config.seconds * TICKS_PER_SECOND;
+
+    let mut a = Asm::new();
+    setup(&mut a, true);
+    a.store8(DEMO_PATTERN, 0);
+    a.store24(DEMO_NEXT, interval);
+    a.op_label(&[0xCD], "draw_pattern"); // CALL draw_pattern
+
+    a.label("main");
+    a.bytes(&[0x76]); // HALT
+    a.inc24(LOOPS);
+    a.ldir(KEYPAD_DATA, KEYS, 16);
+    a.op_label(&[0xCD], "draw_keys"); // CALL draw_keys
+    // Wait until TICKS reaches DEMO_NEXT
+    a.op_imm24(&[0x2A], TICKS); // LD HL,(TICKS)
+    a.op_imm24(&[0xED, 0x5B], DEMO_NEXT); // LD DE,(DEMO_NEXT)
+    a.bytes(&[0xB7, 0xED, 0x52]); // OR A ; SBC HL,DE
+    a.jr(0x38, "main"); // JR C,main
+    a.op_imm24(&[0x2A], DEMO_NEXT); // LD HL,(DEMO_NEXT)
+    a.op_imm24(&[0x11], interval); // LD DE,interval
+    a.bytes(&[0x19]); // ADD HL,DE
+    a.op_imm24(&[0x22], DEMO_NEXT); // LD (DEMO_NEXT),HL
+    // Next pattern, wrapping to the first
+    a.op_imm24(&[0x3A], DEMO_PATTERN); // LD A,(DEMO_PATTERN)
+    a.bytes(&[0x3C, 0xFE, patterns.len() as u8]); // INC A ; CP n
+    a.jr(0x38, "next_pattern"); // JR C,next_pattern
+    a.bytes(&[0xAF]); // XOR A
+    a.label("next_pattern");
+    a.op_imm24(&[0x32], DEMO_PATTERN); // LD (DEMO_PATTERN),A
+    a.op_label(&[0xCD], "draw_pattern"); // CALL draw_pattern
+    a.jr(0x18, "main"); // JR main
+
+    isr(&mut a);
+
+    // Tail-jump to the routine for DEMO_PATTERN
+    a.label("draw_pattern");
+    a.op_imm24(&[0x3A], DEMO_PATTERN); // LD A,(DEMO_PATTERN)
+    for (index, &(_, routine)) in patterns.iter().enumerate() {
+        a.bytes(&[0xFE, index as u8]); // CP index
+        a.op_label(&[0xCA], routine); // JP Z,routine
+    }
+    a.bytes(&[0xC9]); // RET
+
+    for &(bit, routine) in &patterns {
+        a.label(routine);
+        match bit {
+            DemoConfig::BARS => {
+                // Eight vertical bars on row 0, copied down the screen
+                for (i, &color) in BAR_COLORS.iter().enumerate() {
+                    a.fill16(VRAM + i as u32 * 80, color, 80);
+                }
+                a.ldir(VRAM, VRAM + ROW_BYTES, ROW_BYTES * 239);
+            }
+            DemoConfig::GRAY => {
+                // Eight 30-row bands from black to white
+                for i in 0..8u32 {
+                    let level = i * 255 / 7;
+                    let color = ((level >> 3) << 11 | (level >> 2) << 5 | level >> 3) as u16;
+                    a.fill16(VRAM + i * 30 * ROW_BYTES, color, 30 * ROW_BYTES);
+                }
+            }
+            _ => {
+                // Two 40-row strips of alternating squares, then repeated
+                for strip in 0..2u32 {
+                    let row = VRAM + strip * 40 * ROW_BYTES;
+                    for i in 0..8u32 {
+                        let color = if (i + strip) % 2 == 0 { 0xFFFF } else { 0x0000 };
+                        a.fill16(row + i * 80, color, 80);
+                    }
+                    a.ldir(row, row + ROW_BYTES, ROW_BYTES * 39);
+                }
+                a.ldir(VRAM, VRAM + 80 * ROW_BYTES, ROW_BYTES * 160);
+            }
+        }
+        a.bytes(&[0xC9]); // RET
+    }
+
+    // One cell per keypad data bit (groups 1-7, bit 0 first), lit while
+    // the key is down
+    let strip = VRAM + KEY_STRIP_ROW * ROW_BYTES;
+    a.label("draw_keys");
+    a.fill16(strip, 0x0000, ROW_BYTES);
+    a.op_imm24(&[0xDD, 0x21], KEYS + 2); // LD IX,KEYS+2
+    a.op_imm24(&[0x21], strip + KEY_STRIP_X * 2); // LD HL,first cell
+    a.bytes(&[0x0E, 7]); // LD C,7
+    a.label("key_group");
+    a.bytes(&[0xDD, 0x7E, 0x00, 0x06, 8]); // LD A,(IX+0) ; LD B,8
+    a.label("key_bit");
+    a.op_imm24(&[0x11], KEY_UP_COLOR as u32); // LD DE,up color
+    a.bytes(&[0x0F]); // RRCA
+    a.jr(0x30, "key_cell"); // JR NC,key_cell
+    a.op_imm24(&[0x11], KEY_DOWN_COLOR as u32); // LD DE,down color
+    a.label("key_cell");
+    for _ in 0..4 {
+        a.bytes(&[0x73, 0x23, 0x72, 0x23]); // LD (HL),E ; INC HL ; LD (HL),D ; INC HL
+    }
+    a.bytes(&[0x23, 0x23]); // skip the gap pixel
+    a.jr(0x10, "key_bit"); // DJNZ key_bit
+    a.bytes(&[0xDD, 0x23, 0xDD, 0x23, 0x0D]); // INC IX ; INC IX ; DEC C
+    a.jr(0x20, "key_group"); // JR NZ,key_group
+    a.ldir(strip, strip + ROW_BYTES, ROW_BYTES * (KEY_STRIP_ROWS - 1));
+    a.bytes(&[0xC9]); // RET
+
+    a.finish()
+}
+
+/// Vectors and hardware setup shared by both ROMs; ends with EI
+fn setup(a: &mut Asm, panel_timing: bool) {
+    // Reset vector: runs in Z80 mode, so switch to ADL with JP.LIL
+    a.bytes(&[0xF3]); // DI
+    a.op_label(&[0x5B, 0xC3], "start"); // JP.LIL start
+
+    // IM 1 vector (entered in ADL mode)
+    a.org(0x38);
+    a.op_label(&[0xC3], "isr");
+
+    // NMI vector: nothing to report, just return
+    a.org(0x66);
+    a.bytes(&[0xED, 0x45]); // RETN
+
+    a.org(0x100);
+    a.label("start");
+    a.op_imm24(&[0x31], STACK_TOP); // LD SP,nn
+    a.bytes(&[0xED, 0x56]); // IM 1
+
+    // Clear the mailbox
+    a.store8(READY, 0);
+    a.store24(TICKS, 0);
+    a.store24(LOOPS, 0);
+
+    // LCD: 16bpp at VRAM, panel enabled
+    if panel_timing {
+        for (i, &word) in LCD_TIMING_VALUES.iter().enumerate() {
+            let addr = LCD_TIMING + i as u32 * 4;
+            a.store24(addr, word & 0xFFFFFF);
+            a.store8(addr + 3, (word >> 24) as u8);
+        }
+    }
+    a.store24(LCD_UPBASE, VRAM);
+    a.store24(LCD_CONTROL, LCD_CONTROL_VALUE);
+    a.store8(CONTROL_FLAGS, 0x10);
+
+    a.fill16(VRAM, FILL_COLOR, VRAM_BYTES);
+
+    // Timer 1: count down from TIMER1_RELOAD on the 32kHz clock, reload on zero
+    a.store24(TIMER1_COUNTER, TIMER1_RELOAD);
+    a.store24(TIMER1_RESET, TIMER1_RELOAD);
+    a.store8(TIMER_MASK, 0x04); // interrupt on timer 1 zero
+    a.store8(TIMER_CONTROL, 0x07); // enable | 32kHz | reload
+
+    // Keypad: continuous scan, interrupt when data changes
+    a.store8(KEYPAD_ENABLE, 0x02);
+    a.store24(KEYPAD_CONTROL, KEYPAD_CONTROL_VALUE);
+
+    // Interrupt controller: timer 1 + keypad
+    a.store24(INT_ENABLE, (1 << 1) | (1 << 10));
+
+    a.store8(READY, READY_MAGIC);
+    a.bytes(&[0xFB]); // EI
+}
+
+/// Counts timer 1 interrupts in `TICKS` and acknowledges the keypad
+fn isr(a: &mut Asm) {
+    a.label("isr");
+    a.bytes(&[0xF5, 0xE5]); // PUSH AF ; PUSH HL
+    a.op_imm24(&[0x3A], TIMER_STATUS); // LD A,(TIMER_STATUS)
+    a.bytes(&[0xE6, 0x07]); // AND 7
+    a.jr(0x28, "isr_keypad"); // JR Z,isr_keypad
+    a.op_imm24(&[0x32], TIMER_STATUS); // LD (TIMER_STATUS),A (write-to-clear)
+    a.inc24(TICKS);
+    a.label("isr_keypad");
+    a.op_imm24(&[0x3A], KEYPAD_STATUS); // LD A,(KEYPAD_STATUS)
+    a.op_imm24(&[0x32], KEYPAD_STATUS); // LD (KEYPAD_STATUS),A (write-1-to-clear)
+    a.store24(INT_ACK, (1 << 1) | (1 << 10));
+    a.bytes(&[0xE1, 0xF1]); // POP HL ; POP AF
+    a.bytes(&[0xFB, 0xED, 0x4D]); // EI ; RETI
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Emu, EmuError};
+
+    fn boot(cycles: u32) -> Emu {
+        let mut emu = Emu::new();
+        emu.load_rom(&build()).expect("test ROM should load");
+        emu.power_on();
+        emu.run_cycles(cycles);
+        emu
+    }
+
+    #[test]
+    fn test_build_layout() {
+        let rom = build();
+        assert_eq!(rom[0], 0xF3, "reset vector starts with DI");
+        assert_eq!(&rom[1..3], &[0x5B, 0xC3], "then JP.LIL");
+        assert_eq!(rom[0x38], 0xC3, "IM 1 vector is a JP");
+        assert_eq!(&rom[0x66..0x68], &[0xED, 0x45], "NMI vector is RETN");
+        assert!(rom.len() < 0x400);
+        assert_eq!(rom, build(), "assembly is deterministic");
+    }
+
+    #[test]
+    fn test_boots_and_configures_lcd() {
+        let mut emu = boot(5_000_000);
+        assert_eq!(emu.peek_byte(READY), READY_MAGIC);
+        assert!(emu.is_lcd_on());
+        assert_eq!(emu.lcd_snapshot().upbase, VRAM);
+
+        emu.render_frame();
+        let expected = emu.framebuffer_data()[320 * 240 - 1];
+        assert_ne!(expected, 0xFF000000, "screen should be filled");
+        assert!(emu.framebuffer_data()[320..].iter().all(|&p| p == expected));
+    }
+
+    #[test]
+    fn test_frame_counters_track_fetches() {
+        let mut emu = boot(5_000_000);
+        // TI-OS panel timing (~63.5 Hz); the ROM leaves it zeroed
+        let timing = [0x1F0A0338u32, 0x0402093F, 0x00EF7802];
+        let bytes: Vec<u8> = timing.iter().flat_map(|t| t.to_le_bytes()).collect();
+        emu.write_block(0xE30000, &bytes, true).unwrap();
+        emu.run_cycles(200_000);
+        assert!(emu.frame_counters().generated > 0, "LCD should be scanning out");
+
+        // One fetch takes the newest frame and drops the ones before it
+        emu.note_frame_fetched();
+        emu.note_frame_fetched();
+        let counters = emu.frame_counters();
+        assert_eq!(counters.fetched, 1);
+        assert_eq!(counters.dropped, counters.generated - 1);
+
+        // Fetching every 1/60 s (6 MHz) keeps up with the panel
+        for _ in 0..30 {
+            emu.run_cycles(100_000);
+            emu.note_frame_fetched();
+        }
+        let after = emu.frame_counters();
+        assert!(after.generated >= counters.generated + 30);
+        assert_eq!(after.fetched + after.dropped, after.generated);
+        assert!(after.dropped - counters.dropped <= 2);
+
+        // Running 10 frames between fetches drops 9 of them
+        emu.run_cycles(945_000);
+        emu.note_frame_fetched();
+        let skipped = emu.frame_counters();
+        assert!((8..=10).contains(&(skipped.dropped - after.dropped)), "{:?}", skipped);
+    }
+
+    #[test]
+    fn test_run_frame_stops_at_vsync() {
+        use crate::emu::StopReason;
+
+        let mut emu = boot(5_000_000);
+        let timing = [0x1F0A0338u32, 0x0402093F, 0x00EF7802];
+        let bytes: Vec<u8> = timing.iter().flat_map(|t| t.to_le_bytes()).collect();
+        emu.write_block(0xE30000, &bytes, true).unwrap();
+        emu.run_cycles(200_000);
+        // Ends partway into a frame
+        assert!(emu.run_frame() < 94_000);
+
+        // Then one whole panel frame (~63.5 Hz at 6 MHz) per call
+        for _ in 0..3 {
+            let generated = emu.frame_counters().generated;
+            let cycles = emu.run_frame();
+            assert_eq!(emu.last_stop_reason(), StopReason::VSync);
+            assert_eq!(emu.frame_counters().generated, generated + 1);
+            assert!((90_000..100_000).contains(&cycles), "{}", cycles);
+        }
+    }
+
+    extern "C" fn collect_frame(user: *mut std::os::raw::c_void, frame: u64, dirty: u32) {
+        unsafe { &mut *(user as *mut Vec<(u64, u32)>) }.push((frame, dirty));
+    }
+
+    #[test]
+    fn test_frame_callback_reports_dirty_frames() {
+        let mut emu = boot(5_000_000);
+        let timing = [0x1F0A0338u32, 0x0402093F, 0x00EF7802];
+        let bytes: Vec<u8> = timing.iter().flat_map(|t| t.to_le_bytes()).collect();
+        emu.write_block(0xE30000, &bytes, true).unwrap();
+        // Mask all interrupts so the main loop stops updating the tick pixel
+        emu.write_block(INT_ENABLE, &[0, 0, 0], true).unwrap();
+
+        let mut frames: Vec<(u64, u32)> = Vec::new();
+        emu.set_frame_callback(Some(collect_frame), &mut frames as *mut _ as *mut std::os::raw::c_void);
+        for _ in 0..3 {
+            emu.run_frame();
+        }
+        // The first frame seen is dirty, the static screen after it is not
+        let first = emu.frame_counters().generated - 2;
+        assert_eq!(frames, [(first, 1), (first + 1, 0), (first + 2, 0)]);
+
+        frames.clear();
+        emu.write_block(VRAM + 100, &[0xFF, 0xFF], false).unwrap();
+        emu.run_frame();
+        emu.run_frame();
+        assert_eq!(frames.iter().map(|&(_, dirty)| dirty).collect::<Vec<_>>(), [1, 0]);
+
+        emu.set_frame_callback(None, std::ptr::null_mut());
+        emu.run_frame();
+        assert_eq!(frames.len(), 2);
+    }
+
+    #[test]
+    fn test_find_last_change_replays_from_slot() {
+        let mut emu = boot(5_000_000);
+        emu.slot_save(0).unwrap();
+        emu.run_cycles(3_000_000);
+        let now = emu.total_cycles();
+        let ticks = emu.peek_byte(TICKS);
+
+        // Never written since the slot: state is left alone
+        assert_eq!(emu.find_last_change(VRAM + VRAM_BYTES - 1), Ok(None));
+        assert_eq!(emu.total_cycles(), now);
+
+        // The ISR's last increment of the tick counter; the present stays,
+        // and nothing collected during the replay
+        emu.set_heatmap_enabled(true);
+        emu.set_opcode_coverage_enabled(true);
+        emu.start_macro_recording();
+        let coverage = emu.opcode_coverage().unwrap().report();
+        let heatmap = |emu: &Emu| {
+            let mut out = vec![0u8; crate::heatmap::EXPORT_SIZE];
+            emu.export_heatmap(&mut out).unwrap();
+            out
+        };
+        let counts = heatmap(&emu);
+        let change = emu.find_last_change(TICKS).unwrap().expect("ticks changed");
+        assert_eq!((change.addr, change.slot), (TICKS, 0));
+        assert_eq!(change.new_value, ticks);
+        assert_eq!(change.old_value, ticks.wrapping_sub(1));
+        assert!(change.cycles < now);
+        assert_eq!(emu.total_cycles(), now);
+        assert_eq!(emu.opcode_coverage().unwrap().report(), coverage);
+        assert!(heatmap(&emu) == counts);
+        assert!(emu.stop_macro_recording().is_some(), "recording kept");
+
+        // Stopping at the write keeps the present in a slot
+        assert_eq!(emu.rewind_to_last_change(TICKS, 1), Ok(Some(change)));
+        assert!(emu.total_cycles() < now);
+        assert_eq!(emu.peek_byte(TICKS), ticks);
+        assert_eq!(emu.last_watchpoint().map(|hit| hit.pc), Some(change.pc));
+        assert!(emu.watchpoints().is_empty(), "replay watchpoint removed");
+        emu.slot_load(1).unwrap();
+        assert_eq!(emu.total_cycles(), now);
+        emu.slot_clear(1).unwrap();
+
+        emu.slot_clear(0).unwrap();
+        assert_eq!(emu.find_last_change(TICKS), Err(EmuError::NoSnapshot));
+    }
+
+    #[test]
+    fn test_timer_interrupts_wake_main_loop() {
+        let mut emu = boot(5_000_000);
+        let ticks = read24(&emu, TICKS);
+        let loops = read24(&emu, LOOPS);
+        assert!(ticks > 0, "timer ISR should have run");
+
+        emu.run_cycles(5_000_000);
+        assert!(read24(&emu, TICKS) > ticks);
+        assert!(read24(&emu, LOOPS) > loops);
+    }
+
+    #[test]
+    fn test_coarse_granularity_keeps_timing() {
+        use crate::RunGranularity;
+
+        let mut results = Vec::new();
+        for granularity in [RunGranularity::Instruction, RunGranularity::Cycles(2_000), RunGranularity::EventDriven] {
+            let mut emu = boot(5_000_000);
+            emu.set_run_granularity(granularity);
+            let timing = [0x1F0A0338u32, 0x0402093F, 0x00EF7802];
+            let bytes: Vec<u8> = timing.iter().flat_map(|t| t.to_le_bytes()).collect();
+            emu.write_block(0xE30000, &bytes, true).unwrap();
+            let ticks = read24(&emu, TICKS);
+            let frames = emu.frame_counters().generated;
+
+            assert_eq!(emu.run_cycles(3_000_000), 3_000_000, "{:?}", granularity);
+            assert!(read24(&emu, TICKS) > ticks, "{:?}: timer ISR still runs", granularity);
+            results.push(emu.frame_counters().generated - frames);
+        }
+        // LCD events are scheduler-driven, so frame pacing doesn't change
+        assert!(results.iter().all(|&f| f.abs_diff(results[0]) <= 1), "{:?}", results);
+    }
+
+    #[test]
+    fn test_lookahead_matches_instruction_granularity() {
+        use crate::RunGranularity;
+
+        let states: Vec<Vec<u8>> = [RunGranularity::Instruction, RunGranularity::Lookahead]
+            .into_iter()
+            .map(|granularity| {
+                let mut emu = Emu::new();
+                emu.set_run_granularity(granularity);
+                emu.load_rom(&build()).expect("test ROM should load");
+                emu.power_on();
+                emu.run_cycles(4_000_000);
+                emu.set_key(3, 2, true);
+                emu.run_cycles(1_500_000);
+                emu.set_key(3, 2, false);
+                emu.run_cycles(1_000_000);
+                let mut state = vec![0; emu.save_state_size()];
+                emu.save_state(&mut state).unwrap();
+                state
+            })
+            .collect();
+        assert!(states[0] == states[1], "lookahead diverged from per-instruction servicing");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        use crate::cpu::Cpu;
+        use crate::peripherals::Peripherals;
+
+        let emu = boot(2_000_000);
+        let cpu = serde_json::to_string(emu.cpu()).unwrap();
+        let restored: Cpu = serde_json::from_str(&cpu).unwrap();
+        assert_eq!(restored.pc, emu.cpu().pc);
+        assert_eq!(serde_json::to_string(&restored).unwrap(), cpu);
+
+        let ports = serde_json::to_vec(emu.peripherals()).unwrap();
+        let restored: Peripherals = serde_json::from_slice(&ports).unwrap();
+        assert_eq!(restored.lcd.upbase(), emu.peripherals().lcd.upbase());
+        assert_eq!(serde_json::to_vec(&restored).unwrap(), ports);
+
+        let snapshot = serde_json::to_string(&emu.lcd_snapshot()).unwrap();
+        assert_eq!(serde_json::from_str::<crate::LcdSnapshot>(&snapshot).unwrap(), emu.lcd_snapshot());
+        assert!(serde_json::to_string(emu.scheduler()).is_ok());
+    }
+
+    #[test]
+    fn test_demo_cycles_patterns_and_echoes_keys() {
+        let config = DemoConfig::new(DemoConfig::BARS | DemoConfig::CHECKER, 1).unwrap();
+        let mut emu = Emu::new();
+        emu.load_demo(config).expect("demo ROM should load");
+        emu.power_on();
+        emu.run_cycles(3_000_000);
+        assert_eq!(emu.peek_byte(READY), READY_MAGIC);
+        let pixel = |emu: &mut Emu, x: u32, y: u32| {
+            let addr = VRAM + y * ROW_BYTES + x * 2;
+            emu.peek_byte(addr) as u16 | (emu.peek_byte(addr + 1) as u16) << 8
+        };
+        assert_eq!(emu.peek_byte(DEMO_PATTERN), 0);
+        assert_eq!([pixel(&mut emu, 0, 100), pixel(&mut emu, 50, 100)], [BAR_COLORS[0], BAR_COLORS[1]]);
+        emu.render_frame();
+        assert_eq!(emu.framebuffer_data()[100 * 320 + 5 * 40] & 0xFFFFFF, 0xFF0000, "red bar");
+        // TI-OS panel timing: ~63.5 Hz, so ~32 frames in half a second
+        assert!((28..=36).contains(&emu.frame_counters().generated));
+
+        // ENTER is group 6 bit 0: cell 40 of the strip
+        let cell = KEY_STRIP_X + 40 * 5;
+        assert_eq!(pixel(&mut emu, cell, KEY_STRIP_ROW + 4), KEY_UP_COLOR);
+        emu.set_named_key(crate::KeyName::Enter, true);
+        emu.run_cycles(500_000);
+        assert_eq!(pixel(&mut emu, cell, KEY_STRIP_ROW + 4), KEY_DOWN_COLOR);
+        assert_eq!(pixel(&mut emu, cell + 5, KEY_STRIP_ROW + 4), KEY_UP_COLOR);
+        emu.set_named_key(crate::KeyName::Enter, false);
+
+        // Checkerboard once a second of ticks has passed
+        let start = read24(&emu, TICKS);
+        while read24(&emu, TICKS) < start + 2 * TICKS_PER_SECOND {
+            emu.run_cycles(1_000_000);
+            if emu.peek_byte(DEMO_PATTERN) == 1 {
+                break;
+            }
+        }
+        assert_eq!(emu.peek_byte(DEMO_PATTERN), 1);
+        emu.run_cycles(1_000_000); // let the copy down the screen finish
+        assert_eq!([pixel(&mut emu, 0, 0), pixel(&mut emu, 40, 0), pixel(&mut emu, 0, 40)], [0xFFFF, 0, 0]);
+        assert_eq!(pixel(&mut emu, 40, 200), 0xFFFF);
+
+        assert_eq!(DemoConfig::new(0, 0), Some(DemoConfig::default()));
+        assert_eq!(DemoConfig::new(8, 0), None);
+        assert_eq!(DemoConfig::new(0, DemoConfig::MAX_SECONDS + 1), None);
+    }
+
+    #[test]
+    fn test_key_matrix_holds_taps_until_scanned() {
+        let mut emu = boot(5_000_000);
+        emu.set_key(3, 2, true);
+        assert_eq!(emu.key_matrix()[3], 1 << 2);
+        emu.set_key(3, 2, false);
+        assert_eq!(emu.key_matrix(), [0; 8]);
+        assert_eq!(emu.key_edges()[3], 1 << 2, "tap not scanned yet");
+
+        emu.run_cycles(1_000_000);
+        assert_eq!(emu.key_edges(), [0; 8]);
+
+        // ON is held in the matrix but has no scan edge
+        emu.set_on_key(true);
+        assert_eq!((emu.key_matrix()[2], emu.key_edges()[2]), (1, 0));
+    }
+
+    #[test]
+    fn test_keypad_state_reported() {
+        let mut emu = boot(5_000_000);
+        // "4" key: row 3, col 2
+        emu.set_key(3, 2, true);
+        emu.run_cycles(2_000_000);
+        assert_eq!(emu.peek_byte(KEYS + 3 * 2), 1 << 2);
+
+        emu.set_key(3, 2, false);
+        emu.run_cycles(2_000_000);
+        assert_eq!(emu.peek_byte(KEYS + 3 * 2), 0);
+    }
+}
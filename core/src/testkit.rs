@@ -0,0 +1,351 @@
+//! Test runner for assembled code snippets
+//!
+//! Formalizes the pattern the CPU tests use: place bytes in memory, set the
+//! registers, run a bare CPU and bus until HALT (or a cycle cap), then diff
+//! registers and memory against expectations. No ROM or peripherals are
+//! involved beyond what the bus provides after reset.
+//!
+//! Tests are built in Rust with `AsmTest`, or written as text for host
+//! tools and scripts (`AsmTest::parse`, `emu_testkit_run` in the C API):
+//!
+//! ```text
+//! # LD A,0x42 ; LD (0xD00000),A ; HALT
+//! pc   D00100
+//! adl  1
+//! code D00100 3E 42 32 00 00 D0 76
+//! reg  HL=D00000
+//! max  1000
+//! expect A=42 HL=D00000
+//! expect_mem D00000 42
+//! ```
+//!
+//! Values are hex. Both forms start in ADL mode at PC 0 with a 1M cycle cap
+//! unless the test says otherwise (`adl 0` for Z80 mode). Errors: -70 spec
+//! parse error.
+
+use crate::bus::Bus;
+use crate::cpu::Cpu;
+
+/// Default cycle cap when the test sets none
+pub const DEFAULT_MAX_CYCLES: u64 = 1_000_000;
+
+/// Register a test can set or check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reg {
+    A, F, B, C, D, E, H, L,
+    BC, DE, HL, IX, IY,
+    /// Stack pointer for the current mode (SPL in ADL, else SPS)
+    SP,
+    PC, I, R, MBASE,
+    /// ADL mode flag (0/1)
+    ADL,
+}
+
+impl Reg {
+    /// Parse a register name (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Reg> {
+        Some(match name.to_ascii_uppercase().as_str() {
+            "A" => Reg::A, "F" => Reg::F, "B" => Reg::B, "C" => Reg::C,
+            "D" => Reg::D, "E" => Reg::E, "H" => Reg::H, "L" => Reg::L,
+            "BC" => Reg::BC, "DE" => Reg::DE, "HL" => Reg::HL,
+            "IX" => Reg::IX, "IY" => Reg::IY, "SP" => Reg::SP, "PC" => Reg::PC,
+            "I" => Reg::I, "R" => Reg::R, "MBASE" => Reg::MBASE, "ADL" => Reg::ADL,
+            _ => return None,
+        })
+    }
+
+    pub fn get(self, cpu: &Cpu) -> u32 {
+        match self {
+            Reg::A => cpu.a as u32,
+            Reg::F => cpu.f as u32,
+            Reg::B => cpu.b() as u32,
+            Reg::C => cpu.c() as u32,
+            Reg::D => cpu.d() as u32,
+            Reg::E => cpu.e() as u32,
+            Reg::H => cpu.h() as u32,
+            Reg::L => cpu.l() as u32,
+            Reg::BC => cpu.bc,
+            Reg::DE => cpu.de,
+            Reg::HL => cpu.hl,
+            Reg::IX => cpu.ix,
+            Reg::IY => cpu.iy,
+            Reg::SP => cpu.sp(),
+            Reg::PC => cpu.pc,
+            Reg::I => cpu.i as u32,
+            Reg::R => cpu.r as u32,
+            Reg::MBASE => cpu.mbase as u32,
+            Reg::ADL => cpu.adl as u32,
+        }
+    }
+
+    pub fn set(self, cpu: &mut Cpu, value: u32) {
+        match self {
+            Reg::A => cpu.a = value as u8,
+            Reg::F => cpu.f = value as u8,
+            Reg::B => cpu.set_b(value as u8),
+            Reg::C => cpu.set_c(value as u8),
+            Reg::D => cpu.set_d(value as u8),
+            Reg::E => cpu.set_e(value as u8),
+            Reg::H => cpu.set_h(value as u8),
+            Reg::L => cpu.set_l(value as u8),
+            Reg::BC => cpu.bc = value & 0xFFFFFF,
+            Reg::DE => cpu.de = value & 0xFFFFFF,
+            Reg::HL => cpu.hl = value & 0xFFFFFF,
+            Reg::IX => cpu.ix = value & 0xFFFFFF,
+            Reg::IY => cpu.iy = value & 0xFFFFFF,
+            Reg::SP => cpu.set_sp(value & 0xFFFFFF),
+            Reg::PC => cpu.pc = value & 0xFFFFFF,
+            Reg::I => cpu.i = value as u16,
+            Reg::R => cpu.r = value as u8,
+            Reg::MBASE => cpu.mbase = value as u8,
+            Reg::ADL => {
+                cpu.adl = value != 0;
+                cpu.l = cpu.adl;
+                cpu.il = cpu.adl;
+            }
+        }
+    }
+}
+
+/// One snippet test: setup, limits and expectations
+#[derive(Debug, Clone)]
+pub struct AsmTest {
+    /// Start address (PC; 16-bit with MBASE when not in ADL mode)
+    pub pc: u32,
+    /// Start in ADL mode
+    pub adl: bool,
+    /// Bytes placed before the run (24-bit addresses)
+    pub memory: Vec<(u32, Vec<u8>)>,
+    /// Registers set before the run, after `adl` and `pc`
+    pub regs: Vec<(Reg, u32)>,
+    /// The run stops at HALT or after this many cycles
+    pub max_cycles: u64,
+    pub expect_regs: Vec<(Reg, u32)>,
+    pub expect_memory: Vec<(u32, Vec<u8>)>,
+    /// Require the code to reach HALT within the cycle cap
+    pub expect_halt: bool,
+}
+
+/// Result of `AsmTest::run`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestOutcome {
+    /// CPU cycles used (sum of step cycles)
+    pub cycles: u64,
+    /// Steps executed (prefixes count separately, as in CEmu)
+    pub steps: u64,
+    pub halted: bool,
+    /// One line per failed expectation (empty = pass)
+    pub mismatches: Vec<String>,
+}
+
+impl TestOutcome {
+    pub fn passed(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl AsmTest {
+    /// ADL-mode test starting at `pc`, stopping at HALT
+    pub fn new(pc: u32) -> Self {
+        Self {
+            pc,
+            adl: true,
+            memory: Vec::new(),
+            regs: Vec::new(),
+            max_cycles: DEFAULT_MAX_CYCLES,
+            expect_regs: Vec::new(),
+            expect_memory: Vec::new(),
+            expect_halt: true,
+        }
+    }
+
+    /// Place `bytes` at `addr`
+    pub fn place(mut self, addr: u32, bytes: &[u8]) -> Self {
+        self.memory.push((addr, bytes.to_vec()));
+        self
+    }
+
+    /// Set a register before the run
+    pub fn reg(mut self, reg: Reg, value: u32) -> Self {
+        self.regs.push((reg, value));
+        self
+    }
+
+    pub fn max_cycles(mut self, cycles: u64) -> Self {
+        self.max_cycles = cycles;
+        self
+    }
+
+    pub fn expect_reg(mut self, reg: Reg, value: u32) -> Self {
+        self.expect_regs.push((reg, value));
+        self
+    }
+
+    pub fn expect_memory(mut self, addr: u32, bytes: &[u8]) -> Self {
+        self.expect_memory.push((addr, bytes.to_vec()));
+        self
+    }
+
+    /// Parse the text format (see module docs); defaults match `new(0)`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut test = AsmTest::new(0);
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let mut fields = line.split_whitespace();
+            let Some(keyword) = fields.next() else { continue };
+            let err = |msg: &str| format!("line {}: {}", number + 1, msg);
+            let hex = |s: &str| u32::from_str_radix(s, 16).map_err(|_| err(&format!("bad hex value '{s}'")));
+            let rest: Vec<&str> = fields.collect();
+            let single = || match rest.as_slice() {
+                [value] => hex(value),
+                _ => Err(err("expected one value")),
+            };
+
+            match keyword {
+                "pc" => test.pc = single()?,
+                "adl" => test.adl = single()? != 0,
+                "max" => test.max_cycles = single()? as u64,
+                "halt" => test.expect_halt = single()? != 0,
+                "code" | "mem" | "expect_mem" => {
+                    let (addr, bytes) = rest.split_first().ok_or_else(|| err("expected an address"))?;
+                    let addr = hex(addr)?;
+                    let bytes = bytes.iter().map(|b| hex(b).map(|v| v as u8)).collect::<Result<Vec<u8>, String>>()?;
+                    if keyword == "expect_mem" {
+                        test.expect_memory.push((addr, bytes));
+                    } else {
+                        test.memory.push((addr, bytes));
+                    }
+                }
+                "reg" | "expect" => {
+                    for assignment in &rest {
+                        let (name, value) = assignment.split_once('=').ok_or_else(|| err("expected REG=value"))?;
+                        let reg = Reg::from_name(name).ok_or_else(|| err(&format!("unknown register '{name}'")))?;
+                        let value = hex(value)?;
+                        if keyword == "reg" {
+                            test.regs.push((reg, value));
+                        } else {
+                            test.expect_regs.push((reg, value));
+                        }
+                    }
+                }
+                other => return Err(err(&format!("unknown keyword '{other}'"))),
+            }
+        }
+        Ok(test)
+    }
+
+    /// Execute the snippet on a fresh CPU and bus and diff the result
+    pub fn run(&self) -> TestOutcome {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus::new();
+        for (addr, bytes) in &self.memory {
+            for (i, &byte) in bytes.iter().enumerate() {
+                bus.poke_byte(addr.wrapping_add(i as u32) & 0xFFFFFF, byte);
+            }
+        }
+        Reg::ADL.set(&mut cpu, self.adl as u32);
+        cpu.pc = self.pc;
+        for &(reg, value) in &self.regs {
+            reg.set(&mut cpu, value);
+        }
+        cpu.init_prefetch(&mut bus);
+
+        let mut cycles = 0u64;
+        let mut steps = 0u64;
+        while !cpu.halted && cycles < self.max_cycles {
+            cycles += cpu.step(&mut bus) as u64;
+            steps += 1;
+        }
+
+        let mut mismatches = Vec::new();
+        if self.expect_halt && !cpu.halted {
+            mismatches.push(format!("no HALT within {} cycles (PC={:06X})", self.max_cycles, cpu.pc));
+        }
+        for &(reg, expected) in &self.expect_regs {
+            let actual = reg.get(&cpu);
+            if actual != expected {
+                mismatches.push(format!("{reg:?}: expected {expected:X}, got {actual:X}"));
+            }
+        }
+        for (addr, expected) in &self.expect_memory {
+            for (i, &want) in expected.iter().enumerate() {
+                let at = addr.wrapping_add(i as u32) & 0xFFFFFF;
+                let got = bus.peek_byte(at);
+                if got != want {
+                    mismatches.push(format!("[{at:06X}]: expected {want:02X}, got {got:02X}"));
+                }
+            }
+        }
+        TestOutcome { cycles, steps, halted: cpu.halted, mismatches }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_pass_and_fail() {
+        // LD A,0x42 ; LD (HL),A ; INC HL ; HALT
+        let test = AsmTest::new(0xD00100)
+            .place(0xD00100, &[0x3E, 0x42, 0x77, 0x23, 0x76])
+            .reg(Reg::HL, 0xD00000)
+            .expect_reg(Reg::A, 0x42)
+            .expect_reg(Reg::HL, 0xD00001)
+            .expect_memory(0xD00000, &[0x42]);
+        let outcome = test.run();
+        assert!(outcome.passed(), "{:?}", outcome.mismatches);
+        assert!(outcome.halted);
+        assert_eq!(outcome.steps, 4);
+
+        let outcome = test.expect_reg(Reg::B, 0x01).expect_memory(0xD00001, &[0xAA]).run();
+        assert_eq!(outcome.mismatches, vec![
+            "B: expected 1, got 0".to_string(),
+            "[D00001]: expected AA, got 00".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_cycle_cap_without_halt() {
+        // JR $ never halts
+        let outcome = AsmTest::new(0xD00000).place(0xD00000, &[0x18, 0xFE]).max_cycles(100).run();
+        assert!(!outcome.halted);
+        assert!(outcome.cycles >= 100);
+        assert_eq!(outcome.mismatches, vec!["no HALT within 100 cycles (PC=D00000)".to_string()]);
+    }
+
+    #[test]
+    fn test_memory_addresses_wrap_at_24_bits() {
+        // The last byte is at FFFFFF, the next one wraps to 000000
+        let outcome = AsmTest::new(0xD00000)
+            .place(0xD00000, &[0x76])
+            .expect_memory(u32::MAX, &[0xAA, 0xBB])
+            .run();
+        let addrs: Vec<&str> = outcome.mismatches.iter().map(|m| &m[..8]).collect();
+        assert_eq!(addrs, vec!["[FFFFFF]", "[000000]"]);
+    }
+
+    #[test]
+    fn test_parse_z80_mode_spec() {
+        let spec = "
+            # Z80 mode: PC is 16-bit with MBASE
+            pc 0100
+            adl 0
+            reg MBASE=D0 SP=FFFF
+            code D00100 21 00 20 36 5A 76   # LD HL,2000 ; LD (HL),5A ; HALT
+            expect HL=2000 PC=0106
+            expect_mem D02000 5A
+        ";
+        let test = AsmTest::parse(spec).unwrap();
+        assert!(!test.adl);
+        let outcome = test.run();
+        assert!(outcome.passed(), "{:?}", outcome.mismatches);
+
+        // Without a directive the text form starts in ADL mode, like `new`
+        assert!(AsmTest::parse("pc D00000").unwrap().adl);
+
+        assert_eq!(AsmTest::parse("reg Q=1").unwrap_err(), "line 1: unknown register 'Q'");
+        assert_eq!(AsmTest::parse("\nmax zz").unwrap_err(), "line 2: bad hex value 'zz'");
+        assert_eq!(AsmTest::parse("jump 0").unwrap_err(), "line 1: unknown keyword 'jump'");
+    }
+}
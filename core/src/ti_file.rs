@@ -133,20 +133,31 @@ pub struct TiFile {
 }
 
 /// Errors that can occur during parsing
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TiFileError {
-    TooShort,
-    BadMagic,
-    TruncatedEntry,
+    /// File is `len` bytes but its header or data length needs `needed`
+    TooShort { len: usize, needed: usize },
+    /// First 8 bytes are not `**TI83F*`
+    BadMagic { found: [u8; 8] },
+    /// Entry at `offset` declares more data than the data section holds
+    TruncatedEntry { offset: usize, len: usize, available: usize },
     BadChecksum { expected: u16, actual: u16 },
 }
 
 impl std::fmt::Display for TiFileError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            TiFileError::TooShort => write!(f, "file too short"),
-            TiFileError::BadMagic => write!(f, "bad magic (expected **TI83F*)"),
-            TiFileError::TruncatedEntry => write!(f, "truncated variable entry"),
+            TiFileError::TooShort { len, needed } => {
+                write!(f, "file too short: {} bytes, need {}", len, needed)
+            }
+            TiFileError::BadMagic { found } => {
+                write!(f, "bad magic (expected **TI83F*, found {:02X?})", found)
+            }
+            TiFileError::TruncatedEntry { offset, len, available } => write!(
+                f,
+                "truncated variable entry at offset {}: {} data bytes, {} available",
+                offset, len, available
+            ),
             TiFileError::BadChecksum { expected, actual } => {
                 write!(f, "bad checksum: expected 0x{:04X}, got 0x{:04X}", expected, actual)
             }
@@ -154,17 +165,30 @@ impl std::fmt::Display for TiFileError {
     }
 }
 
+impl std::error::Error for TiFileError {}
+
+impl TiFileError {
+    /// C ABI error code for `send_file` (see emu.h)
+    pub fn code(&self) -> i32 {
+        match self {
+            TiFileError::TooShort { .. } | TiFileError::BadMagic { .. } => -11,
+            TiFileError::BadChecksum { .. } => -14,
+            TiFileError::TruncatedEntry { .. } => -15,
+        }
+    }
+}
+
 impl TiFile {
     /// Parse a TI 8x file from raw bytes.
     /// Supports .8xp (programs), .8xv (appvars), and other TI83F format files.
     pub fn parse(data: &[u8]) -> Result<Self, TiFileError> {
         if data.len() < MIN_FILE_SIZE {
-            return Err(TiFileError::TooShort);
+            return Err(TiFileError::TooShort { len: data.len(), needed: MIN_FILE_SIZE });
         }
 
         // Validate magic signature
         if &data[0..8] != MAGIC {
-            return Err(TiFileError::BadMagic);
+            return Err(TiFileError::BadMagic { found: data[0..8].try_into().unwrap() });
         }
 
         // Read data section length at offset 53 (2 bytes LE)
@@ -172,7 +196,7 @@ impl TiFile {
 
         // Validate file is long enough: 55 header + data_len + 2 checksum
         if data.len() < 55 + data_len + 2 {
-            return Err(TiFileError::TooShort);
+            return Err(TiFileError::TooShort { len: data.len(), needed: 55 + data_len + 2 });
         }
 
         // Verify checksum (lower 16 bits of sum of all bytes in the data section)
@@ -212,7 +236,11 @@ impl TiFile {
             let var_data_start = offset + 17;
             let var_data_end = var_data_start + var_data_len;
             if var_data_end > data_end {
-                return Err(TiFileError::TruncatedEntry);
+                return Err(TiFileError::TruncatedEntry {
+                    offset,
+                    len: var_data_len,
+                    available: data_end.saturating_sub(var_data_start),
+                });
             }
 
             entries.push(TiVarEntry {
@@ -230,35 +258,36 @@ impl TiFile {
     }
 }
 
+/// Build a minimal valid .8xp file from components
+#[cfg(test)]
+pub(crate) fn make_8xp(var_type: u8, name: &[u8; 8], version: u8, flag: u8, var_data: &[u8]) -> Vec<u8> {
+    let mut file = Vec::new();
+    // Header (55 bytes)
+    file.extend_from_slice(b"**TI83F*"); // magic
+    file.extend_from_slice(&[0x1A, 0x0A, 0x00]); // signature2 + product ID
+    file.extend_from_slice(&[0u8; 42]); // comment
+    // Data section: entry header (17 bytes) + var_data
+    let entry_len = 17 + var_data.len();
+    file.extend_from_slice(&(entry_len as u16).to_le_bytes()); // data length
+    // Variable entry
+    file.extend_from_slice(&13u16.to_le_bytes()); // header size = 0x0D
+    file.extend_from_slice(&(var_data.len() as u16).to_le_bytes()); // data size
+    file.push(var_type);
+    file.extend_from_slice(name);
+    file.push(version);
+    file.push(flag);
+    file.extend_from_slice(&(var_data.len() as u16).to_le_bytes()); // data size (dup)
+    file.extend_from_slice(var_data);
+    // Checksum (lower 16 bits of sum of bytes from offset 55 to end of data section)
+    let checksum: u16 = file[55..].iter().fold(0u16, |acc, &b| acc.wrapping_add(b as u16));
+    file.extend_from_slice(&checksum.to_le_bytes());
+    file
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    /// Build a minimal valid .8xp file from components
-    fn make_8xp(var_type: u8, name: &[u8; 8], version: u8, flag: u8, var_data: &[u8]) -> Vec<u8> {
-        let mut file = Vec::new();
-        // Header (55 bytes)
-        file.extend_from_slice(b"**TI83F*"); // magic
-        file.extend_from_slice(&[0x1A, 0x0A, 0x00]); // signature2 + product ID
-        file.extend_from_slice(&[0u8; 42]); // comment
-        // Data section: entry header (17 bytes) + var_data
-        let entry_len = 17 + var_data.len();
-        file.extend_from_slice(&(entry_len as u16).to_le_bytes()); // data length
-        // Variable entry
-        file.extend_from_slice(&13u16.to_le_bytes()); // header size = 0x0D
-        file.extend_from_slice(&(var_data.len() as u16).to_le_bytes()); // data size
-        file.push(var_type);
-        file.extend_from_slice(name);
-        file.push(version);
-        file.push(flag);
-        file.extend_from_slice(&(var_data.len() as u16).to_le_bytes()); // data size (dup)
-        file.extend_from_slice(var_data);
-        // Checksum (lower 16 bits of sum of bytes from offset 55 to end of data section)
-        let checksum: u16 = file[55..].iter().fold(0u16, |acc, &b| acc.wrapping_add(b as u16));
-        file.extend_from_slice(&checksum.to_le_bytes());
-        file
-    }
-
     #[test]
     fn test_parse_minimal_program() {
         let name = *b"TEST\0\0\0\0";
@@ -311,12 +340,26 @@ mod tests {
     fn test_reject_bad_magic() {
         let mut file = make_8xp(0x05, b"TEST\0\0\0\0", 0, 0, &[0, 0]);
         file[0] = b'X';
-        assert!(matches!(TiFile::parse(&file), Err(TiFileError::BadMagic)));
+        assert!(matches!(TiFile::parse(&file), Err(TiFileError::BadMagic { found }) if found[0] == b'X'));
     }
 
     #[test]
     fn test_reject_too_short() {
-        assert!(matches!(TiFile::parse(&[0; 10]), Err(TiFileError::TooShort)));
+        assert_eq!(TiFile::parse(&[0; 10]).unwrap_err(), TiFileError::TooShort { len: 10, needed: MIN_FILE_SIZE });
+    }
+
+    #[test]
+    fn test_reject_truncated_entry() {
+        let mut file = make_8xp(0x05, b"TEST\0\0\0\0", 0, 0, &[0, 0]);
+        // Claim 0x40 data bytes and fix up the checksum
+        file[57] = 0x40;
+        let len = file.len();
+        let sum = file[55..len - 2].iter().fold(0u16, |acc, &b| acc.wrapping_add(b as u16));
+        file[len - 2..].copy_from_slice(&sum.to_le_bytes());
+        assert_eq!(
+            TiFile::parse(&file).unwrap_err(),
+            TiFileError::TruncatedEntry { offset: 55, len: 0x40, available: 2 }
+        );
     }
 
     #[test]
@@ -326,7 +369,7 @@ mod tests {
         let len = file.len();
         file[len - 1] ^= 0xFF;
         match TiFile::parse(&file) {
-            Err(TiFileError::BadChecksum { .. }) => {}
+            Err(err @ TiFileError::BadChecksum { .. }) => assert_eq!(err.code(), -14),
             other => panic!("expected BadChecksum, got {:?}", other),
         }
     }
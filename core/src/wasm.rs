@@ -51,13 +51,39 @@ impl WasmEmu {
                 log("[WASM] load_rom: success");
                 0
             }
-            Err(code) => {
-                warn(&format!("[WASM] load_rom: error {}", code));
-                code
+            Err(err) => {
+                warn(&format!("[WASM] load_rom: {}", err));
+                err.code()
             }
         }
     }
 
+    /// Load the built-in open test ROM (no TI firmware required).
+    /// Returns 0 on success, negative error code on failure.
+    #[wasm_bindgen]
+    pub fn load_test_rom(&mut self) -> i32 {
+        let rom = crate::test_rom::build();
+        log(&format!("[WASM] load_test_rom: {} bytes", rom.len()));
+        match self.inner.load_rom(&rom) {
+            Ok(()) => 0,
+            Err(err) => err.code(),
+        }
+    }
+
+    /// Load the ROM-less demo (patterns: EMU_DEMO_* mask, 0 = all;
+    /// seconds per pattern, 0 = default).
+    /// Returns 0 on success, negative error code on failure.
+    #[wasm_bindgen]
+    pub fn load_demo(&mut self, patterns: u32, seconds: u32) -> i32 {
+        let Some(config) = crate::test_rom::DemoConfig::new(patterns, seconds) else {
+            return -30;
+        };
+        match self.inner.load_demo(config) {
+            Ok(()) => 0,
+            Err(err) => err.code(),
+        }
+    }
+
     /// Send a .8xp/.8xv file to be injected into flash archive.
     /// Must be called after load_rom() and before power_on().
     /// Returns number of entries injected (>=0), or negative error code.
@@ -69,9 +95,9 @@ impl WasmEmu {
                 log(&format!("[WASM] send_file: injected {} entries", count));
                 count as i32
             }
-            Err(code) => {
-                warn(&format!("[WASM] send_file: error {}", code));
-                code
+            Err(err) => {
+                warn(&format!("[WASM] send_file: {}", err));
+                err.code()
             }
         }
     }
@@ -88,13 +114,68 @@ impl WasmEmu {
                 log(&format!("[WASM] send_file_live: injected {} entries, soft reset done", count));
                 count as i32
             }
-            Err(code) => {
-                warn(&format!("[WASM] send_file_live: error {}", code));
-                code
+            Err(err) => {
+                warn(&format!("[WASM] send_file_live: {}", err));
+                err.code()
+            }
+        }
+    }
+
+    /// Choose how often run_cycles services the peripherals (0 every
+    /// instruction, 1 every `cycles` cycles, 2 event-driven, 3 lookahead).
+    /// Returns 0 on success, -30 for a bad mode.
+    #[wasm_bindgen]
+    pub fn set_run_granularity(&mut self, mode: u32, cycles: u32) -> i32 {
+        match crate::RunGranularity::from_raw(mode, cycles) {
+            Some(granularity) => {
+                self.inner.set_run_granularity(granularity);
+                0
+            }
+            None => -30,
+        }
+    }
+
+    /// Flash an OS upgrade (.8eu) from the host, bypassing the boot code,
+    /// and reboot into it.
+    /// Returns bytes programmed, or negative error code (see emu.h).
+    #[wasm_bindgen]
+    pub fn flash_os(&mut self, data: &[u8]) -> i32 {
+        log(&format!("[WASM] flash_os: {} bytes", data.len()));
+        match self.inner.flash_os(data) {
+            Ok(size) => size as i32,
+            Err(err) => {
+                warn(&format!("[WASM] flash_os: {}", err));
+                err.code()
             }
         }
     }
 
+    /// Hash of the loaded ROM as 16 hex digits, for keying per-ROM
+    /// settings (empty if no ROM is loaded).
+    #[wasm_bindgen]
+    pub fn rom_hash(&self) -> String {
+        self.inner.rom_hash().map(|hash| format!("{:016x}", hash)).unwrap_or_default()
+    }
+
+    /// Model and versions of the loaded ROM, e.g. "TI-84 Plus CE, OS 5.8.1,
+    /// boot 5.3.6.0017" (empty if no ROM is loaded). Versions that can't be
+    /// read are left out; "won't boot" marks an erased or corrupt dump.
+    #[wasm_bindgen]
+    pub fn rom_info(&self) -> String {
+        let Some(info) = self.inner.rom_info() else { return String::new() };
+        let mut parts = vec![info.model.name().to_string()];
+        if let Some(os) = info.os_version {
+            parts.push(format!("OS {}", os));
+        }
+        if let Some(boot) = info.boot_version {
+            parts.push(format!("boot {}", boot));
+        }
+        if !info.bootable() {
+            parts.push("won't boot".to_string());
+        }
+        parts.join(", ")
+    }
+
     /// Power on the emulator (simulates ON key press).
     #[wasm_bindgen]
     pub fn power_on(&mut self) {
@@ -108,6 +189,62 @@ impl WasmEmu {
         self.inner.reset();
     }
 
+    /// Reset of a given kind: 0 power cycle (off and on, RAM kept), 1 clear RAM,
+    /// 2 clear RAM and archive (OS kept). Returns false for an unknown kind.
+    #[wasm_bindgen]
+    pub fn reset_kind(&mut self, kind: u32) -> bool {
+        match crate::emu::ResetKind::from_raw(kind) {
+            Some(kind) => {
+                self.inner.reset_kind(kind);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Press the reset button with a key combination held:
+    /// 0 alone (clear RAM), 1 [del] (reinstall OS), 2 [2nd]+[del].
+    /// Returns false for an unknown combination.
+    #[wasm_bindgen]
+    pub fn reset_combo(&mut self, combo: u32) -> bool {
+        match crate::emu::ResetCombo::from_raw(combo) {
+            Some(combo) => {
+                self.inner.reset_with_combo(combo);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pause or resume emulation. While paused run_cycles does nothing and
+    /// host time is not counted, so resuming causes no clock jump.
+    #[wasm_bindgen]
+    pub fn set_paused(&mut self, paused: bool) {
+        if paused {
+            self.inner.pause();
+        } else {
+            self.inner.resume();
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn is_paused(&self) -> bool {
+        self.inner.is_paused()
+    }
+
+    /// Report the host time in milliseconds (e.g. performance.now()).
+    #[wasm_bindgen]
+    pub fn sync_host_time(&mut self, now_ms: f64) {
+        self.inner.sync_host_time(now_ms.max(0.0) as u64);
+    }
+
+    /// Advance the RTC from host time (true) or emulated cycles (false).
+    #[wasm_bindgen]
+    pub fn set_rtc_host_time(&mut self, enable: bool) {
+        use crate::peripherals::rtc::RtcClock;
+        self.inner.set_rtc_clock(if enable { RtcClock::HostTime } else { RtcClock::Emulated });
+    }
+
     /// Run the emulator for the specified number of cycles.
     /// Returns the number of cycles actually executed.
     #[wasm_bindgen]
@@ -197,7 +334,8 @@ impl WasmEmu {
     /// Copy framebuffer data to a Uint8ClampedArray for canvas rendering.
     /// Returns RGBA8888 format suitable for ImageData.
     #[wasm_bindgen]
-    pub fn get_framebuffer_rgba(&self) -> Vec<u8> {
+    pub fn get_framebuffer_rgba(&mut self) -> Vec<u8> {
+        self.inner.note_frame_fetched();
         let framebuffer = self.inner.framebuffer_data();
         let len = framebuffer.len();
 
@@ -216,6 +354,102 @@ impl WasmEmu {
         rgba
     }
 
+    /// Framebuffer as a PNG file, for downloads and clipboard copies
+    #[wasm_bindgen]
+    pub fn screenshot_png(&self) -> Vec<u8> {
+        self.inner.screenshot_png()
+    }
+
+    /// Framebuffer as RGBA8888 at an integer scale (1-4), rotated clockwise
+    /// by `rotation` degrees (0/90/180/270). Width and height are swapped
+    /// for 90 and 270. Empty for a bad scale or rotation.
+    #[wasm_bindgen]
+    pub fn get_framebuffer_rgba_scaled(&mut self, scale: u32, rotation: u32) -> Vec<u8> {
+        use crate::emu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+        use crate::scale::{output_size, Rotation};
+        let Some(rotation) = Rotation::from_degrees(rotation) else {
+            return Vec::new();
+        };
+        let Some((w, h)) = output_size(SCREEN_WIDTH, SCREEN_HEIGHT, scale as usize, rotation) else {
+            return Vec::new();
+        };
+        let mut argb = vec![0u32; w * h];
+        self.inner.note_frame_fetched();
+        self.inner.framebuffer_scaled(scale as usize, rotation, &mut argb);
+        let mut rgba = Vec::with_capacity(argb.len() * 4);
+        for pixel in argb {
+            let [a, r, g, b] = pixel.to_be_bytes();
+            rgba.extend_from_slice(&[r, g, b, a]);
+        }
+        rgba
+    }
+
+    /// Frame counters as [generated, fetched, dropped]; compare `dropped`
+    /// between polls to decide when to enable frame skip.
+    #[wasm_bindgen]
+    pub fn get_frame_counters(&self) -> Vec<f64> {
+        let counters = self.inner.frame_counters();
+        vec![counters.generated as f64, counters.fetched as f64, counters.dropped as f64]
+    }
+
+    /// Copy the raw frame at the current UPBASE, before color conversion
+    /// (320x240 at get_vram_bpp: palette indices or RGB565 LE).
+    /// Empty if UPBASE doesn't point at a full frame of RAM.
+    #[wasm_bindgen]
+    pub fn get_vram(&self) -> Vec<u8> {
+        self.inner.vram().map(<[u8]>::to_vec).unwrap_or_default()
+    }
+
+    /// Render rows as the LCD scans them out, so mid-frame palette/VRAM
+    /// changes show as on hardware (off: one snapshot per run).
+    #[wasm_bindgen]
+    pub fn set_scanline_render(&mut self, enabled: bool) {
+        self.inner.set_scanline_render(enabled);
+    }
+
+    /// Color profile for rendered frames: 0 raw, 1 panel accurate, 2 vivid.
+    /// Returns false for an unknown profile.
+    #[wasm_bindgen]
+    pub fn set_color_profile(&mut self, profile: u32) -> bool {
+        match crate::color::ColorProfile::from_raw(profile) {
+            Some(profile) => {
+                self.inner.set_color_profile(profile);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Format for get_framebuffer_formatted: 0 ARGB8888, 1 RGB565 LE,
+    /// 2 RGBA8888 premultiplied. Returns false for an unknown format.
+    #[wasm_bindgen]
+    pub fn set_framebuffer_format(&mut self, format: u32) -> bool {
+        match crate::pixel_format::PixelFormat::from_raw(format) {
+            Some(format) => {
+                self.inner.set_pixel_format(format);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Copy the last frame in the format chosen with set_framebuffer_format
+    /// (ARGB8888 as little-endian u32 words by default).
+    #[wasm_bindgen]
+    pub fn get_framebuffer_formatted(&mut self) -> Vec<u8> {
+        self.inner.note_frame_fetched();
+        match self.inner.formatted_framebuffer() {
+            Some(bytes) => bytes.to_vec(),
+            None => self.inner.framebuffer_data().iter().flat_map(|pixel| pixel.to_le_bytes()).collect(),
+        }
+    }
+
+    /// Bits per pixel of get_vram: 8 or 16.
+    #[wasm_bindgen]
+    pub fn get_vram_bpp(&self) -> u32 {
+        self.inner.vram_bpp()
+    }
+
     /// Set key state.
     /// row: 0-7, col: 0-7
     /// down: true for pressed, false for released
@@ -224,6 +458,43 @@ impl WasmEmu {
         self.inner.set_key(row as usize, col as usize, down);
     }
 
+    /// Keypad matrix: 8 bytes of held keys, then 8 of unscanned press edges
+    /// (one byte per row, bit = column; see emu_get_key_matrix).
+    #[wasm_bindgen]
+    pub fn get_key_matrix(&self) -> Vec<u8> {
+        let mut out = self.inner.key_matrix().to_vec();
+        out.extend_from_slice(&self.inner.key_edges());
+        out
+    }
+
+    /// Press or release ON; true if the press woke a calculator that was off.
+    #[wasm_bindgen]
+    pub fn press_on_key(&mut self, down: bool) -> bool {
+        self.inner.set_on_key(down)
+    }
+
+    /// Evaluate an expression on the homescreen and return Ans, or
+    /// undefined if it couldn't be typed or read back (see emu_eval).
+    #[wasm_bindgen]
+    pub fn eval(&mut self, expr: &str) -> Option<f64> {
+        crate::eval::eval(&mut self.inner, expr)
+            .map_err(|err| warn(&format!("[WASM] eval FAILED: {}", err)))
+            .ok()
+    }
+
+    /// Navigate the running OS to a screen (EMU_SCREEN_* code, see emu.h).
+    /// Returns the number of keys pressed, -10 no ROM, -30 unknown screen.
+    #[wasm_bindgen]
+    pub fn nav_open(&mut self, screen: i32) -> i32 {
+        let Some(screen) = crate::os_nav::Screen::from_raw(screen as u32) else {
+            return -30;
+        };
+        if self.inner.rom_hash().is_none() {
+            return -10;
+        }
+        crate::os_nav::open(&mut self.inner, screen) as i32
+    }
+
     /// Get the backlight brightness level (0-255).
     #[wasm_bindgen]
     pub fn get_backlight(&self) -> u8 {
@@ -236,6 +507,13 @@ impl WasmEmu {
         self.inner.is_lcd_on()
     }
 
+    /// Homescreen text from the OS text shadow, one line per row (for
+    /// screen readers).
+    #[wasm_bindgen]
+    pub fn screen_text(&self) -> String {
+        crate::screen_text::extract(self.inner.ram_data()).to_text()
+    }
+
     /// Check if device is off (sleeping).
     /// Returns true when the OS has put the device to sleep.
     #[wasm_bindgen]
@@ -295,9 +573,48 @@ impl WasmEmu {
                 ));
                 0
             }
-            Err(code) => {
-                warn(&format!("[WASM] load_state FAILED: error {}", code));
-                code
+            Err(err) => {
+                warn(&format!("[WASM] load_state FAILED: {}", err));
+                err.code()
+            }
+        }
+    }
+
+    /// Load a state that may come from another ROM/OS, keeping the current
+    /// flash. Returns 0 exact, 1 flash kept, negative error code on failure.
+    #[wasm_bindgen]
+    pub fn load_state_best_effort(&mut self, data: &[u8]) -> i32 {
+        match self.inner.load_state_best_effort(data) {
+            Ok(loaded) => {
+                if loaded == crate::StateLoad::KeptFlash {
+                    warn("[WASM] load_state_best_effort: state is from another ROM, kept flash");
+                }
+                loaded as i32
+            }
+            Err(err) => {
+                warn(&format!("[WASM] load_state_best_effort FAILED: {}", err));
+                err.code()
+            }
+        }
+    }
+
+    /// Save a state with the flash replaced by sector hashes, safe to attach
+    /// to a bug report. Returns an empty array on failure.
+    #[wasm_bindgen]
+    pub fn save_redacted_state(&self) -> Vec<u8> {
+        self.inner.save_redacted_state().unwrap_or_default()
+    }
+
+    /// Load a redacted state against the current ROM. Returns the number of
+    /// flash sectors that differ from the reporter's, negative error code on
+    /// failure.
+    #[wasm_bindgen]
+    pub fn load_redacted_state(&mut self, data: &[u8]) -> i32 {
+        match self.inner.load_redacted_state(data) {
+            Ok(differing) => differing.len() as i32,
+            Err(err) => {
+                warn(&format!("[WASM] load_redacted_state FAILED: {}", err));
+                err.code()
             }
         }
     }
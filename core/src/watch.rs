@@ -0,0 +1,435 @@
+//! Watch expressions evaluated when a frame is rendered
+//!
+//! Debugger frontends register small expressions once and read all of their
+//! values back in one batch per frame, instead of issuing a peek per pane.
+//!
+//! # Grammar (case-insensitive, whitespace ignored)
+//!
+//! ```text
+//! expr  := term (op term)*          op: + - & | ^ (left to right)
+//! term  := number | register | '(' expr ')' | deref
+//! deref := ('b' | 'w' | 'l')? '[' expr ']'
+//! ```
+//!
+//! - Numbers: decimal, `0x`/`$` hex, or trailing-`h` hex with a leading digit (`0D00000h`).
+//! - Registers: A F B C D E H L I R MBASE AF BC DE HL IX IY SP PC.
+//!   SP is SPL in ADL mode and SPS in Z80 mode.
+//! - `[x]` reads a byte, `w[x]` a 16-bit word and `l[x]` a 24-bit value
+//!   (little-endian) through the side-effect-free peek path.
+
+use crate::bus::Bus;
+use crate::cpu::Cpu;
+use crate::error::EmuError;
+
+/// Maximum number of watches registered at once
+pub const MAX_WATCHES: usize = 64;
+
+/// Deepest bracket nesting a watch expression may use; parsing recurses
+/// once per level, so this bounds the stack an FFI caller can consume
+pub const MAX_NESTING: usize = 32;
+
+/// Register operand of a watch expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchReg {
+    A, F, B, C, D, E, H, L, I, R, Mbase,
+    Af, Bc, De, Hl, Ix, Iy, Sp, Pc,
+}
+
+impl WatchReg {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "A" => Self::A,
+            "F" => Self::F,
+            "B" => Self::B,
+            "C" => Self::C,
+            "D" => Self::D,
+            "E" => Self::E,
+            "H" => Self::H,
+            "L" => Self::L,
+            "I" => Self::I,
+            "R" => Self::R,
+            "MBASE" => Self::Mbase,
+            "AF" => Self::Af,
+            "BC" => Self::Bc,
+            "DE" => Self::De,
+            "HL" => Self::Hl,
+            "IX" => Self::Ix,
+            "IY" => Self::Iy,
+            "SP" => Self::Sp,
+            "PC" => Self::Pc,
+            _ => return None,
+        })
+    }
+
+    fn read(self, cpu: &Cpu) -> u32 {
+        match self {
+            Self::A => cpu.a as u32,
+            Self::F => cpu.f as u32,
+            Self::B => cpu.b() as u32,
+            Self::C => cpu.c() as u32,
+            Self::D => cpu.d() as u32,
+            Self::E => cpu.e() as u32,
+            Self::H => cpu.h() as u32,
+            Self::L => cpu.l() as u32,
+            Self::I => cpu.i as u32,
+            Self::R => cpu.r as u32,
+            Self::Mbase => cpu.mbase as u32,
+            Self::Af => ((cpu.a as u32) << 8) | cpu.f as u32,
+            Self::Bc => cpu.bc,
+            Self::De => cpu.de,
+            Self::Hl => cpu.hl,
+            Self::Ix => cpu.ix,
+            Self::Iy => cpu.iy,
+            Self::Sp => if cpu.adl { cpu.spl } else { cpu.sps },
+            Self::Pc => cpu.pc,
+        }
+    }
+}
+
+/// Binary operator of a watch expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchOp {
+    Add,
+    Sub,
+    And,
+    Or,
+    Xor,
+}
+
+/// Parsed watch expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchExpr {
+    /// Constant value
+    Const(u32),
+    /// CPU register
+    Reg(WatchReg),
+    /// Memory read of 1, 2 or 3 bytes at the address expression
+    Deref(Box<WatchExpr>, u8),
+    /// Binary operation
+    Binary(Box<WatchExpr>, WatchOp, Box<WatchExpr>),
+}
+
+impl WatchExpr {
+    /// Parse an expression. Returns None on any syntax error, including
+    /// brackets nested deeper than `MAX_NESTING`.
+    pub fn parse(src: &str) -> Option<Self> {
+        let tokens: Vec<char> = src
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        let mut parser = Parser { tokens: &tokens, pos: 0, depth: 0 };
+        let expr = parser.expr()?;
+        if parser.pos != tokens.len() {
+            return None;
+        }
+        Some(expr)
+    }
+
+    /// Evaluate against the current machine state
+    pub fn eval(&self, cpu: &Cpu, bus: &mut Bus) -> u32 {
+        match self {
+            Self::Const(v) => *v,
+            Self::Reg(r) => r.read(cpu),
+            Self::Deref(addr, width) => {
+                let addr = addr.eval(cpu, bus);
+                (0..*width as u32).fold(0, |acc, i| {
+                    acc | (bus.peek_byte(addr.wrapping_add(i)) as u32) << (8 * i)
+                })
+            }
+            Self::Binary(lhs, op, rhs) => {
+                let (a, b) = (lhs.eval(cpu, bus), rhs.eval(cpu, bus));
+                match op {
+                    WatchOp::Add => a.wrapping_add(b),
+                    WatchOp::Sub => a.wrapping_sub(b),
+                    WatchOp::And => a & b,
+                    WatchOp::Or => a | b,
+                    WatchOp::Xor => a ^ b,
+                }
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [char],
+    pos: usize,
+    /// Brackets currently open
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Option<WatchExpr> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some('+') => WatchOp::Add,
+                Some('-') => WatchOp::Sub,
+                Some('&') => WatchOp::And,
+                Some('|') => WatchOp::Or,
+                Some('^') => WatchOp::Xor,
+                _ => return Some(lhs),
+            };
+            self.pos += 1;
+            let rhs = self.term()?;
+            lhs = WatchExpr::Binary(Box::new(lhs), op, Box::new(rhs));
+        }
+    }
+
+    /// Parse a bracketed sub-expression, failing past `MAX_NESTING`
+    fn nested(&mut self) -> Option<WatchExpr> {
+        if self.depth >= MAX_NESTING {
+            return None;
+        }
+        self.depth += 1;
+        let inner = self.expr();
+        self.depth -= 1;
+        inner
+    }
+
+    fn term(&mut self) -> Option<WatchExpr> {
+        if self.eat('(') {
+            let inner = self.nested()?;
+            return self.eat(')').then_some(inner);
+        }
+        if self.eat('[') {
+            return self.deref(1);
+        }
+        if self.eat('$') {
+            return self.number(16);
+        }
+
+        // Identifier or number: take the whole alphanumeric run
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+        let word: String = self.tokens[start..self.pos].iter().collect();
+
+        // Width prefix directly followed by '['
+        if self.peek() == Some('[') {
+            let width = match word.as_str() {
+                "B" => 1,
+                "W" => 2,
+                "L" => 3,
+                _ => return None,
+            };
+            self.pos += 1;
+            return self.deref(width);
+        }
+
+        if let Some(reg) = WatchReg::from_name(&word) {
+            return Some(WatchExpr::Reg(reg));
+        }
+        parse_number(&word).map(WatchExpr::Const)
+    }
+
+    fn deref(&mut self, width: u8) -> Option<WatchExpr> {
+        let addr = self.nested()?;
+        self.eat(']').then(|| WatchExpr::Deref(Box::new(addr), width))
+    }
+
+    fn number(&mut self, radix: u32) -> Option<WatchExpr> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_digit(radix)) {
+            self.pos += 1;
+        }
+        let digits: String = self.tokens[start..self.pos].iter().collect();
+        u32::from_str_radix(&digits, radix).ok().map(WatchExpr::Const)
+    }
+}
+
+fn parse_number(word: &str) -> Option<u32> {
+    if let Some(hex) = word.strip_prefix("0X") {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(hex) = word.strip_suffix('H') {
+        // Must start with a digit so names like "H" stay registers
+        if word.starts_with(|c: char| c.is_ascii_digit()) {
+            u32::from_str_radix(hex, 16).ok()
+        } else {
+            None
+        }
+    } else if word.starts_with(|c: char| c.is_ascii_digit()) {
+        word.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Registered watch expressions and their values from the last frame
+#[derive(Default)]
+pub struct WatchList {
+    entries: Vec<(u32, WatchExpr)>,
+    values: Vec<u32>,
+    next_id: u32,
+}
+
+impl WatchList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an expression. Returns its id; fails if it does not parse
+    /// or MAX_WATCHES are already registered.
+    pub fn add(&mut self, src: &str) -> Result<u32, EmuError> {
+        let expr = WatchExpr::parse(src).ok_or(EmuError::WatchSyntax)?;
+        if self.entries.len() >= MAX_WATCHES {
+            return Err(EmuError::TooManyWatches);
+        }
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.entries.push((id, expr));
+        self.values.push(0);
+        Ok(id)
+    }
+
+    /// Remove a watch by id. Returns false if the id is unknown.
+    pub fn remove(&mut self, id: u32) -> bool {
+        match self.entries.iter().position(|(i, _)| *i == id) {
+            Some(idx) => {
+                self.entries.remove(idx);
+                self.values.remove(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove every watch
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.values.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Re-evaluate every watch (called from `Emu::render_frame`)
+    pub fn evaluate(&mut self, cpu: &Cpu, bus: &mut Bus) {
+        for ((_, expr), value) in self.entries.iter().zip(self.values.iter_mut()) {
+            *value = expr.eval(cpu, bus);
+        }
+    }
+
+    /// (id, value) pairs from the last evaluation, in registration order
+    pub fn values(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.entries.iter().map(|(id, _)| *id).zip(self.values.iter().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(src: &str, cpu: &Cpu, bus: &mut Bus) -> u32 {
+        WatchExpr::parse(src).expect("parse").eval(cpu, bus)
+    }
+
+    #[test]
+    fn test_registers_and_numbers() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus::new();
+        cpu.hl = 0xD01234;
+        cpu.a = 0x12;
+        cpu.f = 0x34;
+        cpu.adl = true;
+        cpu.spl = 0xD1A87E;
+        assert_eq!(eval("hl", &cpu, &mut bus), 0xD01234);
+        assert_eq!(eval("H", &cpu, &mut bus), 0x12);
+        assert_eq!(eval("AF", &cpu, &mut bus), 0x1234);
+        assert_eq!(eval("SP", &cpu, &mut bus), 0xD1A87E);
+        assert_eq!(eval("hl - 0x1234", &cpu, &mut bus), 0xD00000);
+        assert_eq!(eval("$10 + 16 + 10h", &cpu, &mut bus), 48);
+        assert_eq!(eval("(hl & 0xFF) | 0x100", &cpu, &mut bus), 0x134);
+    }
+
+    #[test]
+    fn test_deref_widths() {
+        let cpu = Cpu::new();
+        let mut bus = Bus::new();
+        bus.poke_byte(0xD00000, 0x11);
+        bus.poke_byte(0xD00001, 0x22);
+        bus.poke_byte(0xD00002, 0x33);
+        assert_eq!(eval("[0xD00000]", &cpu, &mut bus), 0x11);
+        assert_eq!(eval("b[0xD00001]", &cpu, &mut bus), 0x22);
+        assert_eq!(eval("w[0xD00000]", &cpu, &mut bus), 0x2211);
+        assert_eq!(eval("l[0xD00000]", &cpu, &mut bus), 0x332211);
+    }
+
+    #[test]
+    fn test_nested_deref() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus::new();
+        // Pointer at HL points to a byte
+        cpu.hl = 0xD00010;
+        bus.poke_byte(0xD00010, 0x00);
+        bus.poke_byte(0xD00011, 0x01);
+        bus.poke_byte(0xD00012, 0xD0);
+        bus.poke_byte(0xD00102, 0x5A);
+        assert_eq!(eval("[l[hl] + 2]", &cpu, &mut bus), 0x5A);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for bad in ["", "hl +", "[hl", "(a", "q[hl]", "xyz", "0xZZ", "a b"] {
+            assert!(WatchExpr::parse(bad).is_none(), "{bad:?} should not parse");
+        }
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nest = |n: usize| format!("{}hl{}", "([".repeat(n / 2), "])".repeat(n / 2));
+        assert!(WatchExpr::parse(&nest(MAX_NESTING)).is_some());
+        assert!(WatchExpr::parse(&nest(MAX_NESTING + 2)).is_none());
+        // Deep enough to overflow the stack without the limit
+        assert!(WatchExpr::parse(&"[".repeat(1_000_000)).is_none());
+    }
+
+    #[test]
+    fn test_watch_list_batch() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus::new();
+        let mut list = WatchList::new();
+        let a = list.add("a").unwrap();
+        let pc = list.add("pc").unwrap();
+        assert_eq!(list.add("+"), Err(EmuError::WatchSyntax));
+
+        cpu.a = 7;
+        cpu.pc = 0x1234;
+        list.evaluate(&cpu, &mut bus);
+        assert_eq!(list.values().collect::<Vec<_>>(), vec![(a, 7), (pc, 0x1234)]);
+
+        assert!(list.remove(a));
+        assert!(!list.remove(a));
+        assert_eq!(list.values().collect::<Vec<_>>(), vec![(pc, 0x1234)]);
+    }
+
+    #[test]
+    fn test_watch_list_capacity() {
+        let mut list = WatchList::new();
+        for _ in 0..MAX_WATCHES {
+            list.add("pc").unwrap();
+        }
+        assert_eq!(list.add("pc"), Err(EmuError::TooManyWatches));
+        list.clear();
+        assert!(list.is_empty());
+    }
+}
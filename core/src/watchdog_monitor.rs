@@ -0,0 +1,148 @@
+//! Watchdog pet telemetry and stall detection
+//!
+//! An OS that uses the watchdog restarts ("pets") it by writing 0xB9 to the
+//! restart register while it runs normally. The monitor counts those
+//! restarts and the emulated time between them. With a stall threshold
+//! set, it reports a stall once when that much emulated time passes
+//! without a pet (`EventKind::WatchdogStall`), an early sign the emulated
+//! OS is wedged before the frozen screen gives it away.
+//!
+//! Detection arms at the first pet, so an OS that never uses the watchdog
+//! never trips it, and re-arms at the next pet after a stall. Time spent
+//! powered off doesn't count. Pets are noted when the peripherals are
+//! ticked, which during a HALT fast-forward can lag the write by a tick
+//! batch; times come from `emulated_nanos`.
+
+/// Watchdog telemetry since reset or the last state load (C layout, see
+/// emu.h)
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EmuWatchdogStats {
+    /// Restarts (0xB9 written to the restart register)
+    pub pets: u64,
+    /// Emulated time of the last pet, ns since reset (0 before the first)
+    pub last_pet_ns: u64,
+    /// Mean and longest time between pets, us (0 before the second pet)
+    pub mean_interval_us: u32,
+    pub max_interval_us: u32,
+    /// Stalls reported
+    pub stalls: u32,
+    /// Stall threshold in emulated ms, 0 = detection off
+    pub stall_ms: u32,
+}
+
+/// Pet history plus the stall detector
+#[derive(Debug, Clone, Default)]
+pub struct WatchdogMonitor {
+    stats: EmuWatchdogStats,
+    first_pet_ns: u64,
+    /// Start of the current quiet spell: the last pet, or the last time
+    /// the calculator was seen off. None until the first pet.
+    quiet_since_ns: Option<u64>,
+    /// The current quiet spell was already reported
+    stalled: bool,
+}
+
+impl WatchdogMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the pet history (reset or state load), keeping the threshold
+    pub fn clear(&mut self) {
+        let stall_ms = self.stats.stall_ms;
+        *self = Self::default();
+        self.stats.stall_ms = stall_ms;
+    }
+
+    /// Report stalls after `ms` emulated ms without a pet (0 turns it off)
+    pub fn set_stall_ms(&mut self, ms: u32) {
+        self.stats.stall_ms = ms;
+        self.stalled = false;
+    }
+
+    /// A threshold is set and a pet has been seen, so `check` has work
+    pub fn armed(&self) -> bool {
+        self.stats.stall_ms != 0 && self.quiet_since_ns.is_some()
+    }
+
+    /// Note `count` pets at `now_ns`
+    pub fn pet(&mut self, now_ns: u64, count: u32) {
+        if count == 0 {
+            return;
+        }
+        let stats = &mut self.stats;
+        if stats.pets == 0 {
+            self.first_pet_ns = now_ns;
+        } else {
+            let gap_us = (now_ns.saturating_sub(stats.last_pet_ns) / 1000).min(u32::MAX as u64) as u32;
+            stats.max_interval_us = stats.max_interval_us.max(gap_us);
+        }
+        stats.pets += count as u64;
+        stats.last_pet_ns = now_ns;
+        if stats.pets > 1 {
+            let span_us = now_ns.saturating_sub(self.first_pet_ns) / 1000;
+            stats.mean_interval_us = (span_us / (stats.pets - 1)).min(u32::MAX as u64) as u32;
+        }
+        self.quiet_since_ns = Some(now_ns);
+        self.stalled = false;
+    }
+
+    /// Check for a stall at `now_ns`. Returns the emulated ms since the
+    /// last pet the first time a quiet spell crosses the threshold.
+    pub fn check(&mut self, now_ns: u64, off: bool) -> Option<u32> {
+        let since = self.quiet_since_ns.as_mut()?;
+        if off {
+            *since = now_ns;
+            return None;
+        }
+        let threshold_ns = self.stats.stall_ms as u64 * 1_000_000;
+        if threshold_ns == 0 || self.stalled || now_ns.saturating_sub(*since) < threshold_ns {
+            return None;
+        }
+        self.stalled = true;
+        self.stats.stalls += 1;
+        let quiet_ms = now_ns.saturating_sub(self.stats.last_pet_ns) / 1_000_000;
+        Some(quiet_ms.min(u32::MAX as u64) as u32)
+    }
+
+    pub fn stats(&self) -> EmuWatchdogStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: u64 = 1_000_000;
+
+    #[test]
+    fn test_intervals_and_stalls() {
+        let mut monitor = WatchdogMonitor::new();
+        monitor.set_stall_ms(100);
+        assert_eq!(monitor.check(1000 * MS, false), None, "not armed before a pet");
+
+        for i in 0..5 {
+            monitor.pet(10 * MS * i, 1);
+        }
+        monitor.pet(70 * MS, 1);
+        let stats = monitor.stats();
+        assert_eq!((stats.pets, stats.last_pet_ns), (6, 70 * MS));
+        assert_eq!((stats.mean_interval_us, stats.max_interval_us), (14_000, 30_000));
+
+        assert_eq!(monitor.check(169 * MS, false), None);
+        assert_eq!(monitor.check(175 * MS, false), Some(105));
+        assert_eq!(monitor.check(500 * MS, false), None, "reported once");
+
+        // Re-armed by the next pet; time off doesn't count
+        monitor.pet(600 * MS, 1);
+        assert_eq!(monitor.check(650 * MS, true), None);
+        assert_eq!(monitor.check(740 * MS, false), None);
+        assert_eq!(monitor.check(760 * MS, false), Some(160));
+        assert_eq!(monitor.stats().stalls, 2);
+
+        monitor.clear();
+        assert_eq!(monitor.stats(), EmuWatchdogStats { stall_ms: 100, ..Default::default() });
+    }
+}
@@ -0,0 +1,137 @@
+//! Data watchpoints on address ranges
+//!
+//! A watchpoint stops execution after an instruction reads or writes memory
+//! in its range, reporting the access (address, value, direction) and the
+//! PC of the instruction that made it. Like snooping, only CPU data
+//! accesses are checked: instruction fetches and debugger peeks/pokes never
+//! trigger one. When several accesses hit during one instruction, the
+//! first is reported.
+
+use crate::snoop::EmuSnoopAccess;
+
+/// Which accesses trigger a watchpoint (stable C ABI values, see emu.h)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchAccess {
+    Read = 1,
+    Write = 2,
+    ReadWrite = 3,
+}
+
+impl WatchAccess {
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        match raw {
+            1 => Some(WatchAccess::Read),
+            2 => Some(WatchAccess::Write),
+            3 => Some(WatchAccess::ReadWrite),
+            _ => None,
+        }
+    }
+
+    /// Whether an access in this direction triggers
+    #[inline]
+    pub fn matches(self, write: bool) -> bool {
+        self as u32 & if write { 2 } else { 1 } != 0
+    }
+}
+
+/// A watched range, `start..=end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub id: u32,
+    pub start: u32,
+    pub end: u32,
+    pub access: WatchAccess,
+}
+
+/// Watchpoints plus the first hit not yet taken by the emulator
+#[derive(Debug, Clone, Default)]
+pub struct Watchpoints {
+    entries: Vec<Watchpoint>,
+    next_id: u32,
+    hit: Option<EmuSnoopAccess>,
+}
+
+impl Watchpoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Watch `start..=end`; returns an id for `remove` (ids start at 1)
+    pub fn add(&mut self, start: u32, end: u32, access: WatchAccess) -> u32 {
+        self.next_id += 1;
+        self.entries.push(Watchpoint { id: self.next_id, start, end, access });
+        self.next_id
+    }
+
+    /// Remove a watchpoint. Returns false for an unknown id.
+    pub fn remove(&mut self, id: u32) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|watchpoint| watchpoint.id != id);
+        self.entries.len() != before
+    }
+
+    /// Remove every watchpoint and any pending hit
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.hit = None;
+    }
+
+    pub fn list(&self) -> &[Watchpoint] {
+        &self.entries
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Note an access if it hits a watchpoint and none is pending
+    pub fn check(&mut self, access: EmuSnoopAccess) {
+        if self.hit.is_none()
+            && self.entries.iter().any(|watchpoint| {
+                (watchpoint.start..=watchpoint.end).contains(&access.addr)
+                    && watchpoint.access.matches(access.write != 0)
+            })
+        {
+            self.hit = Some(access);
+        }
+    }
+
+    /// The pending hit, if any
+    #[inline]
+    pub fn take_hit(&mut self) -> Option<EmuSnoopAccess> {
+        self.hit.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn access(addr: u32, write: bool) -> EmuSnoopAccess {
+        EmuSnoopAccess { addr, write: write as u8, ..Default::default() }
+    }
+
+    #[test]
+    fn test_ranges_access_kinds_and_first_hit() {
+        let mut watchpoints = Watchpoints::new();
+        let reads = watchpoints.add(0xD00000, 0xD000FF, WatchAccess::Read);
+        watchpoints.add(0xD000C4, 0xD000C4, WatchAccess::Write);
+        assert_eq!(WatchAccess::from_raw(0), None);
+
+        watchpoints.check(access(0xD00100, false));
+        watchpoints.check(access(0xD000C5, true));
+        assert_eq!(watchpoints.take_hit(), None);
+
+        // The first of two hits is kept
+        watchpoints.check(access(0xD000C4, true));
+        watchpoints.check(access(0xD00010, false));
+        assert_eq!(watchpoints.take_hit(), Some(access(0xD000C4, true)));
+        assert_eq!(watchpoints.take_hit(), None);
+
+        assert!(watchpoints.remove(reads));
+        assert!(!watchpoints.remove(reads));
+        watchpoints.check(access(0xD00010, false));
+        assert_eq!(watchpoints.take_hit(), None);
+    }
+}
@@ -0,0 +1,42 @@
+#!/bin/sh
+# Compare the functions declared in emu.h with the ones a library exports.
+#
+# Usage: check_symbols.sh <emu.h> <libemu_core.a> <prefix>
+#   prefix is emu_ for default builds, rust_emu_ for ios_prefixed builds
+#
+# Fails if an exported <prefix>* function is missing from the header, or a
+# declared function is not exported. emu_backend_* are declared in
+# ios/include/emu_backend.h and skipped.
+
+set -eu
+
+header=$1
+lib=$2
+prefix=$3
+tmp=${TMPDIR:-/tmp}/ffi_symbols.$$
+trap 'rm -f "$tmp".*' EXIT
+
+grep -oE '\bemu_[a-z0-9_]+\(' "$header" | tr -d '(' | sort -u > "$tmp.declared"
+
+# Defined text symbols; Mach-O prefixes C names with an underscore
+nm -g "$lib" 2>/dev/null \
+    | awk '$2 == "T" { print $3 }' \
+    | sed 's/^_//' \
+    | grep "^$prefix" \
+    | sed "s/^$prefix/emu_/" \
+    | grep -v '^emu_backend_' \
+    | sort -u > "$tmp.exported"
+
+status=0
+undeclared=$(comm -13 "$tmp.declared" "$tmp.exported")
+missing=$(comm -23 "$tmp.declared" "$tmp.exported")
+if [ -n "$undeclared" ]; then
+    echo "exported but not declared in emu.h:" $undeclared
+    status=1
+fi
+if [ -n "$missing" ]; then
+    echo "declared in emu.h but not exported:" $missing
+    status=1
+fi
+[ $status -eq 0 ] && echo "symbols: $(wc -l < "$tmp.declared") declared, all exported as $prefix*"
+exit $status
@@ -0,0 +1,474 @@
+/**
+ * FFI contract tests
+ *
+ * Drives the C API from C the way the Android/iOS bridges do: create and
+ * destroy, ROM loading, running, callbacks and states, plus the null and
+ * bad-argument codes documented in emu.h. Built against both symbol
+ * namings by the Makefile (the prefixed build force-includes prefix.h).
+ *
+ * Usage: ffi_contract <test.rom>
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "emu.h"
+
+// Test ROM mailbox (see core/src/test_rom.rs)
+#define READY 0xD00000
+#define TICKS 0xD00001
+#define READY_MAGIC 0xA5
+#define VRAM 0xD40000
+
+static int checks;
+static int failures;
+
+#define CHECK(cond)                                                        \
+    do {                                                                   \
+        checks++;                                                          \
+        if (!(cond)) {                                                     \
+            failures++;                                                    \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+        }                                                                  \
+    } while (0)
+
+#define CHECK_EQ(actual, expected)                                         \
+    do {                                                                   \
+        long long a_ = (long long)(actual), e_ = (long long)(expected);    \
+        checks++;                                                          \
+        if (a_ != e_) {                                                    \
+            failures++;                                                    \
+            fprintf(stderr, "%s:%d: %s = %lld, expected %lld\n", __FILE__, __LINE__, \
+                    #actual, a_, e_);                                      \
+        }                                                                  \
+    } while (0)
+
+// Every function declared in emu.h; taking its address makes the link fail
+// if the library doesn't export it under this build's naming
+#define SYMBOL(name) { #name, (void (*)(void))name },
+static const struct {
+    const char* name;
+    void (*fn)(void);
+} symbols[] = {
+#include "symbols.inc"
+};
+
+// Struct layouts shared with the #[repr(C)] types in the core
+_Static_assert(sizeof(EmuEvent) == 16, "EmuEvent layout");
+_Static_assert(sizeof(EmuSnoopAccess) == 24, "EmuSnoopAccess layout");
+_Static_assert(sizeof(EmuPortAccess) == 24, "EmuPortAccess layout");
+_Static_assert(sizeof(EmuSlotInfo) == 24, "EmuSlotInfo layout");
+_Static_assert(sizeof(EmuLastChange) == 24, "EmuLastChange layout");
+_Static_assert(sizeof(EmuFrameCounters) == 24, "EmuFrameCounters layout");
+_Static_assert(sizeof(EmuInterruptStats) == 40, "EmuInterruptStats layout");
+_Static_assert(sizeof(EmuRegisters) == 32, "EmuRegisters layout");
+_Static_assert(sizeof(EmuRomInfo) == 16, "EmuRomInfo layout");
+
+static uint8_t* read_file(const char* path, size_t* len) {
+    FILE* file = fopen(path, "rb");
+    if (!file) {
+        return NULL;
+    }
+    fseek(file, 0, SEEK_END);
+    long size = ftell(file);
+    fseek(file, 0, SEEK_SET);
+    uint8_t* data = malloc(size > 0 ? (size_t)size : 1);
+    if (data && fread(data, 1, (size_t)size, file) != (size_t)size) {
+        free(data);
+        data = NULL;
+    }
+    fclose(file);
+    *len = (size_t)size;
+    return data;
+}
+
+static Emu* boot(const uint8_t* rom, size_t rom_len) {
+    Emu* emu = emu_create();
+    CHECK(emu != NULL);
+    CHECK_EQ(emu_load_rom(emu, rom, rom_len), 0);
+    emu_power_on(emu);
+    CHECK(emu_run_cycles(emu, 5000000) > 0);
+    return emu;
+}
+
+static uint8_t peek(const Emu* emu, uint32_t addr) {
+    uint8_t value = 0;
+    CHECK_EQ(emu_read_memory(emu, addr, &value, 1), 0);
+    return value;
+}
+
+// Panel timing the OS programs, so frames come at ~60 Hz
+static void set_lcd_timing(Emu* emu) {
+    static const uint8_t timing[12] = {
+        0x38, 0x03, 0x0A, 0x1F, 0x3F, 0x09, 0x02, 0x04, 0x02, 0x78, 0xEF, 0x00,
+    };
+    CHECK_EQ(emu_write_block(emu, 0xE30000, timing, sizeof timing, 1), 0);
+}
+
+static void test_symbols(void) {
+    size_t count = sizeof symbols / sizeof symbols[0];
+    for (size_t i = 0; i < count; i++) {
+        CHECK(symbols[i].fn != NULL);
+    }
+    printf("  %zu functions linked\n", count);
+}
+
+static void test_null_handles(void) {
+    EmuRegisters regs;
+    EmuSlotInfo slot;
+    EmuEvent event;
+    uint64_t hash;
+
+    CHECK_EQ(emu_load_rom(NULL, NULL, 0), -1);
+    CHECK_EQ(emu_rom_hash(NULL, &hash), -1);
+    EmuRomInfo info;
+    CHECK_EQ(emu_rom_info(NULL, &info), -1);
+    CHECK_EQ(emu_get_registers(NULL, &regs), -1);
+    CHECK_EQ(emu_slot_info(NULL, 0, &slot), -1);
+    CHECK_EQ(emu_poll_event(NULL, &event), -1);
+    CHECK_EQ(emu_set_frame_callback(NULL, NULL, NULL), -1);
+    CHECK_EQ(emu_get_reset_cause(NULL), -1);
+    CHECK(emu_crash_report(NULL) == NULL);
+    CHECK_EQ(emu_reset_kind(NULL, EMU_RESET_KIND_FULL), -1);
+    CHECK_EQ(emu_save_redacted_state(NULL, NULL, 0), -1);
+    CHECK_EQ(emu_testkit_run(NULL, NULL, 0), -1);
+    CHECK(emu_framebuffer(NULL, NULL, NULL) == NULL);
+    CHECK(emu_disasm(NULL, 0, 1) == NULL);
+    CHECK(emu_memory_map_string(NULL) == NULL);
+    CHECK_EQ(emu_copy_framebuffer(NULL, NULL, 0, NULL, NULL), -1);
+    CHECK_EQ(emu_screenshot_png(NULL, NULL, 0), -1);
+    // Void functions must tolerate NULL too
+    emu_destroy(NULL);
+    emu_reset(NULL);
+    emu_power_on(NULL);
+    emu_set_key(NULL, 0, 0, 1);
+    CHECK_EQ(emu_press_on_key(NULL, 1), -1);
+    CHECK_EQ(emu_get_key_matrix(NULL, NULL, NULL), -1);
+    CHECK_EQ(emu_set_scanline_render(NULL, 1), -1);
+    CHECK_EQ(emu_get_scanline_render(NULL), -1);
+    EmuWatchdogStats watchdog;
+    CHECK_EQ(emu_get_watchdog_stats(NULL, &watchdog), -1);
+    CHECK_EQ(emu_set_watchdog_stall(NULL, 100), -1);
+}
+
+static void test_lifecycle(void) {
+    CHECK_EQ(strcmp(emu_error_string(0), "ok"), 0);
+    CHECK_EQ(strcmp(emu_error_string(-43), "no save slot older than the current state"), 0);
+    CHECK_EQ(strcmp(emu_error_string(-12345), "unknown error"), 0);
+
+    Emu* emu = emu_create();
+    CHECK(emu != NULL);
+    CHECK_EQ(emu_load_rom(emu, NULL, 0), -1);
+    EmuStepInfo step;
+    CHECK_EQ(emu_step(emu, &step), -10);
+    CHECK_EQ(emu_run_cycles(emu, 1000), 0);
+
+    // The ROM-less demo loads in place of a ROM
+    CHECK_EQ(emu_load_demo(NULL, 0, 0), -1);
+    CHECK_EQ(emu_load_demo(emu, 8, 0), -30);
+    CHECK_EQ(emu_load_demo(emu, EMU_DEMO_BARS | EMU_DEMO_CHECKER, 61), -30);
+    CHECK_EQ(emu_load_demo(emu, EMU_DEMO_BARS | EMU_DEMO_CHECKER, 1), 0);
+    emu_power_on(emu);
+    CHECK(emu_run_cycles(emu, 3000000) > 0);
+    CHECK_EQ(peek(emu, READY), READY_MAGIC);
+    // Already on: ON only wakes the CPU
+    CHECK_EQ(emu_press_on_key(emu, 1), 0);
+    CHECK_EQ(emu_press_on_key(emu, 0), 0);
+    emu_destroy(emu);
+}
+
+static void test_load_and_run(const uint8_t* rom, size_t rom_len) {
+    Emu* emu = boot(rom, rom_len);
+    CHECK_EQ(peek(emu, READY), READY_MAGIC);
+    CHECK_EQ(emu_get_reset_cause(emu), EMU_RESET_POWER_ON);
+    CHECK(emu_crash_report(emu) == NULL);
+
+    uint64_t hash = 0;
+    CHECK_EQ(emu_rom_hash(emu, &hash), 0);
+    CHECK(hash != 0);
+
+    // The test ROM is boot code only: no OS header
+    EmuRomInfo info;
+    CHECK_EQ(emu_rom_info(emu, &info), 0);
+    CHECK_EQ(info.flags & (EMU_ROM_BOOT_CODE | EMU_ROM_CERT_VALID), EMU_ROM_BOOT_CODE);
+    CHECK_EQ(info.model, EMU_MODEL_UNKNOWN);
+
+    int w = 0, h = 0;
+    const uint32_t* pixels = emu_framebuffer(emu, &w, &h);
+    CHECK(pixels != NULL);
+    CHECK_EQ(w, 320);
+    CHECK_EQ(h, 240);
+    CHECK_EQ(emu_is_lcd_on(emu), 1);
+    static uint32_t copy[320 * 240];
+    CHECK_EQ(emu_copy_framebuffer(emu, NULL, 0, &w, &h), 320 * 240);
+    CHECK_EQ(emu_copy_framebuffer(emu, copy, 16, NULL, NULL), 320 * 240);
+    CHECK_EQ(emu_copy_framebuffer(emu, copy, 320 * 240, NULL, NULL), 320 * 240);
+    CHECK_EQ(memcmp(copy, pixels, sizeof copy), 0);
+    int png_len = emu_screenshot_png(emu, NULL, 0);
+    CHECK(png_len > 0);
+    uint8_t* png = malloc(png_len);
+    CHECK_EQ(emu_screenshot_png(emu, png, png_len), png_len);
+    CHECK_EQ(memcmp(png, "\x89PNG\r\n\x1a\n", 8), 0);
+    free(png);
+
+    // A static screen scans out the same as it snapshots
+    CHECK_EQ(emu_set_scanline_render(emu, 1), 0);
+    CHECK_EQ(emu_get_scanline_render(emu), 1);
+    emu_run_frame(emu);
+    emu_run_frame(emu);
+    pixels = emu_framebuffer(emu, NULL, NULL);
+    CHECK_EQ(memcmp(copy, pixels, sizeof copy), 0);
+    CHECK_EQ(emu_set_scanline_render(emu, 0), 0);
+
+    EmuStepInfo step;
+    CHECK_EQ(emu_step(emu, &step), 0);
+    CHECK(step.opcode_len >= 1 && step.opcode_len <= 4);
+
+    EmuRegisters regs;
+    CHECK_EQ(emu_get_registers(emu, &regs), 0);
+    CHECK_EQ(regs.adl, 1);
+    CHECK_EQ(emu_set_registers(emu, &regs), 0);
+
+    CHECK_EQ(emu_set_run_granularity(emu, 99, 0), -30);
+    CHECK_EQ(emu_set_run_granularity(emu, EMU_GRANULARITY_INSTRUCTION, 0), 0);
+    CHECK(emu_run_cycles(emu, 100000) > 0);
+
+    CHECK_EQ(emu_breakpoint_add(emu, 0x1000000), -30);
+    CHECK_EQ(emu_press_key_name(emu, "Enter"), 0);
+    CHECK_EQ(emu_release_key_name(emu, "ENTER"), 0);
+    CHECK_EQ(emu_press_key_name(emu, "F1"), -30);
+    // The tap is released but not scanned yet: ENTER is row 6 bit 0
+    uint8_t keys[8], edges[8];
+    CHECK_EQ(emu_get_key_matrix(emu, keys, edges), 0);
+    CHECK_EQ(keys[6], 0);
+    CHECK_EQ(edges[6], 1);
+    CHECK_EQ(emu_get_key_matrix(emu, NULL, NULL), 0);
+    int id = emu_watchpoint_add(emu, TICKS, TICKS, EMU_WATCHPOINT_WRITE);
+    CHECK(id > 0);
+    emu_run_cycles(emu, 2000000);
+    EmuSnoopAccess hit;
+    CHECK_EQ(emu_last_watchpoint(emu, &hit), 1);
+    CHECK_EQ(hit.addr, TICKS);
+    CHECK_EQ(emu_watchpoint_remove(emu, id), 0);
+    CHECK_EQ(emu_watchpoint_remove(emu, id), -30);
+
+    emu_destroy(emu);
+}
+
+static int frames_seen;
+static int dirty_frames;
+static void on_frame(void* user, uint64_t frame, uint32_t dirty) {
+    (void)frame;
+    CHECK(user == &frames_seen);
+    frames_seen++;
+    dirty_frames += dirty != 0;
+}
+
+static int events_seen;
+static void on_event(void* user, const EmuEvent* event) {
+    (void)user;
+    CHECK(event != NULL && event->kind >= EMU_EVENT_LCD_ON);
+    events_seen++;
+}
+
+static int tick_writes;
+static void on_snoop(void* user, const EmuSnoopAccess* access) {
+    (void)user;
+    CHECK_EQ(access->addr, TICKS);
+    tick_writes += access->write;
+}
+
+static int log_lines;
+static void on_log(const char* message) {
+    CHECK(message != NULL);
+    log_lines++;
+}
+
+static int autosaves;
+static void on_autosave(void* user, const uint8_t* data, size_t len, uint32_t reason) {
+    (void)user;
+    CHECK(data != NULL && len > 0);
+    CHECK_EQ(reason, EMU_AUTOSAVE_DESTROY);
+    autosaves++;
+}
+
+static void test_callbacks(const uint8_t* rom, size_t rom_len) {
+    emu_set_log_callback(on_log);
+    Emu* emu = boot(rom, rom_len);
+    // Ticks are snooped before the panel timing is programmed: with it set,
+    // the test ROM's timer ticks stop advancing
+    CHECK_EQ(emu_snoop_range(emu, TICKS, TICKS, on_snoop, NULL), 0);
+    emu_run_cycles(emu, 2000000);
+    CHECK(tick_writes > 0);
+    CHECK_EQ(emu_snoop_range(emu, 0, 0, NULL, NULL), 0);
+    set_lcd_timing(emu);
+
+    CHECK_EQ(emu_set_frame_callback(emu, on_frame, &frames_seen), 0);
+    emu_set_event_callback(emu, on_event, NULL);
+    CHECK_EQ(emu_snoop_range(emu, 0x1000000, 0x1000001, on_snoop, NULL), -30);
+    for (int i = 0; i < 5; i++) {
+        emu_run_frame(emu);
+    }
+    CHECK(frames_seen >= 5);
+    CHECK(dirty_frames >= 1);
+    CHECK(events_seen >= 5); // EMU_EVENT_FRAME_DONE at least
+
+    // Unregistered callbacks stay quiet
+    int frames = frames_seen;
+    CHECK_EQ(emu_set_frame_callback(emu, NULL, NULL), 0);
+    emu_run_frame(emu);
+    CHECK_EQ(frames_seen, frames);
+
+    emu_set_event_callback(emu, NULL, NULL);
+    emu_set_autosave_sink(emu, on_autosave, NULL);
+    emu_destroy(emu);
+    CHECK_EQ(autosaves, 1);
+    emu_set_log_callback(NULL);
+}
+
+static void test_states(const uint8_t* rom, size_t rom_len) {
+    Emu* emu = boot(rom, rom_len);
+
+    size_t size = emu_save_state_size(emu);
+    CHECK(size > 0);
+    uint8_t* state = malloc(size);
+    CHECK(state != NULL);
+    int written = emu_save_state(emu, state, size);
+    CHECK(written > 0 && (size_t)written <= size);
+    CHECK(emu_save_state(emu, state, 16) < 0);
+    uint8_t ticks = peek(emu, TICKS);
+
+    emu_run_cycles(emu, 3000000);
+    CHECK(peek(emu, TICKS) != ticks);
+    CHECK_EQ(emu_load_state(emu, state, (size_t)written), 0);
+    CHECK_EQ(peek(emu, TICKS), ticks);
+    CHECK(emu_load_state(emu, state, 16) < 0);
+    state[written - 1] ^= 0xFF;
+    CHECK_EQ(emu_load_state(emu, state, (size_t)written), -106);
+    state[written - 1] ^= 0xFF;
+
+    // A state restores into a fresh instance with the same ROM
+    Emu* other = emu_create();
+    CHECK_EQ(emu_load_rom(other, rom, rom_len), 0);
+    CHECK_EQ(emu_load_state_best_effort(other, state, (size_t)written), 0);
+    CHECK_EQ(peek(other, TICKS), ticks);
+    emu_destroy(other);
+
+    // Slots and the time-travel watch built on them
+    EmuLastChange change;
+    CHECK_EQ(emu_find_last_change(emu, TICKS, &change), -43);
+    CHECK(emu_slot_save(emu, 0) > 0);
+    CHECK_EQ(emu_slot_save(emu, 10), -40);
+    EmuSlotInfo info;
+    CHECK_EQ(emu_slot_info(emu, 0, &info), 0);
+    CHECK_EQ(info.used, 1);
+    emu_run_cycles(emu, 3000000);
+    CHECK_EQ(emu_find_last_change(emu, TICKS, &change), 1);
+    CHECK_EQ(change.addr, TICKS);
+    CHECK_EQ(change.new_value, peek(emu, TICKS));
+    uint8_t now_ticks = peek(emu, TICKS);
+    CHECK_EQ(emu_rewind_to_last_change(emu, TICKS, 1, &change), 1);
+    CHECK_EQ(peek(emu, TICKS), change.new_value);
+    CHECK_EQ(emu_slot_load(emu, 1), 0);
+    CHECK_EQ(peek(emu, TICKS), now_ticks);
+    CHECK_EQ(emu_slot_clear(emu, 1), 0);
+    CHECK_EQ(emu_slot_load(emu, 0), 0);
+    CHECK_EQ(peek(emu, TICKS), ticks);
+    CHECK_EQ(emu_slot_clear(emu, 0), 0);
+    CHECK_EQ(emu_slot_load(emu, 0), -41);
+
+    // Redacted states restore without shipping flash
+    int redacted_len = emu_save_redacted_state(emu, NULL, 0);
+    CHECK(redacted_len > 0 && (size_t)redacted_len < size);
+    uint8_t* redacted = malloc(redacted_len);
+    CHECK_EQ(emu_save_redacted_state(emu, redacted, redacted_len), redacted_len);
+    emu_run_cycles(emu, 3000000);
+    CHECK_EQ(emu_load_redacted_state(emu, redacted, redacted_len), 0);
+    CHECK_EQ(peek(emu, TICKS), ticks);
+    CHECK_EQ(emu_load_redacted_state(emu, state, 16), -102);
+    free(redacted);
+
+    // A power cycle keeps RAM, a RAM clear doesn't
+    CHECK_EQ(emu_reset_kind(emu, EMU_RESET_KIND_POWER_CYCLE), 0);
+    CHECK_EQ(peek(emu, READY), READY_MAGIC);
+    CHECK_EQ(emu_get_reset_cause(emu), EMU_RESET_POWER_CYCLE);
+    CHECK_EQ(emu_reset_kind(emu, EMU_RESET_KIND_RAM_CLEAR), 0);
+    CHECK_EQ(peek(emu, READY), 0);
+    CHECK_EQ(emu_reset_kind(emu, 3), -30);
+
+    free(state);
+    emu_destroy(emu);
+}
+
+static void test_memory_and_tools(const uint8_t* rom, size_t rom_len) {
+    Emu* emu = boot(rom, rom_len);
+
+    uint8_t pattern[4] = {0xDE, 0xAD, 0xBE, 0xEF};
+    uint8_t back[4] = {0};
+    CHECK_EQ(emu_write_memory(emu, VRAM, pattern, sizeof pattern), 0);
+    CHECK_EQ(emu_read_block(emu, VRAM, back, sizeof back, 0), 0);
+    CHECK_EQ(memcmp(back, pattern, sizeof pattern), 0);
+    CHECK_EQ(emu_read_block(emu, 0xFFFFFE, back, sizeof back, 0), -30);
+
+    uint32_t found[4];
+    CHECK_EQ(emu_search(emu, "DE AD BE EF", VRAM, VRAM + 16, found, 4), 1);
+    CHECK_EQ(found[0], VRAM);
+
+    char text[4096];
+    int len = emu_memory_map(emu, text, sizeof text);
+    CHECK(len > 0 && (size_t)len < sizeof text);
+    CHECK_EQ(strncmp(text, "000000 ", 7), 0);
+    char* owned = emu_memory_map_string(emu);
+    CHECK(owned && strcmp(owned, text) == 0);
+    emu_string_free(owned);
+    owned = emu_disasm(emu, 0, 4);
+    CHECK(owned && strncmp(owned, "000000: ", 8) == 0);
+    emu_string_free(owned);
+    emu_string_free(NULL);
+    CHECK(emu_disasm(emu, 0, 65537) == NULL);
+
+    int watch = emu_watch_add(emu, "[0xD00000]");
+    CHECK(watch >= 0);
+    CHECK(emu_watch_add(emu, "(((") < 0);
+    emu_run_frame(emu);
+    uint32_t ids[4], values[4];
+    CHECK_EQ(emu_watch_values(emu, ids, values, 4), 1);
+    CHECK_EQ(values[0], READY_MAGIC);
+
+    EmuInterruptStats stats[32];
+    CHECK_EQ(emu_get_interrupt_stats(emu, stats, 1), -33);
+    CHECK_EQ(emu_get_interrupt_stats(emu, stats, 32), 22);
+    CHECK(stats[EMU_IRQ_TIMER1].raises > 0);
+
+    char report[64];
+    CHECK_EQ(emu_testkit_run("pc D00000\nadl 1\ncode D00000 3E 07 76\nexpect A=07", report, sizeof report), 0);
+    CHECK_EQ(emu_testkit_run("bogus", report, sizeof report), -70);
+
+    emu_destroy(emu);
+}
+
+int main(int argc, char** argv) {
+    if (argc < 2) {
+        fprintf(stderr, "usage: %s <test.rom>\n", argv[0]);
+        return 2;
+    }
+    size_t rom_len = 0;
+    uint8_t* rom = read_file(argv[1], &rom_len);
+    if (!rom) {
+        fprintf(stderr, "cannot read %s\n", argv[1]);
+        return 2;
+    }
+
+    test_symbols();
+    test_null_handles();
+    test_lifecycle();
+    test_load_and_run(rom, rom_len);
+    test_callbacks(rom, rom_len);
+    test_states(rom, rom_len);
+    test_memory_and_tools(rom, rom_len);
+
+    free(rom);
+    printf("ffi: %d checks, %d failed\n", checks, failures);
+    return failures ? 1 : 0;
+}
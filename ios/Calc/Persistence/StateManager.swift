@@ -170,7 +170,7 @@ class StateManager {
         case -102: return "Invalid state file format"
         case -103: return "State file version mismatch"
         case -104: return "State was saved with a different ROM"
-        case -105: return "State file is corrupted"
+        case -105, -106: return "State file is corrupted"
         default: return "Unknown error (\(code))"
         }
     }